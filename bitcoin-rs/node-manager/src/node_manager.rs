@@ -2,7 +2,7 @@ use message::bytes::Bytes;
 use message::{serialize_payload, Payload};
 use network::network::{PROTOCOL_MINIMUM, PROTOCOL_VERSION};
 use network::Network;
-use p2p::{connect, read_any_message, Connection, NetConfig};
+use p2p::{connect, Connection, NetConfig};
 use rpc_client::{http, AddNodeOperation, RpcClient};
 use std::process::{Child, Command, ExitStatus, Stdio};
 use std::rc::Rc;
@@ -59,6 +59,8 @@ impl NodeManager {
 			user_agent: "bitcoin-rs-test".to_string(),
 			start_height: 0,
 			relay: false,
+			proxy: None,
+			onion_proxy: None,
 		};
 
 		NodeManager {
@@ -124,7 +126,7 @@ impl NodeManager {
 
 	pub async fn connect_p2p(&mut self) -> &mut NodeManager {
 		for _ in 0..5 {
-			if let Ok(connection) = connect(&self.config.local_address, &self.config).await {
+			if let Ok(connection) = connect(&self.config.local_address.into(), &self.config, false).await {
 				self.connection = Some(connection);
 				return self;
 			}
@@ -154,7 +156,7 @@ impl NodeManager {
 	{
 		if let Some(connection) = &self.connection {
 			let stream = serialize_payload(payload, self.config.protocol_version).unwrap();
-			connection.stream.write_all(stream.as_ref()).await.unwrap();
+			connection.stream.write_message(stream.as_ref()).await.unwrap();
 			Ok(())
 		} else {
 			Err("Not connected".to_string())
@@ -163,7 +165,7 @@ impl NodeManager {
 
 	pub async fn read_message(&self) -> Result<(message::Command, Bytes), String> {
 		if let Some(connection) = &self.connection {
-			Ok(read_any_message(&connection.stream, self.config.network.magic()).await.unwrap())
+			Ok(connection.stream.read_message().await.unwrap())
 		} else {
 			Err("Not connected".to_string())
 		}
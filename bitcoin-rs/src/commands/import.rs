@@ -7,8 +7,16 @@ use storage::CanonStore;
 use sync::{create_sync_blocks_writer, Error};
 
 pub fn import(cfg: &Config, matches: &ArgMatches) -> Result<(), String> {
-	let db =
-		Arc::new(db::BlockChainDatabase::persistent(&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap());
+	let mut db = db::BlockChainDatabase::persistent(&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap();
+	if cfg.address_index {
+		db = db
+			.with_script_index()
+			.map_err(|err| format!("Cannot enable address index: {:?}", err))?;
+	}
+	if let Some(prune) = cfg.prune {
+		db = db.with_pruning(prune * 1024 * 1024);
+	}
+	let db = Arc::new(db);
 	let blk_path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
 	let blk_dir = open_blk_dir(blk_path).map_err(|err| format!("Failed to open import directory: {}", err))?;
 
@@ -0,0 +1,49 @@
+use crate::app_dir::app_path;
+use crate::config::Config;
+use clap::ArgMatches;
+use network::Network;
+use std::fs::File;
+use std::io::BufReader;
+use storage::{verify_utxo_snapshot, CanonStore};
+
+pub fn load_snapshot(cfg: &Config, matches: &ArgMatches) -> Result<(), String> {
+	// mainnet and testnet snapshots are not known yet
+	if cfg.network != Network::Regtest {
+		return Err(format!(
+			"UTXO snapshots are only supported on regtest: no snapshots are known for {} network",
+			cfg.network
+		));
+	}
+
+	let mut db = db::BlockChainDatabase::persistent(&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap();
+	if cfg.address_index {
+		db = db
+			.with_script_index()
+			.map_err(|err| format!("Cannot enable address index: {:?}", err))?;
+	}
+	if db.best_block().number != 0 {
+		return Err("UTXO snapshot can only be loaded into a new database".into());
+	}
+
+	let path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
+	let open_snapshot = || {
+		File::open(path)
+			.map(BufReader::new)
+			.map_err(|err| format!("Cannot open snapshot: {}", err))
+	};
+
+	// snapshot is read twice: it is only written to the database once the whole snapshot is verified
+	info!("Verifying UTXO snapshot. This may take a while.");
+	let assumeutxo = verify_utxo_snapshot(open_snapshot()?, &cfg.consensus).map_err(|err| format!("Invalid snapshot: {}", err))?;
+	info!("Loading UTXO snapshot of block {} ({})", assumeutxo.height, assumeutxo.block_hash);
+	let snapshot_base = db
+		.load_utxo_snapshot(open_snapshot()?, &assumeutxo)
+		.map_err(|err| format!("Cannot load snapshot: {}", err))?;
+	db.as_store().shutdown();
+	info!(
+		"Loaded {} transactions with unspent outputs. Blocks up to the snapshot base will be validated in background",
+		snapshot_base.transactions
+	);
+
+	Ok(())
+}
@@ -1,10 +1,12 @@
 mod import;
+mod load_snapshot;
 mod rollback;
 mod start;
 mod stats;
 mod verify;
 
 pub use self::import::import;
+pub use self::load_snapshot::load_snapshot;
 pub use self::rollback::rollback;
 pub use self::start::start;
 pub use self::stats::stats;
@@ -1,19 +1,34 @@
 use crate::app_dir::app_path;
 use crate::block_notifier::BlockNotifier;
 use crate::config;
+use crate::snapshot_validator::{SnapshotValidator, SnapshotValidatorParams};
 use memory::Memory;
 use network::network::{PROTOCOL_MINIMUM, PROTOCOL_VERSION};
+use network::Network;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use storage::CanonStore;
+use storage::{CanonStore, UtxoSnapshotProvider};
 use sync::{create_local_sync_node, create_sync_connection_factory, create_sync_peers};
+use wallet::{Wallet, WalletListener};
 
 /// Some setup functions in here spawn new threads (which should be done off the main thread)
 /// At the moment only the p2p context runs on the Tokio runtime. RPC server has its own Tokio runtime.
 pub fn start(cfg: config::Config) -> Result<(), String> {
-	let db =
-		Arc::new(db::BlockChainDatabase::persistent(&&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap());
+	let mut db = db::BlockChainDatabase::persistent(&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap();
+	if cfg.address_index {
+		db = db
+			.with_script_index()
+			.map_err(|err| format!("Cannot enable address index: {:?}", err))?;
+	}
+	db = db
+		.with_block_filters()
+		.map_err(|err| format!("Cannot build compact filters: {:?}", err))?;
+	if let Some(prune) = cfg.prune {
+		db = db.with_pruning(prune * 1024 * 1024);
+	}
+	let db = Arc::new(db);
 
 	let runtime = tokio::runtime::Builder::new_multi_thread()
 		.enable_io()
@@ -27,13 +42,63 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		db.clone(),
 		sync_peers.clone(),
 		cfg.verification_params.clone(),
+		cfg.memory_pool_params.clone(),
 	);
 	let sync_connection_factory = create_sync_connection_factory(sync_peers.clone(), local_sync_node.clone());
 
+	// fee estimates are restored before memory pool, so that restored transactions are tracked by the estimator
+	let fee_estimates_path = PathBuf::from(app_path(&cfg.data_dir, "")).join("fee_estimates.dat");
+	if fee_estimates_path.exists() {
+		if let Err(err) = local_sync_node.load_fee_estimates(&fee_estimates_path) {
+			warn!("{}", err);
+		}
+	}
+
+	// memory pool is restored before node starts synchronization, because transactions are only accepted when synchronized
+	let memory_pool_path = PathBuf::from(app_path(&cfg.data_dir, "")).join("mempool.dat");
+	if cfg.persist_mempool && memory_pool_path.exists() {
+		if let Err(err) = local_sync_node.load_memory_pool(&memory_pool_path) {
+			warn!("{}", err);
+		}
+	}
+
 	if let Some(block_notify_command) = cfg.block_notify_command.clone() {
 		local_sync_node.install_sync_listener(Box::new(BlockNotifier::new(block_notify_command)));
 	}
 
+	let wallet = match cfg.wallet {
+		true => {
+			let wallet_path = PathBuf::from(app_path(&cfg.data_dir, "")).join("wallet.json");
+			let wallet_network = match cfg.network {
+				Network::Mainnet => keys::Network::Mainnet,
+				Network::Regtest => keys::Network::Regtest,
+				_ => keys::Network::Testnet,
+			};
+			let wallet = Wallet::open(&wallet_path, wallet_network).map_err(|err| format!("Cannot open wallet: {}", err))?;
+			// blocks, inserted while the node was stopped, are processed before the node starts synchronization
+			wallet
+				.sync_chain(db.as_store())
+				.map_err(|err| format!("Cannot synchronize wallet: {}", err))?;
+			let wallet = Arc::new(wallet);
+			local_sync_node.install_sync_listener(Box::new(WalletListener::new(wallet.clone(), db.clone())));
+			Some(wallet)
+		}
+		false => None,
+	};
+
+	let onion_private_key_path = PathBuf::from(app_path(&cfg.data_dir, "p2p")).join("onion_v3_private_key");
+	let tor_control = cfg.tor_control.map(|address| p2p::TorControlConfig {
+		address,
+		password: cfg.tor_password.clone(),
+		private_key_path: onion_private_key_path,
+	});
+	// blocks up to the UTXO snapshot base are never stored => only recent blocks are served
+	let services = match db.snapshot_base() {
+		Some(_) => cfg.services.with_network(false).with_network_limited(true),
+		None => cfg.services,
+	};
+	// filters are only served when they're known for every block since genesis
+	let services = services.with_compact_filters(db.has_block_filters());
 	let p2p_cfg = p2p::Config {
 		inbound_connections: cfg.inbound_connections,
 		outbound_connections: cfg.outbound_connections,
@@ -42,22 +107,43 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 			protocol_minimum: PROTOCOL_MINIMUM,
 			network: cfg.consensus.network,
 			local_address: SocketAddr::new(cfg.host.unwrap(), cfg.port),
-			services: cfg.services,
+			services,
 			user_agent: cfg.user_agent,
 			start_height: 0,
 			relay: true,
+			proxy: cfg.proxy.clone(),
+			onion_proxy: cfg.onion_proxy.clone(),
 		},
 		peers: cfg.connect.map_or_else(|| vec![], |x| vec![x]),
 		seed: cfg.seednode,
+		dns_seed: cfg.dns_seed,
+		force_dns_seed: cfg.force_dns_seed,
 		node_table_path: app_path(&cfg.data_dir, "p2p"),
 		preferable_services: cfg.services,
 		internet_protocol: cfg.internet_protocol,
+		ban_time: cfg.ban_time,
+		tor_control,
 	};
 	let p2p_context = Arc::new(p2p::Context::new(runtime.handle().clone(), sync_connection_factory, p2p_cfg).map_err(|e| e.to_string())?);
 	let p2p = p2p::P2P::new(p2p_context.clone());
 
 	let shutdown_signal = Arc::new(tokio::sync::Notify::new());
 
+	let snapshot_validator = match db.snapshot_base() {
+		Some(snapshot_base) if !snapshot_base.validated => Some(SnapshotValidator::new(
+			SnapshotValidatorParams {
+				db: db.clone(),
+				consensus: cfg.consensus.clone(),
+				snapshot_base,
+				historical_blocks: local_sync_node.historical_blocks(),
+				validation_db_path: app_path(&cfg.data_dir, "snapshot_validation"),
+				validation_db_cache: cfg.db_cache,
+			},
+			shutdown_signal.clone(),
+		)),
+		_ => None,
+	};
+
 	let rpc_deps = rpc_server::Dependencies {
 		network: cfg.network,
 		storage: db.clone(),
@@ -65,9 +151,27 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		p2p_context,
 		memory: Arc::new(Memory::new()),
 		shutdown_signal: shutdown_signal.clone(),
+		memory_pool_path: memory_pool_path.clone(),
+		wallet: wallet.clone(),
 	};
 	let rpc_server = rpc_server::new_http(cfg.rpc_config, rpc_deps)?.unwrap();
 
+	let electrum_deps = electrum::Dependencies {
+		consensus: cfg.consensus.clone(),
+		local_sync_node: local_sync_node.clone(),
+		storage: db.clone(),
+	};
+	if let Some(electrum_server) = runtime.block_on(electrum::new_tcp(cfg.electrum_config, electrum_deps))? {
+		runtime.spawn(electrum_server.run());
+	}
+
+	let stratum_deps = stratum::Dependencies {
+		local_sync_node: local_sync_node.clone(),
+	};
+	if let Some(stratum_server) = runtime.block_on(stratum::new_tcp(cfg.stratum_config, stratum_deps))? {
+		runtime.spawn(stratum_server.run());
+	}
+
 	let p2p2 = p2p.clone();
 	runtime.spawn(async move { p2p2.run().await });
 
@@ -81,8 +185,23 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 	info!("Shutting down, please wait...");
 	rpc_server.close();
 	p2p.shutdown();
+	if cfg.persist_mempool {
+		match local_sync_node.save_memory_pool(&memory_pool_path) {
+			Ok(transactions) => info!("Saved {} memory pool transactions", transactions),
+			Err(err) => error!("{}", err),
+		}
+	}
+	if let Err(err) = local_sync_node.save_fee_estimates(&fee_estimates_path) {
+		error!("{}", err);
+	}
+	if let Some(wallet) = wallet {
+		if let Err(err) = wallet.flush() {
+			error!("Cannot write wallet: {}", err);
+		}
+	}
 	local_sync_node.shutdown();
 	runtime.shutdown_timeout(Duration::from_secs(30));
+	drop(snapshot_validator);
 	db.as_store().shutdown();
 
 	Ok(())
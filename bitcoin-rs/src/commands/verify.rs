@@ -1,7 +1,7 @@
 use crate::app_dir::app_path;
 use crate::config::Config;
 use std::time::SystemTime;
-use storage::{BlockProvider, BlockRef, CanonStore};
+use storage::{BlockProvider, BlockRef, CanonStore, UtxoSnapshotProvider};
 use verification::ChainVerifier;
 
 pub fn verify(cfg: &Config) -> Result<(), String> {
@@ -21,9 +21,11 @@ pub fn verify(cfg: &Config) -> Result<(), String> {
 	};
 
 	let best = db.best_block();
-	info!("Verifying from block 0 to {}", best.number);
+	// blocks up to the UTXO snapshot base are not stored
+	let first = db.snapshot_base().map(|snapshot_base| snapshot_base.block.number + 1).unwrap_or(0);
+	info!("Verifying from block {} to {}", first, best.number);
 
-	for block_number in 0..best.number {
+	for block_number in first..best.number {
 		match db.block(BlockRef::Number(block_number)) {
 			Some(block) => {
 				match ChainVerifier::new(
@@ -1,17 +1,24 @@
 use clap;
+use electrum::TcpConfiguration as ElectrumTcpConfig;
 use message::Services;
 use network::{ConsensusParams, Network};
-use p2p::InternetProtocol;
+use p2p::{InternetProtocol, Proxy};
 use rpc_server::rpc_apis::ApiSet;
 use rpc_server::HttpConfiguration as RpcHttpConfig;
 use std::net;
-use sync::VerificationParameters;
+use stratum::TcpConfiguration as StratumTcpConfig;
+use sync::{MemoryPoolLimits, MemoryPoolParameters, VerificationParameters};
 use verification::VerificationLevel;
 
 pub const USER_AGENT: &'static str = env!("CARGO_PKG_NAME");
 pub const USER_AGENT_VERSION: &'static str = env!("CARGO_PKG_VERSION");
 pub const REGTEST_USER_AGENT: &'static str = "/Satoshi:0.12.1/";
 pub const DEFAULT_DB_CACHE: usize = 64;
+/// Duration of the automatic ban of misbehaving peers, in seconds
+pub const DEFAULT_BAN_TIME: u64 = 24 * 60 * 60;
+/// Minimal size of the stored blocks in pruning mode, in MB
+pub const MIN_PRUNE_TARGET: u64 = 550;
+pub const DEFAULT_STRATUM_PORT: u16 = 3333;
 
 #[derive(Default)]
 pub struct Config {
@@ -22,14 +29,28 @@ pub struct Config {
 	pub connect: Option<net::SocketAddr>,
 	pub host: Option<net::IpAddr>,
 	pub seednode: Option<net::SocketAddr>,
+	pub dns_seed: bool,
+	pub force_dns_seed: bool,
 	pub inbound_connections: u32,
 	pub outbound_connections: u32,
 	pub db_cache: usize,
 	pub data_dir: Option<String>,
 	pub user_agent: String,
 	pub internet_protocol: InternetProtocol,
+	pub proxy: Option<Proxy>,
+	pub onion_proxy: Option<Proxy>,
+	pub tor_control: Option<net::SocketAddr>,
+	pub tor_password: Option<String>,
+	pub ban_time: u64,
 	pub rpc_config: RpcHttpConfig,
+	pub electrum_config: ElectrumTcpConfig,
+	pub stratum_config: StratumTcpConfig,
 	pub block_notify_command: Option<String>,
+	pub address_index: bool,
+	pub wallet: bool,
+	pub prune: Option<u64>,
+	pub persist_mempool: bool,
+	pub memory_pool_params: MemoryPoolParameters,
 	pub verification_params: VerificationParameters,
 }
 
@@ -84,6 +105,10 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		None => None,
 	};
 
+	// DNS seeds are not queried, when connecting to the single node, unless it is requested
+	let dns_seed = matches.is_present("dnsseed") || (!matches.is_present("no-dnsseed") && connect.is_none());
+	let force_dns_seed = matches.is_present("forcednsseed");
+
 	let only_net = match matches.value_of("only-net") {
 		Some(s) => s.parse()?,
 		None => InternetProtocol::default(),
@@ -92,19 +117,67 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 	let host = match matches.value_of("host") {
 		Some(s) => Some(s.parse::<net::IpAddr>().map_err(|_| "Invalid host".to_owned())?),
 		None => match only_net {
-			InternetProtocol::IpV6 => Some("::".parse().unwrap()),
+			InternetProtocol::IpV6 | InternetProtocol::Cjdns => Some("::".parse().unwrap()),
 			_ => Some("0.0.0.0".parse().unwrap()),
 		},
 	};
 
+	let randomize_credentials = !matches.is_present("no-proxy-randomize");
+	let proxy = match matches.value_of("proxy") {
+		Some(s) => Some(Proxy {
+			address: s.parse().map_err(|_| "Invalid proxy".to_owned())?,
+			randomize_credentials,
+		}),
+		None => None,
+	};
+
+	let onion_proxy = match matches.value_of("onion") {
+		Some(s) => Some(Proxy {
+			address: s.parse().map_err(|_| "Invalid onion proxy".to_owned())?,
+			randomize_credentials,
+		}),
+		None => None,
+	};
+
+	let tor_control = match matches.value_of("tor-control") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid tor-control".to_owned())?),
+		None => None,
+	};
+
 	let rpc_config = parse_rpc_config(network, matches)?;
+	let memory_pool_params = parse_memory_pool_params(matches)?;
+	let electrum_config = parse_electrum_config(network, matches)?;
+	let stratum_config = parse_stratum_config(matches)?;
 
 	let block_notify_command = match matches.value_of("blocknotify") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid blocknotify commmand".to_owned())?),
 		None => None,
 	};
 
-	let services = Services::default().with_network(true).with_witness(true);
+	let prune = match matches.value_of("prune") {
+		Some(s) => match s.parse() {
+			Ok(prune) if prune >= MIN_PRUNE_TARGET => Some(prune),
+			_ => {
+				return Err(format!(
+					"Invalid prune target - should be number in MB, not less than {}",
+					MIN_PRUNE_TARGET
+				))
+			}
+		},
+		None => None,
+	};
+
+	let ban_time = match matches.value_of("bantime") {
+		Some(s) => s.parse().map_err(|_| "Invalid bantime - should be number of seconds".to_owned())?,
+		None => DEFAULT_BAN_TIME,
+	};
+
+	// pruned node only serves recent blocks
+	let services = Services::default()
+		.with_network(prune.is_none())
+		.with_network_limited(prune.is_some())
+		.with_witness(true)
+		.with_p2p_v2(!matches.is_present("no-v2transport"));
 
 	let verification_level = match matches.value_of("verification-level") {
 		Some(s) if s == "full" => VerificationLevel::Full,
@@ -127,14 +200,28 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		connect,
 		host,
 		seednode,
+		dns_seed,
+		force_dns_seed,
 		inbound_connections: in_connections,
 		outbound_connections: out_connections,
 		db_cache,
 		data_dir,
 		user_agent,
 		internet_protocol: only_net,
+		proxy,
+		onion_proxy,
+		tor_control,
+		tor_password: matches.value_of("tor-password").map(ToOwned::to_owned),
+		ban_time,
 		rpc_config,
+		electrum_config,
+		stratum_config,
 		block_notify_command,
+		address_index: matches.is_present("address-index"),
+		wallet: matches.is_present("wallet"),
+		prune,
+		persist_mempool: !matches.is_present("no-persist-mempool"),
+		memory_pool_params,
 		verification_params: VerificationParameters {
 			verification_level,
 			verification_edge,
@@ -169,3 +256,77 @@ fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcH
 
 	Ok(config)
 }
+
+fn parse_electrum_config(network: Network, matches: &clap::ArgMatches) -> Result<ElectrumTcpConfig, String> {
+	let mut config = ElectrumTcpConfig::with_port(network.electrum_port());
+	config.enabled = matches.is_present("electrum");
+	if !config.enabled {
+		return Ok(config);
+	}
+
+	if let Some(port) = matches.value_of("electrum-port") {
+		config.port = port.parse().map_err(|_| "Invalid Electrum port".to_owned())?;
+	}
+	if let Some(interface) = matches.value_of("electrum-interface") {
+		config.interface = interface.to_owned();
+	}
+
+	Ok(config)
+}
+
+fn parse_stratum_config(matches: &clap::ArgMatches) -> Result<StratumTcpConfig, String> {
+	let mut config = StratumTcpConfig::with_port(DEFAULT_STRATUM_PORT);
+	config.enabled = matches.is_present("stratum");
+	if !config.enabled {
+		return Ok(config);
+	}
+
+	if let Some(port) = matches.value_of("stratum-port") {
+		config.port = port.parse().map_err(|_| "Invalid Stratum port".to_owned())?;
+	}
+	if let Some(interface) = matches.value_of("stratum-interface") {
+		config.interface = interface.to_owned();
+	}
+	if let Some(address) = matches.value_of("stratum-address") {
+		config.payout_address = Some(address.parse().map_err(|_| "Invalid Stratum payout address".to_owned())?);
+	}
+	if let Some(difficulty) = matches.value_of("stratum-difficulty") {
+		config.difficulty = difficulty.parse().map_err(|_| "Invalid Stratum difficulty".to_owned())?;
+		if !config.difficulty.is_finite() || config.difficulty <= 0.0 {
+			return Err("Invalid Stratum difficulty".into());
+		}
+	}
+
+	Ok(config)
+}
+
+fn parse_memory_pool_params(matches: &clap::ArgMatches) -> Result<MemoryPoolParameters, String> {
+	let mut limits = MemoryPoolLimits::default();
+	if let Some(max_size) = matches.value_of("maxmempool") {
+		let max_size: usize = max_size.parse().map_err(|_| "Invalid maxmempool".to_owned())?;
+		limits.max_size_in_bytes = max_size * 1_000_000;
+	}
+	if let Some(expiry) = matches.value_of("mempoolexpiry") {
+		let expiry: u64 = expiry.parse().map_err(|_| "Invalid mempoolexpiry".to_owned())?;
+		limits.expiry_time = expiry * 60 * 60;
+	}
+	if let Some(count) = matches.value_of("limitancestorcount") {
+		limits.max_ancestors_count = count.parse().map_err(|_| "Invalid limitancestorcount".to_owned())?;
+	}
+	if let Some(size) = matches.value_of("limitancestorsize") {
+		let size: usize = size.parse().map_err(|_| "Invalid limitancestorsize".to_owned())?;
+		limits.max_ancestors_size = size * 1_000;
+	}
+	if let Some(count) = matches.value_of("limitdescendantcount") {
+		limits.max_descendants_count = count.parse().map_err(|_| "Invalid limitdescendantcount".to_owned())?;
+	}
+	if let Some(size) = matches.value_of("limitdescendantsize") {
+		let size: usize = size.parse().map_err(|_| "Invalid limitdescendantsize".to_owned())?;
+		limits.max_descendants_size = size * 1_000;
+	}
+
+	Ok(MemoryPoolParameters {
+		full_rbf: matches.is_present("mempool-full-rbf"),
+		limits,
+	})
+}
@@ -12,6 +12,7 @@ mod block_notifier;
 mod commands;
 mod config;
 mod logger;
+mod snapshot_validator;
 
 #[global_allocator]
 static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
@@ -38,6 +39,7 @@ fn run() -> Result<(), String> {
 		("stats", Some(_)) => commands::stats(&cfg),
 		("import", Some(import_matches)) => commands::import(&cfg, import_matches),
 		("verify", Some(_)) => commands::verify(&cfg),
+		("load-snapshot", Some(load_snapshot_matches)) => commands::load_snapshot(&cfg, load_snapshot_matches),
 		_ => commands::start(cfg),
 	}
 }
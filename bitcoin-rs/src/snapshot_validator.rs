@@ -0,0 +1,203 @@
+use bitcrypto::SHA256D;
+use db::ham_db::HamDb;
+use db::BlockChainDatabase;
+use network::ConsensusParams;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use storage::{utxo_set_hash, BlockProvider, SnapshotBase, Store};
+use sync::{create_sync_blocks_writer, HistoricalBlocksRef, VerificationParameters};
+
+/// Number of blocks, requested from the same peer at once
+const BLOCKS_WINDOW: u32 = 16;
+/// Blocks are requested from another peer if not received in this time
+const BLOCK_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Interval of checking whether validation has been interrupted
+const STOP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Validation fails if block is rejected by this number of peers
+const MAX_BLOCK_VERIFICATION_FAILURES: usize = 3;
+
+/// Validates UTXO snapshot in background, while node is synchronizing blocks after the snapshot base.
+/// Blocks up to the snapshot base are downloaded from full nodes and fully verified, building separate
+/// chain state. Hash of its UTXO set is then compared to the expected hash of the snapshot.
+pub struct SnapshotValidator {
+	stop: Arc<AtomicBool>,
+	worker_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// Snapshot validation dependencies
+pub struct SnapshotValidatorParams {
+	/// Node database, loaded from the snapshot
+	pub db: Arc<BlockChainDatabase<HamDb>>,
+	pub consensus: ConsensusParams,
+	pub snapshot_base: SnapshotBase,
+	/// Downloader of blocks up to the snapshot base
+	pub historical_blocks: HistoricalBlocksRef,
+	/// Path of the database, where validated blocks are stored
+	pub validation_db_path: String,
+	/// Cache size of the validation database (in MB)
+	pub validation_db_cache: usize,
+}
+
+impl SnapshotValidator {
+	pub fn new(params: SnapshotValidatorParams, shutdown_signal: Arc<tokio::sync::Notify>) -> Self {
+		let stop = Arc::new(AtomicBool::default());
+		let worker_stop = stop.clone();
+		SnapshotValidator {
+			stop,
+			worker_thread: Some(
+				std::thread::Builder::new()
+					.name("Snapshot validation thread".to_owned())
+					.spawn(move || {
+						let result = SnapshotValidator::worker(&params, &worker_stop);
+						params.historical_blocks.cancel();
+						match result {
+							Ok(false) => (),
+							Ok(true) => match params.db.set_snapshot_validated() {
+								Ok(()) => info!(target: "bitcoin-rs", "UTXO snapshot has been validated"),
+								Err(err) => error!(target: "bitcoin-rs", "Cannot mark UTXO snapshot as validated: {}", err),
+							},
+							Err(err) => {
+								error!(target: "bitcoin-rs", "UTXO snapshot validation has failed: {}", err);
+								shutdown_signal.notify_one();
+							}
+						}
+					})
+					.expect("Error creating snapshot validation thread"),
+			),
+		}
+	}
+
+	/// Returns Ok(false) if validation has been interrupted
+	fn worker(params: &SnapshotValidatorParams, stop: &AtomicBool) -> Result<bool, String> {
+		let base = &params.snapshot_base.block;
+		let assumeutxo = params
+			.consensus
+			.assumeutxo_for(&base.hash)
+			.ok_or_else(|| format!("Snapshot base {} is not known to the network", base.hash))?;
+
+		// validation database is kept between restarts, so that validation continues from the last validated block
+		let validation_db = Arc::new(
+			BlockChainDatabase::persistent(
+				&params.validation_db_path,
+				params.validation_db_cache,
+				&params.consensus.network.genesis_block(),
+			)
+			.map_err(|err| format!("Cannot open validation database: {}", err))?,
+		);
+		let mut blocks_writer =
+			create_sync_blocks_writer(validation_db.clone(), params.consensus.clone(), VerificationParameters::default());
+
+		let mut number = validation_db.best_block().number + 1;
+		info!(target: "bitcoin-rs", "Validating blocks {}..{} up to the UTXO snapshot base", number, base.number);
+		while number <= base.number {
+			let hashes = (number..=::std::cmp::min(number + BLOCKS_WINDOW - 1, base.number))
+				.map(|number| params.db.block_hash(number).ok_or_else(|| format!("Missing header {}", number)))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			let mut next = 0;
+			let mut failures = 0;
+			while next != hashes.len() {
+				let peer_index = match params.historical_blocks.request(&hashes[next..]) {
+					Some(peer_index) => peer_index,
+					None => {
+						if stop.load(Ordering::SeqCst) {
+							validation_db.shutdown();
+							return Ok(false);
+						}
+						::std::thread::sleep(STOP_CHECK_INTERVAL);
+						continue;
+					}
+				};
+
+				while next != hashes.len() {
+					let block = match wait_block(&params.historical_blocks, &hashes[next], stop) {
+						WaitResult::Received(block) => block,
+						WaitResult::Timeout => break,
+						WaitResult::Interrupted => {
+							validation_db.shutdown();
+							return Ok(false);
+						}
+					};
+
+					if let Err(err) = blocks_writer.append_block(block) {
+						failures += 1;
+						warn!(
+							target: "bitcoin-rs",
+							"Block {} received from peer#{} is invalid: {:?}",
+							number + next as u32,
+							peer_index,
+							err
+						);
+						if failures == MAX_BLOCK_VERIFICATION_FAILURES {
+							return Err(format!("Block {} ({}) is invalid: {:?}", number + next as u32, hashes[next], err));
+						}
+						break;
+					}
+
+					next += 1;
+				}
+			}
+
+			number += hashes.len() as u32;
+			if number % 10000 < BLOCKS_WINDOW {
+				info!(target: "bitcoin-rs", "Validated {} snapshot blocks", number - 1);
+			}
+		}
+
+		let best_block = validation_db.best_block();
+		if best_block != *base {
+			return Err(format!(
+				"Validated chain ends at block {} instead of the snapshot base",
+				best_block.hash
+			));
+		}
+		let txoutset_hash = utxo_set_hash(validation_db.as_ref()).map_err(|err| err.to_string())?;
+		if txoutset_hash != assumeutxo.txoutset_hash {
+			return Err(format!(
+				"UTXO set hash {} does not match the snapshot hash {}",
+				txoutset_hash, assumeutxo.txoutset_hash
+			));
+		}
+
+		// validated chain state is not needed anymore
+		drop(blocks_writer);
+		validation_db.shutdown();
+		drop(validation_db);
+		if let Err(err) = ::std::fs::remove_dir_all(&params.validation_db_path) {
+			warn!(target: "bitcoin-rs", "Cannot remove validation database: {}", err);
+		}
+
+		Ok(true)
+	}
+}
+
+enum WaitResult {
+	Received(chain::IndexedBlock),
+	Timeout,
+	Interrupted,
+}
+
+fn wait_block(historical_blocks: &HistoricalBlocksRef, hash: &SHA256D, stop: &AtomicBool) -> WaitResult {
+	let start = Instant::now();
+	loop {
+		if stop.load(Ordering::SeqCst) {
+			return WaitResult::Interrupted;
+		}
+		if let Some(block) = historical_blocks.wait(hash, STOP_CHECK_INTERVAL) {
+			return WaitResult::Received(block);
+		}
+		if start.elapsed() >= BLOCK_REQUEST_TIMEOUT {
+			return WaitResult::Timeout;
+		}
+	}
+}
+
+impl Drop for SnapshotValidator {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			self.stop.store(true, Ordering::SeqCst);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
@@ -0,0 +1,300 @@
+//! Compact block filters
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+
+use crate::bytes::Bytes;
+use crate::IndexedBlock;
+use bitcrypto::{dhash256, siphash24, SHA256D};
+use ser::{serialize, CompactInteger, Reader, Serializable};
+use std::collections::BTreeSet;
+
+/// Type of the basic block filter
+pub const BASIC_FILTER_TYPE: u8 = 0;
+/// Golomb-Rice coding parameter of the basic filter
+const BASIC_FILTER_P: u8 = 19;
+/// Inverse of the false positive rate of the basic filter
+const BASIC_FILTER_M: u64 = 784_931;
+/// First byte of OP_RETURN outputs, which are never included in the basic filter
+const OP_RETURN: u8 = 0x6a;
+
+/// Golomb-coded set of block elements
+#[derive(Debug, Default, Clone, PartialEq, Serializable, Deserializable)]
+pub struct BlockFilter {
+	/// Number of elements, followed by the Golomb-Rice encoded set
+	pub content: Bytes,
+}
+
+impl From<Bytes> for BlockFilter {
+	fn from(content: Bytes) -> Self {
+		BlockFilter { content }
+	}
+}
+
+impl BlockFilter {
+	/// Builds basic filter of the block.
+	/// `spent_scripts` are script pubkeys of the outputs, spent by the block transactions.
+	pub fn basic<'a, I>(block: &'a IndexedBlock, spent_scripts: I) -> Self
+	where
+		I: IntoIterator<Item = &'a [u8]>,
+	{
+		let elements = block
+			.transactions
+			.iter()
+			.flat_map(|tx| tx.raw.outputs.iter())
+			.map(|output| &output.script_pubkey[..])
+			.filter(|script| script.first().is_some_and(|op| *op != OP_RETURN))
+			.chain(spent_scripts.into_iter().filter(|script| !script.is_empty()))
+			.collect::<BTreeSet<_>>();
+
+		let (k0, k1) = siphash_keys(block.hash());
+		let f = elements.len() as u64 * BASIC_FILTER_M;
+		let mut values = elements
+			.into_iter()
+			.map(|element| hash_to_range(k0, k1, f, element))
+			.collect::<Vec<_>>();
+		values.sort_unstable();
+
+		let mut writer = BitWriter::new(serialize(&CompactInteger::from(values.len())).take());
+		let mut last_value = 0;
+		for value in values {
+			golomb_rice_encode(&mut writer, BASIC_FILTER_P, value - last_value);
+			last_value = value;
+		}
+
+		BlockFilter {
+			content: writer.finish().into(),
+		}
+	}
+
+	/// Returns hash of the filter
+	pub fn hash(&self) -> SHA256D {
+		dhash256(&self.content)
+	}
+
+	/// Returns filter header, given the header of the previous block filter
+	pub fn header(&self, previous_header: &SHA256D) -> SHA256D {
+		let mut data = [0u8; 64];
+		data[..32].copy_from_slice(&self.hash() as &[u8]);
+		data[32..].copy_from_slice(previous_header as &[u8]);
+		dhash256(&data)
+	}
+
+	/// Returns true if any of given elements (probably) belongs to the basic filter of the block with given hash
+	pub fn match_any(&self, block_hash: &SHA256D, elements: &[&[u8]]) -> bool {
+		let n: CompactInteger = match Reader::new(&self.content).read() {
+			Ok(n) => n,
+			Err(_) => return false,
+		};
+		let n: u64 = n.into();
+		if n == 0 || elements.is_empty() {
+			return false;
+		}
+
+		let (k0, k1) = siphash_keys(block_hash);
+		let f = n * BASIC_FILTER_M;
+		let mut queries = elements.iter().map(|element| hash_to_range(k0, k1, f, element)).collect::<Vec<_>>();
+		queries.sort_unstable();
+
+		let offset = CompactInteger::from(n).serialized_size();
+		let mut reader = BitReader::new(&self.content[offset..]);
+		let mut value = 0;
+		let mut queries = queries.into_iter().peekable();
+		for _ in 0..n {
+			value += match golomb_rice_decode(&mut reader, BASIC_FILTER_P) {
+				Some(delta) => delta,
+				None => return false,
+			};
+
+			while let Some(query) = queries.peek() {
+				if *query == value {
+					return true;
+				}
+				if *query > value {
+					break;
+				}
+				queries.next();
+			}
+
+			if queries.peek().is_none() {
+				return false;
+			}
+		}
+
+		false
+	}
+}
+
+fn siphash_keys(block_hash: &SHA256D) -> (u64, u64) {
+	let mut k0 = [0u8; 8];
+	let mut k1 = [0u8; 8];
+	k0.copy_from_slice(&block_hash[0..8]);
+	k1.copy_from_slice(&block_hash[8..16]);
+	(u64::from_le_bytes(k0), u64::from_le_bytes(k1))
+}
+
+fn hash_to_range(k0: u64, k1: u64, f: u64, element: &[u8]) -> u64 {
+	((u128::from(siphash24(k0, k1, element)) * u128::from(f)) >> 64) as u64
+}
+
+fn golomb_rice_encode(writer: &mut BitWriter, p: u8, value: u64) {
+	let mut quotient = value >> p;
+	while quotient > 0 {
+		let bits = quotient.min(64) as u8;
+		writer.write(u64::MAX, bits);
+		quotient -= u64::from(bits);
+	}
+	writer.write(0, 1);
+	writer.write(value, p);
+}
+
+fn golomb_rice_decode(reader: &mut BitReader, p: u8) -> Option<u64> {
+	let mut quotient = 0u64;
+	while reader.read(1)? == 1 {
+		quotient += 1;
+	}
+	let remainder = reader.read(p)?;
+	Some((quotient << p) + remainder)
+}
+
+/// Writes bits, most significant first
+struct BitWriter {
+	buffer: Vec<u8>,
+	byte: u8,
+	offset: u8,
+}
+
+impl BitWriter {
+	fn new(buffer: Vec<u8>) -> Self {
+		BitWriter {
+			buffer,
+			byte: 0,
+			offset: 0,
+		}
+	}
+
+	/// Writes `bits` least significant bits of the `data`
+	fn write(&mut self, data: u64, bits: u8) {
+		for i in (0..bits).rev() {
+			self.byte |= (((data >> i) & 1) as u8) << (7 - self.offset);
+			self.offset += 1;
+			if self.offset == 8 {
+				self.buffer.push(self.byte);
+				self.byte = 0;
+				self.offset = 0;
+			}
+		}
+	}
+
+	fn finish(mut self) -> Vec<u8> {
+		if self.offset != 0 {
+			self.buffer.push(self.byte);
+		}
+		self.buffer
+	}
+}
+
+/// Reads bits, most significant first
+struct BitReader<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> BitReader<'a> {
+	fn new(data: &'a [u8]) -> Self {
+		BitReader { data, position: 0 }
+	}
+
+	fn read(&mut self, bits: u8) -> Option<u64> {
+		let mut result = 0u64;
+		for _ in 0..bits {
+			let byte = self.data.get(self.position / 8)?;
+			let bit = (byte >> (7 - self.position % 8)) & 1;
+			result = (result << 1) | u64::from(bit);
+			self.position += 1;
+		}
+		Some(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::BlockFilter;
+	use crate::bytes::Bytes;
+	use crate::IndexedBlock;
+	use bitcrypto::{FromStr, SHA256D};
+	use std::{env, fs, path};
+
+	fn testnet_genesis() -> IndexedBlock {
+		"0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff001d1aa4ae180101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into()
+	}
+
+	#[test]
+	fn test_basic_filter_testnet_genesis() {
+		// BIP158 test vector for testnet block #0
+		let block = testnet_genesis();
+		let filter = BlockFilter::basic(&block, Vec::new());
+		assert_eq!(filter.content, "019dfca8".into());
+		assert_eq!(
+			filter.header(&SHA256D::default()),
+			SHA256D::from_str("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750").unwrap()
+		);
+	}
+
+	#[test]
+	fn test_basic_filter_match() {
+		let block = testnet_genesis();
+		let spent_script = [0x51u8, 0x52, 0x53];
+		let filter = BlockFilter::basic(&block, vec![&spent_script[..], &[][..]]);
+
+		let output_script = &*block.transactions[0].raw.outputs[0].script_pubkey;
+		assert!(filter.match_any(block.hash(), &[output_script]));
+		assert!(filter.match_any(block.hash(), &[&[0x00][..], &spent_script[..]]));
+		assert!(!filter.match_any(block.hash(), &[&[0x00][..], &[0x51][..]]));
+		assert!(!filter.match_any(block.hash(), &[]));
+		assert!(!filter.match_any(&SHA256D::default(), &[output_script]));
+	}
+
+	#[test]
+	fn test_basic_filter_empty() {
+		let mut block = testnet_genesis();
+		block.transactions[0].raw.outputs[0].script_pubkey = "6a0101".into();
+		let filter = BlockFilter::basic(&block, Vec::new());
+		assert_eq!(filter.content, "00".into());
+		assert!(!filter.match_any(block.hash(), &[&[0x6a, 0x01, 0x01][..]]));
+	}
+
+	/// Checks rows of BIP158 test vectors: height, block hash, block, spent scripts, previous header, filter, header, notes.
+	fn run_bip158_test_vectors(vectors: &serde_json::Value) {
+		// the first row describes columns
+		for row in vectors.as_array().unwrap().iter().skip(1) {
+			let hex = |column: usize| -> Bytes { row[column].as_str().unwrap().parse().unwrap() };
+			let block: IndexedBlock = ser::deserialize(&hex(2)[..]).unwrap();
+			assert_eq!(*block.hash(), SHA256D::from_str(row[1].as_str().unwrap()).unwrap());
+
+			let spent_scripts: Vec<Bytes> = row[3]
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|script| script.as_str().unwrap().parse().unwrap())
+				.collect();
+			let filter = BlockFilter::basic(&block, spent_scripts.iter().map(|script| &script[..]));
+			assert_eq!(filter.content, hex(5), "filter of block #{}", row[0]);
+
+			let previous_header = SHA256D::from_str(row[4].as_str().unwrap()).unwrap();
+			let header = SHA256D::from_str(row[6].as_str().unwrap()).unwrap();
+			assert_eq!(filter.header(&previous_header), header, "filter header of block #{}", row[0]);
+		}
+	}
+
+	#[test]
+	fn test_basic_filter_bip158_vectors() {
+		let vectors = serde_json::from_str(include_str!("../test_data/bip158_testnet-19.json")).unwrap();
+		run_bip158_test_vectors(&vectors);
+
+		// complete testnet-19.json, which is also used by Bitcoin Core as blockfilters.json
+		if let Some(dir) = env::var_os("DIR_UNIT_TEST_DATA") {
+			let vectors = fs::read(path::Path::new(&dir).join("blockfilters.json")).unwrap();
+			run_bip158_test_vectors(&serde_json::from_slice(&vectors).unwrap());
+		}
+	}
+}
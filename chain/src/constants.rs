@@ -16,6 +16,10 @@ pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
 // applied to extract that lock-time from the sequence field.
 pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
 
+/// Transaction, which has at least one input with sequence number below or equal to this value,
+/// signals that it could be replaced in the memory pool (BIP125).
+pub const MAX_BIP125_RBF_SEQUENCE: u32 = 0xfffffffd;
+
 /// Threshold for `nLockTime`: below this value it is interpreted as block number,
 /// otherwise as UNIX timestamp.
 pub const LOCKTIME_THRESHOLD: u32 = 500000000; // Tue Nov  5 00:53:20 1985 UTC
@@ -1,11 +1,14 @@
 extern crate heapsize;
 extern crate primitives;
 extern crate rayon;
+#[cfg(test)]
+extern crate serde_json;
 extern crate serialization as ser;
 #[macro_use]
 extern crate serialization_derive;
 
 pub use block::Block;
+pub use block_filter::{BlockFilter, BASIC_FILTER_TYPE};
 pub use block_header::BlockHeader;
 pub use indexed_block::IndexedBlock;
 pub use indexed_header::IndexedBlockHeader;
@@ -19,6 +22,7 @@ pub use transaction::{OutPoint, Transaction, TransactionInput, TransactionOutput
 pub mod constants;
 
 mod block;
+mod block_filter;
 mod block_header;
 mod indexed_block;
 mod indexed_header;
@@ -2,7 +2,7 @@
 //! https://en.bitcoin.it/wiki/Protocol_documentation#tx
 
 use crate::bytes::Bytes;
-use crate::constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL};
+use crate::constants::{LOCKTIME_THRESHOLD, MAX_BIP125_RBF_SEQUENCE, SEQUENCE_FINAL};
 use bitcrypto::{dhash256, FromHex, Hash, SHA256D};
 use heapsize::HeapSizeOf;
 use ser::{deserialize, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
@@ -145,6 +145,11 @@ impl Transaction {
 		self.inputs.iter().all(TransactionInput::is_final)
 	}
 
+	/// Returns true if transaction explicitly signals replaceability (BIP125)
+	pub fn signals_replaceability(&self) -> bool {
+		self.inputs.iter().any(|input| input.sequence <= MAX_BIP125_RBF_SEQUENCE)
+	}
+
 	pub fn is_final_in_block(&self, block_height: u32, block_time: u32) -> bool {
 		if self.lock_time == 0 {
 			return true;
@@ -167,6 +172,13 @@ impl Transaction {
 		self.inputs.iter().any(TransactionInput::has_witness)
 	}
 
+	/// Returns transaction weight, as defined in BIP141
+	pub fn weight(&self) -> usize {
+		let size = self.serialized_size();
+		let size_with_witness = self.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS);
+		(size * 3) + size_with_witness
+	}
+
 	pub fn total_spends(&self) -> u64 {
 		let mut result = 0u64;
 		for output in self.outputs.iter() {
@@ -369,4 +381,14 @@ mod tests {
 			"0000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000".into();
 		assert_ne!(transaction_with_witness.hash(), transaction_with_witness.witness_hash());
 	}
+
+	#[test]
+	fn test_transaction_signals_replaceability() {
+		let mut transaction: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		assert!(!transaction.signals_replaceability());
+		transaction.inputs[0].sequence = 0xfffffffe;
+		assert!(!transaction.signals_replaceability());
+		transaction.inputs[0].sequence = 0xfffffffd;
+		assert!(transaction.signals_replaceability());
+	}
 }
@@ -2,15 +2,18 @@
 extern crate bitcoin_hashes;
 #[macro_use]
 extern crate heapsize;
+extern crate tiny_keccak;
 
 pub use bitcoin_hashes::core::str::FromStr;
 use bitcoin_hashes::hash160;
 pub use bitcoin_hashes::hex::Error as HexError;
 pub use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::hmac::{Hmac, HmacEngine};
 use bitcoin_hashes::siphash24;
 pub use bitcoin_hashes::Hash;
 pub use bitcoin_hashes::HashEngine;
-use bitcoin_hashes::{ripemd160, sha1, sha256, sha256d};
+use bitcoin_hashes::{ripemd160, sha1, sha256, sha256d, sha512};
+use tiny_keccak::Hasher;
 
 hash_newtype!(
 	RIPEMD160,
@@ -43,6 +46,16 @@ pub fn sha256(input: &[u8]) -> SHA256 {
 	SHA256(sha256::Hash::hash(input))
 }
 
+/// BIP340 tagged hash: `sha256(sha256(tag) || sha256(tag) || input)`
+pub fn tagged_hash(tag: &[u8], input: &[u8]) -> SHA256 {
+	let tag_hash = sha256::Hash::hash(tag);
+	let mut engine = sha256::Hash::engine();
+	engine.input(&tag_hash[..]);
+	engine.input(&tag_hash[..]);
+	engine.input(input);
+	SHA256(sha256::Hash::from_engine(engine))
+}
+
 hash_newtype!(
 	HASH160,
 	hash160::Hash,
@@ -81,6 +94,51 @@ impl FromInnerHex for SHA256D {
 
 known_heap_size!(0, SHA256D);
 
+/// HMAC-SHA256 of the input (RFC 2104)
+pub fn hmac_sha256(key: &[u8], input: &[u8]) -> SHA256 {
+	let mut engine = HmacEngine::<sha256::Hash>::new(key);
+	engine.input(input);
+	SHA256::from_inner(Hmac::<sha256::Hash>::from_engine(engine).into_inner())
+}
+
+/// HMAC-SHA512 of the input (RFC 2104)
+pub fn hmac_sha512(key: &[u8], input: &[u8]) -> [u8; 64] {
+	let mut engine = HmacEngine::<sha512::Hash>::new(key);
+	engine.input(input);
+	Hmac::<sha512::Hash>::from_engine(engine).into_inner()
+}
+
+/// SHA3-256 of the input (FIPS 202)
+pub fn sha3_256(input: &[u8]) -> [u8; 32] {
+	let mut hasher = tiny_keccak::Sha3::v256();
+	hasher.update(input);
+	let mut output = [0u8; 32];
+	hasher.finalize(&mut output);
+	output
+}
+
+/// PBKDF2 key derivation with HMAC-SHA512 pseudorandom function (RFC 8018). Fills the whole output
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8]) {
+	// keyed engine is reused by every iteration
+	let keyed_engine = HmacEngine::<sha512::Hash>::new(password);
+	for (index, chunk) in output.chunks_mut(64).enumerate() {
+		let mut engine = keyed_engine.clone();
+		engine.input(salt);
+		engine.input(&(index as u32 + 1).to_be_bytes());
+		let mut block = Hmac::<sha512::Hash>::from_engine(engine).into_inner();
+		let mut result = block;
+		for _ in 1..iterations {
+			let mut engine = keyed_engine.clone();
+			engine.input(&block);
+			block = Hmac::<sha512::Hash>::from_engine(engine).into_inner();
+			for (result, byte) in result.iter_mut().zip(block.iter()) {
+				*result ^= byte;
+			}
+		}
+		chunk.copy_from_slice(&result[..chunk.len()]);
+	}
+}
+
 #[inline]
 pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
 	siphash24::Hash::hash_to_u64_with_keys(key0, key1, input)
@@ -88,9 +146,11 @@ pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
 
 #[cfg(test)]
 mod tests {
-	use super::{dhash160, ripemd160, sha1, sha256, siphash24, FromStr};
+	use super::{
+		dhash160, hmac_sha256, hmac_sha512, pbkdf2_hmac_sha512, ripemd160, sha1, sha256, sha3_256, siphash24, tagged_hash, FromStr,
+	};
 	use crate::{dhash256, FromInnerHex, HASH160, RIPEMD160, SHA1, SHA256, SHA256D};
-	use bitcoin_hashes::hex::FromHex;
+	use bitcoin_hashes::hex::{FromHex, ToHex};
 
 	#[test]
 	fn test_ripemd160() {
@@ -113,6 +173,14 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn test_tagged_hash() {
+		let expected = SHA256::from_str("dabc11914abcd8072900042a2681e52f8dba99ce82e224f97b5fdb7cd4b9c803").unwrap();
+		assert_eq!(tagged_hash(b"TapSighash", &[]), expected);
+		let expected = SHA256::from_str("ed1382037800c9dd938dd8854f1a8863bcdeb6705069b4b56a66ec22519d5829").unwrap();
+		assert_eq!(tagged_hash(b"TapLeaf", &[0]), expected);
+	}
+
 	#[test]
 	fn test_dhash160() {
 		let expected = HASH160::from_str("b6a9c8c230722b7c748331a8b450f05566dc7d0f").unwrap();
@@ -140,4 +208,35 @@ mod tests {
 		let result = siphash24(0x0706050403020100_u64, 0x0F0E0D0C0B0A0908_u64, &[0; 1]);
 		assert_eq!(result, expected);
 	}
+
+	#[test]
+	fn test_hmac() {
+		let expected = SHA256::from_str("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843").unwrap();
+		assert_eq!(hmac_sha256(b"Jefe", b"what do ya want for nothing?"), expected);
+		assert_eq!(
+			hmac_sha512(b"Jefe", b"what do ya want for nothing?").to_hex(),
+			"164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+		);
+	}
+
+	#[test]
+	fn test_sha3_256() {
+		assert_eq!(
+			sha3_256(b"abc").to_hex(),
+			"3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+		);
+	}
+
+	#[test]
+	fn test_pbkdf2_hmac_sha512() {
+		let mut output = [0u8; 64];
+		pbkdf2_hmac_sha512(b"password", b"salt", 1, &mut output);
+		assert_eq!(
+			output.to_hex(),
+			"867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce"
+		);
+		let mut output = [0u8; 32];
+		pbkdf2_hmac_sha512(b"password", b"salt", 2, &mut output);
+		assert_eq!(output.to_hex(), "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53c");
+	}
 }
@@ -1,15 +1,19 @@
 use crate::db_interface::DbInterface;
 use crate::ham_db::HamDb;
 use crate::overlay_db::OverlayDatabase;
-use bitcrypto::SHA256D;
-use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint, TransactionOutput};
+use bitcrypto::{sha256, SHA256, SHA256D};
+use chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint, TransactionOutput};
+use network::AssumeUtxo;
 use parking_lot::RwLock;
 use serialization::serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io;
 use storage::bytes::Bytes;
 use storage::{
-	BlockChain, BlockHeaderProvider, BlockHeight, BlockMeta, BlockOrigin, BlockProvider, BlockRef, CanonStore, Error, ForkChain, Forkable,
-	SideChainOrigin, Store, TransactionMeta, TransactionMetaProvider, TransactionOutputProvider, TransactionProvider,
+	BlockChain, BlockFilterProvider, BlockHeaderProvider, BlockHeight, BlockMeta, BlockOrigin, BlockProvider, BlockRef, CanonStore, Error,
+	ForkChain, Forkable, PruneProvider, ScriptIndexProvider, ScriptOutput, ScriptOutputSpend, SideChainOrigin, SnapshotBase,
+	SnapshotHasher, SnapshotReader, Store, TransactionMeta, TransactionMetaProvider, TransactionOutputProvider, TransactionProvider,
+	UtxoSnapshotProvider, MIN_BLOCKS_TO_KEEP,
 };
 
 const MAX_FORK_ROUTE_PRESET: usize = 2048;
@@ -26,6 +30,9 @@ where
 	T: DbInterface,
 {
 	best_block: RwLock<BlockHeight>,
+	script_index: bool,
+	prune_target: Option<u64>,
+	pruned_height: RwLock<Option<u32>>,
 	db: T,
 }
 
@@ -123,14 +130,209 @@ where
 	pub fn open(db: T) -> Result<BlockChainDatabase<T>, storage::Error> {
 		let best_block = db.best_block()?;
 		info!("Best block is: {:?}", best_block);
+		let script_index = db.is_script_indexed()?;
+		let pruned_height = db.fetch_pruned_height()?;
 		Ok(BlockChainDatabase {
 			db,
+			script_index,
+			prune_target: None,
+			pruned_height: RwLock::new(pruned_height),
 			best_block: RwLock::new(best_block),
 		})
 	}
 
+	/// Enables pruning mode. Fully spent transactions are deleted from the canon blocks, which are
+	/// at least `MIN_BLOCKS_TO_KEEP` blocks deep, until the total size of the remaining blocks fits into
+	/// the target size (in bytes). Pruned blocks could never be decanonized.
+	pub fn with_pruning(mut self, prune_target: u64) -> Self {
+		self.prune_target = Some(prune_target);
+		self
+	}
+
+	/// Enables script index. If the database already contains canon blocks, the index is rebuilt from them, which
+	/// is only possible when all transactions are stored (i.e. database is neither pruned nor loaded from UTXO snapshot).
+	/// Once enabled, index is maintained until the database is removed.
+	pub fn with_script_index(mut self) -> Result<Self, storage::Error> {
+		if self.script_index {
+			return Ok(self);
+		}
+
+		let best_block = self.best_block();
+		if best_block.number != 0 && (self.pruned_height.read().is_some() || self.db.fetch_snapshot_base()?.is_some()) {
+			return Err(Error::DatabaseError(
+				"Script index can not be built on a pruned database or database, loaded from UTXO snapshot".to_string(),
+			));
+		}
+
+		for number in 0..=best_block.number {
+			let block = self
+				.db
+				.block_hash(number)?
+				.map(|hash| self.db.fetch_block(&hash))
+				.transpose()?
+				.flatten()
+				.ok_or(Error::InconsistentData)?;
+			let spent_outputs = self.spent_outputs(&block)?;
+			self.index_block_scripts(&block, number, &spent_outputs)?;
+		}
+		self.db.set_script_indexed()?;
+		self.db.flush()?;
+		self.script_index = true;
+		Ok(self)
+	}
+
+	/// Builds compact filters of the canon blocks, which have been stored before filters were indexed. Filters
+	/// could only be built when all transactions are stored (i.e. database is neither pruned nor loaded from UTXO
+	/// snapshot), otherwise the filter chain is left incomplete.
+	pub fn with_block_filters(self) -> Result<Self, storage::Error> {
+		let best_block = self.best_block();
+		let mut first_missing = best_block.number + 1;
+		while first_missing != 0 {
+			let hash = self.db.block_hash(first_missing - 1)?.ok_or(Error::InconsistentData)?;
+			if self.db.fetch_block_filter_header(&hash)?.is_some() {
+				break;
+			}
+			first_missing -= 1;
+		}
+
+		if first_missing > best_block.number {
+			return Ok(self);
+		}
+		if self.pruned_height.read().is_some() || self.db.fetch_snapshot_base()?.is_some() {
+			warn!(target: "db", "Compact filters can not be built on a pruned database or database, loaded from UTXO snapshot");
+			return Ok(self);
+		}
+
+		info!(target: "db", "Building compact filters of blocks {}..={}", first_missing, best_block.number);
+		for number in first_missing..=best_block.number {
+			let block = self
+				.db
+				.block_hash(number)?
+				.map(|hash| self.db.fetch_block(&hash))
+				.transpose()?
+				.flatten()
+				.ok_or(Error::InconsistentData)?;
+			let spent_outputs = self.spent_outputs(&block)?;
+			self.index_block_filter(&block, &spent_outputs)?;
+		}
+		self.db.flush()?;
+		Ok(self)
+	}
+
+	/// Returns true if compact filters of all canon blocks are stored.
+	pub fn has_block_filters(&self) -> bool {
+		self.db.fetch_block_filter_header(&self.best_block().hash).unwrap().is_some()
+	}
+
+	/// Loads UTXO set snapshot into a database that contains nothing but genesis block. Blocks up to the snapshot
+	/// base are stored without transactions, so snapshot base could never be decanonized.
+	/// Snapshot must be verified with `storage::verify_utxo_snapshot` first: if the snapshot hash doesn't match
+	/// the expected one, the error is returned, but the database is left in inconsistent state.
+	pub fn load_utxo_snapshot<R: io::Read>(&self, read: R, assumeutxo: &AssumeUtxo) -> Result<SnapshotBase, storage::Error> {
+		let mut best_block = self.best_block.write();
+		if best_block.number != 0 {
+			return Err(Error::SnapshotError(
+				"UTXO snapshot can only be loaded into a new database".to_string(),
+			));
+		}
+
+		let mut reader = SnapshotReader::new(read)?;
+		if reader.metadata().base_hash != assumeutxo.block_hash || reader.metadata().base_height != assumeutxo.height {
+			return Err(Error::SnapshotError("snapshot base does not match the known snapshot".to_string()));
+		}
+
+		let mut base = best_block.clone();
+		while let Some(header) = reader.read_header()? {
+			if header.raw.previous_header_hash != base.hash {
+				return Err(Error::SnapshotError(format!(
+					"header {} is not connected to the previous header",
+					header.hash
+				)));
+			}
+
+			base = BlockHeight {
+				hash: header.hash,
+				number: base.number + 1,
+			};
+			self.db.insert_block(IndexedBlock::new(header, Vec::new()))?;
+			self.db.set_block_by_number(&base.hash, base.number)?;
+			let meta = BlockMeta {
+				number: base.number,
+				..BlockMeta::default()
+			};
+			self.db.update_block_meta(&base.hash, &meta)?;
+		}
+
+		let mut transactions = 0;
+		let mut outputs = 0;
+		let mut total_supply = 0;
+		let mut hasher = SnapshotHasher::default();
+		while let Some(transaction) = reader.read_transaction()? {
+			hasher.input(&transaction);
+			let (tx, tx_meta) = transaction.to_stored();
+			self.db.insert_snapshot_transaction(transactions, &tx, &tx_meta)?;
+			for output in &transaction.outputs {
+				if self.script_index && !output.output.script_pubkey.is_empty() {
+					let script_output = ScriptOutput {
+						outpoint: OutPoint {
+							hash: transaction.hash,
+							index: output.index,
+						},
+						height: transaction.height,
+						value: output.output.value,
+						spent: None,
+					};
+					self.db
+						.insert_script_output(&sha256(&output.output.script_pubkey), &script_output)?;
+				}
+				total_supply += output.output.value;
+			}
+			outputs += transaction.outputs.len() as u64;
+			transactions += 1;
+		}
+
+		if hasher.finish() != assumeutxo.txoutset_hash {
+			return Err(Error::SnapshotError("snapshot has changed since it was verified".to_string()));
+		}
+
+		let base_meta = BlockMeta {
+			number: base.number,
+			n_tx: 0,
+			n_chain_tx: assumeutxo.chain_transactions,
+			n_tx_with_utxos: outputs,
+			total_size: 0,
+			total_supply,
+		};
+		self.db.update_block_meta(&base.hash, &base_meta)?;
+
+		let snapshot_base = SnapshotBase {
+			block: base.clone(),
+			transactions,
+			validated: false,
+		};
+		self.db.set_snapshot_base(&snapshot_base)?;
+		self.db.set_best(base.number)?;
+		self.db.flush()?;
+
+		info!("Loaded UTXO snapshot with {} unspent outputs. Best block is: {:?}", outputs, base);
+		*best_block = base;
+		Ok(snapshot_base)
+	}
+
+	/// Marks headers chain up to the UTXO snapshot base as validated
+	pub fn set_snapshot_validated(&self) -> Result<(), storage::Error> {
+		let mut snapshot_base = self
+			.db
+			.fetch_snapshot_base()?
+			.ok_or_else(|| Error::SnapshotError("chain state has not been loaded from the snapshot".to_string()))?;
+		snapshot_base.validated = true;
+		self.db.set_snapshot_base(&snapshot_base)?;
+		self.db.flush()
+	}
+
 	pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, storage::Error> {
-		let overlay_db = BlockChainDatabase::open(OverlayDatabase::new(&self.db))?;
+		let mut overlay_db = BlockChainDatabase::open(OverlayDatabase::new(&self.db))?;
+		overlay_db.prune_target = self.prune_target;
 
 		for hash in side_chain.decanonized_route.into_iter().rev() {
 			let decanonized_hash = overlay_db.decanonize()?;
@@ -149,6 +351,7 @@ where
 	pub fn switch_to_fork(&self, fork: ForkChainDatabase<T>) -> Result<(), storage::Error> {
 		let mut best_block = self.best_block.write();
 		*best_block = fork.blockchain.best_block.read().clone();
+		*self.pruned_height.write() = *fork.blockchain.pruned_height.read();
 		fork.blockchain.db.flush()
 	}
 
@@ -239,13 +442,13 @@ where
 			// genesis block
 			best_block_meta.number = 0;
 			best_block_meta.n_chain_tx = new_best_block.transactions.len() as u64;
-			best_block_meta.total_size = new_best_block.weight() as u64;
+			best_block_meta.total_size = new_best_block.size_with_witness() as u64;
 			best_block_meta.n_tx_with_utxos = 0;
 		} else {
 			let current_db_block = self.db.fetch_block_meta(&best_height.hash)?.unwrap();
 			best_block_meta.number = current_db_block.number + 1;
 			best_block_meta.n_chain_tx = current_db_block.n_chain_tx + new_best_block.transactions.len() as u64;
-			best_block_meta.total_size = current_db_block.total_size + new_best_block.weight() as u64;
+			best_block_meta.total_size = current_db_block.total_size + new_best_block.size_with_witness() as u64;
 			best_block_meta.n_tx_with_utxos = current_db_block.n_tx_with_utxos;
 			total_supply += current_db_block.total_supply;
 		}
@@ -302,12 +505,35 @@ where
 		}
 
 		best_block_meta.total_supply = total_supply + new_supply;
+		let spent_outputs = self.spent_outputs(&new_best_block)?;
+		self.index_block_filter(&new_best_block, &spent_outputs)?;
+		if self.script_index {
+			self.index_block_scripts(&new_best_block, best_block_meta.number, &spent_outputs)?;
+		}
 		self.db.set_block_by_number(&block_hash, best_block_meta.number)?;
 		self.db.set_best(best_block_meta.number)?;
 		self.db.update_block_meta(&block_hash, &best_block_meta)?;
 		for (tx_hash, meta) in metas {
 			self.db.update_transaction_meta(&tx_hash, meta)?;
 		}
+		if self.prune_target.is_some() {
+			// transactions, fully spent by this block, are deleted once this block is pruned. Transactions with
+			// outputs that are spent by later blocks are listed by the block that spends the last output, so
+			// that every block within the retention window could still be decanonized
+			let spent_txs = new_best_block
+				.transactions
+				.iter()
+				.skip(1)
+				.flat_map(|tx| tx.raw.inputs.iter().map(|input| input.previous_output.hash))
+				.collect::<HashSet<_>>();
+			let mut fully_spent_txs = Vec::new();
+			for tx_hash in spent_txs {
+				if self.is_fully_spent(&tx_hash)? {
+					fully_spent_txs.push(tx_hash);
+				}
+			}
+			self.db.set_spent_transactions(best_block_meta.number, &fully_spent_txs)?;
+		}
 
 		debug!("Canonized block number: {:?}, hash: {:?}", best_block_meta.number, block_hash);
 
@@ -315,6 +541,193 @@ where
 			hash: *block_hash,
 			number: best_block_meta.number,
 		};
+		self.prune_blocks(&best_block_meta)
+	}
+
+	/// Deletes fully spent transactions of the canon blocks, which are out of the retention window
+	fn prune_blocks(&self, best_block_meta: &BlockMeta) -> Result<(), storage::Error> {
+		let prune_target = match self.prune_target {
+			Some(prune_target) => prune_target,
+			None => return Ok(()),
+		};
+
+		let mut pruned_height = self.pruned_height.write();
+		// genesis block is never pruned and blocks up to the UTXO snapshot base have no transactions at all
+		let mut number = match *pruned_height {
+			Some(pruned_height) => pruned_height + 1,
+			None => self
+				.db
+				.fetch_snapshot_base()?
+				.map_or(1, |snapshot_base| snapshot_base.block.number + 1),
+		};
+		while number + MIN_BLOCKS_TO_KEEP <= best_block_meta.number {
+			// total size of blocks number..=best is compared to the target
+			let previous_hash = self.db.block_hash(number - 1)?.ok_or(Error::InconsistentData)?;
+			let previous_meta = self.db.fetch_block_meta(&previous_hash)?.ok_or(Error::InconsistentData)?;
+			if best_block_meta.total_size - previous_meta.total_size <= prune_target {
+				break;
+			}
+
+			// listed transactions have been fully spent by this block, so it is the last block that needs them
+			for tx_hash in self.db.fetch_spent_transactions(number)?.unwrap_or_default() {
+				if self.is_fully_spent(&tx_hash)? {
+					self.db.delete_transaction(&tx_hash)?;
+				}
+			}
+			self.db.remove_spent_transactions(number)?;
+			self.db.set_pruned_height(number)?;
+			*pruned_height = Some(number);
+
+			debug!("Pruned block number: {}", number);
+			number += 1;
+		}
+
+		Ok(())
+	}
+
+	/// Returns true if all outputs of the stored transaction are spent. Stored meta is padded with unspent bits,
+	/// so transaction outputs are counted instead of using `TransactionMeta::is_fully_spent`.
+	fn is_fully_spent(&self, tx_hash: &SHA256D) -> Result<bool, storage::Error> {
+		match (self.db.fetch_transaction(tx_hash)?, self.db.fetch_transaction_meta(tx_hash)?) {
+			(Some(tx), Some(meta)) => Ok((0..tx.raw.outputs.len()).all(|index| meta.is_spent(index) == Some(true))),
+			_ => Ok(false),
+		}
+	}
+
+	/// Returns true if transaction has been loaded from the UTXO snapshot. All transactions of the blocks up to the
+	/// snapshot base are loaded from the snapshot, and stored snapshot transactions only have unspent outputs
+	fn is_snapshot_transaction(&self, tx_hash: &SHA256D) -> Result<bool, storage::Error> {
+		let snapshot_base = match self.db.fetch_snapshot_base()? {
+			Some(snapshot_base) => snapshot_base,
+			None => return Ok(false),
+		};
+		Ok(self
+			.db
+			.fetch_transaction_meta(tx_hash)?
+			.map_or(false, |meta| meta.height() <= snapshot_base.block.number))
+	}
+
+	/// Returns true if transactions of the canon block with given hash have been pruned
+	fn is_pruned_block(&self, block_hash: &SHA256D) -> bool {
+		match self.block_number(block_hash) {
+			Some(number) => self.is_pruned(number) && self.block_hash(number).as_ref() == Some(block_hash),
+			None => false,
+		}
+	}
+
+	/// Returns outputs, spent by the block transactions (in order of inputs)
+	fn spent_outputs(&self, block: &IndexedBlock) -> Result<Vec<TransactionOutput>, storage::Error> {
+		let block_txs: HashMap<SHA256D, &IndexedTransaction> = block.transactions.iter().map(|tx| (tx.hash, tx)).collect();
+		let mut spent_outputs = Vec::new();
+		for input in block.transactions.iter().skip(1).flat_map(|tx| tx.raw.inputs.iter()) {
+			let prevout = &input.previous_output;
+			let output = match block_txs.get(&prevout.hash) {
+				Some(tx) => tx.raw.outputs.get(prevout.index as usize).cloned(),
+				None => self
+					.db
+					.fetch_transaction(&prevout.hash)?
+					.and_then(|tx| tx.raw.outputs.get(prevout.index as usize).cloned()),
+			};
+			match output {
+				Some(output) => spent_outputs.push(output),
+				None => {
+					error!(target: "db", "Cannot find spent output during canonization: {}/{}", prevout.hash, prevout.index);
+					return Err(storage::Error::CannotCanonize);
+				}
+			}
+		}
+		Ok(spent_outputs)
+	}
+
+	/// Builds and stores basic compact filter of the block, chaining its header to the parent filter header
+	fn index_block_filter(&self, block: &IndexedBlock, spent_outputs: &[TransactionOutput]) -> Result<(), storage::Error> {
+		if self.db.fetch_block_filter_header(block.hash())?.is_some() {
+			return Ok(());
+		}
+
+		let parent_hash = &block.header.raw.previous_header_hash;
+		let previous_header = if *parent_hash == SHA256D::default() {
+			SHA256D::default()
+		} else {
+			match self.db.fetch_block_filter_header(parent_hash)? {
+				Some(header) => header,
+				None => {
+					// database has been created before filters were indexed
+					debug!(target: "db", "Filter of the parent block {} is not indexed", parent_hash);
+					return Ok(());
+				}
+			}
+		};
+
+		let filter = BlockFilter::basic(block, spent_outputs.iter().map(|output| &output.script_pubkey[..]));
+		let header = filter.header(&previous_header);
+		self.db.insert_block_filter(block.hash(), &filter, &header)
+	}
+
+	/// Adds block outputs to the script index and marks outputs, spent by the block, as spent
+	fn index_block_scripts(&self, block: &IndexedBlock, number: u32, spent_outputs: &[TransactionOutput]) -> Result<(), storage::Error> {
+		let mut spent_outputs = spent_outputs.iter();
+		for (tx_index, tx) in block.transactions.iter().enumerate() {
+			if tx_index != 0 {
+				for (input, spent_output) in tx.raw.inputs.iter().zip(&mut spent_outputs) {
+					// outputs with empty scripts are not indexed
+					if spent_output.script_pubkey.is_empty() {
+						continue;
+					}
+
+					let spent = ScriptOutputSpend {
+						tx_hash: tx.hash,
+						height: number,
+					};
+					self.db.update_script_output(&input.previous_output, Some(spent))?;
+				}
+			}
+
+			for (output_index, output) in tx.raw.outputs.iter().enumerate() {
+				if output.script_pubkey.is_empty() {
+					continue;
+				}
+
+				let script_output = ScriptOutput {
+					outpoint: OutPoint {
+						hash: tx.hash,
+						index: output_index as u32,
+					},
+					height: number,
+					value: output.value,
+					spent: None,
+				};
+				self.db.insert_script_output(&sha256(&output.script_pubkey), &script_output)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Reverts changes, made to the script index by the block
+	fn unindex_block_scripts(&self, block: &IndexedBlock) -> Result<(), storage::Error> {
+		let spent_outputs = self.spent_outputs(block).map_err(|_| storage::Error::CannotDecanonize)?;
+		let mut spent_outputs = spent_outputs.iter().rev();
+		for (tx_index, tx) in block.transactions.iter().enumerate().rev() {
+			for (output_index, output) in tx.raw.outputs.iter().enumerate().rev() {
+				if output.script_pubkey.is_empty() {
+					continue;
+				}
+
+				let outpoint = OutPoint {
+					hash: tx.hash,
+					index: output_index as u32,
+				};
+				self.db.remove_script_output(&sha256(&output.script_pubkey), &outpoint)?;
+			}
+
+			if tx_index != 0 {
+				for (input, spent_output) in tx.raw.inputs.iter().rev().zip(&mut spent_outputs) {
+					if !spent_output.script_pubkey.is_empty() {
+						self.db.update_script_output(&input.previous_output, None)?;
+					}
+				}
+			}
+		}
 		Ok(())
 	}
 
@@ -322,6 +735,11 @@ where
 		let mut best = self.best_block.write();
 		let best_height = &best.clone();
 
+		if self.is_pruned(best_height.number) {
+			error!("Cannot decanonize pruned block: {}", best_height.hash);
+			return Err(storage::Error::CannotDecanonize);
+		}
+
 		let best_block = match self.db.fetch_block(&best_height.hash)? {
 			Some(block) => block,
 			_ => {
@@ -342,6 +760,13 @@ where
 
 		self.db.set_best(new_best_block.number)?;
 
+		if self.script_index {
+			self.unindex_block_scripts(&best_block)?;
+		}
+		if self.prune_target.is_some() {
+			self.db.remove_spent_transactions(best_height.number)?;
+		}
+
 		let mut metas: HashMap<SHA256D, TransactionMeta> = HashMap::new();
 
 		for tx in best_block.transactions.iter().skip(1) {
@@ -430,7 +855,9 @@ where
 	}
 
 	fn block(&self, block_ref: BlockRef) -> Option<IndexedBlock> {
-		self.resolve_hash(block_ref).and_then(|hash| self.db.fetch_block(&hash).unwrap())
+		self.resolve_hash(block_ref)
+			.filter(|hash| !self.is_pruned_block(hash))
+			.and_then(|hash| self.db.fetch_block(&hash).unwrap())
 	}
 
 	fn block_transaction_hashes(&self, block_ref: BlockRef) -> Vec<SHA256D> {
@@ -440,10 +867,80 @@ where
 	}
 
 	fn block_transactions(&self, block_ref: BlockRef) -> Vec<IndexedTransaction> {
-		self.block_transaction_hashes(block_ref)
-			.into_iter()
-			.filter_map(|hash| self.transaction(&hash))
-			.collect()
+		match self.resolve_hash(block_ref) {
+			Some(hash) if !self.is_pruned_block(&hash) => self
+				.block_transaction_hashes(hash.into())
+				.into_iter()
+				.filter_map(|hash| self.transaction(&hash))
+				.collect(),
+			_ => Vec::new(),
+		}
+	}
+}
+
+impl<T> BlockFilterProvider for BlockChainDatabase<T>
+where
+	T: DbInterface,
+{
+	fn block_filter(&self, block_ref: BlockRef) -> Option<BlockFilter> {
+		self.resolve_hash(block_ref)
+			.and_then(|hash| self.db.fetch_block_filter(&hash).unwrap())
+	}
+
+	fn block_filter_header(&self, block_ref: BlockRef) -> Option<SHA256D> {
+		self.resolve_hash(block_ref)
+			.and_then(|hash| self.db.fetch_block_filter_header(&hash).unwrap())
+	}
+}
+
+impl<T> ScriptIndexProvider for BlockChainDatabase<T>
+where
+	T: DbInterface,
+{
+	fn is_script_index_enabled(&self) -> bool {
+		self.script_index
+	}
+
+	fn script_outputs(&self, script_hash: &SHA256) -> Result<Option<Vec<ScriptOutput>>, storage::Error> {
+		if !self.script_index {
+			return Ok(None);
+		}
+
+		self.db.fetch_script_outputs(script_hash).map(Some)
+	}
+}
+
+impl<T> UtxoSnapshotProvider for BlockChainDatabase<T>
+where
+	T: DbInterface,
+{
+	fn snapshot_base(&self) -> Option<SnapshotBase> {
+		self.db.fetch_snapshot_base().unwrap()
+	}
+
+	fn snapshot_transaction_hash(&self, index: u64) -> Option<SHA256D> {
+		self.db.fetch_snapshot_transaction_hash(index).unwrap()
+	}
+
+	fn snapshot_transaction(&self, hash: &SHA256D) -> Option<IndexedTransaction> {
+		if !self.is_snapshot_transaction(hash).unwrap() {
+			return None;
+		}
+
+		self.db.fetch_transaction(hash).unwrap()
+	}
+}
+
+impl<T> PruneProvider for BlockChainDatabase<T>
+where
+	T: DbInterface,
+{
+	fn is_pruning_enabled(&self) -> bool {
+		self.prune_target.is_some()
+	}
+
+	fn pruned_height(&self) -> Option<u32> {
+		*self.pruned_height.read()
 	}
 }
 
@@ -524,6 +1021,11 @@ where
 	}
 
 	fn transaction(&self, hash: &SHA256D) -> Option<IndexedTransaction> {
+		// snapshot transactions must never be served as real transactions
+		if self.is_snapshot_transaction(hash).unwrap() {
+			return None;
+		}
+
 		self.db.fetch_transaction(hash).unwrap()
 	}
 }
@@ -534,7 +1036,9 @@ where
 {
 	fn transaction_output(&self, prevout: &OutPoint, _transaction_index: usize) -> Option<TransactionOutput> {
 		// return previous transaction outputs only for canon chain transactions
-		self.transaction(&prevout.hash)
+		self.db
+			.fetch_transaction(&prevout.hash)
+			.unwrap()
 			.and_then(|tx| tx.raw.outputs.get(prevout.index as usize).cloned())
 	}
 
@@ -575,9 +1079,14 @@ mod tests {
 	use super::SHA256D;
 	use crate::blockchain_db::BlockChainDatabase;
 	use crate::ham_db::HamDb;
-	use chain::IndexedBlock;
-	use storage::{AsSubstore, BlockMeta, BlockProvider, BlockRef, TransactionMetaProvider};
-	use test_data::{block_h0, block_h1, block_h2};
+	use crate::ham_types::DbBlockFilterKey;
+	use bitcrypto::sha256;
+	use chain::{IndexedBlock, OutPoint};
+	use storage::{
+		AsSubstore, BlockFilterProvider, BlockMeta, BlockProvider, BlockRef, ScriptIndexProvider, ScriptOutputSpend,
+		TransactionMetaProvider,
+	};
+	use test_data::{block_builder, block_h0, block_h1, block_h2};
 
 	#[test]
 	fn test_persistence() {
@@ -615,7 +1124,7 @@ mod tests {
 				n_chain_tx: 1,
 				n_tx_with_utxos: 1,
 				total_supply: 5000000000,
-				total_size: 1140
+				total_size: 285
 			}
 		);
 
@@ -666,6 +1175,150 @@ mod tests {
 		assert_eq!(db.block_hash(2).unwrap(), b2.header.hash);
 	}
 
+	#[test]
+	fn test_block_filter_provider() {
+		let b0: IndexedBlock = block_h0().into();
+		let b1: IndexedBlock = block_h1().into();
+		let db = BlockChainDatabase::transient(&b0).unwrap();
+		db.insert(b1.clone()).unwrap();
+
+		assert!(db.block_filter(BlockRef::Hash(b1.hash().clone())).is_none());
+		db.canonize(b1.hash()).unwrap();
+
+		let filter0 = db.as_block_filter_provider().block_filter(BlockRef::Number(0)).unwrap();
+		let header0 = db.as_block_filter_provider().block_filter_header(BlockRef::Number(0)).unwrap();
+		assert_eq!(header0, filter0.header(&SHA256D::default()));
+
+		let filter1 = db.block_filter(BlockRef::Hash(b1.hash().clone())).unwrap();
+		let header1 = db.block_filter_header(BlockRef::Number(1)).unwrap();
+		assert_eq!(header1, filter1.header(&header0));
+
+		let coinbase_script = &b1.transactions[0].raw.outputs[0].script_pubkey;
+		assert!(filter1.match_any(b1.hash(), &[&coinbase_script[..]]));
+		assert!(db.block_filter(BlockRef::Number(2)).is_none());
+	}
+
+	#[test]
+	fn test_block_filters_rebuilt_on_existing_database() {
+		let b0: IndexedBlock = block_h0().into();
+		let b1: IndexedBlock = block_h1().into();
+		let b2: IndexedBlock = block_h2().into();
+		let db = BlockChainDatabase::init_test_chain(vec![b0, b1.clone(), b2.clone()]);
+		let header1 = db.block_filter_header(BlockRef::Number(1)).unwrap();
+		let header2 = db.block_filter_header(BlockRef::Number(2)).unwrap();
+		assert!(db.has_block_filters());
+
+		// database has been created before filters were indexed
+		db.db.forget(&DbBlockFilterKey::basic(b1.hash())).unwrap();
+		db.db.forget(&DbBlockFilterKey::basic(b2.hash())).unwrap();
+		assert!(!db.has_block_filters());
+
+		let db = db.with_block_filters().unwrap();
+		assert!(db.has_block_filters());
+		assert_eq!(db.block_filter_header(BlockRef::Number(1)), Some(header1));
+		assert_eq!(db.block_filter_header(BlockRef::Number(2)), Some(header2));
+	}
+
+	#[test]
+	fn test_script_index_provider() {
+		let b0: IndexedBlock = block_h0().into();
+		let b0_coinbase = b0.transactions[0].hash;
+		let b0_script = b0.transactions[0].raw.outputs[0].script_pubkey.clone();
+		#[rustfmt::skip]
+		let b1: IndexedBlock = block_builder()
+			.header().parent(b0.hash().clone()).build()
+			.transaction().coinbase().output().value(50).script_pubkey("51").build().build()
+			.transaction()
+				.input().hash(b0_coinbase).index(0).build()
+				.output().value(30).script_pubkey("52").build()
+				.output().value(20).script_pubkey("51").build()
+				.build()
+			.build()
+			.into();
+		let b1_spend = b1.transactions[1].hash;
+
+		let db = BlockChainDatabase::transient(&b0).unwrap();
+		assert!(!db.is_script_index_enabled());
+		assert!(db.script_outputs(&sha256(&b0_script)).unwrap().is_none());
+
+		let db = db.with_script_index().unwrap();
+		assert!(db.is_script_index_enabled());
+		let outputs = db.script_outputs(&sha256(&b0_script)).unwrap().unwrap();
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(
+			outputs[0].outpoint,
+			OutPoint {
+				hash: b0_coinbase,
+				index: 0
+			}
+		);
+		assert_eq!(outputs[0].height, 0);
+		assert_eq!(outputs[0].spent, None);
+
+		db.insert(b1.clone()).unwrap();
+		db.canonize(b1.hash()).unwrap();
+
+		let outputs = db.script_outputs(&sha256(&b0_script)).unwrap().unwrap();
+		assert_eq!(
+			outputs[0].spent,
+			Some(ScriptOutputSpend {
+				tx_hash: b1_spend,
+				height: 1,
+			})
+		);
+
+		let outputs = db.script_outputs(&sha256(&[0x51])).unwrap().unwrap();
+		assert_eq!(outputs.len(), 2);
+		assert_eq!(
+			outputs[0].outpoint,
+			OutPoint {
+				hash: b1.transactions[0].hash,
+				index: 0
+			}
+		);
+		assert_eq!(outputs[0].value, 50);
+		assert_eq!(outputs[1].outpoint, OutPoint { hash: b1_spend, index: 1 });
+		assert_eq!(outputs[1].value, 20);
+		assert_eq!(db.script_outputs(&sha256(&[0x52])).unwrap().unwrap().len(), 1);
+		assert!(db.script_outputs(&sha256(&[0x53])).unwrap().unwrap().is_empty());
+
+		db.decanonize().unwrap();
+		assert_eq!(db.script_outputs(&sha256(&b0_script)).unwrap().unwrap()[0].spent, None);
+		assert!(db.script_outputs(&sha256(&[0x51])).unwrap().unwrap().is_empty());
+		assert!(db.script_outputs(&sha256(&[0x52])).unwrap().unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_script_index_rebuilt_on_existing_database() {
+		let b0: IndexedBlock = block_h0().into();
+		let b0_coinbase = b0.transactions[0].hash;
+		let b0_script = b0.transactions[0].raw.outputs[0].script_pubkey.clone();
+		#[rustfmt::skip]
+		let b1: IndexedBlock = block_builder()
+			.header().parent(b0.hash().clone()).build()
+			.transaction().coinbase().output().value(50).script_pubkey("51").build().build()
+			.transaction()
+				.input().hash(b0_coinbase).index(0).build()
+				.output().value(30).script_pubkey("52").build()
+				.build()
+			.build()
+			.into();
+		let db = BlockChainDatabase::transient(&b0).unwrap();
+		db.insert(b1.clone()).unwrap();
+		db.canonize(b1.hash()).unwrap();
+
+		let db = db.with_script_index().unwrap();
+		assert_eq!(
+			db.script_outputs(&sha256(&b0_script)).unwrap().unwrap()[0].spent,
+			Some(ScriptOutputSpend {
+				tx_hash: b1.transactions[1].hash,
+				height: 1,
+			})
+		);
+		assert_eq!(db.script_outputs(&sha256(&[0x51])).unwrap().unwrap().len(), 1);
+		assert_eq!(db.script_outputs(&sha256(&[0x52])).unwrap().unwrap()[0].height, 1);
+	}
+
 	fn assert_best(db: &BlockChainDatabase<HamDb>, number: u32, hash: SHA256D) {
 		let best = db.best_block();
 		assert_eq!(best.number, number);
@@ -1,6 +1,6 @@
-use bitcrypto::SHA256D;
-use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
-use storage::{BlockHeight, BlockMeta, TransactionMeta};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint};
+use storage::{BlockHeight, BlockMeta, ScriptOutput, ScriptOutputSpend, SnapshotBase, TransactionMeta};
 
 pub trait DbInterface: Send + Sync {
 	fn insert_block(&self, block: IndexedBlock) -> Result<(), storage::Error>;
@@ -19,6 +19,44 @@ pub trait DbInterface: Send + Sync {
 
 	fn fetch_transaction_meta(&self, tx_hash: &SHA256D) -> Result<Option<TransactionMeta>, storage::Error>;
 
+	fn fetch_block_filter(&self, block_hash: &SHA256D) -> Result<Option<BlockFilter>, storage::Error>;
+
+	fn fetch_block_filter_header(&self, block_hash: &SHA256D) -> Result<Option<SHA256D>, storage::Error>;
+
+	fn insert_block_filter(&self, block_hash: &SHA256D, filter: &BlockFilter, header: &SHA256D) -> Result<(), storage::Error>;
+
+	fn is_script_indexed(&self) -> Result<bool, storage::Error>;
+
+	fn set_script_indexed(&self) -> Result<(), storage::Error>;
+
+	fn fetch_script_outputs(&self, script_hash: &SHA256) -> Result<Vec<ScriptOutput>, storage::Error>;
+
+	fn insert_script_output(&self, script_hash: &SHA256, output: &ScriptOutput) -> Result<(), storage::Error>;
+
+	fn remove_script_output(&self, script_hash: &SHA256, outpoint: &OutPoint) -> Result<(), storage::Error>;
+
+	fn update_script_output(&self, outpoint: &OutPoint, spent: Option<ScriptOutputSpend>) -> Result<(), storage::Error>;
+
+	fn fetch_snapshot_base(&self) -> Result<Option<SnapshotBase>, storage::Error>;
+
+	fn set_snapshot_base(&self, snapshot_base: &SnapshotBase) -> Result<(), storage::Error>;
+
+	fn fetch_snapshot_transaction_hash(&self, index: u64) -> Result<Option<SHA256D>, storage::Error>;
+
+	fn insert_snapshot_transaction(&self, index: u64, tx: &IndexedTransaction, tx_meta: &TransactionMeta) -> Result<(), storage::Error>;
+
+	fn fetch_pruned_height(&self) -> Result<Option<u32>, storage::Error>;
+
+	fn set_pruned_height(&self, number: u32) -> Result<(), storage::Error>;
+
+	fn fetch_spent_transactions(&self, number: u32) -> Result<Option<Vec<SHA256D>>, storage::Error>;
+
+	fn set_spent_transactions(&self, number: u32, tx_hashes: &[SHA256D]) -> Result<(), storage::Error>;
+
+	fn remove_spent_transactions(&self, number: u32) -> Result<(), storage::Error>;
+
+	fn delete_transaction(&self, tx_hash: &SHA256D) -> Result<(), storage::Error>;
+
 	fn update_block_meta(&self, block_hash: &SHA256D, block_meta: &BlockMeta) -> Result<(), storage::Error>;
 
 	fn update_transaction_meta(&self, tx_hash: &SHA256D, tx_meta: TransactionMeta) -> Result<(), storage::Error>;
@@ -1,15 +1,26 @@
 use crate::db_interface::DbInterface;
-use crate::ham_types::{DbBlock, DbInputKey, DbOutputKey, DbTransaction};
-use bitcrypto::SHA256D;
-use chain::{BlockHeader, IndexedBlock, IndexedBlockHeader, IndexedTransaction, Transaction};
+use crate::ham_types::{
+	DbBlock, DbBlockFilter, DbBlockFilterKey, DbInputKey, DbOutputKey, DbScriptOutput, DbSnapshotBase, DbSnapshotTransactionKey,
+	DbSpentTransactions, DbSpentTransactionsKey, DbTransaction,
+};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, BlockHeader, IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint, Transaction};
 use hammersbald::{persistent, transient, HammersbaldAPI};
 use parking_lot::RwLock;
 use serialization::{deserialize, serialize, Deserializable, Serializable};
 use std::sync::Arc;
-use storage::{BlockHeight, BlockMeta, Error, TransactionMeta};
+use storage::{BlockHeight, BlockMeta, Error, ScriptOutput, ScriptOutputSpend, SnapshotBase, TransactionMeta};
 
 pub type PRef = u64;
 const BEST_PREF: PRef = 0;
+/// Refers to nothing
+pub const NO_PREF: PRef = PRef::MAX;
+/// Key of the script index presence marker
+const SCRIPT_INDEX_KEY: &str = "script-index";
+/// Key of the UTXO snapshot base
+const SNAPSHOT_KEY: &str = "snapshot";
+/// Key of the number of the last pruned block
+const PRUNED_HEIGHT_KEY: &str = "pruned-height";
 
 pub struct HamDb {
 	hammersbald: Arc<RwLock<Box<dyn HammersbaldAPI>>>,
@@ -93,6 +104,21 @@ impl HamDb {
 	{
 		self.hammersbald.write().set(pref, &serialize(data)).map_err(from_ham)
 	}
+
+	/// Blocks up to the UTXO snapshot base are stored without transactions
+	fn is_header_only_block(&self, block_hash: &SHA256D) -> Result<bool, storage::Error> {
+		match (self.fetch_snapshot_base()?, self.fetch_block_meta(block_hash)?) {
+			(Some(snapshot_base), Some(meta)) => Ok(meta.number <= snapshot_base.block.number),
+			_ => Ok(false),
+		}
+	}
+
+	pub(crate) fn forget<K>(&self, key: &K) -> Result<(), storage::Error>
+	where
+		K: Serializable,
+	{
+		self.hammersbald.write().forget(&serialize(key)).map_err(from_ham)
+	}
 }
 
 impl DbInterface for HamDb {
@@ -109,17 +135,6 @@ impl DbInterface for HamDb {
 			let tx_pref = self.put(&tx.raw)?;
 			let db_tx = DbTransaction { tx_pref, meta_pref };
 			let db_tx_pref = self.put_keyed(&tx.hash, &db_tx)?;
-
-			for input in &tx.raw.inputs {
-				if !input.previous_output.is_null() {
-					let key = DbInputKey::from(input);
-					self.put_keyed(&key, &db_tx_pref)?;
-				}
-			}
-			for output in &tx.raw.outputs {
-				let key = DbOutputKey::from(output);
-				self.put_keyed(&key, &db_tx_pref)?;
-			}
 			tx_refs.push(db_tx_pref);
 		}
 		let mut meta = BlockMeta::default();
@@ -142,7 +157,10 @@ impl DbInterface for HamDb {
 		let header = self.fetch_block_header(block_hash)?;
 		if let Some(header) = header {
 			let txs = self.fetch_transactions(block_hash)?;
-			return Ok(txs.and_then(|transactions| Some(IndexedBlock::new(header, transactions))));
+			if txs.as_ref().map_or(false, Vec::is_empty) && self.is_header_only_block(block_hash)? {
+				return Ok(None);
+			}
+			return Ok(txs.map(|transactions| IndexedBlock::new(header, transactions)));
 		}
 		Ok(None)
 	}
@@ -198,7 +216,7 @@ impl DbInterface for HamDb {
 	fn fetch_transaction(&self, tx_hash: &SHA256D) -> Result<Option<IndexedTransaction>, storage::Error> {
 		if let Some((_, db_tx)) = self.get_by_key::<SHA256D, DbTransaction>(tx_hash)? {
 			if let Some(tx) = self.get_by_pref::<Transaction>(db_tx.tx_pref)? {
-				return Ok(Some(tx.into()));
+				return Ok(Some(IndexedTransaction::new(*tx_hash, tx)));
 			}
 		}
 		Ok(None)
@@ -211,6 +229,167 @@ impl DbInterface for HamDb {
 		Ok(None)
 	}
 
+	fn fetch_block_filter(&self, block_hash: &SHA256D) -> Result<Option<BlockFilter>, storage::Error> {
+		Ok(self
+			.get_by_key::<DbBlockFilterKey, DbBlockFilter>(&DbBlockFilterKey::basic(block_hash))?
+			.map(|(_, db_filter)| db_filter.filter))
+	}
+
+	fn fetch_block_filter_header(&self, block_hash: &SHA256D) -> Result<Option<SHA256D>, storage::Error> {
+		Ok(self
+			.get_by_key::<DbBlockFilterKey, DbBlockFilter>(&DbBlockFilterKey::basic(block_hash))?
+			.map(|(_, db_filter)| db_filter.header))
+	}
+
+	fn insert_block_filter(&self, block_hash: &SHA256D, filter: &BlockFilter, header: &SHA256D) -> Result<(), storage::Error> {
+		let key = DbBlockFilterKey::basic(block_hash);
+		// filter is fully defined by the block (and its ancestors) => never changes once stored
+		if self.get_by_key::<DbBlockFilterKey, DbBlockFilter>(&key)?.is_none() {
+			debug!("Inserting block filter: {} {}", block_hash, header);
+			let db_filter = DbBlockFilter {
+				header: *header,
+				filter: filter.clone(),
+			};
+			self.put_keyed(&key, &db_filter)?;
+		}
+		Ok(())
+	}
+
+	fn is_script_indexed(&self) -> Result<bool, storage::Error> {
+		Ok(self.get_by_key::<&str, bool>(&SCRIPT_INDEX_KEY)?.is_some())
+	}
+
+	fn set_script_indexed(&self) -> Result<(), storage::Error> {
+		if !self.is_script_indexed()? {
+			self.put_keyed(&SCRIPT_INDEX_KEY, &true)?;
+		}
+		Ok(())
+	}
+
+	fn fetch_script_outputs(&self, script_hash: &SHA256) -> Result<Vec<ScriptOutput>, storage::Error> {
+		let mut outputs = Vec::new();
+		let mut pref = match self.get_by_key::<DbOutputKey, PRef>(&DbOutputKey::from(script_hash))? {
+			Some((_, pref)) => pref,
+			None => return Ok(outputs),
+		};
+		while pref != NO_PREF {
+			let output = self.get_by_pref::<DbScriptOutput>(pref)?.ok_or(storage::Error::InconsistentData)?;
+			pref = output.previous;
+			outputs.push(output.into());
+		}
+		outputs.reverse();
+		Ok(outputs)
+	}
+
+	fn insert_script_output(&self, script_hash: &SHA256, output: &ScriptOutput) -> Result<(), storage::Error> {
+		let key = DbOutputKey::from(script_hash);
+		let last = self.get_by_key::<DbOutputKey, PRef>(&key)?;
+		let db_output = DbScriptOutput::new(output, last.as_ref().map(|(_, pref)| *pref).unwrap_or(NO_PREF));
+		let output_pref = self.put(&db_output)?;
+		match last {
+			Some((key_pref, _)) => self.set(key_pref, &output_pref)?,
+			None => self.put_keyed(&key, &output_pref)?,
+		};
+		self.put_keyed(&DbInputKey::from(&output.outpoint), &output_pref)?;
+		Ok(())
+	}
+
+	fn remove_script_output(&self, script_hash: &SHA256, outpoint: &OutPoint) -> Result<(), storage::Error> {
+		// outputs are removed in reverse order => removed output is always the last one
+		let (key_pref, output_pref) = self
+			.get_by_key::<DbOutputKey, PRef>(&DbOutputKey::from(script_hash))?
+			.ok_or(storage::Error::InconsistentData)?;
+		let db_output = self
+			.get_by_pref::<DbScriptOutput>(output_pref)?
+			.ok_or(storage::Error::InconsistentData)?;
+		if db_output.tx_hash != outpoint.hash || db_output.index != outpoint.index {
+			return Err(storage::Error::InconsistentData);
+		}
+
+		self.set(key_pref, &db_output.previous)?;
+		self.forget(&DbInputKey::from(outpoint))
+	}
+
+	fn update_script_output(&self, outpoint: &OutPoint, spent: Option<ScriptOutputSpend>) -> Result<(), storage::Error> {
+		let (_, output_pref) = self
+			.get_by_key::<DbInputKey, PRef>(&DbInputKey::from(outpoint))?
+			.ok_or(storage::Error::InconsistentData)?;
+		let mut db_output = self
+			.get_by_pref::<DbScriptOutput>(output_pref)?
+			.ok_or(storage::Error::InconsistentData)?;
+		db_output.set_spent(spent);
+		self.set(output_pref, &db_output)?;
+		Ok(())
+	}
+
+	fn fetch_snapshot_base(&self) -> Result<Option<SnapshotBase>, storage::Error> {
+		Ok(self
+			.get_by_key::<&str, DbSnapshotBase>(&SNAPSHOT_KEY)?
+			.map(|(_, snapshot_base)| snapshot_base.into()))
+	}
+
+	fn set_snapshot_base(&self, snapshot_base: &SnapshotBase) -> Result<(), storage::Error> {
+		let db_snapshot_base = DbSnapshotBase::from(snapshot_base);
+		match self.get_by_key::<&str, DbSnapshotBase>(&SNAPSHOT_KEY)? {
+			Some((pref, _)) => self.set(pref, &db_snapshot_base)?,
+			None => self.put_keyed(&SNAPSHOT_KEY, &db_snapshot_base)?,
+		};
+		Ok(())
+	}
+
+	fn fetch_snapshot_transaction_hash(&self, index: u64) -> Result<Option<SHA256D>, storage::Error> {
+		Ok(self
+			.get_by_key::<DbSnapshotTransactionKey, SHA256D>(&DbSnapshotTransactionKey { index })?
+			.map(|(_, hash)| hash))
+	}
+
+	fn insert_snapshot_transaction(&self, index: u64, tx: &IndexedTransaction, tx_meta: &TransactionMeta) -> Result<(), storage::Error> {
+		debug!("Inserting snapshot transaction: {} {:?}", tx.hash, tx.raw);
+		let meta_pref = self.put(tx_meta)?;
+		let tx_pref = self.put(&tx.raw)?;
+		self.put_keyed(&tx.hash, &DbTransaction { tx_pref, meta_pref })?;
+		self.put_keyed(&DbSnapshotTransactionKey { index }, &tx.hash)?;
+		Ok(())
+	}
+
+	fn fetch_pruned_height(&self) -> Result<Option<u32>, storage::Error> {
+		Ok(self.get_by_key::<&str, u32>(&PRUNED_HEIGHT_KEY)?.map(|(_, number)| number))
+	}
+
+	fn set_pruned_height(&self, number: u32) -> Result<(), storage::Error> {
+		match self.get_by_key::<&str, u32>(&PRUNED_HEIGHT_KEY)? {
+			Some((pref, _)) => self.set(pref, &number)?,
+			None => self.put_keyed(&PRUNED_HEIGHT_KEY, &number)?,
+		};
+		Ok(())
+	}
+
+	fn fetch_spent_transactions(&self, number: u32) -> Result<Option<Vec<SHA256D>>, storage::Error> {
+		Ok(self
+			.get_by_key::<DbSpentTransactionsKey, DbSpentTransactions>(&DbSpentTransactionsKey::new(number))?
+			.map(|(_, spent_txs)| spent_txs.tx_hashes))
+	}
+
+	fn set_spent_transactions(&self, number: u32, tx_hashes: &[SHA256D]) -> Result<(), storage::Error> {
+		// list size differs from block to block => it is never updated in-place
+		let key = DbSpentTransactionsKey::new(number);
+		self.forget(&key)?;
+		let spent_txs = DbSpentTransactions {
+			tx_hashes: tx_hashes.to_vec(),
+		};
+		self.put_keyed(&key, &spent_txs)?;
+		Ok(())
+	}
+
+	fn remove_spent_transactions(&self, number: u32) -> Result<(), storage::Error> {
+		self.forget(&DbSpentTransactionsKey::new(number))
+	}
+
+	fn delete_transaction(&self, tx_hash: &SHA256D) -> Result<(), storage::Error> {
+		debug!("Delete tx: {}", tx_hash);
+		self.forget(tx_hash)
+	}
+
 	fn update_block_meta(&self, block_hash: &SHA256D, block_meta: &BlockMeta) -> Result<(), storage::Error> {
 		debug!("Update block meta: {} {:?}", block_hash, block_meta);
 		if let Some((_, db_block)) = self.get_by_key::<SHA256D, DbBlock>(block_hash)? {
@@ -1,6 +1,9 @@
 use crate::ham_db::PRef;
-use bitcrypto::sha256;
-use chain::{TransactionInput, TransactionOutput};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, OutPoint, BASIC_FILTER_TYPE};
+use serialization::{Deserializable, Error as ReaderError, Reader, Serializable, Stream};
+use std::io;
+use storage::{BlockHeight, ScriptOutput, ScriptOutputSpend, SnapshotBase};
 
 // Keyed by block hash.
 #[derive(Debug, Clone, Serializable, Deserializable)]
@@ -17,30 +20,192 @@ pub struct DbTransaction {
 	pub meta_pref: PRef,
 }
 
+// Keyed by DbBlockFilterKey.
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbBlockFilter {
+	pub header: SHA256D,
+	pub filter: BlockFilter,
+}
+
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbBlockFilterKey {
+	pub filter_type: u8,
+	pub block_hash: SHA256D,
+}
+
+// Script index entry, keyed by DbInputKey. Has fixed size, so it could be updated in-place.
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbScriptOutput {
+	pub tx_hash: SHA256D,
+	pub index: u32,
+	pub height: u32,
+	pub value: u64,
+	pub is_spent: bool,
+	pub spent_tx_hash: SHA256D,
+	pub spent_height: u32,
+	pub previous: PRef, // previous DbScriptOutput with the same script or NO_PREF
+}
+
+// Keyed by SNAPSHOT_KEY. Has fixed size, so it could be updated in-place.
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbSnapshotBase {
+	pub hash: SHA256D,
+	pub number: u32,
+	pub transactions: u64,
+	pub validated: bool,
+}
+
+// Refers to the hash of the snapshot transaction with given index.
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbSnapshotTransactionKey {
+	pub index: u64,
+}
+
+// Hashes of transactions with outputs spent by the canon block. Keyed by DbSpentTransactionsKey.
+#[derive(Debug, Clone)]
+pub struct DbSpentTransactions {
+	pub tx_hashes: Vec<SHA256D>,
+}
+
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbSpentTransactionsKey {
+	prefix: u8,
+	number: u32,
+}
+
+// Refers to the output, spent by the input.
 #[derive(Debug, Clone, Serializable, Deserializable)]
 pub struct DbInputKey {
-	pub hash_prefix: Vec<u8>,
-	pub index: u16,
+	pub hash: SHA256D,
+	pub index: u32,
 }
 
+// Refers to the latest DbScriptOutput with given script. Hash is stored as a vector to avoid collision with block/tx keys.
 #[derive(Debug, Clone, Serializable, Deserializable)]
 pub struct DbOutputKey {
-	script_hash_prefix: Vec<u8>,
+	script_hash: Vec<u8>,
+}
+
+impl DbScriptOutput {
+	pub fn new(output: &ScriptOutput, previous: PRef) -> DbScriptOutput {
+		let spent = output.spent.clone().unwrap_or(ScriptOutputSpend {
+			tx_hash: SHA256D::default(),
+			height: 0,
+		});
+		DbScriptOutput {
+			tx_hash: output.outpoint.hash,
+			index: output.outpoint.index,
+			height: output.height,
+			value: output.value,
+			is_spent: output.spent.is_some(),
+			spent_tx_hash: spent.tx_hash,
+			spent_height: spent.height,
+			previous,
+		}
+	}
+
+	pub fn set_spent(&mut self, spent: Option<ScriptOutputSpend>) {
+		self.is_spent = spent.is_some();
+		let spent = spent.unwrap_or(ScriptOutputSpend {
+			tx_hash: SHA256D::default(),
+			height: 0,
+		});
+		self.spent_tx_hash = spent.tx_hash;
+		self.spent_height = spent.height;
+	}
+}
+
+impl From<DbScriptOutput> for ScriptOutput {
+	fn from(output: DbScriptOutput) -> Self {
+		ScriptOutput {
+			outpoint: OutPoint {
+				hash: output.tx_hash,
+				index: output.index,
+			},
+			height: output.height,
+			value: output.value,
+			spent: if output.is_spent {
+				Some(ScriptOutputSpend {
+					tx_hash: output.spent_tx_hash,
+					height: output.spent_height,
+				})
+			} else {
+				None
+			},
+		}
+	}
+}
+
+impl From<&SnapshotBase> for DbSnapshotBase {
+	fn from(snapshot_base: &SnapshotBase) -> Self {
+		DbSnapshotBase {
+			hash: snapshot_base.block.hash,
+			number: snapshot_base.block.number,
+			transactions: snapshot_base.transactions,
+			validated: snapshot_base.validated,
+		}
+	}
+}
+
+impl From<DbSnapshotBase> for SnapshotBase {
+	fn from(snapshot_base: DbSnapshotBase) -> Self {
+		SnapshotBase {
+			block: BlockHeight {
+				hash: snapshot_base.hash,
+				number: snapshot_base.number,
+			},
+			transactions: snapshot_base.transactions,
+			validated: snapshot_base.validated,
+		}
+	}
 }
 
 impl DbInputKey {
-	pub fn from(input: &TransactionInput) -> DbInputKey {
+	pub fn from(outpoint: &OutPoint) -> DbInputKey {
 		DbInputKey {
-			hash_prefix: input.previous_output.hash[0..7].to_vec(),
-			index: input.previous_output.index as u16,
+			hash: outpoint.hash,
+			index: outpoint.index,
 		}
 	}
 }
 
+impl Serializable for DbSpentTransactions {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append_list::<SHA256D, SHA256D>(&self.tx_hashes);
+	}
+}
+
+impl Deserializable for DbSpentTransactions {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError>
+	where
+		T: io::Read,
+	{
+		Ok(DbSpentTransactions {
+			tx_hashes: reader.read_list()?,
+		})
+	}
+}
+
+impl DbSpentTransactionsKey {
+	pub fn new(number: u32) -> DbSpentTransactionsKey {
+		// 5-bytes key doesn't collide with other keys
+		DbSpentTransactionsKey { prefix: 0, number }
+	}
+}
+
 impl DbOutputKey {
-	pub fn from(output: &TransactionOutput) -> DbOutputKey {
+	pub fn from(script_hash: &SHA256) -> DbOutputKey {
 		DbOutputKey {
-			script_hash_prefix: sha256(&output.script_pubkey[..]).to_vec(),
+			script_hash: script_hash.to_vec(),
+		}
+	}
+}
+
+impl DbBlockFilterKey {
+	pub fn basic(block_hash: &SHA256D) -> DbBlockFilterKey {
+		DbBlockFilterKey {
+			filter_type: BASIC_FILTER_TYPE,
+			block_hash: *block_hash,
 		}
 	}
 }
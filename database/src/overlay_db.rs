@@ -1,9 +1,16 @@
 use crate::db_interface::DbInterface;
-use bitcrypto::SHA256D;
-use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint};
 use parking_lot::RwLock;
-use std::collections::HashMap;
-use storage::{BlockHeight, BlockMeta, Error, TransactionMeta};
+use std::collections::{HashMap, HashSet};
+use storage::{BlockHeight, BlockMeta, Error, ScriptOutput, ScriptOutputSpend, SnapshotBase, TransactionMeta};
+
+/// Script index updates must be applied in the same order they have been made
+enum ScriptIndexUpdate {
+	Insert(SHA256, ScriptOutput),
+	Remove(SHA256, OutPoint),
+	Update(OutPoint, Option<ScriptOutputSpend>),
+}
 
 #[derive(Default)]
 struct MemoryDatabase {
@@ -13,6 +20,11 @@ struct MemoryDatabase {
 	txs: HashMap<SHA256D, IndexedTransaction>,
 	tx_metas: HashMap<SHA256D, TransactionMeta>,
 	block_numbers: HashMap<u32, SHA256D>,
+	block_filters: HashMap<SHA256D, (BlockFilter, SHA256D)>,
+	script_index_updates: Vec<ScriptIndexUpdate>,
+	spent_txs: HashMap<u32, Option<Vec<SHA256D>>>,
+	deleted_txs: HashSet<SHA256D>,
+	pruned_height: Option<u32>,
 	best_block: Option<u32>,
 }
 
@@ -119,6 +131,10 @@ impl<'a, T: DbInterface> DbInterface for OverlayDatabase<'a, T> {
 	}
 
 	fn fetch_transaction(&self, tx_hash: &SHA256D) -> Result<Option<IndexedTransaction>, storage::Error> {
+		if self.mem_db.read().deleted_txs.contains(tx_hash) {
+			return Ok(None);
+		}
+
 		Ok(self
 			.mem_db
 			.read()
@@ -129,6 +145,10 @@ impl<'a, T: DbInterface> DbInterface for OverlayDatabase<'a, T> {
 	}
 
 	fn fetch_transaction_meta(&self, tx_hash: &SHA256D) -> Result<Option<TransactionMeta>, storage::Error> {
+		if self.mem_db.read().deleted_txs.contains(tx_hash) {
+			return Ok(None);
+		}
+
 		Ok(self
 			.mem_db
 			.read()
@@ -138,6 +158,118 @@ impl<'a, T: DbInterface> DbInterface for OverlayDatabase<'a, T> {
 			.or(self.ham_db.fetch_transaction_meta(tx_hash)?))
 	}
 
+	fn fetch_block_filter(&self, block_hash: &SHA256D) -> Result<Option<BlockFilter>, storage::Error> {
+		Ok(self
+			.mem_db
+			.read()
+			.block_filters
+			.get(block_hash)
+			.map(|(filter, _)| filter.clone())
+			.or(self.ham_db.fetch_block_filter(block_hash)?))
+	}
+
+	fn fetch_block_filter_header(&self, block_hash: &SHA256D) -> Result<Option<SHA256D>, storage::Error> {
+		Ok(self
+			.mem_db
+			.read()
+			.block_filters
+			.get(block_hash)
+			.map(|(_, header)| *header)
+			.or(self.ham_db.fetch_block_filter_header(block_hash)?))
+	}
+
+	fn insert_block_filter(&self, block_hash: &SHA256D, filter: &BlockFilter, header: &SHA256D) -> Result<(), storage::Error> {
+		self.mem_db.write().block_filters.insert(*block_hash, (filter.clone(), *header));
+		Ok(())
+	}
+
+	fn is_script_indexed(&self) -> Result<bool, storage::Error> {
+		self.ham_db.is_script_indexed()
+	}
+
+	fn set_script_indexed(&self) -> Result<(), storage::Error> {
+		unreachable!()
+	}
+
+	fn fetch_script_outputs(&self, _script_hash: &SHA256) -> Result<Vec<ScriptOutput>, storage::Error> {
+		// script index is never read during reorganization
+		unreachable!()
+	}
+
+	fn insert_script_output(&self, script_hash: &SHA256, output: &ScriptOutput) -> Result<(), storage::Error> {
+		self.mem_db
+			.write()
+			.script_index_updates
+			.push(ScriptIndexUpdate::Insert(*script_hash, output.clone()));
+		Ok(())
+	}
+
+	fn remove_script_output(&self, script_hash: &SHA256, outpoint: &OutPoint) -> Result<(), storage::Error> {
+		self.mem_db
+			.write()
+			.script_index_updates
+			.push(ScriptIndexUpdate::Remove(*script_hash, outpoint.clone()));
+		Ok(())
+	}
+
+	fn update_script_output(&self, outpoint: &OutPoint, spent: Option<ScriptOutputSpend>) -> Result<(), storage::Error> {
+		self.mem_db
+			.write()
+			.script_index_updates
+			.push(ScriptIndexUpdate::Update(outpoint.clone(), spent));
+		Ok(())
+	}
+
+	fn fetch_snapshot_base(&self) -> Result<Option<SnapshotBase>, storage::Error> {
+		self.ham_db.fetch_snapshot_base()
+	}
+
+	fn set_snapshot_base(&self, _snapshot_base: &SnapshotBase) -> Result<(), storage::Error> {
+		unreachable!()
+	}
+
+	fn fetch_snapshot_transaction_hash(&self, index: u64) -> Result<Option<SHA256D>, storage::Error> {
+		self.ham_db.fetch_snapshot_transaction_hash(index)
+	}
+
+	fn insert_snapshot_transaction(&self, _index: u64, _tx: &IndexedTransaction, _tx_meta: &TransactionMeta) -> Result<(), storage::Error> {
+		// snapshot is never loaded during reorganization
+		unreachable!()
+	}
+
+	fn fetch_pruned_height(&self) -> Result<Option<u32>, storage::Error> {
+		Ok(self.mem_db.read().pruned_height.or(self.ham_db.fetch_pruned_height()?))
+	}
+
+	fn set_pruned_height(&self, number: u32) -> Result<(), storage::Error> {
+		self.mem_db.write().pruned_height = Some(number);
+		Ok(())
+	}
+
+	fn fetch_spent_transactions(&self, number: u32) -> Result<Option<Vec<SHA256D>>, storage::Error> {
+		match self.mem_db.read().spent_txs.get(&number) {
+			Some(tx_hashes) => Ok(tx_hashes.clone()),
+			None => self.ham_db.fetch_spent_transactions(number),
+		}
+	}
+
+	fn set_spent_transactions(&self, number: u32, tx_hashes: &[SHA256D]) -> Result<(), storage::Error> {
+		self.mem_db.write().spent_txs.insert(number, Some(tx_hashes.to_vec()));
+		Ok(())
+	}
+
+	fn remove_spent_transactions(&self, number: u32) -> Result<(), storage::Error> {
+		self.mem_db.write().spent_txs.insert(number, None);
+		Ok(())
+	}
+
+	fn delete_transaction(&self, tx_hash: &SHA256D) -> Result<(), storage::Error> {
+		let mut db = self.mem_db.write();
+		db.tx_metas.remove(tx_hash);
+		db.deleted_txs.insert(*tx_hash);
+		Ok(())
+	}
+
 	fn update_block_meta(&self, block_hash: &SHA256D, block_meta: &BlockMeta) -> Result<(), storage::Error> {
 		self.mem_db.write().block_metas.insert(*block_hash, block_meta.clone());
 		Ok(())
@@ -200,9 +332,31 @@ impl<'a, T: DbInterface> DbInterface for OverlayDatabase<'a, T> {
 		for (hash, tx_meta) in db.tx_metas.iter() {
 			self.ham_db.update_transaction_meta(hash, tx_meta.clone())?;
 		}
+		for (hash, (filter, header)) in db.block_filters.iter() {
+			self.ham_db.insert_block_filter(hash, filter, header)?;
+		}
+		for update in db.script_index_updates.iter() {
+			match update {
+				ScriptIndexUpdate::Insert(script_hash, output) => self.ham_db.insert_script_output(script_hash, output)?,
+				ScriptIndexUpdate::Remove(script_hash, outpoint) => self.ham_db.remove_script_output(script_hash, outpoint)?,
+				ScriptIndexUpdate::Update(outpoint, spent) => self.ham_db.update_script_output(outpoint, spent.clone())?,
+			}
+		}
 		for (number, hash) in db.block_numbers.iter() {
 			self.ham_db.set_block_by_number(hash, *number)?;
 		}
+		for (number, tx_hashes) in db.spent_txs.iter() {
+			match tx_hashes {
+				Some(tx_hashes) => self.ham_db.set_spent_transactions(*number, tx_hashes)?,
+				None => self.ham_db.remove_spent_transactions(*number)?,
+			}
+		}
+		for tx_hash in db.deleted_txs.iter() {
+			self.ham_db.delete_transaction(tx_hash)?;
+		}
+		if let Some(pruned_height) = db.pruned_height {
+			self.ham_db.set_pruned_height(pruned_height)?;
+		}
 		if db.best_block.is_some() {
 			self.ham_db.set_best(db.best_block.unwrap())?;
 		}
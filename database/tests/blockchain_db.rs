@@ -1,11 +1,19 @@
 extern crate chain;
 extern crate db;
+extern crate network;
 extern crate storage;
 extern crate test_data;
 
-use chain::IndexedBlock;
+use bitcrypto::sha256;
+use chain::{IndexedBlock, OutPoint};
 use db::blockchain_db::BlockChainDatabase;
-use storage::{BlockProvider, ForkChain, SideChainOrigin, Store};
+use network::{AssumeUtxo, ConsensusParams, Network};
+use std::io::Cursor;
+use storage::{
+	utxo_set_hash, verify_utxo_snapshot, write_utxo_snapshot, BlockHeaderProvider, BlockProvider, BlockRef, Error, ForkChain,
+	PruneProvider, ScriptIndexProvider, SideChainOrigin, Store, TransactionOutputProvider, TransactionProvider, UtxoSnapshotProvider,
+	MIN_BLOCKS_TO_KEEP,
+};
 
 #[test]
 fn insert_block() {
@@ -116,3 +124,408 @@ fn switch_to_simple_fork() {
 	assert_eq!(b2.hash(), &store.best_block().hash);
 	assert_eq!(store.best_block().hash, store.block_hash(2).unwrap());
 }
+
+#[test]
+fn utxo_snapshot_roundtrip() {
+	let b0: IndexedBlock = test_data::block_h0().into();
+	#[rustfmt::skip]
+	let b1: IndexedBlock = test_data::block_builder()
+		.header().parent(b0.hash().clone()).build()
+		.transaction().coinbase().output().value(50).script_pubkey("51").build().build()
+		.transaction()
+			.input().hash(b0.transactions[0].hash).index(0).build()
+			.output().value(30).script_pubkey("52").build()
+			.output().value(20).script_pubkey("51").build()
+			.build()
+		.build()
+		.into();
+	let b1_spend = b1.transactions[1].hash;
+	#[rustfmt::skip]
+	let b2: IndexedBlock = test_data::block_builder()
+		.header().parent(b1.hash().clone()).build()
+		.transaction().coinbase().output().value(50).script_pubkey("53").build().build()
+		.transaction()
+			.input().hash(b1_spend).index(0).build()
+			.output().value(30).script_pubkey("53").build()
+			.build()
+		.build()
+		.into();
+	let source = BlockChainDatabase::init_test_chain(vec![b0.clone(), b1.clone(), b2.clone()]);
+
+	let mut snapshot = Cursor::new(Vec::new());
+	let info = write_utxo_snapshot(&source, Network::Mainnet.magic(), &mut snapshot).unwrap();
+	let snapshot = snapshot.into_inner();
+	assert_eq!(info.metadata.base_hash, *b2.hash());
+	assert_eq!(info.metadata.base_height, 2);
+	// genesis coinbase is spent
+	assert_eq!(info.metadata.transactions, 4);
+	assert_eq!(info.outputs, 4);
+
+	// snapshot is unknown to the network
+	let mut consensus = ConsensusParams::new(Network::Mainnet);
+	match verify_utxo_snapshot(&snapshot[..], &consensus) {
+		Err(Error::SnapshotError(_)) => (),
+		result => panic!("unexpected verification result: {:?}", result),
+	}
+
+	let assumeutxo = AssumeUtxo {
+		height: 2,
+		block_hash: *b2.hash(),
+		txoutset_hash: info.hash,
+		transactions: 4,
+		chain_transactions: 5,
+	};
+	consensus.assumeutxo.push(assumeutxo.clone());
+	assert_eq!(verify_utxo_snapshot(&snapshot[..], &consensus).unwrap(), assumeutxo);
+
+	// snapshot could only be loaded into a new database
+	assert!(source.load_utxo_snapshot(&snapshot[..], &assumeutxo).is_err());
+
+	let store = BlockChainDatabase::transient(&b0).unwrap().with_script_index().unwrap();
+	let snapshot_base = store.load_utxo_snapshot(&snapshot[..], &assumeutxo).unwrap();
+	assert_eq!(snapshot_base, store.snapshot_base().unwrap());
+	assert!(!snapshot_base.validated);
+	assert_eq!(store.best_block(), source.best_block());
+	assert_eq!(store.block_hash(1).unwrap(), *b1.hash());
+	assert_eq!(store.block_header(BlockRef::Number(1)).unwrap(), b1.header);
+	assert!(store.block(BlockRef::Number(1)).is_none());
+	assert_eq!(store.block_meta(BlockRef::Number(2)).unwrap().n_chain_tx, 5);
+
+	let spent = OutPoint { hash: b1_spend, index: 0 };
+	let unspent = OutPoint { hash: b1_spend, index: 1 };
+	assert!(store.is_spent(&spent));
+	assert!(!store.is_spent(&unspent));
+	assert_eq!(store.transaction_output(&unspent, 0).unwrap().value, 20);
+	// snapshot transactions are not served as real transactions
+	assert!(store.transaction(&b1_spend).is_none());
+	assert_eq!(store.snapshot_transaction(&b1_spend).unwrap().raw.outputs[1].value, 20);
+	assert!(store.snapshot_transaction(&b2.transactions[0].hash).is_some());
+	assert_eq!(store.script_outputs(&sha256(&[0x51])).unwrap().unwrap().len(), 2);
+	assert!(store.script_outputs(&sha256(&[0x52])).unwrap().unwrap().is_empty());
+
+	// snapshot of the loaded chain state is the same
+	let mut snapshot2 = Cursor::new(Vec::new());
+	write_utxo_snapshot(&store, Network::Mainnet.magic(), &mut snapshot2).unwrap();
+	assert_eq!(snapshot2.into_inner(), snapshot);
+
+	store.set_snapshot_validated().unwrap();
+	assert!(store.snapshot_base().unwrap().validated);
+
+	#[rustfmt::skip]
+	let b3: IndexedBlock = test_data::block_builder()
+		.header().parent(b2.hash().clone()).build()
+		.transaction().coinbase().output().value(50).script_pubkey("53").build().build()
+		.transaction()
+			.input().hash(b1_spend).index(1).build()
+			.output().value(20).script_pubkey("54").build()
+			.build()
+		.build()
+		.into();
+	store.insert(b3.clone()).unwrap();
+	store.canonize(b3.hash()).unwrap();
+	assert!(store.is_spent(&unspent));
+	assert_eq!(store.script_outputs(&sha256(&[0x54])).unwrap().unwrap().len(), 1);
+	assert!(store.transaction(&b3.transactions[1].hash).is_some());
+	assert!(store.snapshot_transaction(&b3.transactions[1].hash).is_none());
+
+	assert_eq!(store.decanonize().unwrap(), *b3.hash());
+	assert!(!store.is_spent(&unspent));
+	// snapshot base could not be decanonized
+	match store.decanonize() {
+		Err(Error::CannotDecanonize) => (),
+		result => panic!("unexpected decanonization result: {:?}", result),
+	}
+}
+
+#[test]
+fn prune_blocks() {
+	let b0: IndexedBlock = test_data::block_h0().into();
+	#[rustfmt::skip]
+	let b1: IndexedBlock = test_data::block_builder()
+		.header().parent(b0.hash().clone()).build()
+		.transaction().coinbase().output().value(50).script_pubkey("51").build().build()
+		.transaction()
+			.input().hash(b0.transactions[0].hash).index(0).build()
+			.output().value(30).script_pubkey("52").build()
+			.build()
+		.build()
+		.into();
+	let b1_coinbase = b1.transactions[0].hash;
+	let b1_spend = b1.transactions[1].hash;
+	#[rustfmt::skip]
+	let b2: IndexedBlock = test_data::block_builder()
+		.header().parent(b1.hash().clone()).build()
+		.transaction().coinbase().output().value(50).script_pubkey("53").build().build()
+		.transaction()
+			.input().hash(b1_spend).index(0).build()
+			.output().value(30).script_pubkey("53").build()
+			.build()
+		.build()
+		.into();
+
+	let mut blocks = vec![b0.clone(), b1.clone(), b2.clone()];
+	for value in 3..=MIN_BLOCKS_TO_KEEP as u64 + 3 {
+		#[rustfmt::skip]
+		let block: IndexedBlock = test_data::block_builder()
+			.header().parent(blocks.last().unwrap().hash().clone()).build()
+			.transaction().coinbase().output().value(value).script_pubkey("54").build().build()
+			.build()
+			.into();
+		blocks.push(block);
+	}
+
+	let store = BlockChainDatabase::transient(&b0).unwrap().with_pruning(0);
+	let reference = BlockChainDatabase::transient(&b0).unwrap();
+	assert!(store.is_pruning_enabled());
+	assert!(!reference.is_pruning_enabled());
+	for block in blocks.iter().skip(1) {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+		reference.insert(block.clone()).unwrap();
+		reference.canonize(block.hash()).unwrap();
+	}
+
+	// best block is MIN_BLOCKS_TO_KEEP + 3 => blocks 1..=3 are pruned
+	assert_eq!(store.pruned_height(), Some(3));
+	assert_eq!(reference.pruned_height(), None);
+	assert!(store.is_pruned(3));
+	assert!(!store.is_pruned(4));
+	assert!(store.block(BlockRef::Number(2)).is_none());
+	assert!(store.block_transactions(BlockRef::Number(2)).is_empty());
+	assert!(store.block(BlockRef::Number(4)).is_some());
+	assert!(reference.block(BlockRef::Number(2)).is_some());
+
+	// headers, metadata and unspent transactions are kept
+	assert_eq!(store.block_header(BlockRef::Number(2)).unwrap(), b2.header);
+	assert_eq!(store.block_meta(BlockRef::Number(2)), reference.block_meta(BlockRef::Number(2)));
+	assert!(store.transaction(&b1_spend).is_none());
+	assert!(store.transaction(&b1_coinbase).is_some());
+	assert_eq!(
+		store
+			.transaction_output(
+				&OutPoint {
+					hash: b1_coinbase,
+					index: 0
+				},
+				0
+			)
+			.unwrap()
+			.value,
+		50
+	);
+
+	// fully spent transactions are not included in the snapshot anyway
+	let mut snapshot = Cursor::new(Vec::new());
+	write_utxo_snapshot(&store, Network::Mainnet.magic(), &mut snapshot).unwrap();
+	let mut reference_snapshot = Cursor::new(Vec::new());
+	write_utxo_snapshot(&reference, Network::Mainnet.magic(), &mut reference_snapshot).unwrap();
+	assert_eq!(snapshot.into_inner(), reference_snapshot.into_inner());
+
+	// blocks within the retention window could be decanonized
+	let best_hash = store.best_block().hash;
+	assert_eq!(store.decanonize().unwrap(), best_hash);
+	store.canonize(&best_hash).unwrap();
+	assert_eq!(store.pruned_height(), Some(3));
+}
+
+#[test]
+fn pruning_target_is_serialized_size() {
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let mut blocks = vec![b0.clone()];
+	for value in 1..=MIN_BLOCKS_TO_KEEP as u64 + 3 {
+		#[rustfmt::skip]
+		let block: IndexedBlock = test_data::block_builder()
+			.header().parent(blocks.last().unwrap().hash().clone()).build()
+			.transaction().coinbase().output().value(value).script_pubkey("51").build().build()
+			.build()
+			.into();
+		blocks.push(block);
+	}
+
+	// remaining blocks 2..=best fit exactly into the target => only block 1 is pruned
+	let prune_target = blocks.iter().skip(2).map(|block| block.size_with_witness() as u64).sum();
+	let store = BlockChainDatabase::transient(&b0).unwrap().with_pruning(prune_target);
+	for block in blocks.iter().skip(1) {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	assert_eq!(store.pruned_height(), Some(1));
+	let best_meta = store.block_meta(BlockRef::Number(store.best_block().number)).unwrap();
+	let total_size: u64 = blocks.iter().map(|block| block.size_with_witness() as u64).sum();
+	assert_eq!(best_meta.total_size, total_size);
+}
+
+#[test]
+fn pruned_blocks_could_not_be_decanonized() {
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let mut blocks = vec![b0.clone()];
+	for value in 1..=MIN_BLOCKS_TO_KEEP as u64 + 1 {
+		#[rustfmt::skip]
+		let block: IndexedBlock = test_data::block_builder()
+			.header().parent(blocks.last().unwrap().hash().clone()).build()
+			.transaction().coinbase().output().value(value).script_pubkey("51").build().build()
+			.build()
+			.into();
+		blocks.push(block);
+	}
+
+	let store = BlockChainDatabase::transient(&b0).unwrap().with_pruning(0);
+	for block in blocks.iter().skip(1) {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+	assert_eq!(store.pruned_height(), Some(1));
+
+	for _ in 0..MIN_BLOCKS_TO_KEEP {
+		store.decanonize().unwrap();
+	}
+	assert_eq!(store.best_block().number, 1);
+	match store.decanonize() {
+		Err(Error::CannotDecanonize) => (),
+		result => panic!("unexpected decanonization result: {:?}", result),
+	}
+}
+
+#[test]
+fn prune_blocks_keeps_transactions_spent_within_retention_window() {
+	let b0: IndexedBlock = test_data::block_h0().into();
+	#[rustfmt::skip]
+	let b1: IndexedBlock = test_data::block_builder()
+		.header().parent(b0.hash().clone()).build()
+		.transaction().coinbase()
+			.output().value(50).script_pubkey("51").build()
+			.output().value(25).script_pubkey("51").build()
+			.build()
+		.build()
+		.into();
+	let b1_coinbase = b1.transactions[0].hash;
+	#[rustfmt::skip]
+	let b2: IndexedBlock = test_data::block_builder()
+		.header().parent(b1.hash().clone()).build()
+		.transaction().coinbase().output().value(2).script_pubkey("52").build().build()
+		.transaction()
+			.input().hash(b1_coinbase).index(0).build()
+			.output().value(50).script_pubkey("52").build()
+			.build()
+		.build()
+		.into();
+
+	// the last output of b1 coinbase is spent by the block 5
+	let mut blocks = vec![b0.clone(), b1.clone(), b2.clone()];
+	for value in 3..=MIN_BLOCKS_TO_KEEP as u64 + 3 {
+		let builder = test_data::block_builder()
+			.header()
+			.parent(blocks.last().unwrap().hash().clone())
+			.build()
+			.transaction()
+			.coinbase()
+			.output()
+			.value(value)
+			.script_pubkey("53")
+			.build()
+			.build();
+		let block: IndexedBlock = if value == 5 {
+			#[rustfmt::skip]
+			let block = builder
+				.transaction()
+					.input().hash(b1_coinbase).index(1).build()
+					.output().value(25).script_pubkey("53").build()
+					.build()
+				.build();
+			block.into()
+		} else {
+			builder.build().into()
+		};
+		blocks.push(block);
+	}
+
+	let store = BlockChainDatabase::transient(&b0)
+		.unwrap()
+		.with_pruning(0)
+		.with_script_index()
+		.unwrap();
+	for block in blocks.iter().skip(1) {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	// blocks 1..=3 are pruned, but b1 coinbase is still required to decanonize block 5
+	assert_eq!(store.pruned_height(), Some(3));
+	assert!(store.transaction(&b1_coinbase).is_some());
+
+	// reorg across the block 5
+	for _ in 5..=MIN_BLOCKS_TO_KEEP + 3 {
+		store.decanonize().unwrap();
+	}
+	assert_eq!(store.best_block().number, 4);
+	assert!(!store.is_spent(&OutPoint {
+		hash: b1_coinbase,
+		index: 1
+	}));
+	assert!(store.script_outputs(&sha256(&[0x51])).unwrap().unwrap()[1].spent.is_none());
+
+	#[rustfmt::skip]
+	let fork_block: IndexedBlock = test_data::block_builder()
+		.header().parent(blocks[4].hash().clone()).build()
+		.transaction().coinbase().output().value(100).script_pubkey("54").build().build()
+		.transaction()
+			.input().hash(b1_coinbase).index(1).build()
+			.output().value(25).script_pubkey("54").build()
+			.build()
+		.build()
+		.into();
+	store.insert(fork_block.clone()).unwrap();
+	store.canonize(fork_block.hash()).unwrap();
+	assert!(store.is_spent(&OutPoint {
+		hash: b1_coinbase,
+		index: 1
+	}));
+
+	// b1 coinbase is deleted once the block, spending its last output, is pruned
+	let mut parent = fork_block.hash().clone();
+	for value in 6..=MIN_BLOCKS_TO_KEEP as u64 + 5 {
+		#[rustfmt::skip]
+		let block: IndexedBlock = test_data::block_builder()
+			.header().parent(parent).build()
+			.transaction().coinbase().output().value(value + 1000).script_pubkey("54").build().build()
+			.build()
+			.into();
+		parent = block.hash().clone();
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+	assert_eq!(store.pruned_height(), Some(5));
+	assert!(store.transaction(&b1_coinbase).is_none());
+}
+
+#[test]
+fn regtest_assumeutxo_snapshot() {
+	let genesis = Network::Regtest.genesis_block();
+	let mut blocks = vec![genesis.clone()];
+	blocks.extend(
+		test_data::build_n_regtest_blocks_from(110, 0, &genesis.header.raw)
+			.into_iter()
+			.map(IndexedBlock::from),
+	);
+	let store = BlockChainDatabase::init_test_chain(blocks);
+
+	// snapshot of the chain matches the regtest assumeutxo data
+	let mut snapshot = Cursor::new(Vec::new());
+	write_utxo_snapshot(&store, Network::Regtest.magic(), &mut snapshot).unwrap();
+	let snapshot = snapshot.into_inner();
+	let consensus = ConsensusParams::new(Network::Regtest);
+	let assumeutxo = verify_utxo_snapshot(&snapshot[..], &consensus).unwrap();
+	assert_eq!(assumeutxo, consensus.assumeutxo[0]);
+	assert_eq!(
+		assumeutxo.chain_transactions,
+		store.block_meta(BlockRef::Number(110)).unwrap().n_chain_tx
+	);
+	// UTXO set of the fully validated chain has the same hash
+	assert_eq!(utxo_set_hash(&store).unwrap(), assumeutxo.txoutset_hash);
+
+	let loaded = BlockChainDatabase::transient(&genesis).unwrap();
+	loaded.load_utxo_snapshot(&snapshot[..], &assumeutxo).unwrap();
+	assert_eq!(loaded.best_block(), store.best_block());
+	assert_eq!(utxo_set_hash(&loaded).unwrap(), assumeutxo.txoutset_hash);
+}
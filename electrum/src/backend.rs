@@ -0,0 +1,457 @@
+use crate::errors::{bad_request, daemon_error};
+use crate::types::{Balance, HeaderNotification, HistoryItem, Unspent};
+use bitcrypto::{sha256, ToHex, SHA256, SHA256D};
+use chain::{IndexedTransaction, OutPoint, Transaction};
+use jsonrpc_core::Error;
+use miner::MemoryPoolOrderingStrategy;
+use network::ConsensusParams;
+use ser::{deserialize, serialize, serialize_with_flags, Reader, SERIALIZE_TRANSACTION_WITNESS};
+use std::collections::{HashMap, HashSet};
+use storage::{ScriptOutput, SharedStore};
+use sync::{LocalNodeRef, MemoryPoolRef};
+
+/// Fee rate, reported when memory pool isn't large enough to estimate fee (satoshis per kilobyte)
+const MIN_FEE_RATE: u64 = 1_000;
+/// Number of satoshis in one coin
+const SATOSHIS_IN_COIN: f64 = 100_000_000.0;
+
+/// Answers Electrum queries using node storage and memory pool
+pub struct Backend {
+	consensus: ConsensusParams,
+	storage: SharedStore,
+	memory_pool: MemoryPoolRef,
+	local_sync_node: Option<LocalNodeRef>, // Might be None only in tests.
+}
+
+/// Memory pool transactions, related to the script hash
+#[derive(Default)]
+struct MempoolHistory {
+	/// Memory pool transactions, paying to or spending from the script
+	history: Vec<HistoryItem>,
+	/// Memory pool outputs, paying to the script
+	outputs: Vec<(OutPoint, u64)>,
+	/// Confirmed and memory pool outputs, paying to the script and spent by memory pool transactions
+	spent: HashMap<OutPoint, u64>,
+}
+
+impl Backend {
+	pub fn new(
+		consensus: ConsensusParams,
+		storage: SharedStore,
+		memory_pool: MemoryPoolRef,
+		local_sync_node: Option<LocalNodeRef>,
+	) -> Self {
+		Backend {
+			consensus,
+			storage,
+			memory_pool,
+			local_sync_node,
+		}
+	}
+
+	/// Returns header of the best block
+	pub fn best_header(&self) -> HeaderNotification {
+		let best_block = self.storage.best_block();
+		let header = self.storage.best_header();
+		HeaderNotification {
+			hex: serialize(&header.raw).to_hex(),
+			height: best_block.number,
+		}
+	}
+
+	/// Returns canon chain and memory pool transactions, paying to or spending from the script
+	pub fn script_hash_history(&self, script_hash: &SHA256) -> Result<Vec<HistoryItem>, Error> {
+		let outputs = self.script_outputs(script_hash)?;
+		let mut history = confirmed_history(&outputs);
+		history.extend(self.mempool_history(script_hash, &outputs).history);
+		Ok(history)
+	}
+
+	/// Returns status of the script hash, or None if script has no history
+	pub fn script_hash_status(&self, script_hash: &SHA256) -> Result<Option<String>, Error> {
+		let history = self.script_hash_history(script_hash)?;
+		if history.is_empty() {
+			return Ok(None);
+		}
+
+		let status = history
+			.iter()
+			.map(|item| format!("{}:{}:", item.tx_hash, item.height))
+			.collect::<String>();
+		Ok(Some(sha256(status.as_bytes()).to_hex()))
+	}
+
+	/// Returns confirmed and unconfirmed balance of the script
+	pub fn script_hash_balance(&self, script_hash: &SHA256) -> Result<Balance, Error> {
+		let outputs = self.script_outputs(script_hash)?;
+		let mempool = self.mempool_history(script_hash, &outputs);
+		let confirmed = outputs
+			.iter()
+			.filter(|output| output.spent.is_none())
+			.map(|output| output.value)
+			.sum();
+		let received: u64 = mempool.outputs.iter().map(|(_, value)| value).sum();
+		let spent: u64 = mempool.spent.values().sum();
+		Ok(Balance {
+			confirmed,
+			unconfirmed: received as i64 - spent as i64,
+		})
+	}
+
+	/// Returns outputs, paying to the script, that are not spent by canon chain and memory pool transactions
+	pub fn script_hash_unspent(&self, script_hash: &SHA256) -> Result<Vec<Unspent>, Error> {
+		let outputs = self.script_outputs(script_hash)?;
+		let MempoolHistory {
+			outputs: mempool_outputs,
+			spent,
+			..
+		} = self.mempool_history(script_hash, &outputs);
+		let confirmed = outputs
+			.into_iter()
+			.filter(|output| output.spent.is_none())
+			.map(|output| (output.outpoint, output.value, output.height));
+		let unconfirmed = mempool_outputs.into_iter().map(|(outpoint, value)| (outpoint, value, 0));
+		Ok(confirmed
+			.chain(unconfirmed)
+			.filter(|(outpoint, _, _)| !spent.contains_key(outpoint))
+			.map(|(outpoint, value, height)| Unspent {
+				tx_hash: outpoint.hash,
+				tx_pos: outpoint.index,
+				height,
+				value,
+			})
+			.collect())
+	}
+
+	/// Returns serialized canon chain or memory pool transaction
+	pub fn transaction(&self, hash: &SHA256D) -> Result<String, Error> {
+		let transaction = match self.storage.transaction(hash) {
+			Some(transaction) => transaction.raw,
+			None => self
+				.memory_pool
+				.read()
+				.read_by_hash(hash)
+				.cloned()
+				.ok_or_else(|| bad_request(format!("transaction {} is not found", hash)))?,
+		};
+
+		Ok(serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).to_hex())
+	}
+
+	/// Verifies and relays serialized transaction
+	pub fn broadcast(&self, raw_transaction: &[u8]) -> Result<SHA256D, Error> {
+		let transaction: Transaction = deserialize(Reader::new(raw_transaction)).map_err(|err| bad_request(format!("{:?}", err)))?;
+		let local_sync_node = self.local_sync_node.as_ref().ok_or_else(Error::internal_error)?;
+		local_sync_node
+			.accept_transaction(IndexedTransaction::from_raw(transaction))
+			.map_err(daemon_error)
+	}
+
+	/// Estimates fee rate (in coins per kilobyte), required for transaction to be included in one of given number of blocks
+	pub fn estimate_fee(&self, blocks: u32) -> f64 {
+		let target_size = self.consensus.max_block_size * blocks.max(1) as usize;
+		let memory_pool = self.memory_pool.read();
+		let mut size = 0;
+		let mut fee_rate = MIN_FEE_RATE;
+		for entry in memory_pool.iter(MemoryPoolOrderingStrategy::ByTransactionScore) {
+			size += entry.size;
+			if size >= target_size {
+				fee_rate = fee_rate.max(entry.miner_fee * 1_000 / entry.size as u64);
+				break;
+			}
+		}
+
+		fee_rate as f64 / SATOSHIS_IN_COIN
+	}
+
+	/// Returns minimal fee rate (in coins per kilobyte) of relayed transactions
+	pub fn relay_fee(&self) -> f64 {
+		MIN_FEE_RATE as f64 / SATOSHIS_IN_COIN
+	}
+
+	fn script_outputs(&self, script_hash: &SHA256) -> Result<Vec<ScriptOutput>, Error> {
+		self.storage
+			.script_outputs(script_hash)
+			.map_err(|err| daemon_error(err.to_string()))?
+			.ok_or_else(|| daemon_error("address index is not enabled"))
+	}
+
+	/// Finds memory pool transactions, paying to the script or spending given (or memory pool) outputs of the script
+	fn mempool_history(&self, script_hash: &SHA256, outputs: &[ScriptOutput]) -> MempoolHistory {
+		let mut known_outputs: HashMap<OutPoint, u64> = outputs
+			.iter()
+			.filter(|output| output.spent.is_none())
+			.map(|output| (output.outpoint.clone(), output.value))
+			.collect();
+
+		let memory_pool = self.memory_pool.read();
+		let mut mempool = MempoolHistory::default();
+		let mut unconfirmed = HashSet::new();
+		// parent transactions always enter the pool before their children
+		for entry in memory_pool.iter(MemoryPoolOrderingStrategy::ByTimestamp) {
+			let mut is_related = false;
+			for input in &entry.transaction.inputs {
+				if let Some(value) = known_outputs.remove(&input.previous_output) {
+					mempool.spent.insert(input.previous_output.clone(), value);
+					is_related = true;
+				}
+			}
+
+			for (index, output) in entry.transaction.outputs.iter().enumerate() {
+				if output.script_pubkey.is_empty() || sha256(&output.script_pubkey) != *script_hash {
+					continue;
+				}
+
+				let outpoint = OutPoint {
+					hash: entry.hash,
+					index: index as u32,
+				};
+				known_outputs.insert(outpoint.clone(), output.value);
+				mempool.outputs.push((outpoint, output.value));
+				is_related = true;
+			}
+
+			if is_related {
+				let has_unconfirmed_inputs = entry
+					.transaction
+					.inputs
+					.iter()
+					.any(|input| memory_pool.contains(&input.previous_output.hash));
+				if has_unconfirmed_inputs {
+					unconfirmed.insert(entry.hash);
+				}
+
+				mempool.history.push(HistoryItem {
+					tx_hash: entry.hash,
+					height: 0,
+					fee: Some(entry.miner_fee),
+				});
+			}
+		}
+
+		// confirmed parents go first, then transactions with unconfirmed parents
+		for item in &mut mempool.history {
+			if unconfirmed.contains(&item.tx_hash) {
+				item.height = -1;
+			}
+		}
+		mempool.history.sort_by_key(|item| -item.height);
+		mempool
+	}
+}
+
+/// Returns canon chain transactions, paying to or spending given outputs, ordered by height
+fn confirmed_history(outputs: &[ScriptOutput]) -> Vec<HistoryItem> {
+	let mut transactions = Vec::new();
+	for output in outputs {
+		transactions.push((output.height, output.outpoint.hash));
+		if let Some(ref spent) = output.spent {
+			transactions.push((spent.height, spent.tx_hash));
+		}
+	}
+
+	// stable sort keeps the order of transactions within the same block
+	transactions.sort_by_key(|&(height, _)| height);
+	let mut seen = HashSet::new();
+	transactions
+		.into_iter()
+		.filter(|&(_, hash)| seen.insert(hash))
+		.map(|(height, tx_hash)| HistoryItem {
+			tx_hash,
+			height: height.into(),
+			fee: None,
+		})
+		.collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+	extern crate test_data;
+
+	use super::Backend;
+	use crate::types::{Balance, HistoryItem, Unspent};
+	use bitcrypto::{sha256, ToHex, SHA256, SHA256D};
+	use chain::{IndexedBlock, IndexedTransaction};
+	use db::BlockChainDatabase;
+	use miner::{FeeCalculator, MemoryPool};
+	use network::{ConsensusParams, Network};
+	use parking_lot::RwLock;
+	use std::sync::Arc;
+	use storage::SharedStore;
+	use sync::MemoryPoolRef;
+
+	/// P2PKH script of 1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa
+	pub const SCRIPT: &str = "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac";
+	/// Electrum script hash of the `SCRIPT`
+	pub const SCRIPT_HASH: &str = "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161";
+
+	pub struct TestChain {
+		pub storage: SharedStore,
+		pub memory_pool: MemoryPoolRef,
+		pub backend: Backend,
+		pub coinbase: SHA256D,
+		pub spend: SHA256D,
+		pub receive: SHA256D,
+	}
+
+	/// Builds chain with two outputs, paying to the `SCRIPT`, and memory pool with two transactions:
+	/// one spending the first output and another paying to the `SCRIPT` from the unconfirmed output
+	pub fn prepare_chain() -> TestChain {
+		let b0: IndexedBlock = test_data::genesis().into();
+		#[rustfmt::skip]
+		let b1: IndexedBlock = test_data::block_builder()
+			.header().parent(*b0.hash()).build()
+			.transaction().coinbase()
+				.output().value(5000).script_pubkey(SCRIPT).build()
+				.output().value(3000).script_pubkey(SCRIPT).build()
+				.build()
+			.build()
+			.into();
+		let coinbase = b1.transactions[0].hash;
+
+		let storage: SharedStore = Arc::new(BlockChainDatabase::transient(&b0).unwrap().with_script_index().unwrap());
+		storage.insert(b1.clone()).unwrap();
+		storage.canonize(b1.hash()).unwrap();
+
+		let spend: IndexedTransaction = test_data::TransactionBuilder::with_input(&b1.transactions[0].raw, 0)
+			.add_output(4000)
+			.into();
+		let mut receive = test_data::TransactionBuilder::with_input(&spend.raw, 0)
+			.add_output(3500)
+			.transaction;
+		receive.outputs[0].script_pubkey = SCRIPT.into();
+		let receive = IndexedTransaction::from_raw(receive);
+
+		let mut memory_pool = MemoryPool::new();
+		memory_pool.insert_verified(spend.clone(), &FeeCalculator(storage.as_transaction_output_provider()));
+		memory_pool.insert_verified(receive.clone(), &FeeCalculator(storage.as_transaction_output_provider()));
+
+		let memory_pool: MemoryPoolRef = Arc::new(RwLock::new(memory_pool));
+		let backend = Backend::new(ConsensusParams::new(Network::Unitest), storage.clone(), memory_pool.clone(), None);
+		TestChain {
+			storage,
+			memory_pool,
+			backend,
+			coinbase,
+			spend: spend.hash,
+			receive: receive.hash,
+		}
+	}
+
+	fn script_hash() -> SHA256 {
+		sha256(&SCRIPT.parse::<primitives::bytes::Bytes>().unwrap())
+	}
+
+	#[test]
+	fn script_hash_history() {
+		let chain = prepare_chain();
+		assert_eq!(
+			chain.backend.script_hash_history(&script_hash()).unwrap(),
+			vec![
+				HistoryItem {
+					tx_hash: chain.coinbase,
+					height: 1,
+					fee: None,
+				},
+				HistoryItem {
+					tx_hash: chain.spend,
+					height: 0,
+					fee: Some(1000),
+				},
+				HistoryItem {
+					tx_hash: chain.receive,
+					height: -1,
+					fee: Some(500),
+				},
+			]
+		);
+		assert!(chain.backend.script_hash_history(&sha256(&[0x51])).unwrap().is_empty());
+	}
+
+	#[test]
+	fn script_hash_status() {
+		let chain = prepare_chain();
+		let status = format!("{}:1:{}:0:{}:-1:", chain.coinbase, chain.spend, chain.receive);
+		assert_eq!(
+			chain.backend.script_hash_status(&script_hash()).unwrap(),
+			Some(sha256(status.as_bytes()).to_hex())
+		);
+		assert_eq!(chain.backend.script_hash_status(&sha256(&[0x51])).unwrap(), None);
+	}
+
+	#[test]
+	fn script_hash_balance() {
+		let chain = prepare_chain();
+		assert_eq!(
+			chain.backend.script_hash_balance(&script_hash()).unwrap(),
+			Balance {
+				confirmed: 8000,
+				unconfirmed: -1500,
+			}
+		);
+	}
+
+	#[test]
+	fn script_hash_unspent() {
+		let chain = prepare_chain();
+		assert_eq!(
+			chain.backend.script_hash_unspent(&script_hash()).unwrap(),
+			vec![
+				Unspent {
+					tx_hash: chain.coinbase,
+					tx_pos: 1,
+					height: 1,
+					value: 3000,
+				},
+				Unspent {
+					tx_hash: chain.receive,
+					tx_pos: 0,
+					height: 0,
+					value: 3500,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn script_index_disabled() {
+		let storage: SharedStore = Arc::new(BlockChainDatabase::transient(&test_data::genesis().into()).unwrap());
+		let backend = Backend::new(
+			ConsensusParams::new(Network::Unitest),
+			storage,
+			Arc::new(RwLock::new(MemoryPool::new())),
+			None,
+		);
+		assert!(backend.script_hash_history(&script_hash()).is_err());
+		assert!(backend.script_hash_balance(&script_hash()).is_err());
+		assert!(backend.script_hash_unspent(&script_hash()).is_err());
+	}
+
+	#[test]
+	fn transaction() {
+		let chain = prepare_chain();
+		let coinbase = chain.storage.transaction(&chain.coinbase).unwrap();
+		assert_eq!(
+			chain.backend.transaction(&chain.coinbase).unwrap(),
+			ser::serialize(&coinbase.raw).to_hex()
+		);
+		assert!(chain.backend.transaction(&chain.spend).is_ok());
+		assert!(chain.backend.transaction(&SHA256D::default()).is_err());
+	}
+
+	#[test]
+	fn estimate_fee() {
+		let chain = prepare_chain();
+		// memory pool is nearly empty => minimal fee rate is enough
+		assert_eq!(chain.backend.estimate_fee(1), 0.00001);
+		assert_eq!(chain.backend.relay_fee(), 0.00001);
+	}
+
+	#[test]
+	fn best_header() {
+		let chain = prepare_chain();
+		let header = chain.backend.best_header();
+		assert_eq!(header.height, 1);
+		assert_eq!(header.hex, ser::serialize(&chain.storage.best_header().raw).to_hex());
+	}
+}
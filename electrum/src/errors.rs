@@ -0,0 +1,26 @@
+//! Electrum error codes and error objects
+
+mod codes {
+	pub const BAD_REQUEST: i64 = 1;
+	pub const DAEMON_ERROR: i64 = 2;
+}
+
+use jsonrpc_core::{Error, ErrorCode};
+
+/// Request is well-formed, but cannot be fulfilled
+pub fn bad_request<T: Into<String>>(message: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::BAD_REQUEST),
+		message: message.into(),
+		data: None,
+	}
+}
+
+/// Node has failed to process the request
+pub fn daemon_error<T: Into<String>>(message: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::DAEMON_ERROR),
+		message: message.into(),
+		data: None,
+	}
+}
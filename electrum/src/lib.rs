@@ -0,0 +1,29 @@
+//! Electrum protocol server
+//!
+//! https://electrumx-spesmilo.readthedocs.io/en/latest/protocol.html
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+extern crate bitcrypto;
+extern crate chain;
+extern crate jsonrpc_core;
+extern crate miner;
+extern crate network;
+extern crate parking_lot;
+extern crate primitives;
+extern crate serde;
+extern crate serde_json;
+extern crate serialization as ser;
+extern crate storage;
+extern crate sync;
+
+mod backend;
+mod errors;
+mod notifier;
+mod server;
+mod session;
+mod types;
+
+pub use crate::server::{new_tcp, Dependencies, Server, TcpConfiguration};
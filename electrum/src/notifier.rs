@@ -0,0 +1,46 @@
+use bitcrypto::SHA256D;
+use std::sync::atomic::{AtomicBool, Ordering};
+use sync::SyncListener;
+use tokio::sync::broadcast;
+
+/// Event, which could change the state of the Electrum subscriptions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+	/// New best block is inserted into storage
+	BestBlock,
+	/// Transactions are inserted to or evicted from the memory pool
+	MemoryPool,
+}
+
+/// Forwards new best blocks and memory pool changes to the Electrum sessions
+pub struct Notifier {
+	sender: broadcast::Sender<Event>,
+	is_synchronizing: AtomicBool,
+}
+
+impl Notifier {
+	pub fn new(sender: broadcast::Sender<Event>) -> Self {
+		Notifier {
+			sender,
+			is_synchronizing: AtomicBool::default(),
+		}
+	}
+}
+
+impl SyncListener for Notifier {
+	fn synchronization_state_switched(&self, is_synchronizing: bool) {
+		self.is_synchronizing.store(is_synchronizing, Ordering::SeqCst);
+	}
+
+	fn best_storage_block_inserted(&self, _block_hash: &SHA256D) {
+		// clients aren't interested in every block while node is synchronizing
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			// sending only fails when there are no connected clients
+			let _ = self.sender.send(Event::BestBlock);
+		}
+	}
+
+	fn memory_pool_changed(&self) {
+		let _ = self.sender.send(Event::MemoryPool);
+	}
+}
@@ -0,0 +1,186 @@
+use crate::backend::Backend;
+use crate::notifier::{Event, Notifier};
+use crate::session::Session;
+use network::ConsensusParams;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task;
+
+/// Number of events, buffered for every session
+const NOTIFICATIONS_BUFFER_SIZE: usize = 16;
+/// Maximal length of the single request line. Client is disconnected if it sends longer line
+const MAX_REQUEST_LENGTH: usize = 1_000_000;
+
+pub struct Dependencies {
+	pub consensus: ConsensusParams,
+	pub local_sync_node: sync::LocalNodeRef,
+	pub storage: storage::SharedStore,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct TcpConfiguration {
+	pub enabled: bool,
+	pub interface: String,
+	pub port: u16,
+}
+
+impl TcpConfiguration {
+	pub fn with_port(port: u16) -> Self {
+		TcpConfiguration {
+			enabled: false,
+			interface: "127.0.0.1".into(),
+			port,
+		}
+	}
+}
+
+/// Electrum server, accepting client connections
+pub struct Server {
+	listener: TcpListener,
+	backend: Arc<Backend>,
+	notifications: broadcast::Sender<Event>,
+}
+
+/// Binds Electrum server to the configured address. Must be called from within the Tokio runtime.
+pub async fn new_tcp(conf: TcpConfiguration, deps: Dependencies) -> Result<Option<Server>, String> {
+	if !conf.enabled {
+		return Ok(None);
+	}
+
+	let url = format!("{}:{}", conf.interface, conf.port);
+	let addr: SocketAddr = url
+		.parse()
+		.map_err(|_| format!("Invalid Electrum listen host/port given: {}", url))?;
+	let listener = match TcpListener::bind(addr).await {
+		Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
+			return Err(format!("Electrum address {} is already in use, make sure that another instance of an Electrum server is not running or change the address using the --electrum-port and --electrum-interface options.", url));
+		}
+		Err(err) => return Err(format!("Electrum error: {:?}", err)),
+		Ok(listener) => listener,
+	};
+
+	let (notifications, _) = broadcast::channel(NOTIFICATIONS_BUFFER_SIZE);
+	deps.local_sync_node
+		.install_sync_listener(Box::new(Notifier::new(notifications.clone())));
+
+	let memory_pool = deps.local_sync_node.memory_pool();
+	let backend = Backend::new(deps.consensus, deps.storage, memory_pool, Some(deps.local_sync_node));
+	Ok(Some(Server {
+		listener,
+		backend: Arc::new(backend),
+		notifications,
+	}))
+}
+
+impl Server {
+	/// Accepts client connections until the runtime is shut down
+	pub async fn run(self) {
+		info!(target: "electrum", "Electrum server is listening on {:?}", self.listener.local_addr());
+		loop {
+			match self.listener.accept().await {
+				Ok((stream, address)) => {
+					trace!(target: "electrum", "Accepted Electrum connection from {}", address);
+					let session = Arc::new(Session::new(self.backend.clone()));
+					tokio::spawn(serve_connection(stream, session, self.notifications.subscribe()));
+				}
+				Err(err) => warn!(target: "electrum", "Failed to accept Electrum connection: {}", err),
+			}
+		}
+	}
+}
+
+/// Answers line-delimited requests of the single client and sends notifications on new best blocks and memory pool changes
+async fn serve_connection(stream: TcpStream, session: Arc<Session>, mut events: broadcast::Receiver<Event>) {
+	let handler = Arc::new(Session::handler(session.clone()));
+	let (reader, mut writer) = stream.into_split();
+	let mut reader = BufReader::new(reader);
+	let mut line = Vec::new();
+	loop {
+		// storage and memory pool are accessed synchronously => do not block the runtime threads
+		let messages = tokio::select! {
+			request = read_line(&mut reader, &mut line, MAX_REQUEST_LENGTH) => match request {
+				Ok(Some(request)) => {
+					let handler = handler.clone();
+					task::spawn_blocking(move || handler.handle_request_sync(&request).into_iter().collect::<Vec<_>>()).await
+				}
+				Ok(None) => break,
+				Err(err) => {
+					trace!(target: "electrum", "Electrum connection error: {}", err);
+					break;
+				}
+			},
+			event = events.recv() => {
+				let best_block_changed = match event {
+					Ok(event) => event == Event::BestBlock,
+					// some events are missed => best block could have changed
+					Err(broadcast::error::RecvError::Lagged(_)) => true,
+					Err(broadcast::error::RecvError::Closed) => break,
+				};
+				let session = session.clone();
+				task::spawn_blocking(move || session.notifications(best_block_changed)).await
+			}
+		};
+
+		let messages = match messages {
+			Ok(messages) => messages,
+			Err(err) => {
+				warn!(target: "electrum", "Electrum request processing has failed: {}", err);
+				break;
+			}
+		};
+
+		for message in messages {
+			if writer.write_all(message.as_bytes()).await.is_err() || writer.write_all(b"\n").await.is_err() {
+				return;
+			}
+		}
+	}
+}
+
+/// Reads the next line of at most `max_length` bytes. Partially read line is kept in the `buffer`, so reading
+/// could be safely cancelled and resumed. Returns `None` at the end of stream and error if line is too long.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R, buffer: &mut Vec<u8>, max_length: usize) -> io::Result<Option<String>> {
+	// one more byte to detect too long lines
+	let limit = (max_length + 1).saturating_sub(buffer.len()) as u64;
+	reader.take(limit).read_until(b'\n', buffer).await?;
+
+	if buffer.last() == Some(&b'\n') {
+		buffer.pop();
+		if buffer.last() == Some(&b'\r') {
+			buffer.pop();
+		}
+	} else if buffer.len() > max_length {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "line is too long"));
+	} else if buffer.is_empty() {
+		return Ok(None);
+	}
+
+	let line = std::mem::take(buffer);
+	String::from_utf8(line)
+		.map(Some)
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::read_line;
+
+	#[tokio::test]
+	async fn read_line_is_bounded() {
+		let mut reader: &[u8] = b"{}\r\n0123456789\n";
+		let mut buffer = Vec::new();
+		assert_eq!(read_line(&mut reader, &mut buffer, 10).await.unwrap(), Some("{}".to_owned()));
+		assert_eq!(
+			read_line(&mut reader, &mut buffer, 10).await.unwrap(),
+			Some("0123456789".to_owned())
+		);
+		assert_eq!(read_line(&mut reader, &mut buffer, 10).await.unwrap(), None);
+
+		let mut reader: &[u8] = b"0123456789a\n";
+		assert!(read_line(&mut reader, &mut buffer, 10).await.is_err());
+	}
+}
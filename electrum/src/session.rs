@@ -0,0 +1,294 @@
+use crate::backend::Backend;
+use crate::errors::bad_request;
+use crate::types::parse_script_hash;
+use bitcrypto::{FromHex, SHA256, SHA256D};
+use jsonrpc_core::{Compatibility, Error, IoHandler, Notification, Params, Value, Version};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Name and version of the server, reported in response to `server.version`
+const SERVER_VERSION: &str = concat!("bitcoin-rs ", env!("CARGO_PKG_VERSION"));
+/// Supported version of the Electrum protocol
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// Subscriptions of the single client
+#[derive(Default)]
+struct Subscriptions {
+	/// Client is subscribed to best block headers
+	headers: bool,
+	/// Script hashes, client is subscribed to, with their last reported status
+	script_hashes: HashMap<String, (SHA256, Option<String>)>,
+}
+
+/// Electrum client session
+pub struct Session {
+	backend: Arc<Backend>,
+	subscriptions: Mutex<Subscriptions>,
+}
+
+/// `blockchain.transaction.get` parameters: transaction hash and optional verbose flag
+#[derive(Deserialize)]
+struct TransactionGetParams(SHA256D, #[serde(default)] bool);
+
+impl Session {
+	pub fn new(backend: Arc<Backend>) -> Self {
+		Session {
+			backend,
+			subscriptions: Mutex::default(),
+		}
+	}
+
+	/// Creates handler of the session requests
+	pub fn handler(session: Arc<Session>) -> IoHandler {
+		let mut handler = IoHandler::with_compatibility(Compatibility::Both);
+
+		handler.add_sync_method("server.version", |_| {
+			Ok(Value::Array(vec![SERVER_VERSION.into(), PROTOCOL_VERSION.into()]))
+		});
+		handler.add_sync_method("server.ping", |_| Ok(Value::Null));
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.headers.subscribe", move |_| {
+			s.subscriptions.lock().headers = true;
+			to_value(s.backend.best_header())
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.scripthash.get_history", move |params: Params| {
+			let (script_hash,): (String,) = params.parse()?;
+			to_value(s.backend.script_hash_history(&parse_script_hash(&script_hash)?)?)
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.scripthash.get_balance", move |params: Params| {
+			let (script_hash,): (String,) = params.parse()?;
+			to_value(s.backend.script_hash_balance(&parse_script_hash(&script_hash)?)?)
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.scripthash.listunspent", move |params: Params| {
+			let (script_hash,): (String,) = params.parse()?;
+			to_value(s.backend.script_hash_unspent(&parse_script_hash(&script_hash)?)?)
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.scripthash.subscribe", move |params: Params| {
+			let (script_hash,): (String,) = params.parse()?;
+			let parsed_script_hash = parse_script_hash(&script_hash)?;
+			let status = s.backend.script_hash_status(&parsed_script_hash)?;
+			s.subscriptions
+				.lock()
+				.script_hashes
+				.insert(script_hash, (parsed_script_hash, status.clone()));
+			to_value(status)
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.scripthash.unsubscribe", move |params: Params| {
+			let (script_hash,): (String,) = params.parse()?;
+			let removed = s.subscriptions.lock().script_hashes.remove(&script_hash).is_some();
+			Ok(Value::Bool(removed))
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.transaction.get", move |params: Params| {
+			let TransactionGetParams(hash, verbose) = params.parse()?;
+			if verbose {
+				return Err(bad_request("verbose transactions are not supported"));
+			}
+
+			to_value(s.backend.transaction(&hash)?)
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.transaction.broadcast", move |params: Params| {
+			let (raw_transaction,): (String,) = params.parse()?;
+			let raw_transaction: Vec<u8> =
+				FromHex::from_hex(raw_transaction.as_str()).map_err(|_| bad_request("invalid transaction hex"))?;
+			to_value(s.backend.broadcast(&raw_transaction)?)
+		});
+
+		let s = session.clone();
+		handler.add_sync_method("blockchain.estimatefee", move |params: Params| {
+			let (blocks,): (u32,) = params.parse()?;
+			to_value(s.backend.estimate_fee(blocks))
+		});
+
+		let s = session;
+		handler.add_sync_method("blockchain.relayfee", move |_| to_value(s.backend.relay_fee()));
+
+		handler
+	}
+
+	/// Returns serialized notifications for the client, after new best block has been inserted into storage
+	/// or memory pool has changed
+	pub fn notifications(&self, best_block_changed: bool) -> Vec<String> {
+		let mut subscriptions = self.subscriptions.lock();
+		let mut notifications = Vec::new();
+		if best_block_changed && subscriptions.headers {
+			let header = to_value(self.backend.best_header()).expect("header is always serializable; qed");
+			notifications.push(notification("blockchain.headers.subscribe", vec![header]));
+		}
+
+		for (script_hash, (parsed_script_hash, last_status)) in subscriptions.script_hashes.iter_mut() {
+			let status = match self.backend.script_hash_status(parsed_script_hash) {
+				Ok(status) => status,
+				Err(err) => {
+					warn!(target: "electrum", "Failed to compute status of script hash {}: {:?}", script_hash, err);
+					continue;
+				}
+			};
+
+			if status != *last_status {
+				*last_status = status.clone();
+				let status = status.map(Value::String).unwrap_or(Value::Null);
+				notifications.push(notification(
+					"blockchain.scripthash.subscribe",
+					vec![Value::String(script_hash.clone()), status],
+				));
+			}
+		}
+
+		notifications
+	}
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value, Error> {
+	serde_json::to_value(value).map_err(|_| Error::internal_error())
+}
+
+fn notification(method: &str, params: Vec<Value>) -> String {
+	let notification = Notification {
+		jsonrpc: Some(Version::V2),
+		method: method.into(),
+		params: Params::Array(params),
+	};
+	serde_json::to_string(&notification).expect("notification is always serializable; qed")
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::Session;
+	use crate::backend::tests::{prepare_chain, SCRIPT, SCRIPT_HASH};
+	use chain::IndexedBlock;
+	use jsonrpc_core::IoHandler;
+	use std::sync::Arc;
+
+	fn request(handler: &IoHandler, method: &str, params: &str) -> String {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":{},"id":1}}"#, method, params);
+		handler.handle_request_sync(&request).unwrap()
+	}
+
+	#[test]
+	fn server_version() {
+		let session = Arc::new(Session::new(Arc::new(prepare_chain().backend)));
+		let handler = Session::handler(session);
+		assert_eq!(
+			request(&handler, "server.version", r#"["electrum", "1.4"]"#),
+			format!(
+				r#"{{"jsonrpc":"2.0","result":["bitcoin-rs {}","1.4"],"id":1}}"#,
+				env!("CARGO_PKG_VERSION")
+			)
+		);
+	}
+
+	#[test]
+	fn scripthash_get_balance() {
+		let session = Arc::new(Session::new(Arc::new(prepare_chain().backend)));
+		let handler = Session::handler(session);
+		assert_eq!(
+			request(&handler, "blockchain.scripthash.get_balance", &format!(r#"["{}"]"#, SCRIPT_HASH)),
+			r#"{"jsonrpc":"2.0","result":{"confirmed":8000,"unconfirmed":-1500},"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "blockchain.scripthash.get_balance", r#"["00"]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"invalid script hash"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn transaction_get() {
+		let chain = prepare_chain();
+		let raw = chain.backend.transaction(&chain.coinbase).unwrap();
+		let session = Arc::new(Session::new(Arc::new(chain.backend)));
+		let handler = Session::handler(session);
+		assert_eq!(
+			request(&handler, "blockchain.transaction.get", &format!(r#"["{}"]"#, chain.coinbase)),
+			format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, raw)
+		);
+		assert_eq!(
+			request(&handler, "blockchain.transaction.get", &format!(r#"["{}", true]"#, chain.coinbase)),
+			r#"{"jsonrpc":"2.0","error":{"code":1,"message":"verbose transactions are not supported"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn subscriptions_notifications() {
+		let chain = prepare_chain();
+		let storage = chain.storage.clone();
+		let session = Arc::new(Session::new(Arc::new(chain.backend)));
+		let handler = Session::handler(session.clone());
+
+		// nothing to notify about until subscribed
+		assert!(session.notifications(true).is_empty());
+
+		request(&handler, "blockchain.headers.subscribe", "[]");
+		request(&handler, "blockchain.scripthash.subscribe", &format!(r#"["{}"]"#, SCRIPT_HASH));
+		request(
+			&handler,
+			"blockchain.scripthash.subscribe",
+			r#"["0000000000000000000000000000000000000000000000000000000000000000"]"#,
+		);
+
+		// status hasn't changed => only header is notified
+		assert_eq!(session.notifications(true).len(), 1);
+
+		#[rustfmt::skip]
+		let b2: IndexedBlock = test_data::block_builder()
+			.header().parent(storage.best_block().hash).build()
+			.transaction().coinbase()
+				.output().value(1000).script_pubkey(SCRIPT).build()
+				.build()
+			.build()
+			.into();
+		storage.insert(b2.clone()).unwrap();
+		storage.canonize(b2.hash()).unwrap();
+
+		let notifications = session.notifications(true);
+		assert_eq!(notifications.len(), 2);
+		assert!(notifications[0].contains(r#""method":"blockchain.headers.subscribe""#));
+		assert!(notifications[0].contains(r#""height":2"#));
+		assert!(notifications[1].contains(r#""method":"blockchain.scripthash.subscribe""#));
+		assert!(notifications[1].contains(SCRIPT_HASH));
+
+		// unsubscribed script hash isn't notified
+		assert_eq!(
+			request(&handler, "blockchain.scripthash.unsubscribe", &format!(r#"["{}"]"#, SCRIPT_HASH)),
+			r#"{"jsonrpc":"2.0","result":true,"id":1}"#
+		);
+		assert_eq!(session.notifications(true).len(), 1);
+	}
+
+	#[test]
+	fn memory_pool_notifications() {
+		let chain = prepare_chain();
+		let memory_pool = chain.memory_pool.clone();
+		let receive = chain.receive;
+		let session = Arc::new(Session::new(Arc::new(chain.backend)));
+		let handler = Session::handler(session.clone());
+
+		request(&handler, "blockchain.headers.subscribe", "[]");
+		request(&handler, "blockchain.scripthash.subscribe", &format!(r#"["{}"]"#, SCRIPT_HASH));
+		assert!(session.notifications(false).is_empty());
+
+		// header isn't notified, because best block hasn't changed
+		memory_pool.write().remove_by_hash(&receive);
+		let notifications = session.notifications(false);
+		assert_eq!(notifications.len(), 1);
+		assert!(notifications[0].contains(r#""method":"blockchain.scripthash.subscribe""#));
+		assert!(notifications[0].contains(SCRIPT_HASH));
+	}
+}
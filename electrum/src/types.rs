@@ -0,0 +1,101 @@
+use bitcrypto::{FromHex, Hash, SHA256, SHA256D};
+use jsonrpc_core::Error;
+
+/// Best block header, sent in response to and as `blockchain.headers.subscribe` notification
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HeaderNotification {
+	/// Serialized block header
+	pub hex: String,
+	/// Height of the block
+	pub height: u32,
+}
+
+/// Transaction, paying to or spending from the script hash
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HistoryItem {
+	/// Transaction hash
+	pub tx_hash: SHA256D,
+	/// Height of the block, containing transaction. 0 for memory pool transactions with all inputs confirmed,
+	/// -1 for memory pool transactions, spending unconfirmed outputs
+	pub height: i64,
+	/// Fee of the memory pool transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<u64>,
+}
+
+/// Balance of the script hash
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Balance {
+	/// Sum of canon chain unspent outputs values
+	pub confirmed: u64,
+	/// Change of the balance, made by the memory pool transactions
+	pub unconfirmed: i64,
+}
+
+/// Unspent output, paying to the script hash
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Unspent {
+	/// Hash of the transaction, containing output
+	pub tx_hash: SHA256D,
+	/// Index of the output in the transaction
+	pub tx_pos: u32,
+	/// Height of the block, containing transaction. 0 for memory pool transactions
+	pub height: u32,
+	/// Output value in satoshis
+	pub value: u64,
+}
+
+/// Parses script hash, which is sha256 of the script pubkey, hex-encoded in reversed byte order
+pub fn parse_script_hash(script_hash: &str) -> Result<SHA256, Error> {
+	let mut bytes: Vec<u8> = FromHex::from_hex(script_hash).map_err(|_| Error::invalid_params("invalid script hash"))?;
+	if bytes.len() != 32 {
+		return Err(Error::invalid_params("invalid script hash"));
+	}
+
+	bytes.reverse();
+	let mut inner = [0u8; 32];
+	inner.copy_from_slice(&bytes);
+	Ok(SHA256::from_inner(inner))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_script_hash, HistoryItem};
+	use bitcrypto::{sha256, FromHex, FromStr, SHA256D};
+
+	#[test]
+	fn test_parse_script_hash() {
+		// example from the protocol documentation: P2PKH script of 1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa
+		let script: Vec<u8> = FromHex::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+		assert_eq!(
+			parse_script_hash("8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161").unwrap(),
+			sha256(&script)
+		);
+		assert!(parse_script_hash("8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c391").is_err());
+		assert!(parse_script_hash("not a hash").is_err());
+	}
+
+	#[test]
+	fn test_history_item_serialize() {
+		let tx_hash = SHA256D::from_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b").unwrap();
+		let confirmed = HistoryItem {
+			tx_hash,
+			height: 10,
+			fee: None,
+		};
+		assert_eq!(
+			serde_json::to_string(&confirmed).unwrap(),
+			r#"{"tx_hash":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","height":10}"#
+		);
+
+		let unconfirmed = HistoryItem {
+			tx_hash,
+			height: -1,
+			fee: Some(200),
+		};
+		assert_eq!(
+			serde_json::to_string(&unconfirmed).unwrap(),
+			r#"{"tx_hash":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","height":-1,"fee":200}"#
+		);
+	}
+}
@@ -1,20 +1,22 @@
 //! `AddressHash` with network identifier and format type
 //!
 //! A Bitcoin address, or simply address, is an identifier of 26-35 alphanumeric characters, beginning with the number 1
-//! or 3, that represents a possible destination for a bitcoin payment.
+//! or 3, that represents a possible destination for a bitcoin payment. Segwit addresses are bech32 (BIP173) or
+//! bech32m (BIP350) encoded witness programs, beginning with the human-readable part of the network, eg: bc1.
 //!
 //! https://en.bitcoin.it/wiki/Address
 
+use crate::bech32;
 use crate::network::Network;
 use crate::{AddressHash, DisplayLayout, Error};
 use base58::{FromBase58, ToBase58};
-use bitcrypto::Hash;
+use bitcrypto::{Hash, SHA256};
 use primitives::checksum::Checksum;
 use std::fmt;
 use std::ops::Deref;
 use std::str::FromStr;
 
-/// There are two address formats currently in use.
+/// Address formats currently in use.
 /// https://bitcoin.org/en/developer-reference#address-conversion
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Type {
@@ -26,6 +28,67 @@ pub enum Type {
 	/// Newer P2SH type starting with the number 3, eg: 3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy.
 	/// https://bitcoin.org/en/glossary/p2sh-address
 	P2SH,
+	/// Pay to Witness PubKey Hash
+	/// Version 0 witness program of 20 bytes, eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+	P2WPKH,
+	/// Pay to Witness Script Hash
+	/// Version 0 witness program of 32 bytes, eg: bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+	P2WSH,
+	/// Pay to Taproot
+	/// Version 1 witness program of 32 bytes, eg: bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+	P2TR,
+}
+
+impl Type {
+	/// Returns witness version of segwit address type.
+	pub fn witness_version(&self) -> Option<u8> {
+		match *self {
+			Type::P2PKH | Type::P2SH => None,
+			Type::P2WPKH | Type::P2WSH => Some(0),
+			Type::P2TR => Some(1),
+		}
+	}
+}
+
+/// Hash or witness program, that the address is paying to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AddressPayload {
+	/// 20 bytes long hash of public key or script (P2PKH, P2SH, P2WPKH).
+	Hash(AddressHash),
+	/// 32 bytes long witness program: script hash (P2WSH) or output key (P2TR).
+	Program(SHA256),
+}
+
+impl Default for AddressPayload {
+	fn default() -> Self {
+		AddressPayload::Hash(AddressHash::default())
+	}
+}
+
+impl From<AddressHash> for AddressPayload {
+	fn from(hash: AddressHash) -> Self {
+		AddressPayload::Hash(hash)
+	}
+}
+
+impl From<SHA256> for AddressPayload {
+	fn from(program: SHA256) -> Self {
+		AddressPayload::Program(program)
+	}
+}
+
+impl Deref for AddressPayload {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		match *self {
+			AddressPayload::Hash(ref hash) => hash,
+			AddressPayload::Program(ref program) => program,
+		}
+	}
 }
 
 /// `AddressHash` with network identifier and format type
@@ -35,11 +98,57 @@ pub struct Address {
 	pub kind: Type,
 	/// The network of the address.
 	pub network: Network,
-	/// Public key hash.
-	pub hash: AddressHash,
+	/// Public key hash, script hash or witness program.
+	pub hash: AddressPayload,
 }
 
-pub struct AddressDisplayLayout([u8; 25]);
+impl Address {
+	/// Returns human-readable part of segwit addresses for the network.
+	fn hrp(network: Network) -> &'static str {
+		match network {
+			Network::Mainnet => "bc",
+			Network::Testnet => "tb",
+			Network::Regtest => "bcrt",
+		}
+	}
+
+	/// Parses address of the given network. Legacy regtest addresses share prefixes with testnet addresses
+	/// and are decoded as testnet addresses by `from_str`, so they're accepted here as regtest addresses.
+	pub fn from_str_for_network(s: &str, network: Network) -> Result<Self, Error> {
+		let address: Address = s.parse()?;
+		match (address.network, network) {
+			(decoded, network) if decoded == network => Ok(address),
+			(Network::Testnet, Network::Regtest) if address.kind.witness_version().is_none() => Ok(Address { network, ..address }),
+			_ => Err(Error::InvalidNetwork),
+		}
+	}
+
+	fn from_bech32(s: &str) -> Result<Self, Error> {
+		let (hrp, version, program) = bech32::decode(s)?;
+		let network = match hrp.as_str() {
+			"bc" => Network::Mainnet,
+			"tb" => Network::Testnet,
+			"bcrt" => Network::Regtest,
+			_ => return Err(Error::InvalidNetwork),
+		};
+
+		let (kind, hash) = match (version, program.len()) {
+			(0, 20) => (
+				Type::P2WPKH,
+				AddressHash::from_slice(&program).map_err(|_| Error::InvalidAddress)?.into(),
+			),
+			(0, 32) => (Type::P2WSH, SHA256::from_slice(&program).map_err(|_| Error::InvalidAddress)?.into()),
+			(1, 32) => (Type::P2TR, SHA256::from_slice(&program).map_err(|_| Error::InvalidAddress)?.into()),
+			// other witness programs are valid, but we have no address type for them
+			_ => return Err(Error::InvalidAddress),
+		};
+
+		Ok(Address { kind, network, hash })
+	}
+}
+
+/// Base58 layout of legacy address or witness version followed by witness program for segwit address.
+pub struct AddressDisplayLayout(Vec<u8>);
 
 impl Deref for AddressDisplayLayout {
 	type Target = [u8];
@@ -53,18 +162,24 @@ impl DisplayLayout for Address {
 	type Target = AddressDisplayLayout;
 
 	fn layout(&self) -> Self::Target {
-		let mut result = [0u8; 25];
+		let mut result = Vec::with_capacity(33);
+
+		if let Some(version) = self.kind.witness_version() {
+			result.push(version);
+			result.extend_from_slice(&self.hash);
+			return AddressDisplayLayout(result);
+		}
 
-		result[0] = match (self.network, self.kind) {
-			(Network::Mainnet, Type::P2PKH) => 0,
+		result.push(match (self.network, self.kind) {
 			(Network::Mainnet, Type::P2SH) => 5,
-			(Network::Testnet, Type::P2PKH) => 111,
-			(Network::Testnet, Type::P2SH) => 196,
-		};
+			(Network::Testnet, Type::P2SH) | (Network::Regtest, Type::P2SH) => 196,
+			(Network::Mainnet, _) => 0,
+			(Network::Testnet, _) | (Network::Regtest, _) => 111,
+		});
 
-		result[1..21].copy_from_slice(&*self.hash);
-		let cs = Checksum::generate(&result[0..21]);
-		result[21..25].copy_from_slice(&*cs);
+		result.extend_from_slice(&self.hash);
+		let cs = Checksum::generate(&result);
+		result.extend_from_slice(&*cs);
 		AddressDisplayLayout(result)
 	}
 
@@ -81,6 +196,7 @@ impl DisplayLayout for Address {
 			return Err(Error::InvalidChecksum);
 		}
 
+		// testnet and regtest use the same prefixes, see `from_str_for_network`
 		let (network, kind) = match data[0] {
 			0 => (Network::Mainnet, Type::P2PKH),
 			5 => (Network::Mainnet, Type::P2SH),
@@ -89,7 +205,7 @@ impl DisplayLayout for Address {
 			_ => return Err(Error::InvalidAddress),
 		};
 
-		let hash = AddressHash::from_slice(&data[1..21]).map_err(|_| Error::InvalidAddress)?;
+		let hash = AddressHash::from_slice(&data[1..21]).map_err(|_| Error::InvalidAddress)?.into();
 
 		let address = Address { kind, network, hash };
 
@@ -99,7 +215,11 @@ impl DisplayLayout for Address {
 
 impl fmt::Display for Address {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		self.layout().to_base58().fmt(f)
+		let layout = self.layout();
+		match self.kind.witness_version() {
+			Some(version) => bech32::encode(Address::hrp(self.network), version, &layout[1..]).fmt(f),
+			None => layout.to_base58().fmt(f),
+		}
 	}
 }
 
@@ -110,6 +230,11 @@ impl FromStr for Address {
 	where
 		Self: Sized,
 	{
+		let lowercase = s.to_ascii_lowercase();
+		if ["bc1", "tb1", "bcrt1"].iter().any(|hrp| lowercase.starts_with(hrp)) {
+			return Address::from_bech32(s);
+		}
+
 		let hex = s.from_base58().map_err(|_| Error::InvalidAddress)?;
 		Address::from_layout(&hex)
 	}
@@ -125,15 +250,15 @@ impl From<&'static str> for Address {
 mod tests {
 	use super::{Address, Type};
 	use crate::network::Network;
-	use crate::AddressHash;
-	use bitcrypto::FromHex;
+	use crate::{AddressHash, Error};
+	use bitcrypto::{FromHex, SHA256};
 
 	#[test]
 	fn test_address_to_string() {
 		let address = Address {
 			kind: Type::P2PKH,
 			network: Network::Mainnet,
-			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap(),
+			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
 		};
 
 		assert_eq!("16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".to_owned(), address.to_string());
@@ -144,9 +269,113 @@ mod tests {
 		let address = Address {
 			kind: Type::P2PKH,
 			network: Network::Mainnet,
-			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap(),
+			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
 		};
 
 		assert_eq!(address, "16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".into());
 	}
+
+	#[test]
+	fn test_segwit_address_to_string() {
+		let address = Address {
+			kind: Type::P2WPKH,
+			network: Network::Mainnet,
+			hash: AddressHash::from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap().into(),
+		};
+		assert_eq!("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned(), address.to_string());
+
+		let address = Address {
+			kind: Type::P2WSH,
+			network: Network::Testnet,
+			hash: SHA256::from_hex("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262")
+				.unwrap()
+				.into(),
+		};
+		assert_eq!(
+			"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".to_owned(),
+			address.to_string()
+		);
+
+		let address = Address {
+			kind: Type::P2TR,
+			network: Network::Mainnet,
+			hash: SHA256::from_hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+				.unwrap()
+				.into(),
+		};
+		assert_eq!(
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".to_owned(),
+			address.to_string()
+		);
+	}
+
+	#[test]
+	fn test_segwit_address_from_str() {
+		let address = Address {
+			kind: Type::P2WPKH,
+			network: Network::Regtest,
+			hash: AddressHash::from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap().into(),
+		};
+		assert_eq!(address, address.to_string().parse().unwrap());
+		assert!(address.to_string().starts_with("bcrt1q"));
+
+		let address: Address = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4".into();
+		assert_eq!(address.kind, Type::P2WPKH);
+		assert_eq!(address.network, Network::Mainnet);
+
+		let address: Address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".into();
+		assert_eq!(address.kind, Type::P2TR);
+
+		// valid bech32m, but witness version 16 has no address type
+		assert_eq!("BC1SW50QGDZ25J".parse::<Address>(), Err(Error::InvalidAddress));
+		// P2TR with bech32 checksum
+		assert_eq!(
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd".parse::<Address>(),
+			Err(Error::InvalidChecksum)
+		);
+	}
+
+	#[test]
+	fn test_regtest_address_uses_testnet_prefix() {
+		let address = Address {
+			kind: Type::P2SH,
+			network: Network::Regtest,
+			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
+		};
+		assert!(address.to_string().starts_with('2'));
+	}
+
+	#[test]
+	fn test_regtest_address_round_trip() {
+		for kind in &[Type::P2PKH, Type::P2SH, Type::P2WPKH] {
+			let address = Address {
+				kind: *kind,
+				network: Network::Regtest,
+				hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
+			};
+			assert_eq!(
+				Address::from_str_for_network(&address.to_string(), Network::Regtest),
+				Ok(address.clone())
+			);
+			assert_eq!(
+				Address::from_str_for_network(&address.to_string(), Network::Mainnet),
+				Err(Error::InvalidNetwork)
+			);
+		}
+
+		// legacy addresses without the network are decoded as testnet addresses
+		let address = Address {
+			kind: Type::P2PKH,
+			network: Network::Regtest,
+			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
+		};
+		let decoded: Address = address.to_string().parse().unwrap();
+		assert_eq!(decoded.network, Network::Testnet);
+		assert_eq!(Address::from_str_for_network(&address.to_string(), Network::Testnet), Ok(decoded));
+		// but segwit addresses have distinct prefixes
+		assert_eq!(
+			Address::from_str_for_network("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", Network::Regtest),
+			Err(Error::InvalidNetwork)
+		);
+	}
 }
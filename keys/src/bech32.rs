@@ -0,0 +1,254 @@
+//! Bech32 and Bech32m encoding of segwit addresses
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+
+use crate::Error;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const SEPARATOR: char = '1';
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_LENGTH: usize = 90;
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+/// Checksum variant. Witness version 0 uses Bech32, all later versions use Bech32m.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Variant {
+	Bech32,
+	Bech32m,
+}
+
+impl Variant {
+	fn for_version(version: u8) -> Self {
+		if version == 0 {
+			Variant::Bech32
+		} else {
+			Variant::Bech32m
+		}
+	}
+
+	fn constant(self) -> u32 {
+		match self {
+			Variant::Bech32 => BECH32_CONST,
+			Variant::Bech32m => BECH32M_CONST,
+		}
+	}
+}
+
+fn polymod(values: &[u8]) -> u32 {
+	const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+
+	let mut chk = 1u32;
+	for value in values {
+		let top = chk >> 25;
+		chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+		for (i, generator) in GENERATOR.iter().enumerate() {
+			if (top >> i) & 1 == 1 {
+				chk ^= generator;
+			}
+		}
+	}
+	chk
+}
+
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+	let mut result = Vec::with_capacity(hrp.len() * 2 + 1);
+	result.extend(hrp.iter().map(|c| c >> 5));
+	result.push(0);
+	result.extend(hrp.iter().map(|c| c & 0x1f));
+	result
+}
+
+fn create_checksum(hrp: &[u8], data: &[u8], variant: Variant) -> [u8; CHECKSUM_LENGTH] {
+	let mut values = hrp_expand(hrp);
+	values.extend_from_slice(data);
+	values.extend_from_slice(&[0u8; CHECKSUM_LENGTH]);
+	let modulo = polymod(&values) ^ variant.constant();
+
+	let mut result = [0u8; CHECKSUM_LENGTH];
+	for (i, item) in result.iter_mut().enumerate() {
+		*item = ((modulo >> (5 * (5 - i))) & 0x1f) as u8;
+	}
+	result
+}
+
+/// Regroups bits of `data` from `from`-bit groups to `to`-bit groups.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+	let mut acc = 0u32;
+	let mut bits = 0u32;
+	let max = (1u32 << to) - 1;
+	let mut result = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+	for value in data {
+		let value = u32::from(*value);
+		if value >> from != 0 {
+			return None;
+		}
+		acc = (acc << from) | value;
+		bits += from;
+		while bits >= to {
+			bits -= to;
+			result.push(((acc >> bits) & max) as u8);
+		}
+	}
+
+	if pad {
+		if bits > 0 {
+			result.push(((acc << (to - bits)) & max) as u8);
+		}
+	} else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+		return None;
+	}
+
+	Some(result)
+}
+
+/// Encodes witness program as a segwit address with given human-readable part.
+pub fn encode(hrp: &str, version: u8, program: &[u8]) -> String {
+	let mut data = vec![version];
+	data.extend(convert_bits(program, 8, 5, true).expect("8-bit groups are always valid; qed"));
+	let checksum = create_checksum(hrp.as_bytes(), &data, Variant::for_version(version));
+
+	let mut result = String::with_capacity(hrp.len() + 1 + data.len() + CHECKSUM_LENGTH);
+	result.push_str(hrp);
+	result.push(SEPARATOR);
+	result.extend(data.iter().chain(checksum.iter()).map(|d| CHARSET[*d as usize] as char));
+	result
+}
+
+/// Decodes segwit address. Returns lowercase human-readable part, witness version and witness program.
+pub fn decode(address: &str) -> Result<(String, u8, Vec<u8>), Error> {
+	if address.len() > MAX_LENGTH {
+		return Err(Error::InvalidAddress);
+	}
+
+	let has_lower = address.bytes().any(|c| c.is_ascii_lowercase());
+	let has_upper = address.bytes().any(|c| c.is_ascii_uppercase());
+	if has_lower && has_upper {
+		return Err(Error::InvalidAddress);
+	}
+
+	if address.bytes().any(|c| !(33..=126).contains(&c)) {
+		return Err(Error::InvalidAddress);
+	}
+
+	let address = address.to_ascii_lowercase();
+	let separator = address.rfind(SEPARATOR).ok_or(Error::InvalidAddress)?;
+	let (hrp, data) = (&address[..separator], &address[separator + 1..]);
+	if hrp.is_empty() || data.len() < CHECKSUM_LENGTH + 1 {
+		return Err(Error::InvalidAddress);
+	}
+
+	let data = data
+		.bytes()
+		.map(|c| CHARSET.iter().position(|x| *x == c).map(|p| p as u8))
+		.collect::<Option<Vec<u8>>>()
+		.ok_or(Error::InvalidAddress)?;
+
+	let version = data[0];
+	if version > 16 {
+		return Err(Error::InvalidAddress);
+	}
+
+	let mut values = hrp_expand(hrp.as_bytes());
+	values.extend_from_slice(&data);
+	if polymod(&values) != Variant::for_version(version).constant() {
+		return Err(Error::InvalidChecksum);
+	}
+
+	let program = convert_bits(&data[1..data.len() - CHECKSUM_LENGTH], 5, 8, false).ok_or(Error::InvalidAddress)?;
+	if program.len() < 2 || program.len() > 40 || (version == 0 && program.len() != 20 && program.len() != 32) {
+		return Err(Error::InvalidAddress);
+	}
+
+	Ok((hrp.to_owned(), version, program))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode, encode};
+	use crate::Error;
+	use bitcrypto::FromHex;
+
+	#[test]
+	fn test_bech32_valid_addresses() {
+		// BIP173 and BIP350 test vectors
+		let vectors = [
+			(
+				"BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+				"bc",
+				0,
+				"751e76e8199196d454941c45d1b3a323f1433bd6",
+			),
+			(
+				"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+				"tb",
+				0,
+				"1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+			),
+			(
+				"bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+				"bc",
+				1,
+				"751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+			),
+			("BC1SW50QGDZ25J", "bc", 16, "751e"),
+			(
+				"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+				"bc",
+				1,
+				"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+			),
+		];
+
+		for (address, hrp, version, program) in vectors.iter() {
+			let program: Vec<u8> = FromHex::from_hex(*program).unwrap();
+			let decoded = decode(address).unwrap();
+			assert_eq!(decoded, (hrp.to_string(), *version, program.clone()));
+			assert_eq!(encode(hrp, *version, &program), address.to_lowercase());
+		}
+	}
+
+	#[test]
+	fn test_bech32_invalid_addresses() {
+		// BIP350 test vectors
+		let vectors = [
+			// Bech32 checksum used for witness version 1
+			(
+				"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+				Error::InvalidChecksum,
+			),
+			// Bech32m checksum used for witness version 0
+			("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh", Error::InvalidChecksum),
+			// invalid character
+			(
+				"bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+				Error::InvalidAddress,
+			),
+			// empty data section
+			("bc1gmk9yu", Error::InvalidAddress),
+			// invalid program length
+			("bc1pw5dgrnzv", Error::InvalidAddress),
+			("BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P", Error::InvalidAddress),
+			// mixed case
+			(
+				"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3Q0sL5k7",
+				Error::InvalidAddress,
+			),
+		];
+
+		for (address, error) in vectors.iter() {
+			assert_eq!(&decode(address).unwrap_err(), error, "{}", address);
+		}
+	}
+
+	#[test]
+	fn test_bech32_roundtrip() {
+		let program = [0x33u8; 32];
+		let address = encode("bcrt", 1, &program);
+		let (hrp, version, decoded) = decode(&address).unwrap();
+		assert_eq!(hrp, "bcrt");
+		assert_eq!(version, 1);
+		assert_eq!(decoded, program.to_vec());
+	}
+}
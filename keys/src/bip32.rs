@@ -0,0 +1,882 @@
+//! BIP32 hierarchical deterministic keys.
+//!
+//! Extended keys are serialized with the standard `xprv`/`xpub` (`tprv`/`tpub` on testnet) versions
+//! or with SLIP-132 versions, which also encode the script type of derived keys (`zpub`, `Ypub`, ...).
+
+use crate::network::Network;
+use crate::{AddressHash, DisplayLayout, Error, Private, Public, Secret, SECP256K1};
+use base58::{FromBase58, ToBase58};
+use bitcrypto::{dhash160, hmac_sha512};
+use primitives::checksum::Checksum;
+use secp256k1::{PublicKey, Scalar, SecretKey};
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+/// First hardened child index
+pub const HARDENED: u32 = 1 << 31;
+/// Size of the serialized extended key (without checksum)
+const EXTENDED_KEY_SIZE: usize = 78;
+
+/// 32 bytes long extra entropy of the extended key
+pub type ChainCode = [u8; 32];
+/// First 4 bytes of the key identifier `ripemd160(sha256(public))`
+pub type Fingerprint = [u8; 4];
+
+/// Index of the child key. Indices starting from `HARDENED` are hardened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChildNumber(u32);
+
+impl ChildNumber {
+	/// Normal child with the given index. Index must be below `HARDENED`.
+	pub fn normal(index: u32) -> Result<Self, Error> {
+		if index >= HARDENED {
+			return Err(Error::InvalidDerivationPath);
+		}
+		Ok(ChildNumber(index))
+	}
+
+	/// Hardened child with the given index. Index must be below `HARDENED`.
+	pub fn hardened(index: u32) -> Result<Self, Error> {
+		if index >= HARDENED {
+			return Err(Error::InvalidDerivationPath);
+		}
+		Ok(ChildNumber(index | HARDENED))
+	}
+
+	pub fn is_hardened(&self) -> bool {
+		self.0 >= HARDENED
+	}
+
+	/// Index of the child without the hardened flag
+	pub fn index(&self) -> u32 {
+		self.0 & !HARDENED
+	}
+}
+
+impl From<u32> for ChildNumber {
+	fn from(number: u32) -> Self {
+		ChildNumber(number)
+	}
+}
+
+impl From<ChildNumber> for u32 {
+	fn from(child: ChildNumber) -> Self {
+		child.0
+	}
+}
+
+impl fmt::Display for ChildNumber {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.is_hardened() {
+			write!(f, "{}'", self.index())
+		} else {
+			write!(f, "{}", self.index())
+		}
+	}
+}
+
+impl FromStr for ChildNumber {
+	type Err = Error;
+
+	/// Parses `1`, `1'`, `1h` or `1H`
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let (index, hardened) = match s.strip_suffix(|c| c == '\'' || c == 'h' || c == 'H') {
+			Some(index) => (index, true),
+			None => (s, false),
+		};
+		// u32::from_str accepts leading '+'
+		if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+			return Err(Error::InvalidDerivationPath);
+		}
+		let index = index.parse().map_err(|_| Error::InvalidDerivationPath)?;
+		if hardened {
+			ChildNumber::hardened(index)
+		} else {
+			ChildNumber::normal(index)
+		}
+	}
+}
+
+/// Path from the master key to the derived key, e.g. `m/84'/0'/0'/0/1`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+	/// Path of the master key
+	pub fn master() -> Self {
+		DerivationPath::default()
+	}
+
+	pub fn is_master(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Path of the child of this path's key
+	pub fn child(&self, child: ChildNumber) -> Self {
+		let mut path = self.clone();
+		path.0.push(child);
+		path
+	}
+
+	/// Path of this path's key descendant
+	pub fn extend(&self, path: &DerivationPath) -> Self {
+		let mut result = self.clone();
+		result.0.extend_from_slice(&path.0);
+		result
+	}
+
+	pub fn as_slice(&self) -> &[ChildNumber] {
+		&self.0
+	}
+}
+
+impl From<Vec<ChildNumber>> for DerivationPath {
+	fn from(path: Vec<ChildNumber>) -> Self {
+		DerivationPath(path)
+	}
+}
+
+impl fmt::Display for DerivationPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("m")?;
+		for child in &self.0 {
+			write!(f, "/{}", child)?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for DerivationPath {
+	type Err = Error;
+
+	/// Parses path with or without the leading `m`, e.g. `m/0'/1` or `0h/1`
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let path = match s {
+			"m" => return Ok(DerivationPath::master()),
+			_ => s.strip_prefix("m/").unwrap_or(s),
+		};
+		path.split('/')
+			.map(ChildNumber::from_str)
+			.collect::<Result<Vec<_>, _>>()
+			.map(DerivationPath)
+	}
+}
+
+/// Script type of keys, derived from the extended key (SLIP-132)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyType {
+	/// `xpub`/`tpub`, no script type is implied
+	Standard,
+	/// `ypub`/`upub`, P2WPKH nested in P2SH
+	P2shP2wpkh,
+	/// `zpub`/`vpub`, native P2WPKH
+	P2wpkh,
+	/// `Ypub`/`Upub`, P2WSH multisig nested in P2SH
+	P2shP2wsh,
+	/// `Zpub`/`Vpub`, native P2WSH multisig
+	P2wsh,
+}
+
+/// (network, key type, private version, public version)
+const VERSIONS: [(Network, KeyType, u32, u32); 10] = [
+	(Network::Mainnet, KeyType::Standard, 0x0488_ade4, 0x0488_b21e),
+	(Network::Mainnet, KeyType::P2shP2wpkh, 0x049d_7878, 0x049d_7cb2),
+	(Network::Mainnet, KeyType::P2wpkh, 0x04b2_430c, 0x04b2_4746),
+	(Network::Mainnet, KeyType::P2shP2wsh, 0x0295_b005, 0x0295_b43f),
+	(Network::Mainnet, KeyType::P2wsh, 0x02aa_7a99, 0x02aa_7ed3),
+	(Network::Testnet, KeyType::Standard, 0x0435_8394, 0x0435_87cf),
+	(Network::Testnet, KeyType::P2shP2wpkh, 0x044a_4e28, 0x044a_5262),
+	(Network::Testnet, KeyType::P2wpkh, 0x045f_18bc, 0x045f_1cf6),
+	(Network::Testnet, KeyType::P2shP2wsh, 0x0242_85b5, 0x0242_89ef),
+	(Network::Testnet, KeyType::P2wsh, 0x0257_5048, 0x0257_5483),
+];
+
+/// Returns (private, public) versions of the extended key
+fn versions(network: Network, key_type: KeyType) -> (u32, u32) {
+	// regtest uses testnet versions
+	let network = match network {
+		Network::Regtest => Network::Testnet,
+		network => network,
+	};
+	VERSIONS
+		.iter()
+		.find(|v| v.0 == network && v.1 == key_type)
+		.map(|v| (v.2, v.3))
+		.expect("all networks and key types are listed; qed")
+}
+
+/// Extended private key
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPrivate {
+	/// The network on which this key should be used.
+	pub network: Network,
+	/// Script type of derived keys.
+	pub key_type: KeyType,
+	/// Number of derivations from the master key.
+	pub depth: u8,
+	/// Fingerprint of the parent key, zero for the master key.
+	pub parent_fingerprint: Fingerprint,
+	/// Child number of this key, zero for the master key.
+	pub child_number: ChildNumber,
+	pub chain_code: ChainCode,
+	pub secret: Secret,
+}
+
+impl ExtendedPrivate {
+	/// Creates master key from the 128-512 bits long seed.
+	pub fn from_seed(seed: &[u8], network: Network) -> Result<Self, Error> {
+		if seed.len() < 16 || seed.len() > 64 {
+			return Err(Error::InvalidSeed);
+		}
+
+		let (secret, chain_code) = split_hmac(hmac_sha512(b"Bitcoin seed", seed));
+		SecretKey::from_slice(&secret).map_err(|_| Error::InvalidSeed)?;
+		Ok(ExtendedPrivate {
+			network,
+			key_type: KeyType::Standard,
+			depth: 0,
+			parent_fingerprint: Fingerprint::default(),
+			child_number: ChildNumber(0),
+			chain_code,
+			secret,
+		})
+	}
+
+	/// Derives child key. Fails with negligible probability, in which case the next index should be used.
+	pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, Error> {
+		let secret = SecretKey::from_slice(&self.secret)?;
+		let mut data = Vec::with_capacity(37);
+		if child_number.is_hardened() {
+			data.push(0);
+			data.extend_from_slice(&self.secret);
+		} else {
+			data.extend_from_slice(&PublicKey::from_secret_key(&SECP256K1, &secret).serialize());
+		}
+		data.extend_from_slice(&u32::from(child_number).to_be_bytes());
+
+		let (tweak, chain_code) = split_hmac(hmac_sha512(&self.chain_code, &data));
+		let tweak = Scalar::from_be_bytes(tweak).map_err(|_| Error::InvalidDerivation)?;
+		let child_secret = secret.add_tweak(&tweak).map_err(|_| Error::InvalidDerivation)?;
+
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&child_secret.secret_bytes());
+		Ok(ExtendedPrivate {
+			network: self.network,
+			key_type: self.key_type,
+			depth: self.depth.checked_add(1).ok_or(Error::InvalidDerivation)?,
+			parent_fingerprint: self.fingerprint(),
+			child_number,
+			chain_code,
+			secret,
+		})
+	}
+
+	/// Derives descendant key at the path, relative to this key.
+	pub fn derive(&self, path: &DerivationPath) -> Result<Self, Error> {
+		path.as_slice().iter().try_fold(self.clone(), |key, child| key.derive_child(*child))
+	}
+
+	/// Returns extended public key of this key.
+	pub fn extended_public(&self) -> ExtendedPublic {
+		ExtendedPublic {
+			network: self.network,
+			key_type: self.key_type,
+			depth: self.depth,
+			parent_fingerprint: self.parent_fingerprint,
+			child_number: self.child_number,
+			chain_code: self.chain_code,
+			public: self.public(),
+		}
+	}
+
+	/// Returns compressed private key.
+	pub fn private(&self) -> Private {
+		Private {
+			network: self.network,
+			secret: self.secret,
+			compressed: true,
+		}
+	}
+
+	/// Returns compressed public key.
+	pub fn public(&self) -> Public {
+		let secret = SecretKey::from_slice(&self.secret).expect("secret of the extended key is valid; qed");
+		Public::Compressed(PublicKey::from_secret_key(&SECP256K1, &secret).serialize())
+	}
+
+	pub fn identifier(&self) -> AddressHash {
+		dhash160(&self.public())
+	}
+
+	pub fn fingerprint(&self) -> Fingerprint {
+		fingerprint(&self.identifier())
+	}
+}
+
+impl DisplayLayout for ExtendedPrivate {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		let (version, _) = versions(self.network, self.key_type);
+		let mut key = [0u8; 33];
+		key[1..].copy_from_slice(&self.secret);
+		encode(
+			version,
+			self.depth,
+			&self.parent_fingerprint,
+			self.child_number,
+			&self.chain_code,
+			&key,
+		)
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let decoded = decode(data)?;
+		if !decoded.private || decoded.key[0] != 0 {
+			return Err(Error::InvalidExtendedKey);
+		}
+
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&decoded.key[1..]);
+		SecretKey::from_slice(&secret)?;
+		Ok(ExtendedPrivate {
+			network: decoded.network,
+			key_type: decoded.key_type,
+			depth: decoded.depth,
+			parent_fingerprint: decoded.parent_fingerprint,
+			child_number: decoded.child_number,
+			chain_code: decoded.chain_code,
+			secret,
+		})
+	}
+}
+
+impl fmt::Display for ExtendedPrivate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPrivate {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPrivate::from_layout(&data)
+	}
+}
+
+/// Extended public key
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPublic {
+	/// The network on which this key should be used.
+	pub network: Network,
+	/// Script type of derived keys.
+	pub key_type: KeyType,
+	/// Number of derivations from the master key.
+	pub depth: u8,
+	/// Fingerprint of the parent key, zero for the master key.
+	pub parent_fingerprint: Fingerprint,
+	/// Child number of this key, zero for the master key.
+	pub child_number: ChildNumber,
+	pub chain_code: ChainCode,
+	/// Compressed public key.
+	pub public: Public,
+}
+
+impl ExtendedPublic {
+	/// Derives normal child key. Hardened children can only be derived from the extended private key.
+	pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, Error> {
+		if child_number.is_hardened() {
+			return Err(Error::HardenedDerivation);
+		}
+
+		let mut data = Vec::with_capacity(37);
+		data.extend_from_slice(&self.public);
+		data.extend_from_slice(&u32::from(child_number).to_be_bytes());
+
+		let (tweak, chain_code) = split_hmac(hmac_sha512(&self.chain_code, &data));
+		let tweak = Scalar::from_be_bytes(tweak).map_err(|_| Error::InvalidDerivation)?;
+		let public = PublicKey::from_slice(&self.public)?
+			.add_exp_tweak(&SECP256K1, &tweak)
+			.map_err(|_| Error::InvalidDerivation)?;
+
+		Ok(ExtendedPublic {
+			network: self.network,
+			key_type: self.key_type,
+			depth: self.depth.checked_add(1).ok_or(Error::InvalidDerivation)?,
+			parent_fingerprint: self.fingerprint(),
+			child_number,
+			chain_code,
+			public: Public::Compressed(public.serialize()),
+		})
+	}
+
+	/// Derives descendant key at the path, relative to this key. Path must not contain hardened children.
+	pub fn derive(&self, path: &DerivationPath) -> Result<Self, Error> {
+		path.as_slice().iter().try_fold(self.clone(), |key, child| key.derive_child(*child))
+	}
+
+	pub fn identifier(&self) -> AddressHash {
+		dhash160(&self.public)
+	}
+
+	pub fn fingerprint(&self) -> Fingerprint {
+		fingerprint(&self.identifier())
+	}
+}
+
+impl DisplayLayout for ExtendedPublic {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		let (_, version) = versions(self.network, self.key_type);
+		encode(
+			version,
+			self.depth,
+			&self.parent_fingerprint,
+			self.child_number,
+			&self.chain_code,
+			&self.public,
+		)
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let decoded = decode(data)?;
+		if decoded.private {
+			return Err(Error::InvalidExtendedKey);
+		}
+
+		PublicKey::from_slice(decoded.key)?;
+		Ok(ExtendedPublic {
+			network: decoded.network,
+			key_type: decoded.key_type,
+			depth: decoded.depth,
+			parent_fingerprint: decoded.parent_fingerprint,
+			child_number: decoded.child_number,
+			chain_code: decoded.chain_code,
+			public: Public::from_slice(decoded.key)?,
+		})
+	}
+}
+
+impl fmt::Display for ExtendedPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPublic {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPublic::from_layout(&data)
+	}
+}
+
+fn fingerprint(identifier: &AddressHash) -> Fingerprint {
+	identifier[0..4].try_into().expect("hash is longer than 4 bytes; qed")
+}
+
+/// Splits HMAC-SHA512 into the key (or tweak) and the chain code
+fn split_hmac(hmac: [u8; 64]) -> ([u8; 32], ChainCode) {
+	let mut key = [0u8; 32];
+	let mut chain_code = ChainCode::default();
+	key.copy_from_slice(&hmac[..32]);
+	chain_code.copy_from_slice(&hmac[32..]);
+	(key, chain_code)
+}
+
+fn encode(
+	version: u32,
+	depth: u8,
+	parent_fingerprint: &Fingerprint,
+	child_number: ChildNumber,
+	chain_code: &ChainCode,
+	key: &[u8],
+) -> Vec<u8> {
+	let mut result = Vec::with_capacity(EXTENDED_KEY_SIZE + 4);
+	result.extend_from_slice(&version.to_be_bytes());
+	result.push(depth);
+	result.extend_from_slice(parent_fingerprint);
+	result.extend_from_slice(&u32::from(child_number).to_be_bytes());
+	result.extend_from_slice(chain_code);
+	result.extend_from_slice(key);
+	let cs = Checksum::generate(&result);
+	result.extend_from_slice(&*cs);
+	result
+}
+
+struct Decoded<'a> {
+	network: Network,
+	key_type: KeyType,
+	private: bool,
+	depth: u8,
+	parent_fingerprint: Fingerprint,
+	child_number: ChildNumber,
+	chain_code: ChainCode,
+	key: &'a [u8],
+}
+
+fn decode(data: &[u8]) -> Result<Decoded<'_>, Error> {
+	if data.len() != EXTENDED_KEY_SIZE + 4 {
+		return Err(Error::InvalidExtendedKey);
+	}
+
+	let cs = Checksum::generate(&data[..EXTENDED_KEY_SIZE]);
+	if &data[EXTENDED_KEY_SIZE..] != &*cs {
+		return Err(Error::InvalidChecksum);
+	}
+
+	let version = u32::from_be_bytes(data[0..4].try_into().expect("slice is 4 bytes long; qed"));
+	let (network, key_type, private) = VERSIONS
+		.iter()
+		.find(|v| version == v.2 || version == v.3)
+		.map(|v| (v.0, v.1, version == v.2))
+		.ok_or(Error::InvalidNetwork)?;
+
+	let depth = data[4];
+	let parent_fingerprint: Fingerprint = data[5..9].try_into().expect("slice is 4 bytes long; qed");
+	let child_number = ChildNumber(u32::from_be_bytes(data[9..13].try_into().expect("slice is 4 bytes long; qed")));
+	if depth == 0 && (parent_fingerprint != Fingerprint::default() || child_number != ChildNumber(0)) {
+		return Err(Error::InvalidExtendedKey);
+	}
+
+	let mut chain_code = ChainCode::default();
+	chain_code.copy_from_slice(&data[13..45]);
+	Ok(Decoded {
+		network,
+		key_type,
+		private,
+		depth,
+		parent_fingerprint,
+		child_number,
+		chain_code,
+		key: &data[45..EXTENDED_KEY_SIZE],
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ChildNumber, DerivationPath, ExtendedPrivate, ExtendedPublic, KeyType, HARDENED};
+	use crate::bip39::mnemonic_to_seed;
+	use crate::{Error, Network};
+	use bitcrypto::{FromHex, ToHex};
+
+	/// Checks BIP32 test vector: each step derives next child from the previous key
+	fn check_vector(seed: &str, steps: &[(&str, &str, &str)]) {
+		let seed: Vec<u8> = FromHex::from_hex(seed).unwrap();
+		let master = ExtendedPrivate::from_seed(&seed, Network::Mainnet).unwrap();
+		let mut private = master.clone();
+		let mut public = master.extended_public();
+		for (path, xpub, xprv) in steps {
+			let path: DerivationPath = path.parse().unwrap();
+			if let Some(child) = path.as_slice().last() {
+				private = private.derive_child(*child).unwrap();
+				public = match child.is_hardened() {
+					true => {
+						assert_eq!(public.derive_child(*child), Err(Error::HardenedDerivation));
+						private.extended_public()
+					}
+					false => public.derive_child(*child).unwrap(),
+				};
+			}
+
+			assert_eq!(private.to_string(), *xprv, "xprv of {}", path);
+			assert_eq!(public.to_string(), *xpub, "xpub of {}", path);
+			assert_eq!(private.extended_public(), public);
+			assert_eq!(master.derive(&path).unwrap(), private);
+			assert_eq!(xprv.parse::<ExtendedPrivate>().unwrap(), private);
+			assert_eq!(xpub.parse::<ExtendedPublic>().unwrap(), public);
+		}
+	}
+
+	#[test]
+	fn test_bip32_vector_1() {
+		check_vector(
+			"000102030405060708090a0b0c0d0e0f",
+			&[
+				(
+					"m",
+					"xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+					"xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+				),
+				(
+					"m/0H",
+					"xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+					"xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+				),
+				(
+					"m/0H/1",
+					"xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+					"xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+				),
+				(
+					"m/0H/1/2H",
+					"xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+					"xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+				),
+				(
+					"m/0H/1/2H/2",
+					"xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+					"xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+				),
+				(
+					"m/0H/1/2H/2/1000000000",
+					"xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+					"xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn test_bip32_vector_2() {
+		check_vector(
+			"fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+			&[
+				(
+					"m",
+					"xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+					"xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+				),
+				(
+					"m/0",
+					"xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+					"xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+				),
+				(
+					"m/0/2147483647H",
+					"xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+					"xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+				),
+				(
+					"m/0/2147483647H/1",
+					"xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+					"xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+				),
+				(
+					"m/0/2147483647H/1/2147483646H",
+					"xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+					"xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+				),
+				(
+					"m/0/2147483647H/1/2147483646H/2",
+					"xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+					"xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn test_bip32_vector_3() {
+		// retention of leading zeros
+		check_vector(
+			"4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be",
+			&[
+				(
+					"m",
+					"xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+					"xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+				),
+				(
+					"m/0H",
+					"xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+					"xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn test_bip32_vector_4() {
+		// retention of leading zeros during hardened derivation
+		check_vector(
+			"3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678",
+			&[
+				(
+					"m",
+					"xpub661MyMwAqRbcGczjuMoRm6dXaLDEhW1u34gKenbeYqAix21mdUKJyuyu5F1rzYGVxyL6tmgBUAEPrEz92mBXjByMRiJdba9wpnN37RLLAXa",
+					"xprv9s21ZrQH143K48vGoLGRPxgo2JNkJ3J3fqkirQC2zVdk5Dgd5w14S7fRDyHH4dWNHUgkvsvNDCkvAwcSHNAQwhwgNMgZhLtQC63zxwhQmRv",
+				),
+				(
+					"m/0H",
+					"xpub69AUMk3qDBi3uW1sXgjCmVjJ2G6WQoYSnNHyzkmdCHEhSZ4tBok37xfFEqHd2AddP56Tqp4o56AePAgCjYdvpW2PU2jbUPFKsav5ut6Ch1m",
+					"xprv9vB7xEWwNp9kh1wQRfCCQMnZUEG21LpbR9NPCNN1dwhiZkjjeGRnaALmPXCX7SgjFTiCTT6bXes17boXtjq3xLpcDjzEuGLQBM5ohqkao9G",
+				),
+				(
+					"m/0H/1H",
+					"xpub6BJA1jSqiukeaesWfxe6sNK9CCGaujFFSJLomWHprUL9DePQ4JDkM5d88n49sMGJxrhpjazuXYWdMf17C9T5XnxkopaeS7jGk1GyyVziaMt",
+					"xprv9xJocDuwtYCMNAo3Zw76WENQeAS6WGXQ55RCy7tDJ8oALr4FWkuVoHJeHVAcAqiZLE7Je3vZJHxspZdFHfnBEjHqU5hG1Jaj32dVoS6XLT1",
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn test_bip32_vector_5() {
+		// invalid extended keys
+		let public_keys = [
+			// pubkey version / prvkey mismatch
+			(
+				"xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm",
+				Error::InvalidPublic,
+			),
+			// invalid pubkey prefix 04
+			(
+				"xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Txnt3siSujt9RCVYsx4qHZGc62TG4McvMGcAUjeuwZdduYEvFn",
+				Error::InvalidPublic,
+			),
+			// invalid pubkey prefix 01
+			(
+				"xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6N8ZMMXctdiCjxTNq964yKkwrkBJJwpzZS4HS2fxvyYUA4q2Xe4",
+				Error::InvalidPublic,
+			),
+			// zero depth with non-zero parent fingerprint
+			(
+				"xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ",
+				Error::InvalidExtendedKey,
+			),
+			// zero depth with non-zero index
+			(
+				"xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8",
+				Error::InvalidExtendedKey,
+			),
+			// unknown extended key version
+			(
+				"DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHGMQzT7ayAmfo4z3gY5KfbrZWZ6St24UVf2Qgo6oujFktLHdHY4",
+				Error::InvalidNetwork,
+			),
+			// invalid pubkey 020000000000000000000000000000000000000000000000000000000000000007
+			(
+				"xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Q5JXayek4PRsn35jii4veMimro1xefsM58PgBMrvdYre8QyULY",
+				Error::InvalidPublic,
+			),
+		];
+		for (key, error) in &public_keys {
+			assert_eq!(key.parse::<ExtendedPublic>().as_ref(), Err(error), "{}", key);
+		}
+
+		let private_keys = [
+			// prvkey version / pubkey mismatch
+			(
+				"xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGTQQD3dC4H2D5GBj7vWvSQaaBv5cxi9gafk7NF3pnBju6dwKvH",
+				Error::InvalidExtendedKey,
+			),
+			// invalid prvkey prefix 04
+			(
+				"xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGpWnsj83BHtEy5Zt8CcDr1UiRXuWCmTQLxEK9vbz5gPstX92JQ",
+				Error::InvalidExtendedKey,
+			),
+			// invalid prvkey prefix 01
+			(
+				"xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fEQ3Qen6J",
+				Error::InvalidExtendedKey,
+			),
+			// zero depth with non-zero parent fingerprint
+			(
+				"xprv9s2SPatNQ9Vc6GTbVMFPFo7jsaZySyzk7L8n2uqKXJen3KUmvQNTuLh3fhZMBoG3G4ZW1N2kZuHEPY53qmbZzCHshoQnNf4GvELZfqTUrcv",
+				Error::InvalidExtendedKey,
+			),
+			// zero depth with non-zero index
+			(
+				"xprv9s21ZrQH4r4TsiLvyLXqM9P7k1K3EYhA1kkD6xuquB5i39AU8KF42acDyL3qsDbU9NmZn6MsGSUYZEsuoePmjzsB3eFKSUEh3Gu1N3cqVUN",
+				Error::InvalidExtendedKey,
+			),
+			// unknown extended key version
+			(
+				"DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHPmHJiEDXkTiJTVV9rHEBUem2mwVbbNfvT2MTcAqj3nesx8uBf9",
+				Error::InvalidNetwork,
+			),
+			// private key 0 not in 1..n-1
+			(
+				"xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx",
+				Error::InvalidSecret,
+			),
+			// private key n not in 1..n-1
+			(
+				"xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD5SDKr24z3aiUvKr9bJpdrcLg1y3G",
+				Error::InvalidSecret,
+			),
+			// invalid checksum
+			(
+				"xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL",
+				Error::InvalidChecksum,
+			),
+		];
+		for (key, error) in &private_keys {
+			assert_eq!(key.parse::<ExtendedPrivate>().as_ref(), Err(error), "{}", key);
+		}
+	}
+
+	#[test]
+	fn test_slip132_key_from_mnemonic() {
+		// BIP84 test vector
+		let seed = mnemonic_to_seed(
+			"abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+			"",
+		);
+		let mut master = ExtendedPrivate::from_seed(&seed, Network::Mainnet).unwrap();
+		master.key_type = KeyType::P2wpkh;
+		let account = master.derive(&"m/84'/0'/0'".parse().unwrap()).unwrap();
+		assert_eq!(
+			account.to_string(),
+			"zprvAdG4iTXWBoARxkkzNpNh8r6Qag3irQB8PzEMkAFeTRXxHpbF9z4QgEvBRmfvqWvGp42t42nvgGpNgYSJA9iefm1yYNZKEm7z6qUWCroSQnE"
+		);
+		assert_eq!(
+			account.extended_public().to_string(),
+			"zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs"
+		);
+		assert_eq!(master.fingerprint().to_hex(), "73c5da0a");
+
+		let parsed: ExtendedPublic = account.extended_public().to_string().parse().unwrap();
+		assert_eq!(parsed.key_type, KeyType::P2wpkh);
+		assert_eq!(parsed.network, Network::Mainnet);
+	}
+
+	#[test]
+	fn test_extended_key_from_str_errors() {
+		let xprv = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+		let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+		assert_eq!(xprv.parse::<ExtendedPublic>(), Err(Error::InvalidExtendedKey));
+		assert_eq!(xpub.parse::<ExtendedPrivate>(), Err(Error::InvalidExtendedKey));
+		assert_eq!(xpub[..xpub.len() - 1].parse::<ExtendedPublic>(), Err(Error::InvalidExtendedKey));
+		assert_eq!(
+			format!("{}9", &xpub[..xpub.len() - 1]).parse::<ExtendedPublic>(),
+			Err(Error::InvalidChecksum)
+		);
+		assert_eq!(ExtendedPrivate::from_seed(&[0; 15], Network::Mainnet), Err(Error::InvalidSeed));
+	}
+
+	#[test]
+	fn test_derivation_path() {
+		let path: DerivationPath = "m/44'/0h/0H/1/2".parse().unwrap();
+		assert_eq!(path.to_string(), "m/44'/0'/0'/1/2");
+		assert_eq!(path.as_slice()[0], ChildNumber::hardened(44).unwrap());
+		assert_eq!(path.as_slice()[3], ChildNumber::normal(1).unwrap());
+		assert_eq!("44'/0'/0'/1/2".parse::<DerivationPath>().unwrap(), path);
+		assert!("m".parse::<DerivationPath>().unwrap().is_master());
+		assert_eq!(
+			"m/0'"
+				.parse::<DerivationPath>()
+				.unwrap()
+				.child(ChildNumber::normal(1).unwrap())
+				.to_string(),
+			"m/0'/1"
+		);
+		assert_eq!(ChildNumber::from(HARDENED + 5), ChildNumber::hardened(5).unwrap());
+
+		for invalid in &["", "m/", "m/1//2", "m/x", "m/+1", "m/2147483648", "m/1''", "n/1", "/1"] {
+			assert_eq!(invalid.parse::<DerivationPath>(), Err(Error::InvalidDerivationPath), "{}", invalid);
+		}
+	}
+}
@@ -0,0 +1,51 @@
+//! BIP39 mnemonic seeds.
+
+use bitcrypto::pbkdf2_hmac_sha512;
+use unicode_normalization::UnicodeNormalization;
+
+/// Number of PBKDF2 iterations, deriving the seed from the mnemonic
+const SEED_ITERATIONS: u32 = 2048;
+
+/// 64 bytes long seed of the master extended key
+pub type Seed = [u8; 64];
+
+/// Derives seed from the mnemonic sentence and optional passphrase.
+///
+/// Both mnemonic and passphrase are NFKD-normalized. Mnemonic checksum is not verified, so any
+/// sentence produces a seed.
+pub fn mnemonic_to_seed(mnemonic: &str, passphrase: &str) -> Seed {
+	let mnemonic: String = mnemonic.nfkd().collect();
+	let salt: String = "mnemonic".chars().chain(passphrase.chars()).nfkd().collect();
+	let mut seed = [0u8; 64];
+	pbkdf2_hmac_sha512(mnemonic.as_bytes(), salt.as_bytes(), SEED_ITERATIONS, &mut seed);
+	seed
+}
+
+#[cfg(test)]
+mod tests {
+	use super::mnemonic_to_seed;
+	use bitcrypto::ToHex;
+
+	#[test]
+	fn test_mnemonic_to_seed() {
+		let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+		assert_eq!(
+			mnemonic_to_seed(mnemonic, "TREZOR").to_hex(),
+			"c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+		);
+		assert_eq!(
+			mnemonic_to_seed(mnemonic, "").to_hex(),
+			"5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+		);
+	}
+
+	#[test]
+	fn test_mnemonic_to_seed_normalizes_unicode() {
+		// japanese test vector, ideographic spaces and composed characters are normalized
+		let mnemonic = "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あおぞら";
+		assert_eq!(
+			mnemonic_to_seed(mnemonic, "㍍ガバヴァぱばぐゞちぢ十人十色").to_hex(),
+			"a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55"
+		);
+	}
+}
@@ -12,6 +12,11 @@ pub enum Error {
 	InvalidPrivate,
 	InvalidAddress,
 	FailedKeyGeneration,
+	InvalidSeed,
+	InvalidExtendedKey,
+	InvalidDerivationPath,
+	InvalidDerivation,
+	HardenedDerivation,
 }
 
 impl fmt::Display for Error {
@@ -26,6 +31,11 @@ impl fmt::Display for Error {
 			Error::InvalidPrivate => "Invalid Private",
 			Error::InvalidAddress => "Invalid Address",
 			Error::FailedKeyGeneration => "Key generation failed",
+			Error::InvalidSeed => "Invalid Seed",
+			Error::InvalidExtendedKey => "Invalid Extended Key",
+			Error::InvalidDerivationPath => "Invalid Derivation Path",
+			Error::InvalidDerivation => "Key derivation failed",
+			Error::HardenedDerivation => "Hardened key can't be derived from public key",
 		};
 
 		msg.fmt(f)
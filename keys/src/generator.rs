@@ -1,7 +1,7 @@
 use crate::network::Network;
 use crate::{Error, KeyPair, SECP256K1};
 use secp256k1::rand::rngs::StdRng;
-use secp256k1::rand::FromEntropy;
+use secp256k1::rand::SeedableRng;
 
 pub trait Generator {
 	fn generate(&self) -> Result<KeyPair, Error>;
@@ -2,7 +2,7 @@
 
 use crate::network::Network;
 use crate::{Address, Error, Private, Public, Type, SECP256K1};
-use secp256k1::key;
+use secp256k1::{PublicKey, SecretKey};
 use std::convert::TryInto;
 use std::fmt;
 
@@ -36,8 +36,8 @@ impl KeyPair {
 
 	pub fn from_private(private: Private) -> Result<KeyPair, Error> {
 		let context = &SECP256K1;
-		let s: key::SecretKey = key::SecretKey::from_slice(&private.secret)?;
-		let pub_key = key::PublicKey::from_secret_key(context, &s);
+		let s: SecretKey = SecretKey::from_slice(&private.secret)?;
+		let pub_key = PublicKey::from_secret_key(context, &s);
 
 		let public = if private.compressed {
 			let serialized = pub_key.serialize();
@@ -52,13 +52,13 @@ impl KeyPair {
 		Ok(keypair)
 	}
 
-	pub fn from_keypair(sec: key::SecretKey, public: key::PublicKey, network: Network) -> Self {
+	pub fn from_keypair(sec: SecretKey, public: PublicKey, network: Network) -> Self {
 		let serialized = public.serialize_uncompressed();
 
 		KeyPair {
 			private: Private {
 				network,
-				secret: sec[0..32].try_into().unwrap(),
+				secret: sec.secret_bytes()[..].try_into().unwrap(),
 				compressed: false,
 			},
 			public: Public::Normal(serialized),
@@ -69,7 +69,7 @@ impl KeyPair {
 		Address {
 			kind: Type::P2PKH,
 			network: self.private.network,
-			hash: self.public.address_hash(),
+			hash: self.public.address_hash().into(),
 		}
 	}
 }
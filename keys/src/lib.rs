@@ -7,6 +7,9 @@ extern crate primitives;
 extern crate secp256k1;
 
 mod address;
+mod bech32;
+pub mod bip32;
+pub mod bip39;
 mod display;
 mod error;
 pub mod generator;
@@ -15,10 +18,13 @@ mod network;
 mod private;
 mod public;
 mod signature;
+mod xonly;
 
 pub use primitives::{bytes, hash};
 
-pub use address::{Address, Type};
+pub use address::{Address, AddressPayload, Type};
+pub use bip32::{ChildNumber, DerivationPath, ExtendedPrivate, ExtendedPublic};
+pub use bip39::mnemonic_to_seed;
 use bitcrypto::{HASH160, SHA256D};
 pub use display::DisplayLayout;
 pub use error::Error;
@@ -27,6 +33,7 @@ pub use network::Network;
 pub use private::Private;
 pub use public::Public;
 pub use signature::{CompactSignature, Signature};
+pub use xonly::XOnlyPublic;
 
 /// 20 bytes long hash derived from public `ripemd160(sha256(public))`
 pub type AddressHash = HASH160;
@@ -1,12 +1,11 @@
 //! Secret with additional network identifier and format type
 
 use crate::network::Network;
-use crate::{CompactSignature, DisplayLayout, Error, Message, Secret, Signature, SECP256K1};
+use crate::{CompactSignature, DisplayLayout, Error, Message, Secret, Signature, XOnlyPublic, SECP256K1};
 use base58::{FromBase58, ToBase58};
-use bitcrypto::ToHex;
+use bitcrypto::{ToHex, SHA256};
 use primitives::checksum::Checksum;
-use secp256k1::key;
-use secp256k1::Message as SecpMessage;
+use secp256k1::{Error as SecpError, Keypair, Message as SecpMessage, PublicKey, Scalar, SecretKey, XOnlyPublicKey};
 use std::fmt;
 use std::str::FromStr;
 
@@ -24,9 +23,9 @@ pub struct Private {
 impl Private {
 	pub fn sign(&self, message: &Message) -> Result<Signature, Error> {
 		let context = &SECP256K1;
-		let secret = key::SecretKey::from_slice(&self.secret)?;
-		let message = SecpMessage::from_slice(message as &[u8])?;
-		let signature = context.sign(&message, &secret);
+		let secret = SecretKey::from_slice(&self.secret)?;
+		let message = SecpMessage::from_digest_slice(message as &[u8])?;
+		let signature = context.sign_ecdsa(&message, &secret);
 		let data = signature.serialize_der();
 
 		Ok(data.as_ref().into())
@@ -34,9 +33,9 @@ impl Private {
 
 	pub fn sign_compact(&self, message: &Message) -> Result<CompactSignature, Error> {
 		let context = &SECP256K1;
-		let secret = key::SecretKey::from_slice(&self.secret)?;
-		let message = SecpMessage::from_slice(message as &[u8])?;
-		let signature = context.sign_recoverable(&message, &secret);
+		let secret = SecretKey::from_slice(&self.secret)?;
+		let message = SecpMessage::from_digest_slice(message as &[u8])?;
+		let signature = context.sign_ecdsa_recoverable(&message, &secret);
 		let (recovery_id, data) = signature.serialize_compact();
 		let recovery_id = recovery_id.to_i32() as u8;
 		let mut signature = [0; 65];
@@ -48,6 +47,45 @@ impl Private {
 		}
 		Ok(CompactSignature(signature))
 	}
+
+	/// Creates BIP340 signature of the message.
+	pub fn sign_schnorr(&self, message: &SHA256) -> Result<Signature, Error> {
+		let context = &SECP256K1;
+		let keypair = Keypair::from_seckey_slice(context, &self.secret)?;
+		let message = SecpMessage::from_digest_slice(message as &[u8])?;
+		let signature = context.sign_schnorr_no_aux_rand(&message, &keypair);
+		Ok(signature.serialize().to_vec().into())
+	}
+
+	/// Returns x-only public key of this secret.
+	pub fn xonly_public(&self) -> Result<XOnlyPublic, Error> {
+		let context = &SECP256K1;
+		let keypair = Keypair::from_seckey_slice(context, &self.secret)?;
+		XOnlyPublic::from_slice(&XOnlyPublicKey::from_keypair(&keypair).0.serialize())
+	}
+
+	/// Returns secret matching `XOnlyPublic::tweak_add` of this secret's x-only public key.
+	pub fn tweak_xonly(&self, tweak: &SHA256) -> Result<Private, Error> {
+		let context = &SECP256K1;
+		let mut secret = SecretKey::from_slice(&self.secret)?;
+		let public = PublicKey::from_secret_key(context, &secret);
+		// x-only keys always have even Y, so the secret of a key with odd Y must be negated
+		if public.serialize()[0] == 0x03 {
+			secret = secret.negate();
+		}
+		let mut tweak_bytes = [0u8; 32];
+		tweak_bytes.copy_from_slice(tweak as &[u8]);
+		let tweak = Scalar::from_be_bytes(tweak_bytes).map_err(|_| SecpError::InvalidTweak)?;
+		let secret = secret.add_tweak(&tweak)?;
+
+		let mut tweaked = Secret::default();
+		tweaked.copy_from_slice(&secret.secret_bytes());
+		Ok(Private {
+			network: self.network,
+			secret: tweaked,
+			compressed: self.compressed,
+		})
+	}
 }
 
 impl DisplayLayout for Private {
@@ -57,7 +95,7 @@ impl DisplayLayout for Private {
 		let mut result = vec![];
 		let network_byte = match self.network {
 			Network::Mainnet => 128,
-			Network::Testnet => 239,
+			Network::Testnet | Network::Regtest => 239,
 		};
 
 		result.push(network_byte);
@@ -1,11 +1,11 @@
 use crate::{AddressHash, CompactSignature, Error, Message, Signature, SECP256K1};
 use bitcrypto::{dhash160, ToHex};
-use secp256k1::key;
-use secp256k1::recovery::{RecoverableSignature, RecoveryId};
-use secp256k1::{Error as SecpError, Message as SecpMessage, Signature as SecpSignature};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId, Signature as SecpSignature};
+use secp256k1::{Error as SecpError, Message as SecpMessage, PublicKey};
 use std::{fmt, ops};
 
 /// Secret public key
+#[derive(Clone)]
 pub enum Public {
 	/// Normal version of public key (0x04 byte + X and Y coordinate on curve)
 	Normal([u8; 65]),
@@ -36,11 +36,11 @@ impl Public {
 
 	pub fn verify(&self, message: &Message, signature: &Signature) -> Result<bool, Error> {
 		let context = &SECP256K1;
-		let public = key::PublicKey::from_slice(self)?;
+		let public = PublicKey::from_slice(self)?;
 		let mut signature = SecpSignature::from_der_lax(signature)?;
 		signature.normalize_s();
-		let message = SecpMessage::from_slice(message)?;
-		match context.verify(&message, &signature, &public) {
+		let message = SecpMessage::from_digest_slice(message)?;
+		match context.verify_ecdsa(&message, &signature, &public) {
 			Ok(_) => Ok(true),
 			Err(SecpError::IncorrectSignature) => Ok(false),
 			Err(x) => Err(x.into()),
@@ -53,8 +53,8 @@ impl Public {
 		let compressed = (signature[0] - 27) & 4 != 0;
 		let recovery_id = RecoveryId::from_i32(recovery_id as i32)?;
 		let signature = RecoverableSignature::from_compact(&signature[1..65], recovery_id)?;
-		let message = SecpMessage::from_slice(message)?;
-		let pubkey = context.recover(&message, &signature)?;
+		let message = SecpMessage::from_digest_slice(message)?;
+		let pubkey = context.recover_ecdsa(&message, &signature)?;
 
 		let public = if compressed {
 			let serialized = pubkey.serialize();
@@ -0,0 +1,137 @@
+//! X-only public key (BIP340)
+
+use crate::{Error, SECP256K1};
+use bitcrypto::{ToHex, SHA256};
+use secp256k1::schnorr::Signature as SchnorrSignature;
+use secp256k1::{Error as SecpError, Message as SecpMessage, Parity, Scalar, XOnlyPublicKey};
+use std::{fmt, ops};
+
+/// 32 bytes long public key, represented by the X coordinate of a point with even Y
+#[derive(Clone, Copy, PartialEq)]
+pub struct XOnlyPublic([u8; 32]);
+
+impl XOnlyPublic {
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if data.len() != 32 {
+			return Err(Error::InvalidPublic);
+		}
+
+		let mut pk = [0; 32];
+		pk.copy_from_slice(data);
+		Ok(XOnlyPublic(pk))
+	}
+
+	/// Verifies BIP340 signature. Signature must be exactly 64 bytes long.
+	pub fn verify_schnorr(&self, message: &SHA256, signature: &[u8]) -> Result<bool, Error> {
+		let context = &SECP256K1;
+		let public = XOnlyPublicKey::from_slice(&self.0)?;
+		let signature = SchnorrSignature::from_slice(signature)?;
+		let message = SecpMessage::from_digest_slice(message as &[u8])?;
+		match context.verify_schnorr(&signature, &message, &public) {
+			Ok(_) => Ok(true),
+			Err(SecpError::InvalidSignature) | Err(SecpError::IncorrectSignature) => Ok(false),
+			Err(x) => Err(x.into()),
+		}
+	}
+
+	/// Computes `self + tweak * G`. Returns the tweaked key and parity of its Y coordinate.
+	pub fn tweak_add(&self, tweak: &SHA256) -> Result<(XOnlyPublic, bool), Error> {
+		let context = &SECP256K1;
+		let public = XOnlyPublicKey::from_slice(&self.0)?;
+		let (public, parity) = public.add_tweak(context, &tweak_scalar(tweak)?)?;
+		Ok((XOnlyPublic(public.serialize()), parity == Parity::Odd))
+	}
+
+	/// Checks that `tweaked` is the result of tweaking this key with `tweak`.
+	pub fn check_tweak(&self, tweaked: &XOnlyPublic, parity: bool, tweak: &SHA256) -> bool {
+		let context = &SECP256K1;
+		let internal = match XOnlyPublicKey::from_slice(&self.0) {
+			Ok(public) => public,
+			Err(_) => return false,
+		};
+		let tweaked = match XOnlyPublicKey::from_slice(&tweaked.0) {
+			Ok(public) => public,
+			Err(_) => return false,
+		};
+		let tweak = match tweak_scalar(tweak) {
+			Ok(tweak) => tweak,
+			Err(_) => return false,
+		};
+		let parity = if parity { Parity::Odd } else { Parity::Even };
+		internal.tweak_add_check(context, &tweaked, parity, tweak)
+	}
+}
+
+fn tweak_scalar(tweak: &SHA256) -> Result<Scalar, SecpError> {
+	let mut tweak_bytes = [0u8; 32];
+	tweak_bytes.copy_from_slice(tweak as &[u8]);
+	Scalar::from_be_bytes(tweak_bytes).map_err(|_| SecpError::InvalidTweak)
+}
+
+impl ops::Deref for XOnlyPublic {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl fmt::Debug for XOnlyPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.to_hex().fmt(f)
+	}
+}
+
+impl fmt::Display for XOnlyPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.to_hex().fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::XOnlyPublic;
+	use crate::Private;
+	use bitcrypto::{sha256, FromHex, Hash, SHA256};
+
+	fn from_hex(s: &str) -> Vec<u8> {
+		FromHex::from_hex(s).unwrap()
+	}
+
+	#[test]
+	fn test_verify_schnorr_bip340_vectors() {
+		// test vectors 0, 1 and 5 from BIP340
+		let public = XOnlyPublic::from_slice(&from_hex("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9")).unwrap();
+		let message = SHA256::from_slice(&[0u8; 32]).unwrap();
+		let signature = from_hex("E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0");
+		assert_eq!(public.verify_schnorr(&message, &signature), Ok(true));
+
+		let public = XOnlyPublic::from_slice(&from_hex("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659")).unwrap();
+		let message = SHA256::from_slice(&from_hex("243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89")).unwrap();
+		let signature = from_hex("6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A");
+		assert_eq!(public.verify_schnorr(&message, &signature), Ok(true));
+		assert_eq!(public.verify_schnorr(&SHA256::default(), &signature), Ok(false));
+
+		// public key not on the curve
+		let public = XOnlyPublic::from_slice(&from_hex("EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34")).unwrap();
+		let signature = from_hex("6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B");
+		assert!(public.verify_schnorr(&message, &signature).is_err());
+	}
+
+	#[test]
+	fn test_sign_schnorr_and_tweak() {
+		let private: Private = "KxDQjJwvLdNNGhsipGgmceWaPjRndZuaQB9B2tgdHsw5sQ8Rtqje".into();
+		let internal = private.xonly_public().unwrap();
+		let tweak = sha256(b"tweak");
+		let (output, parity) = internal.tweak_add(&tweak).unwrap();
+		assert!(internal.check_tweak(&output, parity, &tweak));
+		assert!(!internal.check_tweak(&output, !parity, &tweak));
+
+		let message = sha256(b"message");
+		let tweaked = private.tweak_xonly(&tweak).unwrap();
+		assert_eq!(tweaked.xonly_public().unwrap(), output);
+		let signature = tweaked.sign_schnorr(&message).unwrap();
+		assert_eq!(output.verify_schnorr(&message, &signature), Ok(true));
+		assert_eq!(internal.verify_schnorr(&message, &signature), Ok(false));
+	}
+}
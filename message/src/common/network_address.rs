@@ -0,0 +1,336 @@
+use crypto::sha3_256;
+use ser::{CompactInteger, Error as ReaderError, Reader, Serializable, Stream};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::{fmt, io, str};
+
+/// Maximum length of the address in `addrv2` message
+pub const ADDRV2_MAX_ADDRESS_LEN: usize = 512;
+
+const NETWORK_IPV4: u8 = 1;
+const NETWORK_IPV6: u8 = 2;
+const NETWORK_TORV3: u8 = 4;
+const NETWORK_I2P: u8 = 5;
+const NETWORK_CJDNS: u8 = 6;
+
+const TORV3_VERSION: u8 = 3;
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Address of the node in one of networks, supported by `addrv2` message (BIP155)
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum NetworkAddress {
+	IpV4(Ipv4Addr),
+	IpV6(Ipv6Addr),
+	/// Tor v3 hidden service, identified by its ed25519 public key
+	TorV3([u8; 32]),
+	/// I2P destination, identified by SHA256 of the destination
+	I2p([u8; 32]),
+	/// CJDNS address from the fc00::/8 range
+	Cjdns(Ipv6Addr),
+}
+
+/// Address is neither IP address, nor valid onion or i2p address
+#[derive(Debug, PartialEq)]
+pub struct InvalidNetworkAddress;
+
+impl NetworkAddress {
+	/// Returns network address from its `addrv2` network id and bytes. Returns None for
+	/// unknown networks, which should be ignored
+	pub fn from_network_id(network: u8, address: &[u8]) -> Result<Option<Self>, ReaderError> {
+		let expected_len = match network {
+			NETWORK_IPV4 => 4,
+			NETWORK_IPV6 | NETWORK_CJDNS => 16,
+			NETWORK_TORV3 | NETWORK_I2P => 32,
+			_ => return Ok(None),
+		};
+		if address.len() != expected_len {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut bytes = [0u8; 32];
+		bytes[..expected_len].copy_from_slice(address);
+		let mut ipv6 = [0u8; 16];
+		ipv6.copy_from_slice(&bytes[..16]);
+		let address = match network {
+			NETWORK_IPV4 => NetworkAddress::IpV4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+			NETWORK_IPV6 => NetworkAddress::IpV6(Ipv6Addr::from(ipv6)),
+			NETWORK_TORV3 => NetworkAddress::TorV3(bytes),
+			NETWORK_I2P => NetworkAddress::I2p(bytes),
+			_ => NetworkAddress::Cjdns(Ipv6Addr::from(ipv6)),
+		};
+		Ok(Some(address))
+	}
+
+	/// Reads network address in `addrv2` format. Returns None for unknown networks
+	pub fn deserialize_v2<T>(reader: &mut Reader<T>) -> Result<Option<Self>, ReaderError>
+	where
+		T: io::Read,
+	{
+		let network: u8 = reader.read()?;
+		let len: usize = reader.read::<CompactInteger>()?.into();
+		if len > ADDRV2_MAX_ADDRESS_LEN {
+			return Err(ReaderError::MalformedData);
+		}
+		let mut address = vec![0u8; len];
+		reader.read_slice(&mut address)?;
+		NetworkAddress::from_network_id(network, &address)
+	}
+
+	/// Returns `addrv2` network id
+	pub fn network_id(&self) -> u8 {
+		match *self {
+			NetworkAddress::IpV4(_) => NETWORK_IPV4,
+			NetworkAddress::IpV6(_) => NETWORK_IPV6,
+			NetworkAddress::TorV3(_) => NETWORK_TORV3,
+			NetworkAddress::I2p(_) => NETWORK_I2P,
+			NetworkAddress::Cjdns(_) => NETWORK_CJDNS,
+		}
+	}
+
+	/// Returns address bytes as they are serialized in `addrv2` message
+	pub fn to_vec(&self) -> Vec<u8> {
+		match *self {
+			NetworkAddress::IpV4(ip) => ip.octets().to_vec(),
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => ip.octets().to_vec(),
+			NetworkAddress::TorV3(key) | NetworkAddress::I2p(key) => key.to_vec(),
+		}
+	}
+
+	/// Returns IP address of the node, if it can be connected to directly
+	pub fn ip(&self) -> Option<IpAddr> {
+		match *self {
+			NetworkAddress::IpV4(ip) => Some(IpAddr::V4(ip)),
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => Some(IpAddr::V6(ip)),
+			NetworkAddress::TorV3(_) | NetworkAddress::I2p(_) => None,
+		}
+	}
+
+	/// Returns true if address can be relayed in the legacy `addr` message
+	pub fn is_addr_v1_compatible(&self) -> bool {
+		match *self {
+			NetworkAddress::IpV4(_) | NetworkAddress::IpV6(_) => true,
+			_ => false,
+		}
+	}
+}
+
+impl From<IpAddr> for NetworkAddress {
+	fn from(ip: IpAddr) -> Self {
+		match ip {
+			IpAddr::V4(ip) => NetworkAddress::IpV4(ip),
+			IpAddr::V6(ip) => {
+				let segments = ip.segments();
+				if segments[..6] == [0, 0, 0, 0, 0, 0xffff] {
+					let octets = ip.octets();
+					NetworkAddress::IpV4(Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]))
+				} else if segments[0] >> 8 == 0xfc {
+					NetworkAddress::Cjdns(ip)
+				} else {
+					NetworkAddress::IpV6(ip)
+				}
+			}
+		}
+	}
+}
+
+impl fmt::Display for NetworkAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			NetworkAddress::IpV4(ip) => ip.fmt(f),
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => ip.fmt(f),
+			NetworkAddress::TorV3(key) => {
+				let mut data = key.to_vec();
+				data.extend_from_slice(&torv3_checksum(&key));
+				data.push(TORV3_VERSION);
+				write!(f, "{}.onion", base32_encode(&data))
+			}
+			NetworkAddress::I2p(hash) => write!(f, "{}.b32.i2p", base32_encode(&hash)),
+		}
+	}
+}
+
+impl str::FromStr for NetworkAddress {
+	type Err = InvalidNetworkAddress;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(onion) = s.strip_suffix(".onion") {
+			let data = base32_decode(onion).ok_or(InvalidNetworkAddress)?;
+			if data.len() != 35 || data[34] != TORV3_VERSION {
+				return Err(InvalidNetworkAddress);
+			}
+			let mut key = [0u8; 32];
+			key.copy_from_slice(&data[..32]);
+			if data[32..34] != torv3_checksum(&key) {
+				return Err(InvalidNetworkAddress);
+			}
+			return Ok(NetworkAddress::TorV3(key));
+		}
+		if let Some(i2p) = s.strip_suffix(".b32.i2p") {
+			let data = base32_decode(i2p).ok_or(InvalidNetworkAddress)?;
+			if data.len() != 32 {
+				return Err(InvalidNetworkAddress);
+			}
+			let mut hash = [0u8; 32];
+			hash.copy_from_slice(&data);
+			return Ok(NetworkAddress::I2p(hash));
+		}
+		s.parse::<IpAddr>().map(Into::into).map_err(|_| InvalidNetworkAddress)
+	}
+}
+
+impl Serializable for NetworkAddress {
+	fn serialize(&self, stream: &mut Stream) {
+		let address = self.to_vec();
+		stream
+			.append(&self.network_id())
+			.append(&CompactInteger::from(address.len()))
+			.append_slice(&address);
+	}
+}
+
+/// Address and port of the node in any of networks, supported by `addrv2` message
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct NodeAddress {
+	pub address: NetworkAddress,
+	pub port: u16,
+}
+
+impl NodeAddress {
+	pub fn new(address: NetworkAddress, port: u16) -> Self {
+		NodeAddress { address, port }
+	}
+
+	/// Returns socket address of the node, if it can be connected to directly
+	pub fn socket_addr(&self) -> Option<SocketAddr> {
+		self.address.ip().map(|ip| SocketAddr::new(ip, self.port))
+	}
+}
+
+impl From<SocketAddr> for NodeAddress {
+	fn from(addr: SocketAddr) -> Self {
+		NodeAddress::new(addr.ip().into(), addr.port())
+	}
+}
+
+impl fmt::Display for NodeAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.address {
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => SocketAddrV6::new(ip, self.port, 0, 0).fmt(f),
+			ref address => write!(f, "{}:{}", address, self.port),
+		}
+	}
+}
+
+impl str::FromStr for NodeAddress {
+	type Err = InvalidNetworkAddress;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(addr) = s.parse::<SocketAddr>() {
+			return Ok(addr.into());
+		}
+		let separator = s.rfind(':').ok_or(InvalidNetworkAddress)?;
+		let port = s[separator + 1..].parse().map_err(|_| InvalidNetworkAddress)?;
+		Ok(NodeAddress::new(s[..separator].parse()?, port))
+	}
+}
+
+/// Checksum of the Tor v3 onion address (rend-spec-v3)
+fn torv3_checksum(key: &[u8; 32]) -> [u8; 2] {
+	let mut data = b".onion checksum".to_vec();
+	data.extend_from_slice(key);
+	data.push(TORV3_VERSION);
+	let hash = sha3_256(&data);
+	[hash[0], hash[1]]
+}
+
+/// Lowercase RFC 4648 base32 without padding
+fn base32_encode(data: &[u8]) -> String {
+	let mut result = String::with_capacity((data.len() * 8 + 4) / 5);
+	let mut buffer = 0u16;
+	let mut bits = 0;
+	for byte in data {
+		buffer = (buffer << 8) | u16::from(*byte);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+		}
+	}
+	if bits > 0 {
+		result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+	}
+	result
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+	let mut result = Vec::with_capacity(s.len() * 5 / 8);
+	let mut buffer = 0u16;
+	let mut bits = 0;
+	for c in s.bytes() {
+		let value = BASE32_ALPHABET.iter().position(|a| *a == c.to_ascii_lowercase())? as u16;
+		buffer = (buffer << 5) | value;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			result.push((buffer >> bits) as u8);
+		}
+	}
+	// leftover bits must be zero padding
+	if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+		return None;
+	}
+	Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{NetworkAddress, NodeAddress};
+	use ser::{serialize, Reader};
+
+	const ONION: &str = "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion";
+
+	#[test]
+	fn test_network_address_from_str() {
+		let onion: NetworkAddress = ONION.parse().unwrap();
+		assert_eq!(onion.network_id(), 4);
+		assert_eq!(onion.to_string(), ONION);
+		assert!("duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczae.onion"
+			.parse::<NetworkAddress>()
+			.is_err());
+
+		let i2p: NetworkAddress = "ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p".parse().unwrap();
+		assert_eq!(i2p.network_id(), 5);
+		assert_eq!(i2p.to_string(), "ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p");
+
+		assert_eq!("::ffff:a00:1".parse::<NetworkAddress>().unwrap(), "10.0.0.1".parse().unwrap());
+		assert_eq!("fc00::1".parse::<NetworkAddress>().unwrap().network_id(), 6);
+		assert_eq!("2001:db8::1".parse::<NetworkAddress>().unwrap().network_id(), 2);
+		assert!("example.com".parse::<NetworkAddress>().is_err());
+	}
+
+	#[test]
+	fn test_node_address_from_str() {
+		let addr: NodeAddress = format!("{}:8333", ONION).parse().unwrap();
+		assert_eq!(addr.port, 8333);
+		assert_eq!(addr.socket_addr(), None);
+		assert_eq!(addr.to_string(), format!("{}:8333", ONION));
+
+		let addr: NodeAddress = "[2001:db8::1]:8333".parse().unwrap();
+		assert_eq!(addr.socket_addr(), Some("[2001:db8::1]:8333".parse().unwrap()));
+		assert_eq!(addr.to_string(), "[2001:db8::1]:8333");
+		assert!(ONION.parse::<NodeAddress>().is_err());
+	}
+
+	#[test]
+	fn test_network_address_serialize() {
+		let address: NetworkAddress = "1.2.3.4".parse().unwrap();
+		assert_eq!(serialize(&address), "010401020304".into());
+		assert_eq!(
+			NetworkAddress::deserialize_v2(&mut Reader::new(&[1, 4, 1, 2, 3, 4])),
+			Ok(Some(address))
+		);
+		// unknown networks are skipped
+		assert_eq!(NetworkAddress::deserialize_v2(&mut Reader::new(&[0x20, 2, 1, 2])), Ok(None));
+		// known networks must have the exact length
+		assert!(NetworkAddress::deserialize_v2(&mut Reader::new(&[1, 3, 1, 2, 3])).is_err());
+	}
+}
@@ -59,6 +59,33 @@ impl Services {
 		self
 	}
 
+	pub fn compact_filters(&self) -> bool {
+		self.bit_at(6)
+	}
+
+	pub fn with_compact_filters(mut self, v: bool) -> Self {
+		self.set_bit(6, v);
+		self
+	}
+
+	pub fn network_limited(&self) -> bool {
+		self.bit_at(10)
+	}
+
+	pub fn with_network_limited(mut self, v: bool) -> Self {
+		self.set_bit(10, v);
+		self
+	}
+
+	pub fn p2p_v2(&self) -> bool {
+		self.bit_at(11)
+	}
+
+	pub fn with_p2p_v2(mut self, v: bool) -> Self {
+		self.set_bit(11, v);
+		self
+	}
+
 	pub fn includes(&self, other: &Self) -> bool {
 		self.0 & other.0 == other.0
 	}
@@ -92,4 +119,27 @@ mod test {
 		assert!(s1.includes(&s2));
 		assert!(!s2.includes(&s1));
 	}
+
+	#[test]
+	fn test_services_compact_filters() {
+		let services = Services::default().with_network(true).with_compact_filters(true);
+		assert!(services.compact_filters());
+		assert_eq!(u64::from(services), 0x41);
+		assert!(!services.with_compact_filters(false).compact_filters());
+	}
+
+	#[test]
+	fn test_services_network_limited() {
+		let services = Services::default().with_network_limited(true).with_witness(true);
+		assert!(services.network_limited());
+		assert!(!services.network());
+		assert_eq!(u64::from(services), 0x408);
+	}
+
+	#[test]
+	fn test_services_p2p_v2() {
+		let services = Services::default().with_network(true).with_p2p_v2(true);
+		assert!(services.p2p_v2());
+		assert_eq!(u64::from(services), 0x801);
+	}
 }
@@ -17,7 +17,7 @@ pub mod types;
 
 pub use primitives::{bytes, hash};
 
-pub use crate::message::{to_raw_message, Message, MessageHeader, Payload};
+pub use crate::message::{to_raw_message, Message, MessageHeader, Payload, MAX_PROTOCOL_MESSAGE_LENGTH};
 pub use crate::serialization::{deserialize_payload, serialize_payload};
 pub use common::{Command, Services};
 pub use error::{Error, MessageResult};
@@ -4,6 +4,9 @@ use crate::Error;
 use network::Magic;
 use ser::{Reader, Serializable, Stream};
 
+/// Maximum length of the message payload, accepted from peers.
+pub const MAX_PROTOCOL_MESSAGE_LENGTH: usize = 4_000_000;
+
 #[derive(Debug, PartialEq)]
 pub struct MessageHeader {
 	pub magic: Magic,
@@ -3,5 +3,5 @@ mod message_header;
 pub mod payload;
 
 pub use self::message::{to_raw_message, Message};
-pub use self::message_header::MessageHeader;
+pub use self::message_header::{MessageHeader, MAX_PROTOCOL_MESSAGE_LENGTH};
 pub use self::payload::Payload;
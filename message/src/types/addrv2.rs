@@ -0,0 +1,172 @@
+use crate::common::{NetAddress, NetworkAddress, NodeAddress, Port, Services};
+use crate::types::addr::AddressEntry;
+use crate::{Error, MessageResult, Payload};
+use ser::{CompactInteger, Reader, Stream};
+use std::io;
+use std::net::IpAddr;
+
+pub const ADDRV2_MAX_ADDRESSES_LEN: usize = 1_000;
+
+/// Addresses of nodes in any of networks, supported by BIP155
+#[derive(Debug, PartialEq)]
+pub struct AddrV2 {
+	pub addresses: Vec<AddressEntryV2>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddressEntryV2 {
+	pub timestamp: u32,
+	pub services: Services,
+	pub address: NodeAddress,
+}
+
+impl AddressEntryV2 {
+	/// Returns entry of the legacy `addr` message, if address can be represented there
+	pub fn to_address_entry(&self) -> Option<AddressEntry> {
+		if !self.address.address.is_addr_v1_compatible() {
+			return None;
+		}
+		// ipv4 addresses are sent as ipv4-mapped ipv6 addresses
+		let ip = match self
+			.address
+			.address
+			.ip()
+			.expect("addr v1 compatible addresses are ip addresses; qed")
+		{
+			IpAddr::V4(ip) => IpAddr::V6(ip.to_ipv6_mapped()),
+			ip => ip,
+		};
+		Some(AddressEntry {
+			timestamp: self.timestamp,
+			address: NetAddress {
+				services: self.services,
+				address: ip.into(),
+				port: self.address.port.into(),
+			},
+		})
+	}
+}
+
+impl From<AddressEntry> for AddressEntryV2 {
+	fn from(entry: AddressEntry) -> Self {
+		let ip: IpAddr = entry.address.address.into();
+		AddressEntryV2 {
+			timestamp: entry.timestamp,
+			services: entry.address.services,
+			address: NodeAddress::new(ip.into(), entry.address.port.into()),
+		}
+	}
+}
+
+impl Payload for AddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"addrv2"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let len: usize = reader.read::<CompactInteger>()?.into();
+		if len > ADDRV2_MAX_ADDRESSES_LEN {
+			return Err(Error::Deserialize);
+		}
+
+		let mut addresses = Vec::with_capacity(len);
+		for _ in 0..len {
+			let timestamp = reader.read()?;
+			let services: u64 = reader.read::<CompactInteger>()?.into();
+			let address = NetworkAddress::deserialize_v2(reader)?;
+			let port: Port = reader.read()?;
+			// addresses of unknown networks are ignored
+			if let Some(address) = address {
+				addresses.push(AddressEntryV2 {
+					timestamp,
+					services: services.into(),
+					address: NodeAddress::new(address, port.into()),
+				});
+			}
+		}
+
+		Ok(AddrV2 { addresses })
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&CompactInteger::from(self.addresses.len()));
+		for entry in &self.addresses {
+			stream
+				.append(&entry.timestamp)
+				.append(&CompactInteger::from(u64::from(entry.services)))
+				.append(&entry.address.address)
+				.append(&Port::from(entry.address.port));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AddrV2, AddressEntryV2};
+	use crate::bytes::Bytes;
+	use crate::common::{NetworkAddress, NodeAddress, Services};
+	use crate::{deserialize_payload, serialize_payload};
+
+	#[test]
+	fn test_addrv2_serialize() {
+		#[rustfmt::skip]
+		let expected: Bytes = concat!(
+			"02",
+			"e215104d", "01", "01", "04", "01020304", "208d",
+			"e215104d", "09", "04", "20", "1111111111111111111111111111111111111111111111111111111111111111", "208d",
+		).into();
+		let addrv2 = AddrV2 {
+			addresses: vec![
+				AddressEntryV2 {
+					timestamp: 0x4d1015e2,
+					services: Services::default().with_network(true),
+					address: "1.2.3.4:8333".parse().unwrap(),
+				},
+				AddressEntryV2 {
+					timestamp: 0x4d1015e2,
+					services: Services::default().with_network(true).with_witness(true),
+					address: NodeAddress::new(NetworkAddress::TorV3([0x11; 32]), 8333),
+				},
+			],
+		};
+
+		assert_eq!(serialize_payload(&addrv2, 0), Ok(expected.clone()));
+		assert_eq!(deserialize_payload::<AddrV2>(&expected, 0), Ok(addrv2));
+	}
+
+	#[test]
+	fn test_addrv2_deserialize_unknown_network() {
+		// torv2 address is skipped
+		let raw: Bytes = "01e215104d01030a11111111111111111111208d".into();
+		assert_eq!(deserialize_payload::<AddrV2>(&raw, 0), Ok(AddrV2 { addresses: vec![] }));
+		// ipv4 address of invalid length
+		let raw: Bytes = "01e215104d010103010203208d".into();
+		assert!(deserialize_payload::<AddrV2>(&raw, 0).is_err());
+	}
+
+	#[test]
+	fn test_address_entry_conversion() {
+		let entry = AddressEntryV2 {
+			timestamp: 0x4d1015e2,
+			services: Services::default().with_network(true),
+			address: "10.0.0.1:8333".parse().unwrap(),
+		};
+		let v1 = entry.to_address_entry().unwrap();
+		assert_eq!(v1.address, "010000000000000000000000000000000000ffff0a000001208d".into());
+		assert_eq!(AddressEntryV2::from(v1), entry);
+
+		let onion = AddressEntryV2 {
+			address: NodeAddress::new(NetworkAddress::TorV3([0x11; 32]), 8333),
+			..entry
+		};
+		assert_eq!(onion.to_address_entry(), None);
+	}
+}
@@ -0,0 +1,42 @@
+use crate::{MessageResult, Payload};
+use bitcrypto::SHA256D;
+use ser::{Reader, Stream};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+pub struct CFCheckpt {
+	pub filter_type: u8,
+	pub stop_hash: SHA256D,
+	pub filter_headers: Vec<SHA256D>,
+}
+
+impl Payload for CFCheckpt {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"cfcheckpt"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let cfcheckpt = CFCheckpt {
+			filter_type: reader.read()?,
+			stop_hash: reader.read()?,
+			filter_headers: reader.read_list()?,
+		};
+
+		Ok(cfcheckpt)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.filter_type)
+			.append(&self.stop_hash)
+			.append_list::<SHA256D, SHA256D>(&self.filter_headers);
+		Ok(())
+	}
+}
@@ -0,0 +1,45 @@
+use crate::{MessageResult, Payload};
+use bitcrypto::SHA256D;
+use ser::{Reader, Stream};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+pub struct CFHeaders {
+	pub filter_type: u8,
+	pub stop_hash: SHA256D,
+	pub previous_filter_header: SHA256D,
+	pub filter_hashes: Vec<SHA256D>,
+}
+
+impl Payload for CFHeaders {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"cfheaders"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let cfheaders = CFHeaders {
+			filter_type: reader.read()?,
+			stop_hash: reader.read()?,
+			previous_filter_header: reader.read()?,
+			filter_hashes: reader.read_list_max(2000)?,
+		};
+
+		Ok(cfheaders)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.filter_type)
+			.append(&self.stop_hash)
+			.append(&self.previous_filter_header)
+			.append_list::<SHA256D, SHA256D>(&self.filter_hashes);
+		Ok(())
+	}
+}
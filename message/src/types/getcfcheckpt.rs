@@ -0,0 +1,40 @@
+use crate::{MessageResult, Payload};
+use bitcrypto::SHA256D;
+use ser::{Reader, Stream};
+use std::io;
+
+/// Distance between filter headers in the `cfcheckpt` message
+pub const CFCHECKPT_INTERVAL: u32 = 1_000;
+
+#[derive(Debug, PartialEq)]
+pub struct GetCFCheckpt {
+	pub filter_type: u8,
+	pub stop_hash: SHA256D,
+}
+
+impl Payload for GetCFCheckpt {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"getcfcheckpt"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let get_cfcheckpt = GetCFCheckpt {
+			filter_type: reader.read()?,
+			stop_hash: reader.read()?,
+		};
+
+		Ok(get_cfcheckpt)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.filter_type).append(&self.stop_hash);
+		Ok(())
+	}
+}
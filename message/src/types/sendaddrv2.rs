@@ -0,0 +1,28 @@
+use crate::{MessageResult, Payload};
+use ser::{Reader, Stream};
+use std::io;
+
+/// Signals support of `addrv2` message (BIP155). Must be sent before `verack`
+#[derive(Debug, PartialEq)]
+pub struct SendAddrV2;
+
+impl Payload for SendAddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sendaddrv2"
+	}
+
+	fn deserialize_payload<T>(_reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		Ok(SendAddrV2)
+	}
+
+	fn serialize_payload(&self, _stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		Ok(())
+	}
+}
@@ -1,16 +1,26 @@
 use crate::block_template::BlockTemplate;
 use crate::memory_pool::{Entry, MemoryPool, OrderingStrategy};
-use bitcrypto::SHA256D;
-use chain::{IndexedTransaction, OutPoint, TransactionOutput};
+use bitcrypto::{dhash256, SHA256D};
+use chain::{merkle_root, IndexedTransaction, OutPoint, TransactionOutput};
 use network::ConsensusParams;
+use primitives::bytes::Bytes;
+use script::Builder;
+use ser::Stream;
 use std::collections::HashSet;
 use storage::{SharedStore, TransactionOutputProvider};
-use verification::{block_reward_satoshi, transaction_sigops, work_required};
+use verification::{block_reward_satoshi, transaction_sigops, transaction_sigops_cost, work_required, Deployments};
 
 const BLOCK_VERSION: u32 = 0x20000000;
-const BLOCK_HEADER_SIZE: u32 = 4 + 32 + 32 + 4 + 4 + 4;
-
-/// Block size and number of signatures opcodes is limited
+/// Block weight, reserved for the block header, transactions count and coinbase transaction
+const BLOCK_RESERVED_WEIGHT: u32 = 4_000;
+/// Block sigops cost, reserved for the coinbase transaction
+const BLOCK_RESERVED_SIGOPS_COST: u32 = 400;
+/// Header of the witness commitment, which follows OP_RETURN in the coinbase output
+const WITNESS_COMMITMENT_HEADER: [u8; 4] = [0xaa, 0x21, 0xa9, 0xed];
+/// Witness reserved value, which must be put into the coinbase input witness
+pub const WITNESS_RESERVED_VALUE: [u8; 32] = [0; 32];
+
+/// Block weight and sigops cost is limited
 /// This structure should be used for storing this values.
 struct SizePolicy {
 	/// Current size
@@ -26,7 +36,7 @@ struct SizePolicy {
 	finish_limit: u32,
 }
 
-/// When appending transaction, opcode count and block size policies
+/// When appending transaction, sigops cost and block weight policies
 /// must agree on appending the transaction to the block
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum NextStep {
@@ -92,10 +102,12 @@ impl SizePolicy {
 
 /// Block assembler
 pub struct BlockAssembler {
-	/// Maximal block size.
-	pub max_block_size: u32,
-	/// Maximal # of sigops in the block.
-	pub max_block_sigops: u32,
+	/// Maximal block weight.
+	pub max_block_weight: u32,
+	/// Maximal sigops cost of the block.
+	pub max_block_sigops_cost: u32,
+	/// True if miner supports SegWit, so transactions with witness could be included into the block.
+	pub segwit: bool,
 }
 
 /// Iterator iterating over mempool transactions and yielding only those which fit the block
@@ -108,10 +120,12 @@ struct FittingTransactionsIterator<'a, T> {
 	block_height: u32,
 	/// New block time
 	block_time: u32,
-	/// Size policy decides if transactions size fits the block
-	block_size: SizePolicy,
-	/// Sigops policy decides if transactions sigops fits the block
-	sigops: SizePolicy,
+	/// True if transactions with witness could be included into the block
+	witness: bool,
+	/// Weight policy decides if transactions weight fits the block
+	block_weight: SizePolicy,
+	/// Sigops policy decides if transactions sigops cost fits the block
+	sigops_cost: SizePolicy,
 	/// Previous entries are needed to get previous transaction outputs
 	previous_entries: Vec<&'a Entry>,
 	/// Hashes of ignored entries
@@ -127,8 +141,9 @@ where
 	fn new(
 		store: &'a dyn TransactionOutputProvider,
 		iter: T,
-		max_block_size: u32,
-		max_block_sigops: u32,
+		max_block_weight: u32,
+		max_block_sigops_cost: u32,
+		witness: bool,
 		block_height: u32,
 		block_time: u32,
 	) -> Self {
@@ -137,9 +152,10 @@ where
 			iter,
 			block_height,
 			block_time,
-			// reserve some space for header and transations len field
-			block_size: SizePolicy::new(BLOCK_HEADER_SIZE + 4, max_block_size, 1_000, 50),
-			sigops: SizePolicy::new(0, max_block_sigops, 8, 50),
+			witness,
+			// reserve some space for header, transations len field and coinbase transaction
+			block_weight: SizePolicy::new(BLOCK_RESERVED_WEIGHT, max_block_weight, 4_000, 50),
+			sigops_cost: SizePolicy::new(BLOCK_RESERVED_SIGOPS_COST, max_block_sigops_cost, 32, 50),
 			previous_entries: Vec::new(),
 			ignored: HashSet::new(),
 			finished: false,
@@ -182,44 +198,47 @@ where
 				}
 			};
 
-			let transaction_size = entry.size as u32;
+			let transaction_weight = entry.transaction.weight() as u32;
 			let bip16_active = true;
-			let sigops_count = transaction_sigops(&entry.transaction, self, bip16_active) as u32;
+			let sigops = transaction_sigops(&entry.transaction, self, bip16_active);
+			let sigops_cost = transaction_sigops_cost(&entry.transaction, self, sigops) as u32;
 
-			let size_step = self.block_size.decide(transaction_size);
-			let sigops_step = self.sigops.decide(sigops_count);
+			let weight_step = self.block_weight.decide(transaction_weight);
+			let sigops_step = self.sigops_cost.decide(sigops_cost);
 
-			// both next checks could be checked above, but then it will break finishing
+			// all next checks could be checked above, but then it will break finishing
 			// check if transaction is still not finalized in this block
-			if !entry.transaction.is_final_in_block(self.block_height, self.block_time) {
-				continue;
-			}
-			// check if any parent transaction has been ignored
-			if !self.ignored.is_empty()
-				&& entry
+			// or it has witness, which can't be included into this block
+			// or any parent transaction has been ignored
+			if !entry.transaction.is_final_in_block(self.block_height, self.block_time)
+				|| (!self.witness && entry.transaction.has_witness())
+				|| entry
 					.transaction
 					.inputs
 					.iter()
 					.any(|input| self.ignored.contains(&input.previous_output.hash))
 			{
+				self.ignored.insert(entry.hash);
 				continue;
 			}
 
-			match size_step.and(sigops_step) {
+			match weight_step.and(sigops_step) {
 				NextStep::Append => {
-					self.block_size.apply(transaction_size);
-					self.sigops.apply(transaction_size);
+					self.block_weight.apply(transaction_weight);
+					self.sigops_cost.apply(sigops_cost);
 					self.previous_entries.push(entry);
 					return Some(entry);
 				}
 				NextStep::FinishAndAppend => {
 					self.finished = true;
-					self.block_size.apply(transaction_size);
-					self.sigops.apply(transaction_size);
+					self.block_weight.apply(transaction_weight);
+					self.sigops_cost.apply(sigops_cost);
 					self.previous_entries.push(entry);
 					return Some(entry);
 				}
-				NextStep::Ignore => (),
+				NextStep::Ignore => {
+					self.ignored.insert(entry.hash);
+				}
 				NextStep::FinishAndIgnore => {
 					self.ignored.insert(entry.hash.clone());
 					self.finished = true;
@@ -232,7 +251,15 @@ where
 }
 
 impl BlockAssembler {
-	pub fn create_new_block(&self, store: &SharedStore, mempool: &MemoryPool, time: u32, consensus: &ConsensusParams) -> BlockTemplate {
+	/// Creates template of the block on top of the best block. `deployments` cache is shared between templates
+	pub fn create_new_block(
+		&self,
+		store: &SharedStore,
+		mempool: &MemoryPool,
+		time: u32,
+		consensus: &ConsensusParams,
+		deployments: &Deployments,
+	) -> BlockTemplate {
 		// get best block
 		// take it's hash && height
 		let best_block = store.best_block();
@@ -246,6 +273,9 @@ impl BlockAssembler {
 			consensus,
 		);
 		let version = BLOCK_VERSION;
+		// transactions with witness are only included when both miner and network support SegWit
+		let segwit_active = deployments.segwit(height, store.as_block_header_provider(), consensus);
+		let witness = self.segwit && segwit_active;
 
 		let mut coinbase_value = block_reward_satoshi(height);
 		let mut transactions = Vec::new();
@@ -254,8 +284,9 @@ impl BlockAssembler {
 		let tx_iter = FittingTransactionsIterator::new(
 			store.as_transaction_output_provider(),
 			mempool_iter,
-			self.max_block_size,
-			self.max_block_sigops,
+			self.max_block_weight,
+			self.max_block_sigops_cost,
+			witness,
 			height,
 			time,
 		);
@@ -267,6 +298,22 @@ impl BlockAssembler {
 			transactions.push(tx);
 		}
 
+		// pre-SegWit miners are operating with legacy size and sigops limits
+		let witness_scale_factor = consensus.witness_scale_factor as u32;
+		let (size_limit, sigop_limit) = if witness {
+			(self.max_block_weight, self.max_block_sigops_cost)
+		} else {
+			(
+				self.max_block_weight / witness_scale_factor,
+				self.max_block_sigops_cost / witness_scale_factor,
+			)
+		};
+		let default_witness_commitment = if witness {
+			Some(witness_commitment_script(&transactions))
+		} else {
+			None
+		};
+
 		BlockTemplate {
 			version,
 			previous_header_hash,
@@ -275,28 +322,47 @@ impl BlockAssembler {
 			height,
 			transactions,
 			coinbase_value,
-			size_limit: self.max_block_size,
-			sigop_limit: self.max_block_sigops,
+			size_limit,
+			sigop_limit,
+			weight_limit: self.max_block_weight,
+			default_witness_commitment,
 		}
 	}
 }
 
+/// Builds BIP141 witness commitment script for the block with given (non-coinbase) transactions
+fn witness_commitment_script(transactions: &[IndexedTransaction]) -> Bytes {
+	// witness hash of the coinbase transaction is assumed to be zero
+	let mut hashes = vec![SHA256D::default()];
+	hashes.extend(transactions.iter().map(|tx| tx.raw.witness_hash()));
+
+	let mut stream = Stream::new();
+	stream.append(&merkle_root(&hashes));
+	stream.append_slice(&WITNESS_RESERVED_VALUE);
+	let commitment = dhash256(&stream.out());
+
+	let mut data = WITNESS_COMMITMENT_HEADER.to_vec();
+	data.extend_from_slice(&*commitment);
+	Builder::build_nulldata(&data).into()
+}
+
 #[cfg(test)]
 mod tests {
 	extern crate test_data;
 
 	use self::test_data::{ChainBuilder, TransactionBuilder};
-	use super::{BlockAssembler, NextStep, SizePolicy};
+	use super::{BlockAssembler, NextStep, SizePolicy, WITNESS_RESERVED_VALUE};
 	use crate::block_template::BlockTemplate;
 	use crate::fee::{FeeCalculator, NonZeroFeeCalculator};
 	use crate::memory_pool::MemoryPool;
-	use bitcrypto::SHA256D;
-	use chain::IndexedTransaction;
+	use bitcrypto::{dhash256, SHA256D};
+	use chain::{IndexedBlock, IndexedTransaction, Transaction};
 	use db::BlockChainDatabase;
 	use network::{ConsensusParams, Network};
+	use ser::Stream;
 	use std::sync::Arc;
 	use storage::SharedStore;
-	use verification::block_reward_satoshi;
+	use verification::{block_reward_satoshi, Deployments};
 
 	#[test]
 	fn test_size_policy() {
@@ -368,10 +434,11 @@ mod tests {
 
 			(
 				BlockAssembler {
-					max_block_size: 0xffffffff,
-					max_block_sigops: 0xffffffff,
+					max_block_weight: 0xffffffff,
+					max_block_sigops_cost: 0xffffffff,
+					segwit: true,
 				}
-				.create_new_block(&storage, &pool, 0, &consensus),
+				.create_new_block(&storage, &pool, 0, &consensus, &Deployments::new()),
 				hash0,
 				hash1,
 			)
@@ -397,12 +464,61 @@ mod tests {
 
 		let consensus = ConsensusParams::new(Network::Mainnet);
 		let block = BlockAssembler {
-			max_block_size: 0xffffffff,
-			max_block_sigops: 0xffffffff,
+			max_block_weight: 0xffffffff,
+			max_block_sigops_cost: 0xffffffff,
+			segwit: true,
 		}
-		.create_new_block(&storage, &pool, 0, &consensus);
+		.create_new_block(&storage, &pool, 0, &consensus, &Deployments::new());
 
 		let expected_coinbase_value = block_reward_satoshi(1) + expected_tx0_fee;
 		assert_eq!(block.coinbase_value, expected_coinbase_value);
 	}
+
+	#[test]
+	fn block_assembler_witness_transactions() {
+		fn construct_block(segwit: bool) -> BlockTemplate {
+			let mut witness_tx: Transaction = TransactionBuilder::with_default_input(0).set_output(30).into();
+			witness_tx.inputs[0].script_witness = vec![vec![0x01].into()];
+			let legacy_tx: Transaction = TransactionBuilder::with_default_input(1).set_output(40).into();
+
+			let mut pool = MemoryPool::new();
+			let storage: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+			pool.insert_verified(witness_tx.into(), &NonZeroFeeCalculator);
+			pool.insert_verified(legacy_tx.into(), &NonZeroFeeCalculator);
+
+			let mut consensus = ConsensusParams::new(Network::Unitest);
+			consensus.deployments[1] = consensus.deployments[1].buried(0);
+			BlockAssembler {
+				max_block_weight: consensus.max_block_weight as u32,
+				max_block_sigops_cost: consensus.max_block_sigops_cost as u32,
+				segwit,
+			}
+			.create_new_block(&storage, &pool, 0, &consensus, &Deployments::new())
+		}
+
+		// when miner doesn't support SegWit, witness transactions are not included
+		let block = construct_block(false);
+		assert_eq!(block.transactions.len(), 1);
+		assert!(!block.transactions[0].raw.has_witness());
+		assert_eq!(block.size_limit, 1_000_000);
+		assert_eq!(block.sigop_limit, 20_000);
+		assert_eq!(block.default_witness_commitment, None);
+
+		// otherwise witness commitment must be added to the coinbase
+		let block = construct_block(true);
+		assert_eq!(block.transactions.len(), 2);
+		assert_eq!(block.size_limit, 4_000_000);
+		assert_eq!(block.sigop_limit, 80_000);
+		assert_eq!(block.weight_limit, 4_000_000);
+		let commitment = block.default_witness_commitment.clone().unwrap();
+		assert!(script::is_witness_commitment_script(&commitment));
+
+		let mut transactions = vec![IndexedTransaction::from_raw(Transaction::default())];
+		transactions.extend(block.transactions);
+		let block = IndexedBlock::new(test_data::genesis().block_header.into(), transactions);
+		let mut stream = Stream::new();
+		stream.append(&block.witness_merkle_root());
+		stream.append_slice(&WITNESS_RESERVED_VALUE);
+		assert_eq!(&commitment[6..], &*dhash256(&stream.out()));
+	}
 }
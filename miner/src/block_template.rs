@@ -1,5 +1,6 @@
 use bitcrypto::SHA256D;
 use chain::IndexedTransaction;
+use primitives::bytes::Bytes;
 use primitives::compact::Compact;
 
 /// Block template as described in [BIP0022](https://github.com/bitcoin/bips/blob/master/bip-0022.mediawiki#block-template-request)
@@ -18,8 +19,13 @@ pub struct BlockTemplate {
 	pub transactions: Vec<IndexedTransaction>,
 	/// Total funds available for the coinbase (in Satoshis)
 	pub coinbase_value: u64,
-	/// Number of bytes allowed in the block
+	/// Number of bytes allowed in the block (block weight, when SegWit rules are applied)
 	pub size_limit: u32,
-	/// Number of sigops allowed in the block
+	/// Number of sigops allowed in the block (sigops cost, when SegWit rules are applied)
 	pub sigop_limit: u32,
+	/// Number of weight units allowed in the block
+	pub weight_limit: u32,
+	/// Witness commitment script, which must be added to the coinbase transaction outputs.
+	/// None if SegWit rules are not applied to the block
+	pub default_witness_commitment: Option<Bytes>,
 }
@@ -1,16 +1,32 @@
+use crate::block_assembler::WITNESS_RESERVED_VALUE;
 use crate::block_template::BlockTemplate;
 use bitcrypto::dhash256;
 use bitcrypto::SHA256D;
 use byteorder::{LittleEndian, WriteBytesExt};
 use chain::{merkle_root, BlockHeader, IndexedBlock, Transaction, TransactionInput, TransactionOutput};
-use keys::AddressHash;
+use keys::{Address, AddressHash};
 use primitives::bytes::Bytes;
 use primitives::compact::Compact;
 use primitives::U256;
 use script::Builder;
 use ser::Stream;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 use verification::is_valid_proof_of_work_hash;
 
+/// Bits of the block version, which miners may use as an additional nonce (BIP320)
+pub const VERSION_ROLLING_MASK: u32 = 0x1fff_e000;
+/// Version rolling is only applied to the versions with BIP9 top bits
+const VERSION_BITS_TOP_MASK: u32 = 0xe000_0000;
+const VERSION_BITS_TOP_BITS: u32 = 0x2000_0000;
+/// Number of hashes, computed by the worker thread between checks of the shared mining state
+const WORKER_BATCH_SIZE: usize = 4096;
+/// Interval between checks of the mined block tip
+const STALE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Instead of serializing `BlockHeader` from scratch over and over again,
 /// let's keep it serialized in memory and replace needed bytes
 struct BlockHeaderBytes {
@@ -36,6 +52,12 @@ impl BlockHeaderBytes {
 		BlockHeaderBytes { data: stream.out() }
 	}
 
+	/// Set block header version
+	fn set_version(&mut self, version: u32) {
+		let mut version_bytes: &mut [u8] = &mut self.data[..];
+		version_bytes.write_u32::<LittleEndian>(version).unwrap();
+	}
+
 	/// Set merkle root hash
 	fn set_merkle_root_hash(&mut self, hash: &SHA256D) {
 		let merkle_bytes: &mut [u8] = &mut self.data[4 + 32..4 + 32 + 32];
@@ -70,14 +92,24 @@ pub trait CoinbaseTransactionBuilder {
 	fn finish(self) -> Transaction;
 }
 
-pub struct P2shCoinbaseTransactionBuilder {
+/// Coinbase transaction with single output.
+#[derive(Clone)]
+pub struct SimpleCoinbaseTransactionBuilder {
 	transaction: Transaction,
 }
 
-impl P2shCoinbaseTransactionBuilder {
+impl SimpleCoinbaseTransactionBuilder {
+	/// Creates coinbase transaction paying to the given p2sh hash.
 	pub fn new(hash: &AddressHash, value: u64) -> Self {
-		let script_pubkey = Builder::build_p2sh(hash).into();
+		SimpleCoinbaseTransactionBuilder::with_script_pubkey(Builder::build_p2sh(hash).into(), value)
+	}
 
+	/// Creates coinbase transaction paying to the given address.
+	pub fn with_address(address: &Address, value: u64) -> Self {
+		SimpleCoinbaseTransactionBuilder::with_script_pubkey(Builder::build_address(address).into(), value)
+	}
+
+	fn with_script_pubkey(script_pubkey: Bytes, value: u64) -> Self {
 		let transaction = Transaction {
 			version: 0,
 			inputs: vec![TransactionInput::coinbase(Bytes::default())],
@@ -85,11 +117,21 @@ impl P2shCoinbaseTransactionBuilder {
 			lock_time: 0,
 		};
 
-		P2shCoinbaseTransactionBuilder { transaction }
+		SimpleCoinbaseTransactionBuilder { transaction }
+	}
+
+	/// Adds witness commitment output and witness reserved value to the coinbase transaction.
+	pub fn with_witness_commitment(mut self, commitment: Bytes) -> Self {
+		self.transaction.inputs[0].script_witness = vec![WITNESS_RESERVED_VALUE.to_vec().into()];
+		self.transaction.outputs.push(TransactionOutput {
+			value: 0,
+			script_pubkey: commitment,
+		});
+		self
 	}
 }
 
-impl CoinbaseTransactionBuilder for P2shCoinbaseTransactionBuilder {
+impl CoinbaseTransactionBuilder for SimpleCoinbaseTransactionBuilder {
 	fn set_extranonce(&mut self, extranonce: &[u8]) {
 		self.transaction.inputs[0].script_sig = extranonce.to_vec().into();
 	}
@@ -105,6 +147,8 @@ impl CoinbaseTransactionBuilder for P2shCoinbaseTransactionBuilder {
 
 /// Cpu miner solution.
 pub struct Solution {
+	/// Block header version.
+	pub version: u32,
 	/// Block header nonce.
 	pub nonce: u32,
 	/// Coinbase transaction extra nonce (modifiable by miner).
@@ -160,6 +204,7 @@ where
 			let hash = header_bytes.hash();
 			if is_valid_proof_of_work_hash(block.bits, &hash) {
 				let solution = Solution {
+					version: block.version,
 					nonce: nonce as u32,
 					extranonce,
 					time: block.time,
@@ -179,41 +224,258 @@ where
 	None
 }
 
-pub fn mine_block(mut block_template: BlockTemplate, address: Option<AddressHash>, max_tries: usize) -> Option<IndexedBlock> {
-	let hash = address.unwrap_or_default();
-	let coinbase_builder = P2shCoinbaseTransactionBuilder::new(&hash, block_template.coinbase_value);
-	if let Some(solution) = find_solution(&block_template, coinbase_builder, U256::max_value(), max_tries) {
-		block_template.transactions.push(solution.coinbase_transaction.into());
-		let hashes: Vec<SHA256D> = block_template.transactions.iter().map(|t| t.hash).collect();
-		let merkle_root_hash = if hashes.is_empty() {
-			SHA256D::default()
-		} else {
-			merkle_root(&hashes)
-		};
+pub fn mine_block(block_template: BlockTemplate, address: Option<Address>, max_tries: usize) -> Option<IndexedBlock> {
+	let coinbase_builder = coinbase_builder(&block_template, address.as_ref());
+	find_solution(&block_template, coinbase_builder, U256::max_value(), max_tries).map(|solution| solved_block(block_template, solution))
+}
+
+/// Multi-threaded cpu miner.
+///
+/// Extranonce space is split across worker threads: worker `i` tries extranonces `height + i`,
+/// `height + i + threads`, ... For every extranonce it tries all nonces with every block version,
+/// allowed by the BIP320 mask.
+pub struct CpuMiner {
+	/// Number of worker threads
+	threads: usize,
+	/// Number of hashes, computed during the last mining run, and its duration
+	last_run: Mutex<(u64, Duration)>,
+}
 
-		return Some(IndexedBlock {
-			header: BlockHeader {
-				version: block_template.version,
-				previous_header_hash: block_template.previous_header_hash,
-				merkle_root_hash,
-				time: block_template.time,
-				bits: block_template.bits,
-				nonce: solution.nonce,
+impl Default for CpuMiner {
+	fn default() -> Self {
+		CpuMiner::new(thread::available_parallelism().map_or(1, |threads| threads.get()))
+	}
+}
+
+impl CpuMiner {
+	pub fn new(threads: usize) -> Self {
+		CpuMiner {
+			threads: threads.max(1),
+			last_run: Mutex::new((0, Duration::default())),
+		}
+	}
+
+	/// Number of worker threads
+	pub fn threads(&self) -> usize {
+		self.threads
+	}
+
+	/// Hashes per second, computed during the last mining run
+	pub fn hashes_per_second(&self) -> f64 {
+		let (hashes, duration) = *self.last_run.lock().expect("poisoned mutex");
+		if duration.as_secs_f64() == 0.0 {
+			return 0.0;
+		}
+		hashes as f64 / duration.as_secs_f64()
+	}
+
+	/// Searches for the block solution, using all worker threads.
+	/// Returns None if all `max_tries` hashes have been computed, or if `is_stale` has returned true
+	/// (i.e. block tip has changed and the solution is useless anyway)
+	pub fn find_solution<T, F>(
+		&self,
+		block: &BlockTemplate,
+		coinbase_transaction_builder: T,
+		max_tries: usize,
+		is_stale: F,
+	) -> Option<Solution>
+	where
+		T: CoinbaseTransactionBuilder + Clone + Send,
+		F: Fn() -> bool,
+	{
+		let started = Instant::now();
+		let state = WorkersState {
+			stop: AtomicBool::new(false),
+			tries: AtomicUsize::new(0),
+			hashes: AtomicU64::new(0),
+			max_tries,
+		};
+		let (sender, receiver) = mpsc::channel();
+		let solution = thread::scope(|scope| {
+			for worker in 0..self.threads {
+				let sender = sender.clone();
+				let coinbase_transaction_builder = coinbase_transaction_builder.clone();
+				let state = &state;
+				let threads = self.threads;
+				scope.spawn(move || {
+					if let Some(solution) = mine_worker(block, coinbase_transaction_builder, worker, threads, state) {
+						state.stop.store(true, Ordering::SeqCst);
+						// only fails if another solution has already been received
+						let _ = sender.send(solution);
+					}
+				});
+			}
+			drop(sender);
+
+			loop {
+				match receiver.recv_timeout(STALE_CHECK_INTERVAL) {
+					Ok(solution) => break Some(solution),
+					Err(RecvTimeoutError::Timeout) => {
+						if is_stale() {
+							state.stop.store(true, Ordering::SeqCst);
+						}
+					}
+					Err(RecvTimeoutError::Disconnected) => break None,
+				}
 			}
-			.into(),
-			transactions: block_template.transactions,
 		});
+
+		*self.last_run.lock().expect("poisoned mutex") = (state.hashes.load(Ordering::SeqCst), started.elapsed());
+		solution
+	}
+
+	/// Mines block on top of the template, using all worker threads
+	pub fn mine_block<F>(
+		&self,
+		block_template: BlockTemplate,
+		address: Option<Address>,
+		max_tries: usize,
+		is_stale: F,
+	) -> Option<IndexedBlock>
+	where
+		F: Fn() -> bool,
+	{
+		let coinbase_builder = coinbase_builder(&block_template, address.as_ref());
+		self.find_solution(&block_template, coinbase_builder, max_tries, is_stale)
+			.map(|solution| solved_block(block_template, solution))
+	}
+}
+
+/// Mining state, shared by all worker threads
+struct WorkersState {
+	/// Set when solution is found or mining is cancelled
+	stop: AtomicBool,
+	/// Number of hashes, reserved by workers
+	tries: AtomicUsize,
+	/// Number of computed hashes
+	hashes: AtomicU64,
+	max_tries: usize,
+}
+
+impl WorkersState {
+	/// Reserves next batch of hashes for the worker. Returns 0 if worker must stop
+	fn reserve_batch(&self) -> usize {
+		if self.stop.load(Ordering::SeqCst) {
+			return 0;
+		}
+		let reserved = self.tries.fetch_add(WORKER_BATCH_SIZE, Ordering::SeqCst);
+		if reserved >= self.max_tries {
+			return 0;
+		}
+		WORKER_BATCH_SIZE.min(self.max_tries - reserved)
+	}
+}
+
+fn mine_worker<T>(
+	block: &BlockTemplate,
+	mut coinbase_transaction_builder: T,
+	worker: usize,
+	threads: usize,
+	state: &WorkersState,
+) -> Option<Solution>
+where
+	T: CoinbaseTransactionBuilder,
+{
+	let mut extranonce = U256::from(block.height) + U256::from(worker as u64);
+	let mut extranonce_bytes = [0u8; 32];
+	let mut header_bytes = BlockHeaderBytes::new(block.version, block.previous_header_hash, block.bits);
+	header_bytes.set_time(block.time);
+	let versions_count = if block.version & VERSION_BITS_TOP_MASK == VERSION_BITS_TOP_BITS {
+		(VERSION_ROLLING_MASK >> VERSION_ROLLING_MASK.trailing_zeros()) + 1
+	} else {
+		1
+	};
+
+	let mut batch_left = 0;
+	let mut hashes = 0u64;
+	let solution = 'extranonce: loop {
+		extranonce.to_little_endian(&mut extranonce_bytes);
+		coinbase_transaction_builder.set_extranonce(&extranonce_bytes);
+		let mut merkle_tree = vec![coinbase_transaction_builder.hash()];
+		merkle_tree.extend(block.transactions.iter().map(|tx| &tx.hash));
+		header_bytes.set_merkle_root_hash(&merkle_root(merkle_tree.as_slice()));
+
+		for version_index in 0..versions_count {
+			let version = rolled_version(block.version, version_index);
+			header_bytes.set_version(version);
+			for nonce in 0..=u32::max_value() {
+				if batch_left == 0 {
+					batch_left = state.reserve_batch();
+					if batch_left == 0 {
+						break 'extranonce None;
+					}
+				}
+				batch_left -= 1;
+				hashes += 1;
+
+				header_bytes.set_nonce(nonce);
+				if is_valid_proof_of_work_hash(block.bits, &header_bytes.hash()) {
+					break 'extranonce Some((version, nonce));
+				}
+			}
+		}
+
+		extranonce = extranonce + U256::from(threads as u64);
+	};
+
+	state.hashes.fetch_add(hashes, Ordering::SeqCst);
+	solution.map(|(version, nonce)| Solution {
+		version,
+		nonce,
+		extranonce,
+		time: block.time,
+		coinbase_transaction: coinbase_transaction_builder.finish(),
+	})
+}
+
+/// Returns block version with given value of the BIP320 bits. Index 0 is the original version
+fn rolled_version(version: u32, index: u32) -> u32 {
+	version ^ ((index << VERSION_ROLLING_MASK.trailing_zeros()) & VERSION_ROLLING_MASK)
+}
+
+fn coinbase_builder(block_template: &BlockTemplate, address: Option<&Address>) -> SimpleCoinbaseTransactionBuilder {
+	let mut coinbase_builder = match address {
+		Some(address) => SimpleCoinbaseTransactionBuilder::with_address(address, block_template.coinbase_value),
+		None => SimpleCoinbaseTransactionBuilder::new(&AddressHash::default(), block_template.coinbase_value),
+	};
+	if let Some(commitment) = block_template.default_witness_commitment.clone() {
+		coinbase_builder = coinbase_builder.with_witness_commitment(commitment);
+	}
+	coinbase_builder
+}
+
+fn solved_block(mut block_template: BlockTemplate, solution: Solution) -> IndexedBlock {
+	block_template.transactions.insert(0, solution.coinbase_transaction.into());
+	let hashes: Vec<SHA256D> = block_template.transactions.iter().map(|t| t.hash).collect();
+	let merkle_root_hash = if hashes.is_empty() {
+		SHA256D::default()
+	} else {
+		merkle_root(&hashes)
+	};
+
+	IndexedBlock {
+		header: BlockHeader {
+			version: solution.version,
+			previous_header_hash: block_template.previous_header_hash,
+			merkle_root_hash,
+			time: solution.time,
+			bits: block_template.bits,
+			nonce: solution.nonce,
+		}
+		.into(),
+		transactions: block_template.transactions,
 	}
-	None
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{find_solution, P2shCoinbaseTransactionBuilder};
+	use super::{find_solution, rolled_version, CpuMiner, SimpleCoinbaseTransactionBuilder, VERSION_ROLLING_MASK};
 	use crate::block_template::BlockTemplate;
 	use crate::cpu_miner::mine_block;
 	use bitcrypto::SHA256D;
+	use chain::Transaction;
 	use keys::AddressHash;
+	use primitives::bytes::Bytes;
 	use primitives::U256;
 
 	#[test]
@@ -228,10 +490,12 @@ mod tests {
 			coinbase_value: 10,
 			size_limit: 1000,
 			sigop_limit: 100,
+			weight_limit: 4000,
+			default_witness_commitment: None,
 		};
 
 		let hash = AddressHash::default();
-		let coinbase_builder = P2shCoinbaseTransactionBuilder::new(&hash, 10);
+		let coinbase_builder = SimpleCoinbaseTransactionBuilder::new(&hash, 10);
 		let solution = find_solution(&block_template, coinbase_builder, U256::max_value(), usize::max_value());
 		assert!(solution.is_some());
 	}
@@ -248,9 +512,103 @@ mod tests {
 			coinbase_value: 10,
 			size_limit: 1000,
 			sigop_limit: 100,
+			weight_limit: 4000,
+			default_witness_commitment: None,
 		};
 
 		let block = mine_block(block_template, None, usize::max_value());
 		assert!(block.is_some());
 	}
+
+	#[test]
+	fn test_cpu_miner_mine_block_with_witness_commitment() {
+		let commitment: Bytes = vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed].into();
+		let block_template = BlockTemplate {
+			version: 0,
+			previous_header_hash: SHA256D::default(),
+			time: 0,
+			bits: U256::max_value().into(),
+			height: 0,
+			transactions: vec![Transaction::default().into()],
+			coinbase_value: 10,
+			size_limit: 4000,
+			sigop_limit: 400,
+			weight_limit: 4000,
+			default_witness_commitment: Some(commitment.clone()),
+		};
+
+		let block = mine_block(block_template, None, usize::max_value()).unwrap();
+		let coinbase = &block.transactions[0].raw;
+		assert!(coinbase.is_coinbase());
+		assert_eq!(coinbase.inputs[0].script_witness, vec![Bytes::from(vec![0u8; 32])]);
+		assert_eq!(coinbase.outputs.last().unwrap().script_pubkey, commitment);
+		assert_eq!(block.header.raw.merkle_root_hash, block.merkle_root());
+	}
+
+	fn hard_block_template() -> BlockTemplate {
+		BlockTemplate {
+			version: 0x2000_0000,
+			previous_header_hash: SHA256D::default(),
+			time: 0,
+			bits: 0x1d00ffff.into(),
+			height: 0,
+			transactions: Vec::new(),
+			coinbase_value: 10,
+			size_limit: 1000,
+			sigop_limit: 100,
+			weight_limit: 4000,
+			default_witness_commitment: None,
+		}
+	}
+
+	#[test]
+	fn test_parallel_cpu_miner_mine_block() {
+		let block_template = BlockTemplate {
+			version: 0x2000_0000,
+			previous_header_hash: SHA256D::default(),
+			time: 0,
+			bits: 0x207fffff.into(),
+			height: 0,
+			transactions: vec![Transaction::default().into()],
+			coinbase_value: 10,
+			size_limit: 1000,
+			sigop_limit: 100,
+			weight_limit: 4000,
+			default_witness_commitment: None,
+		};
+
+		let miner = CpuMiner::new(4);
+		let block = miner.mine_block(block_template, None, usize::max_value(), || false).unwrap();
+		assert!(verification::is_valid_proof_of_work_hash(0x207fffff.into(), block.hash()));
+		assert_eq!(block.header.raw.merkle_root_hash, block.merkle_root());
+		assert_eq!(block.transactions.len(), 2);
+		assert!(miner.hashes_per_second() > 0.0);
+	}
+
+	#[test]
+	fn test_parallel_cpu_miner_max_tries() {
+		let miner = CpuMiner::new(3);
+		let coinbase_builder = SimpleCoinbaseTransactionBuilder::new(&AddressHash::default(), 10);
+		assert!(miner
+			.find_solution(&hard_block_template(), coinbase_builder, 10_000, || false)
+			.is_none());
+		assert!(miner.hashes_per_second() > 0.0);
+	}
+
+	#[test]
+	fn test_parallel_cpu_miner_stops_on_stale_tip() {
+		let miner = CpuMiner::new(2);
+		let coinbase_builder = SimpleCoinbaseTransactionBuilder::new(&AddressHash::default(), 10);
+		assert!(miner
+			.find_solution(&hard_block_template(), coinbase_builder, usize::max_value(), || true)
+			.is_none());
+	}
+
+	#[test]
+	fn test_version_rolling() {
+		assert_eq!(rolled_version(0x2000_0004, 0), 0x2000_0004);
+		assert_eq!(rolled_version(0x2000_0004, 1), 0x2000_2004);
+		assert_eq!(rolled_version(0x2000_0004, 0xffff), 0x3fff_e004);
+		assert_eq!(rolled_version(0x2000_0004, 0xffff) & !VERSION_ROLLING_MASK, 0x2000_0004);
+	}
 }
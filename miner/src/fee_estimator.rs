@@ -0,0 +1,895 @@
+//! Fee estimator, which tracks how long memory pool transactions, paying different fee rates, take to confirm
+//!
+//! Transactions are grouped into exponentially spaced fee rate buckets. For every bucket the estimator keeps
+//! exponentially decaying counts of transactions, confirmed within given number of blocks, and of transactions,
+//! which have left the memory pool without being confirmed. Statistics are collected over three horizons (short,
+//! medium and long), which differ in decay speed and in the maximal number of blocks they track.
+//! Estimation algorithm follows Bitcoin Core `CBlockPolicyEstimator`.
+use bitcrypto::SHA256D;
+use primitives::bytes::Bytes;
+use ser::{Error as ReaderError, Reader, Stream};
+use std::collections::{HashMap, HashSet};
+
+/// Version of the serialized estimator state
+pub const FEE_ESTIMATES_VERSION: u32 = 1;
+
+/// Fee rate (in satoshis per 1000 bytes) of the lowest bucket
+const MIN_BUCKET_FEE_RATE: f64 = 1000.0;
+/// Fee rate (in satoshis per 1000 bytes) of the highest bucket
+const MAX_BUCKET_FEE_RATE: f64 = 1e7;
+/// Spacing of fee rate buckets
+const FEE_SPACING: f64 = 1.05;
+/// Upper bound of the last bucket, which contains all transactions paying more than `MAX_BUCKET_FEE_RATE`
+const INF_FEE_RATE: f64 = 1e99;
+
+/// Short horizon tracks up to 12 blocks
+const SHORT_BLOCK_PERIODS: usize = 12;
+const SHORT_SCALE: u32 = 1;
+const SHORT_DECAY: f64 = 0.962;
+/// Medium horizon tracks up to 48 blocks
+const MEDIUM_BLOCK_PERIODS: usize = 24;
+const MEDIUM_SCALE: u32 = 2;
+const MEDIUM_DECAY: f64 = 0.9952;
+/// Long horizon tracks up to 1008 blocks
+const LONG_BLOCK_PERIODS: usize = 42;
+const LONG_SCALE: u32 = 24;
+const LONG_DECAY: f64 = 0.99931;
+
+/// Required share of confirmed transactions when estimating for the half of the target
+const HALF_SUCCESS_PCT: f64 = 0.6;
+/// Required share of confirmed transactions when estimating for the target
+const SUCCESS_PCT: f64 = 0.85;
+/// Required share of confirmed transactions when estimating for the double target
+pub const DOUBLE_SUCCESS_PCT: f64 = 0.95;
+
+/// Required number of transactions per block (before decay) in the range of buckets of medium and long horizons
+const SUFFICIENT_FEE_TXS: f64 = 0.1;
+/// Required number of transactions per block (before decay) in the range of buckets of short horizon
+const SUFFICIENT_TXS_SHORT: f64 = 0.5;
+
+/// Statistics, which are older than this number of blocks, are not used for estimation after restart
+const OLDEST_ESTIMATE_HISTORY: u32 = 6 * 1008;
+/// Statistics, saved more than this number of blocks (~60 hours) before the best block, are not restored
+const MAX_FILE_AGE: u32 = 360;
+
+/// Estimation horizon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EstimationHorizon {
+	/// Up to 12 blocks, fast decay
+	Short,
+	/// Up to 48 blocks, medium decay
+	Medium,
+	/// Up to 1008 blocks, slow decay
+	Long,
+}
+
+/// Statistics of the range of fee rate buckets
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EstimatorBucket {
+	/// Lower bound of the range fee rate (in satoshis per 1000 bytes)
+	pub start: f64,
+	/// Upper bound of the range fee rate (in satoshis per 1000 bytes)
+	pub end: f64,
+	/// Number of transactions, confirmed within the target
+	pub within_target: f64,
+	/// Number of confirmed transactions
+	pub total_confirmed: f64,
+	/// Number of transactions, which are in the memory pool for at least target blocks
+	pub in_mempool: f64,
+	/// Number of transactions, which have left the memory pool without being confirmed
+	pub left_mempool: f64,
+}
+
+/// Details of the single horizon estimation
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EstimationResult {
+	/// Range of buckets, which has passed the threshold
+	pub pass: Option<EstimatorBucket>,
+	/// Range of buckets, which has failed the threshold
+	pub fail: Option<EstimatorBucket>,
+	/// Decay of the horizon statistics
+	pub decay: f64,
+	/// Number of blocks in the single period of the horizon
+	pub scale: u32,
+}
+
+/// Result of the smart fee estimation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmartFeeEstimate {
+	/// Estimated fee rate (in satoshis per 1000 bytes) or None if there's not enough data
+	pub fee_rate: Option<u64>,
+	/// Number of blocks, for which the estimate is valid
+	pub blocks: u32,
+}
+
+/// Confirmation statistics of the single horizon
+#[derive(Debug, Clone, PartialEq)]
+struct ConfirmationStats {
+	/// Decay, applied to all averages on every block
+	decay: f64,
+	/// Number of blocks in the single period
+	scale: u32,
+	/// Average number of transactions, confirmed within given number of periods, by period and bucket
+	confirmed: Vec<Vec<f64>>,
+	/// Average number of transactions, which have left the memory pool after given number of periods, by period and bucket
+	failed: Vec<Vec<f64>>,
+	/// Average number of confirmed transactions by bucket
+	transactions: Vec<f64>,
+	/// Sum of fee rates of confirmed transactions by bucket
+	fee_rates: Vec<f64>,
+}
+
+/// Memory pool transaction, tracked by the estimator
+#[derive(Debug, Clone, Copy)]
+struct TrackedTransaction {
+	/// Best block height when transaction has entered the memory pool
+	height: u32,
+	/// Fee rate bucket
+	bucket: usize,
+	/// Transaction fee rate (in satoshis per 1000 bytes)
+	fee_rate: u64,
+}
+
+/// Fee estimator
+#[derive(Debug, Clone)]
+pub struct FeeEstimator {
+	/// Upper bounds of fee rate buckets
+	buckets: Vec<f64>,
+	/// Short horizon statistics
+	short: ConfirmationStats,
+	/// Medium horizon statistics
+	medium: ConfirmationStats,
+	/// Long horizon statistics
+	long: ConfirmationStats,
+	/// Memory pool transactions, which are waiting for confirmation
+	tracked: HashMap<SHA256D, TrackedTransaction>,
+	/// Height of the last processed block
+	best_height: u32,
+	/// Height of the first block, which has confirmed tracked transaction
+	first_recorded_height: u32,
+	/// First recorded height of the estimator state, loaded from the disk
+	historical_first_height: u32,
+	/// Best height of the estimator state, loaded from the disk
+	historical_best_height: u32,
+}
+
+impl ConfirmationStats {
+	fn new(buckets_count: usize, periods: usize, scale: u32, decay: f64) -> Self {
+		ConfirmationStats {
+			decay,
+			scale,
+			confirmed: vec![vec![0f64; buckets_count]; periods],
+			failed: vec![vec![0f64; buckets_count]; periods],
+			transactions: vec![0f64; buckets_count],
+			fee_rates: vec![0f64; buckets_count],
+		}
+	}
+
+	/// Maximal confirmation target of this horizon
+	fn max_confirms(&self) -> u32 {
+		self.scale * self.confirmed.len() as u32
+	}
+
+	fn update_moving_averages(&mut self) {
+		let decay = self.decay;
+		let periods = self
+			.confirmed
+			.iter_mut()
+			.chain(self.failed.iter_mut())
+			.flat_map(|period| period.iter_mut());
+		for value in periods.chain(self.transactions.iter_mut()).chain(self.fee_rates.iter_mut()) {
+			*value *= decay;
+		}
+	}
+
+	fn record(&mut self, blocks_to_confirm: u32, bucket: usize, fee_rate: f64) {
+		if blocks_to_confirm < 1 {
+			return;
+		}
+
+		// transaction is counted as confirmed within every period, starting from the one it has been confirmed in
+		let periods_to_confirm = ((blocks_to_confirm + self.scale - 1) / self.scale) as usize;
+		for confirmed in self.confirmed.iter_mut().skip(periods_to_confirm - 1) {
+			confirmed[bucket] += 1.0;
+		}
+		self.transactions[bucket] += 1.0;
+		self.fee_rates[bucket] += fee_rate;
+	}
+
+	fn record_failure(&mut self, blocks_ago: u32, bucket: usize) {
+		if blocks_ago < self.scale {
+			return;
+		}
+
+		let periods_ago = (blocks_ago / self.scale) as usize;
+		for failed in self.failed.iter_mut().take(periods_ago) {
+			failed[bucket] += 1.0;
+		}
+	}
+
+	/// Returns median fee rate of the cheapest range of buckets, where at least `success_threshold` share of
+	/// transactions has been confirmed within `conf_target` blocks.
+	/// `unconfirmed` contains number of transactions, which are waiting for at least `conf_target` blocks, by bucket.
+	fn estimate_median(
+		&self,
+		conf_target: u32,
+		sufficient_txs: f64,
+		success_threshold: f64,
+		buckets: &[f64],
+		unconfirmed: &[f64],
+	) -> (Option<f64>, EstimationResult) {
+		let period = ((conf_target + self.scale - 1) / self.scale) as usize - 1;
+		let max_bucket = buckets.len() - 1;
+
+		let (mut confirmed, mut total, mut in_mempool, mut failed) = (0f64, 0f64, 0f64, 0f64);
+		let (mut near_bucket, mut far_bucket) = (max_bucket, max_bucket);
+		let (mut best_near_bucket, mut best_far_bucket) = (max_bucket, max_bucket);
+		let mut found_answer = false;
+		let mut new_bucket_range = true;
+		let mut passing = true;
+		let mut pass_bucket = EstimatorBucket::default();
+		let mut fail_bucket = None;
+
+		// start from the highest fee rate and combine buckets until there are enough transactions to judge
+		for bucket in (0..=max_bucket).rev() {
+			if new_bucket_range {
+				near_bucket = bucket;
+				new_bucket_range = false;
+			}
+			far_bucket = bucket;
+			confirmed += self.confirmed[period][bucket];
+			total += self.transactions[bucket];
+			failed += self.failed[period][bucket];
+			in_mempool += unconfirmed[bucket];
+
+			if total < sufficient_txs / (1.0 - self.decay) {
+				continue;
+			}
+
+			if confirmed / (total + failed + in_mempool) < success_threshold {
+				// remember the first failing range only
+				if passing {
+					fail_bucket = Some(bucket_range(buckets, far_bucket, near_bucket, confirmed, total, in_mempool, failed));
+					passing = false;
+				}
+			} else {
+				fail_bucket = None;
+				found_answer = true;
+				passing = true;
+				pass_bucket = bucket_range(buckets, 0, 0, confirmed, total, in_mempool, failed);
+				confirmed = 0.0;
+				total = 0.0;
+				in_mempool = 0.0;
+				failed = 0.0;
+				best_near_bucket = near_bucket;
+				best_far_bucket = far_bucket;
+				new_bucket_range = true;
+			}
+		}
+
+		// answer is the median fee rate of the last passing range
+		let mut median = None;
+		let transactions = &self.transactions[best_far_bucket..=best_near_bucket];
+		let transactions_sum: f64 = transactions.iter().sum();
+		if found_answer && transactions_sum > 0.0 {
+			let mut half_sum = transactions_sum / 2.0;
+			for (bucket, transactions) in (best_far_bucket..).zip(transactions) {
+				if *transactions < half_sum {
+					half_sum -= transactions;
+				} else {
+					median = Some(self.fee_rates[bucket] / transactions);
+					break;
+				}
+			}
+
+			let (start, end) = bucket_bounds(buckets, best_far_bucket, best_near_bucket);
+			pass_bucket.start = start;
+			pass_bucket.end = end;
+		}
+
+		// buckets after the last passing range had insufficient data => report them as failed
+		if passing && !new_bucket_range {
+			fail_bucket = Some(bucket_range(buckets, far_bucket, near_bucket, confirmed, total, in_mempool, failed));
+		}
+
+		let result = EstimationResult {
+			pass: median.map(|_| pass_bucket),
+			fail: fail_bucket,
+			decay: self.decay,
+			scale: self.scale,
+		};
+		(median, result)
+	}
+
+	fn serialize(&self, stream: &mut Stream) {
+		append_f64(stream, self.decay);
+		stream.append(&self.scale);
+		append_f64_list(stream, &self.transactions);
+		append_f64_list(stream, &self.fee_rates);
+		stream.append(&(self.confirmed.len() as u32));
+		for period in self.confirmed.iter().chain(self.failed.iter()) {
+			append_f64_list(stream, period);
+		}
+	}
+
+	fn deserialize(reader: &mut Reader<&[u8]>, buckets_count: usize) -> Result<Self, ReaderError> {
+		let decay = read_f64(reader)?;
+		let scale: u32 = reader.read()?;
+		let transactions = read_f64_list(reader, buckets_count)?;
+		let fee_rates = read_f64_list(reader, buckets_count)?;
+		let periods: u32 = reader.read()?;
+		if scale == 0 || periods == 0 || decay <= 0.0 || decay >= 1.0 {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut stats = ConfirmationStats::new(buckets_count, periods as usize, scale, decay);
+		stats.transactions = transactions;
+		stats.fee_rates = fee_rates;
+		for period in stats.confirmed.iter_mut().chain(stats.failed.iter_mut()) {
+			*period = read_f64_list(reader, buckets_count)?;
+		}
+		Ok(stats)
+	}
+}
+
+impl Default for FeeEstimator {
+	fn default() -> Self {
+		FeeEstimator::new()
+	}
+}
+
+impl FeeEstimator {
+	/// Creates new estimator without any statistics
+	pub fn new() -> Self {
+		let mut buckets = Vec::new();
+		let mut bucket = MIN_BUCKET_FEE_RATE;
+		while bucket <= MAX_BUCKET_FEE_RATE {
+			buckets.push(bucket);
+			bucket *= FEE_SPACING;
+		}
+		buckets.push(INF_FEE_RATE);
+
+		let buckets_count = buckets.len();
+		FeeEstimator {
+			buckets,
+			short: ConfirmationStats::new(buckets_count, SHORT_BLOCK_PERIODS, SHORT_SCALE, SHORT_DECAY),
+			medium: ConfirmationStats::new(buckets_count, MEDIUM_BLOCK_PERIODS, MEDIUM_SCALE, MEDIUM_DECAY),
+			long: ConfirmationStats::new(buckets_count, LONG_BLOCK_PERIODS, LONG_SCALE, LONG_DECAY),
+			tracked: HashMap::new(),
+			best_height: 0,
+			first_recorded_height: 0,
+			historical_first_height: 0,
+			historical_best_height: 0,
+		}
+	}
+
+	/// Height of the last processed block
+	pub fn best_height(&self) -> u32 {
+		self.best_height
+	}
+
+	/// Number of memory pool transactions, which are waiting for confirmation
+	pub fn tracked_transactions_count(&self) -> usize {
+		self.tracked.len()
+	}
+
+	/// Maximal confirmation target, tracked by given horizon
+	pub fn highest_target_tracked(&self, horizon: EstimationHorizon) -> u32 {
+		self.stats(horizon).max_confirms()
+	}
+
+	/// Starts tracking transaction, which has entered the memory pool, paying given fee rate (in satoshis per 1000 bytes)
+	pub fn process_transaction(&mut self, hash: SHA256D, fee_rate: u64) {
+		let bucket = self.bucket_index(fee_rate as f64);
+		let height = self.best_height;
+		self.tracked.entry(hash).or_insert(TrackedTransaction { height, bucket, fee_rate });
+	}
+
+	/// Updates statistics with transactions of the new best block.
+	/// Tracked transactions, which are neither confirmed nor in the memory pool, are counted as failed to confirm.
+	pub fn process_block<F>(&mut self, height: u32, transactions: &[SHA256D], is_in_memory_pool: F)
+	where
+		F: Fn(&SHA256D) -> bool,
+	{
+		// blocks of the reorganized chain are ignored
+		if height <= self.best_height {
+			return;
+		}
+
+		let confirmed: HashSet<&SHA256D> = transactions.iter().collect();
+		let left_memory_pool: Vec<SHA256D> = self
+			.tracked
+			.keys()
+			.filter(|hash| !confirmed.contains(hash) && !is_in_memory_pool(hash))
+			.cloned()
+			.collect();
+		for hash in left_memory_pool {
+			self.remove_failed_transaction(&hash);
+		}
+
+		// confirmation time of transactions is unknown if some blocks have been missed
+		if height != self.best_height + 1 {
+			self.tracked.clear();
+		}
+
+		self.best_height = height;
+		for stats in self.all_stats_mut().iter_mut() {
+			stats.update_moving_averages();
+		}
+
+		let mut counted_transactions = 0;
+		for hash in transactions {
+			if let Some(tracked) = self.tracked.remove(hash) {
+				let blocks_to_confirm = height - tracked.height;
+				for stats in self.all_stats_mut().iter_mut() {
+					stats.record(blocks_to_confirm, tracked.bucket, tracked.fee_rate as f64);
+				}
+				counted_transactions += 1;
+			}
+		}
+
+		if self.first_recorded_height == 0 && counted_transactions > 0 {
+			self.first_recorded_height = height;
+		}
+	}
+
+	/// Estimates fee rate (in satoshis per 1000 bytes), required to get confirmed within `conf_target` blocks.
+	/// Conservative estimate also requires fee rate to be sufficient for longer targets.
+	pub fn estimate_smart_fee(&self, conf_target: u32, conservative: bool) -> SmartFeeEstimate {
+		if conf_target == 0 || conf_target > self.long.max_confirms() {
+			return SmartFeeEstimate { fee_rate: None, blocks: 0 };
+		}
+
+		// it is impossible to get meaningful estimate for the next block
+		let conf_target = ::std::cmp::min(::std::cmp::max(conf_target, 2), self.max_usable_estimate());
+		if conf_target <= 1 {
+			return SmartFeeEstimate {
+				fee_rate: None,
+				blocks: conf_target,
+			};
+		}
+
+		let mut estimate = self.estimate_combined_fee(conf_target / 2, HALF_SUCCESS_PCT, true);
+		estimate = higher_estimate(estimate, self.estimate_combined_fee(conf_target, SUCCESS_PCT, true));
+		estimate = higher_estimate(
+			estimate,
+			self.estimate_combined_fee(2 * conf_target, DOUBLE_SUCCESS_PCT, !conservative),
+		);
+		if conservative || estimate.is_none() {
+			estimate = higher_estimate(estimate, self.estimate_conservative_fee(2 * conf_target));
+		}
+
+		SmartFeeEstimate {
+			fee_rate: estimate.map(|estimate| estimate.round() as u64),
+			blocks: conf_target,
+		}
+	}
+
+	/// Estimates fee rate (in satoshis per 1000 bytes), required to get confirmed within `conf_target` blocks
+	/// with given probability, using statistics of the single horizon
+	pub fn estimate_raw_fee(
+		&self,
+		conf_target: u32,
+		success_threshold: f64,
+		horizon: EstimationHorizon,
+	) -> (Option<u64>, EstimationResult) {
+		let stats = self.stats(horizon);
+		if conf_target == 0 || conf_target > stats.max_confirms() || success_threshold > 1.0 {
+			return (None, EstimationResult::default());
+		}
+
+		let sufficient_txs = match horizon {
+			EstimationHorizon::Short => SUFFICIENT_TXS_SHORT,
+			EstimationHorizon::Medium | EstimationHorizon::Long => SUFFICIENT_FEE_TXS,
+		};
+		let (median, result) = self.estimate_median(stats, conf_target, sufficient_txs, success_threshold);
+		(median.map(|median| median.round() as u64), result)
+	}
+
+	/// Serializes estimator statistics. Tracked transactions are not serialized
+	pub fn serialize(&self) -> Bytes {
+		// remember the longest span of blocks, which statistics are based on
+		let (first_height, best_height) = if self.block_span() > self.historical_block_span() {
+			(self.first_recorded_height, self.best_height)
+		} else {
+			(self.historical_first_height, self.historical_best_height)
+		};
+
+		let mut stream = Stream::default();
+		stream
+			.append(&FEE_ESTIMATES_VERSION)
+			.append(&self.best_height)
+			.append(&first_height)
+			.append(&best_height);
+		append_f64_list(&mut stream, &self.buckets);
+		for stats in self.all_stats() {
+			stats.serialize(&mut stream);
+		}
+		stream.out()
+	}
+
+	/// Deserializes estimator statistics. `best_height` is the height of the current best block:
+	/// statistics are dropped if they have been saved too long ago
+	pub fn deserialize(data: &[u8], best_height: u32) -> Result<Self, ReaderError> {
+		let mut reader = Reader::new(data);
+		if reader.read::<u32>()? != FEE_ESTIMATES_VERSION {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut estimator = FeeEstimator::new();
+		estimator.best_height = best_height;
+		let file_best_height: u32 = reader.read()?;
+		estimator.historical_first_height = reader.read()?;
+		estimator.historical_best_height = reader.read()?;
+		// statistics are only compatible if buckets are the same
+		if read_f64_list(&mut reader, estimator.buckets.len())? != estimator.buckets {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let buckets_count = estimator.buckets.len();
+		estimator.short = ConfirmationStats::deserialize(&mut reader, buckets_count)?;
+		estimator.medium = ConfirmationStats::deserialize(&mut reader, buckets_count)?;
+		estimator.long = ConfirmationStats::deserialize(&mut reader, buckets_count)?;
+		if !reader.is_finished() {
+			return Err(ReaderError::UnreadData);
+		}
+
+		// outdated statistics would only give wrong estimates
+		if best_height.saturating_sub(file_best_height) > MAX_FILE_AGE {
+			estimator = FeeEstimator {
+				best_height,
+				..FeeEstimator::new()
+			};
+		}
+
+		Ok(estimator)
+	}
+
+	fn stats(&self, horizon: EstimationHorizon) -> &ConfirmationStats {
+		match horizon {
+			EstimationHorizon::Short => &self.short,
+			EstimationHorizon::Medium => &self.medium,
+			EstimationHorizon::Long => &self.long,
+		}
+	}
+
+	fn all_stats(&self) -> [&ConfirmationStats; 3] {
+		[&self.short, &self.medium, &self.long]
+	}
+
+	fn all_stats_mut(&mut self) -> [&mut ConfirmationStats; 3] {
+		[&mut self.short, &mut self.medium, &mut self.long]
+	}
+
+	fn bucket_index(&self, fee_rate: f64) -> usize {
+		self.buckets
+			.iter()
+			.position(|bucket| *bucket >= fee_rate)
+			.unwrap_or(self.buckets.len() - 1)
+	}
+
+	fn remove_failed_transaction(&mut self, hash: &SHA256D) {
+		if let Some(tracked) = self.tracked.remove(hash) {
+			let blocks_ago = self.best_height - tracked.height;
+			for stats in self.all_stats_mut().iter_mut() {
+				stats.record_failure(blocks_ago, tracked.bucket);
+			}
+		}
+	}
+
+	/// Number of tracked transactions, which are waiting for at least `conf_target` blocks, by bucket
+	fn unconfirmed(&self, conf_target: u32) -> Vec<f64> {
+		let mut unconfirmed = vec![0f64; self.buckets.len()];
+		for tracked in self.tracked.values() {
+			if self.best_height - tracked.height >= conf_target {
+				unconfirmed[tracked.bucket] += 1.0;
+			}
+		}
+		unconfirmed
+	}
+
+	fn estimate_median(
+		&self,
+		stats: &ConfirmationStats,
+		conf_target: u32,
+		sufficient_txs: f64,
+		success_threshold: f64,
+	) -> (Option<f64>, EstimationResult) {
+		stats.estimate_median(
+			conf_target,
+			sufficient_txs,
+			success_threshold,
+			&self.buckets,
+			&self.unconfirmed(conf_target),
+		)
+	}
+
+	/// Estimate for the target, using the shortest horizon, which tracks it
+	fn estimate_combined_fee(&self, conf_target: u32, success_threshold: f64, check_shorter_horizon: bool) -> Option<f64> {
+		if conf_target == 0 || conf_target > self.long.max_confirms() {
+			return None;
+		}
+
+		let mut estimate = if conf_target <= self.short.max_confirms() {
+			self.estimate_median(&self.short, conf_target, SUFFICIENT_TXS_SHORT, success_threshold)
+				.0
+		} else if conf_target <= self.medium.max_confirms() {
+			self.estimate_median(&self.medium, conf_target, SUFFICIENT_FEE_TXS, success_threshold)
+				.0
+		} else {
+			self.estimate_median(&self.long, conf_target, SUFFICIENT_FEE_TXS, success_threshold)
+				.0
+		};
+
+		// if more recent horizon gives lower estimate for its maximal target, use it
+		if check_shorter_horizon {
+			if conf_target > self.medium.max_confirms() {
+				let medium_max = self
+					.estimate_median(&self.medium, self.medium.max_confirms(), SUFFICIENT_FEE_TXS, success_threshold)
+					.0;
+				estimate = lower_estimate(estimate, medium_max);
+			}
+			if conf_target > self.short.max_confirms() {
+				let short_max = self
+					.estimate_median(&self.short, self.short.max_confirms(), SUFFICIENT_TXS_SHORT, success_threshold)
+					.0;
+				estimate = lower_estimate(estimate, short_max);
+			}
+		}
+
+		estimate
+	}
+
+	/// Estimate for the double target, which must also pass for the longer horizons
+	fn estimate_conservative_fee(&self, double_target: u32) -> Option<f64> {
+		let mut estimate = None;
+		if double_target <= self.short.max_confirms() {
+			estimate = self
+				.estimate_median(&self.medium, double_target, SUFFICIENT_FEE_TXS, DOUBLE_SUCCESS_PCT)
+				.0;
+		}
+		if double_target <= self.medium.max_confirms() {
+			let long_estimate = self
+				.estimate_median(&self.long, double_target, SUFFICIENT_FEE_TXS, DOUBLE_SUCCESS_PCT)
+				.0;
+			estimate = higher_estimate(estimate, long_estimate);
+		}
+		estimate
+	}
+
+	/// Maximal target, which could be estimated using collected statistics
+	fn max_usable_estimate(&self) -> u32 {
+		::std::cmp::min(
+			self.long.max_confirms(),
+			::std::cmp::max(self.block_span(), self.historical_block_span()) / 2,
+		)
+	}
+
+	fn block_span(&self) -> u32 {
+		if self.first_recorded_height == 0 {
+			return 0;
+		}
+
+		self.best_height - self.first_recorded_height
+	}
+
+	fn historical_block_span(&self) -> u32 {
+		if self.historical_first_height == 0 || self.historical_best_height < self.historical_first_height {
+			return 0;
+		}
+		if self.best_height.saturating_sub(self.historical_best_height) > OLDEST_ESTIMATE_HISTORY {
+			return 0;
+		}
+
+		self.historical_best_height - self.historical_first_height
+	}
+}
+
+fn bucket_bounds(buckets: &[f64], min_bucket: usize, max_bucket: usize) -> (f64, f64) {
+	let start = if min_bucket == 0 { 0.0 } else { buckets[min_bucket - 1] };
+	(start, buckets[max_bucket])
+}
+
+fn bucket_range(
+	buckets: &[f64],
+	min_bucket: usize,
+	max_bucket: usize,
+	within_target: f64,
+	total_confirmed: f64,
+	in_mempool: f64,
+	left_mempool: f64,
+) -> EstimatorBucket {
+	let (start, end) = bucket_bounds(buckets, min_bucket, max_bucket);
+	EstimatorBucket {
+		start,
+		end,
+		within_target,
+		total_confirmed,
+		in_mempool,
+		left_mempool,
+	}
+}
+
+fn higher_estimate(first: Option<f64>, second: Option<f64>) -> Option<f64> {
+	match (first, second) {
+		(Some(first), Some(second)) => Some(first.max(second)),
+		(first, None) => first,
+		(None, second) => second,
+	}
+}
+
+fn lower_estimate(first: Option<f64>, second: Option<f64>) -> Option<f64> {
+	match (first, second) {
+		(Some(first), Some(second)) => Some(first.min(second)),
+		(first, None) => first,
+		(None, second) => second,
+	}
+}
+
+fn append_f64(stream: &mut Stream, value: f64) {
+	stream.append(&value.to_bits());
+}
+
+fn append_f64_list(stream: &mut Stream, values: &[f64]) {
+	let values: Vec<u64> = values.iter().map(|value| value.to_bits()).collect();
+	stream.append_list::<u64, u64>(&values);
+}
+
+fn read_f64(reader: &mut Reader<&[u8]>) -> Result<f64, ReaderError> {
+	reader.read::<u64>().map(f64::from_bits)
+}
+
+fn read_f64_list(reader: &mut Reader<&[u8]>, expected_len: usize) -> Result<Vec<f64>, ReaderError> {
+	let values: Vec<u64> = reader.read_list_max(expected_len)?;
+	if values.len() != expected_len {
+		return Err(ReaderError::MalformedData);
+	}
+
+	Ok(values.into_iter().map(f64::from_bits).collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{EstimationHorizon, FeeEstimator, SmartFeeEstimate, DOUBLE_SUCCESS_PCT, MAX_FILE_AGE};
+	use bitcrypto::{Hash, SHA256D};
+	use ser::Error as ReaderError;
+
+	fn transaction_hash(index: u32) -> SHA256D {
+		let mut hash = [0u8; 32];
+		hash[0..4].copy_from_slice(&index.to_le_bytes());
+		SHA256D::from_inner(hash)
+	}
+
+	/// Every block confirms all transactions paying 20_000 sat/kB, while transactions paying 2_000 sat/kB never confirm
+	fn prepare_estimator(blocks: u32) -> FeeEstimator {
+		let mut estimator = FeeEstimator::new();
+		let mut index = 0;
+		let mut stuck = Vec::new();
+		for height in 1..=blocks {
+			let mut confirmed = Vec::new();
+			for _ in 0..10 {
+				index += 1;
+				estimator.process_transaction(transaction_hash(index), 20_000);
+				confirmed.push(transaction_hash(index));
+			}
+			index += 1;
+			estimator.process_transaction(transaction_hash(index), 2_000);
+			stuck.push(transaction_hash(index));
+
+			estimator.process_block(height, &confirmed, |hash| stuck.contains(hash));
+		}
+		estimator
+	}
+
+	#[test]
+	fn fee_estimator_no_data() {
+		let estimator = FeeEstimator::new();
+		assert_eq!(
+			estimator.estimate_smart_fee(6, true),
+			SmartFeeEstimate { fee_rate: None, blocks: 0 }
+		);
+		assert_eq!(
+			estimator.estimate_smart_fee(0, true),
+			SmartFeeEstimate { fee_rate: None, blocks: 0 }
+		);
+		assert_eq!(estimator.estimate_raw_fee(6, DOUBLE_SUCCESS_PCT, EstimationHorizon::Short).0, None);
+		assert_eq!(estimator.highest_target_tracked(EstimationHorizon::Short), 12);
+		assert_eq!(estimator.highest_target_tracked(EstimationHorizon::Medium), 48);
+		assert_eq!(estimator.highest_target_tracked(EstimationHorizon::Long), 1008);
+	}
+
+	#[test]
+	fn fee_estimator_estimates_confirmed_fee_rate() {
+		let estimator = prepare_estimator(100);
+		assert_eq!(estimator.best_height(), 100);
+		// stuck transactions are still tracked
+		assert_eq!(estimator.tracked_transactions_count(), 100);
+
+		let estimate = estimator.estimate_smart_fee(6, true);
+		assert_eq!(
+			estimate,
+			SmartFeeEstimate {
+				fee_rate: Some(20_000),
+				blocks: 6
+			}
+		);
+		assert_eq!(estimator.estimate_smart_fee(6, false).fee_rate, Some(20_000));
+		// target of the single block is not tracked
+		assert_eq!(estimator.estimate_smart_fee(1, false).blocks, 2);
+		// target is limited by the span of collected statistics
+		assert_eq!(estimator.estimate_smart_fee(1008, false).blocks, 49);
+
+		let (fee_rate, result) = estimator.estimate_raw_fee(6, DOUBLE_SUCCESS_PCT, EstimationHorizon::Short);
+		assert_eq!(fee_rate, Some(20_000));
+		let pass = result.pass.unwrap();
+		assert!(pass.start < 20_000.0 && pass.end >= 20_000.0);
+		assert_eq!(pass.in_mempool, 0.0);
+		// the failing range contains stuck transactions
+		let fail = result.fail.unwrap();
+		assert!(fail.start < 2_000.0 && fail.end >= 2_000.0);
+		assert!(fail.in_mempool > 0.0);
+		assert_eq!(result.scale, 1);
+	}
+
+	#[test]
+	fn fee_estimator_counts_evicted_transactions_as_failed() {
+		let mut estimator = prepare_estimator(100);
+		let (_, result) = estimator.estimate_raw_fee(12, DOUBLE_SUCCESS_PCT, EstimationHorizon::Short);
+		assert_eq!(result.fail.unwrap().left_mempool, 0.0);
+
+		// all stuck transactions are evicted from the memory pool
+		estimator.process_block(101, &[], |_| false);
+		assert_eq!(estimator.tracked_transactions_count(), 0);
+		let (_, result) = estimator.estimate_raw_fee(12, DOUBLE_SUCCESS_PCT, EstimationHorizon::Short);
+		assert!(result.fail.unwrap().left_mempool > 0.0);
+	}
+
+	#[test]
+	fn fee_estimator_ignores_transactions_when_blocks_are_missed() {
+		let mut estimator = FeeEstimator::new();
+		estimator.process_transaction(transaction_hash(1), 20_000);
+		estimator.process_block(10, &[transaction_hash(1)], |_| true);
+		assert_eq!(estimator.short.transactions.iter().sum::<f64>(), 0.0);
+		assert_eq!(estimator.tracked_transactions_count(), 0);
+		// block of the reorganized chain
+		estimator.process_transaction(transaction_hash(2), 20_000);
+		estimator.process_block(10, &[transaction_hash(2)], |_| true);
+		assert_eq!(estimator.tracked_transactions_count(), 1);
+	}
+
+	#[test]
+	fn fee_estimator_serialization_roundtrip() {
+		let estimator = prepare_estimator(100);
+		let restored = FeeEstimator::deserialize(&estimator.serialize(), 100).unwrap();
+		assert_eq!(restored.tracked_transactions_count(), 0);
+		assert_eq!(restored.best_height(), 100);
+		assert_eq!(restored.short, estimator.short);
+		assert_eq!(restored.medium, estimator.medium);
+		assert_eq!(restored.long, estimator.long);
+		// statistics of the previous run are used after restart
+		assert_eq!(restored.estimate_smart_fee(6, true), estimator.estimate_smart_fee(6, true));
+
+		let mut serialized = estimator.serialize().take();
+		assert_eq!(
+			FeeEstimator::deserialize(&serialized[..100], 100).unwrap_err(),
+			ReaderError::UnexpectedEnd
+		);
+		serialized[0] = 2;
+		assert_eq!(FeeEstimator::deserialize(&serialized, 100).unwrap_err(), ReaderError::MalformedData);
+	}
+
+	#[test]
+	fn fee_estimator_drops_outdated_statistics() {
+		let estimator = prepare_estimator(100);
+		let serialized = estimator.serialize();
+
+		let restored = FeeEstimator::deserialize(&serialized, 100 + MAX_FILE_AGE).unwrap();
+		assert_eq!(restored.short, estimator.short);
+		assert!(restored.estimate_smart_fee(6, true).fee_rate.is_some());
+
+		let restored = FeeEstimator::deserialize(&serialized, 101 + MAX_FILE_AGE).unwrap();
+		assert_eq!(restored.best_height(), 101 + MAX_FILE_AGE);
+		assert_eq!(restored.short, FeeEstimator::new().short);
+		assert_eq!(restored.estimate_smart_fee(6, true).fee_rate, None);
+	}
+}
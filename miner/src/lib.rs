@@ -13,17 +13,23 @@ extern crate verification;
 
 pub use block_assembler::BlockAssembler;
 pub use block_template::BlockTemplate;
-pub use cpu_miner::{find_solution, mine_block};
+pub use cpu_miner::{
+	find_solution, mine_block, CoinbaseTransactionBuilder, CpuMiner, SimpleCoinbaseTransactionBuilder, VERSION_ROLLING_MASK,
+};
 #[cfg(feature = "test-helpers")]
 pub use fee::NonZeroFeeCalculator;
 pub use fee::{transaction_fee, transaction_fee_rate, FeeCalculator};
+pub use fee_estimator::{EstimationHorizon, EstimationResult, EstimatorBucket, FeeEstimator, SmartFeeEstimate, DOUBLE_SUCCESS_PCT};
 pub use memory_pool::{
-	DoubleSpendCheckResult, HashedOutPoint, Information as MemoryPoolInformation, MemoryPool, NonFinalDoubleSpendSet,
-	OrderingStrategy as MemoryPoolOrderingStrategy,
+	DoubleSpendCheckResult, HashedOutPoint, Information as MemoryPoolInformation, Limits as MemoryPoolLimits, MemoryPool,
+	NonFinalDoubleSpendSet, OrderingStrategy as MemoryPoolOrderingStrategy,
 };
+pub use memory_pool_dump::{MemoryPoolDump, MemoryPoolDumpEntry};
 
 mod block_assembler;
 pub mod block_template;
 mod cpu_miner;
 mod fee;
+mod fee_estimator;
 mod memory_pool;
+mod memory_pool_dump;
@@ -6,10 +6,12 @@
 //! It also guarantees that ancestor-descendant relation won't break during ordered removal (ancestors always removed
 //! before descendants). Removal using `remove_by_hash` can break this rule.
 use crate::fee::MemoryPoolFeeCalculator;
+use crate::fee_estimator::FeeEstimator;
 use bitcrypto::SHA256D;
 use chain::{IndexedTransaction, OutPoint, Transaction, TransactionOutput};
 use heapsize::HeapSizeOf;
 use primitives::bytes::Bytes;
+use primitives::time::{RealTime, Time};
 use ser::{serialize, Serializable};
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
@@ -18,6 +20,14 @@ use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::hash::{Hash, Hasher};
 use storage::{TransactionOutputProvider, TransactionProvider};
+use verification::{MemoryPoolLimitError, ReplacementError};
+
+/// Maximal number of memory pool transactions, which could be evicted by single replacement (BIP125)
+const MAX_REPLACEMENT_CANDIDATES: usize = 100;
+/// Fee rate (in satoshis per byte) the replacement must pay for its own size on top of the fees of replaced transactions
+const INCREMENTAL_RELAY_FEE_RATE: u64 = 1;
+/// Time (in seconds), during which rolling minimal fee rate of the full memory pool is halved
+const ROLLING_FEE_HALFLIFE: u64 = 12 * 60 * 60;
 
 /// Transactions ordering strategy
 #[cfg_attr(feature = "cargo-clippy", allow(enum_variant_names))]
@@ -38,6 +48,27 @@ pub struct Information {
 	pub transactions_count: usize,
 	/// Total number of bytes occupied by transactions from the `MemoryPool`
 	pub transactions_size_in_bytes: usize,
+	/// Maximal number of bytes, which could be occupied by transactions from the `MemoryPool`
+	pub max_size_in_bytes: usize,
+	/// Minimal fee rate (in satoshis per 1000 bytes) of transactions, accepted to the `MemoryPool`
+	pub min_fee_rate: u64,
+}
+
+/// `MemoryPool` limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+	/// Maximal total size of memory pool transactions. Transactions with lowest package score are evicted, when exceeded
+	pub max_size_in_bytes: usize,
+	/// Transactions, which have entered the memory pool more than this number of seconds ago, are evicted
+	pub expiry_time: u64,
+	/// Maximal number of in-pool ancestors of the transaction (including transaction itself)
+	pub max_ancestors_count: usize,
+	/// Maximal total size of the transaction and its in-pool ancestors
+	pub max_ancestors_size: usize,
+	/// Maximal number of in-pool descendants of the transaction (including transaction itself)
+	pub max_descendants_count: usize,
+	/// Maximal total size of the transaction and its in-pool descendants
+	pub max_descendants_size: usize,
 }
 
 /// Transactions memory pool
@@ -47,6 +78,20 @@ pub struct MemoryPool {
 	storage: Storage,
 	/// Do we accept zero fee transactions?
 	accept_zero_fee_transactions: bool,
+	/// Do we replace transactions, which do not signal replaceability?
+	full_rbf: bool,
+	/// Memory pool limits
+	limits: Limits,
+	/// Minimal fee rate (in satoshis per 1000 bytes), raised when transactions are evicted from the full memory pool
+	rolling_min_fee_rate: u64,
+	/// Time (seconds since UNIX epoch) when `rolling_min_fee_rate` has been raised last time
+	rolling_min_fee_rate_time: u64,
+	/// Estimator of fee rates, required to get transactions confirmed
+	fee_estimator: FeeEstimator,
+	/// Virtual fees of transactions, which are applied when transaction enters the memory pool
+	fee_deltas: HashMap<SHA256D, i64>,
+	/// Own transactions, which have not been requested by any peer yet
+	unbroadcast: HashSet<SHA256D>,
 }
 
 /// Single entry
@@ -62,6 +107,8 @@ pub struct Entry {
 	pub size: usize,
 	/// Throughout index of this transaction in memory pool (non persistent)
 	pub storage_index: u64,
+	/// Time (seconds since UNIX epoch) when this transaction has entered the memory pool
+	pub time: u64,
 	/// Transaction fee (stored for efficiency)
 	pub miner_fee: u64,
 	/// Virtual transaction fee (a way to prioritize/penalize transaction)
@@ -154,21 +201,26 @@ pub struct HashedOutPoint {
 pub enum DoubleSpendCheckResult {
 	/// No double spend
 	NoDoubleSpend,
-	/// Input {self.1, self.2} of new transaction is already spent in previous final memory-pool transaction {self.0}
+	/// Input {self.1, self.2} of new transaction is already spent in previous non-replaceable memory-pool transaction {self.0}
 	DoubleSpend(SHA256D, SHA256D, u32),
-	/// Some inputs of new transaction are already spent by non-final memory-pool transactions
+	/// Some inputs of new transaction are already spent by replaceable memory-pool transactions
 	NonFinalDoubleSpend(NonFinalDoubleSpendSet),
 }
 
 /// Set of transaction outputs, which can be replaced if newer transaction
-/// replaces non-final transaction in memory pool
+/// replaces replaceable (BIP125) transactions in memory pool
 #[derive(Debug, PartialEq)]
 pub struct NonFinalDoubleSpendSet {
-	/// Double-spend outputs (outputs of newer transaction, which are also spent by nonfinal transactions of mempool)
+	/// Double-spend outputs (outputs of newer transaction, which are also spent by replaceable transactions of mempool)
 	pub double_spends: HashSet<HashedOutPoint>,
 	/// Outputs which also will be removed from memory pool in case of newer transaction insertion
-	/// (i.e. outputs of nonfinal transactions && their descendants)
+	/// (i.e. outputs of replaceable transactions && their descendants)
 	pub dependent_spends: HashSet<HashedOutPoint>,
+	/// Hashes of memory pool transactions, which are directly conflicting with newer transaction
+	pub conflicts: HashSet<SHA256D>,
+	/// Hashes of memory pool transactions, which will be removed in case of newer transaction insertion
+	/// (i.e. conflicting transactions && their descendants)
+	pub replaced: HashSet<SHA256D>,
 }
 
 impl From<OutPoint> for HashedOutPoint {
@@ -451,21 +503,35 @@ impl Storage {
 		})
 	}
 
-	pub fn check_double_spend(&self, transaction: &Transaction) -> DoubleSpendCheckResult {
+	pub fn is_bip125_replaceable(&self, h: &SHA256D) -> bool {
+		// transaction is replaceable if it signals replaceability itself or if any of its in-pool ancestors does
+		self.by_hash.get(h).map_or(false, |entry| {
+			entry.transaction.signals_replaceability()
+				|| entry.ancestors.iter().any(|ancestor| {
+					self.by_hash
+						.get(ancestor)
+						.map_or(false, |ancestor_entry| ancestor_entry.transaction.signals_replaceability())
+				})
+		})
+	}
+
+	pub fn check_double_spend(&self, transaction: &Transaction, full_rbf: bool) -> DoubleSpendCheckResult {
 		let mut double_spends: HashSet<HashedOutPoint> = HashSet::new();
 		let mut dependent_spends: HashSet<HashedOutPoint> = HashSet::new();
+		let mut conflicts: HashSet<SHA256D> = HashSet::new();
+		let mut replaced: HashSet<SHA256D> = HashSet::new();
 
 		for input in &transaction.inputs {
 			// find transaction that spends the same output
 			let prevout: HashedOutPoint = input.previous_output.clone().into();
 			if let Some(entry_hash) = self.by_previous_output.get(&prevout).cloned() {
-				// check if this is final transaction. If so, that's a potential double-spend error
-				let entry = self.by_hash.get(&entry_hash).expect("checked that it exists line above; qed");
-				if entry.transaction.is_final() {
+				// check if this transaction could be replaced. If not, that's a potential double-spend error
+				if !full_rbf && !self.is_bip125_replaceable(&entry_hash) {
 					return DoubleSpendCheckResult::DoubleSpend(entry_hash, prevout.out_point.hash, prevout.out_point.index);
 				}
 				// else remember this double spend
 				double_spends.insert(prevout.clone());
+				conflicts.insert(entry_hash);
 				// and 'virtually' remove entry && all descendants from mempool
 				let mut queue: VecDeque<HashedOutPoint> = VecDeque::new();
 				queue.push_back(prevout);
@@ -476,6 +542,7 @@ impl Storage {
 							.by_hash
 							.get(&dependent_entry_hash)
 							.expect("checked that it exists line above; qed");
+						replaced.insert(dependent_entry_hash.clone());
 						let dependent_outputs: Vec<_> = dependent_entry
 							.transaction
 							.outputs
@@ -502,10 +569,63 @@ impl Storage {
 			DoubleSpendCheckResult::NonFinalDoubleSpend(NonFinalDoubleSpendSet {
 				double_spends,
 				dependent_spends,
+				conflicts,
+				replaced,
 			})
 		}
 	}
 
+	pub fn check_replacement(
+		&self,
+		transaction: &Transaction,
+		fee: u64,
+		double_spends: &NonFinalDoubleSpendSet,
+	) -> Result<(), ReplacementError> {
+		// replacement can't evict too many transactions
+		if double_spends.replaced.len() > MAX_REPLACEMENT_CANDIDATES {
+			return Err(ReplacementError::TooManyReplacements(double_spends.replaced.len()));
+		}
+
+		// replacement may only spend outputs of unconfirmed transactions, which are also spent by conflicting transactions
+		let conflicts_parents: HashSet<SHA256D> = double_spends
+			.conflicts
+			.iter()
+			.filter_map(|hash| self.by_hash.get(hash))
+			.flat_map(|entry| entry.transaction.inputs.iter().map(|input| input.previous_output.hash))
+			.collect();
+		for input in &transaction.inputs {
+			let parent_hash = &input.previous_output.hash;
+			if !conflicts_parents.contains(parent_hash) && self.by_hash.contains_key(parent_hash) {
+				return Err(ReplacementError::NewUnconfirmedInput(*parent_hash, input.previous_output.index));
+			}
+		}
+
+		// replacement must pay higher fee rate (per virtual byte) than every conflicting transaction
+		let vsize = (transaction.weight() + 3) / 4;
+		for hash in &double_spends.conflicts {
+			let entry = self.by_hash.get(hash).expect("conflicts are read from by_hash; qed");
+			let conflict_fee = (entry.miner_fee as i64 + entry.miner_virtual_fee).max(0) as u128;
+			let conflict_vsize = (entry.transaction.weight() + 3) / 4;
+			if fee as u128 * conflict_vsize as u128 <= conflict_fee * vsize as u128 {
+				return Err(ReplacementError::InsufficientFeeRate(*hash));
+			}
+		}
+
+		// replacement must pay for all replaced transactions + for its own relay
+		let replaced_fee: i64 = double_spends
+			.replaced
+			.iter()
+			.filter_map(|hash| self.by_hash.get(hash))
+			.map(|entry| entry.miner_fee as i64 + entry.miner_virtual_fee)
+			.sum();
+		let required_fee = replaced_fee.max(0) as u64 + vsize as u64 * INCREMENTAL_RELAY_FEE_RATE;
+		if fee < required_fee {
+			return Err(ReplacementError::InsufficientFee(fee, required_fee));
+		}
+
+		Ok(())
+	}
+
 	pub fn remove_by_prevout(&mut self, prevout: &OutPoint) -> Option<Vec<IndexedTransaction>> {
 		let mut queue: VecDeque<OutPoint> = VecDeque::new();
 		let mut removed: Vec<IndexedTransaction> = Vec::new();
@@ -525,6 +645,42 @@ impl Storage {
 		Some(removed)
 	}
 
+	pub fn remove_with_descendants(&mut self, h: &SHA256D) -> Vec<IndexedTransaction> {
+		let entry = match self.remove_by_hash(h) {
+			Some(entry) => entry,
+			None => return Vec::new(),
+		};
+
+		let outputs_count = entry.transaction.outputs.len();
+		let mut removed = vec![IndexedTransaction::new(entry.hash, entry.transaction)];
+		for index in 0..outputs_count {
+			if let Some(descendants) = self.remove_by_prevout(&OutPoint {
+				hash: *h,
+				index: index as u32,
+			}) {
+				removed.extend(descendants);
+			}
+		}
+		removed
+	}
+
+	pub fn get_descendants(&self, h: &SHA256D) -> HashSet<SHA256D> {
+		let mut descendants: HashSet<SHA256D> = HashSet::new();
+		let mut queue: VecDeque<SHA256D> = VecDeque::new();
+		queue.push_back(*h);
+		while let Some(hash) = queue.pop_front() {
+			if let Some(children) = self.references.by_input.get(&hash) {
+				for child in children {
+					// `by_input` could still reference removed transactions
+					if self.by_hash.contains_key(child) && descendants.insert(*child) {
+						queue.push_back(*child);
+					}
+				}
+			}
+		}
+		descendants
+	}
+
 	pub fn remove_by_parent_hash(&mut self, h: &SHA256D) -> Option<Vec<IndexedTransaction>> {
 		// this code will run only when ancestor transaction is inserted
 		// in memory pool after its descendants
@@ -702,6 +858,19 @@ impl HeapSizeOf for OrderedReferenceStorage {
 	}
 }
 
+impl Default for Limits {
+	fn default() -> Self {
+		Limits {
+			max_size_in_bytes: 300_000_000,
+			expiry_time: 336 * 60 * 60,
+			max_ancestors_count: 25,
+			max_ancestors_size: 101_000,
+			max_descendants_count: 25,
+			max_descendants_size: 101_000,
+		}
+	}
+}
+
 impl Default for MemoryPool {
 	fn default() -> Self {
 		MemoryPool::new()
@@ -714,23 +883,74 @@ impl MemoryPool {
 		MemoryPool {
 			storage: Storage::new(),
 			accept_zero_fee_transactions: false,
+			full_rbf: false,
+			limits: Limits::default(),
+			rolling_min_fee_rate: 0,
+			rolling_min_fee_rate_time: 0,
+			fee_estimator: FeeEstimator::new(),
+			fee_deltas: HashMap::new(),
+			unbroadcast: HashSet::new(),
 		}
 	}
 
+	/// Set memory pool limits.
+	pub fn set_limits(&mut self, limits: Limits) {
+		self.limits = limits;
+	}
+
+	/// Get memory pool limits.
+	pub fn limits(&self) -> &Limits {
+		&self.limits
+	}
+
 	/// Accept zero fee transactions.
 	pub fn accept_zero_fee_transactions(&mut self) {
 		self.accept_zero_fee_transactions = true;
 	}
 
+	/// Allow replacement of transactions, which do not signal replaceability (full-RBF).
+	pub fn enable_full_rbf(&mut self) {
+		self.full_rbf = true;
+	}
+
+	/// Get fee estimator.
+	pub fn fee_estimator(&self) -> &FeeEstimator {
+		&self.fee_estimator
+	}
+
+	/// Replace fee estimator (used when estimator is restored from the disk).
+	pub fn set_fee_estimator(&mut self, fee_estimator: FeeEstimator) {
+		self.fee_estimator = fee_estimator;
+	}
+
+	/// Updates fee estimator with transactions of the new best block at given height.
+	/// Must be called before block transactions are removed from the `MemoryPool`.
+	pub fn process_best_block(&mut self, height: u32, transactions: &[SHA256D]) {
+		let storage = &self.storage;
+		self.fee_estimator
+			.process_block(height, transactions, |hash| storage.contains(hash));
+		// confirmed transactions won't enter the memory pool again
+		for hash in transactions {
+			self.fee_deltas.remove(hash);
+			self.unbroadcast.remove(hash);
+		}
+	}
+
 	/// Insert verified transaction to the `MemoryPool`
 	pub fn insert_verified<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) {
 		if let Some(entry) = self.make_entry(t, fc) {
+			// fee rate of transactions with inpool ancestors depends on ancestors => they are not used for estimation
+			if entry.ancestors.is_empty() {
+				let vsize = (entry.transaction.weight() + 3) / 4;
+				self.fee_estimator
+					.process_transaction(entry.hash, entry.miner_fee * 1000 / vsize as u64);
+			}
 			let descendants = self.storage.remove_by_parent_hash(&entry.hash);
-			self.storage.insert(entry);
+			self.insert_entry(entry);
 			if let Some(descendants_iter) = descendants.map(|d| d.into_iter()) {
 				for descendant in descendants_iter {
 					if let Some(descendant_entry) = self.make_entry(descendant, fc) {
-						self.storage.insert(descendant_entry);
+						self.insert_entry(descendant_entry);
 					}
 				}
 			}
@@ -752,7 +972,80 @@ impl MemoryPool {
 
 	/// Checks if `transaction` spends some outputs, already spent by inpool transactions.
 	pub fn check_double_spend(&self, transaction: &Transaction) -> DoubleSpendCheckResult {
-		self.storage.check_double_spend(transaction)
+		self.storage.check_double_spend(transaction, self.full_rbf)
+	}
+
+	/// Checks if `transaction`, paying given `fee`, could replace conflicting inpool transactions (BIP125).
+	pub fn check_replacement(
+		&self,
+		transaction: &Transaction,
+		fee: u64,
+		double_spends: &NonFinalDoubleSpendSet,
+	) -> Result<(), ReplacementError> {
+		self.storage.check_replacement(transaction, fee, double_spends)
+	}
+
+	/// Checks if `transaction`, paying given `fee`, could be accepted to the memory pool without exceeding its limits.
+	pub fn check_limits(&self, transaction: &Transaction, fee: u64) -> Result<(), MemoryPoolLimitError> {
+		// transaction must pay at least current minimal fee of the memory pool
+		let size = self.get_transaction_size(transaction);
+		let required_fee = self.min_fee_rate() * size as u64 / 1000;
+		if fee < required_fee {
+			return Err(MemoryPoolLimitError::InsufficientFee(fee, required_fee));
+		}
+
+		let ancestors = self.get_ancestors(transaction);
+		if ancestors.len() + 1 > self.limits.max_ancestors_count {
+			return Err(MemoryPoolLimitError::TooManyAncestors(ancestors.len() + 1));
+		}
+		let ancestors_size = size
+			+ ancestors
+				.iter()
+				.filter_map(|hash| self.storage.get_by_hash(hash))
+				.map(|entry| entry.size)
+				.sum::<usize>();
+		if ancestors_size > self.limits.max_ancestors_size {
+			return Err(MemoryPoolLimitError::TooLargeAncestors(ancestors_size));
+		}
+
+		// every ancestor gets new descendant
+		for ancestor_hash in &ancestors {
+			let descendants = self.storage.get_descendants(ancestor_hash);
+			if descendants.len() + 2 > self.limits.max_descendants_count {
+				return Err(MemoryPoolLimitError::TooManyDescendants(*ancestor_hash));
+			}
+			let descendants_size = size
+				+ descendants
+					.iter()
+					.chain(::std::iter::once(ancestor_hash))
+					.filter_map(|hash| self.storage.get_by_hash(hash))
+					.map(|entry| entry.size)
+					.sum::<usize>();
+			if descendants_size > self.limits.max_descendants_size {
+				return Err(MemoryPoolLimitError::TooLargeDescendants(*ancestor_hash));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Evicts expired transactions and transactions with lowest package score, if memory pool exceeds its size limit.
+	/// Returns all evicted transactions.
+	pub fn limit_size(&mut self) -> Vec<IndexedTransaction> {
+		let now = RealTime.now().as_secs();
+		let mut removed = self.remove_expired(now);
+		removed.extend(self.trim_to_size(now));
+		removed
+	}
+
+	/// Returns current minimal fee rate (in satoshis per 1000 bytes) of transactions, accepted to the memory pool.
+	pub fn min_fee_rate(&self) -> u64 {
+		self.min_fee_rate_at(RealTime.now().as_secs())
+	}
+
+	/// Returns true if inpool transaction signals replaceability, either explicitly or through its inpool ancestors (BIP125).
+	pub fn is_bip125_replaceable(&self, hash: &SHA256D) -> bool {
+		self.storage.is_bip125_replaceable(hash)
 	}
 
 	/// Removes transaction (and all its descendants) which has spent given output
@@ -790,11 +1083,64 @@ impl MemoryPool {
 		self.storage.remove_n_with_strategy(n, strategy)
 	}
 
-	/// Set miner virtual fee for transaction
+	/// Set miner virtual fee for transaction. Virtual fee is remembered even if transaction is not in the memory pool yet
 	pub fn set_virtual_fee(&mut self, h: &SHA256D, virtual_fee: i64) {
+		if virtual_fee == 0 {
+			self.fee_deltas.remove(h);
+		} else {
+			self.fee_deltas.insert(*h, virtual_fee);
+		}
 		self.storage.set_virtual_fee(h, virtual_fee)
 	}
 
+	/// Returns virtual fees of transactions, which are not in the memory pool
+	pub fn pending_fee_deltas(&self) -> Vec<(SHA256D, i64)> {
+		let mut fee_deltas: Vec<_> = self
+			.fee_deltas
+			.iter()
+			.filter(|(hash, _)| !self.storage.contains(hash))
+			.map(|(hash, fee_delta)| (*hash, *fee_delta))
+			.collect();
+		fee_deltas.sort_unstable();
+		fee_deltas
+	}
+
+	/// Marks own transaction as not broadcasted yet
+	pub fn add_unbroadcast(&mut self, h: &SHA256D) {
+		if self.storage.contains(h) {
+			self.unbroadcast.insert(*h);
+		}
+	}
+
+	/// Marks transaction as broadcasted (i.e. requested by some peer)
+	pub fn remove_unbroadcast(&mut self, h: &SHA256D) {
+		self.unbroadcast.remove(h);
+	}
+
+	/// Returns hashes of inpool transactions, which have not been broadcasted yet
+	pub fn unbroadcast(&self) -> Vec<SHA256D> {
+		let mut unbroadcast: Vec<_> = self
+			.unbroadcast
+			.iter()
+			.filter(|hash| self.storage.contains(hash))
+			.cloned()
+			.collect();
+		unbroadcast.sort_unstable();
+		unbroadcast
+	}
+
+	/// Set time when transaction has entered the memory pool (used when memory pool is restored from the dump)
+	pub fn set_entry_time(&mut self, h: &SHA256D, time: u64) {
+		if let Some(entry) = self.storage.by_hash.get_mut(h) {
+			entry.time = time;
+		}
+	}
+
+	/// Get memory pool entry by transaction hash
+	pub fn get_entry(&self, hash: &SHA256D) -> Option<&Entry> {
+		self.storage.get_by_hash(hash)
+	}
+
 	/// Get transaction by hash
 	pub fn get(&self, hash: &SHA256D) -> Option<&Transaction> {
 		self.storage.get_by_hash(hash).map(|entry| &entry.transaction)
@@ -811,6 +1157,8 @@ impl MemoryPool {
 		Information {
 			transactions_count: self.storage.by_hash.len(),
 			transactions_size_in_bytes: self.storage.transactions_size_in_bytes,
+			max_size_in_bytes: self.limits.max_size_in_bytes,
+			min_fee_rate: self.min_fee_rate(),
 		}
 	}
 
@@ -825,6 +1173,14 @@ impl MemoryPool {
 		self.storage.is_output_spent(prevout)
 	}
 
+	fn insert_entry(&mut self, entry: Entry) {
+		let hash = entry.hash;
+		self.storage.insert(entry);
+		if let Some(fee_delta) = self.fee_deltas.get(&hash) {
+			self.storage.set_virtual_fee(&hash, *fee_delta);
+		}
+	}
+
 	fn make_entry<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) -> Option<Entry> {
 		let ancestors = self.get_ancestors(&t.raw);
 		let size = self.get_transaction_size(&t.raw);
@@ -841,6 +1197,7 @@ impl MemoryPool {
 			hash: t.hash,
 			ancestors,
 			storage_index,
+			time: RealTime.now().as_secs(),
 			size,
 			miner_fee,
 			miner_virtual_fee: 0,
@@ -851,6 +1208,70 @@ impl MemoryPool {
 		})
 	}
 
+	fn remove_expired(&mut self, now: u64) -> Vec<IndexedTransaction> {
+		let min_time = now.saturating_sub(self.limits.expiry_time);
+		let expired: Vec<SHA256D> = self
+			.storage
+			.by_hash
+			.values()
+			.filter(|entry| entry.time < min_time)
+			.map(|entry| entry.hash)
+			.collect();
+
+		let mut removed = Vec::new();
+		for hash in expired {
+			// could be already removed as a descendant of other expired transaction
+			removed.extend(self.storage.remove_with_descendants(&hash));
+		}
+		removed
+	}
+
+	fn trim_to_size(&mut self, now: u64) -> Vec<IndexedTransaction> {
+		let mut removed = Vec::new();
+		while self.storage.transactions_size_in_bytes > self.limits.max_size_in_bytes {
+			// evict package with the lowest score
+			let (hash, package_fee_rate) = match self.storage.references.ordered.by_package_score.iter().next_back() {
+				Some(entry) => (
+					entry.hash,
+					(entry.package_miner_fee as i64 + entry.package_miner_virtual_fee).max(0) as u64 * 1000 / entry.package_size as u64,
+				),
+				None => break,
+			};
+
+			// only accept new transactions, paying more than evicted ones
+			let min_fee_rate = package_fee_rate + INCREMENTAL_RELAY_FEE_RATE * 1000;
+			if min_fee_rate > self.min_fee_rate_at(now) {
+				self.rolling_min_fee_rate = min_fee_rate;
+				self.rolling_min_fee_rate_time = now;
+			}
+
+			removed.extend(self.storage.remove_with_descendants(&hash));
+		}
+		removed
+	}
+
+	fn min_fee_rate_at(&self, now: u64) -> u64 {
+		if self.rolling_min_fee_rate == 0 {
+			return 0;
+		}
+
+		// decay faster when the memory pool is far from being full
+		let mut halflife = ROLLING_FEE_HALFLIFE;
+		if self.storage.transactions_size_in_bytes < self.limits.max_size_in_bytes / 4 {
+			halflife /= 4;
+		} else if self.storage.transactions_size_in_bytes < self.limits.max_size_in_bytes / 2 {
+			halflife /= 2;
+		}
+
+		let elapsed = now.saturating_sub(self.rolling_min_fee_rate_time);
+		let min_fee_rate = self.rolling_min_fee_rate as f64 / 2f64.powf(elapsed as f64 / halflife as f64);
+		if min_fee_rate < (INCREMENTAL_RELAY_FEE_RATE * 1000 / 2) as f64 {
+			0
+		} else {
+			min_fee_rate as u64
+		}
+	}
+
 	fn get_ancestors(&self, t: &Transaction) -> HashSet<SHA256D> {
 		let mut ancestors: HashSet<SHA256D> = HashSet::new();
 		let ancestors_entries = t
@@ -976,11 +1397,12 @@ pub mod tests {
 	extern crate test_data;
 
 	use self::test_data::{ChainBuilder, TransactionBuilder};
-	use super::{DoubleSpendCheckResult, MemoryPool, OrderingStrategy};
+	use super::{DoubleSpendCheckResult, Limits, MemoryPool, OrderingStrategy, ROLLING_FEE_HALFLIFE};
 	use crate::fee::NonZeroFeeCalculator;
 	use bitcrypto::SHA256D;
 	use chain::{OutPoint, Transaction};
 	use heapsize::HeapSizeOf;
+	use verification::{MemoryPoolLimitError, ReplacementError};
 
 	fn to_memory_pool(chain: &mut ChainBuilder) -> MemoryPool {
 		let mut pool = MemoryPool::new();
@@ -1697,4 +2119,374 @@ pub mod tests {
 		assert!(memory_pool.is_spent(&out1));
 		assert!(!memory_pool.is_spent(&out2));
 	}
+
+	#[test]
+	fn test_memory_pool_bip125_replaceability() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.add_output(10)
+			.store(chain) // t0
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.add_output(20)
+			.lock()
+			.store(chain) // signalling: t0[0] -> t1
+			.reset()
+			.set_input(&chain.at(1), 0)
+			.add_output(30)
+			.store(chain) // inherits replaceability: t0[0] -> t1[0] -> t2
+			.reset()
+			.set_input(&chain.at(0), 1)
+			.add_output(40)
+			.store(chain) // non-signalling: t0[1] -> t3
+			.reset()
+			.set_input(&chain.at(0), 1)
+			.add_output(50)
+			.store(chain); // replacement of non-signalling: t0[1] -> t4
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator);
+		assert!(pool.is_bip125_replaceable(&chain.hash(1)));
+		assert!(pool.is_bip125_replaceable(&chain.hash(2)));
+		assert!(!pool.is_bip125_replaceable(&chain.hash(3)));
+		assert!(!pool.is_bip125_replaceable(&chain.hash(4)));
+
+		match pool.check_double_spend(&chain.at(4)) {
+			DoubleSpendCheckResult::DoubleSpend(inpool_hash, _, _) => assert_eq!(inpool_hash, chain.hash(3)),
+			_ => panic!("unexpected"),
+		}
+
+		// with full-RBF, non-signalling transactions could also be replaced
+		pool.enable_full_rbf();
+		match pool.check_double_spend(&chain.at(4)) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
+				assert_eq!(set.conflicts, vec![chain.hash(3)].into_iter().collect());
+				assert_eq!(set.replaced, vec![chain.hash(3)].into_iter().collect());
+			}
+			_ => panic!("unexpected"),
+		}
+	}
+
+	#[test]
+	fn test_memory_pool_check_replacement() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.add_output(10)
+			.store(chain) // t0
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.add_output(20)
+			.lock()
+			.store(chain) // signalling: t0[0] -> t1
+			.reset()
+			.set_input(&chain.at(1), 0)
+			.add_output(30)
+			.store(chain) // descendant: t0[0] -> t1[0] -> t2
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.add_output(40)
+			.store(chain) // replacement: t0[0] -> t3
+			.reset()
+			.set_input(&chain.at(0), 1)
+			.add_output(50)
+			.store(chain) // unrelated: t0[1] -> t4
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.add_input(&chain.at(4), 0)
+			.add_output(60)
+			.store(chain); // replacement with new unconfirmed input: t0[0] + t4[0] -> t5
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator);
+
+		let set = match pool.check_double_spend(&chain.at(3)) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => set,
+			_ => panic!("unexpected"),
+		};
+		assert_eq!(set.conflicts, vec![chain.hash(1)].into_iter().collect());
+		assert_eq!(set.replaced, vec![chain.hash(1), chain.hash(2)].into_iter().collect());
+
+		// replacement must pay higher fee rate than conflicting transaction
+		let replaced_fee = 100_000_020 + 100_000_030;
+		assert_eq!(
+			pool.check_replacement(&chain.at(3), 100_000_020, &set),
+			Err(ReplacementError::InsufficientFeeRate(chain.hash(1)))
+		);
+		// replacement must pay for all replaced transactions and for its own relay
+		let required_fee = replaced_fee + chain.size(3) as u64;
+		assert_eq!(
+			pool.check_replacement(&chain.at(3), replaced_fee, &set),
+			Err(ReplacementError::InsufficientFee(replaced_fee, required_fee))
+		);
+		assert_eq!(pool.check_replacement(&chain.at(3), required_fee, &set), Ok(()));
+
+		// replacement must pay for its witness too
+		let mut witness_replacement = chain.at(3);
+		witness_replacement.inputs[0].script_witness = vec![vec![0; 100].into()];
+		let vsize = (witness_replacement.weight() + 3) / 4;
+		assert!(vsize > chain.size(3) + 20);
+		let required_witness_fee = replaced_fee + vsize as u64;
+		assert_eq!(
+			pool.check_replacement(&witness_replacement, required_fee, &set),
+			Err(ReplacementError::InsufficientFee(required_fee, required_witness_fee))
+		);
+		assert_eq!(pool.check_replacement(&witness_replacement, required_witness_fee, &set), Ok(()));
+
+		// replacement can't spend outputs of unconfirmed transactions, which are not spent by conflicting transactions
+		let set = match pool.check_double_spend(&chain.at(5)) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => set,
+			_ => panic!("unexpected"),
+		};
+		assert_eq!(
+			pool.check_replacement(&chain.at(5), required_fee * 2, &set),
+			Err(ReplacementError::NewUnconfirmedInput(chain.hash(4), 0))
+		);
+	}
+
+	#[test]
+	fn test_memory_pool_check_replacement_too_many_replacements() {
+		let chain = &mut ChainBuilder::new();
+		let mut builder = TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.add_output(10)
+			.lock()
+			.store(chain); // signalling: t0[0] -> t1
+		for _ in 0..100 {
+			// descendants: t1[0] -> t2[0] -> ... -> t101
+			builder = builder.into_input(0).add_output(10).store(chain);
+		}
+		TransactionBuilder::with_input(&chain.at(0), 0).add_output(1).store(chain); // replacement: t0[0] -> t102
+
+		let mut pool = MemoryPool::new();
+		for index in 1..102 {
+			pool.insert_verified(chain.at(index).into(), &NonZeroFeeCalculator);
+		}
+
+		let set = match pool.check_double_spend(&chain.at(102)) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => set,
+			_ => panic!("unexpected"),
+		};
+		assert_eq!(
+			pool.check_replacement(&chain.at(102), u64::max_value(), &set),
+			Err(ReplacementError::TooManyReplacements(101))
+		);
+	}
+
+	#[test]
+	fn test_memory_pool_trim_to_size() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.add_output(20)
+			.store(chain) // t0 -> t1
+			.reset()
+			.add_output(1_000)
+			.store(chain); // t2
+		let mut pool = to_memory_pool(chain);
+		let total_size = chain.size(0) + chain.size(1) + chain.size(2);
+		pool.set_limits(Limits {
+			max_size_in_bytes: total_size - 1,
+			..Default::default()
+		});
+
+		// package t0 + t1 has the lowest score => it is evicted
+		let now = 1_600_000_000;
+		let removed = pool.trim_to_size(now);
+		assert_eq!(removed, vec![chain.at(0).into(), chain.at(1).into()]);
+		assert_eq!(pool.get_transactions_ids(), vec![chain.hash(2)]);
+		assert_eq!(pool.trim_to_size(now), vec![]);
+
+		// new transactions must pay more than evicted package
+		let min_fee_rate = (100_000_010 + 100_000_020) * 1000 / (chain.size(0) + chain.size(1)) as u64 + 1000;
+		assert_eq!(pool.min_fee_rate_at(now), min_fee_rate);
+		// memory pool is less than quarter-full => minimal fee rate is halved four times as fast
+		assert!(pool.information().transactions_size_in_bytes < total_size / 4);
+		assert_eq!(pool.min_fee_rate_at(now + ROLLING_FEE_HALFLIFE / 4), min_fee_rate / 2);
+		assert_eq!(pool.min_fee_rate_at(now + ROLLING_FEE_HALFLIFE * 100), 0);
+	}
+
+	#[test]
+	fn test_memory_pool_check_limits_min_fee() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.reset()
+			.add_output(20)
+			.store(chain); // t1
+		let mut pool = to_memory_pool(chain);
+		assert_eq!(pool.check_limits(&chain.at(1), 0), Ok(()));
+
+		pool.set_limits(Limits {
+			max_size_in_bytes: chain.size(0),
+			..Default::default()
+		});
+		assert_eq!(pool.limit_size(), vec![chain.at(0).into()]);
+		assert!(pool.information().min_fee_rate > 100_000_010 * 1000 / chain.size(0) as u64);
+		match pool.check_limits(&chain.at(0), 0) {
+			Err(MemoryPoolLimitError::InsufficientFee(0, _)) => (),
+			result => panic!("unexpected result: {:?}", result),
+		}
+		assert_eq!(pool.check_limits(&chain.at(0), 200_000_000), Ok(()));
+	}
+
+	#[test]
+	fn test_memory_pool_remove_expired() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.add_output(20)
+			.store(chain) // t0 -> t1
+			.reset()
+			.add_output(30)
+			.store(chain); // t2
+		let mut pool = to_memory_pool(chain);
+
+		let now = 1_600_000_000;
+		let expiry_time = pool.limits().expiry_time;
+		pool.set_entry_time(&chain.hash(0), now - expiry_time - 1);
+		pool.set_entry_time(&chain.hash(1), now);
+		pool.set_entry_time(&chain.hash(2), now - expiry_time);
+
+		// expired transaction is evicted together with its descendants
+		assert_eq!(pool.remove_expired(now), vec![chain.at(0).into(), chain.at(1).into()]);
+		assert_eq!(pool.get_transactions_ids(), vec![chain.hash(2)]);
+	}
+
+	#[test]
+	fn test_memory_pool_check_limits_packages() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.add_output(20)
+			.store(chain) // t0 -> t1
+			.into_input(0)
+			.add_output(30)
+			.store(chain); // t0 -> t1 -> t2
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		assert_eq!(pool.check_limits(&chain.at(2), 0), Ok(()));
+
+		let package_size = chain.size(0) + chain.size(1) + chain.size(2);
+		pool.set_limits(Limits {
+			max_ancestors_count: 2,
+			..Default::default()
+		});
+		assert_eq!(pool.check_limits(&chain.at(2), 0), Err(MemoryPoolLimitError::TooManyAncestors(3)));
+
+		pool.set_limits(Limits {
+			max_ancestors_size: package_size - 1,
+			..Default::default()
+		});
+		assert_eq!(
+			pool.check_limits(&chain.at(2), 0),
+			Err(MemoryPoolLimitError::TooLargeAncestors(package_size))
+		);
+
+		pool.set_limits(Limits {
+			max_descendants_count: 2,
+			..Default::default()
+		});
+		assert_eq!(
+			pool.check_limits(&chain.at(2), 0),
+			Err(MemoryPoolLimitError::TooManyDescendants(chain.hash(0)))
+		);
+
+		pool.set_limits(Limits {
+			max_descendants_size: package_size - 1,
+			..Default::default()
+		});
+		assert_eq!(
+			pool.check_limits(&chain.at(2), 0),
+			Err(MemoryPoolLimitError::TooLargeDescendants(chain.hash(0)))
+		);
+	}
+
+	#[test]
+	fn test_memory_pool_fee_estimator_tracking() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.add_output(20)
+			.store(chain) // t0 -> t1
+			.reset()
+			.add_output(30)
+			.store(chain); // t2
+		let mut pool = to_memory_pool(chain);
+		// transactions with inpool ancestors are not tracked
+		assert_eq!(pool.fee_estimator().tracked_transactions_count(), 2);
+
+		// t0 is confirmed, t2 is evicted
+		pool.remove_by_hash(&chain.hash(2));
+		pool.process_best_block(1, &[chain.hash(0)]);
+		assert_eq!(pool.fee_estimator().best_height(), 1);
+		assert_eq!(pool.fee_estimator().tracked_transactions_count(), 0);
+	}
+
+	#[test]
+	fn test_memory_pool_virtual_fee_of_unknown_transaction() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.add_output(20)
+			.store(chain); // t0 -> t1
+		let mut pool = MemoryPool::new();
+
+		// virtual fee is applied when transaction enters the memory pool
+		pool.set_virtual_fee(&chain.hash(0), 100);
+		pool.set_virtual_fee(&chain.hash(1), -10);
+		assert_eq!(pool.pending_fee_deltas(), {
+			let mut fee_deltas = vec![(chain.hash(0), 100), (chain.hash(1), -10)];
+			fee_deltas.sort_unstable();
+			fee_deltas
+		});
+		// descendant is re-inserted when its parent arrives
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		assert_eq!(pool.get_entry(&chain.hash(0)).unwrap().miner_virtual_fee, 100);
+		assert_eq!(pool.get_entry(&chain.hash(1)).unwrap().miner_virtual_fee, -10);
+		assert_eq!(pool.pending_fee_deltas(), vec![]);
+
+		// virtual fee of confirmed transaction is forgotten
+		pool.process_best_block(1, &[chain.hash(0)]);
+		pool.remove_by_hash(&chain.hash(0));
+		pool.remove_by_hash(&chain.hash(1));
+		assert_eq!(pool.pending_fee_deltas(), vec![(chain.hash(1), -10)]);
+	}
+
+	#[test]
+	fn test_memory_pool_unbroadcast() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain)
+			.set_output(20)
+			.store(chain)
+			.set_output(30)
+			.store(chain);
+		let mut pool = to_memory_pool(chain);
+
+		// transactions, which are not in the memory pool, can't be unbroadcast
+		pool.remove_by_hash(&chain.hash(2));
+		pool.add_unbroadcast(&chain.hash(2));
+		pool.add_unbroadcast(&chain.hash(0));
+		pool.add_unbroadcast(&chain.hash(1));
+		assert_eq!(pool.unbroadcast().len(), 2);
+
+		pool.remove_unbroadcast(&chain.hash(0));
+		assert_eq!(pool.unbroadcast(), vec![chain.hash(1)]);
+
+		// removed transactions are not reported
+		pool.remove_by_hash(&chain.hash(1));
+		assert_eq!(pool.unbroadcast(), vec![]);
+	}
 }
@@ -0,0 +1,234 @@
+//! Memory pool dump, compatible with Bitcoin Core `mempool.dat` file format
+//!
+//! The dump starts with the format version. Version 2 dumps are followed by the XOR key, which
+//! obfuscates the rest of the file. Then go memory pool transactions (with their entry time and fee delta),
+//! fee deltas of transactions, which are not in the memory pool, and the set of unbroadcast transactions.
+use crate::memory_pool::{MemoryPool, OrderingStrategy};
+use bitcrypto::SHA256D;
+use chain::{IndexedTransaction, Transaction};
+use primitives::bytes::Bytes;
+use ser::{CompactInteger, Error as ReaderError, Reader, Serializable, Stream, SERIALIZE_TRANSACTION_WITNESS};
+
+/// Version of the dump, which is not obfuscated
+pub const MEMORY_POOL_DUMP_VERSION_NO_XOR_KEY: u64 = 1;
+/// Version of the dump, which is obfuscated with the XOR key
+pub const MEMORY_POOL_DUMP_VERSION: u64 = 2;
+
+/// Single memory pool transaction of the dump
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPoolDumpEntry {
+	/// Transaction
+	pub transaction: IndexedTransaction,
+	/// Time (seconds since UNIX epoch) when transaction has entered the memory pool
+	pub time: i64,
+	/// Virtual transaction fee
+	pub fee_delta: i64,
+}
+
+/// Memory pool dump
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MemoryPoolDump {
+	/// Memory pool transactions. Ancestors always go before descendants
+	pub entries: Vec<MemoryPoolDumpEntry>,
+	/// Virtual fees of transactions, which are not in the memory pool
+	pub fee_deltas: Vec<(SHA256D, i64)>,
+	/// Hashes of transactions, which have not been broadcasted yet
+	pub unbroadcast: Vec<SHA256D>,
+}
+
+impl MemoryPoolDump {
+	/// Creates dump of the memory pool
+	pub fn new(memory_pool: &MemoryPool) -> Self {
+		MemoryPoolDump {
+			entries: memory_pool
+				.iter(OrderingStrategy::ByTimestamp)
+				.map(|entry| MemoryPoolDumpEntry {
+					transaction: IndexedTransaction::new(entry.hash, entry.transaction.clone()),
+					time: entry.time as i64,
+					fee_delta: entry.miner_virtual_fee,
+				})
+				.collect(),
+			fee_deltas: memory_pool.pending_fee_deltas(),
+			unbroadcast: memory_pool.unbroadcast(),
+		}
+	}
+
+	/// Serializes dump using version 1 format, which is readable by all Bitcoin Core versions
+	pub fn serialize(&self) -> Bytes {
+		let mut stream = Stream::with_flags(SERIALIZE_TRANSACTION_WITNESS);
+		stream
+			.append(&MEMORY_POOL_DUMP_VERSION_NO_XOR_KEY)
+			.append(&(self.entries.len() as u64));
+		for entry in &self.entries {
+			stream.append(&entry.transaction.raw).append(&entry.time).append(&entry.fee_delta);
+		}
+		stream.append(&CompactInteger::from(self.fee_deltas.len()));
+		for (hash, fee_delta) in &self.fee_deltas {
+			stream.append(hash).append(fee_delta);
+		}
+		stream.append_list::<SHA256D, SHA256D>(&self.unbroadcast);
+		stream.out()
+	}
+
+	/// Deserializes dump of any supported version
+	pub fn deserialize(data: &[u8]) -> Result<Self, ReaderError> {
+		let mut reader = Reader::new(data);
+		match reader.read::<u64>()? {
+			MEMORY_POOL_DUMP_VERSION_NO_XOR_KEY => Self::deserialize_content(reader),
+			MEMORY_POOL_DUMP_VERSION => {
+				let key: Bytes = reader.read()?;
+				let offset = MEMORY_POOL_DUMP_VERSION.serialized_size() + key.serialized_size();
+				if key.is_empty() {
+					return Self::deserialize_content(Reader::new(&data[offset..]));
+				}
+
+				// every byte is obfuscated using key byte at the same (modulo key length) position of the file
+				let content: Vec<u8> = data[offset..]
+					.iter()
+					.enumerate()
+					.map(|(index, byte)| byte ^ key[(offset + index) % key.len()])
+					.collect();
+				Self::deserialize_content(Reader::new(&content))
+			}
+			_ => Err(ReaderError::MalformedData),
+		}
+	}
+
+	fn deserialize_content(mut reader: Reader<&[u8]>) -> Result<Self, ReaderError> {
+		let entries_count: u64 = reader.read()?;
+		let mut entries = Vec::new();
+		for _ in 0..entries_count {
+			let transaction: Transaction = reader.read()?;
+			entries.push(MemoryPoolDumpEntry {
+				transaction: transaction.into(),
+				time: reader.read()?,
+				fee_delta: reader.read()?,
+			});
+		}
+
+		let fee_deltas_count: usize = reader.read::<CompactInteger>()?.into();
+		let mut fee_deltas = Vec::new();
+		for _ in 0..fee_deltas_count {
+			fee_deltas.push((reader.read()?, reader.read()?));
+		}
+
+		// dumps, created by Bitcoin Core prior to v0.21 have no unbroadcast set
+		let unbroadcast = if reader.is_finished() { Vec::new() } else { reader.read_list()? };
+
+		if !reader.is_finished() {
+			return Err(ReaderError::UnreadData);
+		}
+
+		Ok(MemoryPoolDump {
+			entries,
+			fee_deltas,
+			unbroadcast,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::{MemoryPoolDump, MemoryPoolDumpEntry, MEMORY_POOL_DUMP_VERSION};
+	use crate::fee::NonZeroFeeCalculator;
+	use crate::memory_pool::MemoryPool;
+	use bitcrypto::{FromStr, SHA256D};
+	use primitives::bytes::Bytes;
+	use ser::{Error as ReaderError, Serializable, Stream};
+
+	fn prepare_dump() -> MemoryPoolDump {
+		let chain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_default_input(0)
+			.add_output(100)
+			.store(chain)
+			.into_input(0)
+			.add_output(90)
+			.store(chain);
+
+		MemoryPoolDump {
+			entries: vec![
+				MemoryPoolDumpEntry {
+					transaction: chain.at(0).into(),
+					time: 1_600_000_000,
+					fee_delta: 0,
+				},
+				MemoryPoolDumpEntry {
+					transaction: chain.at(1).into(),
+					time: 1_600_000_001,
+					fee_delta: -500,
+				},
+			],
+			fee_deltas: vec![(
+				SHA256D::from_str("0000000000000000000000000000000000000000000000000000000000000001").unwrap(),
+				1000,
+			)],
+			unbroadcast: vec![chain.at(1).hash()],
+		}
+	}
+
+	#[test]
+	fn memory_pool_dump_roundtrip() {
+		let dump = prepare_dump();
+		let serialized = dump.serialize();
+		assert_eq!(&serialized[0..8], &[1, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(MemoryPoolDump::deserialize(&serialized), Ok(dump));
+	}
+
+	#[test]
+	fn memory_pool_dump_without_unbroadcast_set() {
+		let mut dump = prepare_dump();
+		dump.unbroadcast.clear();
+		let serialized = dump.serialize();
+		// strip empty unbroadcast set, like in dumps of old Bitcoin Core versions
+		let legacy = &serialized[..serialized.len() - 1];
+		assert_eq!(MemoryPoolDump::deserialize(legacy), Ok(dump));
+	}
+
+	#[test]
+	fn memory_pool_dump_obfuscated() {
+		let dump = prepare_dump();
+		let key: Bytes = vec![0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef].into();
+		let content = &dump.serialize()[8..];
+
+		let offset = MEMORY_POOL_DUMP_VERSION.serialized_size() + key.serialized_size();
+		let mut stream = Stream::default();
+		stream.append(&MEMORY_POOL_DUMP_VERSION).append(&key);
+		let obfuscated: Vec<u8> = content
+			.iter()
+			.enumerate()
+			.map(|(index, byte)| byte ^ key[(offset + index) % key.len()])
+			.collect();
+		stream.append_slice(&obfuscated);
+
+		assert_eq!(MemoryPoolDump::deserialize(&stream.out()), Ok(dump));
+	}
+
+	#[test]
+	fn memory_pool_dump_unknown_version() {
+		let mut serialized = prepare_dump().serialize().take();
+		serialized[0] = 3;
+		assert_eq!(MemoryPoolDump::deserialize(&serialized), Err(ReaderError::MalformedData));
+		assert_eq!(MemoryPoolDump::deserialize(&[1, 0, 0]), Err(ReaderError::UnexpectedEnd));
+	}
+
+	#[test]
+	fn memory_pool_dump_from_memory_pool() {
+		let dump = prepare_dump();
+		let mut pool = MemoryPool::new();
+		for entry in &dump.entries {
+			pool.insert_verified(entry.transaction.clone(), &NonZeroFeeCalculator);
+			pool.set_entry_time(&entry.transaction.hash, entry.time as u64);
+			pool.set_virtual_fee(&entry.transaction.hash, entry.fee_delta);
+		}
+		for (hash, fee_delta) in &dump.fee_deltas {
+			pool.set_virtual_fee(hash, *fee_delta);
+		}
+		for hash in &dump.unbroadcast {
+			pool.add_unbroadcast(hash);
+		}
+
+		assert_eq!(MemoryPoolDump::new(&pool), dump);
+	}
+}
@@ -0,0 +1,17 @@
+use bitcrypto::SHA256D;
+
+/// UTXO set snapshot, which is known to be valid (assumeutxo).
+/// Node could load such snapshot instead of synchronizing the chain from the genesis block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssumeUtxo {
+	/// Height of the snapshot base block.
+	pub height: u32,
+	/// Hash of the snapshot base block.
+	pub block_hash: SHA256D,
+	/// Hash of the serialized snapshot transactions.
+	pub txoutset_hash: SHA256D,
+	/// Number of transactions with unspent outputs in the snapshot.
+	pub transactions: u64,
+	/// Total number of transactions in the chain up to and including the base block.
+	pub chain_transactions: u64,
+}
@@ -1,4 +1,5 @@
-use crate::{Deployment, Magic, Network};
+use crate::deployments::{DEPLOYMENT_CSV, DEPLOYMENT_SEGWIT, DEPLOYMENT_TAPROOT, DEPLOYMENT_TESTDUMMY};
+use crate::{AssumeUtxo, Deployment, Magic, Network};
 use bitcrypto::{FromStr, SHA256D};
 
 #[derive(Debug, Clone)]
@@ -22,10 +23,11 @@ pub struct ConsensusParams {
 	pub rule_change_activation_threshold: u32,
 	/// Number of blocks with the same set of rules
 	pub miner_confirmation_window: u32,
-	/// BIP68, BIP112, BIP113 deployment
-	pub csv_deployment: Option<Deployment>,
-	/// BIP141, BIP143, BIP147 deployment
-	pub segwit_deployment: Option<Deployment>,
+	/// Version bits deployments (BIP9), known to this network
+	pub deployments: Vec<Deployment>,
+	/// UTXO set snapshots, which could be loaded instead of synchronizing the chain.
+	/// Only known for regtest, so snapshots can't be loaded on mainnet and testnet
+	pub assumeutxo: Vec<AssumeUtxo>,
 
 	/// Absolute maximum block size.
 	pub absolute_maximum_block_size: usize,
@@ -51,13 +53,14 @@ impl ConsensusParams {
 				bip34_height: 227931,   // 000000000000024b89b42a942fe0d9fea3bb44ab7bd1b19115dd6a759c0808b8
 				bip65_height: 388381,   // 000000000000000004c2b624ed5d7756c508d90fd0da2c7c679febfa6c4735f0
 				bip66_height: 363725,   // 00000000000000000379eaa19dce8c9b722d46ae6a57c2f1a988119488b50931
-				segwit_deployment: Some(Deployment {
-					name: "segwit",
-					bit: 1,
-					start_time: 1479168000,
-					timeout: 1510704000,
-					activation: Some(481824),
-				}),
+				deployments: vec![
+					Deployment::bip9(DEPLOYMENT_CSV, 0, 1462060800, 1493596800).buried(419328),
+					Deployment::bip9(DEPLOYMENT_SEGWIT, 1, 1479168000, 1510704000).buried(481824),
+					Deployment::bip9(DEPLOYMENT_TAPROOT, 2, 1619222400, 1628640000)
+						.with_min_activation_height(709632)
+						.buried(709632),
+				],
+				assumeutxo: Vec::new(),
 				absolute_maximum_block_size: 32_000_000,
 				absolute_maximum_block_sigops: 160_000,
 				witness_scale_factor: 4,
@@ -69,13 +72,6 @@ impl ConsensusParams {
 				rule_change_activation_threshold: 1916, // 95%
 				miner_confirmation_window: 2016,
 				coinbase_maturity: 100, // 2 hours
-				csv_deployment: Some(Deployment {
-					name: "csv",
-					bit: 0,
-					start_time: 1462060800,
-					timeout: 1493596800,
-					activation: Some(419328),
-				}),
 			},
 			Network::Testnet => ConsensusParams {
 				network,
@@ -83,13 +79,12 @@ impl ConsensusParams {
 				bip34_height: 21111,    // 0000000023b3a96d3484e5abb3755c413e7d41500f8e2a5c3f0dd01299cd8ef8
 				bip65_height: 581885,   // 00000000007f6655f22f98e72ed80d8b06dc761d5da09df0fa1dc4be4f861eb6
 				bip66_height: 330776,   // 000000002104c8c45e99a8853285a3b592602a3ccde2b832481da85e9e4ba182
-				segwit_deployment: Some(Deployment {
-					name: "segwit",
-					bit: 1,
-					start_time: 1462060800,
-					timeout: 1493596800,
-					activation: Some(834624),
-				}),
+				deployments: vec![
+					Deployment::bip9(DEPLOYMENT_CSV, 0, 1456790400, 1493596800).buried(770112),
+					Deployment::bip9(DEPLOYMENT_SEGWIT, 1, 1462060800, 1493596800).buried(834624),
+					Deployment::bip9(DEPLOYMENT_TAPROOT, 2, 1619222400, 1628640000),
+				],
+				assumeutxo: Vec::new(),
 				absolute_maximum_block_size: 32_000_000,
 				absolute_maximum_block_sigops: 160_000,
 				witness_scale_factor: 4,
@@ -101,13 +96,6 @@ impl ConsensusParams {
 				rule_change_activation_threshold: 1512, // 75%
 				miner_confirmation_window: 2016,
 				coinbase_maturity: 100, // 2 hours
-				csv_deployment: Some(Deployment {
-					name: "csv",
-					bit: 0,
-					start_time: 1456790400,
-					timeout: 1493596800,
-					activation: Some(770112),
-				}),
 			},
 			Network::Regtest | Network::Unitest => ConsensusParams {
 				network,
@@ -115,13 +103,20 @@ impl ConsensusParams {
 				bip34_height: 100000000, // not activated on regtest
 				bip65_height: 1351,
 				bip66_height: 1251, // used only in rpc tests
-				segwit_deployment: Some(Deployment {
-					name: "segwit",
-					bit: 1,
-					start_time: 0,
-					timeout: ::std::u32::MAX,
-					activation: None,
-				}),
+				deployments: vec![
+					Deployment::bip9(DEPLOYMENT_CSV, 0, 0, 0).buried(0),
+					Deployment::bip9(DEPLOYMENT_SEGWIT, 1, 0, u32::MAX),
+					Deployment::bip9(DEPLOYMENT_TAPROOT, 2, 0, u32::MAX).buried(0),
+					Deployment::bip9(DEPLOYMENT_TESTDUMMY, 28, 0, u32::MAX),
+				],
+				// chain of blocks with single coinbase, built by `test_data::build_n_regtest_blocks_from`
+				assumeutxo: vec![AssumeUtxo {
+					height: 110,
+					block_hash: SHA256D::from_str("33a1037e9948c6a0448a0f50792d12cf90a3cf8ec9ec117b3e4ca0125830e5c7").unwrap(),
+					txoutset_hash: SHA256D::from_str("582be6fd2658dba8855812ee4cbb6e95810baeecdb028376bcd5fa46ad4fc327").unwrap(),
+					transactions: 110,
+					chain_transactions: 111,
+				}],
 				absolute_maximum_block_size: 32_000_000,
 				absolute_maximum_block_sigops: 160_000,
 				witness_scale_factor: 4,
@@ -133,17 +128,20 @@ impl ConsensusParams {
 				rule_change_activation_threshold: 108, // 75%
 				miner_confirmation_window: 144,
 				coinbase_maturity: 100,
-				csv_deployment: Some(Deployment {
-					name: "csv",
-					bit: 0,
-					start_time: 0,
-					timeout: 0,
-					activation: Some(0),
-				}),
 			},
 		}
 	}
 
+	/// Returns deployment with given name.
+	pub fn deployment(&self, name: &str) -> Option<&Deployment> {
+		self.deployments.iter().find(|deployment| deployment.name == name)
+	}
+
+	/// Returns UTXO set snapshot with given base block.
+	pub fn assumeutxo_for(&self, block_hash: &SHA256D) -> Option<&AssumeUtxo> {
+		self.assumeutxo.iter().find(|assumeutxo| assumeutxo.block_hash == *block_hash)
+	}
+
 	pub fn magic(&self) -> Magic {
 		self.network.magic()
 	}
@@ -1,6 +1,15 @@
 const VERSIONBITS_TOP_MASK: u32 = 0xe0000000;
 const VERSIONBITS_TOP_BITS: u32 = 0x20000000;
 
+/// Name of BIP68, BIP112, BIP113 deployment
+pub const DEPLOYMENT_CSV: &str = "csv";
+/// Name of BIP141, BIP143, BIP147 deployment
+pub const DEPLOYMENT_SEGWIT: &str = "segwit";
+/// Name of BIP340, BIP341, BIP342 deployment
+pub const DEPLOYMENT_TAPROOT: &str = "taproot";
+/// Name of deployment, used only for testing version bits on regtest
+pub const DEPLOYMENT_TESTDUMMY: &str = "testdummy";
+
 #[derive(Debug, Clone, Copy)]
 pub struct Deployment {
 	/// Deployment's name
@@ -11,11 +20,52 @@ pub struct Deployment {
 	pub start_time: u32,
 	/// Timeout
 	pub timeout: u32,
-	/// Activation block number (if already activated)
+	/// Activation block number (if already activated). Buried deployments do not check signalling at all.
 	pub activation: Option<u32>,
+	/// Block number, before which locked in deployment can not become active (speedy trial).
+	pub min_activation_height: u32,
+	/// Whether deployment is locked in instead of failing once the timeout is reached (BIP8 `lockinontimeout`).
+	pub lock_in_on_timeout: bool,
 }
 
 impl Deployment {
+	/// Creates BIP9 deployment, signalled by version bit during [start_time; timeout) interval.
+	pub fn bip9(name: &'static str, bit: u8, start_time: u32, timeout: u32) -> Self {
+		Deployment {
+			name,
+			bit,
+			start_time,
+			timeout,
+			activation: None,
+			min_activation_height: 0,
+			lock_in_on_timeout: false,
+		}
+	}
+
+	/// Creates deployment, which is known to be active since given block.
+	pub fn buried(self, activation: u32) -> Self {
+		Deployment {
+			activation: Some(activation),
+			..self
+		}
+	}
+
+	/// Sets minimal activation height of the deployment.
+	pub fn with_min_activation_height(self, min_activation_height: u32) -> Self {
+		Deployment {
+			min_activation_height,
+			..self
+		}
+	}
+
+	/// Makes deployment lock in on timeout instead of failing.
+	pub fn with_lock_in_on_timeout(self) -> Self {
+		Deployment {
+			lock_in_on_timeout: true,
+			..self
+		}
+	}
+
 	pub fn matches(&self, version: u32) -> bool {
 		(version & VERSIONBITS_TOP_MASK) == VERSIONBITS_TOP_BITS && (version & (1 << self.bit)) != 0
 	}
@@ -4,6 +4,7 @@ extern crate lazy_static;
 extern crate chain;
 extern crate primitives;
 
+mod assumeutxo;
 mod consensus;
 mod deployments;
 pub mod network;
@@ -11,5 +12,6 @@ pub mod network;
 pub use primitives::{compact, hash};
 
 pub use crate::network::{Magic, Network};
+pub use assumeutxo::AssumeUtxo;
 pub use consensus::ConsensusParams;
-pub use deployments::Deployment;
+pub use deployments::{Deployment, DEPLOYMENT_CSV, DEPLOYMENT_SEGWIT, DEPLOYMENT_TAPROOT, DEPLOYMENT_TESTDUMMY};
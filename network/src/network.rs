@@ -81,6 +81,40 @@ impl Network {
 		}
 	}
 
+	pub fn electrum_port(&self) -> u16 {
+		match *self {
+			Network::Mainnet | Network::Other(_) => 50001,
+			Network::Testnet => 60001,
+			Network::Regtest | Network::Unitest => 60401,
+		}
+	}
+
+	/// DNS seeds, which return addresses of the network nodes.
+	pub fn dns_seeds(&self) -> &'static [&'static str] {
+		match *self {
+			Network::Mainnet => &[
+				"seed.bitcoin.sipa.be",
+				"dnsseed.bluematt.me",
+				"dnsseed.bitcoin.dashjr-list-of-p2p-nodes.us",
+				"seed.bitcoinstats.com",
+				"seed.bitcoin.jonasschnelli.ch",
+				"seed.btc.petertodd.net",
+				"seed.bitcoin.sprovoost.nl",
+				"dnsseed.emzy.de",
+				"seed.bitcoin.wiz.biz",
+				"seed.mainnet.achownodes.xyz",
+			],
+			Network::Testnet => &[
+				"testnet-seed.bitcoin.jonasschnelli.ch",
+				"seed.tbtc.petertodd.net",
+				"seed.testnet.bitcoin.sprovoost.nl",
+				"testnet-seed.bluematt.me",
+				"seed.testnet.achownodes.xyz",
+			],
+			Network::Regtest | Network::Unitest | Network::Other(_) => &[],
+		}
+	}
+
 	pub fn genesis_block(&self) -> IndexedBlock {
 		match *self {
 			Network::Mainnet | Network::Other(_) => "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into(),
@@ -154,4 +188,20 @@ mod tests {
 		assert_eq!(Network::Regtest.rpc_port(), 18443);
 		assert_eq!(Network::Unitest.rpc_port(), 18443);
 	}
+
+	#[test]
+	fn test_network_electrum_port() {
+		assert_eq!(Network::Mainnet.electrum_port(), 50001);
+		assert_eq!(Network::Testnet.electrum_port(), 60001);
+		assert_eq!(Network::Regtest.electrum_port(), 60401);
+		assert_eq!(Network::Unitest.electrum_port(), 60401);
+	}
+
+	#[test]
+	fn test_network_dns_seeds() {
+		assert_eq!(Network::Mainnet.dns_seeds()[0], "seed.bitcoin.sipa.be");
+		assert_eq!(Network::Testnet.dns_seeds()[0], "testnet-seed.bitcoin.jonasschnelli.ch");
+		assert!(Network::Regtest.dns_seeds().is_empty());
+		assert!(Network::Other(0).dns_seeds().is_empty());
+	}
 }
@@ -1,4 +1,4 @@
-use crate::{InternetProtocol, NetConfig};
+use crate::{InternetProtocol, NetConfig, TorControlConfig};
 use message::common::Services;
 use std::net;
 use std::net::SocketAddr;
@@ -15,10 +15,18 @@ pub struct Config {
 	pub peers: Vec<net::SocketAddr>,
 	/// Optional seed node to use instead of default bitcoin-core seeds.
 	pub seed: Option<SocketAddr>,
+	/// Query DNS seeds for node addresses, if the node table is empty.
+	pub dns_seed: bool,
+	/// Query DNS seeds even if the node table isn't empty or `dns_seed` is false.
+	pub force_dns_seed: bool,
 	/// p2p/nodes.csv file path.
 	pub node_table_path: String,
 	/// Peers with this services will get a boost in node_table.
 	pub preferable_services: Services,
 	/// Internet protocol.
 	pub internet_protocol: InternetProtocol,
+	/// Duration of the automatic ban of misbehaving peers in seconds.
+	pub ban_time: u64,
+	/// Tor control port used to publish onion service for inbound connections.
+	pub tor_control: Option<TorControlConfig>,
 }
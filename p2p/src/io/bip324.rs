@@ -0,0 +1,423 @@
+//! BIP324 v2 transport: key exchange, packet encryption and short command ids.
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Tag};
+use hkdf::Hkdf;
+use message::{Command, MAX_PROTOCOL_MESSAGE_LENGTH};
+use network::Magic;
+use secp256k1::ellswift::{ElligatorSwift, ElligatorSwiftParty};
+use secp256k1::{Secp256k1, SecretKey};
+use ser::serialize;
+use sha2::Sha256;
+
+/// Length of the ElligatorSwift encoded public key.
+pub const ELLSWIFT_KEY_LEN: usize = 64;
+pub const GARBAGE_TERMINATOR_LEN: usize = 16;
+pub const MAX_GARBAGE_LEN: usize = 4095;
+/// Length of the encrypted length of the packet contents.
+pub const LENGTH_FIELD_LEN: usize = 3;
+/// Maximum length of the packet contents: long command encoding and the largest message payload.
+pub const MAX_CONTENTS_LEN: usize = 1 + 12 + MAX_PROTOCOL_MESSAGE_LENGTH;
+const HEADER_LEN: usize = 1;
+const TAG_LEN: usize = 16;
+/// Packets with this header bit set are decoys and must be ignored.
+const IGNORE_BIT: u8 = 0x80;
+/// Number of packets, after which the ciphers are rekeyed.
+const REKEY_INTERVAL: u32 = 224;
+
+/// Commands, which are sent using one byte id. Id of the command is its index + 1.
+const SHORT_COMMAND_IDS: [&str; 28] = [
+	"addr",
+	"block",
+	"blocktxn",
+	"cmpctblock",
+	"feefilter",
+	"filteradd",
+	"filterclear",
+	"filterload",
+	"getblocks",
+	"getblocktxn",
+	"getdata",
+	"getheaders",
+	"headers",
+	"inv",
+	"mempool",
+	"merkleblock",
+	"notfound",
+	"ping",
+	"pong",
+	"sendcmpct",
+	"tx",
+	"getcfilters",
+	"cfilter",
+	"getcfheaders",
+	"cfheaders",
+	"getcfcheckpt",
+	"cfcheckpt",
+	"addrv2",
+];
+
+/// Returns length of the packet, which follows the encrypted length field.
+pub fn packet_len(contents_len: usize) -> usize {
+	HEADER_LEN + contents_len + TAG_LEN
+}
+
+/// Encodes command and payload as contents of the packet.
+pub fn encode_contents(command: &Command, payload: &[u8]) -> Vec<u8> {
+	let mut contents = Vec::with_capacity(1 + command.len() + payload.len());
+	match SHORT_COMMAND_IDS.iter().position(|short| command == short) {
+		Some(index) => contents.push(index as u8 + 1),
+		None => {
+			contents.push(0);
+			contents.extend_from_slice(&**command);
+		}
+	}
+	contents.extend_from_slice(payload);
+	contents
+}
+
+/// Decodes command and payload from contents of the packet.
+/// Returns `None` if the command is unknown or the contents are malformed.
+pub fn decode_contents(contents: &[u8]) -> Option<(Command, &[u8])> {
+	match contents.split_first() {
+		Some((0, rest)) if rest.len() >= 12 => Some((Command::from(&rest[..12]), &rest[12..])),
+		Some((&id, rest)) if id != 0 => SHORT_COMMAND_IDS
+			.get(id as usize - 1)
+			.map(|command| (Command::from(*command), rest)),
+		_ => None,
+	}
+}
+
+fn nonce(first: u32, second: u64) -> [u8; 12] {
+	let mut nonce = [0u8; 12];
+	nonce[..4].copy_from_slice(&first.to_le_bytes());
+	nonce[4..].copy_from_slice(&second.to_le_bytes());
+	nonce
+}
+
+/// ChaCha20 with forward secrecy, used to encrypt lengths of the packets.
+struct FSChaCha20 {
+	cipher: ChaCha20,
+	chunk_counter: u32,
+	rekey_counter: u64,
+}
+
+impl FSChaCha20 {
+	fn new(key: [u8; 32]) -> Self {
+		FSChaCha20 {
+			cipher: ChaCha20::new(&key.into(), &nonce(0, 0).into()),
+			chunk_counter: 0,
+			rekey_counter: 0,
+		}
+	}
+
+	fn crypt(&mut self, chunk: &mut [u8]) {
+		self.cipher.apply_keystream(chunk);
+		self.chunk_counter += 1;
+		if self.chunk_counter == REKEY_INTERVAL {
+			let mut key = [0u8; 32];
+			self.cipher.apply_keystream(&mut key);
+			self.chunk_counter = 0;
+			self.rekey_counter += 1;
+			self.cipher = ChaCha20::new(&key.into(), &nonce(0, self.rekey_counter).into());
+		}
+	}
+}
+
+/// ChaCha20-Poly1305 AEAD with forward secrecy, used to encrypt packets.
+struct FSChaCha20Poly1305 {
+	aead: ChaCha20Poly1305,
+	packet_counter: u32,
+	rekey_counter: u64,
+}
+
+impl FSChaCha20Poly1305 {
+	fn new(key: [u8; 32]) -> Self {
+		FSChaCha20Poly1305 {
+			aead: ChaCha20Poly1305::new(&key.into()),
+			packet_counter: 0,
+			rekey_counter: 0,
+		}
+	}
+
+	fn next_packet(&mut self) {
+		self.packet_counter += 1;
+		if self.packet_counter == REKEY_INTERVAL {
+			let mut key = [0u8; 32];
+			self.aead
+				.encrypt_in_place_detached(&nonce(u32::MAX, self.rekey_counter).into(), &[], &mut key)
+				.expect("32 bytes are always encrypted; qed");
+			self.aead = ChaCha20Poly1305::new(&key.into());
+			self.packet_counter = 0;
+			self.rekey_counter += 1;
+		}
+	}
+
+	fn encrypt(&mut self, aad: &[u8], text: &mut [u8]) -> [u8; TAG_LEN] {
+		let tag = self
+			.aead
+			.encrypt_in_place_detached(&nonce(self.packet_counter, self.rekey_counter).into(), aad, text)
+			.expect("packets are limited by the max message size; qed");
+		self.next_packet();
+		tag.into()
+	}
+
+	fn decrypt(&mut self, aad: &[u8], text: &mut [u8], tag: &[u8]) -> bool {
+		let result = self.aead.decrypt_in_place_detached(
+			&nonce(self.packet_counter, self.rekey_counter).into(),
+			aad,
+			text,
+			Tag::from_slice(tag),
+		);
+		self.next_packet();
+		result.is_ok()
+	}
+}
+
+/// Packet cipher for one direction of the connection.
+pub struct PacketCipher {
+	length: FSChaCha20,
+	packet: FSChaCha20Poly1305,
+}
+
+impl PacketCipher {
+	fn new(length_key: [u8; 32], packet_key: [u8; 32]) -> Self {
+		PacketCipher {
+			length: FSChaCha20::new(length_key),
+			packet: FSChaCha20Poly1305::new(packet_key),
+		}
+	}
+
+	/// Encrypts the packet. Decoy packets are ignored by the receiver.
+	pub fn encrypt(&mut self, contents: &[u8], aad: &[u8], decoy: bool) -> Vec<u8> {
+		let mut packet = Vec::with_capacity(LENGTH_FIELD_LEN + packet_len(contents.len()));
+		packet.extend_from_slice(&(contents.len() as u32).to_le_bytes()[..LENGTH_FIELD_LEN]);
+		self.length.crypt(&mut packet[..LENGTH_FIELD_LEN]);
+
+		packet.push(if decoy { IGNORE_BIT } else { 0 });
+		packet.extend_from_slice(contents);
+		let tag = self.packet.encrypt(aad, &mut packet[LENGTH_FIELD_LEN..]);
+		packet.extend_from_slice(&tag);
+		packet
+	}
+
+	/// Decrypts length of the packet contents.
+	pub fn decrypt_length(&mut self, mut length: [u8; LENGTH_FIELD_LEN]) -> usize {
+		self.length.crypt(&mut length);
+		u32::from_le_bytes([length[0], length[1], length[2], 0]) as usize
+	}
+
+	/// Decrypts the packet, which follows the length field. Returns contents of the packet or
+	/// `None` for the decoy packet. Fails if the packet can't be authenticated.
+	pub fn decrypt(&mut self, mut packet: Vec<u8>, aad: &[u8]) -> Result<Option<Vec<u8>>, ()> {
+		if packet.len() < packet_len(0) {
+			return Err(());
+		}
+		let tag = packet.split_off(packet.len() - TAG_LEN);
+		if !self.packet.decrypt(aad, &mut packet, &tag) {
+			return Err(());
+		}
+		if packet[0] & IGNORE_BIT != 0 {
+			return Ok(None);
+		}
+		packet.remove(0);
+		Ok(Some(packet))
+	}
+}
+
+/// Ciphers and garbage terminators of the established v2 session.
+pub struct Session {
+	pub send: PacketCipher,
+	pub recv: PacketCipher,
+	pub send_garbage_terminator: [u8; GARBAGE_TERMINATOR_LEN],
+	pub recv_garbage_terminator: [u8; GARBAGE_TERMINATOR_LEN],
+	pub id: [u8; 32],
+}
+
+/// Ephemeral key of the v2 handshake.
+pub struct KeyExchange {
+	secret_key: SecretKey,
+	public_key: ElligatorSwift,
+	initiator: bool,
+}
+
+impl KeyExchange {
+	pub fn new(initiator: bool) -> Self {
+		let secret_key = SecretKey::new(&mut rand::thread_rng());
+		let public_key = ElligatorSwift::from_seckey(&Secp256k1::new(), secret_key, Some(rand::random()));
+		KeyExchange {
+			secret_key,
+			public_key,
+			initiator,
+		}
+	}
+
+	/// ElligatorSwift encoded public key, which is sent to the peer.
+	pub fn public_key(&self) -> [u8; ELLSWIFT_KEY_LEN] {
+		self.public_key.to_array()
+	}
+
+	/// Derives session keys from the public key of the peer.
+	pub fn session(&self, peer_public_key: [u8; ELLSWIFT_KEY_LEN], magic: Magic) -> Session {
+		let peer_public_key = ElligatorSwift::from_array(peer_public_key);
+		let (party, initiator_key, responder_key) = if self.initiator {
+			(ElligatorSwiftParty::A, self.public_key, peer_public_key)
+		} else {
+			(ElligatorSwiftParty::B, peer_public_key, self.public_key)
+		};
+		let shared_secret = ElligatorSwift::shared_secret(initiator_key, responder_key, self.secret_key, party, None);
+
+		let mut salt = b"bitcoin_v2_shared_secret".to_vec();
+		salt.extend_from_slice(&serialize(&magic));
+		let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared_secret.as_secret_bytes());
+		let expand = |info: &str| {
+			let mut key = [0u8; 32];
+			hkdf.expand(info.as_bytes(), &mut key).expect("32 bytes is a valid length; qed");
+			key
+		};
+
+		let initiator = PacketCipher::new(expand("initiator_L"), expand("initiator_P"));
+		let responder = PacketCipher::new(expand("responder_L"), expand("responder_P"));
+		let garbage_terminators = expand("garbage_terminators");
+		let mut initiator_terminator = [0u8; GARBAGE_TERMINATOR_LEN];
+		let mut responder_terminator = [0u8; GARBAGE_TERMINATOR_LEN];
+		initiator_terminator.copy_from_slice(&garbage_terminators[..GARBAGE_TERMINATOR_LEN]);
+		responder_terminator.copy_from_slice(&garbage_terminators[GARBAGE_TERMINATOR_LEN..]);
+
+		let (send, recv, send_garbage_terminator, recv_garbage_terminator) = if self.initiator {
+			(initiator, responder, initiator_terminator, responder_terminator)
+		} else {
+			(responder, initiator, responder_terminator, initiator_terminator)
+		};
+		Session {
+			send,
+			recv,
+			send_garbage_terminator,
+			recv_garbage_terminator,
+			id: expand("session_id"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode_contents, encode_contents, KeyExchange, PacketCipher, Session, REKEY_INTERVAL};
+	use crate::bytes::Bytes;
+	use message::Command;
+	use network::Network;
+	use secp256k1::ellswift::ElligatorSwift;
+	use secp256k1::SecretKey;
+
+	fn bytes<const N: usize>(hex: &'static str) -> [u8; N] {
+		let bytes: Bytes = hex.into();
+		let mut result = [0u8; N];
+		result.copy_from_slice(&bytes);
+		result
+	}
+
+	fn sessions() -> (Session, Session) {
+		let initiator = KeyExchange::new(true);
+		let responder = KeyExchange::new(false);
+		let magic = Network::Mainnet.magic();
+		(
+			initiator.session(responder.public_key(), magic),
+			responder.session(initiator.public_key(), magic),
+		)
+	}
+
+	fn roundtrip(send: &mut PacketCipher, recv: &mut PacketCipher, contents: &[u8], aad: &[u8], decoy: bool) -> Option<Vec<u8>> {
+		let packet = send.encrypt(contents, aad, decoy);
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&packet[..3]);
+		assert_eq!(recv.decrypt_length(length), contents.len());
+		recv.decrypt(packet[3..].to_vec(), aad).unwrap()
+	}
+
+	#[test]
+	fn test_bip324_test_vector() {
+		// first test vector of BIP324 packet encoding test vectors
+		let key_exchange = KeyExchange {
+			secret_key: SecretKey::from_slice(&bytes::<32>(
+				"61062ea5071d800bbfd59e2e8b53d47d194b095ae5a4df04936b49772ef0d4d7",
+			))
+			.unwrap(),
+			public_key: ElligatorSwift::from_array(bytes::<64>(
+				"ec0adff257bbfe500c188c80b4fdd640f6b45a482bbc15fc7cef5931deff0aa186f6eb9bba7b85dc4dcc28b28722de1e3d9108b985e2967045668f66098e475b",
+			)),
+			initiator: true,
+		};
+		let mut session = key_exchange.session(
+			bytes::<64>(
+				"a4a94dfce69b4a2a0a099313d10f9f7e7d649d60501c9e1d274c300e0d89aafaffffffffffffffffffffffffffffffffffffffffffffffffffffffff8faf88d5",
+			),
+			Network::Mainnet.magic(),
+		);
+		assert_eq!(session.send_garbage_terminator, bytes::<16>("faef555dfcdb936425d84aba524758f3"));
+		assert_eq!(session.recv_garbage_terminator, bytes::<16>("02cb8ff24307a6e27de3b4e7ea3fa65b"));
+		assert_eq!(
+			session.id,
+			bytes::<32>("ce72dffb015da62b0d0f5474cab8bc72605225b0cee3f62312ec680ec5f41ba5")
+		);
+		// the vector encrypts the second packet
+		session.send.encrypt(&[], &[], false);
+		let ciphertext: Bytes = session.send.encrypt(&[0x8e], &[], false).into();
+		assert_eq!(ciphertext, "7530d2a18720162ac09c25329a60d75adf36eda3c3".into());
+	}
+
+	#[test]
+	fn test_packet_roundtrip() {
+		let (mut initiator, mut responder) = sessions();
+		assert_eq!(initiator.id, responder.id);
+		assert_eq!(initiator.send_garbage_terminator, responder.recv_garbage_terminator);
+		assert_eq!(initiator.recv_garbage_terminator, responder.send_garbage_terminator);
+
+		assert_eq!(
+			roundtrip(&mut initiator.send, &mut responder.recv, b"", b"garbage", false),
+			Some(vec![])
+		);
+		assert_eq!(roundtrip(&mut responder.send, &mut initiator.recv, b"decoy", b"", true), None);
+		// ciphers are rekeyed after every 224 packets
+		for i in 0..REKEY_INTERVAL * 2 + 1 {
+			let contents = vec![i as u8; i as usize];
+			assert_eq!(
+				roundtrip(&mut initiator.send, &mut responder.recv, &contents, b"", false),
+				Some(contents)
+			);
+		}
+	}
+
+	#[test]
+	fn test_packet_authentication() {
+		let (mut initiator, mut responder) = sessions();
+		let mut packet = initiator.send.encrypt(b"contents", b"", false);
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&packet[..3]);
+		assert_eq!(responder.recv.decrypt_length(length), 8);
+		packet[5] ^= 1;
+		assert!(responder.recv.decrypt(packet[3..].to_vec(), b"").is_err());
+
+		// aad must match
+		let (mut initiator, mut responder) = sessions();
+		let packet = initiator.send.encrypt(b"", b"garbage", false);
+		responder.recv.decrypt_length([packet[0], packet[1], packet[2]]);
+		assert!(responder.recv.decrypt(packet[3..].to_vec(), b"other").is_err());
+	}
+
+	#[test]
+	fn test_contents_encoding() {
+		let contents = encode_contents(&"ping".into(), &[1, 2]);
+		assert_eq!(contents, vec![18, 1, 2]);
+		assert_eq!(decode_contents(&contents), Some((Command::from("ping"), &[1u8, 2][..])));
+
+		let contents = encode_contents(&"version".into(), &[1]);
+		assert_eq!(contents, b"\x00version\x00\x00\x00\x00\x00\x01".to_vec());
+		assert_eq!(decode_contents(&contents), Some((Command::from("version"), &[1u8][..])));
+
+		// unknown short ids and truncated commands
+		assert_eq!(decode_contents(&[29]), None);
+		assert_eq!(decode_contents(&[0, 1, 2]), None);
+		assert_eq!(decode_contents(&[]), None);
+	}
+}
@@ -1,14 +1,13 @@
-use crate::io::Error;
-use crate::io::{read_message, write_message, SharedTcpStream};
-use message::types::{Verack, Version};
-use message::{Error as MessageError, Message};
+use crate::io::{Error, Transport};
+use message::types::{SendAddrV2, Verack, Version};
+use message::{Error as MessageError, Message, Payload};
 use network::Magic;
 use std::cmp;
 
-pub async fn handshake(stream: &SharedTcpStream, magic: Magic, version: Version, min_version: u32) -> Result<HandshakeResult, Error> {
-	write_message(stream, version_message(magic, &version)).await?;
+pub async fn handshake(stream: &Transport, magic: Magic, version: Version, min_version: u32) -> Result<HandshakeResult, Error> {
+	stream.write_message(version_message(magic, &version).as_ref()).await?;
 
-	let peer_version: Version = read_message(stream, magic, 0).await?;
+	let peer_version: Version = stream.read_typed_message(0).await?;
 
 	if peer_version.version() < min_version {
 		return Err(MessageError::InvalidVersion.into());
@@ -18,18 +17,30 @@ pub async fn handshake(stream: &SharedTcpStream, magic: Magic, version: Version,
 			return Err(MessageError::InvalidVersion.into());
 		}
 	}
-	write_message(stream, verack_message(magic)).await?;
-
-	let _: Verack = read_message(stream, magic, 0).await?;
+	stream.write_message(sendaddrv2_message(magic).as_ref()).await?;
+	stream.write_message(verack_message(magic).as_ref()).await?;
+
+	// feature negotiation messages may be sent before verack, others are ignored
+	let mut addr_v2 = false;
+	loop {
+		let (command, _) = stream.read_message().await?;
+		if command == Verack::command() {
+			break;
+		}
+		if command == SendAddrV2::command() {
+			addr_v2 = true;
+		}
+	}
 
 	Ok(HandshakeResult {
 		negotiated_version: negotiate_version(version.version(), peer_version.version()),
 		version: peer_version,
+		addr_v2,
 	})
 }
 
-pub async fn accept_handshake(a: &SharedTcpStream, magic: Magic, version: Version, min_version: u32) -> Result<HandshakeResult, Error> {
-	let peer_version: Version = read_message(a, magic, 0).await?;
+pub async fn accept_handshake(a: &Transport, magic: Magic, version: Version, min_version: u32) -> Result<HandshakeResult, Error> {
+	let peer_version: Version = a.read_typed_message(0).await?;
 
 	if peer_version.version() < min_version {
 		return Err(MessageError::InvalidVersion.into());
@@ -39,12 +50,15 @@ pub async fn accept_handshake(a: &SharedTcpStream, magic: Magic, version: Versio
 			return Err(MessageError::InvalidVersion.into());
 		}
 	}
-	write_message(a, version_message(magic, &version)).await?;
-	write_message(a, verack_message(magic)).await?;
+	a.write_message(version_message(magic, &version).as_ref()).await?;
+	a.write_message(sendaddrv2_message(magic).as_ref()).await?;
+	a.write_message(verack_message(magic).as_ref()).await?;
 
 	Ok(HandshakeResult {
 		negotiated_version: negotiate_version(version.version(), peer_version.version()),
 		version: peer_version,
+		// peer sends `sendaddrv2` after receiving our version, it's handled by the session
+		addr_v2: false,
 	})
 }
 
@@ -56,6 +70,8 @@ pub fn negotiate_version(local: u32, other: u32) -> u32 {
 pub struct HandshakeResult {
 	pub version: Version,
 	pub negotiated_version: u32,
+	/// True if peer has sent `sendaddrv2` before `verack`
+	pub addr_v2: bool,
 }
 
 fn version_message(magic: Magic, version: &Version) -> Message<Version> {
@@ -66,12 +82,17 @@ fn verack_message(magic: Magic) -> Message<Verack> {
 	Message::new(magic, 0, &Verack).expect("verack message should always be serialized correctly")
 }
 
+fn sendaddrv2_message(magic: Magic) -> Message<SendAddrV2> {
+	Message::new(magic, 0, &SendAddrV2).expect("sendaddrv2 message should always be serialized correctly")
+}
+
 #[cfg(test)]
 mod tests {
 	use super::{accept_handshake, handshake, HandshakeResult};
 	use crate::io::shared_tcp_stream::SharedTcpStream;
+	use crate::io::Transport;
 	use message::types::version::{Version, V0, V106, V70001};
-	use message::types::Verack;
+	use message::types::{SendAddrV2, Verack};
 	use message::{Error as MessageError, Message};
 	use network::Network;
 	use ser::Stream;
@@ -128,16 +149,19 @@ mod tests {
 
 		let mut remote_stream = Stream::new();
 		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &SendAddrV2).unwrap().as_ref());
 		remote_stream.append_slice(Message::new(magic, remote_version.version(), &Verack).unwrap().as_ref());
-		let shared_stream = SharedTcpStream::new(remote_stream.out());
+		let shared_stream = Transport::v1(SharedTcpStream::new(remote_stream.out()), magic);
 
 		let mut expected_stream = Stream::new();
 		expected_stream.append_slice(Message::new(magic, remote_version.version(), &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, remote_version.version(), &SendAddrV2).unwrap().as_ref());
 		expected_stream.append_slice(Message::new(magic, remote_version.version(), &Verack).unwrap().as_ref());
 
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addr_v2: true,
 		};
 
 		let hs = handshake(&shared_stream, magic, local_version, 0).await;
@@ -153,15 +177,17 @@ mod tests {
 
 		let mut remote_stream = Stream::new();
 		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
-		let shared_stream = SharedTcpStream::new(remote_stream.out());
+		let shared_stream = Transport::v1(SharedTcpStream::new(remote_stream.out()), magic);
 
 		let mut expected_stream = Stream::new();
 		expected_stream.append_slice(Message::new(magic, local_version.version(), &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &SendAddrV2).unwrap().as_ref());
 		expected_stream.append_slice(Message::new(magic, local_version.version(), &Verack).unwrap().as_ref());
 
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addr_v2: false,
 		};
 
 		let hs = accept_handshake(&shared_stream, magic, local_version, 0).await;
@@ -177,7 +203,7 @@ mod tests {
 
 		let mut remote_stream = Stream::new();
 		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
-		let shared_stream = SharedTcpStream::new(remote_stream.out());
+		let shared_stream = Transport::v1(SharedTcpStream::new(remote_stream.out()), magic);
 
 		let expected_error = MessageError::InvalidVersion;
 
@@ -193,7 +219,7 @@ mod tests {
 
 		let mut remote_stream = Stream::new();
 		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
-		let shared_stream = SharedTcpStream::new(remote_stream.out());
+		let shared_stream = Transport::v1(SharedTcpStream::new(remote_stream.out()), magic);
 
 		let expected_error = MessageError::InvalidVersion;
 
@@ -1,3 +1,4 @@
+mod bip324;
 mod error;
 mod handshake;
 mod read_any_message;
@@ -5,6 +6,7 @@ mod read_header;
 mod read_message;
 mod read_payload;
 mod shared_tcp_stream;
+mod transport;
 mod write_message;
 
 pub use self::error::Error;
@@ -14,4 +16,5 @@ pub use self::read_header::read_header;
 pub use self::read_message::read_message;
 pub use self::read_payload::read_payload;
 pub use self::shared_tcp_stream::SharedTcpStream;
+pub use self::transport::{Transport, TransportVersion};
 pub use self::write_message::write_message;
@@ -7,7 +7,6 @@ use std::io::Cursor;
 use std::io::ErrorKind;
 #[cfg(test)]
 use std::io::Write;
-use std::net::SocketAddr;
 use std::sync::Arc;
 #[cfg(not(test))]
 use tokio::io::AsyncReadExt;
@@ -23,11 +22,6 @@ pub struct SharedTcpStream {
 
 #[cfg(not(test))]
 impl SharedTcpStream {
-	pub async fn connect(address: &SocketAddr) -> Result<SharedTcpStream, io::Error> {
-		let stream = TcpStream::connect(address).await?;
-		Ok(Self::new(stream))
-	}
-
 	fn new(stream: TcpStream) -> Self {
 		SharedTcpStream {
 			io: Arc::new(Mutex::new(stream)),
@@ -46,6 +40,11 @@ impl SharedTcpStream {
 	pub async fn read_exact(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
 		self.io.lock().await.read_exact(buf).await
 	}
+
+	/// Reads available bytes without removing them from the stream.
+	pub async fn peek(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		self.io.lock().await.peek(buf).await
+	}
 }
 
 #[cfg(not(test))]
@@ -72,10 +71,6 @@ pub struct SharedTcpStream {
 
 #[cfg(test)]
 impl SharedTcpStream {
-	pub async fn connect(_address: &SocketAddr) -> Result<SharedTcpStream, io::Error> {
-		Ok(Self::new(Bytes::new()))
-	}
-
 	pub fn new(bytes: Bytes) -> Self {
 		SharedTcpStream {
 			read: Arc::new(Mutex::new(Cursor::new(bytes.into()))),
@@ -105,6 +100,14 @@ impl SharedTcpStream {
 
 		Ok(buf.len())
 	}
+
+	pub async fn peek(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		let read = self.read.lock().await;
+		let remaining = &read.get_ref()[read.position() as usize..];
+		let len = std::cmp::min(buf.len(), remaining.len());
+		buf[..len].copy_from_slice(&remaining[..len]);
+		Ok(len)
+	}
 }
 
 #[cfg(test)]
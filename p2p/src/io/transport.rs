@@ -0,0 +1,346 @@
+use crate::bytes::Bytes;
+use crate::io::bip324::{
+	decode_contents, encode_contents, packet_len, KeyExchange, PacketCipher, ELLSWIFT_KEY_LEN, GARBAGE_TERMINATOR_LEN, LENGTH_FIELD_LEN,
+	MAX_CONTENTS_LEN, MAX_GARBAGE_LEN,
+};
+use crate::io::{read_any_message, read_message, write_message, Error, SharedTcpStream};
+use message::{deserialize_payload, Command, Error as MessageError, MessageHeader, Payload};
+use network::Magic;
+use rand::Rng;
+use ser::serialize;
+use std::io;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Length of the v1 message header.
+const V1_HEADER_LEN: usize = 24;
+/// Length of the v1 `version` message prefix (magic and command), which is used to detect v1 peers.
+const V1_PREFIX_LEN: usize = 16;
+
+/// Version of the transport protocol.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TransportVersion {
+	/// Plaintext messages with magic and checksum header.
+	V1,
+	/// BIP324 encrypted messages.
+	V2 {
+		/// Identifier of the session, which can be compared out of band to detect man-in-the-middle.
+		session_id: [u8; 32],
+	},
+}
+
+struct V2Ciphers {
+	send: Mutex<PacketCipher>,
+	recv: Mutex<PacketCipher>,
+	session_id: [u8; 32],
+}
+
+/// Stream of messages, sent either in plaintext (v1) or encrypted (BIP324 v2).
+pub struct Transport {
+	stream: SharedTcpStream,
+	magic: Magic,
+	v2: Option<V2Ciphers>,
+}
+
+fn v2_error(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("v2 transport error: {}", message))
+}
+
+fn random_garbage() -> Vec<u8> {
+	let mut rng = rand::thread_rng();
+	let len = rng.gen_range(0..=MAX_GARBAGE_LEN);
+	(0..len).map(|_| rng.gen()).collect()
+}
+
+impl Transport {
+	/// Plaintext transport.
+	pub fn v1(stream: SharedTcpStream, magic: Magic) -> Self {
+		Transport { stream, magic, v2: None }
+	}
+
+	/// Initiates the v2 transport handshake.
+	pub async fn initiate_v2(stream: SharedTcpStream, magic: Magic) -> Result<Self, Error> {
+		Self::v2(stream, magic, KeyExchange::new(true), random_garbage()).await
+	}
+
+	/// Responds to the transport handshake of the inbound peer. Peers, which start with v1 `version`
+	/// message, and all peers, if v2 is not allowed, use v1 transport.
+	pub async fn accept(stream: SharedTcpStream, magic: Magic, allow_v2: bool) -> Result<Self, Error> {
+		if !allow_v2 || Self::is_v1_peer(&stream, magic).await? {
+			return Ok(Self::v1(stream, magic));
+		}
+		Self::v2(stream, magic, KeyExchange::new(false), random_garbage()).await
+	}
+
+	async fn is_v1_peer(stream: &SharedTcpStream, magic: Magic) -> Result<bool, Error> {
+		let mut v1_prefix = serialize(&magic).take();
+		v1_prefix.extend_from_slice(&*Command::from("version"));
+
+		let mut buf = [0u8; V1_PREFIX_LEN];
+		loop {
+			let len = stream.peek(&mut buf).await?;
+			if len == 0 {
+				return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+			}
+			if buf[..len] != v1_prefix[..len] {
+				return Ok(false);
+			}
+			if len == V1_PREFIX_LEN {
+				return Ok(true);
+			}
+			// wait until the peer sends the whole prefix
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+	}
+
+	/// Exchanges keys and garbage with the peer, then sends and receives the version packets.
+	async fn v2(stream: SharedTcpStream, magic: Magic, key_exchange: KeyExchange, garbage: Vec<u8>) -> Result<Self, Error> {
+		let mut handshake = key_exchange.public_key().to_vec();
+		handshake.extend_from_slice(&garbage);
+		stream.write_all(&handshake).await?;
+
+		let mut peer_public_key = [0u8; ELLSWIFT_KEY_LEN];
+		stream.read_exact(&mut peer_public_key).await?;
+		let mut session = key_exchange.session(peer_public_key, magic);
+
+		// version packet has no contents, it's reserved for the future upgrades
+		let mut packets = session.send_garbage_terminator.to_vec();
+		packets.extend(session.send.encrypt(&[], &garbage, false));
+		stream.write_all(&packets).await?;
+
+		let mut peer_garbage = vec![0u8; GARBAGE_TERMINATOR_LEN];
+		stream.read_exact(&mut peer_garbage).await?;
+		while peer_garbage[peer_garbage.len() - GARBAGE_TERMINATOR_LEN..] != session.recv_garbage_terminator {
+			if peer_garbage.len() == MAX_GARBAGE_LEN + GARBAGE_TERMINATOR_LEN {
+				return Err(v2_error("garbage terminator not found").into());
+			}
+			let mut byte = [0u8; 1];
+			stream.read_exact(&mut byte).await?;
+			peer_garbage.push(byte[0]);
+		}
+		peer_garbage.truncate(peer_garbage.len() - GARBAGE_TERMINATOR_LEN);
+
+		// garbage is authenticated by the first packet, decoys may precede the version packet
+		let mut aad = peer_garbage;
+		while read_packet(&stream, &mut session.recv, &aad).await?.is_none() {
+			aad.clear();
+		}
+
+		Ok(Transport {
+			stream,
+			magic,
+			v2: Some(V2Ciphers {
+				send: Mutex::new(session.send),
+				recv: Mutex::new(session.recv),
+				session_id: session.id,
+			}),
+		})
+	}
+
+	pub fn version(&self) -> TransportVersion {
+		match self.v2 {
+			Some(ref v2) => TransportVersion::V2 { session_id: v2.session_id },
+			None => TransportVersion::V1,
+		}
+	}
+
+	/// Reads the next message, skipping v2 decoy packets.
+	pub async fn read_message(&self) -> Result<(Command, Bytes), Error> {
+		let v2 = match self.v2 {
+			Some(ref v2) => v2,
+			None => return read_any_message(&self.stream, self.magic).await,
+		};
+
+		let mut recv = v2.recv.lock().await;
+		loop {
+			if let Some(contents) = read_packet(&self.stream, &mut recv, &[]).await? {
+				let (command, payload) = decode_contents(&contents).ok_or(MessageError::InvalidCommand)?;
+				return Ok((command, payload.to_vec().into()));
+			}
+		}
+	}
+
+	/// Reads the next message, which must be of the given type.
+	pub async fn read_typed_message<M>(&self, version: u32) -> Result<M, Error>
+	where
+		M: Payload,
+	{
+		if self.v2.is_none() {
+			return read_message(&self.stream, self.magic, version).await;
+		}
+
+		let (command, payload) = self.read_message().await?;
+		if command != M::command() {
+			return Err(MessageError::InvalidCommand.into());
+		}
+		deserialize_payload(&payload, version).map_err(Into::into)
+	}
+
+	/// Writes serialized v1 message. v2 transport sends the command and the payload of the message in the encrypted packet.
+	pub async fn write_message(&self, message: &[u8]) -> Result<(), io::Error> {
+		let v2 = match self.v2 {
+			Some(ref v2) => v2,
+			None => return write_message(&self.stream, message).await,
+		};
+
+		let header = MessageHeader::deserialize(&message[..V1_HEADER_LEN], self.magic)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "malformed message"))?;
+		let contents = encode_contents(&header.command, &message[V1_HEADER_LEN..]);
+		// packets must be written in the order of encryption
+		let mut send = v2.send.lock().await;
+		let packet = send.encrypt(&contents, &[], false);
+		self.stream.write_all(&packet).await
+	}
+
+	pub async fn shutdown(&self) {
+		self.stream.shutdown().await;
+	}
+
+	#[cfg(test)]
+	pub async fn get_written(&self) -> Bytes {
+		self.stream.get_written().await
+	}
+}
+
+/// Reads and decrypts single v2 packet. Returns `None` for the decoy packet.
+async fn read_packet(stream: &SharedTcpStream, cipher: &mut PacketCipher, aad: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+	let mut length = [0u8; LENGTH_FIELD_LEN];
+	stream.read_exact(&mut length).await?;
+	// length is not authenticated => check it before allocating the packet
+	let contents_len = cipher.decrypt_length(length);
+	if contents_len > MAX_CONTENTS_LEN {
+		return Err(v2_error("packet is too large").into());
+	}
+	let mut packet = vec![0u8; packet_len(contents_len)];
+	stream.read_exact(&mut packet).await?;
+	cipher
+		.decrypt(packet, aad)
+		.map_err(|_| v2_error("packet authentication failed").into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Transport, TransportVersion};
+	use crate::bytes::Bytes;
+	use crate::io::bip324::{KeyExchange, Session, LENGTH_FIELD_LEN, MAX_CONTENTS_LEN};
+	use crate::io::shared_tcp_stream::SharedTcpStream;
+	use message::types::Ping;
+	use message::Message;
+	use network::Network;
+
+	/// Stream of the peer, which has already sent its part of the v2 handshake and a ping.
+	fn peer_stream(peer: &KeyExchange, peer_session: &mut Session, garbage: &[u8]) -> SharedTcpStream {
+		let mut bytes = peer.public_key().to_vec();
+		bytes.extend_from_slice(garbage);
+		bytes.extend_from_slice(&peer_session.send_garbage_terminator);
+		bytes.extend(peer_session.send.encrypt(b"decoy", garbage, true));
+		bytes.extend(peer_session.send.encrypt(&[], &[], false));
+		bytes.extend(peer_session.send.encrypt(&[], &[], true));
+		// ping with the short command id
+		bytes.extend(peer_session.send.encrypt(&[18, 7, 0, 0, 0, 0, 0, 0, 0], &[], false));
+		SharedTcpStream::new(bytes.into())
+	}
+
+	async fn test_v2_handshake(initiator: bool) {
+		let magic = Network::Mainnet.magic();
+		let local = KeyExchange::new(initiator);
+		let peer = KeyExchange::new(!initiator);
+		let mut peer_session = peer.session(local.public_key(), magic);
+		let stream = peer_stream(&peer, &mut peer_session, b"peer garbage");
+
+		let transport = Transport::v2(stream, magic, local, b"garbage".to_vec()).await.unwrap();
+		assert_eq!(
+			transport.version(),
+			TransportVersion::V2 {
+				session_id: peer_session.id
+			}
+		);
+		assert_eq!(transport.read_message().await.unwrap(), ("ping".into(), "0700000000000000".into()));
+
+		let ping = Message::new(magic, 0, &Ping::new(8)).unwrap();
+		transport.write_message(ping.as_ref()).await.unwrap();
+
+		// local key, garbage, garbage terminator, version packet (20 bytes) and ping packet (29 bytes)
+		let written: Vec<u8> = transport.get_written().await.into();
+		assert_eq!(written.len(), 64 + 7 + 16 + 20 + 29);
+		assert_eq!(&written[64..71], b"garbage");
+		assert_eq!(written[71..87], peer_session.recv_garbage_terminator);
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&written[87..90]);
+		assert_eq!(peer_session.recv.decrypt_length(length), 0);
+		assert_eq!(peer_session.recv.decrypt(written[90..107].to_vec(), b"garbage"), Ok(Some(vec![])));
+		length.copy_from_slice(&written[107..110]);
+		assert_eq!(peer_session.recv.decrypt_length(length), 9);
+		assert_eq!(
+			peer_session.recv.decrypt(written[110..].to_vec(), &[]),
+			Ok(Some(vec![18, 8, 0, 0, 0, 0, 0, 0, 0]))
+		);
+	}
+
+	#[tokio::test]
+	async fn test_initiate_v2() {
+		test_v2_handshake(true).await;
+	}
+
+	#[tokio::test]
+	async fn test_accept_v2() {
+		test_v2_handshake(false).await;
+	}
+
+	#[tokio::test]
+	async fn test_accept_v1() {
+		let magic = Network::Mainnet.magic();
+		let ping: Bytes = Message::new(magic, 0, &Ping::new(7)).unwrap().as_ref().to_vec().into();
+		let version: Bytes = "f9beb4d976657273696f6e0000000000".into();
+
+		assert!(Transport::is_v1_peer(&SharedTcpStream::new(version.clone()), magic).await.unwrap());
+		assert!(!Transport::is_v1_peer(&SharedTcpStream::new(ping.clone()), magic).await.unwrap());
+		assert!(!Transport::is_v1_peer(&SharedTcpStream::new(version), Network::Testnet.magic())
+			.await
+			.unwrap());
+
+		// v2 is not allowed
+		let transport = Transport::accept(SharedTcpStream::new(ping.clone()), magic, false).await.unwrap();
+		assert_eq!(transport.version(), TransportVersion::V1);
+		assert_eq!(transport.read_message().await.unwrap(), ("ping".into(), "0700000000000000".into()));
+		transport.write_message(&ping).await.unwrap();
+		assert_eq!(transport.get_written().await, ping);
+	}
+
+	#[tokio::test]
+	async fn test_v2_garbage_terminator_not_found() {
+		let magic = Network::Mainnet.magic();
+		let local = KeyExchange::new(true);
+		let peer = KeyExchange::new(false);
+		let mut bytes = peer.public_key().to_vec();
+		bytes.extend_from_slice(&[0u8; 4200]);
+
+		let result = Transport::v2(SharedTcpStream::new(bytes.into()), magic, local, vec![]).await;
+		assert_eq!(
+			result.err().unwrap().to_string(),
+			"IO Error: v2 transport error: garbage terminator not found"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_v2_packet_too_large() {
+		let magic = Network::Mainnet.magic();
+		let local = KeyExchange::new(true);
+		let peer = KeyExchange::new(false);
+		let mut peer_session = peer.session(local.public_key(), magic);
+		let mut bytes = peer.public_key().to_vec();
+		bytes.extend_from_slice(&peer_session.send_garbage_terminator);
+		bytes.extend(peer_session.send.encrypt(&[], &[], false));
+		// only the length of the packet is sent => the packet must be rejected before it is read
+		let packet = peer_session.send.encrypt(&vec![0u8; MAX_CONTENTS_LEN + 1], &[], false);
+		bytes.extend_from_slice(&packet[..LENGTH_FIELD_LEN]);
+
+		let transport = Transport::v2(SharedTcpStream::new(bytes.into()), magic, local, vec![])
+			.await
+			.unwrap();
+		assert_eq!(
+			transport.read_message().await.unwrap_err().to_string(),
+			"IO Error: v2 transport error: packet is too large"
+		);
+	}
+}
@@ -1,6 +1,8 @@
 use crate::io::SharedTcpStream;
-use message::Message;
 
-pub async fn write_message<M>(stream: &SharedTcpStream, message: Message<M>) -> Result<(), std::io::Error> {
+pub async fn write_message<T>(stream: &SharedTcpStream, message: T) -> Result<(), std::io::Error>
+where
+	T: AsRef<[u8]>,
+{
 	stream.write_all(message.as_ref()).await
 }
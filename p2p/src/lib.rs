@@ -3,7 +3,12 @@ extern crate rand;
 extern crate tokio;
 #[macro_use]
 extern crate log;
+extern crate chacha20;
+extern crate chacha20poly1305;
 extern crate csv;
+extern crate hkdf;
+extern crate secp256k1;
+extern crate sha2;
 
 extern crate bitcrypto as crypto;
 extern crate message;
@@ -19,15 +24,16 @@ mod protocol;
 mod session;
 mod util;
 
+pub use message::common::NodeAddress;
 pub use primitives::{bytes, hash};
 
-pub use crate::p2p::{Context, P2P};
+pub use crate::p2p::{Context, BAN_SCORE, P2P};
 pub use config::Config;
-pub use io::read_any_message;
+pub use io::{read_any_message, Transport, TransportVersion};
 pub use net::Config as NetConfig;
-pub use net::{connect, Connection};
+pub use net::{connect, Connection, Proxy, ResolveFuture, Resolver, SystemResolver, TorControlConfig};
 pub use protocol::{
 	InboundSyncConnection, InboundSyncConnectionRef, InboundSyncConnectionState, InboundSyncConnectionStateRef, LocalSyncNode,
 	LocalSyncNodeRef, OutboundSyncConnection, OutboundSyncConnectionRef,
 };
-pub use util::{Direction, InternetProtocol, NodeTableError, PeerId, PeerInfo};
+pub use util::{BanEntry, Direction, InternetProtocol, InvalidSubnet, NodeTableError, PeerId, PeerInfo, Subnet};
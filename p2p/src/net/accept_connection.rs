@@ -1,4 +1,4 @@
-use crate::io::{accept_handshake, Error, SharedTcpStream};
+use crate::io::{accept_handshake, Error, Transport};
 use crate::net::{Config, Connection};
 use std::net;
 use std::time::Duration;
@@ -6,12 +6,12 @@ use tokio::net::TcpStream;
 use tokio::time::timeout;
 
 pub async fn accept_connection<'a>(stream: TcpStream, config: &Config, address: net::SocketAddr) -> Result<Connection, Error> {
-	let shared_stream: SharedTcpStream = stream.into();
 	let handshake = async {
+		let shared_stream = Transport::accept(stream.into(), config.network.magic(), config.services.p2p_v2()).await?;
 		let handshake_result = accept_handshake(
 			&shared_stream,
 			config.network.magic(),
-			config.version(&address),
+			config.version(&address.into()),
 			config.protocol_minimum,
 		)
 		.await?;
@@ -21,8 +21,9 @@ pub async fn accept_connection<'a>(stream: TcpStream, config: &Config, address:
 			services: handshake_result.version.services(),
 			version: handshake_result.negotiated_version,
 			version_message: handshake_result.version,
+			addr_v2: handshake_result.addr_v2,
 			magic: config.network.magic(),
-			address,
+			address: address.into(),
 		})
 	};
 
@@ -1,17 +1,17 @@
 use crate::bytes::Bytes;
-use crate::io::{read_any_message, Error, SharedTcpStream};
+use crate::io::{Error, Transport};
 use crate::session::Session;
 use crate::PeerInfo;
 use message::Command;
 
 pub struct Channel {
-	stream: SharedTcpStream,
+	stream: Transport,
 	peer_info: PeerInfo,
 	session: Session,
 }
 
 impl Channel {
-	pub fn new(stream: SharedTcpStream, peer_info: PeerInfo, session: Session) -> Self {
+	pub fn new(stream: Transport, peer_info: PeerInfo, session: Session) -> Self {
 		Channel {
 			stream,
 			peer_info,
@@ -23,11 +23,11 @@ impl Channel {
 	where
 		T: AsRef<[u8]>,
 	{
-		self.stream.write_all(message.as_ref()).await
+		self.stream.write_message(message.as_ref()).await
 	}
 
 	pub async fn read_message(&self) -> Result<(Command, Bytes), Error> {
-		read_any_message(&self.stream, self.peer_info.magic).await
+		self.stream.read_message().await
 	}
 
 	pub async fn shutdown(&self) {
@@ -1,9 +1,10 @@
+use crate::net::Proxy;
 use crate::util::nonce::{NonceGenerator, RandomNonce};
-use message::common::{NetAddress, Services};
+use message::common::{NetAddress, NetworkAddress, NodeAddress, Services};
 use message::types::version::{Version, V0, V106, V70001};
 use network::Network;
 use primitives::time::{RealTime, Time};
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -15,10 +16,35 @@ pub struct Config {
 	pub user_agent: String,
 	pub start_height: u32,
 	pub relay: bool,
+	/// SOCKS5 proxy used for outbound connections.
+	pub proxy: Option<Proxy>,
+	/// SOCKS5 proxy used for outbound connections to onion services. Defaults to `proxy`.
+	pub onion_proxy: Option<Proxy>,
 }
 
 impl Config {
-	pub fn version(&self, to: &SocketAddr) -> Version {
+	/// Returns proxy, which should be used to connect to the address.
+	pub fn proxy(&self, address: &NetworkAddress) -> Option<&Proxy> {
+		match *address {
+			NetworkAddress::TorV3(_) => self.onion_proxy.as_ref().or_else(|| self.proxy.as_ref()),
+			_ => self.proxy.as_ref(),
+		}
+	}
+
+	/// Returns true if outbound connections to the address can be established.
+	pub fn is_reachable(&self, address: &NetworkAddress) -> bool {
+		match *address {
+			NetworkAddress::IpV4(_) | NetworkAddress::IpV6(_) | NetworkAddress::Cjdns(_) => true,
+			NetworkAddress::TorV3(_) => self.proxy(address).is_some(),
+			NetworkAddress::I2p(_) => false,
+		}
+	}
+
+	pub fn version(&self, to: &NodeAddress) -> Version {
+		// addresses, which can't be represented in the version message, are sent as zeros
+		let to = to
+			.socket_addr()
+			.unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0));
 		Version::V70001(
 			V0 {
 				version: self.protocol_version,
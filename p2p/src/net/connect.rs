@@ -1,20 +1,59 @@
-use crate::io::{handshake, Error, SharedTcpStream};
+use crate::io::{handshake, Error, SharedTcpStream, Transport};
 use crate::net::{Config, Connection};
-use std::net::SocketAddr;
+use message::common::NodeAddress;
+use std::io;
 use std::time::Duration;
+use tokio::net::TcpStream;
 use tokio::time::timeout;
 
-pub async fn connect<'a>(address: &SocketAddr, config: &Config) -> Result<Connection, Error> {
-	let stream = SharedTcpStream::connect(address).await?;
+async fn open_stream(address: &NodeAddress, config: &Config) -> Result<TcpStream, io::Error> {
+	if !config.is_reachable(&address.address) {
+		return Err(io::Error::new(io::ErrorKind::Other, format!("{} is not reachable", address)));
+	}
+
+	match config.proxy(&address.address) {
+		Some(proxy) => proxy.connect(address).await,
+		None => {
+			let socket_addr = address
+				.socket_addr()
+				.expect("addresses, reachable without proxy, are ip addresses; qed");
+			TcpStream::connect(socket_addr).await
+		}
+	}
+}
+
+/// Connects to the node. If `v2_transport` is true, BIP324 transport is tried first
+/// and the node is reconnected using v1 transport, if it fails.
+pub async fn connect(address: &NodeAddress, config: &Config, v2_transport: bool) -> Result<Connection, Error> {
+	let magic = config.network.magic();
+	let v2 = if v2_transport {
+		let stream: SharedTcpStream = open_stream(address, config).await?.into();
+		match timeout(Duration::new(5, 0), Transport::initiate_v2(stream, magic)).await {
+			Ok(Ok(transport)) => Some(transport),
+			_ => {
+				// v1 nodes disconnect after receiving our public key
+				trace!("v2 transport handshake with {} failed, falling back to v1", address);
+				None
+			}
+		}
+	} else {
+		None
+	};
+	let stream = match v2 {
+		Some(transport) => transport,
+		None => Transport::v1(open_stream(address, config).await?.into(), magic),
+	};
+
 	let connect = async {
-		let handshake = handshake(&stream, config.network.magic(), config.version(address), config.protocol_minimum).await?;
+		let handshake = handshake(&stream, magic, config.version(address), config.protocol_minimum).await?;
 
 		Ok(Connection {
 			stream,
 			services: handshake.version.services(),
 			version: handshake.negotiated_version,
 			version_message: handshake.version,
-			magic: config.network.magic(),
+			addr_v2: handshake.addr_v2,
+			magic,
 			address: *address,
 		})
 	};
@@ -1,14 +1,15 @@
-use crate::io::SharedTcpStream;
-use message::common::Services;
+use crate::io::Transport;
+use message::common::{NodeAddress, Services};
 use message::types;
 use network::Magic;
-use std::net;
 
 pub struct Connection {
-	pub stream: SharedTcpStream,
+	pub stream: Transport,
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
 	pub services: Services,
-	pub address: net::SocketAddr,
+	pub address: NodeAddress,
+	/// True if peer has sent `sendaddrv2` during handshake
+	pub addr_v2: bool,
 }
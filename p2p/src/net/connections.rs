@@ -2,11 +2,12 @@ use crate::net::{Channel, Connection};
 use crate::p2p::Context;
 use crate::session::SessionFactory;
 use crate::{Direction, PeerId, PeerInfo};
+use message::common::NodeAddress;
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet};
+use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{mem, net};
 
 const SYNCHRONOUS_RESPONSES: bool = true;
 
@@ -30,7 +31,7 @@ impl Connections {
 	}
 
 	/// Returns addresses of all active channels (nonblocking).
-	pub fn addresses(&self) -> HashSet<net::SocketAddr> {
+	pub fn addresses(&self) -> HashSet<NodeAddress> {
 		self.channels().values().map(|channel| channel.peer_info().address).collect()
 	}
 
@@ -60,6 +61,8 @@ impl Connections {
 			version: connection.version,
 			version_message: connection.version_message,
 			magic: connection.magic,
+			addr_v2: connection.addr_v2,
+			transport: connection.stream.version(),
 		};
 
 		let session = T::new_session(context, peer_info.clone(), SYNCHRONOUS_RESPONSES);
@@ -0,0 +1,136 @@
+use message::common::{NodeAddress, Services};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Services, which DNS seeds can filter nodes by: network, bloom, witness, compact filters and network limited.
+const SEED_FILTER_SERVICES: u64 = 0x44d;
+
+/// Future, returned by the resolver.
+pub type ResolveFuture = Pin<Box<dyn Future<Output = Result<Vec<SocketAddr>, io::Error>> + Send>>;
+
+/// Resolves host names to socket addresses.
+pub trait Resolver: Send + Sync {
+	fn resolve(&self, host: String, port: u16) -> ResolveFuture;
+}
+
+/// Resolves host names using the system resolver.
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+	fn resolve(&self, host: String, port: u16) -> ResolveFuture {
+		Box::pin(async move {
+			tokio::net::lookup_host((host.as_str(), port))
+				.await
+				.map(|addresses| addresses.collect())
+		})
+	}
+}
+
+/// Returns subdomain of the seed, which lists only nodes with the given services (x9.seed.bitcoin.sipa.be).
+fn filtered_seed(seed: &str, services: Services) -> Option<String> {
+	let filter = u64::from(services) & SEED_FILTER_SERVICES;
+	if filter == 0 {
+		None
+	} else {
+		Some(format!("x{:x}.{}", filter, seed))
+	}
+}
+
+async fn resolve_seed(
+	resolver: Arc<dyn Resolver>,
+	seed: &'static str,
+	services: Services,
+	port: u16,
+) -> Result<Vec<SocketAddr>, io::Error> {
+	// seeds, which don't support filtering by services, are queried without the filter
+	if let Some(host) = filtered_seed(seed, services) {
+		match resolver.resolve(host, port).await {
+			Ok(addresses) if !addresses.is_empty() => return Ok(addresses),
+			_ => (),
+		}
+	}
+	resolver.resolve(seed.to_owned(), port).await
+}
+
+/// Queries all seeds for addresses of nodes with the given services.
+pub async fn seed_addresses(resolver: Arc<dyn Resolver>, seeds: &[&'static str], services: Services, port: u16) -> Vec<NodeAddress> {
+	let queries: Vec<_> = seeds
+		.iter()
+		.map(|seed| (*seed, tokio::spawn(resolve_seed(resolver.clone(), seed, services, port))))
+		.collect();
+
+	let mut addresses = Vec::new();
+	for (seed, query) in queries {
+		match query.await {
+			Ok(Ok(seed_addresses)) => {
+				trace!("DNS seed {} returned {} addresses", seed, seed_addresses.len());
+				addresses.extend(seed_addresses.into_iter().map(NodeAddress::from));
+			}
+			Ok(Err(err)) => warn!("DNS seed {} failed: {}", seed, err),
+			Err(err) => warn!("DNS seed {} failed: {}", seed, err),
+		}
+	}
+	addresses
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{filtered_seed, seed_addresses, ResolveFuture, Resolver};
+	use message::common::{NodeAddress, Services};
+	use std::collections::HashMap;
+	use std::io;
+	use std::net::{IpAddr, SocketAddr};
+	use std::sync::Arc;
+
+	/// Resolves the known hosts. Unknown hosts are not found.
+	struct ResolverStandIn(HashMap<&'static str, Vec<IpAddr>>);
+
+	impl Resolver for ResolverStandIn {
+		fn resolve(&self, host: String, port: u16) -> ResolveFuture {
+			let result = match self.0.get(host.as_str()) {
+				Some(ips) => Ok(ips.iter().map(|ip| SocketAddr::new(*ip, port)).collect()),
+				None => Err(io::Error::new(io::ErrorKind::Other, "host not found")),
+			};
+			Box::pin(async move { result })
+		}
+	}
+
+	#[test]
+	fn test_filtered_seed() {
+		let services = Services::default().with_network(true).with_witness(true);
+		assert_eq!(filtered_seed("seed.example", services), Some("x9.seed.example".to_owned()));
+		let services = services.with_compact_filters(true).with_p2p_v2(true);
+		assert_eq!(filtered_seed("seed.example", services), Some("x49.seed.example".to_owned()));
+		assert_eq!(filtered_seed("seed.example", Services::default()), None);
+	}
+
+	#[tokio::test]
+	async fn test_seed_addresses() {
+		let mut records = HashMap::new();
+		records.insert(
+			"x9.seed1.example",
+			vec!["10.0.0.1".parse().unwrap(), "2001:db8::1".parse().unwrap()],
+		);
+		// seed, which doesn't support filtering by services
+		records.insert("seed2.example", vec!["10.0.0.2".parse().unwrap()]);
+		let resolver = Arc::new(ResolverStandIn(records));
+
+		let services = Services::default().with_network(true).with_witness(true);
+		let addresses = seed_addresses(resolver, &["seed1.example", "seed2.example", "seed3.example"], services, 8333).await;
+		let expected: Vec<NodeAddress> = vec![
+			"10.0.0.1:8333".parse().unwrap(),
+			"[2001:db8::1]:8333".parse().unwrap(),
+			"10.0.0.2:8333".parse().unwrap(),
+		];
+		assert_eq!(addresses, expected);
+	}
+
+	#[tokio::test]
+	async fn test_system_resolver() {
+		let addresses = super::SystemResolver.resolve("127.0.0.1".to_owned(), 8333).await.unwrap();
+		assert_eq!(addresses, vec!["127.0.0.1:8333".parse::<SocketAddr>().unwrap()]);
+	}
+}
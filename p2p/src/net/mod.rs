@@ -5,8 +5,11 @@ mod connect;
 mod connection;
 mod connection_counter;
 mod connections;
+mod dns;
 mod peer_context;
+mod proxy;
 mod stats;
+mod tor_control;
 
 pub use self::accept_connection::accept_connection;
 pub use self::channel::Channel;
@@ -15,5 +18,8 @@ pub use self::connect::connect;
 pub use self::connection::Connection;
 pub use self::connection_counter::ConnectionCounter;
 pub use self::connections::Connections;
+pub use self::dns::{seed_addresses, ResolveFuture, Resolver, SystemResolver};
 pub use self::peer_context::PeerContext;
+pub use self::proxy::Proxy;
 pub use self::stats::PeerStats;
+pub use self::tor_control::{TorControl, TorControlConfig};
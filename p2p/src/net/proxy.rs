@@ -0,0 +1,242 @@
+use message::common::{NetworkAddress, NodeAddress};
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const SOCKS_VERSION: u8 = 0x05;
+const SOCKS_AUTH_VERSION: u8 = 0x01;
+const SOCKS_NO_AUTH: u8 = 0x00;
+const SOCKS_USER_PASS_AUTH: u8 = 0x02;
+const SOCKS_CONNECT: u8 = 0x01;
+const SOCKS_ATYP_IPV4: u8 = 0x01;
+const SOCKS_ATYP_DOMAIN_NAME: u8 = 0x03;
+const SOCKS_ATYP_IPV6: u8 = 0x04;
+
+/// SOCKS5 proxy (RFC 1928)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proxy {
+	/// Address of the proxy.
+	pub address: SocketAddr,
+	/// Use random credentials for every connection, so Tor isolates streams of different connections.
+	pub randomize_credentials: bool,
+}
+
+impl Proxy {
+	/// Connects to the target through the proxy.
+	pub async fn connect(&self, target: &NodeAddress) -> Result<TcpStream, io::Error> {
+		let mut stream = TcpStream::connect(&self.address).await?;
+		let credentials = if self.randomize_credentials {
+			let credential = format!("{:016x}", rand::random::<u64>());
+			Some((credential.clone(), credential))
+		} else {
+			None
+		};
+		socks5_connect(&mut stream, target, credentials.as_ref().map(|(u, p)| (u.as_str(), p.as_str()))).await?;
+		Ok(stream)
+	}
+}
+
+fn proxy_error(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, format!("proxy error: {}", message))
+}
+
+fn reply_message(reply: u8) -> &'static str {
+	match reply {
+		0x01 => "general failure",
+		0x02 => "connection not allowed",
+		0x03 => "network unreachable",
+		0x04 => "host unreachable",
+		0x05 => "connection refused",
+		0x06 => "TTL expired",
+		0x07 => "protocol error",
+		0x08 => "address type not supported",
+		_ => "unknown",
+	}
+}
+
+/// Performs SOCKS5 handshake, after which the stream is connected to the target.
+async fn socks5_connect<S>(stream: &mut S, target: &NodeAddress, credentials: Option<(&str, &str)>) -> Result<(), io::Error>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let method = if credentials.is_some() {
+		SOCKS_USER_PASS_AUTH
+	} else {
+		SOCKS_NO_AUTH
+	};
+	stream.write_all(&[SOCKS_VERSION, 0x01, method]).await?;
+
+	let mut response = [0u8; 2];
+	stream.read_exact(&mut response).await?;
+	if response[0] != SOCKS_VERSION {
+		return Err(proxy_error("failed to initialize"));
+	}
+	match (response[1], credentials) {
+		(SOCKS_NO_AUTH, _) => (),
+		(SOCKS_USER_PASS_AUTH, Some((username, password))) => {
+			// RFC 1929
+			if username.len() > 255 || password.len() > 255 {
+				return Err(proxy_error("credentials too long"));
+			}
+			let mut request = vec![SOCKS_AUTH_VERSION, username.len() as u8];
+			request.extend_from_slice(username.as_bytes());
+			request.push(password.len() as u8);
+			request.extend_from_slice(password.as_bytes());
+			stream.write_all(&request).await?;
+
+			stream.read_exact(&mut response).await?;
+			if response[0] != SOCKS_AUTH_VERSION || response[1] != 0x00 {
+				return Err(proxy_error("authentication unsuccessful"));
+			}
+		}
+		_ => return Err(proxy_error("requested wrong authentication method")),
+	}
+
+	let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0x00];
+	match target.address {
+		NetworkAddress::IpV4(ip) => {
+			request.push(SOCKS_ATYP_IPV4);
+			request.extend_from_slice(&ip.octets());
+		}
+		NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => {
+			request.push(SOCKS_ATYP_IPV6);
+			request.extend_from_slice(&ip.octets());
+		}
+		NetworkAddress::TorV3(_) | NetworkAddress::I2p(_) => {
+			// the proxy resolves the name itself
+			let host = target.address.to_string();
+			request.push(SOCKS_ATYP_DOMAIN_NAME);
+			request.push(host.len() as u8);
+			request.extend_from_slice(host.as_bytes());
+		}
+	}
+	request.extend_from_slice(&target.port.to_be_bytes());
+	stream.write_all(&request).await?;
+
+	let mut response = [0u8; 4];
+	stream.read_exact(&mut response).await?;
+	if response[0] != SOCKS_VERSION {
+		return Err(proxy_error("malformed connect response"));
+	}
+	if response[1] != 0x00 {
+		return Err(proxy_error(reply_message(response[1])));
+	}
+	// skip the bound address and port
+	let bound_address_len = match response[3] {
+		SOCKS_ATYP_IPV4 => 4,
+		SOCKS_ATYP_IPV6 => 16,
+		SOCKS_ATYP_DOMAIN_NAME => stream.read_u8().await? as usize,
+		_ => return Err(proxy_error("malformed connect response")),
+	};
+	let mut bound_address = vec![0u8; bound_address_len + 2];
+	stream.read_exact(&mut bound_address).await?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Proxy;
+	use message::common::NodeAddress;
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::{TcpListener, TcpStream};
+
+	/// Minimal SOCKS5 server, which accepts single connection and returns the connect request.
+	async fn socks5_stand_in(mut stream: TcpStream, require_auth: bool, reply: u8) -> Vec<u8> {
+		let mut greeting = [0u8; 3];
+		stream.read_exact(&mut greeting).await.unwrap();
+		if require_auth {
+			assert_eq!(greeting, [0x05, 0x01, 0x02]);
+			stream.write_all(&[0x05, 0x02]).await.unwrap();
+			let mut version_and_len = [0u8; 2];
+			stream.read_exact(&mut version_and_len).await.unwrap();
+			let mut username = vec![0u8; version_and_len[1] as usize];
+			stream.read_exact(&mut username).await.unwrap();
+			let mut password = vec![0u8; stream.read_u8().await.unwrap() as usize];
+			stream.read_exact(&mut password).await.unwrap();
+			assert_eq!(username.len(), 16);
+			assert_eq!(username, password);
+			stream.write_all(&[0x01, 0x00]).await.unwrap();
+		} else {
+			assert_eq!(greeting, [0x05, 0x01, 0x00]);
+			stream.write_all(&[0x05, 0x00]).await.unwrap();
+		}
+
+		let mut request = vec![0u8; 4];
+		stream.read_exact(&mut request).await.unwrap();
+		let address_len = match request[3] {
+			0x01 => 4,
+			0x04 => 16,
+			0x03 => {
+				let len = stream.read_u8().await.unwrap();
+				request.push(len);
+				len as usize
+			}
+			_ => unreachable!(),
+		};
+		let mut address = vec![0u8; address_len + 2];
+		stream.read_exact(&mut address).await.unwrap();
+		request.extend_from_slice(&address);
+
+		stream
+			.write_all(&[0x05, reply, 0x00, 0x01, 0x7f, 0x00, 0x00, 0x01, 0x20, 0x8d])
+			.await
+			.unwrap();
+		if reply == 0x00 {
+			stream.write_all(b"ping").await.unwrap();
+		}
+		request
+	}
+
+	async fn connect_through_stand_in(target: &str, randomize_credentials: bool, reply: u8) -> (Result<Vec<u8>, String>, Vec<u8>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let proxy = Proxy {
+			address: listener.local_addr().unwrap(),
+			randomize_credentials,
+		};
+		let server = tokio::spawn(async move {
+			let (stream, _) = listener.accept().await.unwrap();
+			socks5_stand_in(stream, randomize_credentials, reply).await
+		});
+
+		let target: NodeAddress = target.parse().unwrap();
+		let result = match proxy.connect(&target).await {
+			Ok(mut stream) => {
+				let mut data = vec![0u8; 4];
+				stream.read_exact(&mut data).await.unwrap();
+				Ok(data)
+			}
+			Err(err) => Err(err.to_string()),
+		};
+		(result, server.await.unwrap())
+	}
+
+	#[tokio::test]
+	async fn test_proxy_connect_onion() {
+		let (result, request) =
+			connect_through_stand_in("duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion:8333", true, 0x00).await;
+		assert_eq!(result, Ok(b"ping".to_vec()));
+		let mut expected = vec![0x05, 0x01, 0x00, 0x03, 62];
+		expected.extend_from_slice(b"duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion");
+		expected.extend_from_slice(&[0x20, 0x8d]);
+		assert_eq!(request, expected);
+	}
+
+	#[tokio::test]
+	async fn test_proxy_connect_ip() {
+		let (result, request) = connect_through_stand_in("10.0.0.1:18333", false, 0x00).await;
+		assert_eq!(result, Ok(b"ping".to_vec()));
+		assert_eq!(request, vec![0x05, 0x01, 0x00, 0x01, 0x0a, 0x00, 0x00, 0x01, 0x47, 0x9d]);
+
+		let (result, request) = connect_through_stand_in("[::1]:8333", false, 0x00).await;
+		assert_eq!(result, Ok(b"ping".to_vec()));
+		assert_eq!(request[3], 0x04);
+		assert_eq!(request.len(), 22);
+	}
+
+	#[tokio::test]
+	async fn test_proxy_connect_refused() {
+		let (result, _) = connect_through_stand_in("10.0.0.1:8333", true, 0x05).await;
+		assert_eq!(result, Err("proxy error: connection refused".to_owned()));
+	}
+}
@@ -0,0 +1,242 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::{fs, io};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Key type requested from Tor, when the onion service is created for the first time.
+const NEW_ONION_KEY: &str = "NEW:ED25519-V3";
+
+/// Tor control port settings used to publish the onion service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorControlConfig {
+	/// Address of the Tor control port.
+	pub address: SocketAddr,
+	/// Password for the HASHEDPASSWORD authentication.
+	pub password: Option<String>,
+	/// File with the private key of the onion service. Created if it doesn't exist.
+	pub private_key_path: PathBuf,
+}
+
+/// Connection to the Tor control port (control-spec.txt).
+pub struct TorControl {
+	stream: BufReader<TcpStream>,
+}
+
+fn control_error(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, format!("tor control error: {}", message))
+}
+
+/// Removes quotes and escapes of the QuotedString.
+fn unquote(s: &str) -> String {
+	let s = s.trim_start_matches('"').trim_end_matches('"');
+	let mut result = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			result.extend(chars.next());
+		} else {
+			result.push(c);
+		}
+	}
+	result
+}
+
+fn quote(s: &str) -> String {
+	format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl TorControl {
+	pub async fn connect(address: &SocketAddr) -> Result<Self, io::Error> {
+		let stream = TcpStream::connect(address).await?;
+		Ok(TorControl {
+			stream: BufReader::new(stream),
+		})
+	}
+
+	/// Sends the command and returns lines of the successful reply, without the status code.
+	async fn command(&mut self, command: &str) -> Result<Vec<String>, io::Error> {
+		self.stream.get_mut().write_all(format!("{}\r\n", command).as_bytes()).await?;
+
+		let mut lines = Vec::new();
+		loop {
+			let mut line = String::new();
+			if self.stream.read_line(&mut line).await? == 0 {
+				return Err(io::ErrorKind::UnexpectedEof.into());
+			}
+			let line = line.trim_end();
+			if line.len() < 4 {
+				return Err(control_error(format!("malformed reply '{}'", line)));
+			}
+			let (status, separator, data) = (&line[..3], &line[3..4], &line[4..]);
+			if status != "250" {
+				return Err(control_error(format!(
+					"{} failed with '{}'",
+					command.split(' ').next().unwrap_or(""),
+					line
+				)));
+			}
+			lines.push(data.to_owned());
+			match separator {
+				" " => return Ok(lines),
+				// data reply, which lasts until the line with a single dot
+				"+" => loop {
+					let mut data_line = String::new();
+					if self.stream.read_line(&mut data_line).await? == 0 {
+						return Err(io::ErrorKind::UnexpectedEof.into());
+					}
+					if data_line.trim_end() == "." {
+						break;
+					}
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Authenticates using the password, if it's given, the cookie file or no authentication.
+	pub async fn authenticate(&mut self, password: Option<&str>) -> Result<(), io::Error> {
+		let protocol_info = self.command("PROTOCOLINFO 1").await?;
+		let auth = protocol_info
+			.iter()
+			.find(|line| line.starts_with("AUTH "))
+			.ok_or_else(|| control_error("PROTOCOLINFO reply has no AUTH line".into()))?;
+
+		let mut methods = Vec::new();
+		let mut cookie_file = None;
+		for field in auth.split(' ').skip(1) {
+			if let Some(value) = field.strip_prefix("METHODS=") {
+				methods = value.split(',').map(ToOwned::to_owned).collect();
+			} else if let Some(value) = field.strip_prefix("COOKIEFILE=") {
+				cookie_file = Some(unquote(value));
+			}
+		}
+		let supports = |method: &str| methods.iter().any(|m| m == method);
+
+		let command = match (password, cookie_file) {
+			(Some(password), _) if supports("HASHEDPASSWORD") => format!("AUTHENTICATE {}", quote(password)),
+			_ if supports("NULL") => "AUTHENTICATE".to_owned(),
+			(_, Some(cookie_file)) if supports("COOKIE") => {
+				let cookie = fs::read(&cookie_file)?;
+				let cookie: String = cookie.iter().map(|byte| format!("{:02x}", byte)).collect();
+				format!("AUTHENTICATE {}", cookie)
+			}
+			_ => return Err(control_error(format!("unsupported authentication methods {}", methods.join(",")))),
+		};
+		self.command(&command).await.map(|_| ())
+	}
+
+	/// Publishes the ephemeral onion service, which forwards connections on the virtual port to the target.
+	/// Returns the service id and the private key of the service, if the key has been generated by Tor.
+	pub async fn add_onion(
+		&mut self,
+		private_key: Option<&str>,
+		virtual_port: u16,
+		target: &SocketAddr,
+	) -> Result<(String, Option<String>), io::Error> {
+		let reply = self
+			.command(&format!(
+				"ADD_ONION {} Port={},{}",
+				private_key.unwrap_or(NEW_ONION_KEY),
+				virtual_port,
+				target
+			))
+			.await?;
+
+		let service_id = reply
+			.iter()
+			.find_map(|line| line.strip_prefix("ServiceID="))
+			.ok_or_else(|| control_error("ADD_ONION reply has no ServiceID".into()))?;
+		let private_key = reply.iter().find_map(|line| line.strip_prefix("PrivateKey="));
+		Ok((service_id.to_owned(), private_key.map(ToOwned::to_owned)))
+	}
+
+	/// Waits until the control connection is closed. Ephemeral onion service is removed after that.
+	pub async fn wait_closed(&mut self) {
+		let mut line = String::new();
+		while let Ok(read) = self.stream.read_line(&mut line).await {
+			if read == 0 {
+				break;
+			}
+			line.clear();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{quote, unquote, TorControl};
+	use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+	use tokio::net::TcpListener;
+
+	/// Answers the commands of the single control connection with the given replies.
+	async fn tor_control_stand_in(replies: Vec<&'static str>) -> (TorControl, tokio::task::JoinHandle<Vec<String>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let address = listener.local_addr().unwrap();
+		let server = tokio::spawn(async move {
+			let (stream, _) = listener.accept().await.unwrap();
+			let mut stream = BufReader::new(stream);
+			let mut commands = Vec::new();
+			for reply in replies {
+				let mut command = String::new();
+				stream.read_line(&mut command).await.unwrap();
+				commands.push(command.trim_end().to_owned());
+				stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+			}
+			commands
+		});
+		(TorControl::connect(&address).await.unwrap(), server)
+	}
+
+	#[test]
+	fn test_quote() {
+		assert_eq!(quote("pass\"word\\"), "\"pass\\\"word\\\\\"");
+		assert_eq!(unquote(&quote("pass\"word\\")), "pass\"word\\");
+	}
+
+	#[tokio::test]
+	async fn test_tor_control_add_onion() {
+		let (mut control, server) = tor_control_stand_in(vec![
+			"250-PROTOCOLINFO 1\r\n250-AUTH METHODS=HASHEDPASSWORD,NULL\r\n250-VERSION Tor=\"0.4.5.7\"\r\n250 OK\r\n",
+			"250 OK\r\n",
+			"250-ServiceID=duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad\r\n250-PrivateKey=ED25519-V3:a2V5\r\n250 OK\r\n",
+		])
+		.await;
+
+		control.authenticate(Some("secret")).await.unwrap();
+		let (service_id, private_key) = control.add_onion(None, 8333, &"127.0.0.1:8333".parse().unwrap()).await.unwrap();
+		assert_eq!(service_id, "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad");
+		assert_eq!(private_key, Some("ED25519-V3:a2V5".to_owned()));
+
+		assert_eq!(
+			server.await.unwrap(),
+			vec![
+				"PROTOCOLINFO 1".to_owned(),
+				"AUTHENTICATE \"secret\"".to_owned(),
+				"ADD_ONION NEW:ED25519-V3 Port=8333,127.0.0.1:8333".to_owned(),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_tor_control_errors() {
+		let (mut control, server) = tor_control_stand_in(vec![
+			"250-PROTOCOLINFO 1\r\n250-AUTH METHODS=NULL\r\n250 OK\r\n",
+			"250 OK\r\n",
+			"512 Bad argument\r\n",
+		])
+		.await;
+
+		// password is ignored, if it's not required
+		control.authenticate(Some("secret")).await.unwrap();
+		assert_eq!(
+			control
+				.add_onion(Some("ED25519-V3:a2V5"), 8333, &"127.0.0.1:8333".parse().unwrap())
+				.await
+				.unwrap_err()
+				.to_string(),
+			"tor control error: ADD_ONION failed with '512 Bad argument'"
+		);
+		assert_eq!(server.await.unwrap()[1], "AUTHENTICATE");
+	}
+}
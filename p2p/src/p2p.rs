@@ -1,22 +1,29 @@
 use crate::io::Error;
-use crate::net::{accept_connection, connect, Channel, ConnectionCounter, Connections};
+use crate::net::{
+	accept_connection, connect, seed_addresses, Channel, ConnectionCounter, Connections, SystemResolver, TorControl, TorControlConfig,
+};
 use crate::session::{NormalSessionFactory, SeednodeSessionFactory, SessionFactory};
-use crate::util::{Node, NodeTable};
+use crate::util::{BanEntry, BanList, MisbehaviorScores, Node, NodeTable, Subnet};
 use crate::{Config, Direction, InboundSyncConnectionRef, LocalSyncNodeRef, NetConfig, NodeTableError, OutboundSyncConnectionRef, PeerId};
-use message::common::Services;
-use message::types::addr::AddressEntry;
+use message::common::{NetworkAddress, NodeAddress, Services};
+use message::types::AddressEntryV2;
 use message::{Message, Payload};
 use network::Network;
 use parking_lot::{Mutex, RwLock};
+use primitives::time::{RealTime, Time};
 use rand::seq::SliceRandom;
+use rand::Rng;
 use std::future::Future;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
-use std::{error, net, time};
+use std::{error, fs, net, time};
 use tokio::runtime::Handle;
 use tokio::{net::TcpListener, net::TcpStream, stream::StreamExt};
 
+/// Misbehavior score, at which the peer is banned.
+pub const BAN_SCORE: u32 = 100;
+
 /// Network context.
 pub struct Context {
 	/// Handle to current Tokio runtime.
@@ -29,8 +36,14 @@ pub struct Context {
 	connection_counter: ConnectionCounter,
 	/// Node Table.
 	node_table: RwLock<NodeTable>,
+	/// Banned subnets.
+	ban_list: RwLock<BanList>,
+	/// Misbehavior scores by address.
+	misbehavior: Mutex<MisbehaviorScores>,
 	/// Local synchronization node.
 	local_sync_node: LocalSyncNodeRef,
+	/// Addresses of the published onion services.
+	local_addresses: RwLock<Vec<NodeAddress>>,
 	/// Node table path.
 	config: Config,
 }
@@ -44,7 +57,10 @@ impl Context {
 			connections: Default::default(),
 			connection_counter: ConnectionCounter::new(config.inbound_connections, config.outbound_connections),
 			node_table: RwLock::new(NodeTable::from_file(config.preferable_services, config.node_table_path.clone())?),
+			ban_list: RwLock::new(BanList::from_file(config.node_table_path.clone())?),
+			misbehavior: Default::default(),
 			local_sync_node,
+			local_addresses: Default::default(),
 			config,
 		};
 
@@ -86,25 +102,87 @@ impl Context {
 	}
 
 	/// Updates node table.
-	pub fn update_node_table(&self, nodes: Vec<AddressEntry>) {
+	pub fn update_node_table(&self, nodes: Vec<AddressEntryV2>) {
 		trace!("Updating node table with {} entries", nodes.len());
 		self.node_table.write().insert_many(nodes);
 	}
 
 	/// Penalize node.
-	pub fn penalize_node(&self, addr: &SocketAddr) {
+	pub fn penalize_node(&self, addr: &NodeAddress) {
 		trace!("Penalizing node {}", addr);
 		self.node_table.write().note_failure(addr);
 	}
 
+	/// Increases misbehavior score of the peer. Peer is banned, when the score reaches `BAN_SCORE`.
+	pub fn misbehaving(&self, addr: &NodeAddress, score: u32) {
+		let total = self.misbehavior.lock().misbehaving(addr.address, score);
+		trace!("Misbehavior score of {} increased by {} to {}", addr, score, total);
+		// inbound onion service connections come from the loopback address, they are only disconnected
+		if total >= BAN_SCORE && !addr.address.ip().map_or(false, |ip| ip.is_loopback()) {
+			self.misbehavior.lock().remove(&addr.address);
+			self.ban(addr.address.into(), None);
+		}
+	}
+
+	/// Returns misbehavior score of the address.
+	pub fn misbehavior_score(&self, addr: &NetworkAddress) -> u32 {
+		self.misbehavior.lock().score(addr)
+	}
+
+	/// Bans the subnet until the given time or for the configured ban time and disconnects its peers.
+	pub fn ban(&self, subnet: Subnet, until: Option<u64>) {
+		let until = until.unwrap_or_else(|| RealTime.now().as_secs() + self.config.ban_time);
+		trace!("Banning {} until {}", subnet, until);
+		self.ban_list.write().ban(subnet, until);
+		self.save_ban_list();
+
+		for info in self.connections.info() {
+			if subnet.contains(&info.address.address) {
+				self.close_channel(info.id);
+			}
+		}
+	}
+
+	/// Removes ban of the subnet. Returns false if the subnet isn't banned.
+	pub fn unban(&self, subnet: &Subnet) -> bool {
+		trace!("Unbanning {}", subnet);
+		let unbanned = self.ban_list.write().unban(subnet);
+		self.save_ban_list();
+		unbanned
+	}
+
+	/// Removes all bans.
+	pub fn clear_banned(&self) {
+		self.ban_list.write().clear();
+		self.save_ban_list();
+	}
+
+	/// Returns active bans.
+	pub fn banned(&self) -> Vec<BanEntry> {
+		self.ban_list.read().entries()
+	}
+
+	/// Returns true if the address is banned.
+	pub fn is_banned(&self, addr: &NetworkAddress) -> bool {
+		self.ban_list.read().is_banned(addr)
+	}
+
+	fn save_ban_list(&self) {
+		let mut ban_list = self.ban_list.write();
+		ban_list.sweep();
+		if let Err(_err) = ban_list.save_to_file() {
+			error!("Saving ban list to disk failed");
+		}
+	}
+
 	/// Adds node to table.
-	pub fn add_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
+	pub fn add_node(&self, addr: NodeAddress) -> Result<(), NodeTableError> {
 		trace!("Adding node {} to node table", &addr);
 		self.node_table.write().add(addr, self.config.connection.services)
 	}
 
 	/// Removes node from table.
-	pub fn remove_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
+	pub fn remove_node(&self, addr: NodeAddress) -> Result<(), NodeTableError> {
 		trace!("Removing node {} from node table", &addr);
 		self.node_table.write().remove(&addr)
 	}
@@ -134,12 +212,15 @@ impl Context {
 		let needed = context.connection_counter.outbound_connections_needed() as usize;
 		if needed != 0 {
 			let used_addresses = context.connections.addresses();
-			let peers = context.node_table.read().nodes_with_services(
-				&Services::default(),
-				context.config.internet_protocol,
-				&used_addresses,
-				needed,
-			);
+			let is_connectable = |address: &NetworkAddress| {
+				context.config.internet_protocol.is_allowed(address)
+					&& context.config.connection.is_reachable(address)
+					&& !context.is_banned(address)
+			};
+			let peers = context
+				.node_table
+				.read()
+				.nodes_with_services(&Services::default(), is_connectable, &used_addresses, needed);
 			let addresses = peers.into_iter().map(|peer| peer.address()).collect::<Vec<_>>();
 
 			trace!("Creating {} more outbound connections", addresses.len());
@@ -153,13 +234,19 @@ impl Context {
 		}
 	}
 
-	/// Connect to socket.
-	async fn connect_future<T>(context: Arc<Context>, socket: net::SocketAddr)
+	/// Connect to node.
+	async fn connect_future<T>(context: Arc<Context>, socket: NodeAddress)
 	where
 		T: SessionFactory,
 	{
 		trace!("Trying to connect to: {}", socket);
-		match connect(&socket, &context.config.connection).await {
+		let v2_transport = context.config.connection.services.p2p_v2()
+			&& context
+				.node_table
+				.read()
+				.services(&socket)
+				.map_or(false, |services| services.p2p_v2());
+		match connect(&socket, &context.config.connection, v2_transport).await {
 			Ok(connection) => {
 				// successful handshake
 				trace!("Connected to {}", connection.address);
@@ -197,8 +284,8 @@ impl Context {
 		}
 	}
 
-	/// Connect to socket using given context.
-	pub fn connect<T>(context: Arc<Context>, socket: net::SocketAddr)
+	/// Connect to node using given context.
+	pub fn connect<T>(context: Arc<Context>, socket: NodeAddress)
 	where
 		T: SessionFactory + 'static,
 	{
@@ -206,7 +293,7 @@ impl Context {
 		context.runtime_handle.spawn(Context::connect_future::<T>(context.clone(), socket));
 	}
 
-	pub fn connect_normal(context: Arc<Context>, socket: net::SocketAddr) {
+	pub fn connect_normal(context: Arc<Context>, socket: NodeAddress) {
 		Self::connect::<NormalSessionFactory>(context, socket)
 	}
 
@@ -228,30 +315,130 @@ impl Context {
 				// protocol error
 				trace!("Accepting handshake from {} failed with error: {}", socket, err);
 				// TODO: close socket
-				context.node_table.write().note_failure(&socket);
+				context.node_table.write().note_failure(&socket.into());
 				context.connection_counter.note_close_inbound_connection();
 			}
 			Err(Error::Timeout) => {
 				// connection time out
 				trace!("Accepting handshake from {} timed out", socket);
 				// TODO: close socket
-				context.node_table.write().note_failure(&socket);
+				context.node_table.write().note_failure(&socket.into());
 				context.connection_counter.note_close_inbound_connection();
 			}
 			_ => {
 				// network error
 				trace!("Accepting handshake from {} failed with network error", socket);
-				context.node_table.write().note_failure(&socket);
+				context.node_table.write().note_failure(&socket.into());
 				context.connection_counter.note_close_inbound_connection();
 			}
 		}
 	}
 
 	pub fn accept_connection(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, config: NetConfig) {
+		if context.is_banned(&socket.ip().into()) {
+			trace!("Rejecting connection from banned {}", socket);
+			return;
+		}
 		context.connection_counter.note_new_inbound_connection();
 		tokio::spawn(Context::accept_connection_future(context.clone(), stream, socket, config));
 	}
 
+	/// Publishes onion service, which forwards connections to the local listener.
+	/// The service is removed by Tor, when the control connection is closed.
+	async fn publish_onion_service(context: Arc<Context>, config: TorControlConfig) {
+		let private_key = fs::read_to_string(&config.private_key_path).ok().map(|key| key.trim().to_owned());
+		let virtual_port = context.config.connection.network.port();
+		let local_address = context.config.connection.local_address;
+		let target = match local_address.ip() {
+			IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), local_address.port()),
+			IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(Ipv6Addr::LOCALHOST.into(), local_address.port()),
+			_ => local_address,
+		};
+
+		let mut control = match TorControl::connect(&config.address).await {
+			Ok(control) => control,
+			Err(err) => {
+				error!("Failed to connect to Tor control port {}: {}", config.address, err);
+				return;
+			}
+		};
+		let published = async {
+			control.authenticate(config.password.as_deref()).await?;
+			control.add_onion(private_key.as_deref(), virtual_port, &target).await
+		};
+		let (service_id, new_private_key) = match published.await {
+			Ok(published) => published,
+			Err(err) => {
+				error!("Failed to publish onion service: {}", err);
+				return;
+			}
+		};
+		if let Some(new_private_key) = new_private_key {
+			if let Err(err) = fs::write(&config.private_key_path, new_private_key) {
+				warn!("Failed to save onion service private key: {}", err);
+			}
+		}
+
+		let address = match format!("{}.onion", service_id).parse::<NetworkAddress>() {
+			Ok(address) => NodeAddress::new(address, virtual_port),
+			Err(_) => {
+				error!("Tor returned invalid onion service id {}", service_id);
+				return;
+			}
+		};
+		info!("Published onion service {}", address);
+		context.local_addresses.write().push(address);
+
+		control.wait_closed().await;
+		warn!("Tor control connection closed, onion service {} is no longer available", address);
+		context.local_addresses.write().retain(|local_address| *local_address != address);
+	}
+
+	/// Fills node table with addresses from DNS seeds. Node table is empty on the first run
+	/// and fixed seeds are used, if DNS seeds don't return any addresses.
+	async fn bootstrap(context: Arc<Context>) {
+		let config = &context.config;
+		let network = config.connection.network;
+		let query_dns =
+			(config.force_dns_seed || (config.dns_seed && context.node_table.read().is_empty())) && !network.dns_seeds().is_empty();
+		if query_dns && config.connection.proxy.is_some() {
+			// resolving seeds directly would reveal that this node is running
+			info!("DNS seeds are not queried, because proxy is used");
+		} else if query_dns {
+			let addresses = seed_addresses(
+				Arc::new(SystemResolver),
+				network.dns_seeds(),
+				config.preferable_services,
+				network.port(),
+			)
+			.await;
+			info!("Received {} addresses from DNS seeds", addresses.len());
+			// addresses are a few days old, so nodes announced recently are preferred
+			let now = RealTime.now().as_secs() as u32;
+			let entries = addresses
+				.into_iter()
+				.map(|address| AddressEntryV2 {
+					timestamp: now - rand::thread_rng().gen_range(3 * 24 * 60 * 60..7 * 24 * 60 * 60),
+					services: config.preferable_services,
+					address,
+				})
+				.collect();
+			context.update_node_table(entries);
+		}
+
+		if context.node_table.read().is_empty() {
+			let seeds: Vec<SocketAddr> = match network {
+				Network::Mainnet => seeds_main(),
+				Network::Testnet => seeds_test(),
+				_ => vec![],
+			};
+
+			for seed in seeds.choose_multiple(&mut rand::thread_rng(), 5) {
+				Context::connect::<SeednodeSessionFactory>(context.clone(), (*seed).into());
+			}
+		}
+	}
+
 	/// Starts tcp server and listens for incoming connections.
 	pub async fn listen(context: Arc<Context>, config: NetConfig) {
 		trace!("Starting tcp server");
@@ -259,11 +446,15 @@ impl Context {
 		while let Some(stream) = server.next().await {
 			match stream {
 				Ok(stream) => {
+					let peer_address = match stream.peer_addr() {
+						Ok(address) => address,
+						Err(_) => continue,
+					};
 					// because we acquire atomic value twice,
 					// it may happen that accept slightly more connections than we need
 					// we don't mind
 					if context.connection_counter.inbound_connections_needed() > 0 {
-						Context::accept_connection(context.clone(), stream, config.local_address, config.clone());
+						Context::accept_connection(context.clone(), stream, peer_address, config.clone());
 					} else {
 						// ignore result
 						let _ = stream.shutdown(net::Shutdown::Both);
@@ -400,6 +591,25 @@ impl Context {
 	pub fn nodes(&self) -> Vec<Node> {
 		self.node_table.read().nodes()
 	}
+
+	/// Returns addresses, under which this node accepts connections.
+	pub fn local_addresses(&self) -> Vec<NodeAddress> {
+		self.local_addresses.read().clone()
+	}
+
+	/// Returns entries of the local addresses, which are advertised to peers.
+	pub fn local_address_entries(&self) -> Vec<AddressEntryV2> {
+		let timestamp = RealTime.now().as_secs() as u32;
+		self.local_addresses
+			.read()
+			.iter()
+			.map(|address| AddressEntryV2 {
+				timestamp,
+				services: self.config.connection.services,
+				address: *address,
+			})
+			.collect()
+	}
 }
 
 include!(concat!(env!("OUT_DIR"), "/seeds_main.rs"));
@@ -429,18 +639,14 @@ impl P2P {
 			self.connect::<NormalSessionFactory>(*peer);
 		}
 
+		if let Some(tor_control) = self.context.config.tor_control.clone() {
+			tokio::spawn(Context::publish_onion_service(self.context.clone(), tor_control));
+		}
+
 		if self.context.config.seed.is_some() {
-			Context::connect::<SeednodeSessionFactory>(self.context.clone(), self.context.config.seed.unwrap());
+			Context::connect::<SeednodeSessionFactory>(self.context.clone(), self.context.config.seed.unwrap().into());
 		} else {
-			let seeds: Vec<SocketAddr> = match self.context.config.connection.network {
-				Network::Mainnet => seeds_main(),
-				Network::Testnet => seeds_test(),
-				_ => vec![],
-			};
-
-			for seed in seeds.choose_multiple(&mut rand::thread_rng(), 5) {
-				Context::connect::<SeednodeSessionFactory>(self.context.clone(), *seed);
-			}
+			tokio::spawn(Context::bootstrap(self.context.clone()));
 		}
 
 		tokio::spawn(Context::autoconnect(self.context.clone()));
@@ -453,7 +659,7 @@ impl P2P {
 	where
 		T: SessionFactory + 'static,
 	{
-		Context::connect::<T>(self.context.clone(), addr);
+		Context::connect::<T>(self.context.clone(), addr.into());
 	}
 
 	pub fn context(&self) -> &Arc<Context> {
@@ -3,7 +3,8 @@ use crate::io::Error;
 use crate::net::PeerContext;
 use crate::protocol::Protocol;
 use crate::Direction;
-use message::types::{Addr, GetAddr};
+use message::types::addr::AddressEntry;
+use message::types::{Addr, AddrV2, AddressEntryV2, GetAddr, SendAddrV2};
 use message::{deserialize_payload, Command, Payload};
 use std::sync::Arc;
 use std::time::Duration;
@@ -13,15 +14,46 @@ pub struct AddrProtocol {
 	context: Arc<PeerContext>,
 	/// True if this is a connection to the seednode && we should disconnect after receiving addr message
 	is_seed_node_connection: bool,
+	/// True if peer prefers `addrv2` messages (BIP155)
+	addr_v2: bool,
 }
 
 impl AddrProtocol {
 	pub fn new(context: Arc<PeerContext>, is_seed_node_connection: bool) -> Self {
 		AddrProtocol {
+			addr_v2: context.info().addr_v2,
 			context,
 			is_seed_node_connection,
 		}
 	}
+
+	fn on_addresses(&mut self, addresses: Vec<AddressEntryV2>) {
+		let nodes_len = addresses.len();
+		self.context.global().update_node_table(addresses);
+		// seednodes are currently responding with two addr messages:
+		// 1) addr message with single address - seednode itself
+		// 2) addr message with 1000 addresses (seednode node_table contents)
+		if self.is_seed_node_connection && nodes_len > 1 {
+			self.context.close();
+		}
+	}
+
+	/// Sends addresses of the onion services published by this node
+	fn advertise_local_addresses(&self) {
+		let entries = self.context.global().local_address_entries();
+		if entries.is_empty() {
+			return;
+		}
+
+		if self.addr_v2 {
+			self.context.send_request(AddrV2 { addresses: entries });
+		} else {
+			let entries: Vec<_> = entries.iter().filter_map(AddressEntryV2::to_address_entry).collect();
+			if !entries.is_empty() {
+				self.context.send_request(Addr::new(entries));
+			}
+		}
+	}
 }
 
 impl Protocol for AddrProtocol {
@@ -29,6 +61,7 @@ impl Protocol for AddrProtocol {
 		if let Direction::Outbound = self.context.info().direction {
 			self.context.send_request(GetAddr);
 		}
+		self.advertise_local_addresses();
 	}
 
 	fn on_message(&mut self, command: &Command, payload: &Bytes) -> Result<(), Error> {
@@ -36,26 +69,36 @@ impl Protocol for AddrProtocol {
 		// meanwhile seednodes, surprisingly, send addr message even before they are asked for it
 		if command == &GetAddr::command() {
 			let _: GetAddr = deserialize_payload(payload, self.context.info().version)?;
-			let entries = self.context.global().node_table_entries().into_iter().map(Into::into).collect();
-			let addr = Addr::new(entries);
-			self.context.send_response_inline(addr);
+			let entries = self.context.global().node_table_entries().into_iter().map(AddressEntryV2::from);
+			if self.addr_v2 {
+				self.context.send_response_inline(AddrV2 {
+					addresses: entries.collect(),
+				});
+			} else {
+				let addr = Addr::new(entries.filter_map(|entry| entry.to_address_entry()).collect());
+				self.context.send_response_inline(addr);
+			}
+		} else if command == &SendAddrV2::command() {
+			let _: SendAddrV2 = deserialize_payload(payload, self.context.info().version)?;
+			if !self.addr_v2 {
+				self.addr_v2 = true;
+				self.advertise_local_addresses();
+			}
+		} else if command == &AddrV2::command() {
+			let addr: AddrV2 = deserialize_payload(payload, self.context.info().version)?;
+			self.on_addresses(addr.addresses);
 		} else if command == &Addr::command() {
 			let addr: Addr = deserialize_payload(payload, self.context.info().version)?;
-			match addr {
-				Addr::V0(_) => {
-					unreachable!("This version of protocol is not supported!");
-				}
-				Addr::V31402(addr) => {
-					let nodes_len = addr.addresses.len();
-					self.context.global().update_node_table(addr.addresses);
-					// seednodes are currently responding with two addr messages:
-					// 1) addr message with single address - seednode itself
-					// 2) addr message with 1000 addresses (seednode node_table contents)
-					if self.is_seed_node_connection && nodes_len > 1 {
-						self.context.close();
-					}
-				}
-			}
+			let addresses = match addr {
+				// addresses without timestamp are considered the oldest ones
+				Addr::V0(addr) => addr
+					.addresses
+					.into_iter()
+					.map(|address| AddressEntryV2::from(AddressEntry { timestamp: 0, address }))
+					.collect(),
+				Addr::V31402(addr) => addr.addresses.into_iter().map(Into::into).collect(),
+			};
+			self.on_addresses(addresses);
 		}
 		Ok(())
 	}
@@ -81,7 +124,7 @@ impl Protocol for SeednodeProtocol {
 	fn on_message(&mut self, command: &Command, _payload: &Bytes) -> Result<(), Error> {
 		// Seed nodes send addr message more than once with different addresses.
 		// We can't disconnect after first read. Let's delay it by 60 seconds.
-		if !self.disconnecting && command == &Addr::command() {
+		if !self.disconnecting && (command == &Addr::command() || command == &AddrV2::command()) {
 			self.disconnecting = true;
 			let context = self.context.global().clone();
 			let peer = self.context.info().id;
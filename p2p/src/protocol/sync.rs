@@ -42,6 +42,9 @@ pub trait InboundSyncConnection: Send + Sync {
 	fn on_get_block_txn(&self, message: types::GetBlockTxn);
 	fn on_block_txn(&self, message: types::BlockTxn);
 	fn on_notfound(&self, message: types::NotFound);
+	fn on_get_cfilters(&self, message: types::GetCFilters);
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders);
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt);
 }
 
 pub trait OutboundSyncConnection: Send + Sync {
@@ -67,7 +70,12 @@ pub trait OutboundSyncConnection: Send + Sync {
 	fn send_get_block_txn(&self, message: types::GetBlockTxn);
 	fn send_block_txn(&self, message: types::BlockTxn);
 	fn send_notfound(&self, message: types::NotFound);
+	fn send_cfilter(&self, message: types::CFilter);
+	fn send_cfheaders(&self, message: types::CFHeaders);
+	fn send_cfcheckpt(&self, message: types::CFCheckpt);
 	fn ignored(&self, id: u32);
+	/// Increases misbehavior score of the peer, which gets banned after reaching `BAN_SCORE`
+	fn misbehaving(&self, score: u32);
 	fn close(&self);
 }
 
@@ -170,10 +178,28 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.send_request(message);
 	}
 
+	fn send_cfilter(&self, message: types::CFilter) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfheaders(&self, message: types::CFHeaders) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfcheckpt(&self, message: types::CFCheckpt) {
+		self.context.send_request(message);
+	}
+
 	fn ignored(&self, id: u32) {
 		self.context.ignore_response(id);
 	}
 
+	fn misbehaving(&self, score: u32) {
+		let context = self.context.global().clone();
+		let address = self.context.info().address;
+		context.clone().spawn(async move { context.misbehaving(&address, score) });
+	}
+
 	fn close(&self) {
 		self.context.global().penalize_node(&self.context.info().address);
 		self.context.close()
@@ -304,6 +330,27 @@ impl Protocol for SyncProtocol {
 		} else if command == &types::NotFound::command() {
 			let message: types::NotFound = deserialize_payload(payload, version)?;
 			self.inbound_connection.on_notfound(message);
+		} else if command == &types::GetCFilters::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFilters = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfilters(message);
+		} else if command == &types::GetCFHeaders::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFHeaders = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfheaders(message);
+		} else if command == &types::GetCFCheckpt::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFCheckpt = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfcheckpt(message);
 		}
 		Ok(())
 	}
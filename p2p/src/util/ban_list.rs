@@ -0,0 +1,313 @@
+use csv;
+use message::common::NetworkAddress;
+use primitives::time::{RealTime, Time};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::{fmt, fs, io, str};
+
+const BANLIST_FILE: &str = "banlist.csv";
+
+/// Range of banned addresses. Onion and i2p subnets contain single address.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Subnet {
+	/// First address of the subnet.
+	network: NetworkAddress,
+	/// Length of the network prefix in bits. Unused for onion and i2p addresses.
+	prefix_len: u8,
+}
+
+/// Subnet is neither an address, nor an IP address with the prefix length.
+#[derive(Debug, PartialEq)]
+pub struct InvalidSubnet;
+
+fn ipv4_mask(prefix_len: u8) -> u32 {
+	u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+}
+
+fn ipv6_mask(prefix_len: u8) -> u128 {
+	u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0)
+}
+
+impl Subnet {
+	/// Creates subnet with the given prefix length. Host bits of the address are cleared.
+	pub fn new(address: NetworkAddress, prefix_len: u8) -> Result<Self, InvalidSubnet> {
+		let network = match address.ip() {
+			Some(IpAddr::V4(ip)) if prefix_len <= 32 => IpAddr::from((u32::from(ip) & ipv4_mask(prefix_len)).to_be_bytes()).into(),
+			Some(IpAddr::V6(ip)) if prefix_len <= 128 => IpAddr::from((u128::from(ip) & ipv6_mask(prefix_len)).to_be_bytes()).into(),
+			Some(_) => return Err(InvalidSubnet),
+			None => address,
+		};
+		Ok(Subnet { network, prefix_len })
+	}
+
+	/// Returns true if the subnet contains the address.
+	pub fn contains(&self, address: &NetworkAddress) -> bool {
+		match (self.network.ip(), address.ip()) {
+			(Some(IpAddr::V4(network)), Some(IpAddr::V4(ip))) => u32::from(ip) & ipv4_mask(self.prefix_len) == u32::from(network),
+			(Some(IpAddr::V6(network)), Some(IpAddr::V6(ip))) => u128::from(ip) & ipv6_mask(self.prefix_len) == u128::from(network),
+			_ => self.network == *address,
+		}
+	}
+}
+
+impl From<NetworkAddress> for Subnet {
+	fn from(address: NetworkAddress) -> Self {
+		let prefix_len = match address.ip() {
+			Some(IpAddr::V4(_)) => 32,
+			Some(IpAddr::V6(_)) => 128,
+			None => 0,
+		};
+		Subnet {
+			network: address,
+			prefix_len,
+		}
+	}
+}
+
+impl fmt::Display for Subnet {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.network.ip() {
+			Some(_) => write!(f, "{}/{}", self.network, self.prefix_len),
+			None => self.network.fmt(f),
+		}
+	}
+}
+
+impl str::FromStr for Subnet {
+	type Err = InvalidSubnet;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.splitn(2, '/');
+		let address: NetworkAddress = parts
+			.next()
+			.expect("splitn always returns at least one item; qed")
+			.parse()
+			.map_err(|_| InvalidSubnet)?;
+		match parts.next() {
+			Some(prefix_len) if address.ip().is_some() => Subnet::new(address, prefix_len.parse().map_err(|_| InvalidSubnet)?),
+			Some(_) => Err(InvalidSubnet),
+			None => Ok(address.into()),
+		}
+	}
+}
+
+/// Banned subnet.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BanEntry {
+	pub subnet: Subnet,
+	/// Time, when the ban has been created.
+	pub created: u64,
+	/// Time, when the ban expires.
+	pub until: u64,
+}
+
+/// List of banned subnets.
+#[derive(Default, Debug)]
+pub struct BanList<T = RealTime>
+where
+	T: Time,
+{
+	/// Time source.
+	time: T,
+	/// Bans by subnet.
+	bans: HashMap<Subnet, BanEntry>,
+	/// where ban list is stored
+	path: String,
+}
+
+impl BanList {
+	/// Opens a file and loads ban list from it.
+	pub fn from_file(path: String) -> Result<Self, io::Error> {
+		let file_path = path + "/" + BANLIST_FILE;
+		let mut list = fs::OpenOptions::new()
+			.create(true)
+			.read(true)
+			// without opening for write, mac os returns os error 22
+			.write(true)
+			.open(&file_path)
+			.and_then(Self::load)?;
+		list.path = file_path;
+		Ok(list)
+	}
+
+	/// Saves ban list to file
+	pub fn save_to_file(&self) -> Result<(), io::Error> {
+		fs::File::create(&self.path).and_then(|file| self.save(file))
+	}
+}
+
+impl<T> BanList<T>
+where
+	T: Time,
+{
+	/// Bans the subnet until the given time. Existing ban of the subnet is replaced.
+	pub fn ban(&mut self, subnet: Subnet, until: u64) {
+		let created = self.time.now().as_secs();
+		self.bans.insert(subnet, BanEntry { subnet, created, until });
+	}
+
+	/// Removes ban of the subnet. Returns false if the subnet isn't banned.
+	pub fn unban(&mut self, subnet: &Subnet) -> bool {
+		self.bans.remove(subnet).is_some()
+	}
+
+	/// Removes all bans.
+	pub fn clear(&mut self) {
+		self.bans.clear();
+	}
+
+	/// Returns true if the address belongs to the banned subnet.
+	pub fn is_banned(&self, address: &NetworkAddress) -> bool {
+		let now = self.time.now().as_secs();
+		self.bans.values().any(|entry| entry.until > now && entry.subnet.contains(address))
+	}
+
+	/// Removes expired bans.
+	pub fn sweep(&mut self) {
+		let now = self.time.now().as_secs();
+		self.bans.retain(|_, entry| entry.until > now);
+	}
+
+	/// Returns active bans, sorted by creation time.
+	pub fn entries(&self) -> Vec<BanEntry> {
+		let now = self.time.now().as_secs();
+		let mut entries: Vec<_> = self.bans.values().filter(|entry| entry.until > now).cloned().collect();
+		entries.sort_by_key(|entry| (entry.created, entry.until));
+		entries
+	}
+
+	/// Save ban list in csv format.
+	pub fn save<W>(&self, write: W) -> Result<(), io::Error>
+	where
+		W: io::Write,
+	{
+		let mut writer = csv::WriterBuilder::new().delimiter(b' ').from_writer(write);
+
+		let err = || io::Error::new(io::ErrorKind::Other, "Write csv error");
+
+		for entry in self.entries() {
+			let record = (entry.subnet.to_string(), entry.created, entry.until);
+			writer.serialize(record).map_err(|_| err())?;
+		}
+
+		Ok(())
+	}
+
+	/// Loads ban list from a csv source.
+	pub fn load<R>(read: R) -> Result<Self, io::Error>
+	where
+		R: io::Read,
+		T: Default,
+	{
+		let mut rdr = csv::ReaderBuilder::new().has_headers(false).delimiter(b' ').from_reader(read);
+
+		let mut ban_list = BanList::default();
+
+		let err = || io::Error::new(io::ErrorKind::Other, "Load csv error");
+
+		for row in rdr.deserialize() {
+			let (subnet, created, until): (String, u64, u64) = row.map_err(|_| err())?;
+			let subnet: Subnet = subnet.parse().map_err(|_| err())?;
+			ban_list.bans.insert(subnet, BanEntry { subnet, created, until });
+		}
+
+		ban_list.sweep();
+		Ok(ban_list)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BanEntry, BanList, InvalidSubnet, Subnet};
+	use message::common::NetworkAddress;
+	use test_data::time::{IncrementalTime, ZeroTime};
+
+	fn address(s: &str) -> NetworkAddress {
+		s.parse().unwrap()
+	}
+
+	#[test]
+	fn test_subnet_parse() {
+		let subnet: Subnet = "10.1.2.3/16".parse().unwrap();
+		assert_eq!(subnet.to_string(), "10.1.0.0/16");
+		assert_eq!("10.1.2.3".parse::<Subnet>().unwrap().to_string(), "10.1.2.3/32");
+		assert_eq!("2001:db8::1/32".parse::<Subnet>().unwrap().to_string(), "2001:db8::/32");
+		assert_eq!("::ffff:10.0.0.1".parse::<Subnet>().unwrap().to_string(), "10.0.0.1/32");
+
+		let onion = "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion";
+		assert_eq!(onion.parse::<Subnet>().unwrap().to_string(), onion);
+
+		assert_eq!("10.1.2.3/33".parse::<Subnet>(), Err(InvalidSubnet));
+		assert_eq!("10.1.2.3/x".parse::<Subnet>(), Err(InvalidSubnet));
+		assert_eq!(format!("{}/8", onion).parse::<Subnet>(), Err(InvalidSubnet));
+		assert_eq!("example.com".parse::<Subnet>(), Err(InvalidSubnet));
+	}
+
+	#[test]
+	fn test_subnet_contains() {
+		let subnet: Subnet = "10.1.0.0/16".parse().unwrap();
+		assert!(subnet.contains(&address("10.1.255.1")));
+		assert!(!subnet.contains(&address("10.2.0.1")));
+		assert!(!subnet.contains(&address("::1")));
+
+		let all: Subnet = "0.0.0.0/0".parse().unwrap();
+		assert!(all.contains(&address("192.168.0.1")));
+
+		let subnet: Subnet = "fc00::/8".parse().unwrap();
+		assert!(subnet.contains(&address("fc12::1")));
+		assert!(!subnet.contains(&address("2001:db8::1")));
+
+		let onion = "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion";
+		let subnet: Subnet = onion.parse().unwrap();
+		assert!(subnet.contains(&address(onion)));
+		assert!(!subnet.contains(&address("10.1.0.1")));
+	}
+
+	#[test]
+	fn test_ban_list_expiration() {
+		// every call to `now` increases time by one second
+		let mut ban_list = BanList::<IncrementalTime>::default();
+		ban_list.ban("10.0.0.0/8".parse().unwrap(), 3);
+		assert!(ban_list.is_banned(&address("10.0.0.1")));
+		assert!(!ban_list.is_banned(&address("11.0.0.1")));
+		assert!(!ban_list.is_banned(&address("10.0.0.1")));
+		assert_eq!(ban_list.entries(), vec![]);
+	}
+
+	#[test]
+	fn test_ban_list_unban() {
+		let mut ban_list = BanList::<ZeroTime>::default();
+		let subnet: Subnet = "10.0.0.1".parse().unwrap();
+		ban_list.ban(subnet, 100);
+		ban_list.ban("10.0.0.2".parse().unwrap(), 100);
+		assert!(ban_list.unban(&subnet));
+		assert!(!ban_list.unban(&subnet));
+		assert!(!ban_list.is_banned(&address("10.0.0.1")));
+		assert!(ban_list.is_banned(&address("10.0.0.2")));
+		ban_list.clear();
+		assert!(!ban_list.is_banned(&address("10.0.0.2")));
+	}
+
+	#[test]
+	fn test_ban_list_save_and_load() {
+		let mut ban_list = BanList::<ZeroTime>::default();
+		ban_list.ban("10.0.0.0/8".parse().unwrap(), 100);
+		ban_list.ban(
+			"duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion".parse().unwrap(),
+			200,
+		);
+
+		let mut db = Vec::new();
+		assert_eq!(ban_list.save(&mut db).unwrap(), ());
+		let loaded = BanList::<ZeroTime>::load(&db as &[u8]).unwrap();
+		assert_eq!(loaded.entries().len(), 2);
+		assert_eq!(
+			loaded.entries().into_iter().find(|entry| entry.until == 100),
+			Some(BanEntry {
+				subnet: "10.0.0.0/8".parse().unwrap(),
+				created: 0,
+				until: 100,
+			})
+		);
+	}
+}
@@ -1,10 +1,15 @@
-use std::{net, str};
+use message::common::NetworkAddress;
+use std::str;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum InternetProtocol {
 	Any,
 	IpV4,
 	IpV6,
+	/// Tor v3 hidden services
+	Onion,
+	I2p,
+	Cjdns,
 }
 
 impl Default for InternetProtocol {
@@ -20,23 +25,24 @@ impl str::FromStr for InternetProtocol {
 		match s {
 			"ipv4" => Ok(InternetProtocol::IpV4),
 			"ipv6" => Ok(InternetProtocol::IpV6),
+			"onion" => Ok(InternetProtocol::Onion),
+			"i2p" => Ok(InternetProtocol::I2p),
+			"cjdns" => Ok(InternetProtocol::Cjdns),
 			_ => Err("Invalid internet protocol"),
 		}
 	}
 }
 
 impl InternetProtocol {
-	pub fn is_allowed(&self, addr: &net::SocketAddr) -> bool {
-		match *self {
-			InternetProtocol::Any => true,
-			InternetProtocol::IpV4 => match *addr {
-				net::SocketAddr::V4(_) => true,
-				_ => false,
-			},
-			InternetProtocol::IpV6 => match *addr {
-				net::SocketAddr::V6(_) => true,
-				_ => false,
-			},
+	pub fn is_allowed(&self, addr: &NetworkAddress) -> bool {
+		match (*self, *addr) {
+			(InternetProtocol::Any, _) => true,
+			(InternetProtocol::IpV4, NetworkAddress::IpV4(_)) => true,
+			(InternetProtocol::IpV6, NetworkAddress::IpV6(_)) => true,
+			(InternetProtocol::Onion, NetworkAddress::TorV3(_)) => true,
+			(InternetProtocol::I2p, NetworkAddress::I2p(_)) => true,
+			(InternetProtocol::Cjdns, NetworkAddress::Cjdns(_)) => true,
+			_ => false,
 		}
 	}
 }
@@ -54,6 +60,20 @@ mod tests {
 	fn test_parsing_internet_protocol() {
 		assert_eq!(InternetProtocol::IpV4, "ipv4".parse().unwrap());
 		assert_eq!(InternetProtocol::IpV6, "ipv6".parse().unwrap());
+		assert_eq!(InternetProtocol::Onion, "onion".parse().unwrap());
+		assert_eq!(InternetProtocol::I2p, "i2p".parse().unwrap());
+		assert_eq!(InternetProtocol::Cjdns, "cjdns".parse().unwrap());
 		assert!("sa".parse::<InternetProtocol>().is_err());
 	}
+
+	#[test]
+	fn test_internet_protocol_is_allowed() {
+		let ipv4 = "10.0.0.1".parse().unwrap();
+		let cjdns = "fc00::1".parse().unwrap();
+		assert!(InternetProtocol::Any.is_allowed(&ipv4));
+		assert!(InternetProtocol::IpV4.is_allowed(&ipv4));
+		assert!(!InternetProtocol::IpV6.is_allowed(&ipv4));
+		assert!(InternetProtocol::Cjdns.is_allowed(&cjdns));
+		assert!(!InternetProtocol::IpV6.is_allowed(&cjdns));
+	}
 }
@@ -0,0 +1,116 @@
+use message::common::NetworkAddress;
+use primitives::time::{RealTime, Time};
+use std::collections::HashMap;
+
+/// Misbehavior score is forgotten, if the address hasn't misbehaved for this time (in seconds).
+const MISBEHAVIOR_EXPIRY_TIME: u64 = 24 * 60 * 60;
+
+/// Misbehavior score of the address.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+	/// Total score.
+	score: u32,
+	/// Time of the last misbehavior.
+	updated: u64,
+}
+
+/// Misbehavior scores by address. Scores are kept after disconnect, so peers can't reset them by reconnecting.
+#[derive(Default, Debug)]
+pub struct MisbehaviorScores<T = RealTime>
+where
+	T: Time,
+{
+	/// Time source.
+	time: T,
+	/// Scores by address.
+	scores: HashMap<NetworkAddress, Entry>,
+}
+
+impl<T> MisbehaviorScores<T>
+where
+	T: Time,
+{
+	/// Increases misbehavior score of the address and returns the new score.
+	pub fn misbehaving(&mut self, address: NetworkAddress, score: u32) -> u32 {
+		let now = self.time.now().as_secs();
+		self.scores.retain(|_, entry| entry.updated + MISBEHAVIOR_EXPIRY_TIME > now);
+		let entry = self.scores.entry(address).or_insert(Entry { score: 0, updated: now });
+		entry.score = entry.score.saturating_add(score);
+		entry.updated = now;
+		entry.score
+	}
+
+	/// Returns misbehavior score of the address.
+	pub fn score(&self, address: &NetworkAddress) -> u32 {
+		let now = self.time.now().as_secs();
+		self.scores
+			.get(address)
+			.filter(|entry| entry.updated + MISBEHAVIOR_EXPIRY_TIME > now)
+			.map_or(0, |entry| entry.score)
+	}
+
+	/// Forgets misbehavior score of the address.
+	pub fn remove(&mut self, address: &NetworkAddress) {
+		self.scores.remove(address);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{MisbehaviorScores, MISBEHAVIOR_EXPIRY_TIME};
+	use crate::p2p::BAN_SCORE;
+	use message::common::NetworkAddress;
+	use primitives::time::Time;
+	use std::cell::Cell;
+	use std::time::Duration;
+	use test_data::time::ZeroTime;
+
+	#[derive(Default)]
+	struct ManualTime(Cell<u64>);
+
+	impl Time for ManualTime {
+		fn now(&self) -> Duration {
+			Duration::from_secs(self.0.get())
+		}
+	}
+
+	fn address(s: &str) -> NetworkAddress {
+		s.parse().unwrap()
+	}
+
+	#[test]
+	fn test_misbehavior_accumulates_across_reconnects() {
+		let mut scores = MisbehaviorScores::<ZeroTime>::default();
+		let peer = address("10.0.0.1");
+		// peer reconnects after every misbehavior, but the score is kept by address
+		for _ in 0..4 {
+			assert!(scores.misbehaving(peer, 20) < BAN_SCORE);
+		}
+		assert_eq!(scores.score(&peer), 80);
+		assert_eq!(scores.score(&address("10.0.0.2")), 0);
+		// fifth misbehavior gets the peer banned
+		assert!(scores.misbehaving(peer, 20) >= BAN_SCORE);
+
+		scores.remove(&peer);
+		assert_eq!(scores.score(&peer), 0);
+	}
+
+	#[test]
+	fn test_misbehavior_expires() {
+		let mut scores = MisbehaviorScores::<ManualTime>::default();
+		let peer = address("10.0.0.1");
+		let other = address("10.0.0.2");
+		scores.misbehaving(peer, 20);
+		scores.misbehaving(other, 20);
+
+		scores.time.0.set(MISBEHAVIOR_EXPIRY_TIME - 1);
+		assert_eq!(scores.misbehaving(peer, 20), 40);
+		assert_eq!(scores.score(&other), 20);
+
+		// score of the address, which hasn't misbehaved recently, is forgotten
+		scores.time.0.set(MISBEHAVIOR_EXPIRY_TIME);
+		assert_eq!(scores.score(&other), 0);
+		assert_eq!(scores.misbehaving(peer, 20), 60);
+		assert_eq!(scores.scores.len(), 1);
+	}
+}
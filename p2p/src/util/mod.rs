@@ -1,12 +1,16 @@
+mod ban_list;
 mod internet_protocol;
 pub mod interval;
+mod misbehavior;
 mod node_table;
 pub mod nonce;
 mod peer;
 mod response_queue;
 mod synchronizer;
 
+pub use self::ban_list::{BanEntry, BanList, InvalidSubnet, Subnet};
 pub use self::internet_protocol::InternetProtocol;
+pub use self::misbehavior::MisbehaviorScores;
 pub use self::node_table::{Node, NodeTable, NodeTableError};
 pub use self::peer::{Direction, PeerId, PeerInfo};
 pub use self::response_queue::{ResponseQueue, Responses};
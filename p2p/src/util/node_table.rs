@@ -1,20 +1,19 @@
 use crate::util::InternetProtocol;
 use csv;
-use message::common::{NetAddress, Services};
-use message::types::addr::AddressEntry;
+use message::common::{NetworkAddress, NodeAddress, Services};
+use message::types::AddressEntryV2;
 use primitives::time::{RealTime, Time};
 use std::cmp::{Ord, Ordering, PartialOrd};
 use std::collections::hash_map::Entry;
 use std::collections::{BTreeSet, HashMap, HashSet};
-use std::net::SocketAddr;
-use std::{fs, io, net};
+use std::{fs, io};
 
 const NODES_FILE: &str = "nodes.csv";
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Node {
 	/// Node address.
-	addr: SocketAddr,
+	addr: NodeAddress,
 	/// Timestamp of last interaction with a node.
 	time: u64,
 	/// Services supported by the node.
@@ -26,20 +25,17 @@ pub struct Node {
 }
 
 impl Node {
-	pub fn address(&self) -> SocketAddr {
+	pub fn address(&self) -> NodeAddress {
 		self.addr
 	}
 }
 
-impl From<Node> for AddressEntry {
+impl From<Node> for AddressEntryV2 {
 	fn from(node: Node) -> Self {
-		AddressEntry {
+		AddressEntryV2 {
 			timestamp: node.time as u32,
-			address: NetAddress {
-				services: node.services,
-				address: node.addr.ip().into(),
-				port: node.addr.port().into(),
-			},
+			services: node.services,
+			address: node.addr,
 		}
 	}
 }
@@ -125,32 +121,8 @@ impl Ord for NodeByTime {
 impl Ord for Node {
 	fn cmp(&self, other: &Self) -> Ordering {
 		// some ordering using address as unique key
-		match self.addr {
-			SocketAddr::V4(self_addr) => match other.addr {
-				SocketAddr::V4(other_addr) => {
-					let self_port = self_addr.port();
-					let other_port = other_addr.port();
-					if self_port == other_port {
-						self_addr.ip().cmp(other_addr.ip())
-					} else {
-						self_port.cmp(&other_port)
-					}
-				}
-				SocketAddr::V6(_) => Ordering::Less,
-			},
-			SocketAddr::V6(self_addr) => match other.addr {
-				SocketAddr::V4(_) => Ordering::Greater,
-				SocketAddr::V6(other_addr) => {
-					let self_port = self_addr.port();
-					let other_port = other_addr.port();
-					if self_port == other_port {
-						self_addr.ip().cmp(other_addr.ip())
-					} else {
-						self_port.cmp(&other_port)
-					}
-				}
-			},
-		}
+		let key = |node: &Node| (node.addr.address.network_id(), node.addr.port, node.addr.address.to_vec());
+		key(self).cmp(&key(other))
 	}
 }
 
@@ -175,8 +147,8 @@ where
 	time: T,
 	/// Preferable services.
 	preferable_services: Services,
-	/// Nodes by socket address.
-	by_addr: HashMap<SocketAddr, Node>,
+	/// Nodes by address.
+	by_addr: HashMap<NodeAddress, Node>,
 	/// Nodes sorted by score.
 	by_score: BTreeSet<NodeByScore>,
 	/// Nodes sorted by time.
@@ -220,7 +192,7 @@ where
 	T: Time,
 {
 	/// Inserts new address and services pair into NodeTable.
-	pub fn insert(&mut self, addr: SocketAddr, services: Services) {
+	pub fn insert(&mut self, addr: NodeAddress, services: Services) {
 		let now = self.time.now().as_secs();
 		match self.by_addr.entry(addr) {
 			Entry::Occupied(mut entry) => {
@@ -247,12 +219,22 @@ where
 		}
 	}
 
-	pub fn exists(&self, addr: SocketAddr) -> bool {
+	/// Returns true if there are no nodes in the table.
+	pub fn is_empty(&self) -> bool {
+		self.by_addr.is_empty()
+	}
+
+	pub fn exists(&self, addr: NodeAddress) -> bool {
 		self.by_addr.contains_key(&addr)
 	}
 
-	pub fn add(&mut self, addr: SocketAddr, services: Services) -> Result<(), NodeTableError> {
-		if self.exists(addr.clone()) {
+	/// Returns services of the node, if it's known.
+	pub fn services(&self, addr: &NodeAddress) -> Option<Services> {
+		self.by_addr.get(addr).map(|node| node.services)
+	}
+
+	pub fn add(&mut self, addr: NodeAddress, services: Services) -> Result<(), NodeTableError> {
+		if self.exists(addr) {
 			Err(NodeTableError::AddressAlreadyAdded)
 		} else {
 			self.insert(addr, services);
@@ -260,10 +242,10 @@ where
 		}
 	}
 
-	/// Tries to remove node with the speicified socket address
+	/// Tries to remove node with the speicified address
 	/// from table, if exists.
 	/// Returnes `true` if it has removed anything
-	pub fn remove(&mut self, addr: &SocketAddr) -> Result<(), NodeTableError> {
+	pub fn remove(&mut self, addr: &NodeAddress) -> Result<(), NodeTableError> {
 		let node = self.by_addr.remove(&addr);
 		match node {
 			Some(val) => {
@@ -276,9 +258,9 @@ where
 	}
 
 	/// Inserts many new addresses into node table.
-	/// Used in `addr` request handler.
+	/// Used in `addr` and `addrv2` request handlers.
 	/// Discards all nodes with timestamp newer than current time.
-	pub fn insert_many(&mut self, addresses: Vec<AddressEntry>) {
+	pub fn insert_many(&mut self, addresses: Vec<AddressEntryV2>) {
 		// discard all nodes with timestamp newer than current time.
 		let now = self.time.now().as_secs();
 		let iter = addresses.into_iter().filter(|addr| addr.timestamp as u64 <= now);
@@ -286,10 +268,10 @@ where
 		// iterate over the rest
 		for addr in iter {
 			let node = Node {
-				addr: SocketAddr::new(addr.address.address.into(), addr.address.port.into()),
+				addr: addr.address,
 				time: addr.timestamp as u64,
-				services: addr.address.services,
-				is_preferable: addr.address.services.includes(&self.preferable_services),
+				services: addr.services,
+				is_preferable: addr.services.includes(&self.preferable_services),
 				failures: 0,
 			};
 
@@ -317,35 +299,16 @@ where
 		}
 	}
 
-	/// Returnes most reliable nodes with desired services.
-	pub fn nodes_with_services(
-		&self,
-		services: &Services,
-		protocol: InternetProtocol,
-		except: &HashSet<net::SocketAddr>,
-		limit: usize,
-	) -> Vec<Node> {
+	/// Returnes most reliable nodes with desired services, which addresses pass the filter.
+	pub fn nodes_with_services<F>(&self, services: &Services, filter: F, except: &HashSet<NodeAddress>, limit: usize) -> Vec<Node>
+	where
+		F: Fn(&NetworkAddress) -> bool,
+	{
 		self.by_score
 			.iter()
-			.filter(|node| protocol.is_allowed(&node.0.addr))
+			.filter(|node| filter(&node.0.addr.address))
 			.filter(|node| node.0.services.includes(services))
-			.filter(|node| {
-				let node_address = node.0.address();
-				!except.contains(&node_address)
-					&& match node_address {
-						net::SocketAddr::V4(v4) => !except.contains(&net::SocketAddr::V6(net::SocketAddrV6::new(
-							v4.ip().to_ipv6_compatible(),
-							v4.port(),
-							0,
-							0,
-						))),
-						net::SocketAddr::V6(v6) => v6
-							.ip()
-							.to_ipv4()
-							.map(|v4| !except.contains(&net::SocketAddr::V4(net::SocketAddrV4::new(v4, v6.port()))))
-							.unwrap_or(true),
-					}
-			})
+			.filter(|node| !except.contains(&node.0.addr))
 			.map(|node| node.0.clone())
 			.take(limit)
 			.collect()
@@ -367,14 +330,14 @@ where
 	pub fn recently_active_nodes(&self, protocol: InternetProtocol) -> Vec<Node> {
 		self.by_time
 			.iter()
-			.filter(|node| protocol.is_allowed(&node.0.addr))
+			.filter(|node| protocol.is_allowed(&node.0.addr.address))
 			.map(|node| node.0.clone())
 			.take(1000)
 			.collect()
 	}
 
 	/// Marks address as recently used.
-	pub fn note_used(&mut self, addr: &SocketAddr) {
+	pub fn note_used(&mut self, addr: &NodeAddress) {
 		if let Some(ref mut node) = self.by_addr.get_mut(addr) {
 			assert!(self.by_score.remove(&node.clone().into()));
 			assert!(self.by_time.remove(&node.clone().into()));
@@ -385,7 +348,7 @@ where
 	}
 
 	/// Notes failure.
-	pub fn note_failure(&mut self, addr: &SocketAddr) {
+	pub fn note_failure(&mut self, addr: &NodeAddress) {
 		if let Some(ref mut node) = self.by_addr.get_mut(addr) {
 			assert!(self.by_score.remove(&node.clone().into()));
 			assert!(self.by_time.remove(&node.clone().into()));
@@ -452,21 +415,22 @@ mod tests {
 	use super::NodeTable;
 
 	use crate::InternetProtocol;
-	use message::common::Services;
+	use message::common::{NodeAddress, Services};
 	use std::collections::HashSet;
-	use std::net::SocketAddr;
 	use test_data::time::{IncrementalTime, ZeroTime};
 
 	#[test]
 	fn test_node_table_insert() {
-		let s0: SocketAddr = "127.0.0.1:8000".parse().unwrap();
-		let s1: SocketAddr = "127.0.0.1:8001".parse().unwrap();
-		let s2: SocketAddr = "127.0.0.1:8002".parse().unwrap();
+		let s0: NodeAddress = "127.0.0.1:8000".parse().unwrap();
+		let s1: NodeAddress = "127.0.0.1:8001".parse().unwrap();
+		let s2: NodeAddress = "127.0.0.1:8002".parse().unwrap();
 		let mut table = NodeTable::<IncrementalTime>::default();
 		table.insert(s0, Services::default());
 		table.insert(s1, Services::default());
 		table.insert(s2, Services::default());
-		let nodes = table.nodes_with_services(&Services::default(), InternetProtocol::default(), &HashSet::new(), 2);
+		assert_eq!(table.services(&s0), Some(Services::default()));
+		assert_eq!(table.services(&"127.0.0.1:8003".parse().unwrap()), None);
+		let nodes = table.nodes_with_services(&Services::default(), |_| true, &HashSet::new(), 2);
 		assert_eq!(nodes.len(), 2);
 		assert_eq!(nodes[0].addr, s2);
 		assert_eq!(nodes[0].time, 2);
@@ -478,11 +442,11 @@ mod tests {
 
 	#[test]
 	fn test_node_table_note() {
-		let s0: SocketAddr = "127.0.0.1:8000".parse().unwrap();
-		let s1: SocketAddr = "127.0.0.1:8001".parse().unwrap();
-		let s2: SocketAddr = "127.0.0.1:8002".parse().unwrap();
-		let s3: SocketAddr = "127.0.0.1:8003".parse().unwrap();
-		let s4: SocketAddr = "127.0.0.1:8004".parse().unwrap();
+		let s0: NodeAddress = "127.0.0.1:8000".parse().unwrap();
+		let s1: NodeAddress = "127.0.0.1:8001".parse().unwrap();
+		let s2: NodeAddress = "127.0.0.1:8002".parse().unwrap();
+		let s3: NodeAddress = "127.0.0.1:8003".parse().unwrap();
+		let s4: NodeAddress = "127.0.0.1:8004".parse().unwrap();
 		let mut table = NodeTable::<IncrementalTime>::default();
 		table.insert(s0, Services::default());
 		table.insert(s1, Services::default());
@@ -494,7 +458,7 @@ mod tests {
 		table.note_used(&s1);
 		table.note_failure(&s2);
 		table.note_failure(&s3);
-		let nodes = table.nodes_with_services(&Services::default(), InternetProtocol::default(), &HashSet::new(), 10);
+		let nodes = table.nodes_with_services(&Services::default(), |_| true, &HashSet::new(), 10);
 		assert_eq!(nodes.len(), 5);
 
 		assert_eq!(nodes[0].addr, s1);
@@ -543,8 +507,8 @@ mod tests {
 
 	#[test]
 	fn test_node_table_duplicates() {
-		let s0: SocketAddr = "127.0.0.1:8000".parse().unwrap();
-		let s1: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+		let s0: NodeAddress = "127.0.0.1:8000".parse().unwrap();
+		let s1: NodeAddress = "127.0.0.1:8001".parse().unwrap();
 		let mut table = NodeTable::<ZeroTime>::default();
 		table.insert(s0, Services::default());
 		table.insert(s1, Services::default());
@@ -591,11 +555,11 @@ mod tests {
 
 	#[test]
 	fn test_save_and_load() {
-		let s0: SocketAddr = "127.0.0.1:8000".parse().unwrap();
-		let s1: SocketAddr = "127.0.0.1:8001".parse().unwrap();
-		let s2: SocketAddr = "127.0.0.1:8002".parse().unwrap();
-		let s3: SocketAddr = "127.0.0.1:8003".parse().unwrap();
-		let s4: SocketAddr = "127.0.0.1:8004".parse().unwrap();
+		let s0: NodeAddress = "127.0.0.1:8000".parse().unwrap();
+		let s1: NodeAddress = "127.0.0.1:8001".parse().unwrap();
+		let s2: NodeAddress = "127.0.0.1:8002".parse().unwrap();
+		let s3: NodeAddress = "127.0.0.1:8003".parse().unwrap();
+		let s4: NodeAddress = "127.0.0.1:8004".parse().unwrap();
 		let mut table = NodeTable::<IncrementalTime>::default();
 		table.insert(s0, Services::default());
 		table.insert(s1, Services::default());
@@ -630,27 +594,65 @@ mod tests {
 
 	#[test]
 	fn test_preferable_services() {
-		let s0: SocketAddr = "127.0.0.1:8000".parse().unwrap();
-		let s1: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+		let s0: NodeAddress = "127.0.0.1:8000".parse().unwrap();
+		let s1: NodeAddress = "127.0.0.1:8001".parse().unwrap();
 
 		let mut table = NodeTable::new(Services::default().with_network(true));
 		table.insert(s0, Services::default().with_network(true));
 		table.insert(s1, Services::default().with_network(true));
 		assert_eq!(
-			table.nodes_with_services(&Services::default(), InternetProtocol::default(), &HashSet::new(), 1)[0].address(),
+			table.nodes_with_services(&Services::default(), |_| true, &HashSet::new(), 1)[0].address(),
 			s1
 		);
 
 		table.note_failure(&s1);
 		assert_eq!(
-			table.nodes_with_services(&Services::default(), InternetProtocol::default(), &HashSet::new(), 1)[0].address(),
+			table.nodes_with_services(&Services::default(), |_| true, &HashSet::new(), 1)[0].address(),
 			s0
 		);
 
 		table.note_failure(&s0);
 		assert_eq!(
-			table.nodes_with_services(&Services::default(), InternetProtocol::default(), &HashSet::new(), 1)[0].address(),
+			table.nodes_with_services(&Services::default(), |_| true, &HashSet::new(), 1)[0].address(),
 			s1
 		);
 	}
+
+	#[test]
+	fn test_non_ip_nodes() {
+		let s0: NodeAddress = "127.0.0.1:8000".parse().unwrap();
+		let s1: NodeAddress = "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion:8333"
+			.parse()
+			.unwrap();
+		let s2: NodeAddress = "ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p:0".parse().unwrap();
+		let mut table = NodeTable::<IncrementalTime>::default();
+		table.insert(s0, Services::default());
+		table.insert(s1, Services::default());
+		table.insert(s2, Services::default());
+
+		let nodes = table.nodes_with_services(&Services::default(), |address| address.ip().is_some(), &HashSet::new(), 10);
+		assert_eq!(nodes.len(), 1);
+		assert_eq!(nodes[0].addr, s0);
+
+		let nodes = table.nodes_with_services(&Services::default(), |_| true, &vec![s0].into_iter().collect(), 10);
+		assert_eq!(nodes.len(), 2);
+		assert!(nodes.iter().all(|node| node.addr != s0));
+
+		let nodes = table.recently_active_nodes(InternetProtocol::Onion);
+		assert_eq!(nodes.len(), 1);
+		assert_eq!(nodes[0].addr, s1);
+		assert_eq!(table.recently_active_nodes(InternetProtocol::default()).len(), 3);
+
+		let mut db = Vec::new();
+		table.save(&mut db).unwrap();
+		let loaded_table = NodeTable::<IncrementalTime>::load(Services::default(), &db as &[u8]).unwrap();
+		assert_eq!(table.by_addr, loaded_table.by_addr);
+		assert_eq!(
+			"ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p:0 2 0 0
+duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion:8333 1 0 0
+127.0.0.1:8000 0 0 0
+",
+			String::from_utf8(db).unwrap()
+		);
+	}
 }
@@ -1,6 +1,7 @@
+use crate::io::TransportVersion;
+use message::common::NodeAddress;
 use message::types;
 use network::Magic;
-use std::net::SocketAddr;
 
 pub type PeerId = usize;
 
@@ -13,10 +14,14 @@ pub enum Direction {
 #[derive(Debug, PartialEq, Clone)]
 pub struct PeerInfo {
 	pub id: PeerId,
-	pub address: SocketAddr,
+	pub address: NodeAddress,
 	pub user_agent: String,
 	pub direction: Direction,
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
+	/// True if peer has sent `sendaddrv2` during handshake
+	pub addr_v2: bool,
+	/// Transport protocol used by the connection
+	pub transport: TransportVersion,
 }
@@ -0,0 +1,207 @@
+//! Analysis of the PSBT: what's missing and which role should process it next.
+
+use crate::finalizer::{solve, Missing};
+use crate::input::Input;
+use crate::psbt::Psbt;
+use script::Script;
+use std::fmt;
+
+/// Role of the PSBT processing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+	Creator,
+	Updater,
+	Signer,
+	Finalizer,
+	Extractor,
+}
+
+impl fmt::Display for Role {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Role::Creator => "creator".fmt(f),
+			Role::Updater => "updater".fmt(f),
+			Role::Signer => "signer".fmt(f),
+			Role::Finalizer => "finalizer".fmt(f),
+			Role::Extractor => "extractor".fmt(f),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputAnalysis {
+	/// True if output, spent by the input, is known
+	pub has_utxo: bool,
+	pub is_final: bool,
+	/// Data, required to finalize the input
+	pub missing: Option<Missing>,
+	/// Role, which should process the input next
+	pub next: Role,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+	pub inputs: Vec<InputAnalysis>,
+	/// Virtual size of the transaction, if all inputs can be finalized
+	pub estimated_vsize: Option<usize>,
+	/// Fee rate of the transaction in satoshis per 1000 virtual bytes
+	pub estimated_fee_rate: Option<u64>,
+	pub fee: Option<u64>,
+	/// Role, which should process the PSBT next
+	pub next: Role,
+	/// Reason, why PSBT is invalid
+	pub error: Option<String>,
+}
+
+impl Psbt {
+	pub fn analyze(&self) -> Analysis {
+		let inputs: Vec<_> = self.inputs.iter().map(analyze_input).collect();
+		let mut analysis = Analysis {
+			next: inputs.iter().map(|input| input.next).min().unwrap_or(Role::Extractor),
+			inputs,
+			estimated_vsize: None,
+			estimated_fee_rate: None,
+			fee: None,
+			error: None,
+		};
+		if let Err(err) = self.lock_time() {
+			analysis.error = Some(format!("PSBT is not valid. {}", err));
+			analysis.next = Role::Creator;
+			return analysis;
+		}
+		if !analysis.inputs.iter().all(|input| input.has_utxo) {
+			return analysis;
+		}
+
+		analysis.fee = self.fee();
+		let fee = match analysis.fee {
+			Some(fee) => fee,
+			None => {
+				analysis.error = Some("PSBT is not valid. Outputs spend more than inputs".into());
+				analysis.next = Role::Creator;
+				return analysis;
+			}
+		};
+
+		let mut finalized = self.clone();
+		if finalized.finalize() {
+			let transaction = finalized.extract().expect("finalized PSBT is extractable; qed");
+			let vsize = (transaction.weight() + 3) / 4;
+			analysis.estimated_vsize = Some(vsize);
+			analysis.estimated_fee_rate = Some(fee * 1000 / vsize as u64);
+		}
+		analysis
+	}
+}
+
+fn analyze_input(input: &Input) -> InputAnalysis {
+	let spent_output = input.spent_output();
+	let mut analysis = InputAnalysis {
+		has_utxo: spent_output.is_some(),
+		is_final: input.is_finalized(),
+		missing: None,
+		next: Role::Extractor,
+	};
+	let spent_output = match spent_output {
+		Some(spent_output) => spent_output,
+		None => {
+			analysis.next = Role::Updater;
+			return analysis;
+		}
+	};
+	if analysis.is_final {
+		return analysis;
+	}
+
+	match solve(input, &Script::new(spent_output.script_pubkey)) {
+		Ok(_) => analysis.next = Role::Finalizer,
+		Err(missing) => {
+			let only_signatures = missing.pubkeys.is_empty()
+				&& missing.redeem_script.is_none()
+				&& missing.witness_script.is_none()
+				&& !missing.signatures.is_empty();
+			analysis.next = if only_signatures { Role::Signer } else { Role::Updater };
+			analysis.missing = Some(missing);
+		}
+	}
+	analysis
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Role;
+	use crate::map::KeyOrigin;
+	use crate::psbt::Psbt;
+	use bitcrypto::dhash160;
+	use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+	use keys::{KeyPair, Network, Private};
+	use primitives::bytes::Bytes;
+	use script::Builder;
+
+	#[test]
+	fn test_psbt_analyze() {
+		let private = || Private {
+			network: Network::Regtest,
+			secret: [1; 32],
+			compressed: true,
+		};
+		let public = KeyPair::from_private(private()).unwrap().public().clone();
+		let spent_output = TransactionOutput {
+			value: 100_000,
+			script_pubkey: Builder::build_p2wpkh(&dhash160(&public)).to_bytes(),
+		};
+		let transaction = Transaction {
+			version: 2,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint::default(),
+				script_sig: Bytes::default(),
+				sequence: 0xffff_fffd,
+				script_witness: Vec::new(),
+			}],
+			outputs: vec![TransactionOutput {
+				value: 99_000,
+				script_pubkey: spent_output.script_pubkey.clone(),
+			}],
+			lock_time: 0,
+		};
+
+		let mut psbt = Psbt::from_unsigned_transaction(transaction, 2).unwrap();
+		let analysis = psbt.analyze();
+		assert_eq!(analysis.next, Role::Updater);
+		assert!(!analysis.inputs[0].has_utxo);
+		assert_eq!(analysis.fee, None);
+
+		psbt.inputs[0].witness_utxo = Some(spent_output);
+		let analysis = psbt.analyze();
+		assert_eq!(analysis.next, Role::Updater);
+		assert_eq!(analysis.fee, Some(1_000));
+		assert_eq!(analysis.estimated_vsize, None);
+		assert_eq!(analysis.inputs[0].missing.as_ref().unwrap().pubkeys, vec![dhash160(&public)]);
+
+		let origin = KeyOrigin {
+			fingerprint: [1, 2, 3, 4],
+			path: "m/0'".parse().unwrap(),
+		};
+		psbt.inputs[0].bip32_derivation.insert(public.to_vec().into(), origin);
+		let analysis = psbt.analyze();
+		assert_eq!(analysis.next, Role::Signer);
+		assert_eq!(analysis.inputs[0].missing.as_ref().unwrap().signatures, vec![dhash160(&public)]);
+
+		psbt.sign(&private()).unwrap();
+		let analysis = psbt.analyze();
+		assert_eq!(analysis.next, Role::Finalizer);
+		assert_eq!(analysis.inputs[0].missing, None);
+		// 1 p2wpkh input and 1 p2wpkh output, signature may be 1 byte shorter
+		let vsize = analysis.estimated_vsize.unwrap();
+		assert!(vsize == 110 || vsize == 109);
+		assert_eq!(analysis.estimated_fee_rate, Some(1_000_000 / vsize as u64));
+
+		psbt.finalize();
+		assert_eq!(psbt.analyze().next, Role::Extractor);
+
+		psbt.outputs[0].amount = 100_001;
+		let analysis = psbt.analyze();
+		assert_eq!(analysis.next, Role::Creator);
+		assert_eq!(analysis.error, Some("PSBT is not valid. Outputs spend more than inputs".into()));
+	}
+}
@@ -0,0 +1,68 @@
+use bitcrypto::ToHex;
+use primitives::bytes::Bytes;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// PSBT is not base64 encoded
+	InvalidBase64,
+	/// Serialized PSBT doesn't start with the magic bytes
+	InvalidMagic,
+	/// PSBT is truncated or has trailing data
+	Deserialize(ser::Error),
+	/// Key is present more than once in the same map
+	DuplicateKey(Bytes),
+	/// Key data is invalid for the key type
+	InvalidKey(Bytes),
+	/// Value is invalid for the key type
+	InvalidValue(Bytes),
+	/// Field, required by the PSBT version, is missing
+	MissingField(&'static str),
+	/// Field is not allowed in the PSBT version
+	UnexpectedField(&'static str),
+	/// PSBT version is not supported
+	UnsupportedVersion(u32),
+	/// Non-witness UTXO of the input is not the transaction, spent by the input
+	UtxoMismatch(usize),
+	/// PSBTs of different transactions can't be combined
+	DifferentTransactions,
+	/// Inputs require lock times of different types
+	LockTimeConflict,
+	/// Transaction can't be extracted until all inputs are finalized
+	NotFinalized,
+	/// Key error
+	Key(keys::Error),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::InvalidBase64 => "PSBT is not base64 encoded".fmt(f),
+			Error::InvalidMagic => "Invalid PSBT magic bytes".fmt(f),
+			Error::Deserialize(ref err) => err.fmt(f),
+			Error::DuplicateKey(ref key) => write!(f, "Duplicate key {}", key.to_hex()),
+			Error::InvalidKey(ref key) => write!(f, "Invalid key {}", key.to_hex()),
+			Error::InvalidValue(ref key) => write!(f, "Invalid value of the key {}", key.to_hex()),
+			Error::MissingField(field) => write!(f, "Missing {}", field),
+			Error::UnexpectedField(field) => write!(f, "Unexpected {}", field),
+			Error::UnsupportedVersion(version) => write!(f, "Unsupported PSBT version {}", version),
+			Error::UtxoMismatch(index) => write!(f, "Non-witness UTXO of input {} does not match outpoint hash", index),
+			Error::DifferentTransactions => "PSBTs not compatible (different transactions)".fmt(f),
+			Error::LockTimeConflict => "Inputs require conflicting lock times".fmt(f),
+			Error::NotFinalized => "PSBT is not finalized".fmt(f),
+			Error::Key(ref err) => err.fmt(f),
+		}
+	}
+}
+
+impl From<ser::Error> for Error {
+	fn from(err: ser::Error) -> Self {
+		Error::Deserialize(err)
+	}
+}
+
+impl From<keys::Error> for Error {
+	fn from(err: keys::Error) -> Self {
+		Error::Key(err)
+	}
+}
@@ -0,0 +1,422 @@
+//! Finalizer role: builds final scripts of the inputs from partial signatures.
+
+use crate::input::Input;
+use crate::psbt::Psbt;
+use bitcrypto::{dhash160, sha256, Hash, SHA256};
+use keys::AddressHash;
+use primitives::bytes::Bytes;
+use script::{
+	verify_script, Builder, Script, ScriptType, ScriptWitness, SignatureVersion, TransactionInputSigner, TransactionSignatureChecker,
+	VerificationFlags,
+};
+
+/// Data, which is missing to finalize the input
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Missing {
+	/// Hashes of public keys, which are not known
+	pub pubkeys: Vec<AddressHash>,
+	/// Hashes of public keys, which haven't signed the input yet
+	pub signatures: Vec<AddressHash>,
+	/// Hash of the unknown redeem script
+	pub redeem_script: Option<AddressHash>,
+	/// Hash of the unknown witness script
+	pub witness_script: Option<SHA256>,
+}
+
+/// Returns script_sig and witness of the input, spending output with the given script_pubkey
+pub(crate) fn solve(input: &Input, script_pubkey: &Script) -> Result<(Bytes, ScriptWitness), Missing> {
+	match script_pubkey.script_type() {
+		ScriptType::ScriptHash => {
+			let hash = AddressHash::from_slice(&script_pubkey[2..22]).expect("p2sh script has 20 bytes hash; qed");
+			let redeem_script = match input.redeem_script {
+				Some(ref redeem_script) if dhash160(redeem_script) == hash => redeem_script,
+				_ => {
+					return Err(Missing {
+						redeem_script: Some(hash),
+						..Default::default()
+					})
+				}
+			};
+
+			let script = Script::new(redeem_script.clone());
+			if script.parse_witness_program().is_some() {
+				let witness = solve_witness(input, &script)?;
+				return Ok((Builder::default().push_data(redeem_script).into_bytes(), witness));
+			}
+			let mut stack = solve_stack(input, &script)?;
+			stack.push(redeem_script.clone());
+			Ok((push_all(stack), Vec::new()))
+		}
+		_ if script_pubkey.parse_witness_program().is_some() => Ok((Bytes::default(), solve_witness(input, script_pubkey)?)),
+		_ => Ok((push_all(solve_stack(input, script_pubkey)?), Vec::new())),
+	}
+}
+
+fn solve_witness(input: &Input, script_pubkey: &Script) -> Result<ScriptWitness, Missing> {
+	match script_pubkey.script_type() {
+		ScriptType::WitnessKey => {
+			let hash = AddressHash::from_slice(&script_pubkey[2..]).expect("p2wpkh script has 20 bytes hash; qed");
+			solve_stack(input, &Builder::build_p2pkh(&hash))
+		}
+		ScriptType::WitnessScript => {
+			let hash = SHA256::from_slice(&script_pubkey[2..]).expect("p2wsh script has 32 bytes hash; qed");
+			let witness_script = match input.witness_script {
+				Some(ref witness_script) if sha256(witness_script) == hash => witness_script,
+				_ => {
+					return Err(Missing {
+						witness_script: Some(hash),
+						..Default::default()
+					})
+				}
+			};
+
+			let mut stack = solve_stack(input, &Script::new(witness_script.clone()))?;
+			stack.push(witness_script.clone());
+			Ok(stack)
+		}
+		ScriptType::WitnessTaproot => match input.tap_key_sig {
+			Some(ref signature) => Ok(vec![signature.clone()]),
+			None => {
+				let key = match input.tap_internal_key {
+					Some(ref key) => dhash160(key),
+					None => dhash160(&script_pubkey[2..]),
+				};
+				Err(Missing {
+					signatures: vec![key],
+					..Default::default()
+				})
+			}
+		},
+		_ => Err(Missing::default()),
+	}
+}
+
+/// Returns stack items, satisfying the script
+fn solve_stack(input: &Input, script: &Script) -> Result<Vec<Bytes>, Missing> {
+	match script.script_type() {
+		ScriptType::PubKey => {
+			let public = &script[1..script.len() - 1];
+			match input.partial_sigs.get(&Bytes::from(public)) {
+				Some(signature) => Ok(vec![signature.clone()]),
+				None => Err(Missing {
+					signatures: vec![dhash160(public)],
+					..Default::default()
+				}),
+			}
+		}
+		ScriptType::PubKeyHash => {
+			let hash = AddressHash::from_slice(&script[3..23]).expect("p2pkh script has 20 bytes hash; qed");
+			if let Some((public, signature)) = input.partial_sigs.iter().find(|(public, _)| dhash160(public) == hash) {
+				return Ok(vec![signature.clone(), public.clone()]);
+			}
+
+			let mut missing = Missing::default();
+			if input.bip32_derivation.keys().any(|public| dhash160(public) == hash) {
+				missing.signatures.push(hash);
+			} else {
+				missing.pubkeys.push(hash);
+			}
+			Err(missing)
+		}
+		ScriptType::Multisig => {
+			let required = script.num_signatures_required() as usize;
+			// empty item is consumed by the CHECKMULTISIG bug
+			let mut stack = vec![Bytes::default()];
+			let mut missing = Missing::default();
+			for public in multisig_keys(script) {
+				match input.partial_sigs.get(&Bytes::from(public)) {
+					Some(signature) if stack.len() <= required => stack.push(signature.clone()),
+					Some(_) => (),
+					None => missing.signatures.push(dhash160(public)),
+				}
+			}
+			if stack.len() <= required {
+				return Err(missing);
+			}
+			Ok(stack)
+		}
+		_ => Err(Missing::default()),
+	}
+}
+
+/// Returns public keys of the multisig script in their script order
+fn multisig_keys(script: &Script) -> Vec<&[u8]> {
+	script
+		.iter()
+		.filter_map(Result::ok)
+		.filter_map(|instruction| instruction.data)
+		.filter(|data| data.len() == 33 || data.len() == 65)
+		.collect()
+}
+
+fn push_all(stack: Vec<Bytes>) -> Bytes {
+	stack
+		.iter()
+		.fold(Builder::default(), |builder, item| builder.push_data(item))
+		.into_bytes()
+}
+
+impl Psbt {
+	/// Finalizes inputs, which have enough valid signatures. Returns true if all inputs are finalized
+	pub fn finalize(&mut self) -> bool {
+		let transaction = match self.unsigned_transaction() {
+			Ok(transaction) => transaction,
+			Err(_) => return false,
+		};
+
+		let mut signer = TransactionInputSigner::from(transaction);
+		signer.spent_outputs = self
+			.inputs
+			.iter()
+			.map(Input::spent_output)
+			.collect::<Option<_>>()
+			.unwrap_or_default();
+		let mut checker = TransactionSignatureChecker {
+			signer,
+			input_index: 0,
+			input_amount: 0,
+		};
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+			.verify_nulldummy(true)
+			.verify_cleanstack(true);
+
+		for (index, input) in self.inputs.iter_mut().enumerate() {
+			if input.is_finalized() {
+				continue;
+			}
+			let spent_output = match input.spent_output() {
+				Some(spent_output) => spent_output,
+				None => continue,
+			};
+			let script_pubkey = Script::new(spent_output.script_pubkey);
+			let (script_sig, witness) = match solve(input, &script_pubkey) {
+				Ok(solution) => solution,
+				Err(_) => continue,
+			};
+
+			checker.input_index = index;
+			checker.input_amount = spent_output.value;
+			let script_sig_script = Script::new(script_sig.clone());
+			if verify_script(
+				&script_sig_script,
+				&script_pubkey,
+				&witness,
+				&flags,
+				&checker,
+				SignatureVersion::Base,
+			)
+			.is_ok()
+			{
+				input.set_final(script_sig, witness);
+			}
+		}
+		self.is_finalized()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Missing;
+	use crate::psbt::Psbt;
+	use bitcrypto::{dhash160, sha256, tagged_hash};
+	use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+	use keys::{KeyPair, Network, Private, Public};
+	use primitives::bytes::Bytes;
+	use script::{
+		verify_script, Builder, Opcode, Script, SignatureVersion, TransactionInputSigner, TransactionSignatureChecker, VerificationFlags,
+	};
+
+	fn private(secret: u8) -> Private {
+		Private {
+			network: Network::Regtest,
+			secret: [secret; 32],
+			compressed: true,
+		}
+	}
+
+	fn public(secret: u8) -> Public {
+		KeyPair::from_private(private(secret)).unwrap().public().clone()
+	}
+
+	fn multisig_script() -> Bytes {
+		Builder::default()
+			.push_opcode(Opcode::OP_2)
+			.push_data(&public(4))
+			.push_data(&public(5))
+			.push_data(&public(6))
+			.push_opcode(Opcode::OP_3)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_bytes()
+	}
+
+	fn redeem_script() -> Bytes {
+		Builder::build_p2wpkh(&dhash160(&public(3))).to_bytes()
+	}
+
+	fn funding_transaction() -> Transaction {
+		let internal_key = private(7).xonly_public().unwrap();
+		let (output_key, _) = internal_key.tweak_add(&tagged_hash(b"TapTweak", &internal_key)).unwrap();
+		let script_pubkeys = vec![
+			Builder::build_p2pkh(&dhash160(&public(1))).to_bytes(),
+			Builder::build_p2wpkh(&dhash160(&public(2))).to_bytes(),
+			Builder::build_p2sh(&dhash160(&redeem_script())).to_bytes(),
+			Builder::build_p2wsh(&sha256(&multisig_script())).to_bytes(),
+			Builder::default().push_opcode(Opcode::OP_1).push_data(&output_key).into_bytes(),
+		];
+		Transaction {
+			version: 2,
+			inputs: vec![TransactionInput::coinbase(Bytes::default())],
+			outputs: script_pubkeys
+				.into_iter()
+				.map(|script_pubkey| TransactionOutput {
+					value: 100_000,
+					script_pubkey,
+				})
+				.collect(),
+			lock_time: 0,
+		}
+	}
+
+	fn updated_psbt(funding: &Transaction) -> Psbt {
+		let spending = Transaction {
+			version: 2,
+			inputs: (0..funding.outputs.len() as u32)
+				.map(|index| TransactionInput {
+					previous_output: OutPoint {
+						hash: funding.hash(),
+						index,
+					},
+					script_sig: Bytes::default(),
+					sequence: 0xffff_fffd,
+					script_witness: Vec::new(),
+				})
+				.collect(),
+			outputs: vec![TransactionOutput {
+				value: 450_000,
+				script_pubkey: Builder::build_p2wpkh(&dhash160(&public(1))).to_bytes(),
+			}],
+			lock_time: 0,
+		};
+
+		let mut psbt = Psbt::from_unsigned_transaction(spending, 0).unwrap();
+		psbt.update_utxos(
+			|outpoint| funding.outputs.get(outpoint.index as usize).cloned(),
+			|_| Some(funding.clone()),
+		);
+		psbt.update_scripts(&funding.outputs[2].script_pubkey, Some(&redeem_script()), None, None);
+		psbt.update_scripts(&funding.outputs[3].script_pubkey, None, Some(&multisig_script()), None);
+		psbt.update_scripts(
+			&funding.outputs[4].script_pubkey,
+			None,
+			None,
+			Some(private(7).xonly_public().unwrap()),
+		);
+		psbt
+	}
+
+	#[test]
+	fn test_psbt_sign_finalize_extract() {
+		let funding = funding_transaction();
+		let mut psbt = updated_psbt(&funding);
+		assert!(psbt.inputs[0].non_witness_utxo.is_some());
+		assert!(psbt.inputs[1].witness_utxo.is_some());
+		assert_eq!(psbt.fee(), Some(50_000));
+
+		let mut cosigned = psbt.clone();
+		assert_eq!(cosigned.sign(&private(6)), Ok(1));
+		for secret in 1..6 {
+			assert_eq!(psbt.sign(&private(secret)), Ok(1));
+		}
+		// the multisig input is not finalized with a single signature
+		assert!(!psbt.clone().finalize());
+		assert_eq!(psbt.sign(&private(7)), Ok(1));
+		assert_eq!(psbt.sign(&private(8)), Ok(0));
+
+		psbt.combine(cosigned).unwrap();
+		assert!(psbt.finalize());
+		assert!(psbt.inputs[3].partial_sigs.is_empty());
+		assert_eq!(Psbt::from_slice(&psbt.to_bytes()), Ok(psbt.clone()));
+
+		let transaction = psbt.extract().unwrap();
+		let mut signer = TransactionInputSigner::from(transaction.clone());
+		signer.spent_outputs = funding.outputs.clone();
+		let mut checker = TransactionSignatureChecker {
+			signer,
+			input_index: 0,
+			input_amount: 100_000,
+		};
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+			.verify_nulldummy(true)
+			.verify_cleanstack(true);
+		for (index, input) in transaction.inputs.iter().enumerate() {
+			checker.input_index = index;
+			let result = verify_script(
+				&input.script_sig.clone().into(),
+				&funding.outputs[index].script_pubkey.clone().into(),
+				&input.script_witness,
+				&flags,
+				&checker,
+				SignatureVersion::Base,
+			);
+			assert_eq!(result, Ok(()));
+		}
+	}
+
+	#[test]
+	fn test_psbt_finalize_invalid_signature() {
+		let funding = funding_transaction();
+		let mut psbt = updated_psbt(&funding);
+		psbt.inputs[1]
+			.partial_sigs
+			.insert(public(2).to_vec().into(), "300602010102010101".into());
+		assert!(!psbt.finalize());
+		assert!(!psbt.inputs[1].is_finalized());
+	}
+
+	#[test]
+	fn test_psbt_missing_data() {
+		let funding = funding_transaction();
+		let mut psbt = updated_psbt(&funding);
+		psbt.inputs[2].redeem_script = None;
+		psbt.inputs[3].witness_script = Some(redeem_script());
+		let script_pubkey = |index: usize| Script::new(funding.outputs[index].script_pubkey.clone());
+
+		assert_eq!(
+			super::solve(&psbt.inputs[0], &script_pubkey(0)),
+			Err(Missing {
+				pubkeys: vec![dhash160(&public(1))],
+				..Default::default()
+			})
+		);
+		assert_eq!(
+			super::solve(&psbt.inputs[2], &script_pubkey(2)),
+			Err(Missing {
+				redeem_script: Some(dhash160(&redeem_script())),
+				..Default::default()
+			})
+		);
+		assert_eq!(
+			super::solve(&psbt.inputs[3], &script_pubkey(3)),
+			Err(Missing {
+				witness_script: Some(sha256(&multisig_script())),
+				..Default::default()
+			})
+		);
+
+		psbt.inputs[3].witness_script = Some(multisig_script());
+		psbt.sign(&private(5)).unwrap();
+		assert_eq!(
+			super::solve(&psbt.inputs[3], &script_pubkey(3)),
+			Err(Missing {
+				signatures: vec![dhash160(&public(4)), dhash160(&public(6))],
+				..Default::default()
+			})
+		);
+	}
+}
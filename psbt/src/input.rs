@@ -0,0 +1,266 @@
+//! Per-input map of the PSBT.
+
+use crate::error::Error;
+use crate::map::{merge_maps, read_key_origin, write_key_origins, write_pair, write_unknown_and_separator, write_value, KeyOrigin, Pair};
+use bitcrypto::{Hash, SHA256, SHA256D};
+use chain::constants::LOCKTIME_THRESHOLD;
+use chain::{OutPoint, Transaction, TransactionOutput};
+use keys::{Public, XOnlyPublic};
+use primitives::bytes::Bytes;
+use script::ScriptWitness;
+use ser::{serialize_with_flags, Stream, SERIALIZE_TRANSACTION_WITNESS};
+use std::collections::BTreeMap;
+
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+const PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const PSBT_IN_PREVIOUS_TXID: u8 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u8 = 0x0f;
+const PSBT_IN_SEQUENCE: u8 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+const PSBT_IN_TAP_KEY_SIG: u8 = 0x13;
+const PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
+const PSBT_IN_TAP_MERKLE_ROOT: u8 = 0x18;
+
+/// Input of the PSBT. Fields, which aren't used by this implementation (hash preimages, taproot script
+/// path data, proprietary fields), are kept as unknown pairs.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Input {
+	/// Output, spent by the input
+	pub previous_output: OutPoint,
+	/// Sequence number of the input, final if not set
+	pub sequence: Option<u32>,
+	/// Minimal time-based lock time, required by the input (version 2 only)
+	pub required_time_locktime: Option<u32>,
+	/// Minimal height-based lock time, required by the input (version 2 only)
+	pub required_height_locktime: Option<u32>,
+	/// Transaction, spent by the input
+	pub non_witness_utxo: Option<Transaction>,
+	/// Output, spent by the segwit input
+	pub witness_utxo: Option<TransactionOutput>,
+	/// Signatures by public keys
+	pub partial_sigs: BTreeMap<Bytes, Bytes>,
+	pub sighash_type: Option<u32>,
+	pub redeem_script: Option<Bytes>,
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys, required to sign the input
+	pub bip32_derivation: BTreeMap<Bytes, KeyOrigin>,
+	pub final_script_sig: Option<Bytes>,
+	pub final_script_witness: Option<ScriptWitness>,
+	/// Taproot key path signature
+	pub tap_key_sig: Option<Bytes>,
+	pub tap_internal_key: Option<XOnlyPublic>,
+	/// Merkle root of the taproot script tree
+	pub tap_merkle_root: Option<SHA256>,
+	pub unknown: BTreeMap<Bytes, Bytes>,
+}
+
+impl Input {
+	pub(crate) fn from_pairs(pairs: Vec<Pair>, version: u32) -> Result<Self, Error> {
+		let mut input = Input::default();
+		let mut previous_hash = None;
+		let mut previous_index = None;
+		for pair in pairs {
+			match pair.key_type() {
+				Some(PSBT_IN_NON_WITNESS_UTXO) => input.non_witness_utxo = Some(pair.value()?),
+				Some(PSBT_IN_WITNESS_UTXO) => input.witness_utxo = Some(pair.value()?),
+				Some(PSBT_IN_PARTIAL_SIG) => {
+					if Public::from_slice(pair.key_data()).is_err() {
+						return Err(Error::InvalidKey(pair.key));
+					}
+					input.partial_sigs.insert(pair.key_data().into(), pair.value);
+				}
+				Some(PSBT_IN_SIGHASH_TYPE) => input.sighash_type = Some(pair.value()?),
+				Some(PSBT_IN_REDEEM_SCRIPT) => {
+					pair.expect_empty_key()?;
+					input.redeem_script = Some(pair.value);
+				}
+				Some(PSBT_IN_WITNESS_SCRIPT) => {
+					pair.expect_empty_key()?;
+					input.witness_script = Some(pair.value);
+				}
+				Some(PSBT_IN_BIP32_DERIVATION) => read_key_origin(&pair, &mut input.bip32_derivation)?,
+				Some(PSBT_IN_FINAL_SCRIPTSIG) => {
+					pair.expect_empty_key()?;
+					input.final_script_sig = Some(pair.value);
+				}
+				Some(PSBT_IN_FINAL_SCRIPTWITNESS) => {
+					pair.expect_empty_key()?;
+					let witness = ser::Reader::new(&pair.value)
+						.read_list()
+						.map_err(|_| Error::InvalidValue(pair.key.clone()))?;
+					input.final_script_witness = Some(witness);
+				}
+				Some(PSBT_IN_PREVIOUS_TXID) if version >= 2 => previous_hash = Some(pair.value::<SHA256D>()?),
+				Some(PSBT_IN_OUTPUT_INDEX) if version >= 2 => previous_index = Some(pair.value()?),
+				Some(PSBT_IN_SEQUENCE) if version >= 2 => input.sequence = Some(pair.value()?),
+				Some(PSBT_IN_REQUIRED_TIME_LOCKTIME) if version >= 2 => {
+					let lock_time = pair.value()?;
+					if lock_time < LOCKTIME_THRESHOLD {
+						return Err(Error::InvalidValue(pair.key));
+					}
+					input.required_time_locktime = Some(lock_time);
+				}
+				Some(PSBT_IN_REQUIRED_HEIGHT_LOCKTIME) if version >= 2 => {
+					let lock_time = pair.value()?;
+					if lock_time == 0 || lock_time >= LOCKTIME_THRESHOLD {
+						return Err(Error::InvalidValue(pair.key));
+					}
+					input.required_height_locktime = Some(lock_time);
+				}
+				Some(PSBT_IN_PREVIOUS_TXID) => return Err(Error::UnexpectedField("previous txid")),
+				Some(PSBT_IN_OUTPUT_INDEX) => return Err(Error::UnexpectedField("previous output index")),
+				Some(PSBT_IN_SEQUENCE) => return Err(Error::UnexpectedField("input sequence")),
+				Some(PSBT_IN_REQUIRED_TIME_LOCKTIME) | Some(PSBT_IN_REQUIRED_HEIGHT_LOCKTIME) => {
+					return Err(Error::UnexpectedField("required lock time"))
+				}
+				Some(PSBT_IN_TAP_KEY_SIG) => {
+					pair.expect_empty_key()?;
+					if pair.value.len() != 64 && pair.value.len() != 65 {
+						return Err(Error::InvalidValue(pair.key));
+					}
+					input.tap_key_sig = Some(pair.value);
+				}
+				Some(PSBT_IN_TAP_INTERNAL_KEY) => {
+					pair.expect_empty_key()?;
+					let key = XOnlyPublic::from_slice(pair.value_with_len(32)?).map_err(|_| Error::InvalidValue(pair.key.clone()))?;
+					input.tap_internal_key = Some(key);
+				}
+				Some(PSBT_IN_TAP_MERKLE_ROOT) => {
+					pair.expect_empty_key()?;
+					let merkle_root = SHA256::from_slice(pair.value_with_len(32)?).map_err(|_| Error::InvalidValue(pair.key.clone()))?;
+					input.tap_merkle_root = Some(merkle_root);
+				}
+				_ => {
+					input.unknown.insert(pair.key, pair.value);
+				}
+			}
+		}
+
+		if version >= 2 {
+			input.previous_output = OutPoint {
+				hash: previous_hash.ok_or(Error::MissingField("previous txid"))?,
+				index: previous_index.ok_or(Error::MissingField("previous output index"))?,
+			};
+		}
+		Ok(input)
+	}
+
+	pub(crate) fn write(&self, stream: &mut Stream, version: u32) {
+		if let Some(ref transaction) = self.non_witness_utxo {
+			let transaction = serialize_with_flags(transaction, SERIALIZE_TRANSACTION_WITNESS);
+			write_pair(stream, PSBT_IN_NON_WITNESS_UTXO, &[], &transaction);
+		}
+		if let Some(ref output) = self.witness_utxo {
+			write_value(stream, PSBT_IN_WITNESS_UTXO, output);
+		}
+		for (public, signature) in &self.partial_sigs {
+			write_pair(stream, PSBT_IN_PARTIAL_SIG, public, signature);
+		}
+		if let Some(sighash_type) = self.sighash_type {
+			write_value(stream, PSBT_IN_SIGHASH_TYPE, &sighash_type);
+		}
+		if let Some(ref script) = self.redeem_script {
+			write_pair(stream, PSBT_IN_REDEEM_SCRIPT, &[], script);
+		}
+		if let Some(ref script) = self.witness_script {
+			write_pair(stream, PSBT_IN_WITNESS_SCRIPT, &[], script);
+		}
+		write_key_origins(stream, PSBT_IN_BIP32_DERIVATION, &self.bip32_derivation);
+		if let Some(ref script) = self.final_script_sig {
+			write_pair(stream, PSBT_IN_FINAL_SCRIPTSIG, &[], script);
+		}
+		if let Some(ref witness) = self.final_script_witness {
+			let mut witness_stream = Stream::new();
+			witness_stream.append_list(witness);
+			write_pair(stream, PSBT_IN_FINAL_SCRIPTWITNESS, &[], &witness_stream.out());
+		}
+		if version >= 2 {
+			write_value(stream, PSBT_IN_PREVIOUS_TXID, &self.previous_output.hash);
+			write_value(stream, PSBT_IN_OUTPUT_INDEX, &self.previous_output.index);
+			if let Some(sequence) = self.sequence {
+				write_value(stream, PSBT_IN_SEQUENCE, &sequence);
+			}
+			if let Some(lock_time) = self.required_time_locktime {
+				write_value(stream, PSBT_IN_REQUIRED_TIME_LOCKTIME, &lock_time);
+			}
+			if let Some(lock_time) = self.required_height_locktime {
+				write_value(stream, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, &lock_time);
+			}
+		}
+		if let Some(ref signature) = self.tap_key_sig {
+			write_pair(stream, PSBT_IN_TAP_KEY_SIG, &[], signature);
+		}
+		if let Some(ref key) = self.tap_internal_key {
+			write_pair(stream, PSBT_IN_TAP_INTERNAL_KEY, &[], key);
+		}
+		if let Some(ref merkle_root) = self.tap_merkle_root {
+			write_pair(stream, PSBT_IN_TAP_MERKLE_ROOT, &[], &merkle_root[..]);
+		}
+		write_unknown_and_separator(stream, &self.unknown);
+	}
+
+	/// Returns output, spent by the input, if it's known
+	pub fn spent_output(&self) -> Option<TransactionOutput> {
+		match (&self.witness_utxo, &self.non_witness_utxo) {
+			(Some(output), _) => Some(output.clone()),
+			(None, Some(transaction)) => transaction.outputs.get(self.previous_output.index as usize).cloned(),
+			(None, None) => None,
+		}
+	}
+
+	pub fn is_finalized(&self) -> bool {
+		self.final_script_sig.is_some() || self.final_script_witness.is_some()
+	}
+
+	/// Adds fields of other input, which are missing in this input
+	pub(crate) fn merge(&mut self, other: Input) {
+		macro_rules! merge_option {
+			($($field: ident),*) => {
+				$(if self.$field.is_none() {
+					self.$field = other.$field;
+				})*
+			};
+		}
+
+		merge_option!(
+			sequence,
+			required_time_locktime,
+			required_height_locktime,
+			non_witness_utxo,
+			witness_utxo,
+			sighash_type,
+			redeem_script,
+			witness_script,
+			final_script_sig,
+			final_script_witness,
+			tap_key_sig,
+			tap_internal_key,
+			tap_merkle_root
+		);
+		merge_maps(&mut self.partial_sigs, other.partial_sigs);
+		merge_maps(&mut self.bip32_derivation, other.bip32_derivation);
+		merge_maps(&mut self.unknown, other.unknown);
+	}
+
+	/// Sets final scripts and removes data, which is not needed anymore
+	pub(crate) fn set_final(&mut self, script_sig: Bytes, witness: ScriptWitness) {
+		self.final_script_sig = if script_sig.is_empty() { None } else { Some(script_sig) };
+		self.final_script_witness = if witness.is_empty() { None } else { Some(witness) };
+		self.partial_sigs.clear();
+		self.sighash_type = None;
+		self.redeem_script = None;
+		self.witness_script = None;
+		self.bip32_derivation.clear();
+		self.tap_key_sig = None;
+		self.tap_internal_key = None;
+		self.tap_merkle_root = None;
+	}
+}
@@ -0,0 +1,30 @@
+//! Partially signed bitcoin transactions (BIP174 and BIP370).
+//!
+//! PSBT of both versions is kept in the same structure: fields of the version 0 unsigned transaction
+//! are stored in the global, input and output maps, the same way version 2 stores them.
+
+extern crate base64;
+extern crate bitcrypto;
+extern crate chain;
+extern crate keys;
+extern crate primitives;
+extern crate script;
+extern crate serialization as ser;
+
+mod analyzer;
+mod error;
+mod finalizer;
+mod input;
+mod map;
+mod output;
+mod psbt;
+mod signer;
+mod updater;
+
+pub use crate::analyzer::{Analysis, InputAnalysis, Role};
+pub use crate::error::Error;
+pub use crate::finalizer::Missing;
+pub use crate::input::Input;
+pub use crate::map::KeyOrigin;
+pub use crate::output::Output;
+pub use crate::psbt::Psbt;
@@ -0,0 +1,167 @@
+//! Key-value maps of the serialized PSBT.
+
+use crate::error::Error;
+use keys::bip32::{ChildNumber, DerivationPath, Fingerprint};
+use keys::{DisplayLayout, ExtendedPublic, Public};
+use primitives::bytes::Bytes;
+use primitives::checksum::Checksum;
+use ser::{deserialize, serialize, CompactInteger, Deserializable, Reader, Serializable, Stream};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryInto;
+use std::io;
+
+/// Key-value pair of the map
+pub(crate) struct Pair {
+	/// Key type followed by the key data
+	pub key: Bytes,
+	pub value: Bytes,
+}
+
+impl Pair {
+	/// Returns type of the key. Types, which don't fit into a single byte, are all unknown
+	pub fn key_type(&self) -> Option<u8> {
+		match self.key[0] {
+			key_type if key_type < 0xfd => Some(key_type),
+			_ => None,
+		}
+	}
+
+	pub fn key_data(&self) -> &[u8] {
+		&self.key[1..]
+	}
+
+	/// Fails if the key has any data besides the key type
+	pub fn expect_empty_key(&self) -> Result<(), Error> {
+		match self.key.len() {
+			1 => Ok(()),
+			_ => Err(Error::InvalidKey(self.key.clone())),
+		}
+	}
+
+	/// Deserializes value of the key without key data
+	pub fn value<T: Deserializable>(&self) -> Result<T, Error> {
+		self.expect_empty_key()?;
+		self.deserialize_value()
+	}
+
+	pub fn deserialize_value<T: Deserializable>(&self) -> Result<T, Error> {
+		deserialize(&self.value[..]).map_err(|_| Error::InvalidValue(self.key.clone()))
+	}
+
+	/// Returns value with the expected length
+	pub fn value_with_len(&self, len: usize) -> Result<&[u8], Error> {
+		if self.value.len() != len {
+			return Err(Error::InvalidValue(self.key.clone()));
+		}
+		Ok(&self.value)
+	}
+}
+
+/// Source of the key, derived from the master key
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyOrigin {
+	/// Fingerprint of the master key
+	pub fingerprint: Fingerprint,
+	/// Derivation path from the master key
+	pub path: DerivationPath,
+}
+
+impl KeyOrigin {
+	fn from_value(pair: &Pair) -> Result<Self, Error> {
+		let value = &pair.value;
+		if value.len() < 4 || value.len() % 4 != 0 {
+			return Err(Error::InvalidValue(pair.key.clone()));
+		}
+
+		let child = |chunk: &[u8]| ChildNumber::from(u32::from_le_bytes(chunk.try_into().expect("chunk is 4 bytes long; qed")));
+		Ok(KeyOrigin {
+			fingerprint: value[..4].try_into().expect("slice is 4 bytes long; qed"),
+			path: value[4..].chunks(4).map(child).collect::<Vec<_>>().into(),
+		})
+	}
+
+	fn to_value(&self) -> Vec<u8> {
+		let mut value = self.fingerprint.to_vec();
+		for child in self.path.as_slice() {
+			value.extend_from_slice(&u32::from(*child).to_le_bytes());
+		}
+		value
+	}
+}
+
+/// Reads key-value pairs up to the map separator
+pub(crate) fn read_map<T: io::Read>(reader: &mut Reader<T>) -> Result<Vec<Pair>, Error> {
+	let mut keys = BTreeSet::new();
+	let mut pairs = Vec::new();
+	loop {
+		let key: Bytes = reader.read()?;
+		if key.is_empty() {
+			return Ok(pairs);
+		}
+
+		let value: Bytes = reader.read()?;
+		if !keys.insert(key.clone()) {
+			return Err(Error::DuplicateKey(key));
+		}
+		pairs.push(Pair { key, value });
+	}
+}
+
+/// Reads key origins of public keys. Public key must be the key data
+pub(crate) fn read_key_origin(pair: &Pair, origins: &mut BTreeMap<Bytes, KeyOrigin>) -> Result<(), Error> {
+	let public = pair.key_data();
+	if Public::from_slice(public).is_err() {
+		return Err(Error::InvalidKey(pair.key.clone()));
+	}
+	origins.insert(public.into(), KeyOrigin::from_value(pair)?);
+	Ok(())
+}
+
+pub(crate) fn write_pair(stream: &mut Stream, key_type: u8, key_data: &[u8], value: &[u8]) {
+	stream
+		.append(&CompactInteger::from(key_data.len() + 1))
+		.append(&key_type)
+		.append_slice(key_data)
+		.append(&CompactInteger::from(value.len()))
+		.append_slice(value);
+}
+
+/// Writes pair with the serialized value and without key data
+pub(crate) fn write_value<T: Serializable>(stream: &mut Stream, key_type: u8, value: &T) {
+	write_pair(stream, key_type, &[], &serialize(value));
+}
+
+pub(crate) fn write_key_origins(stream: &mut Stream, key_type: u8, origins: &BTreeMap<Bytes, KeyOrigin>) {
+	for (public, origin) in origins {
+		write_pair(stream, key_type, public, &origin.to_value());
+	}
+}
+
+/// Writes unknown pairs and the map separator
+pub(crate) fn write_unknown_and_separator(stream: &mut Stream, unknown: &BTreeMap<Bytes, Bytes>) {
+	for (key, value) in unknown {
+		stream.append(key).append(value);
+	}
+	stream.append(&CompactInteger::from(0u8));
+}
+
+/// Adds pairs of other map, which are missing in this map
+pub(crate) fn merge_maps<V>(map: &mut BTreeMap<Bytes, V>, other: BTreeMap<Bytes, V>) {
+	for (key, value) in other {
+		map.entry(key).or_insert(value);
+	}
+}
+
+pub(crate) fn read_xpub_origin(pair: &Pair) -> Result<(ExtendedPublic, KeyOrigin), Error> {
+	// serialized key is stored without checksum
+	let mut layout = pair.key_data().to_vec();
+	let cs = Checksum::generate(&layout);
+	layout.extend_from_slice(&*cs);
+	let xpub = ExtendedPublic::from_layout(&layout).map_err(|_| Error::InvalidKey(pair.key.clone()))?;
+	Ok((xpub, KeyOrigin::from_value(pair)?))
+}
+
+pub(crate) fn write_xpub_origin(stream: &mut Stream, key_type: u8, xpub: &ExtendedPublic, origin: &KeyOrigin) {
+	let layout = xpub.layout();
+	write_pair(stream, key_type, &layout[..layout.len() - 4], &origin.to_value());
+}
@@ -0,0 +1,103 @@
+//! Per-output map of the PSBT.
+
+use crate::error::Error;
+use crate::map::{merge_maps, read_key_origin, write_key_origins, write_pair, write_unknown_and_separator, write_value, KeyOrigin, Pair};
+use keys::XOnlyPublic;
+use primitives::bytes::Bytes;
+use ser::Stream;
+use std::collections::BTreeMap;
+
+const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+const PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+const PSBT_OUT_AMOUNT: u8 = 0x03;
+const PSBT_OUT_SCRIPT: u8 = 0x04;
+const PSBT_OUT_TAP_INTERNAL_KEY: u8 = 0x05;
+
+/// Output of the PSBT. Taproot script tree and proprietary fields are kept as unknown pairs.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Output {
+	pub amount: u64,
+	pub script_pubkey: Bytes,
+	pub redeem_script: Option<Bytes>,
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys of the output script
+	pub bip32_derivation: BTreeMap<Bytes, KeyOrigin>,
+	pub tap_internal_key: Option<XOnlyPublic>,
+	pub unknown: BTreeMap<Bytes, Bytes>,
+}
+
+impl Output {
+	pub(crate) fn from_pairs(pairs: Vec<Pair>, version: u32) -> Result<Self, Error> {
+		let mut output = Output::default();
+		let mut amount = None;
+		let mut script_pubkey = None;
+		for pair in pairs {
+			match pair.key_type() {
+				Some(PSBT_OUT_REDEEM_SCRIPT) => {
+					pair.expect_empty_key()?;
+					output.redeem_script = Some(pair.value);
+				}
+				Some(PSBT_OUT_WITNESS_SCRIPT) => {
+					pair.expect_empty_key()?;
+					output.witness_script = Some(pair.value);
+				}
+				Some(PSBT_OUT_BIP32_DERIVATION) => read_key_origin(&pair, &mut output.bip32_derivation)?,
+				Some(PSBT_OUT_AMOUNT) if version >= 2 => amount = Some(pair.value()?),
+				Some(PSBT_OUT_SCRIPT) if version >= 2 => {
+					pair.expect_empty_key()?;
+					script_pubkey = Some(pair.value);
+				}
+				Some(PSBT_OUT_AMOUNT) => return Err(Error::UnexpectedField("output amount")),
+				Some(PSBT_OUT_SCRIPT) => return Err(Error::UnexpectedField("output script")),
+				Some(PSBT_OUT_TAP_INTERNAL_KEY) => {
+					pair.expect_empty_key()?;
+					let key = XOnlyPublic::from_slice(pair.value_with_len(32)?).map_err(|_| Error::InvalidValue(pair.key.clone()))?;
+					output.tap_internal_key = Some(key);
+				}
+				_ => {
+					output.unknown.insert(pair.key, pair.value);
+				}
+			}
+		}
+
+		if version >= 2 {
+			output.amount = amount.ok_or(Error::MissingField("output amount"))?;
+			output.script_pubkey = script_pubkey.ok_or(Error::MissingField("output script"))?;
+		}
+		Ok(output)
+	}
+
+	pub(crate) fn write(&self, stream: &mut Stream, version: u32) {
+		if let Some(ref script) = self.redeem_script {
+			write_pair(stream, PSBT_OUT_REDEEM_SCRIPT, &[], script);
+		}
+		if let Some(ref script) = self.witness_script {
+			write_pair(stream, PSBT_OUT_WITNESS_SCRIPT, &[], script);
+		}
+		write_key_origins(stream, PSBT_OUT_BIP32_DERIVATION, &self.bip32_derivation);
+		if version >= 2 {
+			write_value(stream, PSBT_OUT_AMOUNT, &self.amount);
+			write_pair(stream, PSBT_OUT_SCRIPT, &[], &self.script_pubkey);
+		}
+		if let Some(ref key) = self.tap_internal_key {
+			write_pair(stream, PSBT_OUT_TAP_INTERNAL_KEY, &[], key);
+		}
+		write_unknown_and_separator(stream, &self.unknown);
+	}
+
+	/// Adds fields of other output, which are missing in this output
+	pub(crate) fn merge(&mut self, other: Output) {
+		if self.redeem_script.is_none() {
+			self.redeem_script = other.redeem_script;
+		}
+		if self.witness_script.is_none() {
+			self.witness_script = other.witness_script;
+		}
+		if self.tap_internal_key.is_none() {
+			self.tap_internal_key = other.tap_internal_key;
+		}
+		merge_maps(&mut self.bip32_derivation, other.bip32_derivation);
+		merge_maps(&mut self.unknown, other.unknown);
+	}
+}
@@ -0,0 +1,533 @@
+//! PSBT global map, serialization, combining and extraction.
+
+use crate::error::Error;
+use crate::input::Input;
+use crate::map::{
+	merge_maps, read_map, read_xpub_origin, write_pair, write_unknown_and_separator, write_value, write_xpub_origin, KeyOrigin,
+};
+use crate::output::Output;
+use bitcrypto::SHA256D;
+use chain::constants::SEQUENCE_FINAL;
+use chain::{Transaction, TransactionInput, TransactionOutput};
+use keys::ExtendedPublic;
+use primitives::bytes::Bytes;
+use ser::{serialize, CompactInteger, Reader, Stream};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Magic bytes of the serialized PSBT
+const MAGIC: &[u8] = b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_XPUB: u8 = 0x01;
+const PSBT_GLOBAL_TX_VERSION: u8 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+const PSBT_GLOBAL_VERSION: u8 = 0xfb;
+
+/// Partially signed bitcoin transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Psbt {
+	/// Version of the PSBT, 0 (BIP174) or 2 (BIP370)
+	pub version: u32,
+	/// Version of the transaction
+	pub tx_version: i32,
+	/// Lock time of the transaction, used if no input requires lock time. Version 0 PSBT always has it
+	pub fallback_locktime: Option<u32>,
+	/// Flags, which allow adding inputs and outputs (version 2 only)
+	pub tx_modifiable: Option<u8>,
+	/// Extended public keys with their origins
+	pub xpubs: Vec<(ExtendedPublic, KeyOrigin)>,
+	pub unknown: BTreeMap<Bytes, Bytes>,
+	pub inputs: Vec<Input>,
+	pub outputs: Vec<Output>,
+}
+
+impl Psbt {
+	/// Creates PSBT of the unsigned transaction. Scripts and witnesses of the transaction inputs are discarded
+	pub fn from_unsigned_transaction(transaction: Transaction, version: u32) -> Result<Self, Error> {
+		if version != 0 && version != 2 {
+			return Err(Error::UnsupportedVersion(version));
+		}
+
+		Ok(Psbt {
+			version,
+			tx_version: transaction.version,
+			fallback_locktime: Some(transaction.lock_time),
+			tx_modifiable: None,
+			xpubs: Vec::new(),
+			unknown: BTreeMap::new(),
+			inputs: transaction
+				.inputs
+				.into_iter()
+				.map(|input| Input {
+					previous_output: input.previous_output,
+					sequence: Some(input.sequence),
+					..Default::default()
+				})
+				.collect(),
+			outputs: transaction
+				.outputs
+				.into_iter()
+				.map(|output| Output {
+					amount: output.value,
+					script_pubkey: output.script_pubkey,
+					..Default::default()
+				})
+				.collect(),
+		})
+	}
+
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if !data.starts_with(MAGIC) {
+			return Err(Error::InvalidMagic);
+		}
+
+		let mut reader = Reader::new(&data[MAGIC.len()..]);
+		let mut version = 0;
+		let mut unsigned_transaction: Option<Transaction> = None;
+		let mut tx_version = None;
+		let mut fallback_locktime = None;
+		let mut input_count: Option<CompactInteger> = None;
+		let mut output_count: Option<CompactInteger> = None;
+		let mut tx_modifiable = None;
+		let mut xpubs = Vec::new();
+		let mut unknown = BTreeMap::new();
+		for pair in read_map(&mut reader)? {
+			match pair.key_type() {
+				Some(PSBT_GLOBAL_UNSIGNED_TX) => unsigned_transaction = Some(pair.value()?),
+				Some(PSBT_GLOBAL_XPUB) => xpubs.push(read_xpub_origin(&pair)?),
+				Some(PSBT_GLOBAL_TX_VERSION) => tx_version = Some(pair.value()?),
+				Some(PSBT_GLOBAL_FALLBACK_LOCKTIME) => fallback_locktime = Some(pair.value()?),
+				Some(PSBT_GLOBAL_INPUT_COUNT) => input_count = Some(pair.value()?),
+				Some(PSBT_GLOBAL_OUTPUT_COUNT) => output_count = Some(pair.value()?),
+				Some(PSBT_GLOBAL_TX_MODIFIABLE) => tx_modifiable = Some(pair.value()?),
+				Some(PSBT_GLOBAL_VERSION) => version = pair.value()?,
+				_ => {
+					unknown.insert(pair.key, pair.value);
+				}
+			}
+		}
+
+		let mut psbt = match version {
+			0 => {
+				let transaction = unsigned_transaction.ok_or(Error::MissingField("unsigned transaction"))?;
+				if tx_version.is_some()
+					|| fallback_locktime.is_some()
+					|| input_count.is_some()
+					|| output_count.is_some()
+					|| tx_modifiable.is_some()
+				{
+					return Err(Error::UnexpectedField("version 2 transaction field"));
+				}
+				if transaction
+					.inputs
+					.iter()
+					.any(|input| !input.script_sig.is_empty() || input.has_witness())
+				{
+					return Err(Error::UnexpectedField("signed unsigned transaction input"));
+				}
+				Psbt::from_unsigned_transaction(transaction, version)?
+			}
+			2 => {
+				if unsigned_transaction.is_some() {
+					return Err(Error::UnexpectedField("unsigned transaction"));
+				}
+				Psbt {
+					version,
+					tx_version: tx_version.ok_or(Error::MissingField("transaction version"))?,
+					fallback_locktime,
+					tx_modifiable,
+					xpubs: Vec::new(),
+					unknown: BTreeMap::new(),
+					inputs: Vec::new(),
+					outputs: Vec::new(),
+				}
+			}
+			version => return Err(Error::UnsupportedVersion(version)),
+		};
+		psbt.xpubs = xpubs;
+		psbt.unknown = unknown;
+
+		let input_count = input_count.map(usize::from).unwrap_or_else(|| psbt.inputs.len());
+		let output_count = output_count.map(usize::from).unwrap_or_else(|| psbt.outputs.len());
+		let mut inputs = Vec::new();
+		for index in 0..input_count {
+			let mut input = Input::from_pairs(read_map(&mut reader)?, version)?;
+			if version == 0 {
+				input.previous_output = psbt.inputs[index].previous_output.clone();
+				input.sequence = psbt.inputs[index].sequence;
+			}
+			if let Some(ref transaction) = input.non_witness_utxo {
+				if transaction.hash() != input.previous_output.hash {
+					return Err(Error::UtxoMismatch(index));
+				}
+			}
+			inputs.push(input);
+		}
+		let mut outputs = Vec::new();
+		for index in 0..output_count {
+			let mut output = Output::from_pairs(read_map(&mut reader)?, version)?;
+			if version == 0 {
+				output.amount = psbt.outputs[index].amount;
+				output.script_pubkey = psbt.outputs[index].script_pubkey.clone();
+			}
+			outputs.push(output);
+		}
+		if !reader.is_finished() {
+			return Err(Error::Deserialize(ser::Error::UnreadData));
+		}
+
+		psbt.inputs = inputs;
+		psbt.outputs = outputs;
+		Ok(psbt)
+	}
+
+	pub fn to_bytes(&self) -> Bytes {
+		let mut stream = Stream::new();
+		stream.append_slice(MAGIC);
+		if self.version == 0 {
+			let transaction = self.transaction(self.fallback_locktime.unwrap_or_default(), false);
+			write_value(&mut stream, PSBT_GLOBAL_UNSIGNED_TX, &transaction);
+		}
+		for (xpub, origin) in &self.xpubs {
+			write_xpub_origin(&mut stream, PSBT_GLOBAL_XPUB, xpub, origin);
+		}
+		if self.version >= 2 {
+			write_value(&mut stream, PSBT_GLOBAL_TX_VERSION, &self.tx_version);
+			if let Some(lock_time) = self.fallback_locktime {
+				write_value(&mut stream, PSBT_GLOBAL_FALLBACK_LOCKTIME, &lock_time);
+			}
+			write_value(&mut stream, PSBT_GLOBAL_INPUT_COUNT, &CompactInteger::from(self.inputs.len()));
+			write_value(&mut stream, PSBT_GLOBAL_OUTPUT_COUNT, &CompactInteger::from(self.outputs.len()));
+			if let Some(tx_modifiable) = self.tx_modifiable {
+				write_value(&mut stream, PSBT_GLOBAL_TX_MODIFIABLE, &tx_modifiable);
+			}
+		}
+		if self.version > 0 {
+			write_pair(&mut stream, PSBT_GLOBAL_VERSION, &[], &serialize(&self.version));
+		}
+		write_unknown_and_separator(&mut stream, &self.unknown);
+
+		for input in &self.inputs {
+			input.write(&mut stream, self.version);
+		}
+		for output in &self.outputs {
+			output.write(&mut stream, self.version);
+		}
+		stream.out()
+	}
+
+	/// Returns lock time of the transaction. Height-based lock time is preferred if inputs allow both types
+	pub fn lock_time(&self) -> Result<u32, Error> {
+		let requiring: Vec<_> = self
+			.inputs
+			.iter()
+			.filter(|input| input.required_time_locktime.is_some() || input.required_height_locktime.is_some())
+			.collect();
+		if requiring.is_empty() {
+			return Ok(self.fallback_locktime.unwrap_or_default());
+		}
+
+		if requiring.iter().all(|input| input.required_height_locktime.is_some()) {
+			Ok(requiring
+				.iter()
+				.filter_map(|input| input.required_height_locktime)
+				.max()
+				.unwrap_or_default())
+		} else if requiring.iter().all(|input| input.required_time_locktime.is_some()) {
+			Ok(requiring
+				.iter()
+				.filter_map(|input| input.required_time_locktime)
+				.max()
+				.unwrap_or_default())
+		} else {
+			Err(Error::LockTimeConflict)
+		}
+	}
+
+	/// Returns transaction without scripts and witnesses
+	pub fn unsigned_transaction(&self) -> Result<Transaction, Error> {
+		Ok(self.transaction(self.lock_time()?, false))
+	}
+
+	/// Returns hash, identifying the transaction of the PSBT. Sequences of version 2 PSBT inputs are
+	/// not committed to, since they may be changed by updaters
+	pub fn unique_id(&self) -> Result<SHA256D, Error> {
+		Ok(self.transaction(self.lock_time()?, self.version >= 2).hash())
+	}
+
+	fn transaction(&self, lock_time: u32, zero_sequences: bool) -> Transaction {
+		Transaction {
+			version: self.tx_version,
+			inputs: self
+				.inputs
+				.iter()
+				.map(|input| TransactionInput {
+					previous_output: input.previous_output.clone(),
+					script_sig: Bytes::default(),
+					sequence: if zero_sequences {
+						0
+					} else {
+						input.sequence.unwrap_or(SEQUENCE_FINAL)
+					},
+					script_witness: Vec::new(),
+				})
+				.collect(),
+			outputs: self
+				.outputs
+				.iter()
+				.map(|output| TransactionOutput {
+					value: output.amount,
+					script_pubkey: output.script_pubkey.clone(),
+				})
+				.collect(),
+			lock_time,
+		}
+	}
+
+	/// Returns fee of the transaction if all spent outputs are known
+	pub fn fee(&self) -> Option<u64> {
+		let mut input_amount = 0u64;
+		for input in &self.inputs {
+			input_amount = input_amount.checked_add(input.spent_output()?.value)?;
+		}
+		let output_amount = self.outputs.iter().try_fold(0u64, |sum, output| sum.checked_add(output.amount))?;
+		input_amount.checked_sub(output_amount)
+	}
+
+	/// Returns true if all inputs are finalized and the transaction can be extracted
+	pub fn is_finalized(&self) -> bool {
+		self.inputs.iter().all(Input::is_finalized)
+	}
+
+	/// Merges other PSBT of the same transaction into this PSBT
+	pub fn combine(&mut self, other: Psbt) -> Result<(), Error> {
+		if self.version != other.version
+			|| self.inputs.len() != other.inputs.len()
+			|| self.outputs.len() != other.outputs.len()
+			|| self.unique_id()? != other.unique_id()?
+		{
+			return Err(Error::DifferentTransactions);
+		}
+
+		for (xpub, origin) in other.xpubs {
+			if !self.xpubs.iter().any(|(known, _)| *known == xpub) {
+				self.xpubs.push((xpub, origin));
+			}
+		}
+		merge_maps(&mut self.unknown, other.unknown);
+		for (input, other) in self.inputs.iter_mut().zip(other.inputs) {
+			input.merge(other);
+		}
+		for (output, other) in self.outputs.iter_mut().zip(other.outputs) {
+			output.merge(other);
+		}
+		Ok(())
+	}
+
+	/// Returns signed transaction of the finalized PSBT
+	pub fn extract(&self) -> Result<Transaction, Error> {
+		if !self.is_finalized() {
+			return Err(Error::NotFinalized);
+		}
+
+		let mut transaction = self.unsigned_transaction()?;
+		for (transaction_input, input) in transaction.inputs.iter_mut().zip(&self.inputs) {
+			transaction_input.script_sig = input.final_script_sig.clone().unwrap_or_default();
+			transaction_input.script_witness = input.final_script_witness.clone().unwrap_or_default();
+		}
+		Ok(transaction)
+	}
+}
+
+impl fmt::Display for Psbt {
+	/// Formats PSBT as base64 string
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		base64::encode(&*self.to_bytes()).fmt(f)
+	}
+}
+
+impl FromStr for Psbt {
+	type Err = Error;
+
+	/// Parses base64 encoded PSBT
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let data = base64::decode(s.trim()).map_err(|_| Error::InvalidBase64)?;
+		Psbt::from_slice(&data)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Psbt;
+	use crate::error::Error;
+	use crate::map::KeyOrigin;
+	use bitcrypto::{Hash, SHA256D};
+	use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+	use keys::{ExtendedPrivate, Network, XOnlyPublic};
+	use primitives::bytes::Bytes;
+	use std::str::FromStr;
+
+	fn transaction() -> Transaction {
+		Transaction {
+			version: 2,
+			inputs: vec![
+				TransactionInput::coinbase(Bytes::default()),
+				TransactionInput {
+					previous_output: OutPoint {
+						hash: SHA256D::from_inner([2; 32]),
+						index: 1,
+					},
+					script_sig: Bytes::default(),
+					sequence: 0xffff_fffd,
+					script_witness: Vec::new(),
+				},
+			],
+			outputs: vec![TransactionOutput {
+				value: 50_000,
+				script_pubkey: "0014d0c59903c5bac2868760e90fd521a4665aa76520".into(),
+			}],
+			lock_time: 100,
+		}
+	}
+
+	fn updated_psbt(version: u32) -> Psbt {
+		let master = ExtendedPrivate::from_seed(&[1; 32], Network::Mainnet).unwrap();
+		let origin = KeyOrigin {
+			fingerprint: master.fingerprint(),
+			path: "m/84'/0'/0'".parse().unwrap(),
+		};
+		let account = master.derive(&origin.path).unwrap();
+		let public = account.public();
+
+		let mut psbt = Psbt::from_unsigned_transaction(transaction(), version).unwrap();
+		psbt.xpubs.push((account.extended_public(), origin.clone()));
+		psbt.unknown.insert("fc0102".into(), "ab".into());
+		psbt.inputs[0].witness_utxo = Some(TransactionOutput {
+			value: 60_000,
+			script_pubkey: "0014d0c59903c5bac2868760e90fd521a4665aa76520".into(),
+		});
+		psbt.inputs[0].partial_sigs.insert(public.to_vec().into(), "3044".into());
+		psbt.inputs[0].sighash_type = Some(1);
+		psbt.inputs[0].bip32_derivation.insert(public.to_vec().into(), origin);
+		psbt.inputs[1].tap_internal_key = Some(XOnlyPublic::from_slice(&public[1..]).unwrap());
+		psbt.inputs[1].final_script_witness = Some(vec!["01".into(), Bytes::default()]);
+		psbt.outputs[0].redeem_script = Some("51".into());
+		psbt.outputs[0].unknown.insert("ff".into(), Bytes::default());
+		psbt
+	}
+
+	#[test]
+	fn test_psbt_v0_roundtrip() {
+		let psbt = updated_psbt(0);
+		let serialized = psbt.to_string();
+		assert!(serialized.starts_with("cHNidP8BA"));
+		assert_eq!(Psbt::from_str(&serialized), Ok(psbt.clone()));
+		assert_eq!(psbt.unsigned_transaction(), Ok(transaction()));
+		assert_eq!(psbt.unique_id(), Ok(transaction().hash()));
+	}
+
+	#[test]
+	fn test_psbt_v2_roundtrip() {
+		let mut psbt = updated_psbt(2);
+		psbt.tx_modifiable = Some(3);
+		psbt.inputs[1].required_height_locktime = Some(200);
+		let serialized = psbt.to_string();
+		assert_eq!(Psbt::from_str(&serialized), Ok(psbt.clone()));
+		assert_eq!(psbt.lock_time(), Ok(200));
+
+		// sequences are not committed to by the unique id
+		let unique_id = psbt.unique_id().unwrap();
+		psbt.inputs[0].sequence = Some(1);
+		assert_eq!(psbt.unique_id(), Ok(unique_id));
+		assert!(psbt.unsigned_transaction().unwrap().hash() != unique_id);
+	}
+
+	#[test]
+	fn test_psbt_bip174_vector() {
+		let psbt = Psbt::from_str("cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAAAA").unwrap();
+		assert_eq!(psbt.version, 0);
+		assert_eq!(psbt.inputs.len(), 1);
+		assert_eq!(psbt.outputs.len(), 2);
+		assert!(psbt.inputs[0].non_witness_utxo.is_some());
+		assert_eq!(psbt.fee(), Some(301));
+	}
+
+	#[test]
+	fn test_psbt_invalid() {
+		let serialized = updated_psbt(0).to_bytes().to_vec();
+		let unknown_pair = [3u8, 0xfc, 1, 2, 1, 0xab];
+		let position = serialized
+			.windows(unknown_pair.len())
+			.position(|window| window == unknown_pair)
+			.unwrap();
+		let mut duplicated = serialized.clone();
+		duplicated.splice(position..position, unknown_pair.iter().cloned());
+		let mut with_tx_version = serialized.clone();
+		with_tx_version.splice(5..5, [1u8, 2, 4, 2, 0, 0, 0].iter().cloned());
+
+		assert_eq!(Psbt::from_slice(&serialized[1..]), Err(Error::InvalidMagic));
+		assert_eq!(Psbt::from_str("cHNidP8!"), Err(Error::InvalidBase64));
+		assert_eq!(Psbt::from_slice(&duplicated), Err(Error::DuplicateKey("fc0102".into())));
+		assert_eq!(
+			Psbt::from_slice(&with_tx_version),
+			Err(Error::UnexpectedField("version 2 transaction field"))
+		);
+		assert_eq!(
+			Psbt::from_slice(&serialized[..serialized.len() - 1]),
+			Err(Error::Deserialize(ser::Error::UnexpectedEnd))
+		);
+		assert_eq!(Psbt::from_unsigned_transaction(transaction(), 1), Err(Error::UnsupportedVersion(1)));
+	}
+
+	#[test]
+	fn test_psbt_lock_time_conflict() {
+		let mut psbt = Psbt::from_unsigned_transaction(transaction(), 2).unwrap();
+		assert_eq!(psbt.lock_time(), Ok(100));
+		psbt.inputs[0].required_time_locktime = Some(500_000_000);
+		assert_eq!(psbt.lock_time(), Ok(500_000_000));
+		psbt.inputs[1].required_height_locktime = Some(10);
+		assert_eq!(psbt.lock_time(), Err(Error::LockTimeConflict));
+		psbt.inputs[0].required_height_locktime = Some(20);
+		assert_eq!(psbt.lock_time(), Ok(20));
+	}
+
+	#[test]
+	fn test_psbt_combine() {
+		let mut psbt = Psbt::from_unsigned_transaction(transaction(), 0).unwrap();
+		let mut other = psbt.clone();
+		psbt.inputs[0].partial_sigs.insert("02aa".into(), "30aa".into());
+		other.inputs[0].partial_sigs.insert("02bb".into(), "30bb".into());
+		other.inputs[1].redeem_script = Some("51".into());
+		other.outputs[0].witness_script = Some("52".into());
+
+		let mut expected = psbt.clone();
+		expected.inputs[0].partial_sigs.insert("02bb".into(), "30bb".into());
+		expected.inputs[1].redeem_script = Some("51".into());
+		expected.outputs[0].witness_script = Some("52".into());
+		psbt.combine(other).unwrap();
+		assert_eq!(psbt, expected);
+
+		let mut different = transaction();
+		different.lock_time = 101;
+		let different = Psbt::from_unsigned_transaction(different, 0).unwrap();
+		assert_eq!(psbt.combine(different), Err(Error::DifferentTransactions));
+	}
+
+	#[test]
+	fn test_psbt_extract_requires_finalized_inputs() {
+		let mut psbt = Psbt::from_unsigned_transaction(transaction(), 0).unwrap();
+		psbt.inputs[0].final_script_sig = Some("51".into());
+		assert_eq!(psbt.extract(), Err(Error::NotFinalized));
+
+		psbt.inputs[1].final_script_witness = Some(vec!["01".into()]);
+		let mut expected = transaction();
+		expected.inputs[0].script_sig = "51".into();
+		expected.inputs[1].script_witness = vec!["01".into()];
+		assert_eq!(psbt.extract(), Ok(expected));
+	}
+}
@@ -0,0 +1,133 @@
+//! Signer role: adds signatures of the private key to the inputs.
+
+use crate::error::Error;
+use crate::input::Input;
+use crate::psbt::Psbt;
+use bitcrypto::{dhash160, sha256, tagged_hash, Hash, SHA256};
+use keys::{AddressHash, KeyPair, Private, XOnlyPublic};
+use primitives::bytes::Bytes;
+use script::{Builder, Script, ScriptExecutionData, ScriptType, SignatureVersion, TransactionInputSigner};
+
+/// SIGHASH_ALL, used by ECDSA signatures if input doesn't specify sighash type
+const SIGHASH_ALL: u32 = 1;
+/// SIGHASH_DEFAULT, used by taproot signatures if input doesn't specify sighash type
+const SIGHASH_DEFAULT: u32 = 0;
+
+/// Script, which is signed by the input signature
+enum ScriptCode {
+	Ecdsa(Script, SignatureVersion),
+	/// Taproot key path, spending output with the given key
+	TaprootKey(XOnlyPublic),
+}
+
+impl Psbt {
+	/// Signs inputs, which are spendable by the private key. Taproot inputs are signed only if the key
+	/// is their internal key. Returns number of added signatures
+	pub fn sign(&mut self, private: &Private) -> Result<usize, Error> {
+		let public = KeyPair::from_private(Private { ..*private })?.public().clone();
+		let public_hash = dhash160(&public);
+		let mut signer = TransactionInputSigner::from(self.unsigned_transaction()?);
+		signer.spent_outputs = self
+			.inputs
+			.iter()
+			.map(Input::spent_output)
+			.collect::<Option<_>>()
+			.unwrap_or_default();
+
+		let mut signed = 0;
+		for (index, input) in self.inputs.iter_mut().enumerate() {
+			if input.is_finalized() {
+				continue;
+			}
+			let spent_output = match input.spent_output() {
+				Some(spent_output) => spent_output,
+				None => continue,
+			};
+
+			match script_code(input, Script::new(spent_output.script_pubkey)) {
+				Some(ScriptCode::Ecdsa(script_code, sigversion)) => {
+					if input.partial_sigs.contains_key(&Bytes::from(&*public)) || !involves_key(&script_code, &public, &public_hash) {
+						continue;
+					}
+
+					let sighash = input.sighash_type.unwrap_or(SIGHASH_ALL);
+					let hash = signer.signature_hash(index, spent_output.value, &script_code, sigversion, sighash);
+					let mut signature: Vec<u8> = private.sign(&hash)?.into();
+					signature.push(sighash as u8);
+					input.partial_sigs.insert(public.to_vec().into(), signature.into());
+				}
+				Some(ScriptCode::TaprootKey(output_key)) => {
+					let internal_key = match input.tap_internal_key {
+						Some(internal_key) if input.tap_key_sig.is_none() && internal_key == private.xonly_public()? => internal_key,
+						_ => continue,
+					};
+
+					let mut tweak_data = internal_key.to_vec();
+					if let Some(ref merkle_root) = input.tap_merkle_root {
+						tweak_data.extend_from_slice(&merkle_root[..]);
+					}
+					let tweaked = private.tweak_xonly(&tagged_hash(b"TapTweak", &tweak_data))?;
+					if tweaked.xonly_public()? != output_key {
+						continue;
+					}
+
+					let sighash = input.sighash_type.unwrap_or(SIGHASH_DEFAULT);
+					let hash =
+						match signer.signature_hash_schnorr(index, sighash, SignatureVersion::Taproot, &ScriptExecutionData::default()) {
+							Some(hash) => hash,
+							None => continue,
+						};
+					let mut signature: Vec<u8> = tweaked.sign_schnorr(&hash)?.into();
+					if sighash != SIGHASH_DEFAULT {
+						signature.push(sighash as u8);
+					}
+					input.tap_key_sig = Some(signature.into());
+				}
+				None => continue,
+			}
+			signed += 1;
+		}
+		Ok(signed)
+	}
+}
+
+/// Returns script, signed by the input, spending output with the given script_pubkey. Redeem and
+/// witness scripts of the input must match hashes of the spent output
+fn script_code(input: &Input, script_pubkey: Script) -> Option<ScriptCode> {
+	let nested = script_pubkey.is_pay_to_script_hash();
+	let script = if nested {
+		let redeem_script = input.redeem_script.as_ref()?;
+		if dhash160(redeem_script)[..] != script_pubkey[2..22] {
+			return None;
+		}
+		Script::new(redeem_script.clone())
+	} else {
+		script_pubkey
+	};
+
+	match script.script_type() {
+		ScriptType::WitnessKey => {
+			let hash = AddressHash::from_slice(&script[2..]).expect("p2wpkh script has 20 bytes hash; qed");
+			Some(ScriptCode::Ecdsa(Builder::build_p2pkh(&hash), SignatureVersion::WitnessV0))
+		}
+		ScriptType::WitnessScript => {
+			let witness_script = input.witness_script.as_ref()?;
+			if sha256(witness_script) != SHA256::from_slice(&script[2..]).expect("p2wsh script has 32 bytes hash; qed") {
+				return None;
+			}
+			Some(ScriptCode::Ecdsa(Script::new(witness_script.clone()), SignatureVersion::WitnessV0))
+		}
+		ScriptType::WitnessTaproot if !nested => XOnlyPublic::from_slice(&script[2..]).ok().map(ScriptCode::TaprootKey),
+		_ if script.parse_witness_program().is_some() => None,
+		_ => Some(ScriptCode::Ecdsa(script, SignatureVersion::Base)),
+	}
+}
+
+/// Returns true if the script contains the public key or its hash
+fn involves_key(script: &Script, public: &[u8], public_hash: &AddressHash) -> bool {
+	script
+		.iter()
+		.filter_map(Result::ok)
+		.filter_map(|instruction| instruction.data)
+		.any(|data| data == public || data == &public_hash[..])
+}
@@ -0,0 +1,82 @@
+//! Updater role: adds spent outputs and scripts to the PSBT.
+
+use crate::psbt::Psbt;
+use bitcrypto::SHA256D;
+use chain::{OutPoint, Transaction, TransactionOutput};
+use keys::XOnlyPublic;
+use primitives::bytes::Bytes;
+use script::Script;
+
+impl Psbt {
+	/// Adds spent outputs to the inputs, which don't have them. Segwit inputs get only the spent output,
+	/// other inputs get the whole previous transaction. Redeem scripts must be added first to
+	/// recognize nested segwit inputs
+	pub fn update_utxos<F, G>(&mut self, mut spent_output: F, mut previous_transaction: G)
+	where
+		F: FnMut(&OutPoint) -> Option<TransactionOutput>,
+		G: FnMut(&SHA256D) -> Option<Transaction>,
+	{
+		for input in &mut self.inputs {
+			if input.is_finalized() || input.spent_output().is_some() {
+				continue;
+			}
+			let output = match spent_output(&input.previous_output) {
+				Some(output) => output,
+				None => continue,
+			};
+
+			let script_pubkey = Script::new(output.script_pubkey.clone());
+			let is_witness = script_pubkey.parse_witness_program().is_some()
+				|| (script_pubkey.is_pay_to_script_hash()
+					&& input.redeem_script.as_ref().map_or(false, |redeem_script| {
+						Script::new(redeem_script.clone()).parse_witness_program().is_some()
+					}));
+			if is_witness {
+				input.witness_utxo = Some(output);
+			} else {
+				input.non_witness_utxo = previous_transaction(&input.previous_output.hash);
+			}
+		}
+	}
+
+	/// Adds scripts and taproot internal key, required to spend the script_pubkey, to outputs and
+	/// inputs spending outputs with this script_pubkey. Inputs without known spent output are skipped
+	pub fn update_scripts(
+		&mut self,
+		script_pubkey: &Bytes,
+		redeem_script: Option<&Bytes>,
+		witness_script: Option<&Bytes>,
+		tap_internal_key: Option<XOnlyPublic>,
+	) {
+		for input in &mut self.inputs {
+			if input.is_finalized() {
+				continue;
+			}
+			let matches = input.spent_output().map_or(false, |output| output.script_pubkey == *script_pubkey);
+			if !matches {
+				continue;
+			}
+			if input.redeem_script.is_none() {
+				input.redeem_script = redeem_script.cloned();
+			}
+			if input.witness_script.is_none() {
+				input.witness_script = witness_script.cloned();
+			}
+			if input.tap_internal_key.is_none() {
+				input.tap_internal_key = tap_internal_key;
+			}
+		}
+
+		for output in self.outputs.iter_mut().filter(|output| output.script_pubkey == *script_pubkey) {
+			if output.redeem_script.is_none() {
+				output.redeem_script = redeem_script.cloned();
+			}
+			if output.witness_script.is_none() {
+				output.witness_script = witness_script.cloned();
+			}
+			if output.tap_internal_key.is_none() {
+				output.tap_internal_key = tap_internal_key;
+			}
+		}
+	}
+}
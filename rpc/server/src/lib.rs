@@ -13,11 +13,13 @@ extern crate miner;
 extern crate network;
 extern crate p2p;
 extern crate primitives;
+extern crate psbt;
 extern crate script as global_script;
 extern crate serialization as ser;
 extern crate storage;
 extern crate sync;
 extern crate verification;
+extern crate wallet;
 
 pub mod rpc_apis;
 pub mod rpc_server;
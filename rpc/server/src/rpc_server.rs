@@ -6,10 +6,12 @@ use network::Network;
 use p2p;
 use std::io;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use storage;
 use sync;
 use tokio::sync::Notify;
+use wallet;
 
 pub struct Dependencies {
 	pub network: Network,
@@ -18,6 +20,9 @@ pub struct Dependencies {
 	pub p2p_context: Arc<p2p::Context>,
 	pub memory: Arc<Memory>,
 	pub shutdown_signal: Arc<Notify>,
+	pub memory_pool_path: PathBuf,
+	/// Wallet, or None if the wallet is disabled
+	pub wallet: Option<Arc<wallet::Wallet>>,
 }
 
 #[derive(Default, Debug, PartialEq)]
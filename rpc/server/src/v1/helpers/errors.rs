@@ -4,16 +4,27 @@ mod codes {
 	// NOTE [ToDr] Codes from [-32099, -32000]
 	pub const UNKNOWN: i64 = -32000;
 	pub const EXECUTION_ERROR: i64 = -32015;
+	pub const BLOCK_PRUNED: i64 = -32094;
+	pub const ADDRESS_INDEX_DISABLED: i64 = -32095;
 	pub const TRANSACTION_NOT_FOUND: i64 = -32096;
 	pub const TRANSACTION_OUTPUT_NOT_FOUND: i64 = -32097;
 	pub const TRANSACTION_OF_SIDE_BRANCH: i64 = -32098;
 	pub const BLOCK_NOT_FOUND: i64 = -32099;
 	pub const NODE_ALREADY_ADDED: i64 = -32150;
 	pub const NODE_NOT_ADDED: i64 = -32151;
+	pub const NODE_NOT_CONNECTED: i64 = -32152;
+	pub const SUBNET_ALREADY_BANNED: i64 = -32153;
+	pub const SUBNET_NOT_BANNED: i64 = -32154;
+	pub const WALLET_ERROR: i64 = -32160;
+	pub const WALLET_INSUFFICIENT_FUNDS: i64 = -32161;
+	pub const WALLET_UNLOCK_NEEDED: i64 = -32162;
+	pub const WALLET_PASSPHRASE_INCORRECT: i64 = -32163;
+	pub const WALLET_WRONG_ENCRYPTION_STATE: i64 = -32164;
 }
 
 use jsonrpc_core::{Error, ErrorCode, Value};
 use std::fmt;
+use wallet;
 
 #[allow(unused_macros)]
 macro_rules! rpc_unimplemented {
@@ -62,6 +73,14 @@ pub fn block_at_height_not_found<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn block_pruned<T: fmt::Debug>(data: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::BLOCK_PRUNED),
+		message: "Block is not available (pruned data)".into(),
+		data: Some(Value::String(format!("{:?}", data))),
+	}
+}
+
 pub fn transaction_not_found<T: fmt::Debug>(data: T) -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::TRANSACTION_NOT_FOUND),
@@ -70,6 +89,14 @@ pub fn transaction_not_found<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn transaction_pruned<T: fmt::Debug>(data: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::BLOCK_PRUNED),
+		message: "Transaction with given hash is not found. Spent transactions of pruned blocks are not available".into(),
+		data: Some(Value::String(format!("{:?}", data))),
+	}
+}
+
 pub fn transaction_output_not_found<T: fmt::Debug>(data: T) -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::TRANSACTION_OUTPUT_NOT_FOUND),
@@ -86,6 +113,14 @@ pub fn transaction_of_side_branch<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn address_index_disabled() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::ADDRESS_INDEX_DISABLED),
+		message: "Address index is not enabled. Restart node with --address-index".into(),
+		data: None,
+	}
+}
+
 pub fn node_already_added() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::NODE_ALREADY_ADDED),
@@ -102,6 +137,45 @@ pub fn node_not_added() -> Error {
 	}
 }
 
+pub fn node_not_connected() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NODE_NOT_CONNECTED),
+		message: "Node not found in connected nodes".into(),
+		data: None,
+	}
+}
+
+pub fn subnet_already_banned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::SUBNET_ALREADY_BANNED),
+		message: "IP/Subnet already banned".into(),
+		data: None,
+	}
+}
+
+pub fn subnet_not_banned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::SUBNET_NOT_BANNED),
+		message: "IP/Subnet is not banned".into(),
+		data: None,
+	}
+}
+
+pub fn wallet_error(error: wallet::Error) -> Error {
+	let code = match error {
+		wallet::Error::InsufficientFunds => codes::WALLET_INSUFFICIENT_FUNDS,
+		wallet::Error::Locked => codes::WALLET_UNLOCK_NEEDED,
+		wallet::Error::IncorrectPassphrase => codes::WALLET_PASSPHRASE_INCORRECT,
+		wallet::Error::AlreadyEncrypted | wallet::Error::NotEncrypted => codes::WALLET_WRONG_ENCRYPTION_STATE,
+		_ => codes::WALLET_ERROR,
+	};
+	Error {
+		code: ErrorCode::ServerError(code),
+		message: error.to_string(),
+		data: None,
+	}
+}
+
 pub fn unknown() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::UNKNOWN),
@@ -1,24 +1,31 @@
 use crate::v1::helpers::errors::{
-	block_at_height_not_found, block_not_found, transaction_not_found, transaction_of_side_branch, transaction_output_not_found, unknown,
+	address_index_disabled, block_at_height_not_found, block_not_found, block_pruned, execution, invalid_params, transaction_not_found,
+	transaction_of_side_branch, transaction_output_not_found, unknown,
 };
 use crate::v1::traits::BlockChain;
 use crate::v1::types::ChainTxStats;
 use crate::v1::types::U256;
-use crate::v1::types::{BlockchainInfo, GetTxOutSetInfoResponse};
-use crate::v1::types::{GetBlockResponse, RawBlock, VerboseBlock};
+use crate::v1::types::{AddressBalance, AddressIndexRequest, AddressUtxo};
+use crate::v1::types::{Bip9SoftFork, Bip9Statistics, Bip9Status, GetDeploymentInfoResponse, SoftFork, SoftForkType};
+use crate::v1::types::{BlockchainInfo, DumpTxOutSetResponse, GetTxOutSetInfoResponse};
+use crate::v1::types::{GetBlockFilterResponse, GetBlockResponse, RawBlock, VerboseBlock};
 use crate::v1::types::{GetTxOutResponse, TransactionOutputScript};
-use bitcrypto::SHA256D;
+use bitcrypto::{sha256, SHA256D};
 use chain::OutPoint;
-use global_script::Script;
+use global_script::{Builder as ScriptBuilder, Script};
 use jsonrpc_core::Error;
 use keys::{self, Address};
-use network::Network;
+use network::{ConsensusParams, Deployment, Network};
 use ser::serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
 use storage;
-use storage::{BlockMeta, BlockRef};
+use storage::{BlockMeta, BlockRef, ScriptOutput};
 use verification;
 use verification::constants::TARGET_SPACING_SECONDS;
+use verification::{Deployments, ThresholdState};
 
 pub struct BlockChainClient<T: BlockChainClientCoreApi> {
 	core: T,
@@ -28,6 +35,8 @@ pub trait BlockChainClientCoreApi: Send + Sync + 'static {
 	fn is_synchronizing(&self) -> bool;
 	fn synchronization_progress(&self) -> f32;
 	fn size_on_disk(&self) -> u64;
+	fn is_pruning_enabled(&self) -> bool;
+	fn pruned_height(&self) -> Option<u32>;
 	fn network(&self) -> String;
 	fn header_count(&self) -> u32;
 	fn best_block_hash(&self) -> SHA256D;
@@ -40,10 +49,16 @@ pub trait BlockChainClientCoreApi: Send + Sync + 'static {
 	fn verbose_block(&self, hash: SHA256D) -> Option<VerboseBlock>;
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error>;
 	fn chain_tx_stats(&self, nblocks: Option<usize>, blockhash: Option<String>) -> Result<ChainTxStats, Error>;
+	fn deployment_info(&self, hash: SHA256D) -> Option<GetDeploymentInfoResponse>;
+	fn block_filter(&self, hash: SHA256D) -> Result<GetBlockFilterResponse, Error>;
+	fn address_outputs(&self, address: &Address) -> Result<Vec<ScriptOutput>, Error>;
+	fn dump_transaction_out_set(&self, path: &Path) -> Result<DumpTxOutSetResponse, Error>;
 }
 
 pub struct BlockChainClientCore {
 	network: Network,
+	consensus: ConsensusParams,
+	deployments: Deployments,
 	storage: storage::SharedStore,
 	local_sync_node: Option<sync::LocalNodeRef>, // Might be None only in tests.
 }
@@ -52,10 +67,53 @@ impl BlockChainClientCore {
 	pub fn new(network: Network, storage: storage::SharedStore, local_sync_node: Option<sync::LocalNodeRef>) -> Self {
 		BlockChainClientCore {
 			network,
+			consensus: ConsensusParams::new(network),
+			deployments: Deployments::new(),
 			storage,
 			local_sync_node,
 		}
 	}
+
+	fn buried_soft_fork(height: u32, activation: u32) -> SoftFork {
+		SoftFork {
+			fork_type: SoftForkType::Buried,
+			active: activation <= height + 1,
+			height: Some(activation),
+			bip9: None,
+		}
+	}
+
+	fn bip9_soft_fork(&self, deployment: &Deployment, height: u32) -> SoftFork {
+		let headers = self.storage.as_block_header_provider();
+		let info = self.deployments.info(deployment, height, headers, &self.consensus);
+		let activation = match (info.state, info.next_state) {
+			(ThresholdState::Active, _) => Some(info.since),
+			(_, ThresholdState::Active) => Some(height + 1),
+			_ => None,
+		};
+
+		SoftFork {
+			fork_type: SoftForkType::Bip9,
+			active: info.next_state.is_active(),
+			height: activation,
+			bip9: Some(Bip9SoftFork {
+				bit: deployment.bit,
+				start_time: deployment.start_time,
+				timeout: deployment.timeout,
+				min_activation_height: deployment.min_activation_height,
+				status: info.state.into(),
+				since: info.since,
+				status_next: info.next_state.into(),
+				statistics: info.statistics.map(|statistics| Bip9Statistics {
+					period: statistics.period,
+					threshold: statistics.threshold,
+					elapsed: statistics.elapsed,
+					count: statistics.count,
+					possible: statistics.possible,
+				}),
+			}),
+		}
+	}
 }
 
 impl BlockChainClientCoreApi for BlockChainClientCore {
@@ -71,6 +129,14 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 		self.storage.size()
 	}
 
+	fn is_pruning_enabled(&self) -> bool {
+		self.storage.is_pruning_enabled()
+	}
+
+	fn pruned_height(&self) -> Option<u32> {
+		self.storage.pruned_height()
+	}
+
 	fn network(&self) -> String {
 		self.network.to_string()
 	}
@@ -143,10 +209,17 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 	}
 
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
-		let transaction = match self.storage.transaction(&prev_out.hash) {
-			Some(transaction) => transaction,
-			// no transaction => no response
-			None => return Err(transaction_not_found(prev_out.hash)),
+		// transactions, loaded from the UTXO snapshot, are not served as real transactions, but their outputs are
+		let (transaction, version) = match self.storage.transaction(&prev_out.hash) {
+			Some(transaction) => {
+				let version = Some(transaction.raw.version);
+				(transaction, version)
+			}
+			None => match self.storage.snapshot_transaction(&prev_out.hash) {
+				Some(transaction) => (transaction, None),
+				// no transaction => no response
+				None => return Err(transaction_not_found(prev_out.hash)),
+			},
 		};
 
 		if prev_out.index >= transaction.raw.outputs.len() as u32 {
@@ -190,7 +263,8 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 					.map(|a| Address {
 						network: match self.network {
 							Network::Mainnet => keys::Network::Mainnet,
-							// there's no correct choices for Regtests && Other networks
+							Network::Regtest => keys::Network::Regtest,
+							// there's no correct choices for Unitest && Other networks
 							// => let's just make Testnet key
 							_ => keys::Network::Testnet,
 						},
@@ -199,8 +273,8 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 					})
 					.collect(),
 			},
-			version: transaction.raw.version,
-			coinbase: transaction.raw.is_coinbase(),
+			version,
+			coinbase: meta.is_coinbase(),
 		})
 	}
 
@@ -248,6 +322,82 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 			txrate: 0,
 		})
 	}
+
+	fn deployment_info(&self, hash: SHA256D) -> Option<GetDeploymentInfoResponse> {
+		let height = self.storage.block_number(&hash)?;
+
+		let mut deployments = HashMap::new();
+		deployments.insert("bip34".to_owned(), Self::buried_soft_fork(height, self.consensus.bip34_height));
+		deployments.insert("bip66".to_owned(), Self::buried_soft_fork(height, self.consensus.bip66_height));
+		deployments.insert("bip65".to_owned(), Self::buried_soft_fork(height, self.consensus.bip65_height));
+		for deployment in &self.consensus.deployments {
+			let soft_fork = match deployment.activation {
+				Some(activation) => Self::buried_soft_fork(height, activation),
+				None => self.bip9_soft_fork(deployment, height),
+			};
+			deployments.insert(deployment.name.to_owned(), soft_fork);
+		}
+
+		Some(GetDeploymentInfoResponse { hash, height, deployments })
+	}
+
+	fn block_filter(&self, hash: SHA256D) -> Result<GetBlockFilterResponse, Error> {
+		let block_ref = BlockRef::Hash(hash);
+		if !self.storage.contains_block(block_ref) {
+			return Err(block_not_found(hash));
+		}
+
+		match (self.storage.block_filter(block_ref), self.storage.block_filter_header(block_ref)) {
+			(Some(filter), Some(header)) => Ok(GetBlockFilterResponse {
+				filter: filter.content.into(),
+				header,
+			}),
+			_ => Err(execution(format!("Filter of block {} is not indexed", hash))),
+		}
+	}
+
+	fn address_outputs(&self, address: &Address) -> Result<Vec<ScriptOutput>, Error> {
+		let script = ScriptBuilder::build_address(address);
+		self.storage
+			.script_outputs(&sha256(&script))
+			.map_err(execution)?
+			.ok_or_else(address_index_disabled)
+	}
+
+	fn dump_transaction_out_set(&self, path: &Path) -> Result<DumpTxOutSetResponse, Error> {
+		let path = std::env::current_dir().map_err(execution)?.join(path);
+		if path.exists() {
+			return Err(execution(format!("{} already exists", path.display())));
+		}
+
+		// snapshot is written to the temporary file first, so that incomplete snapshot is never left at the path
+		let mut incomplete_path = path.clone().into_os_string();
+		incomplete_path.push(".incomplete");
+		let mut writer = BufWriter::new(File::create(&incomplete_path).map_err(execution)?);
+		let info = match storage::write_utxo_snapshot(&*self.storage, self.network.magic(), &mut writer) {
+			Ok(info) => info,
+			Err(err) => {
+				drop(writer);
+				let _ = fs::remove_file(&incomplete_path);
+				return Err(execution(err));
+			}
+		};
+		drop(writer);
+		fs::rename(&incomplete_path, &path).map_err(execution)?;
+
+		let meta = self
+			.storage
+			.block_meta(BlockRef::Hash(info.metadata.base_hash))
+			.ok_or_else(unknown)?;
+		Ok(DumpTxOutSetResponse {
+			coins_written: info.outputs,
+			base_hash: info.metadata.base_hash,
+			base_height: info.metadata.base_height,
+			path: path.display().to_string(),
+			txoutset_hash: info.hash,
+			nchaintx: meta.n_chain_tx,
+		})
+	}
 }
 
 impl<T> BlockChainClient<T>
@@ -275,8 +425,13 @@ where
 			initialblockdownload: self.core.is_synchronizing(),
 			chainwork: SHA256D::default(),
 			size_on_disk: self.core.size_on_disk(),
-			pruned: false,
-			softforks: HashMap::new(),
+			pruned: self.core.is_pruning_enabled(),
+			pruneheight: self.core.pruned_height().map(|pruned_height| pruned_height + 1),
+			softforks: self
+				.core
+				.deployment_info(self.core.best_block_hash())
+				.map(|info| info.deployments)
+				.unwrap_or_default(),
 			warnings: "".to_string(),
 		})
 	}
@@ -306,7 +461,14 @@ where
 		} else {
 			self.core.raw_block(hash).map(|block| GetBlockResponse::Raw(block))
 		}
-		.ok_or(block_not_found(hash))
+		.ok_or_else(|| {
+			// transactions of pruned blocks are (partially) deleted, but headers and metadata are kept
+			let pruned_height = self.core.pruned_height();
+			match self.core.block_meta(hash) {
+				Some(meta) if pruned_height.map_or(false, |pruned_height| meta.number <= pruned_height) => block_pruned(hash),
+				_ => block_not_found(hash),
+			}
+		})
 	}
 
 	fn transaction_out(
@@ -348,6 +510,93 @@ where
 	fn chain_tx_stats(&self, nblocks: Option<usize>, blockhash: Option<String>) -> Result<ChainTxStats, Error> {
 		self.core.chain_tx_stats(nblocks, blockhash)
 	}
+
+	fn deployment_info(&self, blockhash: Option<SHA256D>) -> Result<GetDeploymentInfoResponse, Error> {
+		let hash = blockhash.unwrap_or_else(|| self.core.best_block_hash());
+		self.core.deployment_info(hash).ok_or(block_not_found(hash))
+	}
+
+	fn block_filter(&self, blockhash: SHA256D, filtertype: Option<String>) -> Result<GetBlockFilterResponse, Error> {
+		match filtertype.as_deref() {
+			None | Some("basic") => self.core.block_filter(blockhash),
+			Some(filtertype) => Err(invalid_params("filtertype", filtertype)),
+		}
+	}
+
+	fn address_tx_ids(&self, request: AddressIndexRequest) -> Result<Vec<SHA256D>, Error> {
+		let mut transactions = Vec::new();
+		for address in &request.addresses {
+			for output in self.core.address_outputs(address)? {
+				transactions.push((output.height, output.outpoint.hash));
+				if let Some(spent) = output.spent {
+					transactions.push((spent.height, spent.tx_hash));
+				}
+			}
+		}
+
+		// stable sort keeps the order of transactions within the same block
+		transactions.sort_by_key(|&(height, _)| height);
+		let mut seen = HashSet::new();
+		Ok(transactions
+			.into_iter()
+			.filter(|&(_, hash)| seen.insert(hash))
+			.map(|(_, hash)| hash)
+			.collect())
+	}
+
+	fn address_utxos(&self, request: AddressIndexRequest) -> Result<Vec<AddressUtxo>, Error> {
+		let mut utxos = Vec::new();
+		for address in request.addresses {
+			let script = ScriptBuilder::build_address(&address).to_bytes();
+			for output in self.core.address_outputs(&address)? {
+				if output.spent.is_some() {
+					continue;
+				}
+
+				utxos.push(AddressUtxo {
+					address: address.clone(),
+					txid: output.outpoint.hash,
+					output_index: output.outpoint.index,
+					script: script.clone().into(),
+					satoshis: output.value,
+					height: output.height,
+				});
+			}
+		}
+
+		utxos.sort_by_key(|utxo| utxo.height);
+		Ok(utxos)
+	}
+
+	fn address_balance(&self, request: AddressIndexRequest) -> Result<AddressBalance, Error> {
+		let mut balance = AddressBalance { balance: 0, received: 0 };
+		for address in &request.addresses {
+			for output in self.core.address_outputs(address)? {
+				balance.received += output.value;
+				if output.spent.is_none() {
+					balance.balance += output.value;
+				}
+			}
+		}
+
+		Ok(balance)
+	}
+
+	fn dump_transaction_out_set(&self, path: String) -> Result<DumpTxOutSetResponse, Error> {
+		self.core.dump_transaction_out_set(Path::new(&path))
+	}
+}
+
+impl From<ThresholdState> for Bip9Status {
+	fn from(state: ThresholdState) -> Self {
+		match state {
+			ThresholdState::Defined => Bip9Status::Defined,
+			ThresholdState::Started => Bip9Status::Started,
+			ThresholdState::LockedIn => Bip9Status::LockedIn,
+			ThresholdState::Active => Bip9Status::Active,
+			ThresholdState::Failed => Bip9Status::Failed,
+		}
+	}
 }
 
 #[cfg(test)]
@@ -361,7 +610,8 @@ pub mod tests {
 	use crate::v1::types::ScriptType;
 	use crate::v1::types::{GetTxOutResponse, TransactionOutputScript};
 	use crate::v1::types::{RawBlock, VerboseBlock};
-	use bitcrypto::{FromInnerHex, SHA256D};
+	use bitcrypto::{FromInnerHex, FromStr, SHA256D};
+	use chain::IndexedBlock;
 	use chain::OutPoint;
 	use db::BlockChainDatabase;
 	use jsonrpc_core::Error;
@@ -369,6 +619,7 @@ pub mod tests {
 	use network::Network;
 	use primitives::bytes::Bytes as GlobalBytes;
 	use std::sync::Arc;
+	use storage::{BlockFilterProvider, PruneProvider, ScriptOutputSpend, MIN_BLOCKS_TO_KEEP};
 
 	#[derive(Default)]
 	struct SuccessBlockChainClientCore;
@@ -388,6 +639,14 @@ pub mod tests {
 			42000
 		}
 
+		fn is_pruning_enabled(&self) -> bool {
+			false
+		}
+
+		fn pruned_height(&self) -> Option<u32> {
+			None
+		}
+
 		fn network(&self) -> String {
 			Network::Mainnet.to_string()
 		}
@@ -468,7 +727,7 @@ pub mod tests {
 						"1H5m1XzvHsjWX3wwU781ubctznEpNACrNC".into(),
 					],
 				},
-				version: 33,
+				version: Some(33),
 				coinbase: false,
 			})
 		}
@@ -476,6 +735,68 @@ pub mod tests {
 		fn chain_tx_stats(&self, _nblocks: Option<usize>, _blockhash: Option<String>) -> Result<ChainTxStats, Error> {
 			Ok(ChainTxStats::default())
 		}
+
+		fn deployment_info(&self, hash: SHA256D) -> Option<GetDeploymentInfoResponse> {
+			let mut deployments = HashMap::new();
+			deployments.insert(
+				"csv".to_owned(),
+				SoftFork {
+					fork_type: SoftForkType::Buried,
+					active: true,
+					height: Some(419328),
+					bip9: None,
+				},
+			);
+			Some(GetDeploymentInfoResponse {
+				hash,
+				height: 1,
+				deployments,
+			})
+		}
+
+		fn block_filter(&self, _hash: SHA256D) -> Result<GetBlockFilterResponse, Error> {
+			Ok(GetBlockFilterResponse {
+				filter: "019dfca8".into(),
+				header: SHA256D::from_str("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750").unwrap(),
+			})
+		}
+
+		fn address_outputs(&self, _address: &Address) -> Result<Vec<ScriptOutput>, Error> {
+			Ok(vec![
+				ScriptOutput {
+					outpoint: OutPoint {
+						hash: SHA256D::from_str("0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098").unwrap(),
+						index: 0,
+					},
+					height: 1,
+					value: 5000,
+					spent: Some(ScriptOutputSpend {
+						tx_hash: SHA256D::from_str("9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5").unwrap(),
+						height: 2,
+					}),
+				},
+				ScriptOutput {
+					outpoint: OutPoint {
+						hash: SHA256D::from_str("999e1c837c76a1b7fbb7e57baf87b309960f5ffefbf2a9b95dd890602272f644").unwrap(),
+						index: 1,
+					},
+					height: 3,
+					value: 2000,
+					spent: None,
+				},
+			])
+		}
+
+		fn dump_transaction_out_set(&self, path: &Path) -> Result<DumpTxOutSetResponse, Error> {
+			Ok(DumpTxOutSetResponse {
+				coins_written: 1,
+				base_hash: SHA256D::from_str("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f").unwrap(),
+				base_height: 0,
+				path: path.display().to_string(),
+				txoutset_hash: SHA256D::from_str("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750").unwrap(),
+				nchaintx: 1,
+			})
+		}
 	}
 
 	impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
@@ -495,6 +816,14 @@ pub mod tests {
 			42000
 		}
 
+		fn is_pruning_enabled(&self) -> bool {
+			false
+		}
+
+		fn pruned_height(&self) -> Option<u32> {
+			None
+		}
+
 		fn network(&self) -> String {
 			Network::Mainnet.to_string()
 		}
@@ -535,6 +864,22 @@ pub mod tests {
 			Err(block_not_found(nblocks))
 		}
 
+		fn deployment_info(&self, _hash: SHA256D) -> Option<GetDeploymentInfoResponse> {
+			None
+		}
+
+		fn block_filter(&self, hash: SHA256D) -> Result<GetBlockFilterResponse, Error> {
+			Err(block_not_found(hash))
+		}
+
+		fn address_outputs(&self, _address: &Address) -> Result<Vec<ScriptOutput>, Error> {
+			Err(address_index_disabled())
+		}
+
+		fn dump_transaction_out_set(&self, path: &Path) -> Result<DumpTxOutSetResponse, Error> {
+			Err(execution(format!("{} already exists", path.display())))
+		}
+
 		fn block_meta(&self, _hash: SHA256D) -> Option<BlockMeta> {
 			None
 		}
@@ -560,7 +905,7 @@ pub mod tests {
 
 		assert_eq!(
 			&sample,
-			r#"{"jsonrpc":"2.0","result":{"bestblockhash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","blocks":1,"chain":"main","chainwork":"0000000000000000000000000000000000000000000000000000000000000000","difficulty":1.0,"headers":1,"initialblockdownload":false,"mediantime":3919284,"pruned":false,"size_on_disk":42000,"softforks":{},"verificationprogress":1.0,"warnings":""},"id":1}"#
+			r#"{"jsonrpc":"2.0","result":{"bestblockhash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","blocks":1,"chain":"main","chainwork":"0000000000000000000000000000000000000000000000000000000000000000","difficulty":1.0,"headers":1,"initialblockdownload":false,"mediantime":3919284,"pruned":false,"size_on_disk":42000,"softforks":{"csv":{"active":true,"height":419328,"type":"buried"}},"verificationprogress":1.0,"warnings":""},"id":1}"#
 		);
 	}
 
@@ -817,6 +1162,49 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn pruned_block_error() {
+		let genesis: IndexedBlock = test_data::genesis().into();
+		let storage = BlockChainDatabase::transient(&genesis).unwrap().with_pruning(0);
+		let mut hashes = vec![*genesis.hash()];
+		for value in 0..=MIN_BLOCKS_TO_KEEP as u64 {
+			#[rustfmt::skip]
+			let block: IndexedBlock = test_data::block_builder()
+				.header().parent(*hashes.last().unwrap()).build()
+				.transaction().coinbase().output().value(value).build().build()
+				.build()
+				.into();
+			hashes.push(*block.hash());
+			storage.insert(block).unwrap();
+			storage.canonize(hashes.last().unwrap()).unwrap();
+		}
+		assert_eq!(storage.pruned_height(), Some(1));
+
+		let core = BlockChainClientCore::new(Network::Mainnet, Arc::new(storage), None);
+		let client = BlockChainClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let request = |hash: SHA256D| {
+			handler
+				.handle_request_sync(&format!(
+					r#"{{"jsonrpc": "2.0", "method": "getblock", "params": ["{}", false], "id": 1}}"#,
+					hash
+				))
+				.unwrap()
+		};
+
+		assert_eq!(
+			request(hashes[1]),
+			format!(
+				r#"{{"jsonrpc":"2.0","error":{{"code":-32094,"message":"Block is not available (pruned data)","data":"{}"}},"id":1}}"#,
+				hashes[1]
+			)
+		);
+		assert!(request(hashes[2]).contains(r#""result""#));
+		assert!(request(SHA256D::default()).contains(r#""code":-32099"#));
+	}
+
 	#[test]
 	fn verbose_block_success() {
 		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
@@ -887,7 +1275,7 @@ pub mod tests {
 					script_type: ScriptType::PubKey,
 					addresses: vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into()]
 				},
-				version: 1,
+				version: Some(1),
 				coinbase: true
 			}));
 	}
@@ -939,4 +1327,389 @@ pub mod tests {
 			r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a"},"id":1}"#
 		);
 	}
+
+	#[test]
+	fn deployment_info_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getdeploymentinfo",
+				"params": [],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"deployments":{"csv":{"active":true,"height":419328,"type":"buried"}},"hash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","height":1},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn deployment_info_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getdeploymentinfo",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn block_filter_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943", "basic"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"filter":"019dfca8","header":"21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn block_filter_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"},"id":1}"#
+		);
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "extended"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: filtertype","data":"\"extended\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn block_filter_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+			test_data::genesis().into(),
+			test_data::block_h1().into(),
+		]));
+
+		let core = BlockChainClientCore::new(Network::Unitest, storage.clone(), None);
+		let response = core.block_filter(test_data::block_h1().hash()).unwrap();
+		let filter = storage.block_filter(BlockRef::Number(1)).unwrap();
+		let previous_header = storage.block_filter_header(BlockRef::Number(0)).unwrap();
+		assert_eq!(response.filter, filter.content.clone().into());
+		assert_eq!(response.header, filter.header(&previous_header));
+		assert!(core.block_filter(SHA256D::default()).is_err());
+	}
+
+	#[test]
+	fn address_tx_ids_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddresstxids",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":["0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098","9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5","999e1c837c76a1b7fbb7e57baf87b309960f5ffefbf2a9b95dd890602272f644"],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn address_utxos_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddressutxos",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":[{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","height":3,"outputIndex":1,"satoshis":2000,"script":"76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac","txid":"999e1c837c76a1b7fbb7e57baf87b309960f5ffefbf2a9b95dd890602272f644"}],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn address_balance_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddressbalance",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"balance":2000,"received":7000},"id":1}"#);
+	}
+
+	#[test]
+	fn address_balance_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddressbalance",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32095,"message":"Address index is not enabled. Restart node with --address-index"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn address_outputs_contents() {
+		let address: Address = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into();
+		let b0: chain::IndexedBlock = test_data::genesis().into();
+		#[rustfmt::skip]
+		let b1: chain::IndexedBlock = test_data::block_builder()
+			.header().parent(b0.hash().clone()).build()
+			.transaction().coinbase()
+				.output().value(50).script_pubkey_bytes(ScriptBuilder::build_address(&address).to_bytes()).build()
+				.build()
+			.build()
+			.into();
+
+		let storage = Arc::new(BlockChainDatabase::transient(&b0).unwrap());
+		let core = BlockChainClientCore::new(Network::Unitest, storage.clone(), None);
+		assert_eq!(core.address_outputs(&address), Err(address_index_disabled()));
+
+		let storage = Arc::new(BlockChainDatabase::transient(&b0).unwrap().with_script_index().unwrap());
+		storage.insert(b1.clone()).unwrap();
+		storage.canonize(b1.hash()).unwrap();
+		let core = BlockChainClientCore::new(Network::Unitest, storage, None);
+		assert_eq!(
+			core.address_outputs(&address),
+			Ok(vec![ScriptOutput {
+				outpoint: OutPoint {
+					hash: b1.transactions[0].hash,
+					index: 0,
+				},
+				height: 1,
+				value: 50,
+				spent: None,
+			}])
+		);
+	}
+
+	#[test]
+	fn deployment_info_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+			test_data::genesis().into(),
+			test_data::block_h1().into(),
+			test_data::block_h2().into(),
+		]));
+
+		let core = BlockChainClientCore::new(Network::Unitest, storage, None);
+		let info = core.deployment_info(test_data::block_h2().hash()).unwrap();
+		assert_eq!(info.height, 2);
+		assert_eq!(
+			info.deployments["bip65"],
+			SoftFork {
+				fork_type: SoftForkType::Buried,
+				active: false,
+				height: Some(1351),
+				bip9: None,
+			}
+		);
+		assert_eq!(
+			info.deployments["csv"],
+			SoftFork {
+				fork_type: SoftForkType::Buried,
+				active: true,
+				height: Some(0),
+				bip9: None,
+			}
+		);
+		assert_eq!(
+			info.deployments["segwit"],
+			SoftFork {
+				fork_type: SoftForkType::Bip9,
+				active: false,
+				height: None,
+				bip9: Some(Bip9SoftFork {
+					bit: 1,
+					start_time: 0,
+					timeout: u32::MAX,
+					min_activation_height: 0,
+					status: Bip9Status::Defined,
+					since: 0,
+					status_next: Bip9Status::Defined,
+					statistics: None,
+				}),
+			}
+		);
+		assert!(info.deployments.contains_key("testdummy"));
+
+		assert_eq!(core.deployment_info(SHA256D::default()), None);
+	}
+
+	#[test]
+	fn dump_transaction_out_set_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumptxoutset",
+				"params": ["/tmp/utxo.dat"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"base_hash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","base_height":0,"coins_written":1,"nchaintx":1,"path":"/tmp/utxo.dat","txoutset_hash":"21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn dump_transaction_out_set_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumptxoutset",
+				"params": ["/tmp/utxo.dat"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"/tmp/utxo.dat already exists\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn dump_transaction_out_set_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+			test_data::genesis().into(),
+			test_data::block_h1().into(),
+		]));
+		let core = BlockChainClientCore::new(Network::Mainnet, storage.clone(), None);
+
+		let path = std::env::temp_dir().join(format!("dumptxoutset-{}.dat", std::process::id()));
+		fs::remove_file(&path).unwrap_or_default();
+		let response = core.dump_transaction_out_set(&path).unwrap();
+		assert_eq!(response.coins_written, 1);
+		assert_eq!(response.base_hash, storage.best_block().hash);
+		assert_eq!(response.base_height, 1);
+		assert_eq!(response.nchaintx, 2);
+		assert_eq!(response.path, path.display().to_string());
+
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let snapshot = File::open(&path).unwrap();
+		let metadata = storage::SnapshotReader::new(snapshot).unwrap().metadata().clone();
+		assert_eq!(metadata.base_hash, response.base_hash);
+		assert_eq!(metadata.network_magic, consensus.magic());
+
+		// existing file is never overwritten
+		assert!(core.dump_transaction_out_set(&path).is_err());
+		fs::remove_file(&path).unwrap();
+	}
 }
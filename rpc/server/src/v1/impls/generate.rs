@@ -1,39 +1,46 @@
 use crate::v1::helpers::errors::invalid_params;
 use crate::v1::traits::Generate;
-use bitcrypto::{FromStr, SHA256D};
+use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
-use keys::AddressHash;
-use tokio::time::Duration;
+use keys::Address;
+use network::Network;
 
 pub struct GenerateClient<T: GenerateClientCoreApi> {
 	core: T,
 }
 
 pub trait GenerateClientCoreApi: Send + Sync + 'static {
-	fn generate_to_address(&self, n_blocks: u32, address: Option<AddressHash>, max_tries: usize) -> Vec<SHA256D>;
+	fn generate_to_address(&self, n_blocks: u32, address: Option<Address>, max_tries: usize) -> Vec<SHA256D>;
+	/// Returns network of the node
+	fn network(&self) -> Network;
 }
 
 pub struct GenerateClientCore {
+	network: Network,
 	local_sync_node: sync::LocalNodeRef,
 }
 
 impl GenerateClientCore {
-	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
-		GenerateClientCore { local_sync_node }
+	pub fn new(network: Network, local_sync_node: sync::LocalNodeRef) -> Self {
+		GenerateClientCore { network, local_sync_node }
 	}
 }
 
 impl GenerateClientCoreApi for GenerateClientCore {
-	fn generate_to_address(&self, n_blocks: u32, address: Option<AddressHash>, max_tries: usize) -> Vec<SHA256D> {
+	fn generate_to_address(&self, n_blocks: u32, address: Option<Address>, max_tries: usize) -> Vec<SHA256D> {
 		let mut block_hashes = vec![];
 		for _ in 0..n_blocks {
-			if let Some(hash) = self.local_sync_node.generate_block(address, max_tries) {
+			// every block is inserted into the storage before next one is mined
+			if let Some(hash) = self.local_sync_node.generate_block(address.clone(), max_tries) {
 				block_hashes.push(hash);
 			}
-			std::thread::sleep(Duration::from_secs(1));
 		}
 		block_hashes
 	}
+
+	fn network(&self) -> Network {
+		self.network
+	}
 }
 
 impl<T> GenerateClient<T>
@@ -54,9 +61,105 @@ where
 	}
 
 	fn generate_to_address(&self, n_blocks: u32, address: String, max_tries: Option<usize>) -> Result<Vec<SHA256D>, Error> {
-		let address_hash = AddressHash::from_str(&address).map_err(|e| invalid_params("address", e.to_string()))?;
-		Ok(self
-			.core
-			.generate_to_address(n_blocks, Some(address_hash), max_tries.unwrap_or(1000000)))
+		let network = match self.core.network() {
+			Network::Mainnet => keys::Network::Mainnet,
+			Network::Regtest => keys::Network::Regtest,
+			_ => keys::Network::Testnet,
+		};
+		let address = Address::from_str_for_network(&address, network).map_err(|e| invalid_params("address", e.to_string()))?;
+		Ok(self.core.generate_to_address(n_blocks, Some(address), max_tries.unwrap_or(1000000)))
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use jsonrpc_core::IoHandler;
+
+	#[derive(Default)]
+	struct SuccessGenerateClientCore;
+
+	impl GenerateClientCoreApi for SuccessGenerateClientCore {
+		fn generate_to_address(&self, n_blocks: u32, address: Option<Address>, _max_tries: usize) -> Vec<SHA256D> {
+			assert_eq!(n_blocks, 1);
+			assert_eq!(address, Some("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".into()));
+			vec![SHA256D::default()]
+		}
+
+		fn network(&self) -> Network {
+			Network::Regtest
+		}
+	}
+
+	#[test]
+	fn generatetoaddress_segwit_address() {
+		let client = GenerateClient::new(SuccessGenerateClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generatetoaddress",
+				"params": [1, "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000000"],"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn generatetoaddress_invalid_address() {
+		let client = GenerateClient::new(SuccessGenerateClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generatetoaddress",
+				"params": [1, "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt081"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: address","data":"\"Invalid Address\""},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn generatetoaddress_other_network_address() {
+		let client = GenerateClient::new(SuccessGenerateClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generatetoaddress",
+				"params": [1, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: address","data":"\"Invalid Network\""},"id":1}"#,
+			&sample
+		);
 	}
 }
@@ -1,8 +1,17 @@
+use crate::v1::helpers::errors::{execution, invalid_params, transaction_not_found};
 use crate::v1::traits::Miner;
-use crate::v1::types::{BlockTemplate, BlockTemplateRequest, MempoolInfo};
+use crate::v1::types::{
+	BlockTemplateRequest, BlockTemplateRequestMode, Bytes, EstimateRawFeeResponse, EstimateSmartFeeResponse, GetBlockTemplateResponse,
+	MempoolEntry, MempoolInfo, MiningInfo, RawBlock, RawFeeEstimate, SaveMempoolResponse,
+};
+use bitcrypto::SHA256D;
+use chain::constants::SATOSHIS_IN_COIN;
+use chain::{Block, BlockHeader, IndexedBlock, IndexedBlockHeader};
 use jsonrpc_core::Error;
 use miner;
-use miner::MemoryPoolInformation;
+use miner::{EstimationHorizon, EstimationResult, MemoryPoolInformation, SmartFeeEstimate};
+use ser::{deserialize, Reader};
+use std::path::PathBuf;
 use sync;
 
 pub struct MinerClient<T: MinerClientCoreApi> {
@@ -10,28 +19,111 @@ pub struct MinerClient<T: MinerClientCoreApi> {
 }
 
 pub trait MinerClientCoreApi: Send + Sync + 'static {
-	fn get_block_template(&self) -> miner::BlockTemplate;
+	fn get_block_template(&self, segwit: bool) -> miner::BlockTemplate;
+	fn verify_block_proposal(&self, block: &IndexedBlock) -> Result<(), String>;
+	fn submit_block(&self, block: IndexedBlock) -> Result<(), String>;
+	fn submit_header(&self, header: IndexedBlockHeader) -> Result<(), String>;
 	fn get_mempool_info(&self) -> miner::MemoryPoolInformation;
+	fn get_mempool_entry(&self, hash: &SHA256D) -> Option<MempoolEntry>;
+	fn save_mempool(&self) -> Result<String, String>;
+	fn highest_target_tracked(&self, horizon: EstimationHorizon) -> u32;
+	fn estimate_smart_fee(&self, conf_target: u32, conservative: bool) -> SmartFeeEstimate;
+	fn estimate_raw_fee(&self, conf_target: u32, threshold: f64, horizon: EstimationHorizon) -> (Option<u64>, EstimationResult);
+	fn mining_info(&self) -> sync::MiningInformation;
 }
 
 pub struct MinerClientCore {
 	local_sync_node: sync::LocalNodeRef,
+	memory_pool_path: PathBuf,
 }
 
 impl MinerClientCore {
-	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
-		MinerClientCore { local_sync_node }
+	pub fn new(local_sync_node: sync::LocalNodeRef, memory_pool_path: PathBuf) -> Self {
+		MinerClientCore {
+			local_sync_node,
+			memory_pool_path,
+		}
 	}
 }
 
 impl MinerClientCoreApi for MinerClientCore {
-	fn get_block_template(&self) -> miner::BlockTemplate {
-		self.local_sync_node.get_block_template()
+	fn get_block_template(&self, segwit: bool) -> miner::BlockTemplate {
+		self.local_sync_node.get_block_template(segwit)
+	}
+
+	fn verify_block_proposal(&self, block: &IndexedBlock) -> Result<(), String> {
+		self.local_sync_node.verify_block_proposal(block)
+	}
+
+	fn submit_block(&self, block: IndexedBlock) -> Result<(), String> {
+		self.local_sync_node.submit_block(block)
+	}
+
+	fn submit_header(&self, header: IndexedBlockHeader) -> Result<(), String> {
+		self.local_sync_node.submit_header(header)
 	}
 
 	fn get_mempool_info(&self) -> MemoryPoolInformation {
 		self.local_sync_node.information().chain.transactions
 	}
+
+	fn get_mempool_entry(&self, hash: &SHA256D) -> Option<MempoolEntry> {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		let entry = memory_pool.get_entry(hash)?;
+		let mut depends: Vec<SHA256D> = Vec::new();
+		for input in &entry.transaction.inputs {
+			let parent_hash = input.previous_output.hash;
+			if memory_pool.contains(&parent_hash) && !depends.contains(&parent_hash) {
+				depends.push(parent_hash);
+			}
+		}
+
+		Some(MempoolEntry {
+			size: entry.size,
+			fee: entry.miner_fee as f64 / SATOSHIS_IN_COIN as f64,
+			modifiedfee: (entry.miner_fee as i64 + entry.miner_virtual_fee) as f64 / SATOSHIS_IN_COIN as f64,
+			time: entry.time,
+			ancestorcount: entry.ancestors.len() + 1,
+			descendantsize: entry.package_size,
+			depends,
+			bip125_replaceable: memory_pool.is_bip125_replaceable(hash),
+		})
+	}
+
+	fn save_mempool(&self) -> Result<String, String> {
+		self.local_sync_node.save_memory_pool(&self.memory_pool_path)?;
+		Ok(self.memory_pool_path.display().to_string())
+	}
+
+	fn highest_target_tracked(&self, horizon: EstimationHorizon) -> u32 {
+		self.local_sync_node
+			.memory_pool()
+			.read()
+			.fee_estimator()
+			.highest_target_tracked(horizon)
+	}
+
+	fn estimate_smart_fee(&self, conf_target: u32, conservative: bool) -> SmartFeeEstimate {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		let mut estimate = memory_pool.fee_estimator().estimate_smart_fee(conf_target, conservative);
+		// transactions, paying less than memory pool minimal fee, won't be accepted anyway
+		estimate.fee_rate = estimate.fee_rate.map(|fee_rate| fee_rate.max(memory_pool.min_fee_rate()));
+		estimate
+	}
+
+	fn estimate_raw_fee(&self, conf_target: u32, threshold: f64, horizon: EstimationHorizon) -> (Option<u64>, EstimationResult) {
+		self.local_sync_node
+			.memory_pool()
+			.read()
+			.fee_estimator()
+			.estimate_raw_fee(conf_target, threshold, horizon)
+	}
+
+	fn mining_info(&self) -> sync::MiningInformation {
+		self.local_sync_node.mining_information()
+	}
 }
 
 impl<T> MinerClient<T>
@@ -41,19 +133,115 @@ where
 	pub fn new(core: T) -> Self {
 		MinerClient { core }
 	}
+
+	/// Checks confirmation target, limiting it with the maximal tracked target
+	fn conf_target(&self, conf_target: u32) -> Result<u32, Error> {
+		let max_target = self.core.highest_target_tracked(EstimationHorizon::Long);
+		if conf_target < 1 {
+			return Err(invalid_params(
+				"conf_target",
+				format!("Invalid conf_target, must be between 1 and {}", max_target),
+			));
+		}
+
+		Ok(conf_target.min(max_target))
+	}
 }
 
 impl<T> Miner for MinerClient<T>
 where
 	T: MinerClientCoreApi,
 {
-	fn get_block_template(&self, _request: BlockTemplateRequest) -> Result<BlockTemplate, Error> {
-		Ok(self.core.get_block_template().into())
+	fn get_block_template(&self, request: BlockTemplateRequest) -> Result<GetBlockTemplateResponse, Error> {
+		if request.mode == Some(BlockTemplateRequestMode::Proposal) {
+			let data = request
+				.data
+				.ok_or_else(|| invalid_params("data", "Block data is required in proposal mode"))?;
+			let block: Block = deserialize(Reader::new(&data.0)).map_err(|e| invalid_params("data", e))?;
+			let block = IndexedBlock::from_raw(block);
+			return Ok(GetBlockTemplateResponse::Proposal(self.core.verify_block_proposal(&block).err()));
+		}
+
+		// transactions with witness are only included when client supports SegWit
+		let segwit = request.rules.map_or(false, |rules| rules.contains("segwit"));
+		Ok(GetBlockTemplateResponse::Template(self.core.get_block_template(segwit).into()))
+	}
+
+	fn submit_block(&self, block: RawBlock) -> Result<Option<String>, Error> {
+		let block: Block = deserialize(Reader::new(&block.0)).map_err(|e| invalid_params("block", e))?;
+		Ok(self.core.submit_block(IndexedBlock::from_raw(block)).err())
+	}
+
+	fn submit_header(&self, header: Bytes) -> Result<(), Error> {
+		let header: BlockHeader = deserialize(Reader::new(&header.0)).map_err(|e| invalid_params("header", e))?;
+		self.core.submit_header(IndexedBlockHeader::from_raw(header)).map_err(execution)
 	}
 
 	fn mempool_info(&self) -> Result<MempoolInfo, Error> {
 		Ok(self.core.get_mempool_info().into())
 	}
+
+	fn mempool_entry(&self, hash: SHA256D) -> Result<MempoolEntry, Error> {
+		self.core.get_mempool_entry(&hash).ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn save_mempool(&self) -> Result<SaveMempoolResponse, Error> {
+		self.core
+			.save_mempool()
+			.map(|filename| SaveMempoolResponse { filename })
+			.map_err(execution)
+	}
+
+	fn estimate_smart_fee(&self, conf_target: u32, estimate_mode: Option<String>) -> Result<EstimateSmartFeeResponse, Error> {
+		let conf_target = self.conf_target(conf_target)?;
+		let conservative = match estimate_mode.map(|mode| mode.to_lowercase()).as_ref().map(String::as_str) {
+			None | Some("unset") | Some("conservative") => true,
+			Some("economical") => false,
+			Some(_) => {
+				return Err(invalid_params(
+					"estimate_mode",
+					r#"Invalid estimate_mode parameter, must be one of: "unset", "economical", "conservative""#,
+				))
+			}
+		};
+
+		let estimate = self.core.estimate_smart_fee(conf_target, conservative);
+		Ok(EstimateSmartFeeResponse {
+			feerate: estimate.fee_rate.map(|fee_rate| fee_rate as f64 / SATOSHIS_IN_COIN as f64),
+			errors: match estimate.fee_rate {
+				Some(_) => None,
+				None => Some(vec!["Insufficient data or no feerate found".into()]),
+			},
+			blocks: estimate.blocks,
+		})
+	}
+
+	fn estimate_raw_fee(&self, conf_target: u32, threshold: Option<f64>) -> Result<EstimateRawFeeResponse, Error> {
+		let conf_target = self.conf_target(conf_target)?;
+		let threshold = threshold.unwrap_or(miner::DOUBLE_SUCCESS_PCT);
+		if !(0.0..=1.0).contains(&threshold) {
+			return Err(invalid_params("threshold", "Invalid threshold"));
+		}
+
+		// horizons, which do not track given target, are omitted
+		let estimate = |horizon| {
+			if conf_target > self.core.highest_target_tracked(horizon) {
+				return None;
+			}
+
+			let (fee_rate, result) = self.core.estimate_raw_fee(conf_target, threshold, horizon);
+			Some(RawFeeEstimate::new(fee_rate, result))
+		};
+		Ok(EstimateRawFeeResponse {
+			short: estimate(EstimationHorizon::Short),
+			medium: estimate(EstimationHorizon::Medium),
+			long: estimate(EstimationHorizon::Long),
+		})
+	}
+
+	fn mining_info(&self) -> Result<MiningInfo, Error> {
+		Ok(self.core.mining_info().into())
+	}
 }
 
 #[cfg(test)]
@@ -69,29 +257,176 @@ pub mod tests {
 	struct SuccessMinerClientCore;
 
 	impl MinerClientCoreApi for SuccessMinerClientCore {
-		fn get_block_template(&self) -> miner::BlockTemplate {
+		fn get_block_template(&self, segwit: bool) -> miner::BlockTemplate {
 			let tx: chain::Transaction =
 				"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"
 					.into();
+			let mut transactions = vec![tx.clone().into()];
+			let mut default_witness_commitment = None;
+			if segwit {
+				let child = chain::Transaction {
+					version: 1,
+					inputs: vec![chain::TransactionInput {
+						previous_output: chain::OutPoint { hash: tx.hash(), index: 0 },
+						script_sig: Default::default(),
+						sequence: 0xffffffff,
+						script_witness: vec![vec![0x01].into()],
+					}],
+					outputs: vec![chain::TransactionOutput::default()],
+					lock_time: 0,
+				};
+				transactions.push(child.into());
+				default_witness_commitment = Some(vec![0x6a, 0x01, 0x02].into());
+			}
 			miner::BlockTemplate {
 				version: 777,
 				previous_header_hash: SHA256D::from_str("0000000000000000000000000000000000000000000000000000000000000001").unwrap(),
 				time: 33,
 				bits: 44.into(),
 				height: 55,
-				transactions: vec![tx.into()],
+				transactions,
 				coinbase_value: 66,
 				size_limit: 77,
 				sigop_limit: 88,
+				weight_limit: 99,
+				default_witness_commitment,
 			}
 		}
 
+		fn verify_block_proposal(&self, _block: &IndexedBlock) -> Result<(), String> {
+			Ok(())
+		}
+
+		fn submit_block(&self, _block: IndexedBlock) -> Result<(), String> {
+			Ok(())
+		}
+
+		fn submit_header(&self, _header: IndexedBlockHeader) -> Result<(), String> {
+			Ok(())
+		}
+
 		fn get_mempool_info(&self) -> MemoryPoolInformation {
 			MemoryPoolInformation {
 				transactions_count: 500,
 				transactions_size_in_bytes: 50000,
+				max_size_in_bytes: 300_000_000,
+				min_fee_rate: 12_345,
+			}
+		}
+
+		fn get_mempool_entry(&self, hash: &SHA256D) -> Option<MempoolEntry> {
+			Some(MempoolEntry {
+				size: 100,
+				fee: 0.0001,
+				modifiedfee: 0.0002,
+				time: 1_600_000_000,
+				ancestorcount: 2,
+				descendantsize: 100,
+				depends: vec![*hash],
+				bip125_replaceable: true,
+			})
+		}
+
+		fn save_mempool(&self) -> Result<String, String> {
+			Ok("/data/mempool.dat".into())
+		}
+
+		fn highest_target_tracked(&self, horizon: EstimationHorizon) -> u32 {
+			match horizon {
+				EstimationHorizon::Short => 12,
+				EstimationHorizon::Medium => 48,
+				EstimationHorizon::Long => 1008,
 			}
 		}
+
+		fn estimate_smart_fee(&self, conf_target: u32, conservative: bool) -> SmartFeeEstimate {
+			SmartFeeEstimate {
+				fee_rate: Some(if conservative { 23_456 } else { 12_345 }),
+				blocks: conf_target,
+			}
+		}
+
+		fn estimate_raw_fee(&self, _conf_target: u32, _threshold: f64, horizon: EstimationHorizon) -> (Option<u64>, EstimationResult) {
+			let result = EstimationResult {
+				pass: Some(miner::EstimatorBucket {
+					start: 19_500.4,
+					end: 20_475.6,
+					within_target: 10.123,
+					total_confirmed: 11.5,
+					in_mempool: 0.0,
+					left_mempool: 0.25,
+				}),
+				fail: None,
+				decay: 0.5,
+				scale: self.highest_target_tracked(horizon) / 24,
+			};
+			(Some(20_000), result)
+		}
+
+		fn mining_info(&self) -> sync::MiningInformation {
+			sync::MiningInformation {
+				network: network::Network::Mainnet,
+				blocks: 101,
+				difficulty: 4.656542373906925e-10,
+				network_hash_rate: 0.5,
+				pooled_transactions: 3,
+				hashes_per_second: 1_234.5,
+				mining_threads: 4,
+			}
+		}
+	}
+
+	struct ErrorMinerClientCore;
+
+	impl MinerClientCoreApi for ErrorMinerClientCore {
+		fn get_block_template(&self, segwit: bool) -> miner::BlockTemplate {
+			SuccessMinerClientCore.get_block_template(segwit)
+		}
+
+		fn verify_block_proposal(&self, _block: &IndexedBlock) -> Result<(), String> {
+			Err("bad-txnmrklroot".into())
+		}
+
+		fn submit_block(&self, _block: IndexedBlock) -> Result<(), String> {
+			Err("high-hash".into())
+		}
+
+		fn submit_header(&self, header: IndexedBlockHeader) -> Result<(), String> {
+			Err(format!("Must submit previous header ({}) first", header.raw.previous_header_hash))
+		}
+
+		fn get_mempool_info(&self) -> MemoryPoolInformation {
+			SuccessMinerClientCore.get_mempool_info()
+		}
+
+		fn get_mempool_entry(&self, _hash: &SHA256D) -> Option<MempoolEntry> {
+			None
+		}
+
+		fn save_mempool(&self) -> Result<String, String> {
+			Err("Cannot write memory pool dump".into())
+		}
+
+		fn highest_target_tracked(&self, horizon: EstimationHorizon) -> u32 {
+			SuccessMinerClientCore.highest_target_tracked(horizon)
+		}
+
+		fn estimate_smart_fee(&self, conf_target: u32, _conservative: bool) -> SmartFeeEstimate {
+			SmartFeeEstimate {
+				fee_rate: None,
+				blocks: conf_target,
+			}
+		}
+
+		fn estimate_raw_fee(&self, conf_target: u32, threshold: f64, horizon: EstimationHorizon) -> (Option<u64>, EstimationResult) {
+			let (_, mut result) = SuccessMinerClientCore.estimate_raw_fee(conf_target, threshold, horizon);
+			result.fail = result.pass.take();
+			(None, result)
+		}
+
+		fn mining_info(&self) -> sync::MiningInformation {
+			SuccessMinerClientCore.mining_info()
+		}
 	}
 
 	#[test]
@@ -116,10 +451,41 @@ pub mod tests {
 		// but client expects reverse hash
 		assert_eq!(
 			&sample,
-			r#"{"jsonrpc":"2.0","result":{"bits":44,"coinbaseaux":null,"coinbasetxn":null,"coinbasevalue":66,"curtime":33,"height":55,"mintime":null,"mutable":null,"noncerange":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","rules":null,"sigoplimit":88,"sizelimit":77,"target":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","depends":null,"fee":null,"hash":null,"required":false,"sigops":null,"txid":null,"weight":null}],"vbavailable":null,"vbrequired":null,"version":777,"weightlimit":null},"id":1}"#
+			r#"{"jsonrpc":"2.0","result":{"bits":44,"coinbaseaux":null,"coinbasetxn":null,"coinbasevalue":66,"curtime":33,"default_witness_commitment":null,"height":55,"mintime":null,"mutable":null,"noncerange":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","rules":null,"sigoplimit":88,"sizelimit":77,"target":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","depends":[],"fee":null,"hash":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","required":false,"sigops":null,"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","weight":240}],"vbavailable":null,"vbrequired":null,"version":777,"weightlimit":null},"id":1}"#
 		);
 	}
 
+	#[test]
+	fn getblocktemplate_segwit_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{"rules": ["segwit"]}],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		let result = &sample["result"];
+		assert_eq!(result["rules"], serde_json::json!(["!segwit"]));
+		assert_eq!(result["weightlimit"], 99);
+		assert_eq!(result["default_witness_commitment"], "6a0102");
+		let transactions = result["transactions"].as_array().unwrap();
+		assert_eq!(transactions.len(), 2);
+		assert_eq!(transactions[0]["depends"], serde_json::json!([]));
+		assert_eq!(transactions[1]["depends"], serde_json::json!([1]));
+		// witness hash differs from the transaction id
+		assert_ne!(transactions[1]["txid"], transactions[1]["hash"]);
+	}
+
 	#[test]
 	fn getmempool_accepted() {
 		let client = MinerClient::new(SuccessMinerClientCore::default());
@@ -140,7 +506,221 @@ pub mod tests {
 
 		assert_eq!(
 			&sample,
-			r#"{"jsonrpc":"2.0","result":{"bytes":0,"maxmempool":0,"mempoolminfee":0,"minrelaytxfee":0,"size":500,"usage":50000},"id":1}"#
+			r#"{"jsonrpc":"2.0","result":{"bytes":0,"maxmempool":300000000,"mempoolminfee":0.00012345,"minrelaytxfee":0,"size":500,"usage":50000},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn savemempool_success() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(&(r#"{"jsonrpc": "2.0", "method": "savemempool", "params": [], "id": 1}"#))
+			.unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"filename":"/data/mempool.dat"},"id":1}"#);
+	}
+
+	#[test]
+	fn savemempool_error() {
+		let client = MinerClient::new(ErrorMinerClientCore);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(&(r#"{"jsonrpc": "2.0", "method": "savemempool", "params": [], "id": 1}"#))
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Cannot write memory pool dump\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getmempoolentry_success() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"{"jsonrpc": "2.0", "method": "getmempoolentry", "params": ["0000000000000000000000000000000000000000000000000000000000000001"], "id": 1}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"ancestorcount":2,"bip125-replaceable":true,"depends":["0000000000000000000000000000000000000000000000000000000000000001"],"descendantsize":100,"fee":0.0001,"modifiedfee":0.0002,"size":100,"time":1600000000},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getmempoolentry_not_found() {
+		let client = MinerClient::new(ErrorMinerClientCore);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"{"jsonrpc": "2.0", "method": "getmempoolentry", "params": ["0000000000000000000000000000000000000000000000000000000000000001"], "id": 1}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"0000000000000000000000000000000000000000000000000000000000000001"},"id":1}"#
+		);
+	}
+
+	const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+	const GENESIS_BLOCK: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+	fn handle_request<T: MinerClientCoreApi>(core: T, method: &str, params: &str) -> String {
+		let client = MinerClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+		handler
+			.handle_request_sync(&format!(
+				r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#,
+				method, params
+			))
+			.unwrap()
+	}
+
+	#[test]
+	fn getblocktemplate_proposal_accepted() {
+		let params = format!(r#"[{{"mode": "proposal", "data": "{}"}}]"#, GENESIS_BLOCK);
+		let sample = handle_request(SuccessMinerClientCore::default(), "getblocktemplate", &params);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn getblocktemplate_proposal_rejected() {
+		let params = format!(r#"[{{"mode": "proposal", "data": "{}"}}]"#, GENESIS_BLOCK);
+		let sample = handle_request(ErrorMinerClientCore, "getblocktemplate", &params);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"bad-txnmrklroot","id":1}"#);
+	}
+
+	#[test]
+	fn getblocktemplate_proposal_without_data() {
+		let sample = handle_request(SuccessMinerClientCore::default(), "getblocktemplate", r#"[{"mode": "proposal"}]"#);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: data","data":"\"Block data is required in proposal mode\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn submitblock_accepted() {
+		let params = format!(r#"["{}"]"#, GENESIS_BLOCK);
+		let sample = handle_request(SuccessMinerClientCore::default(), "submitblock", &params);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn submitblock_rejected() {
+		let params = format!(r#"["{}"]"#, GENESIS_BLOCK);
+		let sample = handle_request(ErrorMinerClientCore, "submitblock", &params);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"high-hash","id":1}"#);
+	}
+
+	#[test]
+	fn submitblock_decode_failed() {
+		let sample = handle_request(SuccessMinerClientCore::default(), "submitblock", r#"["0100"]"#);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: block","data":"UnexpectedEnd"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn submitheader_accepted() {
+		let params = format!(r#"["{}"]"#, GENESIS_HEADER);
+		let sample = handle_request(SuccessMinerClientCore::default(), "submitheader", &params);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn submitheader_rejected() {
+		let params = format!(r#"["{}"]"#, GENESIS_HEADER);
+		let sample = handle_request(ErrorMinerClientCore, "submitheader", &params);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Must submit previous header (0000000000000000000000000000000000000000000000000000000000000000) first\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn estimatesmartfee_success() {
+		let sample = handle_request(SuccessMinerClientCore::default(), "estimatesmartfee", "[6]");
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blocks":6,"feerate":0.00023456},"id":1}"#);
+
+		// estimate mode is case insensitive, target is limited with the maximal tracked target
+		let sample = handle_request(SuccessMinerClientCore::default(), "estimatesmartfee", r#"[2000, "ECONOMICAL"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blocks":1008,"feerate":0.00012345},"id":1}"#);
+	}
+
+	#[test]
+	fn estimatesmartfee_insufficient_data() {
+		let sample = handle_request(ErrorMinerClientCore, "estimatesmartfee", "[6]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"blocks":6,"errors":["Insufficient data or no feerate found"]},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn estimatesmartfee_invalid_params() {
+		let sample = handle_request(SuccessMinerClientCore::default(), "estimatesmartfee", "[0]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: conf_target","data":"\"Invalid conf_target, must be between 1 and 1008\""},"id":1}"#
+		);
+
+		let sample = handle_request(SuccessMinerClientCore::default(), "estimatesmartfee", r#"[6, "fast"]"#);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: estimate_mode","data":"\"Invalid estimate_mode parameter, must be one of: \\\"unset\\\", \\\"economical\\\", \\\"conservative\\\"\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn estimaterawfee_success() {
+		// short horizon doesn't track target of 24 blocks
+		let sample = handle_request(SuccessMinerClientCore::default(), "estimaterawfee", "[24]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"long":{"decay":0.5,"feerate":0.0002,"pass":{"endrange":20476.0,"inmempool":0.0,"leftmempool":0.25,"startrange":19500.0,"totalconfirmed":11.5,"withintarget":10.12},"scale":42},"medium":{"decay":0.5,"feerate":0.0002,"pass":{"endrange":20476.0,"inmempool":0.0,"leftmempool":0.25,"startrange":19500.0,"totalconfirmed":11.5,"withintarget":10.12},"scale":2}},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn estimaterawfee_insufficient_data() {
+		let sample = handle_request(ErrorMinerClientCore, "estimaterawfee", "[1000, 0.5]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"long":{"decay":0.5,"errors":["Insufficient data or no feerate found which meets threshold"],"fail":{"endrange":20476.0,"inmempool":0.0,"leftmempool":0.25,"startrange":19500.0,"totalconfirmed":11.5,"withintarget":10.12},"scale":42}},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn estimaterawfee_invalid_threshold() {
+		let sample = handle_request(SuccessMinerClientCore::default(), "estimaterawfee", "[6, 1.5]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: threshold","data":"\"Invalid threshold\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getmininginfo_success() {
+		let sample = handle_request(SuccessMinerClientCore::default(), "getmininginfo", "[]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"blocks":101,"chain":"main","difficulty":4.656542373906925e-10,"genproclimit":4,"hashespersec":1234.5,"networkhashps":0.5,"pooledtx":3,"warnings":""},"id":1}"#
 		);
 	}
 }
@@ -4,6 +4,7 @@ mod generate;
 mod miner;
 mod network;
 mod raw;
+mod wallet;
 
 pub use self::blockchain::{BlockChainClient, BlockChainClientCore};
 pub use self::control::{ControlClient, ControlClientCore};
@@ -11,3 +12,4 @@ pub use self::generate::{GenerateClient, GenerateClientCore};
 pub use self::miner::{MinerClient, MinerClientCore};
 pub use self::network::{NetworkClient, NetworkClientCore};
 pub use self::raw::{RawClient, RawClientCore};
+pub use self::wallet::{WalletClient, WalletClientCore};
@@ -1,21 +1,29 @@
 use crate::v1::helpers::errors;
 use crate::v1::traits::Network as NetworkRpc;
-use crate::v1::types::{AddNodeOperation, NodeInfo};
-use crate::v1::types::{NetworkInfo, Peer};
+use crate::v1::types::{AddNodeOperation, BannedSubnet, NodeInfo, SetBanOperation};
+use crate::v1::types::{NetworkInfo, NetworkInfoAddress, Peer};
 use jsonrpc_core::Error;
 use p2p;
-use std::net::{IpAddr, SocketAddr};
+use p2p::{BanEntry, NodeAddress, PeerId, Subnet};
+use primitives::time::{RealTime, Time};
+use std::net::IpAddr;
 use std::sync::Arc;
 
 pub trait NetworkApi: Send + Sync + 'static {
-	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
-	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
-	fn connect(&self, socket_addr: SocketAddr);
+	fn add_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError>;
+	fn remove_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError>;
+	fn connect(&self, node_addr: NodeAddress);
 	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError>;
 	fn nodes_info(&self) -> Vec<NodeInfo>;
 	fn peers_info(&self) -> Vec<Peer>;
 	fn connection_count(&self) -> usize;
 	fn network_info(&self) -> NetworkInfo;
+	fn ban(&self, subnet: Subnet, until: Option<u64>);
+	fn unban(&self, subnet: &Subnet) -> bool;
+	fn banned(&self) -> Vec<BanEntry>;
+	fn clear_banned(&self);
+	/// Disconnects peers with the given address or id. Returns false if there are no such peers.
+	fn disconnect(&self, address: Option<NodeAddress>, id: Option<PeerId>) -> bool;
 }
 
 impl<T> NetworkRpc for NetworkClient<T>
@@ -29,7 +37,7 @@ where
 	fn add_node(&self, node: String, operation: AddNodeOperation) -> Result<(), Error> {
 		let addr = node
 			.parse()
-			.map_err(|_| errors::invalid_params("node", "Invalid socket address format, should be ip:port (127.0.0.1:8008)"))?;
+			.map_err(|_| errors::invalid_params("node", "Invalid node address format, should be host:port (127.0.0.1:8008)"))?;
 		match operation {
 			AddNodeOperation::Add => self.api.add_node(addr).map_err(|_| errors::node_already_added()),
 			AddNodeOperation::Remove => self.api.remove_node(addr).map_err(|_| errors::node_not_added()),
@@ -60,6 +68,68 @@ where
 	fn peer_info(&self) -> Result<Vec<Peer>, Error> {
 		Ok(self.api.peers_info())
 	}
+
+	fn set_ban(&self, subnet: String, command: SetBanOperation, bantime: Option<u64>, absolute: Option<bool>) -> Result<(), Error> {
+		let subnet: Subnet = subnet.parse().map_err(|_| {
+			errors::invalid_params(
+				"subnet",
+				"Invalid IP/Subnet, should be ip address, subnet (10.0.0.0/8) or onion address",
+			)
+		})?;
+		match command {
+			SetBanOperation::Add => {
+				if self.api.banned().iter().any(|entry| entry.subnet == subnet) {
+					return Err(errors::subnet_already_banned());
+				}
+				let until = match bantime.unwrap_or(0) {
+					0 => None,
+					bantime if absolute.unwrap_or(false) => Some(bantime),
+					bantime => Some(RealTime.now().as_secs() + bantime),
+				};
+				self.api.ban(subnet, until);
+				Ok(())
+			}
+			SetBanOperation::Remove => {
+				if self.api.unban(&subnet) {
+					Ok(())
+				} else {
+					Err(errors::subnet_not_banned())
+				}
+			}
+		}
+	}
+
+	fn list_banned(&self) -> Result<Vec<BannedSubnet>, Error> {
+		let now = RealTime.now().as_secs();
+		Ok(self.api.banned().iter().map(|entry| BannedSubnet::new(entry, now)).collect())
+	}
+
+	fn clear_banned(&self) -> Result<(), Error> {
+		self.api.clear_banned();
+		Ok(())
+	}
+
+	fn disconnect_node(&self, address: Option<String>, nodeid: Option<usize>) -> Result<(), Error> {
+		let address = match address.filter(|address| !address.is_empty()) {
+			Some(address) => Some(
+				address
+					.parse()
+					.map_err(|_| errors::invalid_params("address", "Invalid node address format, should be host:port (127.0.0.1:8008)"))?,
+			),
+			None => None,
+		};
+		if address.is_some() == nodeid.is_some() {
+			return Err(errors::invalid_params(
+				"nodeid",
+				"Only one of address and nodeid should be provided",
+			));
+		}
+		if self.api.disconnect(address, nodeid) {
+			Ok(())
+		} else {
+			Err(errors::node_not_connected())
+		}
+	}
 }
 
 pub struct NetworkClient<T: NetworkApi> {
@@ -86,16 +156,16 @@ impl NetworkClientCore {
 }
 
 impl NetworkApi for NetworkClientCore {
-	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
-		self.p2p.add_node(socket_addr)
+	fn add_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+		self.p2p.add_node(node_addr)
 	}
 
-	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
-		self.p2p.remove_node(socket_addr)
+	fn remove_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+		self.p2p.remove_node(node_addr)
 	}
 
-	fn connect(&self, socket_addr: SocketAddr) {
-		p2p::Context::connect_normal(self.p2p.clone(), socket_addr);
+	fn connect(&self, node_addr: NodeAddress) {
+		p2p::Context::connect_normal(self.p2p.clone(), node_addr);
 	}
 
 	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError> {
@@ -103,7 +173,7 @@ impl NetworkApi for NetworkClientCore {
 			.p2p
 			.nodes()
 			.iter()
-			.find(|n| n.address().ip() == node_addr)
+			.find(|n| n.address().address.ip() == Some(node_addr))
 			.cloned()
 			.ok_or(p2p::NodeTableError::NoAddressInTable)?;
 
@@ -112,7 +182,7 @@ impl NetworkApi for NetworkClientCore {
 			.connections()
 			.info()
 			.into_iter()
-			.filter(|p| p.address == exact_node.address())
+			.filter(|p| exact_node.address() == p.address)
 			.collect();
 
 		Ok(NodeInfo {
@@ -129,7 +199,7 @@ impl NetworkApi for NetworkClientCore {
 			.nodes()
 			.iter()
 			.map(|n| {
-				let node_peers: Vec<p2p::PeerInfo> = peers.iter().filter(|p| p.address == n.address()).cloned().collect();
+				let node_peers: Vec<p2p::PeerInfo> = peers.iter().filter(|p| n.address() == p.address).cloned().collect();
 				NodeInfo {
 					addednode: format!("{}", n.address()),
 					connected: !node_peers.is_empty(),
@@ -140,7 +210,15 @@ impl NetworkApi for NetworkClientCore {
 	}
 
 	fn peers_info(&self) -> Vec<Peer> {
-		self.p2p.connections().info().iter().map(|p| p.into()).collect()
+		self.p2p
+			.connections()
+			.info()
+			.iter()
+			.map(|p| Peer {
+				banscore: self.p2p.misbehavior_score(&p.address.address),
+				..p.into()
+			})
+			.collect()
 	}
 
 	fn connection_count(&self) -> usize {
@@ -151,7 +229,16 @@ impl NetworkApi for NetworkClientCore {
 		NetworkInfo {
 			connections: self.p2p.connections().count(),
 			incrementalfee: 0,
-			localaddresses: Vec::new(),
+			localaddresses: self
+				.p2p
+				.local_addresses()
+				.into_iter()
+				.map(|address| NetworkInfoAddress {
+					address: address.address.to_string(),
+					port: address.port as usize,
+					score: 0,
+				})
+				.collect(),
 			localrelay: false,
 			localservices: "".to_string(),
 			networkactive: true,
@@ -164,4 +251,180 @@ impl NetworkApi for NetworkClientCore {
 			warnings: "".to_string(),
 		}
 	}
+
+	fn ban(&self, subnet: Subnet, until: Option<u64>) {
+		self.p2p.ban(subnet, until)
+	}
+
+	fn unban(&self, subnet: &Subnet) -> bool {
+		self.p2p.unban(subnet)
+	}
+
+	fn banned(&self) -> Vec<BanEntry> {
+		self.p2p.banned()
+	}
+
+	fn clear_banned(&self) {
+		self.p2p.clear_banned()
+	}
+
+	fn disconnect(&self, address: Option<NodeAddress>, id: Option<PeerId>) -> bool {
+		let peers: Vec<PeerId> = self
+			.p2p
+			.connections()
+			.info()
+			.into_iter()
+			.filter(|p| Some(p.address) == address || Some(p.id) == id)
+			.map(|p| p.id)
+			.collect();
+		for peer in &peers {
+			self.p2p.close_channel(*peer);
+		}
+		!peers.is_empty()
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use jsonrpc_core::IoHandler;
+	use std::sync::Mutex;
+
+	#[derive(Default)]
+	struct TestNetworkApi {
+		bans: Mutex<Vec<(Subnet, Option<u64>)>>,
+		disconnected: Mutex<Vec<(Option<NodeAddress>, Option<PeerId>)>>,
+	}
+
+	impl NetworkApi for TestNetworkApi {
+		fn add_node(&self, _node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+			unimplemented!()
+		}
+
+		fn remove_node(&self, _node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+			unimplemented!()
+		}
+
+		fn connect(&self, _node_addr: NodeAddress) {
+			unimplemented!()
+		}
+
+		fn node_info(&self, _node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError> {
+			unimplemented!()
+		}
+
+		fn nodes_info(&self) -> Vec<NodeInfo> {
+			unimplemented!()
+		}
+
+		fn peers_info(&self) -> Vec<Peer> {
+			unimplemented!()
+		}
+
+		fn connection_count(&self) -> usize {
+			unimplemented!()
+		}
+
+		fn network_info(&self) -> NetworkInfo {
+			unimplemented!()
+		}
+
+		fn ban(&self, subnet: Subnet, until: Option<u64>) {
+			self.bans.lock().unwrap().push((subnet, until));
+		}
+
+		fn unban(&self, subnet: &Subnet) -> bool {
+			let mut bans = self.bans.lock().unwrap();
+			let len = bans.len();
+			bans.retain(|(banned, _)| banned != subnet);
+			bans.len() != len
+		}
+
+		fn banned(&self) -> Vec<BanEntry> {
+			self.bans
+				.lock()
+				.unwrap()
+				.iter()
+				.map(|(subnet, until)| BanEntry {
+					subnet: *subnet,
+					created: 100,
+					until: until.unwrap_or(200),
+				})
+				.collect()
+		}
+
+		fn clear_banned(&self) {
+			self.bans.lock().unwrap().clear();
+		}
+
+		fn disconnect(&self, address: Option<NodeAddress>, id: Option<PeerId>) -> bool {
+			self.disconnected.lock().unwrap().push((address, id));
+			id != Some(0)
+		}
+	}
+
+	fn handle(handler: &IoHandler, method: &str, params: &str) -> String {
+		let request = format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params);
+		handler.handle_request_sync(&request).unwrap()
+	}
+
+	#[test]
+	fn test_set_ban() {
+		let client = NetworkClient::new(TestNetworkApi::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		assert_eq!(
+			handle(&handler, "setban", r#"["10.0.0.1/16", "add"]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "setban", r#"["10.0.0.0/16", "add", 1000]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32153,"message":"IP/Subnet already banned"},"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "setban", r#"["10.1.0.1", "add", 1000, true]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "setban", r#"["10.0.0.1/33", "add"]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: subnet","data":"\"Invalid IP/Subnet, should be ip address, subnet (10.0.0.0/8) or onion address\""},"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "listbanned", "[]"),
+			r#"{"jsonrpc":"2.0","result":[{"address":"10.0.0.0/16","ban_created":100,"ban_duration":100,"banned_until":200,"time_remaining":0},{"address":"10.1.0.1/32","ban_created":100,"ban_duration":900,"banned_until":1000,"time_remaining":0}],"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "setban", r#"["10.1.0.1", "remove"]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "setban", r#"["10.1.0.1", "remove"]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32154,"message":"IP/Subnet is not banned"},"id":1}"#
+		);
+		assert_eq!(handle(&handler, "clearbanned", "[]"), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+		assert_eq!(handle(&handler, "listbanned", "[]"), r#"{"jsonrpc":"2.0","result":[],"id":1}"#);
+	}
+
+	#[test]
+	fn test_disconnect_node() {
+		let client = NetworkClient::new(TestNetworkApi::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		assert_eq!(
+			handle(&handler, "disconnectnode", r#"["127.0.0.1:8333"]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "disconnectnode", r#"["", 1]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			handle(&handler, "disconnectnode", r#"[null, 0]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32152,"message":"Node not found in connected nodes"},"id":1}"#
+		);
+		assert!(handle(&handler, "disconnectnode", r#"["127.0.0.1:8333", 1]"#).contains("-32602"));
+		assert!(handle(&handler, "disconnectnode", "[]").contains("-32602"));
+	}
 }
@@ -1,19 +1,29 @@
-use crate::v1::helpers::errors::{execution, invalid_params, transaction_not_found, transaction_of_side_branch};
+use crate::v1::helpers::errors::{execution, invalid_params, transaction_not_found, transaction_of_side_branch, transaction_pruned};
 use crate::v1::traits::Raw;
 use crate::v1::types::{
-	GetRawTransactionResponse, RawTransaction, SignedTransactionInput, SignedTransactionOutput, Transaction, TransactionInput,
+	AnalyzePsbtInput, AnalyzePsbtMissing, AnalyzePsbtResponse, DecodedPsbt, DecodedPsbtInput, DecodedPsbtOutput, FinalizePsbtResponse,
+	GetRawTransactionResponse, PreviousTransactionOutput, PsbtBip32Derivation, PsbtScript, PsbtWitnessUtxo, PsbtXpub, RawTransaction,
+	SignRawTransactionError, SignRawTransactionResponse, SignedTransactionInput, SignedTransactionOutput, Transaction, TransactionInput,
 	TransactionInputScript, TransactionOutput, TransactionOutputScript, TransactionOutputs,
 };
-use bitcrypto::SHA256D;
-use chain::{IndexedTransaction as GlobalIndexedTransaction, Transaction as GlobalTransaction};
-use global_script::Script;
+use bitcrypto::{ToHex, SHA256D};
+use chain::constants::{MAX_BIP125_RBF_SEQUENCE, SATOSHIS_IN_COIN};
+use chain::{
+	IndexedTransaction as GlobalIndexedTransaction, OutPoint, Transaction as GlobalTransaction,
+	TransactionOutput as GlobalTransactionOutput,
+};
+use global_script::{verify_script, Script, SignatureVersion, TransactionInputSigner, TransactionSignatureChecker, VerificationFlags};
 use jsonrpc_core::Error;
-use keys::Address;
+use keys::{Address, Private};
 use network::{ConsensusParams, Network};
 use primitives::bytes::Bytes as GlobalBytes;
-use ser::{deserialize, serialize, Reader, Serializable, SERIALIZE_TRANSACTION_WITNESS};
-use storage;
+use psbt::{KeyOrigin, Psbt};
+use ser::{deserialize, serialize, serialize_with_flags, Reader, Serializable, SERIALIZE_TRANSACTION_WITNESS};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use storage::{self, TransactionOutputProvider};
 use sync;
+use wallet::Descriptor;
 
 pub struct RawClient<T: RawClientCoreApi> {
 	core: T,
@@ -29,6 +39,12 @@ pub trait RawClientCoreApi: Send + Sync + 'static {
 	) -> Result<GlobalTransaction, String>;
 	fn get_raw_transaction(&self, hash: SHA256D, verbose: bool) -> Result<GetRawTransactionResponse, Error>;
 	fn transaction_to_verbose_transaction(&self, transaction: GlobalIndexedTransaction) -> Transaction;
+	/// Returns transaction from the memory pool or the blockchain
+	fn transaction(&self, hash: &SHA256D) -> Option<GlobalTransaction>;
+	/// Returns output of the memory pool or the blockchain transaction
+	fn transaction_output(&self, outpoint: &OutPoint) -> Option<GlobalTransactionOutput>;
+	/// Returns network of the node
+	fn network(&self) -> Network;
 }
 
 pub struct RawClientCore {
@@ -82,10 +98,7 @@ impl RawClientCore {
 			.map(|output| match output {
 				TransactionOutput::Address(with_address) => {
 					let amount_in_satoshis = (with_address.amount * (chain::constants::SATOSHIS_IN_COIN as f64)) as u64;
-					let script = match with_address.address.kind {
-						keys::Type::P2PKH => ScriptBuilder::build_p2pkh(&with_address.address.hash),
-						keys::Type::P2SH => ScriptBuilder::build_p2sh(&with_address.address.hash),
-					};
+					let script = ScriptBuilder::build_address(&with_address.address);
 
 					chain::TransactionOutput {
 						value: amount_in_satoshis,
@@ -133,7 +146,27 @@ impl RawClientCoreApi for RawClientCore {
 	fn get_raw_transaction(&self, hash: SHA256D, verbose: bool) -> Result<GetRawTransactionResponse, Error> {
 		let transaction = match self.storage.transaction(&hash) {
 			Some(transaction) => transaction,
-			None => return Err(transaction_not_found(hash)),
+			None => {
+				// look for unconfirmed transaction in the memory pool
+				let memory_pool = self.local_sync_node.memory_pool();
+				let memory_pool = memory_pool.read();
+				return match memory_pool.get(&hash) {
+					Some(transaction) => {
+						let raw_transaction = RawTransaction::new(serialize(transaction).take());
+						if !verbose {
+							return Ok(GetRawTransactionResponse::Raw(raw_transaction));
+						}
+
+						let mut verbose_transaction =
+							self.transaction_to_verbose_transaction(GlobalIndexedTransaction::new(hash, transaction.clone()));
+						verbose_transaction.hex = Some(raw_transaction);
+						verbose_transaction.bip125_replaceable = Some(memory_pool.is_bip125_replaceable(&hash));
+						Ok(GetRawTransactionResponse::Verbose(verbose_transaction))
+					}
+					None if self.storage.pruned_height().is_some() => Err(transaction_pruned(hash)),
+					None => Err(transaction_not_found(hash)),
+				};
+			}
 		};
 
 		let transaction_bytes = serialize(&transaction.raw);
@@ -227,6 +260,7 @@ impl RawClientCoreApi for RawClientCore {
 								kind: address.kind,
 								network: match self.network {
 									Network::Mainnet => keys::Network::Mainnet,
+									Network::Regtest => keys::Network::Regtest,
 									_ => keys::Network::Testnet,
 								},
 							})
@@ -254,7 +288,33 @@ impl RawClientCoreApi for RawClientCore {
 			confirmations: None,
 			time: None,
 			blocktime: None,
+			bip125_replaceable: None,
+		}
+	}
+
+	fn transaction(&self, hash: &SHA256D) -> Option<GlobalTransaction> {
+		if let Some(transaction) = self.local_sync_node.memory_pool().read().get(hash) {
+			return Some(transaction.clone());
+		}
+		self.storage.transaction(hash).map(|transaction| transaction.raw)
+	}
+
+	fn network(&self) -> Network {
+		self.network
+	}
+
+	fn transaction_output(&self, outpoint: &OutPoint) -> Option<GlobalTransactionOutput> {
+		if let Some(output) = self
+			.local_sync_node
+			.memory_pool()
+			.read()
+			.transaction_output(outpoint, usize::max_value())
+		{
+			return Some(output);
 		}
+		self.storage
+			.as_transaction_output_provider()
+			.transaction_output(outpoint, usize::max_value())
 	}
 }
 
@@ -265,6 +325,120 @@ where
 	pub fn new(core: T) -> Self {
 		RawClient { core }
 	}
+
+	fn decode_psbt_input(&self, input: &psbt::Input, version: u32) -> DecodedPsbtInput {
+		let mut decoded = DecodedPsbtInput {
+			non_witness_utxo: input.non_witness_utxo.clone().map(|transaction| {
+				self.core
+					.transaction_to_verbose_transaction(GlobalIndexedTransaction::from_raw(transaction))
+			}),
+			witness_utxo: input.witness_utxo.as_ref().map(|output| PsbtWitnessUtxo {
+				amount: to_btc(output.value),
+				script_pubkey: psbt_script(&output.script_pubkey),
+			}),
+			partial_signatures: input
+				.partial_sigs
+				.iter()
+				.map(|(public, signature)| (public.to_hex(), signature.clone().into()))
+				.collect(),
+			sighash: input.sighash_type.map(sighash_name),
+			redeem_script: input.redeem_script.as_ref().map(psbt_script),
+			witness_script: input.witness_script.as_ref().map(psbt_script),
+			bip32_derivs: psbt_bip32_derivations(&input.bip32_derivation),
+			final_script_sig: input.final_script_sig.as_ref().map(|script_sig| TransactionInputScript {
+				asm: Script::from(script_sig.clone()).to_string(),
+				hex: script_sig.clone().into(),
+			}),
+			final_script_witness: input
+				.final_script_witness
+				.as_ref()
+				.map(|witness| witness.iter().map(|item| item.clone().into()).collect()),
+			taproot_key_path_sig: input.tap_key_sig.clone().map(Into::into),
+			taproot_internal_key: input.tap_internal_key.map(|key| key.to_vec().into()),
+			taproot_merkle_root: input.tap_merkle_root,
+			unknown: psbt_unknown(&input.unknown),
+			..Default::default()
+		};
+		if version >= 2 {
+			decoded.previous_txid = Some(input.previous_output.hash);
+			decoded.previous_vout = Some(input.previous_output.index);
+			decoded.sequence = input.sequence;
+			decoded.time_locktime = input.required_time_locktime;
+			decoded.height_locktime = input.required_height_locktime;
+		}
+		decoded
+	}
+}
+
+fn to_btc(satoshis: u64) -> f64 {
+	satoshis as f64 / SATOSHIS_IN_COIN as f64
+}
+
+fn parse_psbt(psbt: &str) -> Result<Psbt, Error> {
+	psbt.parse().map_err(|e| invalid_params("psbt", e))
+}
+
+/// Is previous output spent using witness: P2WPKH, P2WSH, P2SH-P2WPKH, P2SH-P2WSH or P2TR.
+fn is_witness_previous_output(previous: &PreviousTransactionOutput) -> bool {
+	let script_pubkey = Script::new(previous.script_pubkey.clone().to_vec().into());
+	if script_pubkey.parse_witness_program().is_some() {
+		return true;
+	}
+	script_pubkey.is_pay_to_script_hash()
+		&& (previous.witness_script.is_some()
+			|| previous.redeem_script.as_ref().map_or(false, |script| {
+				Script::new(script.clone().to_vec().into()).parse_witness_program().is_some()
+			}))
+}
+
+fn psbt_script(script: &GlobalBytes) -> PsbtScript {
+	let parsed = Script::from(script.clone());
+	PsbtScript {
+		asm: parsed.to_string(),
+		hex: script.clone().into(),
+		script_type: parsed.script_type().into(),
+	}
+}
+
+fn psbt_bip32_derivations(origins: &BTreeMap<GlobalBytes, KeyOrigin>) -> Vec<PsbtBip32Derivation> {
+	origins
+		.iter()
+		.map(|(public, origin)| PsbtBip32Derivation {
+			pubkey: public.clone().into(),
+			master_fingerprint: origin.fingerprint.to_hex(),
+			path: origin.path.to_string(),
+		})
+		.collect()
+}
+
+fn psbt_unknown(unknown: &BTreeMap<GlobalBytes, GlobalBytes>) -> BTreeMap<String, crate::v1::types::Bytes> {
+	unknown.iter().map(|(key, value)| (key.to_hex(), value.clone().into())).collect()
+}
+
+fn sighash_name(sighash: u32) -> String {
+	match sighash {
+		0 => "DEFAULT".into(),
+		1 => "ALL".into(),
+		2 => "NONE".into(),
+		3 => "SINGLE".into(),
+		0x81 => "ALL|ANYONECANPAY".into(),
+		0x82 => "NONE|ANYONECANPAY".into(),
+		0x83 => "SINGLE|ANYONECANPAY".into(),
+		sighash => sighash.to_string(),
+	}
+}
+
+fn parse_sighash(sighash: &str) -> Option<u32> {
+	match sighash {
+		"DEFAULT" => Some(0),
+		"ALL" => Some(1),
+		"NONE" => Some(2),
+		"SINGLE" => Some(3),
+		"ALL|ANYONECANPAY" => Some(0x81),
+		"NONE|ANYONECANPAY" => Some(0x82),
+		"SINGLE|ANYONECANPAY" => Some(0x83),
+		_ => None,
+	}
 }
 
 impl<T> Raw for RawClient<T>
@@ -283,7 +457,7 @@ where
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
-		outputs: TransactionOutputs,
+		mut outputs: TransactionOutputs,
 		lock_time: Option<u32>,
 	) -> Result<RawTransaction, Error> {
 		// reverse hashes of inputs
@@ -295,6 +469,19 @@ where
 			})
 			.collect();
 
+		// addresses of other networks are rejected
+		let network = match self.core.network() {
+			Network::Mainnet => keys::Network::Mainnet,
+			Network::Regtest => keys::Network::Regtest,
+			_ => keys::Network::Testnet,
+		};
+		for output in &mut outputs.outputs {
+			if let TransactionOutput::Address(ref mut with_address) = output {
+				with_address.address = Address::from_str_for_network(&with_address.address.to_string(), network)
+					.map_err(|e| invalid_params("outputs", e.to_string()))?;
+			}
+		}
+
 		let transaction = self
 			.core
 			.create_raw_transaction(inputs, outputs, lock_time)
@@ -313,6 +500,298 @@ where
 		let global_hash: SHA256D = hash.clone().into();
 		self.core.get_raw_transaction(global_hash, verbose.unwrap_or_default())
 	}
+
+	fn create_psbt(
+		&self,
+		inputs: Vec<TransactionInput>,
+		outputs: TransactionOutputs,
+		lock_time: Option<u32>,
+		replaceable: Option<bool>,
+		psbt_version: Option<u32>,
+	) -> Result<String, Error> {
+		let default_sequences: Vec<_> = inputs.iter().map(|input| input.sequence.is_none()).collect();
+		let mut transaction = self
+			.core
+			.create_raw_transaction(inputs, outputs, lock_time)
+			.map_err(|e| execution(e))?;
+		if replaceable.unwrap_or_default() {
+			for (input, _) in transaction.inputs.iter_mut().zip(default_sequences).filter(|(_, default)| *default) {
+				input.sequence = MAX_BIP125_RBF_SEQUENCE;
+			}
+		}
+
+		let psbt_version = psbt_version.unwrap_or_default();
+		// version 2 PSBT requires version 2 transaction
+		if psbt_version >= 2 && transaction.version < 2 {
+			transaction.version = 2;
+		}
+		let psbt = Psbt::from_unsigned_transaction(transaction, psbt_version).map_err(|e| invalid_params("psbt_version", e))?;
+		Ok(psbt.to_string())
+	}
+
+	fn decode_psbt(&self, psbt: String) -> Result<DecodedPsbt, Error> {
+		let psbt = parse_psbt(&psbt)?;
+		let mut decoded = DecodedPsbt {
+			tx: None,
+			global_xpubs: psbt
+				.xpubs
+				.iter()
+				.map(|(xpub, origin)| PsbtXpub {
+					xpub: xpub.to_string(),
+					master_fingerprint: origin.fingerprint.to_hex(),
+					path: origin.path.to_string(),
+				})
+				.collect(),
+			tx_version: None,
+			fallback_locktime: None,
+			input_count: None,
+			output_count: None,
+			tx_modifiable: None,
+			psbt_version: psbt.version,
+			unknown: psbt_unknown(&psbt.unknown),
+			inputs: psbt
+				.inputs
+				.iter()
+				.map(|input| self.decode_psbt_input(input, psbt.version))
+				.collect(),
+			outputs: psbt
+				.outputs
+				.iter()
+				.map(|output| DecodedPsbtOutput {
+					redeem_script: output.redeem_script.as_ref().map(psbt_script),
+					witness_script: output.witness_script.as_ref().map(psbt_script),
+					bip32_derivs: psbt_bip32_derivations(&output.bip32_derivation),
+					taproot_internal_key: output.tap_internal_key.map(|key| key.to_vec().into()),
+					amount: if psbt.version >= 2 { Some(to_btc(output.amount)) } else { None },
+					script: if psbt.version >= 2 {
+						Some(psbt_script(&output.script_pubkey))
+					} else {
+						None
+					},
+					unknown: psbt_unknown(&output.unknown),
+				})
+				.collect(),
+			fee: psbt.fee().map(to_btc),
+		};
+
+		if psbt.version == 0 {
+			let transaction = psbt.unsigned_transaction().map_err(|e| invalid_params("psbt", e))?;
+			decoded.tx = Some(
+				self.core
+					.transaction_to_verbose_transaction(GlobalIndexedTransaction::from_raw(transaction)),
+			);
+		} else {
+			decoded.tx_version = Some(psbt.tx_version);
+			decoded.fallback_locktime = psbt.fallback_locktime;
+			decoded.input_count = Some(psbt.inputs.len());
+			decoded.output_count = Some(psbt.outputs.len());
+			decoded.tx_modifiable = psbt.tx_modifiable;
+		}
+		Ok(decoded)
+	}
+
+	fn combine_psbt(&self, psbts: Vec<String>) -> Result<String, Error> {
+		let mut psbts = psbts.iter().map(|psbt| parse_psbt(psbt));
+		let mut combined = psbts
+			.next()
+			.ok_or_else(|| invalid_params("txs", "at least one PSBT is required"))??;
+		for psbt in psbts {
+			combined.combine(psbt?).map_err(|e| invalid_params("txs", e))?;
+		}
+		Ok(combined.to_string())
+	}
+
+	fn finalize_psbt(&self, psbt: String, extract: Option<bool>) -> Result<FinalizePsbtResponse, Error> {
+		let mut psbt = parse_psbt(&psbt)?;
+		let complete = psbt.finalize();
+		if complete && extract.unwrap_or(true) {
+			let transaction = psbt.extract().map_err(|e| execution(e))?;
+			return Ok(FinalizePsbtResponse {
+				psbt: None,
+				hex: Some(serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).into()),
+				complete,
+			});
+		}
+
+		Ok(FinalizePsbtResponse {
+			psbt: Some(psbt.to_string()),
+			hex: None,
+			complete,
+		})
+	}
+
+	fn analyze_psbt(&self, psbt: String) -> Result<AnalyzePsbtResponse, Error> {
+		let analysis = parse_psbt(&psbt)?.analyze();
+		Ok(AnalyzePsbtResponse {
+			inputs: analysis
+				.inputs
+				.into_iter()
+				.map(|input| AnalyzePsbtInput {
+					has_utxo: input.has_utxo,
+					is_final: input.is_final,
+					missing: input.missing.map(|missing| AnalyzePsbtMissing {
+						pubkeys: missing.pubkeys,
+						signatures: missing.signatures,
+						redeemscript: missing.redeem_script,
+						witnessscript: missing.witness_script,
+					}),
+					next: input.next.to_string(),
+				})
+				.collect(),
+			estimated_vsize: analysis.estimated_vsize,
+			estimated_feerate: analysis.estimated_fee_rate.map(to_btc),
+			fee: analysis.fee.map(to_btc),
+			next: analysis.next.to_string(),
+			error: analysis.error,
+		})
+	}
+
+	fn utxo_update_psbt(&self, psbt: String, descriptors: Option<Vec<String>>) -> Result<String, Error> {
+		let mut psbt = parse_psbt(&psbt)?;
+		let core = &self.core;
+		psbt.update_utxos(|outpoint| core.transaction_output(outpoint), |hash| core.transaction(hash));
+
+		for descriptor in descriptors.unwrap_or_default() {
+			let (descriptor, _) = Descriptor::parse(&descriptor).map_err(|e| invalid_params("descriptors", e))?;
+			let tap_internal_key = match descriptor {
+				Descriptor::Tr(ref key) => Some(key.xonly().map_err(|e| invalid_params("descriptors", e))?),
+				_ => None,
+			};
+			psbt.update_scripts(
+				&descriptor.script_pubkey().to_bytes(),
+				descriptor.redeem_script().map(|script| script.to_bytes()).as_ref(),
+				descriptor.witness_script().map(|script| script.to_bytes()).as_ref(),
+				tap_internal_key,
+			);
+		}
+		Ok(psbt.to_string())
+	}
+
+	fn sign_raw_transaction_with_key(
+		&self,
+		raw_transaction: RawTransaction,
+		private_keys: Vec<String>,
+		previous_outputs: Option<Vec<PreviousTransactionOutput>>,
+		sighash_type: Option<String>,
+	) -> Result<SignRawTransactionResponse, Error> {
+		let raw_transaction_data: Vec<u8> = raw_transaction.into();
+		let mut transaction: GlobalTransaction =
+			deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("hexstring", e))?;
+		let sighash_type = match sighash_type {
+			Some(sighash_type) => Some(parse_sighash(&sighash_type).ok_or_else(|| invalid_params("sighashtype", sighash_type))?),
+			None => None,
+		};
+		let private_keys = private_keys
+			.iter()
+			.map(|key| Private::from_str(key))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|e| invalid_params("privkeys", e))?;
+		// testnet and regtest keys have the same prefix
+		let mainnet = self.core.network() == Network::Mainnet;
+		if private_keys
+			.iter()
+			.any(|private| (private.network == keys::Network::Mainnet) != mainnet)
+		{
+			return Err(invalid_params("privkeys", keys::Error::InvalidNetwork));
+		}
+		let previous_outputs = previous_outputs.unwrap_or_default();
+		// witness signatures commit to the amount, so it can't be guessed
+		if previous_outputs
+			.iter()
+			.any(|previous| previous.amount.is_none() && is_witness_previous_output(previous))
+		{
+			return Err(invalid_params("prevtxs", "Missing amount"));
+		}
+
+		// signing is delegated to the PSBT signer and finalizer. Inputs, which are already signed, are kept as is
+		let mut psbt = Psbt::from_unsigned_transaction(transaction.clone(), 0).map_err(|e| execution(e))?;
+		for (input, transaction_input) in psbt.inputs.iter_mut().zip(&transaction.inputs) {
+			let previous = previous_outputs.iter().find(|previous| {
+				previous.txid == transaction_input.previous_output.hash && previous.vout == transaction_input.previous_output.index
+			});
+			input.witness_utxo = match previous {
+				Some(previous) => Some(GlobalTransactionOutput {
+					value: (previous.amount.unwrap_or_default() * SATOSHIS_IN_COIN as f64).round() as u64,
+					script_pubkey: previous.script_pubkey.clone().to_vec().into(),
+				}),
+				None => self.core.transaction_output(&transaction_input.previous_output),
+			};
+			input.redeem_script = previous
+				.and_then(|previous| previous.redeem_script.clone())
+				.map(|script| script.to_vec().into());
+			input.witness_script = previous
+				.and_then(|previous| previous.witness_script.clone())
+				.map(|script| script.to_vec().into());
+			input.sighash_type = sighash_type;
+			if !transaction_input.script_sig.is_empty() || !transaction_input.script_witness.is_empty() {
+				input.final_script_sig = Some(transaction_input.script_sig.clone());
+				input.final_script_witness = Some(transaction_input.script_witness.clone());
+			}
+		}
+		for private in &private_keys {
+			psbt.sign(private).map_err(|e| execution(e))?;
+		}
+		psbt.finalize();
+
+		for (transaction_input, input) in transaction.inputs.iter_mut().zip(&psbt.inputs) {
+			if input.is_finalized() {
+				transaction_input.script_sig = input.final_script_sig.clone().unwrap_or_default();
+				transaction_input.script_witness = input.final_script_witness.clone().unwrap_or_default();
+			}
+		}
+
+		let spent_outputs: Vec<_> = psbt.inputs.iter().map(|input| input.spent_output()).collect();
+		let mut signer = TransactionInputSigner::from(transaction.clone());
+		signer.spent_outputs = spent_outputs.iter().cloned().collect::<Option<_>>().unwrap_or_default();
+		let mut checker = TransactionSignatureChecker {
+			signer,
+			input_index: 0,
+			input_amount: 0,
+		};
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+			.verify_nulldummy(true)
+			.verify_cleanstack(true);
+
+		let mut errors = Vec::new();
+		for (index, (input, spent_output)) in transaction.inputs.iter().zip(spent_outputs).enumerate() {
+			let error = match spent_output {
+				Some(spent_output) => {
+					checker.input_index = index;
+					checker.input_amount = spent_output.value;
+					verify_script(
+						&Script::new(input.script_sig.clone()),
+						&Script::new(spent_output.script_pubkey),
+						&input.script_witness,
+						&flags,
+						&checker,
+						SignatureVersion::Base,
+					)
+					.err()
+					.map(|e| e.to_string())
+				}
+				None => Some("Input not found or already spent".into()),
+			};
+			if let Some(error) = error {
+				errors.push(SignRawTransactionError {
+					txid: input.previous_output.hash,
+					vout: input.previous_output.index,
+					witness: input.script_witness.iter().map(|item| item.clone().into()).collect(),
+					script_sig: input.script_sig.clone().into(),
+					sequence: input.sequence,
+					error,
+				});
+			}
+		}
+
+		Ok(SignRawTransactionResponse {
+			hex: serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).into(),
+			complete: errors.is_empty(),
+			errors,
+		})
+	}
 }
 
 #[cfg(test)]
@@ -404,6 +883,7 @@ pub mod tests {
 						confirmations: Some(197043),
 						time: Some(1289842148),
 						blocktime: Some(1289842148),
+						bip125_replaceable: None,
 					}
 				))
 			}
@@ -452,8 +932,24 @@ pub mod tests {
 				confirmations: None,
 				time: None,
 				blocktime: None,
+				bip125_replaceable: None,
 			}
 		}
+
+		fn transaction(&self, _hash: &SHA256D) -> Option<GlobalTransaction> {
+			None
+		}
+
+		fn transaction_output(&self, _outpoint: &OutPoint) -> Option<GlobalTransactionOutput> {
+			Some(GlobalTransactionOutput {
+				value: 100_000,
+				script_pubkey: "0014751e76e8199196d454941c45d1b3a323f1433bd6".into(),
+			})
+		}
+
+		fn network(&self) -> Network {
+			Network::Mainnet
+		}
 	}
 
 	impl RawClientCoreApi for ErrorRawClientCore {
@@ -527,8 +1023,21 @@ pub mod tests {
 				confirmations: None,
 				time: None,
 				blocktime: None,
+				bip125_replaceable: None,
 			}
 		}
+
+		fn transaction(&self, _hash: &SHA256D) -> Option<GlobalTransaction> {
+			None
+		}
+
+		fn transaction_output(&self, _outpoint: &OutPoint) -> Option<GlobalTransactionOutput> {
+			None
+		}
+
+		fn network(&self) -> Network {
+			Network::Mainnet
+		}
 	}
 
 	#[test]
@@ -605,6 +1114,39 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn createrawtransaction_segwit_outputs() {
+		let inputs: Vec<TransactionInput> =
+			serde_json::from_str(r#"[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}]"#).unwrap();
+		let outputs: TransactionOutputs = serde_json::from_str(
+			r#"{"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4":0.01,"bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3":0.02,"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0":0.03}"#,
+		)
+		.unwrap();
+
+		let transaction = RawClientCore::do_create_raw_transaction(inputs, outputs, None).unwrap();
+		let scripts: Vec<GlobalBytes> = transaction.outputs.iter().map(|output| output.script_pubkey.clone()).collect();
+		assert_eq!(
+			scripts,
+			vec![
+				"0014751e76e8199196d454941c45d1b3a323f1433bd6".into(),
+				"00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262".into(),
+				"512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into(),
+			]
+		);
+
+		let script: Script = transaction.outputs[2].script_pubkey.clone().into();
+		let destination = script.extract_destinations().unwrap().pop().unwrap();
+		let address = Address {
+			kind: destination.kind,
+			network: keys::Network::Mainnet,
+			hash: destination.hash,
+		};
+		assert_eq!(
+			address.to_string(),
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+		);
+	}
+
 	#[test]
 	fn createrawtransaction_error() {
 		let client = RawClient::new(ErrorRawClientCore::default());
@@ -629,6 +1171,30 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn createrawtransaction_other_network_address() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "createrawtransaction",
+				"params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx":0.01}],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: outputs","data":"\"Invalid Network\""},"id":1}"#,
+			&sample
+		);
+	}
+
 	#[test]
 	fn getrawtransaction_success() {
 		let client = RawClient::new(SuccessRawClientCore::default());
@@ -745,4 +1311,443 @@ pub mod tests {
 			&sample
 		);
 	}
+
+	fn unsigned_psbt(version: u32) -> Psbt {
+		let transaction = GlobalTransaction {
+			version: 2,
+			inputs: vec![chain::TransactionInput {
+				previous_output: OutPoint {
+					hash: SHA256D::from_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b").unwrap(),
+					index: 0,
+				},
+				script_sig: GlobalBytes::default(),
+				sequence: MAX_BIP125_RBF_SEQUENCE,
+				script_witness: Vec::new(),
+			}],
+			outputs: vec![GlobalTransactionOutput {
+				value: 90_000,
+				script_pubkey: "0014751e76e8199196d454941c45d1b3a323f1433bd6".into(),
+			}],
+			lock_time: 0,
+		};
+		Psbt::from_unsigned_transaction(transaction, version).unwrap()
+	}
+
+	/// PSBT, spending output of the generator point key
+	fn signed_psbt() -> Psbt {
+		let mut secret = [0u8; 32];
+		secret[31] = 1;
+		let private = keys::Private {
+			network: keys::Network::Mainnet,
+			secret,
+			compressed: true,
+		};
+		let mut psbt = unsigned_psbt(0);
+		psbt.inputs[0].witness_utxo = SuccessRawClientCore.transaction_output(&psbt.inputs[0].previous_output);
+		assert_eq!(psbt.sign(&private), Ok(1));
+		psbt
+	}
+
+	fn handle_request(client: RawClient<impl RawClientCoreApi>, method: &str, params: &str) -> String {
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+		handler
+			.handle_request_sync(&format!(
+				r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#,
+				method, params
+			))
+			.unwrap()
+	}
+
+	#[test]
+	fn createpsbt_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let sample = handle_request(
+			client,
+			"createpsbt",
+			r#"[[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01},0,true,2]"#,
+		);
+		let psbt: Psbt = serde_json::from_str::<serde_json::Value>(&sample).unwrap()["result"]
+			.as_str()
+			.unwrap()
+			.parse()
+			.unwrap();
+		assert_eq!(psbt.version, 2);
+		assert_eq!(psbt.tx_version, 2);
+		assert_eq!(psbt.inputs[0].sequence, Some(MAX_BIP125_RBF_SEQUENCE));
+		assert_eq!(psbt.inputs[0].final_script_sig, None);
+		assert_eq!(psbt.outputs[0].amount, 390_000);
+	}
+
+	#[test]
+	fn createpsbt_error() {
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"createpsbt",
+			r#"[[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01},0,false,1]"#,
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: psbt_version","data":"UnsupportedVersion(1)"},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn decodepsbt_success() {
+		let mut psbt = unsigned_psbt(2);
+		psbt.inputs[0].witness_utxo = SuccessRawClientCore.transaction_output(&psbt.inputs[0].previous_output);
+		psbt.inputs[0].sighash_type = Some(0x81);
+		psbt.outputs[0].unknown.insert("fc00".into(), "01".into());
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"decodepsbt",
+			&format!(r#"["{}"]"#, psbt),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","result":{"fallback_locktime":0,"fee":0.0001,"global_xpubs":[],"input_count":1,"inputs":[{"previous_txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","previous_vout":0,"sequence":4294967293,"sighash":"ALL|ANYONECANPAY","witness_utxo":{"amount":0.001,"scriptPubKey":{"asm":"OP_0 0x\nOP_PUSHBYTES_20 0x751e76e8199196d454941c45d1b3a323f1433bd6\n","hex":"0014751e76e8199196d454941c45d1b3a323f1433bd6","type":"witness_v0_keyhash"}}}],"output_count":1,"outputs":[{"amount":0.0009,"script":{"asm":"OP_0 0x\nOP_PUSHBYTES_20 0x751e76e8199196d454941c45d1b3a323f1433bd6\n","hex":"0014751e76e8199196d454941c45d1b3a323f1433bd6","type":"witness_v0_keyhash"},"unknown":{"fc00":"01"}}],"psbt_version":2,"tx_version":2,"unknown":{}},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn decodepsbt_error() {
+		let sample = handle_request(RawClient::new(SuccessRawClientCore::default()), "decodepsbt", r#"["cHNidP8!"]"#);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: psbt","data":"InvalidBase64"},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn combinepsbt_success() {
+		let unsigned = unsigned_psbt(0);
+		let signed = signed_psbt();
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"combinepsbt",
+			&format!(r#"[["{}", "{}"]]"#, unsigned, signed),
+		);
+		assert_eq!(format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, signed), sample);
+
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"combinepsbt",
+			&format!(r#"[["{}", "{}"]]"#, unsigned, unsigned_psbt(2)),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: txs","data":"DifferentTransactions"},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn finalizepsbt_success() {
+		let mut finalized = signed_psbt();
+		assert!(finalized.finalize());
+		let transaction = finalized.extract().unwrap();
+
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"finalizepsbt",
+			&format!(r#"["{}"]"#, signed_psbt()),
+		);
+		assert_eq!(
+			format!(
+				r#"{{"jsonrpc":"2.0","result":{{"complete":true,"hex":"{}"}},"id":1}}"#,
+				serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).to_hex()
+			),
+			sample
+		);
+
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"finalizepsbt",
+			&format!(r#"["{}", false]"#, signed_psbt()),
+		);
+		assert_eq!(
+			format!(r#"{{"jsonrpc":"2.0","result":{{"complete":true,"psbt":"{}"}},"id":1}}"#, finalized),
+			sample
+		);
+
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"finalizepsbt",
+			&format!(r#"["{}"]"#, unsigned_psbt(0)),
+		);
+		assert_eq!(
+			format!(
+				r#"{{"jsonrpc":"2.0","result":{{"complete":false,"psbt":"{}"}},"id":1}}"#,
+				unsigned_psbt(0)
+			),
+			sample
+		);
+	}
+
+	#[test]
+	fn analyzepsbt_success() {
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"analyzepsbt",
+			&format!(r#"["{}"]"#, unsigned_psbt(0)),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","result":{"inputs":[{"has_utxo":false,"is_final":false,"next":"updater"}],"next":"updater"},"id":1}"#,
+			&sample
+		);
+
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"analyzepsbt",
+			&format!(r#"["{}"]"#, signed_psbt()),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","result":{"estimated_feerate":0.00090909,"estimated_vsize":110,"fee":0.0001,"inputs":[{"has_utxo":true,"is_final":false,"next":"finalizer"}],"next":"finalizer"},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn utxoupdatepsbt_success() {
+		let descriptor = "wpkh(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)";
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"utxoupdatepsbt",
+			&format!(r#"["{}", ["{}"]]"#, unsigned_psbt(0), descriptor),
+		);
+		let psbt: Psbt = serde_json::from_str::<serde_json::Value>(&sample).unwrap()["result"]
+			.as_str()
+			.unwrap()
+			.parse()
+			.unwrap();
+		assert_eq!(
+			psbt.inputs[0].witness_utxo,
+			SuccessRawClientCore.transaction_output(&psbt.inputs[0].previous_output)
+		);
+
+		let sample = handle_request(
+			RawClient::new(ErrorRawClientCore::default()),
+			"utxoupdatepsbt",
+			&format!(r#"["{}", ["wpkh(00)"]]"#, unsigned_psbt(0)),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: descriptors","data":"InvalidDescriptor(\"invalid key '00'\")"},"id":1}"#,
+			&sample
+		);
+	}
+
+	fn private_key(secret_byte: u8) -> keys::Private {
+		let mut secret = [0u8; 32];
+		secret[31] = secret_byte;
+		keys::Private {
+			network: keys::Network::Mainnet,
+			secret,
+			compressed: true,
+		}
+	}
+
+	fn unsigned_transaction_hex() -> String {
+		serialize(&unsigned_psbt(0).unsigned_transaction().unwrap()).to_hex()
+	}
+
+	fn sign_raw_transaction(client: RawClient<impl RawClientCoreApi>, params: &str) -> serde_json::Value {
+		serde_json::from_str::<serde_json::Value>(&handle_request(client, "signrawtransactionwithkey", params)).unwrap()
+	}
+
+	#[test]
+	fn signrawtransactionwithkey_success() {
+		let result = sign_raw_transaction(
+			RawClient::new(SuccessRawClientCore::default()),
+			&format!(r#"["{}", ["{}"]]"#, unsigned_transaction_hex(), private_key(1)),
+		);
+		assert_eq!(result["result"]["complete"], true);
+		assert_eq!(result["result"]["errors"], serde_json::Value::Null);
+
+		let mut finalized = signed_psbt();
+		assert!(finalized.finalize());
+		let transaction = finalized.extract().unwrap();
+		assert_eq!(
+			result["result"]["hex"],
+			serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).to_hex()
+		);
+	}
+
+	#[test]
+	fn signrawtransactionwithkey_previous_outputs() {
+		use global_script::Builder;
+
+		let public = |secret_byte| keys::KeyPair::from_private(private_key(secret_byte)).unwrap().public().clone();
+		let multisig = Builder::default()
+			.push_opcode(global_script::Opcode::OP_2)
+			.push_data(&public(1))
+			.push_data(&public(2))
+			.push_opcode(global_script::Opcode::OP_2)
+			.push_opcode(global_script::Opcode::OP_CHECKMULTISIG)
+			.into_bytes();
+		let p2wpkh = Builder::build_p2wpkh(&bitcrypto::dhash160(&public(1))).to_bytes();
+		let cases = vec![
+			(Builder::build_p2pkh(&bitcrypto::dhash160(&public(1))).to_bytes(), None, None),
+			(
+				Builder::build_p2sh(&bitcrypto::dhash160(&multisig)).to_bytes(),
+				Some(multisig.clone()),
+				None,
+			),
+			(Builder::build_p2sh(&bitcrypto::dhash160(&p2wpkh)).to_bytes(), Some(p2wpkh), None),
+			(
+				Builder::build_p2wsh(&bitcrypto::sha256(&multisig)).to_bytes(),
+				None,
+				Some(multisig.clone()),
+			),
+		];
+
+		for (script_pubkey, redeem_script, witness_script) in cases {
+			let mut previous_output = serde_json::json!({
+				"txid": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+				"vout": 0,
+				"scriptPubKey": script_pubkey.to_hex(),
+				"amount": 0.001,
+			});
+			if let Some(redeem_script) = redeem_script {
+				previous_output["redeemScript"] = redeem_script.to_hex().into();
+			}
+			if let Some(witness_script) = witness_script {
+				previous_output["witnessScript"] = witness_script.to_hex().into();
+			}
+			let result = sign_raw_transaction(
+				RawClient::new(ErrorRawClientCore::default()),
+				&format!(
+					r#"["{}", ["{}", "{}"], [{}], "SINGLE|ANYONECANPAY"]"#,
+					unsigned_transaction_hex(),
+					private_key(1),
+					private_key(2),
+					previous_output
+				),
+			);
+			assert_eq!(
+				result["result"]["complete"],
+				true,
+				"{} is not signed: {}",
+				script_pubkey.to_hex(),
+				result
+			);
+		}
+	}
+
+	#[test]
+	fn signrawtransactionwithkey_incomplete() {
+		let result = sign_raw_transaction(
+			RawClient::new(SuccessRawClientCore::default()),
+			&format!(r#"["{}", ["{}"]]"#, unsigned_transaction_hex(), private_key(2)),
+		);
+		assert_eq!(
+			result["result"]["errors"],
+			serde_json::json!([{
+				"txid": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+				"vout": 0,
+				"witness": [],
+				"scriptSig": "",
+				"sequence": MAX_BIP125_RBF_SEQUENCE,
+				"error": "Witness program hash mismatch",
+			}])
+		);
+		assert_eq!(result["result"]["complete"], false);
+
+		let result = sign_raw_transaction(
+			RawClient::new(ErrorRawClientCore::default()),
+			&format!(r#"["{}", ["{}"]]"#, unsigned_transaction_hex(), private_key(1)),
+		);
+		assert_eq!(result["result"]["errors"][0]["error"], "Input not found or already spent");
+	}
+
+	#[test]
+	fn signrawtransactionwithkey_error() {
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"signrawtransactionwithkey",
+			&format!(r#"["{}", ["{}"], null, "ALL|NONE"]"#, unsigned_transaction_hex(), private_key(1)),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: sighashtype","data":"\"ALL|NONE\""},"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn signrawtransactionwithkey_missing_amount() {
+		use global_script::Builder;
+
+		let public = keys::KeyPair::from_private(private_key(1)).unwrap().public().clone();
+		let p2pkh = Builder::build_p2pkh(&bitcrypto::dhash160(&public)).to_bytes();
+		let p2wpkh = Builder::build_p2wpkh(&bitcrypto::dhash160(&public)).to_bytes();
+		let p2sh_p2wpkh = Builder::build_p2sh(&bitcrypto::dhash160(&p2wpkh)).to_bytes();
+		let p2wsh = Builder::build_p2wsh(&bitcrypto::sha256(&p2pkh)).to_bytes();
+		let p2sh_p2wsh = Builder::build_p2sh(&bitcrypto::dhash160(&p2wsh)).to_bytes();
+		let cases = vec![
+			(p2wpkh.clone(), None, None),
+			(p2sh_p2wpkh, Some(p2wpkh), None),
+			(p2wsh.clone(), None, Some(p2pkh.clone())),
+			(p2sh_p2wsh, Some(p2wsh), Some(p2pkh.clone())),
+		];
+
+		for (script_pubkey, redeem_script, witness_script) in cases {
+			let mut previous_output = serde_json::json!({
+				"txid": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+				"vout": 0,
+				"scriptPubKey": script_pubkey.to_hex(),
+			});
+			if let Some(redeem_script) = redeem_script {
+				previous_output["redeemScript"] = redeem_script.to_hex().into();
+			}
+			if let Some(witness_script) = witness_script {
+				previous_output["witnessScript"] = witness_script.to_hex().into();
+			}
+			let result = sign_raw_transaction(
+				RawClient::new(ErrorRawClientCore::default()),
+				&format!(
+					r#"["{}", ["{}"], [{}]]"#,
+					unsigned_transaction_hex(),
+					private_key(1),
+					previous_output
+				),
+			);
+			assert_eq!(
+				result["error"]["message"],
+				"Invalid parameter: prevtxs",
+				"{}",
+				script_pubkey.to_hex()
+			);
+			assert_eq!(result["error"]["data"], "\"Missing amount\"");
+		}
+
+		// amount isn't needed to sign non-witness outputs
+		let previous_output = serde_json::json!({
+			"txid": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+			"vout": 0,
+			"scriptPubKey": p2pkh.to_hex(),
+		});
+		let result = sign_raw_transaction(
+			RawClient::new(ErrorRawClientCore::default()),
+			&format!(
+				r#"["{}", ["{}"], [{}]]"#,
+				unsigned_transaction_hex(),
+				private_key(1),
+				previous_output
+			),
+		);
+		assert_eq!(result["result"]["complete"], true, "{}", result);
+	}
+
+	#[test]
+	fn signrawtransactionwithkey_wrong_network() {
+		let mut private = private_key(1);
+		private.network = keys::Network::Testnet;
+		let sample = handle_request(
+			RawClient::new(SuccessRawClientCore::default()),
+			"signrawtransactionwithkey",
+			&format!(r#"["{}", ["{}"]]"#, unsigned_transaction_hex(), private),
+		);
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: privkeys","data":"InvalidNetwork"},"id":1}"#,
+			&sample
+		);
+	}
 }
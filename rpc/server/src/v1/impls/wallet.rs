@@ -0,0 +1,621 @@
+use crate::v1::helpers::errors::{execution, invalid_params, wallet_error};
+use crate::v1::traits::Wallet as WalletRpc;
+use crate::v1::types::{ImportDescriptorRequest, ImportDescriptorResult, ImportTimestamp, ListTransactionsEntry, ListUnspentEntry};
+use bitcrypto::SHA256D;
+use chain::constants::SATOSHIS_IN_COIN;
+use jsonrpc_core::Error;
+use keys::Address;
+use std::sync::Arc;
+use std::time::Duration;
+use storage;
+use sync;
+use wallet::{AddressType, TransactionEntry, Unspent, Wallet};
+
+/// Fee rate (in satoshis per 1000 bytes) of wallet transactions, used when there are not enough data for estimation
+const FALLBACK_FEE_RATE: u64 = 20_000;
+/// Minimal fee rate (in satoshis per 1000 bytes) of wallet transactions
+const MIN_FEE_RATE: u64 = 1_000;
+/// Default confirmation target of wallet transactions
+const DEFAULT_CONF_TARGET: u32 = 6;
+/// Maximal time (in seconds) for which the wallet could be unlocked
+const MAX_UNLOCK_TIMEOUT: u64 = 100_000_000;
+
+pub struct WalletClient<T: WalletClientCoreApi> {
+	core: T,
+}
+
+pub trait WalletClientCoreApi: Send + Sync + 'static {
+	fn new_address(&self, label: String, address_type: AddressType) -> Result<Address, wallet::Error>;
+	fn balance(&self, min_confirmations: u32) -> u64;
+	fn unspent(&self, min_confirmations: u32, max_confirmations: u32) -> Vec<Unspent>;
+	fn fee_rate(&self, conf_target: u32) -> u64;
+	fn send_to_address(
+		&self,
+		address: &Address,
+		amount: u64,
+		fee_rate: u64,
+		subtract_fee: bool,
+		replaceable: bool,
+	) -> Result<SHA256D, Error>;
+	fn transactions(&self, label: Option<&str>, count: usize, skip: usize) -> Vec<TransactionEntry>;
+	fn unlock(&self, passphrase: &str, timeout: Duration) -> Result<(), wallet::Error>;
+	fn lock(&self) -> Result<(), wallet::Error>;
+	fn encrypt(&self, passphrase: &str) -> Result<(), wallet::Error>;
+	fn import_descriptor(&self, descriptor: &str, label: String, internal: bool, timestamp: Option<u32>) -> Result<(), wallet::Error>;
+}
+
+pub struct WalletClientCore {
+	wallet: Arc<Wallet>,
+	local_sync_node: sync::LocalNodeRef,
+	storage: storage::SharedStore,
+}
+
+impl WalletClientCore {
+	pub fn new(wallet: Arc<Wallet>, local_sync_node: sync::LocalNodeRef, storage: storage::SharedStore) -> Self {
+		WalletClientCore {
+			wallet,
+			local_sync_node,
+			storage,
+		}
+	}
+}
+
+impl WalletClientCoreApi for WalletClientCore {
+	fn new_address(&self, label: String, address_type: AddressType) -> Result<Address, wallet::Error> {
+		self.wallet.new_address(label, address_type)
+	}
+
+	fn balance(&self, min_confirmations: u32) -> u64 {
+		self.wallet.balance(&self.local_sync_node.memory_pool().read(), min_confirmations)
+	}
+
+	fn unspent(&self, min_confirmations: u32, max_confirmations: u32) -> Vec<Unspent> {
+		self.wallet
+			.unspent(&self.local_sync_node.memory_pool().read(), min_confirmations, max_confirmations)
+	}
+
+	fn fee_rate(&self, conf_target: u32) -> u64 {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		let estimate = memory_pool.fee_estimator().estimate_smart_fee(conf_target, true);
+		estimate
+			.fee_rate
+			.unwrap_or(FALLBACK_FEE_RATE)
+			.max(memory_pool.min_fee_rate())
+			.max(MIN_FEE_RATE)
+	}
+
+	fn send_to_address(
+		&self,
+		address: &Address,
+		amount: u64,
+		fee_rate: u64,
+		subtract_fee: bool,
+		replaceable: bool,
+	) -> Result<SHA256D, Error> {
+		let transaction = {
+			let memory_pool = self.local_sync_node.memory_pool();
+			let memory_pool = memory_pool.read();
+			self.wallet
+				.create_transaction(&memory_pool, address, amount, fee_rate, subtract_fee, replaceable)
+				.map_err(wallet_error)?
+		};
+		// memory pool lock must be released before the transaction is verified
+		self.local_sync_node.accept_transaction(transaction).map_err(execution)
+	}
+
+	fn transactions(&self, label: Option<&str>, count: usize, skip: usize) -> Vec<TransactionEntry> {
+		self.wallet
+			.transactions(&self.local_sync_node.memory_pool().read(), label, count, skip)
+	}
+
+	fn unlock(&self, passphrase: &str, timeout: Duration) -> Result<(), wallet::Error> {
+		self.wallet.unlock(passphrase, timeout)
+	}
+
+	fn lock(&self) -> Result<(), wallet::Error> {
+		self.wallet.lock()
+	}
+
+	fn encrypt(&self, passphrase: &str) -> Result<(), wallet::Error> {
+		self.wallet.encrypt(passphrase)
+	}
+
+	fn import_descriptor(&self, descriptor: &str, label: String, internal: bool, timestamp: Option<u32>) -> Result<(), wallet::Error> {
+		self.wallet.import_descriptor(descriptor, label, internal)?;
+		match timestamp {
+			Some(timestamp) => self.wallet.rescan(self.storage.as_store(), timestamp),
+			None => Ok(()),
+		}
+	}
+}
+
+impl<T> WalletClient<T>
+where
+	T: WalletClientCoreApi,
+{
+	pub fn new(core: T) -> Self {
+		WalletClient { core }
+	}
+}
+
+impl<T> WalletRpc for WalletClient<T>
+where
+	T: WalletClientCoreApi,
+{
+	fn new_address(&self, label: Option<String>, address_type: Option<String>) -> Result<String, Error> {
+		let address_type = match address_type {
+			Some(address_type) => address_type.parse().map_err(|err| invalid_params("address_type", err))?,
+			None => AddressType::Bech32,
+		};
+		self.core
+			.new_address(label.unwrap_or_default(), address_type)
+			.map(|address| address.to_string())
+			.map_err(wallet_error)
+	}
+
+	fn balance(&self, dummy: Option<String>, minconf: Option<u32>) -> Result<f64, Error> {
+		if dummy.map_or(false, |dummy| dummy != "*") {
+			return Err(invalid_params("dummy", "dummy first argument must be excluded or set to \"*\""));
+		}
+		Ok(self.core.balance(minconf.unwrap_or(0)) as f64 / SATOSHIS_IN_COIN as f64)
+	}
+
+	fn list_unspent(&self, minconf: Option<u32>, maxconf: Option<u32>) -> Result<Vec<ListUnspentEntry>, Error> {
+		Ok(self
+			.core
+			.unspent(minconf.unwrap_or(1), maxconf.unwrap_or(9_999_999))
+			.into_iter()
+			.map(|unspent| ListUnspentEntry {
+				txid: unspent.outpoint.hash,
+				vout: unspent.outpoint.index,
+				address: unspent.address,
+				label: unspent.label,
+				script_pubkey: unspent.output.script_pubkey.into(),
+				amount: unspent.output.value as f64 / SATOSHIS_IN_COIN as f64,
+				confirmations: unspent.confirmations,
+				spendable: unspent.spendable,
+				solvable: true,
+				desc: unspent.descriptor.to_string(),
+				safe: unspent.safe,
+			})
+			.collect())
+	}
+
+	fn send_to_address(
+		&self,
+		address: String,
+		amount: f64,
+		_comment: Option<String>,
+		_comment_to: Option<String>,
+		subtract_fee_from_amount: Option<bool>,
+		replaceable: Option<bool>,
+		conf_target: Option<u32>,
+	) -> Result<SHA256D, Error> {
+		let address: Address = address.parse().map_err(|err| invalid_params("address", err))?;
+		if !amount.is_finite() || amount <= 0.0 {
+			return Err(invalid_params("amount", "Amount must be positive"));
+		}
+		let amount = (amount * SATOSHIS_IN_COIN as f64).round() as u64;
+		let conf_target = conf_target.unwrap_or(DEFAULT_CONF_TARGET);
+		if conf_target < 1 {
+			return Err(invalid_params("conf_target", "Invalid conf_target, must be at least 1"));
+		}
+
+		let fee_rate = self.core.fee_rate(conf_target);
+		self.core.send_to_address(
+			&address,
+			amount,
+			fee_rate,
+			subtract_fee_from_amount.unwrap_or(false),
+			replaceable.unwrap_or(true),
+		)
+	}
+
+	fn list_transactions(
+		&self,
+		label: Option<String>,
+		count: Option<usize>,
+		skip: Option<usize>,
+	) -> Result<Vec<ListTransactionsEntry>, Error> {
+		let label = label.filter(|label| label != "*");
+		Ok(self
+			.core
+			.transactions(label.as_ref().map(String::as_str), count.unwrap_or(10), skip.unwrap_or(0))
+			.into_iter()
+			.map(|entry| ListTransactionsEntry {
+				address: entry.address,
+				category: entry.category.as_str().into(),
+				amount: entry.amount as f64 / SATOSHIS_IN_COIN as f64,
+				label: entry.label,
+				vout: entry.vout,
+				fee: entry.fee.map(|fee| fee as f64 / SATOSHIS_IN_COIN as f64),
+				confirmations: entry.confirmations,
+				blockhash: entry.block.map(|block| block.hash),
+				blockheight: entry.block.map(|block| block.height),
+				blocktime: entry.block.map(|block| block.time),
+				txid: entry.txid,
+				time: entry.time,
+			})
+			.collect())
+	}
+
+	fn wallet_passphrase(&self, passphrase: String, timeout: u64) -> Result<(), Error> {
+		if passphrase.is_empty() {
+			return Err(invalid_params("passphrase", "Passphrase can not be empty"));
+		}
+		let timeout = Duration::from_secs(timeout.min(MAX_UNLOCK_TIMEOUT));
+		self.core.unlock(&passphrase, timeout).map_err(wallet_error)
+	}
+
+	fn wallet_lock(&self) -> Result<(), Error> {
+		self.core.lock().map_err(wallet_error)
+	}
+
+	fn encrypt_wallet(&self, passphrase: String) -> Result<(), Error> {
+		if passphrase.is_empty() {
+			return Err(invalid_params("passphrase", "Passphrase can not be empty"));
+		}
+		self.core.encrypt(&passphrase).map_err(wallet_error)
+	}
+
+	fn import_descriptors(&self, requests: Vec<ImportDescriptorRequest>) -> Result<Vec<ImportDescriptorResult>, Error> {
+		Ok(requests
+			.into_iter()
+			.map(|request| {
+				let timestamp = match request.timestamp {
+					ImportTimestamp::Now => None,
+					ImportTimestamp::Time(time) => Some(time),
+				};
+				match self
+					.core
+					.import_descriptor(&request.desc, request.label, request.internal, timestamp)
+				{
+					Ok(()) => ImportDescriptorResult {
+						success: true,
+						error: None,
+					},
+					Err(err) => ImportDescriptorResult {
+						success: false,
+						error: Some(err.to_string()),
+					},
+				}
+			})
+			.collect())
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use bitcrypto::FromStr;
+	use chain::{OutPoint, TransactionOutput};
+	use jsonrpc_core::IoHandler;
+	use keys::Network;
+	use wallet::{BlockPosition, Category, Descriptor};
+
+	const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+	fn descriptor() -> Descriptor {
+		"wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"
+			.parse()
+			.unwrap()
+	}
+
+	#[derive(Default)]
+	struct SuccessWalletClientCore;
+
+	impl WalletClientCoreApi for SuccessWalletClientCore {
+		fn new_address(&self, _label: String, address_type: AddressType) -> Result<Address, wallet::Error> {
+			let descriptor = match address_type {
+				AddressType::Legacy => "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)"
+					.parse()
+					.unwrap(),
+				_ => descriptor(),
+			};
+			Ok(descriptor.address(Network::Mainnet))
+		}
+
+		fn balance(&self, min_confirmations: u32) -> u64 {
+			100_000_000 - u64::from(min_confirmations) * 10_000_000
+		}
+
+		fn unspent(&self, min_confirmations: u32, _max_confirmations: u32) -> Vec<Unspent> {
+			vec![Unspent {
+				outpoint: OutPoint {
+					hash: SHA256D::from_str(TXID).unwrap(),
+					index: 1,
+				},
+				output: TransactionOutput {
+					value: 150_000,
+					script_pubkey: descriptor().script_pubkey().to_bytes(),
+				},
+				address: descriptor().address(Network::Mainnet),
+				label: "savings".into(),
+				descriptor: descriptor(),
+				confirmations: min_confirmations,
+				spendable: true,
+				safe: true,
+			}]
+		}
+
+		fn fee_rate(&self, conf_target: u32) -> u64 {
+			conf_target as u64 * 1_000
+		}
+
+		fn send_to_address(
+			&self,
+			address: &Address,
+			amount: u64,
+			fee_rate: u64,
+			subtract_fee: bool,
+			replaceable: bool,
+		) -> Result<SHA256D, Error> {
+			assert_eq!(address.to_string(), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+			assert_eq!(amount, 10_000_000);
+			assert_eq!(fee_rate, 6_000);
+			assert!(!subtract_fee && replaceable);
+			Ok(SHA256D::from_str(TXID).unwrap())
+		}
+
+		fn transactions(&self, label: Option<&str>, count: usize, skip: usize) -> Vec<TransactionEntry> {
+			assert_eq!((label, count, skip), (None, 10, 0));
+			let txid = SHA256D::from_str(TXID).unwrap();
+			vec![
+				TransactionEntry {
+					txid,
+					category: Category::Receive,
+					address: Some(descriptor().address(Network::Mainnet)),
+					label: Some("savings".into()),
+					amount: 150_000,
+					vout: 1,
+					fee: None,
+					confirmations: 2,
+					block: Some(BlockPosition {
+						height: 100,
+						hash: txid,
+						time: 1_600_000_000,
+					}),
+					time: 1_600_000_000,
+				},
+				TransactionEntry {
+					txid,
+					category: Category::Send,
+					address: None,
+					label: None,
+					amount: -50_000,
+					vout: 0,
+					fee: Some(-1_000),
+					confirmations: 0,
+					block: None,
+					time: 1_600_000_100,
+				},
+			]
+		}
+
+		fn unlock(&self, _passphrase: &str, _timeout: Duration) -> Result<(), wallet::Error> {
+			Ok(())
+		}
+
+		fn lock(&self) -> Result<(), wallet::Error> {
+			Ok(())
+		}
+
+		fn encrypt(&self, _passphrase: &str) -> Result<(), wallet::Error> {
+			Ok(())
+		}
+
+		fn import_descriptor(
+			&self,
+			descriptor: &str,
+			_label: String,
+			_internal: bool,
+			_timestamp: Option<u32>,
+		) -> Result<(), wallet::Error> {
+			descriptor.parse::<Descriptor>().map(|_| ())
+		}
+	}
+
+	struct ErrorWalletClientCore;
+
+	impl WalletClientCoreApi for ErrorWalletClientCore {
+		fn new_address(&self, _label: String, _address_type: AddressType) -> Result<Address, wallet::Error> {
+			Err(wallet::Error::Io("cannot write wallet.json".into()))
+		}
+
+		fn balance(&self, _min_confirmations: u32) -> u64 {
+			0
+		}
+
+		fn unspent(&self, _min_confirmations: u32, _max_confirmations: u32) -> Vec<Unspent> {
+			Vec::new()
+		}
+
+		fn fee_rate(&self, _conf_target: u32) -> u64 {
+			FALLBACK_FEE_RATE
+		}
+
+		fn send_to_address(
+			&self,
+			_address: &Address,
+			_amount: u64,
+			_fee_rate: u64,
+			_subtract_fee: bool,
+			_replaceable: bool,
+		) -> Result<SHA256D, Error> {
+			Err(wallet_error(wallet::Error::Locked))
+		}
+
+		fn transactions(&self, _label: Option<&str>, _count: usize, _skip: usize) -> Vec<TransactionEntry> {
+			Vec::new()
+		}
+
+		fn unlock(&self, _passphrase: &str, _timeout: Duration) -> Result<(), wallet::Error> {
+			Err(wallet::Error::IncorrectPassphrase)
+		}
+
+		fn lock(&self) -> Result<(), wallet::Error> {
+			Err(wallet::Error::NotEncrypted)
+		}
+
+		fn encrypt(&self, _passphrase: &str) -> Result<(), wallet::Error> {
+			Err(wallet::Error::AlreadyEncrypted)
+		}
+
+		fn import_descriptor(
+			&self,
+			_descriptor: &str,
+			_label: String,
+			_internal: bool,
+			_timestamp: Option<u32>,
+		) -> Result<(), wallet::Error> {
+			Err(wallet::Error::Locked)
+		}
+	}
+
+	fn handle_request<T: WalletClientCoreApi>(core: T, method: &str, params: &str) -> String {
+		let client = WalletClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+		handler
+			.handle_request_sync(&format!(
+				r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#,
+				method, params
+			))
+			.unwrap()
+	}
+
+	#[test]
+	fn getnewaddress_success() {
+		let sample = handle_request(SuccessWalletClientCore::default(), "getnewaddress", "[]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":"bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg","id":1}"#
+		);
+
+		let sample = handle_request(SuccessWalletClientCore::default(), "getnewaddress", r#"["", "legacy"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"1cMh228HTCiwS8ZsaakH8A8wze1JR5ZsP","id":1}"#);
+	}
+
+	#[test]
+	fn getnewaddress_error() {
+		let sample = handle_request(SuccessWalletClientCore::default(), "getnewaddress", r#"["", "p2wpkh"]"#);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: address_type","data":"InvalidAddressType(\"p2wpkh\")"},"id":1}"#
+		);
+
+		let sample = handle_request(ErrorWalletClientCore, "getnewaddress", "[]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32160,"message":"Wallet file error: cannot write wallet.json"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getbalance_success() {
+		let sample = handle_request(SuccessWalletClientCore::default(), "getbalance", "[]");
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1.0,"id":1}"#);
+
+		let sample = handle_request(SuccessWalletClientCore::default(), "getbalance", r#"["*", 6]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":0.4,"id":1}"#);
+
+		let sample = handle_request(SuccessWalletClientCore::default(), "getbalance", r#"["savings"]"#);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: dummy","data":"\"dummy first argument must be excluded or set to \\\"*\\\"\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn listunspent_success() {
+		let sample = handle_request(SuccessWalletClientCore::default(), "listunspent", "[]");
+		assert_eq!(
+			sample,
+			format!(
+				r#"{{"jsonrpc":"2.0","result":[{{"address":"bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg","amount":0.0015,"confirmations":1,"desc":"{}","label":"savings","safe":true,"scriptPubKey":"00147dd65592d0ab2fe0d0257d571abf032cd9db93dc","solvable":true,"spendable":true,"txid":"{}","vout":1}}],"id":1}}"#,
+				descriptor(),
+				TXID
+			)
+		);
+	}
+
+	#[test]
+	fn sendtoaddress_success() {
+		let sample = handle_request(
+			SuccessWalletClientCore::default(),
+			"sendtoaddress",
+			r#"["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", 0.1]"#,
+		);
+		assert_eq!(&sample, &format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, TXID));
+	}
+
+	#[test]
+	fn sendtoaddress_error() {
+		let sample = handle_request(
+			ErrorWalletClientCore,
+			"sendtoaddress",
+			r#"["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", 0.1]"#,
+		);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32162,"message":"Please enter the wallet passphrase with walletpassphrase first"},"id":1}"#
+		);
+
+		let sample = handle_request(
+			ErrorWalletClientCore,
+			"sendtoaddress",
+			r#"["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", -0.1]"#,
+		);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: amount","data":"\"Amount must be positive\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn listtransactions_success() {
+		let sample = handle_request(SuccessWalletClientCore::default(), "listtransactions", r#"["*"]"#);
+		assert_eq!(
+			sample,
+			format!(
+				r#"{{"jsonrpc":"2.0","result":[{{"address":"bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg","amount":0.0015,"blockhash":"{0}","blockheight":100,"blocktime":1600000000,"category":"receive","confirmations":2,"label":"savings","time":1600000000,"txid":"{0}","vout":1}},{{"amount":-0.0005,"category":"send","confirmations":0,"fee":-0.00001,"time":1600000100,"txid":"{0}","vout":0}}],"id":1}}"#,
+				TXID
+			)
+		);
+	}
+
+	#[test]
+	fn walletpassphrase_success() {
+		let sample = handle_request(SuccessWalletClientCore::default(), "walletpassphrase", r#"["passphrase", 60]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn walletpassphrase_error() {
+		let sample = handle_request(ErrorWalletClientCore, "walletpassphrase", r#"["passphrase", 60]"#);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32163,"message":"The wallet passphrase entered was incorrect"},"id":1}"#
+		);
+
+		let sample = handle_request(ErrorWalletClientCore, "walletlock", "[]");
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32164,"message":"Wallet is not encrypted"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn importdescriptors_success() {
+		let sample = handle_request(
+			SuccessWalletClientCore::default(),
+			"importdescriptors",
+			r#"[[{"desc":"wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)","timestamp":"now"},{"desc":"wpkh(0)","timestamp":0}]]"#,
+		);
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":[{"success":true},{"error":"Invalid descriptor: invalid key '0'","success":false}],"id":1}"#
+		);
+	}
+}
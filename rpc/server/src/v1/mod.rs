@@ -10,9 +10,11 @@ pub use self::impls::{GenerateClient, GenerateClientCore};
 pub use self::impls::{MinerClient, MinerClientCore};
 pub use self::impls::{NetworkClient, NetworkClientCore};
 pub use self::impls::{RawClient, RawClientCore};
+pub use self::impls::{WalletClient, WalletClientCore};
 pub use self::traits::BlockChain;
 pub use self::traits::Control;
 pub use self::traits::Generate;
 pub use self::traits::Miner;
 pub use self::traits::Network;
 pub use self::traits::Raw;
+pub use self::traits::Wallet;
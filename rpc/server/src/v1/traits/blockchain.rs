@@ -1,6 +1,10 @@
+use crate::v1::types::DumpTxOutSetResponse;
+use crate::v1::types::GetBlockFilterResponse;
 use crate::v1::types::GetBlockResponse;
+use crate::v1::types::GetDeploymentInfoResponse;
 use crate::v1::types::GetTxOutResponse;
 use crate::v1::types::GetTxOutSetInfoResponse;
+use crate::v1::types::{AddressBalance, AddressIndexRequest, AddressUtxo};
 use crate::v1::types::{BlockchainInfo, ChainTxStats};
 use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
@@ -45,4 +49,28 @@ pub trait BlockChain {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getchaintxstats", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getchaintxstats")]
 	fn chain_tx_stats(&self, nblocks: Option<usize>, blockhash: Option<String>) -> Result<ChainTxStats, Error>;
+	/// Get state of the soft fork deployments at given block (best block by default).
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdeploymentinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getdeploymentinfo")]
+	fn deployment_info(&self, blockhash: Option<SHA256D>) -> Result<GetDeploymentInfoResponse, Error>;
+	/// Get compact filter of given block.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockfilter", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "basic"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getblockfilter")]
+	fn block_filter(&self, blockhash: SHA256D, filtertype: Option<String>) -> Result<GetBlockFilterResponse, Error>;
+	/// Get hashes of canon chain transactions, paying to or spending from given addresses. Requires address index.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddresstxids", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getaddresstxids")]
+	fn address_tx_ids(&self, request: AddressIndexRequest) -> Result<Vec<SHA256D>, Error>;
+	/// Get unspent canon chain outputs, paying to given addresses. Requires address index.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddressutxos", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getaddressutxos")]
+	fn address_utxos(&self, request: AddressIndexRequest) -> Result<Vec<AddressUtxo>, Error>;
+	/// Get balance of given addresses. Requires address index.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddressbalance", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getaddressbalance")]
+	fn address_balance(&self, request: AddressIndexRequest) -> Result<AddressBalance, Error>;
+	/// Write the canon chain UTXO set snapshot to the file, which must not exist.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "dumptxoutset", "params": ["utxo.dat"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "dumptxoutset")]
+	fn dump_transaction_out_set(&self, path: String) -> Result<DumpTxOutSetResponse, Error>;
 }
@@ -1,4 +1,8 @@
-use crate::v1::types::{BlockTemplate, BlockTemplateRequest, MempoolInfo};
+use crate::v1::types::{
+	BlockTemplateRequest, Bytes, EstimateRawFeeResponse, EstimateSmartFeeResponse, GetBlockTemplateResponse, MempoolEntry, MempoolInfo,
+	MiningInfo, RawBlock, SaveMempoolResponse,
+};
+use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
 use jsonrpc_derive::rpc;
 
@@ -8,9 +12,39 @@ pub trait Miner {
 	/// Get block template for mining.
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocktemplate", "params": [{"capabilities": ["coinbasetxn", "workid", "coinbase/append"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getblocktemplate")]
-	fn get_block_template(&self, request: BlockTemplateRequest) -> Result<BlockTemplate, Error>;
+	fn get_block_template(&self, request: BlockTemplateRequest) -> Result<GetBlockTemplateResponse, Error>;
+	/// Submit new block to the node. Returns null if block is accepted or rejection reason otherwise.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitblock", "params": ["0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "submitblock")]
+	fn submit_block(&self, block: RawBlock) -> Result<Option<String>, Error>;
+	/// Submit block header as a candidate chain tip.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitheader", "params": ["0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "submitheader")]
+	fn submit_header(&self, header: Bytes) -> Result<(), Error>;
 	/// Get memory pool info
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getmempoolinfo")]
 	fn mempool_info(&self) -> Result<MempoolInfo, Error>;
+	/// Get memory pool transaction info
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolentry", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getmempoolentry")]
+	fn mempool_entry(&self, hash: SHA256D) -> Result<MempoolEntry, Error>;
+	/// Dump memory pool to the disk.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "savemempool", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "savemempool")]
+	fn save_mempool(&self) -> Result<SaveMempoolResponse, Error>;
+	/// Estimate fee rate (in BTC/kB), required for transaction to begin confirmation within given number of blocks.
+	/// Estimate mode is one of "unset", "economical" or "conservative" (default).
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "estimatesmartfee", "params": [6, "economical"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "estimatesmartfee")]
+	fn estimate_smart_fee(&self, conf_target: u32, estimate_mode: Option<String>) -> Result<EstimateSmartFeeResponse, Error>;
+	/// Estimate fee rate (in BTC/kB), required for transaction to be confirmed within given number of blocks
+	/// with given probability (0.95 by default), separately for every estimation horizon.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "estimaterawfee", "params": [6, 0.9], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "estimaterawfee")]
+	fn estimate_raw_fee(&self, conf_target: u32, threshold: Option<f64>) -> Result<EstimateRawFeeResponse, Error>;
+	/// Get mining-related information, including hash rate of the `generate` calls.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmininginfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getmininginfo")]
+	fn mining_info(&self) -> Result<MiningInfo, Error>;
 }
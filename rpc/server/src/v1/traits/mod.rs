@@ -4,6 +4,7 @@ mod generate;
 mod miner;
 mod network;
 mod raw;
+mod wallet;
 
 pub use self::blockchain::BlockChain;
 pub use self::control::Control;
@@ -11,3 +12,4 @@ pub use self::generate::Generate;
 pub use self::miner::Miner;
 pub use self::network::Network;
 pub use self::raw::Raw;
+pub use self::wallet::Wallet;
@@ -1,4 +1,4 @@
-use crate::v1::types::{AddNodeOperation, NetworkInfo, NodeInfo, Peer};
+use crate::v1::types::{AddNodeOperation, BannedSubnet, NetworkInfo, NodeInfo, Peer, SetBanOperation};
 use jsonrpc_core::Error;
 use jsonrpc_derive::rpc;
 
@@ -28,4 +28,24 @@ pub trait Network {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getpeerinfo", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getpeerinfo")]
 	fn peer_info(&self) -> Result<Vec<Peer>, Error>;
+	/// Attempts to add or remove an IP/Subnet from the banned list.
+	/// Ban time is given in seconds, or as an absolute UNIX epoch time, if `absolute` is true. 0 means the default ban time.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setban", "params": ["192.168.0.6", "add", 86400] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setban", "params": ["192.168.0.0/24", "add"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setban", "params": ["192.168.0.0/24", "remove"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "setban")]
+	fn set_ban(&self, subnet: String, command: SetBanOperation, bantime: Option<u64>, absolute: Option<bool>) -> Result<(), Error>;
+	/// List all manually and automatically banned IPs/Subnets.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "listbanned", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listbanned")]
+	fn list_banned(&self) -> Result<Vec<BannedSubnet>, Error>;
+	/// Clear all banned IPs.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "clearbanned", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "clearbanned")]
+	fn clear_banned(&self) -> Result<(), Error>;
+	/// Immediately disconnects from the specified peer node. Either address or node id should be provided.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "disconnectnode", "params": ["192.168.0.6:8333"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "disconnectnode", "params": ["", 1] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "disconnectnode")]
+	fn disconnect_node(&self, address: Option<String>, nodeid: Option<usize>) -> Result<(), Error>;
 }
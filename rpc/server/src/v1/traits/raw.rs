@@ -1,5 +1,10 @@
+use crate::v1::types::AnalyzePsbtResponse;
+use crate::v1::types::DecodedPsbt;
+use crate::v1::types::FinalizePsbtResponse;
 use crate::v1::types::GetRawTransactionResponse;
+use crate::v1::types::PreviousTransactionOutput;
 use crate::v1::types::RawTransaction;
+use crate::v1::types::SignRawTransactionResponse;
 use crate::v1::types::Transaction;
 use crate::v1::types::TransactionInput;
 use crate::v1::types::TransactionOutputs;
@@ -31,4 +36,46 @@ pub trait Raw {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrawtransaction", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getrawtransaction")]
 	fn get_raw_transaction(&self, hash: SHA256D, verbose: Option<bool>) -> Result<GetRawTransactionResponse, Error>;
+	/// Creates PSBT of the transaction, spending the given inputs and creating new outputs.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "createpsbt", "params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}, 0, true, 2], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "createpsbt")]
+	fn create_psbt(
+		&self,
+		inputs: Vec<TransactionInput>,
+		outputs: TransactionOutputs,
+		lock_time: Option<u32>,
+		replaceable: Option<bool>,
+		psbt_version: Option<u32>,
+	) -> Result<String, Error>;
+	/// Return an object representing the base64-encoded PSBT.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "decodepsbt", "params": ["cHNidP8BAFICAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD9////AUBCDwAAAAAAFgAUdR526BmRltRUlBxF0bOjI/FDO9YAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "decodepsbt")]
+	fn decode_psbt(&self, psbt: String) -> Result<DecodedPsbt, Error>;
+	/// Combines multiple PSBTs of the same transaction into one PSBT.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "combinepsbt", "params": [["cHNidP8BAFICAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD9////AUBCDwAAAAAAFgAUdR526BmRltRUlBxF0bOjI/FDO9YAAAAAAAAA", "cHNidP8BAFICAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD9////AUBCDwAAAAAAFgAUdR526BmRltRUlBxF0bOjI/FDO9YAAAAAAAAA"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "combinepsbt")]
+	fn combine_psbt(&self, psbts: Vec<String>) -> Result<String, Error>;
+	/// Finalizes inputs of the PSBT. Extracts the transaction if all inputs are finalized and `extract` isn't false.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "finalizepsbt", "params": ["cHNidP8BAFICAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD9////AUBCDwAAAAAAFgAUdR526BmRltRUlBxF0bOjI/FDO9YAAAAAAAAA", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "finalizepsbt")]
+	fn finalize_psbt(&self, psbt: String, extract: Option<bool>) -> Result<FinalizePsbtResponse, Error>;
+	/// Analyzes the PSBT and reports what's missing and which role should process it next.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "analyzepsbt", "params": ["cHNidP8BAFICAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD9////AUBCDwAAAAAAFgAUdR526BmRltRUlBxF0bOjI/FDO9YAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "analyzepsbt")]
+	fn analyze_psbt(&self, psbt: String) -> Result<AnalyzePsbtResponse, Error>;
+	/// Adds spent outputs from the memory pool or the blockchain and scripts of the given descriptors to the PSBT.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "utxoupdatepsbt", "params": ["cHNidP8BAFICAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD9////AUBCDwAAAAAAFgAUdR526BmRltRUlBxF0bOjI/FDO9YAAAAAAAAA", ["wpkh(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "utxoupdatepsbt")]
+	fn utxo_update_psbt(&self, psbt: String, descriptors: Option<Vec<String>>) -> Result<String, Error>;
+	/// Signs inputs of the raw transaction with the given WIF-encoded private keys. Outputs, which are not given
+	/// in `prevtxs`, are looked up in the memory pool and the blockchain.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "signrawtransactionwithkey", "params": ["02000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000fdffffff01905f010000000000160014751e76e8199196d454941c45d1b3a323f1433bd600000000", ["KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"], [{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0,"scriptPubKey":"0014751e76e8199196d454941c45d1b3a323f1433bd6","amount":0.001}], "ALL"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "signrawtransactionwithkey")]
+	fn sign_raw_transaction_with_key(
+		&self,
+		raw_transaction: RawTransaction,
+		private_keys: Vec<String>,
+		previous_outputs: Option<Vec<PreviousTransactionOutput>>,
+		sighash_type: Option<String>,
+	) -> Result<SignRawTransactionResponse, Error>;
 }
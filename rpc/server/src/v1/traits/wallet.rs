@@ -0,0 +1,60 @@
+use crate::v1::types::{ImportDescriptorRequest, ImportDescriptorResult, ListTransactionsEntry, ListUnspentEntry};
+use bitcrypto::SHA256D;
+use jsonrpc_core::Error;
+use jsonrpc_derive::rpc;
+
+/// bitcoin-rs wallet interface.
+#[rpc(server)]
+pub trait Wallet {
+	/// Get new address for receiving payments. Address type is one of "legacy", "p2sh-segwit", "bech32" (default) or "bech32m".
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getnewaddress", "params": ["savings", "bech32"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getnewaddress")]
+	fn new_address(&self, label: Option<String>, address_type: Option<String>) -> Result<String, Error>;
+	/// Get spendable balance (in BTC) of outputs with at least `minconf` confirmations. The first argument must be "*" if given.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getbalance", "params": ["*", 6], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getbalance")]
+	fn balance(&self, dummy: Option<String>, minconf: Option<u32>) -> Result<f64, Error>;
+	/// Get unspent outputs of the wallet with between `minconf` (1 by default) and `maxconf` (9999999 by default) confirmations.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listunspent", "params": [0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listunspent")]
+	fn list_unspent(&self, minconf: Option<u32>, maxconf: Option<u32>) -> Result<Vec<ListUnspentEntry>, Error>;
+	/// Send amount (in BTC) to the address. Fee rate is estimated for confirmation within `conf_target` blocks (6 by default).
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "sendtoaddress", "params": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", 0.1, "", "", false, true, 6], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "sendtoaddress")]
+	fn send_to_address(
+		&self,
+		address: String,
+		amount: f64,
+		comment: Option<String>,
+		comment_to: Option<String>,
+		subtract_fee_from_amount: Option<bool>,
+		replaceable: Option<bool>,
+		conf_target: Option<u32>,
+	) -> Result<SHA256D, Error>;
+	/// Get most recent `count` (10 by default) payments of the wallet, skipping `skip` most recent ones.
+	/// Label must be "*" to list payments of all labels.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listtransactions", "params": ["*", 20, 0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listtransactions")]
+	fn list_transactions(
+		&self,
+		label: Option<String>,
+		count: Option<usize>,
+		skip: Option<usize>,
+	) -> Result<Vec<ListTransactionsEntry>, Error>;
+	/// Unlock encrypted wallet for `timeout` seconds.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "walletpassphrase", "params": ["passphrase", 60], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "walletpassphrase")]
+	fn wallet_passphrase(&self, passphrase: String, timeout: u64) -> Result<(), Error>;
+	/// Lock encrypted wallet.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "walletlock", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "walletlock")]
+	fn wallet_lock(&self) -> Result<(), Error>;
+	/// Encrypt private keys of the wallet with the passphrase. Wallet is locked after encryption.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "encryptwallet", "params": ["passphrase"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "encryptwallet")]
+	fn encrypt_wallet(&self, passphrase: String) -> Result<(), Error>;
+	/// Import output descriptors. Blocks after the descriptor timestamp are rescanned.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "importdescriptors", "params": [[{"desc": "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)", "timestamp": "now"}]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "importdescriptors")]
+	fn import_descriptors(&self, requests: Vec<ImportDescriptorRequest>) -> Result<Vec<ImportDescriptorResult>, Error>;
+}
@@ -63,6 +63,29 @@ pub mod vec {
 	}
 }
 
+pub mod option {
+	use super::AddressVisitor;
+	use keys::Address;
+	use serde::de::Visitor;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	pub fn serialize<S>(address: &Option<Address>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		address.as_ref().map(|address| address.to_string()).serialize(serializer)
+	}
+
+	pub fn deserialize<'a, D>(deserializer: D) -> Result<Option<Address>, D::Error>
+	where
+		D: Deserializer<'a>,
+	{
+		<Option<String>>::deserialize(deserializer)?
+			.map(|value| AddressVisitor::default().visit_str(&value))
+			.transpose()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::v1::types;
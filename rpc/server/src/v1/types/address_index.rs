@@ -0,0 +1,76 @@
+use super::bytes::Bytes;
+use crate::v1::types;
+use bitcrypto::SHA256D;
+use keys::Address;
+
+/// Addresses to look up in the address index
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressIndexRequest {
+	/// Addresses
+	#[serde(with = "types::address::vec")]
+	pub addresses: Vec<Address>,
+}
+
+/// Unspent output, paying to indexed address
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressUtxo {
+	/// Address, the output is paying to
+	#[serde(with = "types::address")]
+	pub address: Address,
+	/// Hash of the transaction, containing output
+	pub txid: SHA256D,
+	/// Index of the output in the transaction
+	#[serde(rename = "outputIndex")]
+	pub output_index: u32,
+	/// Output script
+	pub script: Bytes,
+	/// Output value in satoshis
+	pub satoshis: u64,
+	/// Height of the block, containing transaction
+	pub height: u32,
+}
+
+/// Balance of the indexed addresses
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressBalance {
+	/// Sum of unspent outputs values in satoshis
+	pub balance: u64,
+	/// Sum of all received outputs values in satoshis
+	pub received: u64,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AddressBalance, AddressIndexRequest, AddressUtxo};
+	use bitcrypto::{FromStr, SHA256D};
+	use serde_json;
+
+	#[test]
+	fn address_index_request_deserialize() {
+		let request: AddressIndexRequest = serde_json::from_str(r#"{"addresses":["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}"#).unwrap();
+		assert_eq!(request.addresses, vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into()]);
+		assert!(serde_json::from_str::<AddressIndexRequest>(r#"{"addresses":["invalid"]}"#).is_err());
+	}
+
+	#[test]
+	fn address_utxo_serialize() {
+		let utxo = AddressUtxo {
+			address: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into(),
+			txid: SHA256D::from_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b").unwrap(),
+			output_index: 0,
+			script: "51".into(),
+			satoshis: 5000000000,
+			height: 0,
+		};
+		assert_eq!(
+			serde_json::to_string(&utxo).unwrap(),
+			r#"{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","outputIndex":0,"script":"51","satoshis":5000000000,"height":0}"#
+		);
+	}
+
+	#[test]
+	fn address_balance_serialize() {
+		let balance = AddressBalance { balance: 10, received: 20 };
+		assert_eq!(serde_json::to_string(&balance).unwrap(), r#"{"balance":10,"received":20}"#);
+	}
+}
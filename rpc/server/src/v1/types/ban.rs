@@ -0,0 +1,36 @@
+use p2p::BanEntry;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum SetBanOperation {
+	#[serde(rename = "add")]
+	Add,
+	#[serde(rename = "remove")]
+	Remove,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BannedSubnet {
+	/// Banned IP/Subnet or onion/i2p address
+	pub address: String,
+	/// The UNIX epoch time the ban was created
+	pub ban_created: u64,
+	/// The UNIX epoch time the ban expires
+	pub banned_until: u64,
+	/// The ban duration, in seconds
+	pub ban_duration: u64,
+	/// The time remaining until the ban expires, in seconds
+	pub time_remaining: u64,
+}
+
+impl BannedSubnet {
+	pub fn new(entry: &BanEntry, now: u64) -> Self {
+		BannedSubnet {
+			address: entry.subnet.to_string(),
+			ban_created: entry.created,
+			banned_until: entry.until,
+			ban_duration: entry.until.saturating_sub(entry.created),
+			time_remaining: entry.until.saturating_sub(now),
+		}
+	}
+}
@@ -1,7 +1,9 @@
+use super::bytes::Bytes;
 use super::transaction::RawTransaction;
 use bitcrypto::SHA256D;
 use chain;
 use miner;
+use ser::{serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
 use std::collections::HashMap;
 
 /// Block template as described in:
@@ -53,6 +55,8 @@ pub struct BlockTemplate {
 	pub bits: u32,
 	/// The height of the next block
 	pub height: u32,
+	/// Witness commitment script, which must be included in the coinbase transaction outputs
+	pub default_witness_commitment: Option<Bytes>,
 }
 
 /// Transaction data as included in `BlockTemplate`
@@ -81,16 +85,46 @@ pub struct BlockTemplateTransaction {
 
 impl From<miner::BlockTemplate> for BlockTemplate {
 	fn from(block: miner::BlockTemplate) -> Self {
+		let segwit = block.default_witness_commitment.is_some();
+		// transactions are referenced by their 1-based index in the template
+		let indices: HashMap<SHA256D, u64> = block
+			.transactions
+			.iter()
+			.enumerate()
+			.map(|(index, transaction)| (transaction.hash, index as u64 + 1))
+			.collect();
+		let transactions = block
+			.transactions
+			.into_iter()
+			.map(|transaction| {
+				let mut depends: Vec<u64> = transaction
+					.raw
+					.inputs
+					.iter()
+					.filter_map(|input| indices.get(&input.previous_output.hash).cloned())
+					.collect();
+				depends.sort_unstable();
+				depends.dedup();
+				BlockTemplateTransaction {
+					depends: Some(depends),
+					..transaction.into()
+				}
+			})
+			.collect();
+
 		BlockTemplate {
 			version: block.version,
+			rules: if segwit { Some(vec!["!segwit".to_owned()]) } else { None },
 			previousblockhash: block.previous_header_hash.into(),
 			curtime: block.time,
 			bits: block.bits.into(),
 			height: block.height,
-			transactions: block.transactions.into_iter().map(Into::into).collect(),
+			transactions,
 			coinbasevalue: Some(block.coinbase_value),
 			sizelimit: Some(block.size_limit),
 			sigoplimit: Some(block.sigop_limit),
+			weightlimit: if segwit { Some(block.weight_limit) } else { None },
+			default_witness_commitment: block.default_witness_commitment.map(|script| Bytes::new(script.take())),
 			..Default::default()
 		}
 	}
@@ -98,10 +132,12 @@ impl From<miner::BlockTemplate> for BlockTemplate {
 
 impl From<chain::IndexedTransaction> for BlockTemplateTransaction {
 	fn from(transaction: chain::IndexedTransaction) -> Self {
-		use ser::serialize;
-		let serialize = serialize(&transaction.raw);
+		let serialize = serialize_with_flags(&transaction.raw, SERIALIZE_TRANSACTION_WITNESS);
 		BlockTemplateTransaction {
-			data: RawTransaction::new(Vec::from((*serialize).clone())),
+			data: RawTransaction::new(serialize.take()),
+			txid: Some(transaction.hash),
+			hash: Some(transaction.raw.witness_hash()),
+			weight: Some(transaction.raw.weight() as i64),
 			..Default::default()
 		}
 	}
@@ -201,9 +237,10 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				default_witness_commitment: None,
 			})
 			.unwrap(),
-			r#"{"version":0,"rules":null,"vbavailable":null,"vbrequired":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[],"coinbaseaux":null,"coinbasevalue":null,"coinbasetxn":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","mintime":null,"mutable":null,"noncerange":null,"sigoplimit":null,"sizelimit":null,"weightlimit":null,"curtime":100,"bits":200,"height":300}"#
+			r#"{"version":0,"rules":null,"vbavailable":null,"vbrequired":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[],"coinbaseaux":null,"coinbasevalue":null,"coinbasetxn":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","mintime":null,"mutable":null,"noncerange":null,"sigoplimit":null,"sizelimit":null,"weightlimit":null,"curtime":100,"bits":200,"height":300,"default_witness_commitment":null}"#
 		);
 		assert_eq!(
 			serde_json::to_string(&BlockTemplate {
@@ -244,9 +281,10 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				default_witness_commitment: Some(Bytes(FromHex::from_hex("6a").unwrap())),
 			})
 			.unwrap(),
-			r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300}"#
+			r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300,"default_witness_commitment":"6a"}"#
 		);
 	}
 
@@ -274,9 +312,10 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				default_witness_commitment: None,
 			});
 		assert_eq!(
-			serde_json::from_str::<BlockTemplate>(r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300}"#).unwrap(),
+			serde_json::from_str::<BlockTemplate>(r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300,"default_witness_commitment":"6a"}"#).unwrap(),
 			BlockTemplate {
 				version: 0,
 				rules: Some(vec!["a".to_owned()]),
@@ -315,6 +354,7 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				default_witness_commitment: Some(Bytes(FromHex::from_hex("6a").unwrap())),
 			});
 	}
 }
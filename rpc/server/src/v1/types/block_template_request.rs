@@ -1,3 +1,4 @@
+use super::block::RawBlock;
 use std::collections::HashSet;
 
 /// Block template request mode
@@ -26,6 +27,8 @@ pub struct BlockTemplateRequest {
 	pub capabilities: Option<HashSet<String>>,
 	/// Softfork deployments, supported by client
 	pub rules: Option<HashSet<String>>,
+	/// Block data, proposed for validation (proposal mode only)
+	pub data: Option<RawBlock>,
 }
 
 #[cfg(test)]
@@ -55,27 +58,29 @@ mod tests {
 	fn block_template_request_serialize() {
 		assert_eq!(
 			serde_json::to_string(&BlockTemplateRequest::default()).unwrap(),
-			r#"{"mode":null,"capabilities":null,"rules":null}"#
+			r#"{"mode":null,"capabilities":null,"rules":null,"data":null}"#
 		);
 		assert_eq!(
 			serde_json::to_string(&BlockTemplateRequest {
 				mode: Some(BlockTemplateRequestMode::Template),
 				capabilities: Some(vec!["a".to_owned()].into_iter().collect()),
 				rules: Some(vec!["b".to_owned()].into_iter().collect()),
+				data: None,
 			})
 			.unwrap(),
-			r#"{"mode":"template","capabilities":["a"],"rules":["b"]}"#
+			r#"{"mode":"template","capabilities":["a"],"rules":["b"],"data":null}"#
 		);
 	}
 
 	#[test]
 	fn block_template_request_deserialize() {
 		assert_eq!(
-			serde_json::from_str::<BlockTemplateRequest>(r#"{"mode":null,"capabilities":null,"rules":null}"#).unwrap(),
+			serde_json::from_str::<BlockTemplateRequest>(r#"{"mode":null,"capabilities":null,"rules":null,"data":null}"#).unwrap(),
 			BlockTemplateRequest {
 				mode: None,
 				capabilities: None,
 				rules: None,
+				data: None,
 			}
 		);
 		assert_eq!(
@@ -84,6 +89,16 @@ mod tests {
 				mode: Some(BlockTemplateRequestMode::Template),
 				capabilities: Some(vec!["a".to_owned()].into_iter().collect()),
 				rules: Some(vec!["b".to_owned()].into_iter().collect()),
+				data: None,
+			}
+		);
+		assert_eq!(
+			serde_json::from_str::<BlockTemplateRequest>(r#"{"mode":"proposal","data":"0102"}"#).unwrap(),
+			BlockTemplateRequest {
+				mode: Some(BlockTemplateRequestMode::Proposal),
+				capabilities: None,
+				rules: None,
+				data: Some(vec![1, 2].into()),
 			}
 		);
 	}
@@ -27,17 +27,85 @@ pub struct BlockchainInfo {
 	pub size_on_disk: u64,
 	/// Whether the chain is pruned
 	pub pruned: bool,
+	/// Height of the lowest block with all transactions stored, if some blocks have been pruned
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pruneheight: Option<u32>,
 	/// Supported soft forks
 	pub softforks: HashMap<String, SoftFork>,
 	/// Warnings
 	pub warnings: String,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+/// Soft fork deployment status
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct SoftFork {
-	pub f_type: String,
-	pub active: String,
-	pub height: String,
+	/// Type of the deployment
+	#[serde(rename = "type")]
+	pub fork_type: SoftForkType,
+	/// Whether the rules are enforced for the next block
+	pub active: bool,
+	/// Height of the first block, which rules are (or will be) enforced for
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub height: Option<u32>,
+	/// Version bits deployment status. Only for bip9 deployments
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub bip9: Option<Bip9SoftFork>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SoftForkType {
+	/// Deployment, activated at fixed height
+	Buried,
+	/// Version bits deployment
+	Bip9,
+}
+
+/// Version bits deployment status
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Bip9SoftFork {
+	/// Version bit, used to signal the deployment
+	pub bit: u8,
+	/// Median time, since which the signalling is counted
+	pub start_time: u32,
+	/// Median time, after which the deployment is considered failed (or locked in)
+	pub timeout: u32,
+	/// Height, before which the deployment can not become active
+	pub min_activation_height: u32,
+	/// Status of the deployment for the block
+	pub status: Bip9Status,
+	/// Height of the first block, which the status applies to
+	pub since: u32,
+	/// Status of the deployment for the next block
+	pub status_next: Bip9Status,
+	/// Signalling statistics of the current period. Only for started and locked in deployments
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub statistics: Option<Bip9Statistics>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Bip9Status {
+	Defined,
+	Started,
+	LockedIn,
+	Active,
+	Failed,
+}
+
+/// Version bits signalling statistics of the current period
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Bip9Statistics {
+	/// Length of the signalling period
+	pub period: u32,
+	/// Number of signalling blocks, required to lock in the deployment
+	pub threshold: u32,
+	/// Number of blocks, elapsed since the beginning of the current period
+	pub elapsed: u32,
+	/// Number of signalling blocks in the current period
+	pub count: u32,
+	/// Whether the threshold still can be reached in the current period
+	pub possible: bool,
 }
 
 #[cfg(test)]
@@ -59,6 +127,7 @@ mod tests {
 			chainwork: SHA256D::from_str("f000000000000000000000000000000000000000000000000000000000000000").unwrap(),
 			size_on_disk: 1000,
 			pruned: false,
+			pruneheight: None,
 			softforks: HashMap::new(),
 			warnings: "".to_string(),
 		}
@@ -79,4 +148,43 @@ mod tests {
 		let info = info();
 		assert_eq!(serde_json::from_str::<BlockchainInfo>(info_str()).unwrap(), info);
 	}
+
+	#[test]
+	fn soft_fork_serialize() {
+		let buried = SoftFork {
+			fork_type: SoftForkType::Buried,
+			active: true,
+			height: Some(419328),
+			bip9: None,
+		};
+		assert_eq!(
+			serde_json::to_string(&buried).unwrap(),
+			r#"{"type":"buried","active":true,"height":419328}"#
+		);
+
+		let bip9 = SoftFork {
+			fork_type: SoftForkType::Bip9,
+			active: false,
+			height: None,
+			bip9: Some(Bip9SoftFork {
+				bit: 28,
+				start_time: 0,
+				timeout: 4294967295,
+				min_activation_height: 0,
+				status: Bip9Status::LockedIn,
+				since: 144,
+				status_next: Bip9Status::LockedIn,
+				statistics: Some(Bip9Statistics {
+					period: 144,
+					threshold: 108,
+					elapsed: 10,
+					count: 2,
+					possible: true,
+				}),
+			}),
+		};
+		let bip9_str = r#"{"type":"bip9","active":false,"bip9":{"bit":28,"start_time":0,"timeout":4294967295,"min_activation_height":0,"status":"locked_in","since":144,"status_next":"locked_in","statistics":{"period":144,"threshold":108,"elapsed":10,"count":2,"possible":true}}}"#;
+		assert_eq!(serde_json::to_string(&bip9).unwrap(), bip9_str);
+		assert_eq!(serde_json::from_str::<SoftFork>(bip9_str).unwrap(), bip9);
+	}
 }
@@ -0,0 +1,18 @@
+use bitcrypto::SHA256D;
+
+/// Summary of the written UTXO set snapshot
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DumpTxOutSetResponse {
+	/// Number of unspent outputs in the snapshot
+	pub coins_written: u64,
+	/// Hash of the snapshot base block
+	pub base_hash: SHA256D,
+	/// Height of the snapshot base block
+	pub base_height: u32,
+	/// Absolute path to the snapshot file
+	pub path: String,
+	/// Hash of the snapshot transactions, which is used to verify the snapshot when it is loaded
+	pub txoutset_hash: SHA256D,
+	/// Total number of transactions in the chain up to and including the base block
+	pub nchaintx: u64,
+}
@@ -0,0 +1,101 @@
+use chain::constants::SATOSHIS_IN_COIN;
+use miner::{EstimationResult, EstimatorBucket};
+
+/// Result of the smart fee estimation
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EstimateSmartFeeResponse {
+	/// Estimated fee rate in BTC/kB
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub feerate: Option<f64>,
+	/// Errors, encountered during estimation
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub errors: Option<Vec<String>>,
+	/// Number of blocks, for which the estimate is valid
+	pub blocks: u32,
+}
+
+/// Result of the raw fee estimation
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct EstimateRawFeeResponse {
+	/// Estimate of the short horizon
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub short: Option<RawFeeEstimate>,
+	/// Estimate of the medium horizon
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub medium: Option<RawFeeEstimate>,
+	/// Estimate of the long horizon
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub long: Option<RawFeeEstimate>,
+}
+
+/// Fee estimate of the single horizon
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RawFeeEstimate {
+	/// Estimated fee rate in BTC/kB
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub feerate: Option<f64>,
+	/// Exponential decay (per block) of the horizon statistics
+	pub decay: f64,
+	/// Number of blocks in the single period of the horizon
+	pub scale: u32,
+	/// Range of buckets, which has passed the threshold
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pass: Option<FeeEstimateBucket>,
+	/// Range of buckets, which has failed the threshold
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fail: Option<FeeEstimateBucket>,
+	/// Errors, encountered during estimation
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub errors: Option<Vec<String>>,
+}
+
+/// Statistics of the range of fee rate buckets
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FeeEstimateBucket {
+	/// Lower bound of the fee rate range (in satoshis per 1000 bytes)
+	pub startrange: f64,
+	/// Upper bound of the fee rate range (in satoshis per 1000 bytes)
+	pub endrange: f64,
+	/// Number of transactions, confirmed within the target
+	pub withintarget: f64,
+	/// Number of confirmed transactions
+	pub totalconfirmed: f64,
+	/// Number of transactions, which are in the memory pool for at least target blocks
+	pub inmempool: f64,
+	/// Number of transactions, which have left the memory pool without being confirmed
+	pub leftmempool: f64,
+}
+
+impl RawFeeEstimate {
+	pub fn new(fee_rate: Option<u64>, result: EstimationResult) -> Self {
+		let errors = match fee_rate {
+			Some(_) => None,
+			None => Some(vec!["Insufficient data or no feerate found which meets threshold".into()]),
+		};
+		RawFeeEstimate {
+			feerate: fee_rate.map(|fee_rate| fee_rate as f64 / SATOSHIS_IN_COIN as f64),
+			decay: result.decay,
+			scale: result.scale,
+			pass: result.pass.map(Into::into),
+			fail: result.fail.map(Into::into),
+			errors,
+		}
+	}
+}
+
+impl From<EstimatorBucket> for FeeEstimateBucket {
+	fn from(bucket: EstimatorBucket) -> Self {
+		FeeEstimateBucket {
+			startrange: bucket.start.round(),
+			endrange: bucket.end.round(),
+			withintarget: round_to_hundredths(bucket.within_target),
+			totalconfirmed: round_to_hundredths(bucket.total_confirmed),
+			inmempool: round_to_hundredths(bucket.in_mempool),
+			leftmempool: round_to_hundredths(bucket.left_mempool),
+		}
+	}
+}
+
+fn round_to_hundredths(value: f64) -> f64 {
+	(value * 100.0).round() / 100.0
+}
@@ -0,0 +1,11 @@
+use super::bytes::Bytes;
+use bitcrypto::SHA256D;
+
+/// Compact filter of the block
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetBlockFilterResponse {
+	/// Serialized filter
+	pub filter: Bytes,
+	/// Filter header
+	pub header: SHA256D,
+}
@@ -0,0 +1,26 @@
+use super::block_template::BlockTemplate;
+
+/// Response to getblocktemplate RPC request
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum GetBlockTemplateResponse {
+	/// When asking for block template (template mode)
+	Template(BlockTemplate),
+	/// When asking to verify block proposal (proposal mode): null if proposal is valid, or reject reason otherwise
+	Proposal(Option<String>),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json;
+
+	#[test]
+	fn get_block_template_response_proposal_serialize() {
+		assert_eq!(serde_json::to_string(&GetBlockTemplateResponse::Proposal(None)).unwrap(), "null");
+		assert_eq!(
+			serde_json::to_string(&GetBlockTemplateResponse::Proposal(Some("bad-txnmrklroot".into()))).unwrap(),
+			r#""bad-txnmrklroot""#
+		);
+	}
+}
@@ -0,0 +1,14 @@
+use super::blockchain_info::SoftFork;
+use bitcrypto::SHA256D;
+use std::collections::HashMap;
+
+/// Deployments state, as seen by given block
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetDeploymentInfoResponse {
+	/// Hash of the block
+	pub hash: SHA256D,
+	/// Height of the block
+	pub height: u32,
+	/// Known deployments
+	pub deployments: HashMap<String, SoftFork>,
+}
@@ -13,8 +13,9 @@ pub struct GetTxOutResponse {
 	/// Script info
 	#[serde(rename = "scriptPubKey")]
 	pub script: TransactionOutputScript,
-	/// This transaction version
-	pub version: i32,
+	/// This transaction version. Unknown for outputs, loaded from the UTXO snapshot
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub version: Option<i32>,
 	/// Is this transaction a coinbase transaction?
 	pub coinbase: bool,
 }
@@ -44,7 +45,7 @@ mod tests {
 					"1H5m1XzvHsjWX3wwU781ubctznEpNACrNC".into(),
 				],
 			},
-			version: 33,
+			version: Some(33),
 			coinbase: false,
 		};
 		assert_eq!(
@@ -69,7 +70,7 @@ mod tests {
 					"1H5m1XzvHsjWX3wwU781ubctznEpNACrNC".into(),
 				],
 			},
-			version: 33,
+			version: Some(33),
 			coinbase: false,
 		};
 		assert_eq!(
@@ -0,0 +1,118 @@
+use serde::de::{Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// importdescriptors request entry
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ImportDescriptorRequest {
+	/// Output descriptor, optionally with private keys
+	pub desc: String,
+	/// Time of the oldest transaction of the descriptor. Blocks, older than this time, are not rescanned
+	pub timestamp: ImportTimestamp,
+	/// Whether the descriptor is used for change outputs
+	#[serde(default)]
+	pub internal: bool,
+	/// Label of the descriptor
+	#[serde(default)]
+	pub label: String,
+}
+
+/// Timestamp of the imported descriptor
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ImportTimestamp {
+	/// Descriptor has no transactions in existing blocks => no rescan is required
+	Now,
+	/// UNIX timestamp
+	Time(u32),
+}
+
+/// importdescriptors response entry
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ImportDescriptorResult {
+	/// Whether the descriptor has been imported
+	pub success: bool,
+	/// Import error
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl Serialize for ImportTimestamp {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match *self {
+			ImportTimestamp::Now => serializer.serialize_str("now"),
+			ImportTimestamp::Time(time) => serializer.serialize_u32(time),
+		}
+	}
+}
+
+impl<'a> Deserialize<'a> for ImportTimestamp {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'a>,
+	{
+		struct ImportTimestampVisitor;
+
+		impl<'b> Visitor<'b> for ImportTimestampVisitor {
+			type Value = ImportTimestamp;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("either \"now\" or UNIX timestamp")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+			where
+				E: ::serde::de::Error,
+			{
+				match value {
+					"now" => Ok(ImportTimestamp::Now),
+					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+				}
+			}
+
+			fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+			where
+				E: ::serde::de::Error,
+			{
+				if value > u32::max_value() as u64 {
+					return Err(E::invalid_value(Unexpected::Unsigned(value), &self));
+				}
+				Ok(ImportTimestamp::Time(value as u32))
+			}
+		}
+
+		deserializer.deserialize_any(ImportTimestampVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ImportDescriptorRequest, ImportTimestamp};
+	use serde_json;
+
+	#[test]
+	fn import_descriptor_request_deserialize() {
+		assert_eq!(
+			serde_json::from_str::<ImportDescriptorRequest>(r#"{"desc":"wpkh(key)","timestamp":"now"}"#).unwrap(),
+			ImportDescriptorRequest {
+				desc: "wpkh(key)".into(),
+				timestamp: ImportTimestamp::Now,
+				internal: false,
+				label: String::new(),
+			}
+		);
+		assert_eq!(
+			serde_json::from_str::<ImportDescriptorRequest>(r#"{"desc":"wpkh(key)","timestamp":1600000000,"internal":true,"label":"a"}"#)
+				.unwrap(),
+			ImportDescriptorRequest {
+				desc: "wpkh(key)".into(),
+				timestamp: ImportTimestamp::Time(1_600_000_000),
+				internal: true,
+				label: "a".into(),
+			}
+		);
+		assert!(serde_json::from_str::<ImportDescriptorRequest>(r#"{"desc":"wpkh(key)","timestamp":"yesterday"}"#).is_err());
+	}
+}
@@ -0,0 +1,38 @@
+use crate::v1::types;
+use bitcrypto::SHA256D;
+use keys::Address;
+
+/// listtransactions response entry
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ListTransactionsEntry {
+	/// Address of the payment, if output script has an address
+	#[serde(default, skip_serializing_if = "Option::is_none", with = "types::address::option")]
+	pub address: Option<Address>,
+	/// One of "send", "receive", "generate" or "immature"
+	pub category: String,
+	/// Payment amount in BTC, negative for the "send" category
+	pub amount: f64,
+	/// Label of the receiving address
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub label: Option<String>,
+	/// Index of the output
+	pub vout: u32,
+	/// Transaction fee in BTC (negative), "send" category only
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+	/// Number of confirmations, zero for memory pool transactions
+	pub confirmations: u32,
+	/// Hash of the block, containing the transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blockhash: Option<SHA256D>,
+	/// Height of the block, containing the transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blockheight: Option<u32>,
+	/// Time of the block, containing the transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blocktime: Option<u32>,
+	/// Hash of the transaction
+	pub txid: SHA256D,
+	/// Time of the block or time, when transaction has entered the memory pool
+	pub time: u64,
+}
@@ -0,0 +1,33 @@
+use super::bytes::Bytes;
+use crate::v1::types;
+use bitcrypto::SHA256D;
+use keys::Address;
+
+/// listunspent response entry
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ListUnspentEntry {
+	/// Hash of the transaction
+	pub txid: SHA256D,
+	/// Index of the output
+	pub vout: u32,
+	/// Address of the output
+	#[serde(with = "types::address")]
+	pub address: Address,
+	/// Label of the address
+	pub label: String,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pubkey: Bytes,
+	/// Output value in BTC
+	pub amount: f64,
+	/// Number of confirmations, zero for memory pool outputs
+	pub confirmations: u32,
+	/// Whether the wallet has private keys to spend this output
+	pub spendable: bool,
+	/// Whether the wallet knows how to spend this output
+	pub solvable: bool,
+	/// Output descriptor
+	pub desc: String,
+	/// Whether this output is considered safe to spend
+	pub safe: bool,
+}
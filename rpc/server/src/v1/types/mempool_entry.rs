@@ -0,0 +1,23 @@
+use bitcrypto::SHA256D;
+
+/// Memory pool transaction information
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MempoolEntry {
+	/// Transaction size in bytes
+	pub size: usize,
+	/// Transaction fee in BTC
+	pub fee: f64,
+	/// Transaction fee with fee delta (used for mining priority) in BTC
+	pub modifiedfee: f64,
+	/// Time when transaction has entered the memory pool, in seconds since epoch (Jan 1 1970 GMT)
+	pub time: u64,
+	/// Number of in-pool ancestor transactions (including this one)
+	pub ancestorcount: usize,
+	/// Size of in-pool descendant transactions (including this one)
+	pub descendantsize: usize,
+	/// Unconfirmed transactions used as inputs for this transaction
+	pub depends: Vec<SHA256D>,
+	/// Whether this transaction could be replaced (BIP125)
+	#[serde(rename = "bip125-replaceable")]
+	pub bip125_replaceable: bool,
+}
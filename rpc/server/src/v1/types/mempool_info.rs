@@ -1,3 +1,4 @@
+use chain::constants::SATOSHIS_IN_COIN;
 use miner::MemoryPoolInformation;
 
 impl From<MemoryPoolInformation> for MempoolInfo {
@@ -5,6 +6,8 @@ impl From<MemoryPoolInformation> for MempoolInfo {
 		MempoolInfo {
 			size: info.transactions_count,
 			usage: info.transactions_size_in_bytes,
+			maxmempool: info.max_size_in_bytes,
+			mempoolminfee: info.min_fee_rate as f64 / SATOSHIS_IN_COIN as f64,
 			..Default::default()
 		}
 	}
@@ -21,7 +24,7 @@ pub struct MempoolInfo {
 	// Maximum memory usage for the mempool
 	maxmempool: usize,
 	// Minimum fee rate in BTC/kB for tx to be accepted. Is the maximum of minrelaytxfee and minimum mempool fee
-	mempoolminfee: usize,
+	mempoolminfee: f64,
 	// Current minimum relay fee for transactions
 	minrelaytxfee: usize,
 }
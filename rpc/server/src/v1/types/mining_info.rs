@@ -0,0 +1,36 @@
+use sync::MiningInformation;
+
+impl From<MiningInformation> for MiningInfo {
+	fn from(info: MiningInformation) -> Self {
+		MiningInfo {
+			blocks: info.blocks,
+			difficulty: info.difficulty,
+			networkhashps: info.network_hash_rate,
+			pooledtx: info.pooled_transactions,
+			chain: info.network.to_string(),
+			hashespersec: info.hashes_per_second,
+			genproclimit: info.mining_threads,
+			warnings: String::new(),
+		}
+	}
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct MiningInfo {
+	// The current block count
+	blocks: u32,
+	// The current difficulty
+	difficulty: f64,
+	// The network hashes per second, estimated from the last 120 blocks
+	networkhashps: f64,
+	// The number of transactions in the memory pool
+	pooledtx: usize,
+	// Current network name, as reported by getblockchaininfo
+	chain: String,
+	// Hashes per second of the last `generate` call
+	hashespersec: f64,
+	// The number of threads, used by `generate`
+	genproclimit: usize,
+	// Any network and blockchain warnings
+	warnings: String,
+}
@@ -1,37 +1,70 @@
 pub mod address;
+mod address_index;
+mod ban;
 mod block;
 mod block_template;
 mod block_template_request;
 mod blockchain_info;
 mod bytes;
 mod chain_tx_stats;
+mod dump_tx_out_set_response;
+mod fee_estimate;
+mod get_block_filter_response;
 mod get_block_response;
+mod get_block_template_response;
+mod get_deployment_info_response;
 mod get_tx_out_response;
 mod get_tx_out_set_info_response;
+mod import_descriptors;
+mod list_transactions_entry;
+mod list_unspent_entry;
 mod memory_info;
+mod mempool_entry;
 mod mempool_info;
+mod mining_info;
 mod network_info;
 mod nodes;
 mod peer;
+mod psbt;
+mod save_mempool_response;
 mod script;
+mod sign_raw_transaction;
 mod transaction;
 mod uint;
 
+pub use self::address_index::{AddressBalance, AddressIndexRequest, AddressUtxo};
+pub use self::ban::{BannedSubnet, SetBanOperation};
 pub use self::block::RawBlock;
 pub use self::block_template::{BlockTemplate, BlockTemplateTransaction};
 pub use self::block_template_request::{BlockTemplateRequest, BlockTemplateRequestMode};
-pub use self::blockchain_info::BlockchainInfo;
+pub use self::blockchain_info::{Bip9SoftFork, Bip9Statistics, Bip9Status, BlockchainInfo, SoftFork, SoftForkType};
 pub use self::bytes::Bytes;
 pub use self::chain_tx_stats::ChainTxStats;
+pub use self::dump_tx_out_set_response::DumpTxOutSetResponse;
+pub use self::fee_estimate::{EstimateRawFeeResponse, EstimateSmartFeeResponse, FeeEstimateBucket, RawFeeEstimate};
+pub use self::get_block_filter_response::GetBlockFilterResponse;
 pub use self::get_block_response::{GetBlockResponse, VerboseBlock};
+pub use self::get_block_template_response::GetBlockTemplateResponse;
+pub use self::get_deployment_info_response::GetDeploymentInfoResponse;
 pub use self::get_tx_out_response::GetTxOutResponse;
 pub use self::get_tx_out_set_info_response::GetTxOutSetInfoResponse;
+pub use self::import_descriptors::{ImportDescriptorRequest, ImportDescriptorResult, ImportTimestamp};
+pub use self::list_transactions_entry::ListTransactionsEntry;
+pub use self::list_unspent_entry::ListUnspentEntry;
 pub use self::memory_info::MemoryInfo;
+pub use self::mempool_entry::MempoolEntry;
 pub use self::mempool_info::MempoolInfo;
-pub use self::network_info::NetworkInfo;
+pub use self::mining_info::MiningInfo;
+pub use self::network_info::{Address as NetworkInfoAddress, NetworkInfo};
 pub use self::nodes::{AddNodeOperation, NodeInfo};
 pub use self::peer::Peer;
+pub use self::psbt::{
+	AnalyzePsbtInput, AnalyzePsbtMissing, AnalyzePsbtResponse, DecodedPsbt, DecodedPsbtInput, DecodedPsbtOutput, FinalizePsbtResponse,
+	PsbtBip32Derivation, PsbtScript, PsbtWitnessUtxo, PsbtXpub,
+};
+pub use self::save_mempool_response::SaveMempoolResponse;
 pub use self::script::ScriptType;
+pub use self::sign_raw_transaction::{PreviousTransactionOutput, SignRawTransactionError, SignRawTransactionResponse};
 pub use self::transaction::{
 	GetRawTransactionResponse, RawTransaction, SignedTransactionInput, SignedTransactionOutput, Transaction, TransactionInput,
 	TransactionInputScript, TransactionOutput, TransactionOutputScript, TransactionOutputWithAddress, TransactionOutputWithScriptData,
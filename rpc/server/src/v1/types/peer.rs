@@ -1,4 +1,5 @@
-use p2p::{Direction, PeerInfo};
+use bitcrypto::ToHex;
+use p2p::{Direction, PeerInfo, TransportVersion};
 
 impl From<&PeerInfo> for Peer {
 	fn from(info: &PeerInfo) -> Self {
@@ -11,6 +12,14 @@ impl From<&PeerInfo> for Peer {
 			},
 			subver: info.user_agent.clone(),
 			version: info.version,
+			transport_protocol_type: match info.transport {
+				TransportVersion::V1 => "v1".into(),
+				TransportVersion::V2 { .. } => "v2".into(),
+			},
+			session_id: match info.transport {
+				TransportVersion::V1 => String::new(),
+				TransportVersion::V2 { session_id } => session_id.to_hex(),
+			},
 			..Default::default()
 		}
 	}
@@ -54,6 +63,10 @@ pub struct Peer {
 	pub subver: String,
 	// Inbound (true) or Outbound (false)
 	pub inbound: bool,
+	// Type of the transport protocol: v1 (plaintext) or v2 (BIP324 encrypted)
+	pub transport_protocol_type: String,
+	// The session ID for this connection, or "" if there is none (v2 transport protocol only)
+	pub session_id: String,
 	// Whether connection was due to addnode/-connect or if it was an automatic/inbound connection
 	pub addnode: bool,
 	// The starting height (block) of the peer
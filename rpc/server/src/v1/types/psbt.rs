@@ -0,0 +1,212 @@
+use super::bytes::Bytes;
+use super::script::ScriptType;
+use super::transaction::{RawTransaction, Transaction, TransactionInputScript};
+use bitcrypto::{HASH160, SHA256, SHA256D};
+use std::collections::BTreeMap;
+
+/// Script of the PSBT input or output
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtScript {
+	/// Script code
+	pub asm: String,
+	/// Script hex
+	pub hex: Bytes,
+	/// Type of script
+	#[serde(rename = "type")]
+	pub script_type: ScriptType,
+}
+
+/// Output, spent by the segwit input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtWitnessUtxo {
+	/// Output value in BTC
+	pub amount: f64,
+	#[serde(rename = "scriptPubKey")]
+	pub script_pubkey: PsbtScript,
+}
+
+/// Extended public key of the PSBT
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtXpub {
+	pub xpub: String,
+	/// Fingerprint of the master key in hex
+	pub master_fingerprint: String,
+	/// Derivation path from the master key
+	pub path: String,
+}
+
+/// Origin of the public key
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtBip32Derivation {
+	pub pubkey: Bytes,
+	/// Fingerprint of the master key in hex
+	pub master_fingerprint: String,
+	/// Derivation path from the master key
+	pub path: String,
+}
+
+/// Decoded PSBT input
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct DecodedPsbtInput {
+	/// Transaction, spent by the input
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub non_witness_utxo: Option<Transaction>,
+	/// Output, spent by the segwit input
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_utxo: Option<PsbtWitnessUtxo>,
+	/// Signatures by public keys
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub partial_signatures: BTreeMap<String, Bytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sighash: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redeem_script: Option<PsbtScript>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_script: Option<PsbtScript>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub bip32_derivs: Vec<PsbtBip32Derivation>,
+	#[serde(rename = "final_scriptSig", skip_serializing_if = "Option::is_none")]
+	pub final_script_sig: Option<TransactionInputScript>,
+	#[serde(rename = "final_scriptwitness", skip_serializing_if = "Option::is_none")]
+	pub final_script_witness: Option<Vec<Bytes>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub taproot_key_path_sig: Option<Bytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub taproot_internal_key: Option<Bytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub taproot_merkle_root: Option<SHA256>,
+	/// Hash of the previous transaction (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub previous_txid: Option<SHA256D>,
+	/// Index of the previous transaction output (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub previous_vout: Option<u32>,
+	/// Sequence number (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sequence: Option<u32>,
+	/// Required time-based lock time (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub time_locktime: Option<u32>,
+	/// Required height-based lock time (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub height_locktime: Option<u32>,
+	/// Unknown key-value pairs in hex
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub unknown: BTreeMap<String, Bytes>,
+}
+
+/// Decoded PSBT output
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct DecodedPsbtOutput {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redeem_script: Option<PsbtScript>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_script: Option<PsbtScript>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub bip32_derivs: Vec<PsbtBip32Derivation>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub taproot_internal_key: Option<Bytes>,
+	/// Output value in BTC (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub amount: Option<f64>,
+	/// Output script (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub script: Option<PsbtScript>,
+	/// Unknown key-value pairs in hex
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub unknown: BTreeMap<String, Bytes>,
+}
+
+/// Return value of `decodepsbt` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DecodedPsbt {
+	/// Unsigned transaction (version 0 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tx: Option<Transaction>,
+	pub global_xpubs: Vec<PsbtXpub>,
+	/// Transaction version (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tx_version: Option<i32>,
+	/// Lock time, used if no input requires lock time (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fallback_locktime: Option<u32>,
+	/// Number of inputs (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub input_count: Option<usize>,
+	/// Number of outputs (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub output_count: Option<usize>,
+	/// Flags, allowing modification of the transaction (version 2 only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tx_modifiable: Option<u8>,
+	pub psbt_version: u32,
+	/// Unknown key-value pairs in hex
+	pub unknown: BTreeMap<String, Bytes>,
+	pub inputs: Vec<DecodedPsbtInput>,
+	pub outputs: Vec<DecodedPsbtOutput>,
+	/// Transaction fee in BTC, if all spent outputs are known
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+}
+
+/// Return value of `finalizepsbt` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FinalizePsbtResponse {
+	/// Base64-encoded PSBT, if transaction is not extracted
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub psbt: Option<String>,
+	/// Extracted transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub hex: Option<RawTransaction>,
+	/// Whether all inputs are finalized
+	pub complete: bool,
+}
+
+/// Data, required to finalize the input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnalyzePsbtMissing {
+	/// Hashes of unknown public keys
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub pubkeys: Vec<HASH160>,
+	/// Hashes of public keys, which haven't signed the input yet
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub signatures: Vec<HASH160>,
+	/// Hash of the unknown redeem script
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redeemscript: Option<HASH160>,
+	/// Hash of the unknown witness script
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witnessscript: Option<SHA256>,
+}
+
+/// Analysis of the PSBT input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnalyzePsbtInput {
+	/// Whether output, spent by the input, is known
+	pub has_utxo: bool,
+	pub is_final: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub missing: Option<AnalyzePsbtMissing>,
+	/// Role, which should process the input next
+	pub next: String,
+}
+
+/// Return value of `analyzepsbt` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnalyzePsbtResponse {
+	pub inputs: Vec<AnalyzePsbtInput>,
+	/// Virtual size of the finalized transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub estimated_vsize: Option<usize>,
+	/// Fee rate of the finalized transaction in BTC/kvB
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub estimated_feerate: Option<f64>,
+	/// Transaction fee in BTC
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+	/// Role, which should process the PSBT next
+	pub next: String,
+	/// Reason, why PSBT is invalid
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
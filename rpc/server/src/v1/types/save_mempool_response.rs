@@ -0,0 +1,6 @@
+/// Result of the memory pool dump
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SaveMempoolResponse {
+	/// Absolute path to the memory pool dump file
+	pub filename: String,
+}
@@ -0,0 +1,53 @@
+use super::bytes::Bytes;
+use super::transaction::RawTransaction;
+use bitcrypto::SHA256D;
+
+/// Output, spent by the signed transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PreviousTransactionOutput {
+	/// Previous transaction id
+	pub txid: SHA256D,
+	/// Previous transaction output index
+	pub vout: u32,
+	/// Script of the output
+	#[serde(rename = "scriptPubKey")]
+	pub script_pubkey: Bytes,
+	/// Redeem script of the P2SH output
+	#[serde(rename = "redeemScript")]
+	pub redeem_script: Option<Bytes>,
+	/// Witness script of the P2WSH output
+	#[serde(rename = "witnessScript")]
+	pub witness_script: Option<Bytes>,
+	/// Output value in BTC. Required for segwit outputs
+	pub amount: Option<f64>,
+}
+
+/// Input, which hasn't been completely signed
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SignRawTransactionError {
+	/// Previous transaction id
+	pub txid: SHA256D,
+	/// Previous transaction output index
+	pub vout: u32,
+	/// Witness of the input
+	pub witness: Vec<Bytes>,
+	/// Script of the input
+	#[serde(rename = "scriptSig")]
+	pub script_sig: Bytes,
+	/// Sequence number
+	pub sequence: u32,
+	/// Verification or signing error
+	pub error: String,
+}
+
+/// Return value of `signrawtransactionwithkey` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SignRawTransactionResponse {
+	/// Signed transaction
+	pub hex: RawTransaction,
+	/// Whether all inputs are completely signed
+	pub complete: bool,
+	/// Inputs, which haven't been completely signed
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub errors: Vec<SignRawTransactionError>,
+}
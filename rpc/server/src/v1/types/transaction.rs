@@ -142,6 +142,9 @@ pub struct Transaction {
 	/// The block time in seconds since epoch (Jan 1 1970 GMT)
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub blocktime: Option<u32>,
+	/// Whether this memory pool transaction could be replaced (BIP125)
+	#[serde(rename = "bip125-replaceable", skip_serializing_if = "Option::is_none")]
+	pub bip125_replaceable: Option<bool>,
 }
 
 /// Return value of `getrawtransaction` method
@@ -481,6 +484,7 @@ mod tests {
 			confirmations: Some(77),
 			time: Some(88),
 			blocktime: Some(99),
+			bip125_replaceable: None,
 		};
 		assert_eq!(
 			serde_json::to_string(&tx).unwrap(),
@@ -504,9 +508,34 @@ mod tests {
 			confirmations: Some(77),
 			time: Some(88),
 			blocktime: Some(99),
+			bip125_replaceable: None,
 		};
 		assert_eq!(
 			serde_json::from_str::<Transaction>(r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#).unwrap(),
 			tx);
 	}
+
+	#[test]
+	fn memory_pool_transaction_serialize() {
+		let tx = Transaction {
+			hex: None,
+			txid: SHA256D::from_str("0400000000000000000000000000000000000000000000000000000000000000").unwrap(),
+			hash: SHA256D::from_str("0500000000000000000000000000000000000000000000000000000000000000").unwrap(),
+			size: 33,
+			vsize: 44,
+			version: 55,
+			locktime: 66,
+			vin: vec![],
+			vout: vec![],
+			blockhash: None,
+			confirmations: None,
+			time: None,
+			blocktime: None,
+			bip125_replaceable: Some(true),
+		};
+		assert_eq!(
+			serde_json::to_string(&tx).unwrap(),
+			r#"{"txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"version":55,"locktime":66,"vin":[],"vout":[],"bip125-replaceable":true}"#
+		);
+	}
 }
@@ -2,7 +2,8 @@
 
 use crate::bytes::Bytes;
 use crate::{Num, Opcode, Script};
-use keys::AddressHash;
+use crypto::SHA256;
+use keys::{Address, AddressHash, Type, XOnlyPublic};
 
 /// Script builder
 #[derive(Default)]
@@ -13,20 +14,68 @@ pub struct Builder {
 impl Builder {
 	/// Builds p2pkh script pubkey
 	pub fn build_p2pkh(address: &AddressHash) -> Script {
+		Builder::p2pkh(address)
+	}
+
+	/// Builds p2sh script pubkey
+	pub fn build_p2sh(address: &AddressHash) -> Script {
+		Builder::p2sh(address)
+	}
+
+	/// Builds p2wpkh script pubkey
+	pub fn build_p2wpkh(address: &AddressHash) -> Script {
+		Builder::build_witness_program(0, address)
+	}
+
+	/// Builds p2wsh script pubkey
+	pub fn build_p2wsh(script_hash: &SHA256) -> Script {
+		Builder::build_witness_program(0, script_hash)
+	}
+
+	/// Builds p2tr script pubkey
+	pub fn build_p2tr(output_key: &XOnlyPublic) -> Script {
+		Builder::build_witness_program(1, output_key)
+	}
+
+	/// Builds witness program script pubkey
+	pub fn build_witness_program(version: u8, program: &[u8]) -> Script {
+		let version = match version {
+			0 => Opcode::OP_0,
+			_ => Opcode::from_u8(Opcode::OP_1 as u8 + version - 1).expect("witness version is within [0; 16] interval; qed"),
+		};
+
+		Builder::default().push_opcode(version).push_bytes(program).into_script()
+	}
+
+	/// Builds script pubkey, paying to given address
+	pub fn build_address(address: &Address) -> Script {
+		match address.kind {
+			Type::P2PKH => Builder::p2pkh(&address.hash),
+			Type::P2SH => Builder::p2sh(&address.hash),
+			Type::P2WPKH | Type::P2WSH | Type::P2TR => Builder::build_witness_program(
+				address
+					.kind
+					.witness_version()
+					.expect("segwit address types have witness version; qed"),
+				&address.hash,
+			),
+		}
+	}
+
+	fn p2pkh(hash: &[u8]) -> Script {
 		Builder::default()
 			.push_opcode(Opcode::OP_DUP)
 			.push_opcode(Opcode::OP_HASH160)
-			.push_bytes(&**address)
+			.push_bytes(hash)
 			.push_opcode(Opcode::OP_EQUALVERIFY)
 			.push_opcode(Opcode::OP_CHECKSIG)
 			.into_script()
 	}
 
-	/// Builds p2sh script pubkey
-	pub fn build_p2sh(address: &AddressHash) -> Script {
+	fn p2sh(hash: &[u8]) -> Script {
 		Builder::default()
 			.push_opcode(Opcode::OP_HASH160)
-			.push_bytes(&**address)
+			.push_bytes(hash)
 			.push_opcode(Opcode::OP_EQUAL)
 			.into_script()
 	}
@@ -66,6 +66,19 @@ pub enum Error {
 	WitnessMalleatedP2SH,
 	WitnessUnexpected,
 	WitnessPubKeyType,
+
+	// Taproot-related errors
+	SchnorrSigSize,
+	SchnorrSigHashtype,
+	SchnorrSig,
+	TaprootWrongControlSize,
+	TapscriptValidationWeight,
+	TapscriptCheckMultiSig,
+	TapscriptMinimalIf,
+	TapscriptEmptyPubkey,
+	DiscourageUpgradableTaprootVersion,
+	DiscourageOpSuccess,
+	DiscourageUpgradablePubkeyType,
 }
 
 impl fmt::Display for Error {
@@ -131,6 +144,19 @@ impl fmt::Display for Error {
 			Error::WitnessMalleatedP2SH => "Witness requires only-redeemscript scriptSig".fmt(f),
 			Error::WitnessUnexpected => "Witness provided for non-witness script".fmt(f),
 			Error::WitnessPubKeyType => "Using non-compressed keys in segwit".fmt(f),
+
+			// Taproot-related errors
+			Error::SchnorrSigSize => "Invalid Schnorr signature size".fmt(f),
+			Error::SchnorrSigHashtype => "Invalid Schnorr signature hash type".fmt(f),
+			Error::SchnorrSig => "Invalid Schnorr signature".fmt(f),
+			Error::TaprootWrongControlSize => "Invalid Taproot control block size".fmt(f),
+			Error::TapscriptValidationWeight => "Too much signature validation relative to witness weight".fmt(f),
+			Error::TapscriptCheckMultiSig => "OP_CHECKMULTISIG(VERIFY) is not available in tapscript".fmt(f),
+			Error::TapscriptMinimalIf => "OP_IF/NOTIF argument must be minimal in tapscript".fmt(f),
+			Error::TapscriptEmptyPubkey => "Empty public key in tapscript".fmt(f),
+			Error::DiscourageUpgradableTaprootVersion => "Discourage Upgradable Taproot Version".fmt(f),
+			Error::DiscourageOpSuccess => "Discourage OP_SUCCESSx".fmt(f),
+			Error::DiscourageUpgradablePubkeyType => "Discourage Upgradable Pubkey Type".fmt(f),
 		}
 	}
 }
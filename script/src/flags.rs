@@ -66,6 +66,18 @@ pub struct VerificationFlags {
 	/// Making v1-v16 witness program non-standard
 	pub verify_discourage_upgradable_witness_program: bool,
 
+	/// Support taproot (BIP341) and tapscript (BIP342) validation
+	pub verify_taproot: bool,
+
+	/// Making unknown taproot leaf versions non-standard
+	pub verify_discourage_upgradable_taproot_version: bool,
+
+	/// Making OP_SUCCESSx opcodes in tapscript non-standard
+	pub verify_discourage_op_success: bool,
+
+	/// Making unknown public key versions in tapscript non-standard
+	pub verify_discourage_upgradable_pubkeytype: bool,
+
 	/// Support OP_CAT opcode
 	pub verify_concat: bool,
 
@@ -151,6 +163,26 @@ impl VerificationFlags {
 		self
 	}
 
+	pub fn verify_taproot(mut self, value: bool) -> Self {
+		self.verify_taproot = value;
+		self
+	}
+
+	pub fn verify_discourage_upgradable_taproot_version(mut self, value: bool) -> Self {
+		self.verify_discourage_upgradable_taproot_version = value;
+		self
+	}
+
+	pub fn verify_discourage_op_success(mut self, value: bool) -> Self {
+		self.verify_discourage_op_success = value;
+		self
+	}
+
+	pub fn verify_discourage_upgradable_pubkeytype(mut self, value: bool) -> Self {
+		self.verify_discourage_upgradable_pubkeytype = value;
+		self
+	}
+
 	pub fn verify_concat(mut self, value: bool) -> Self {
 		self.verify_concat = value;
 		self
@@ -1,10 +1,14 @@
 use crate::bytes::Bytes;
-use crate::script::MAX_SCRIPT_ELEMENT_SIZE;
-use crate::sign::{Sighash, SignatureVersion};
+use crate::script::{
+	ANNEX_TAG, MAX_SCRIPT_ELEMENT_SIZE, MAX_STACK_SIZE, TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_MAX_SIZE, TAPROOT_CONTROL_NODE_SIZE,
+	TAPROOT_LEAF_MASK, TAPROOT_LEAF_TAPSCRIPT, VALIDATION_WEIGHT_OFFSET, VALIDATION_WEIGHT_PER_SIGOP_PASSED,
+};
+use crate::sign::{ScriptExecutionData, Sighash, SignatureVersion};
 use crate::{script, Builder, Error, Num, Opcode, Script, ScriptWitness, SignatureChecker, Stack, VerificationFlags};
 use chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
-use crypto::{dhash160, dhash256, ripemd160, sha1, sha256, Hash, SHA256};
-use keys::{Public, Signature};
+use crypto::{dhash160, dhash256, ripemd160, sha1, sha256, tagged_hash, Hash, SHA256};
+use keys::{Public, Signature, XOnlyPublic};
+use ser::{serialize, serialized_list_size, Stream};
 use std::{cmp, mem};
 
 /// Helper function.
@@ -27,6 +31,121 @@ fn check_signature(
 	}
 }
 
+/// Helper function. Splits optional hashtype from BIP340 signature and checks it.
+fn check_schnorr_signature(
+	checker: &dyn SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	version: SignatureVersion,
+	execdata: &ScriptExecutionData,
+) -> Result<(), Error> {
+	let public = XOnlyPublic::from_slice(public).map_err(|_| Error::SchnorrSig)?;
+	let (signature, sighashtype) = match signature.len() {
+		64 => (signature, 0),
+		// SIGHASH_DEFAULT must be encoded by omitting the hashtype byte
+		65 if signature[64] == 0 => return Err(Error::SchnorrSigHashtype),
+		65 => (&signature[..64], signature[64] as u32),
+		_ => return Err(Error::SchnorrSigSize),
+	};
+
+	checker.check_schnorr_signature(signature, &public, sighashtype, version, execdata)
+}
+
+/// Evaluates signature check of OP_CHECKSIG, OP_CHECKSIGVERIFY and OP_CHECKSIGADD in tapscript.
+///
+/// The order of the checks is consensus critical: upgradable public key versions precede
+/// other rules, empty public key fails the script even with empty signature and non-empty
+/// invalid signature fails the script.
+fn eval_checksig_tapscript(
+	signature: &[u8],
+	public: &[u8],
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+	let success = !signature.is_empty();
+	if success {
+		// passing with an upgradable public key version is also counted
+		execdata.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+		if execdata.validation_weight_left < 0 {
+			return Err(Error::TapscriptValidationWeight);
+		}
+	}
+
+	match public.len() {
+		0 => return Err(Error::TapscriptEmptyPubkey),
+		32 => {
+			if success {
+				check_schnorr_signature(checker, signature, public, SignatureVersion::Tapscript, execdata)?;
+			}
+		}
+		_ => {
+			if flags.verify_discourage_upgradable_pubkeytype {
+				return Err(Error::DiscourageUpgradablePubkeyType);
+			}
+		}
+	}
+
+	Ok(success)
+}
+
+/// Computes the hash of a taproot script tree leaf
+fn compute_tapleaf_hash(leaf_version: u8, script: &[u8]) -> SHA256 {
+	let mut stream = Stream::default();
+	stream.append(&leaf_version);
+	stream.append_list(script);
+	tagged_hash(b"TapLeaf", &stream.out())
+}
+
+/// Checks that the control block proves that tapleaf is committed to by the witness program
+fn verify_taproot_commitment(control: &[u8], program: &[u8], tapleaf_hash: &SHA256) -> bool {
+	let internal = match XOnlyPublic::from_slice(&control[1..TAPROOT_CONTROL_BASE_SIZE]) {
+		Ok(internal) => internal,
+		Err(_) => return false,
+	};
+	let output = match XOnlyPublic::from_slice(program) {
+		Ok(output) => output,
+		Err(_) => return false,
+	};
+
+	let mut node = *tapleaf_hash;
+	for branch in control[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE) {
+		let mut data = Vec::with_capacity(2 * TAPROOT_CONTROL_NODE_SIZE);
+		if &node[..] < branch {
+			data.extend_from_slice(&node[..]);
+			data.extend_from_slice(branch);
+		} else {
+			data.extend_from_slice(branch);
+			data.extend_from_slice(&node[..]);
+		}
+		node = tagged_hash(b"TapBranch", &data);
+	}
+
+	let mut data = internal.to_vec();
+	data.extend_from_slice(&node[..]);
+	let tweak = tagged_hash(b"TapTweak", &data);
+	internal.check_tweak(&output, control[0] & 1 == 1, &tweak)
+}
+
+/// Returns true if tapscript contains any OP_SUCCESSx opcode.
+fn contains_op_success(script: &Script) -> Result<bool, Error> {
+	let mut pc = 0;
+	while pc < script.len() {
+		if Opcode::is_success(script[pc]) {
+			return Ok(true);
+		}
+
+		match script.get_instruction(pc) {
+			Ok(instruction) => pc += instruction.step,
+			// unknown opcodes fail the script only when executed
+			Err(Error::BadOpcode) if Opcode::from_u8(script[pc]).is_none() => pc += 1,
+			Err(err) => return Err(err),
+		}
+	}
+
+	Ok(false)
+}
+
 fn is_public_key(v: &[u8]) -> bool {
 	match v.len() {
 		33 if v[0] == 2 || v[0] == 3 => true,
@@ -259,7 +378,7 @@ pub fn verify_script(
 
 			had_witness = true;
 			verify_cleanstack = false;
-			if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+			if !verify_witness_program(witness, witness_version, witness_program, flags, checker, false)? {
 				return Err(Error::EvalFalse);
 			}
 		}
@@ -293,7 +412,7 @@ pub fn verify_script(
 
 				had_witness = true;
 				verify_cleanstack = false;
-				if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+				if !verify_witness_program(witness, witness_version, witness_program, flags, checker, true)? {
 					return Err(Error::EvalFalse);
 				}
 			}
@@ -331,7 +450,67 @@ fn verify_witness_program(
 	witness_program: &[u8],
 	flags: &VerificationFlags,
 	checker: &dyn SignatureChecker,
+	is_p2sh: bool,
 ) -> Result<bool, Error> {
+	let mut execdata = ScriptExecutionData::default();
+
+	if witness_version == 1 && witness_program.len() == 32 && !is_p2sh {
+		if !flags.verify_taproot {
+			return Ok(true);
+		}
+
+		let mut stack = witness.clone();
+		if stack.is_empty() {
+			return Err(Error::WitnessProgramWitnessEmpty);
+		}
+
+		if stack.len() >= 2 && stack.last().is_some_and(|last| !last.is_empty() && last[0] == ANNEX_TAG) {
+			let annex = stack.pop().expect("stack.len() >= 2; qed");
+			execdata.annex_hash = Some(sha256(&serialize(&annex)));
+		}
+
+		if stack.len() == 1 {
+			// key path spending
+			check_schnorr_signature(checker, &stack[0], witness_program, SignatureVersion::Taproot, &execdata)?;
+			return Ok(true);
+		}
+
+		// script path spending
+		let control = stack.pop().expect("stack.len() >= 2; qed");
+		let script = stack.pop().expect("stack.len() >= 2; qed");
+		if control.len() < TAPROOT_CONTROL_BASE_SIZE
+			|| control.len() > TAPROOT_CONTROL_MAX_SIZE
+			|| !(control.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+		{
+			return Err(Error::TaprootWrongControlSize);
+		}
+
+		let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+		let tapleaf_hash = compute_tapleaf_hash(leaf_version, &script);
+		if !verify_taproot_commitment(&control, witness_program, &tapleaf_hash) {
+			return Err(Error::WitnessProgramMismatch);
+		}
+		execdata.tapleaf_hash = Some(tapleaf_hash);
+
+		if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+			if flags.verify_discourage_upgradable_taproot_version {
+				return Err(Error::DiscourageUpgradableTaprootVersion);
+			}
+
+			return Ok(true);
+		}
+
+		execdata.validation_weight_left = serialized_list_size::<Bytes, Bytes>(witness) as i64 + VALIDATION_WEIGHT_OFFSET;
+		return execute_witness_script(
+			stack.into(),
+			&script.into(),
+			flags,
+			checker,
+			SignatureVersion::Tapscript,
+			&mut execdata,
+		);
+	}
+
 	if witness_version != 0 {
 		if flags.verify_discourage_upgradable_witness_program {
 			return Err(Error::DiscourageUpgradableWitnessProgram);
@@ -342,7 +521,7 @@ fn verify_witness_program(
 
 	let witness_stack = witness;
 	let witness_stack_len = witness_stack.len();
-	let (mut stack, script_pubkey): (Stack<_>, Script) = match witness_program.len() {
+	let (stack, script_pubkey): (Stack<_>, Script) = match witness_program.len() {
 		32 => {
 			if witness_stack_len == 0 {
 				return Err(Error::WitnessProgramWitnessEmpty);
@@ -376,11 +555,38 @@ fn verify_witness_program(
 		_ => return Err(Error::WitnessProgramWrongLength),
 	};
 
+	execute_witness_script(stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0, &mut execdata)
+}
+
+fn execute_witness_script(
+	mut stack: Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+	if version == SignatureVersion::Tapscript {
+		// OP_SUCCESSx processing overrides everything, including stack element size limits
+		if contains_op_success(script)? {
+			if flags.verify_discourage_op_success {
+				return Err(Error::DiscourageOpSuccess);
+			}
+
+			return Ok(true);
+		}
+
+		// tapscript enforces initial stack size limit (altstack is empty here)
+		if stack.len() > MAX_STACK_SIZE {
+			return Err(Error::StackSize);
+		}
+	}
+
 	if stack.iter().any(|s| s.len() > MAX_SCRIPT_ELEMENT_SIZE) {
 		return Err(Error::PushSize);
 	}
 
-	if !eval_script(&mut stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0)? {
+	if !eval_script_with_data(&mut stack, script, flags, checker, version, execdata)? {
 		return Ok(false);
 	}
 
@@ -397,7 +603,6 @@ fn verify_witness_program(
 }
 
 /// Evaluautes the script
-#[cfg_attr(feature = "cargo-clippy", allow(match_same_arms))]
 pub fn eval_script(
 	stack: &mut Stack<Bytes>,
 	script: &Script,
@@ -405,18 +610,36 @@ pub fn eval_script(
 	checker: &dyn SignatureChecker,
 	version: SignatureVersion,
 ) -> Result<bool, Error> {
-	if script.len() > script::MAX_SCRIPT_SIZE {
+	eval_script_with_data(stack, script, flags, checker, version, &mut ScriptExecutionData::default())
+}
+
+#[allow(clippy::match_same_arms)]
+fn eval_script_with_data(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+	// tapscript has no script size and opcode count limits
+	let is_tapscript = version == SignatureVersion::Tapscript;
+	if !is_tapscript && script.len() > script::MAX_SCRIPT_SIZE {
 		return Err(Error::ScriptSize);
 	}
 
 	let mut pc = 0;
 	let mut op_count = 0;
+	let mut opcode_pos = 0u32;
 	let mut begincode = 0;
 	let mut exec_stack = Vec::<bool>::new();
 	let mut altstack = Stack::<Bytes>::new();
+	execdata.codeseparator_pos = u32::MAX;
 
 	while pc < script.len() {
 		let executing = exec_stack.iter().all(|x| *x);
+		let current_opcode_pos = opcode_pos;
+		opcode_pos += 1;
 		let instruction = match script.get_instruction(pc) {
 			Ok(i) => i,
 			Err(Error::BadOpcode) if !executing => {
@@ -437,7 +660,7 @@ pub fn eval_script(
 			}
 		}
 
-		if opcode.is_countable() {
+		if !is_tapscript && opcode.is_countable() {
 			op_count += 1;
 			if op_count > script::MAX_OPS_PER_SCRIPT {
 				return Err(Error::OpCount);
@@ -749,7 +972,12 @@ pub fn eval_script(
 			Opcode::OP_IF | Opcode::OP_NOTIF => {
 				let mut exec_value = false;
 				if executing {
-					exec_value = cast_to_bool(&stack.pop().map_err(|_| Error::UnbalancedConditional)?);
+					let value = stack.pop().map_err(|_| Error::UnbalancedConditional)?;
+					// minimal if is a consensus rule in tapscript
+					if is_tapscript && (value.len() > 1 || (value.len() == 1 && value[0] != 1)) {
+						return Err(Error::TapscriptMinimalIf);
+					}
+					exec_value = cast_to_bool(&value);
 					if opcode == Opcode::OP_NOTIF {
 						exec_value = !exec_value;
 					}
@@ -998,17 +1226,33 @@ pub fn eval_script(
 			}
 			Opcode::OP_CODESEPARATOR => {
 				begincode = pc;
+				execdata.codeseparator_pos = current_opcode_pos;
+			}
+			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY if is_tapscript => {
+				let pubkey = stack.pop()?;
+				let signature = stack.pop()?;
+				let success = eval_checksig_tapscript(&signature, &pubkey, flags, checker, execdata)?;
+				match opcode {
+					Opcode::OP_CHECKSIG => {
+						if success {
+							stack.push(vec![1].into());
+						} else {
+							stack.push(Bytes::new());
+						}
+					}
+					Opcode::OP_CHECKSIGVERIFY if !success => {
+						return Err(Error::CheckSigVerify);
+					}
+					_ => {}
+				}
 			}
 			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY => {
 				let pubkey = stack.pop()?;
 				let signature = stack.pop()?;
 				let mut subscript = script.subscript(begincode);
-				match version {
-					SignatureVersion::WitnessV0 => (),
-					SignatureVersion::Base => {
-						let signature_script = Builder::default().push_data(&*signature).into_script();
-						subscript = subscript.find_and_delete(&*signature_script);
-					}
+				if version == SignatureVersion::Base {
+					let signature_script = Builder::default().push_data(&*signature).into_script();
+					subscript = subscript.find_and_delete(&*signature_script);
 				}
 
 				check_signature_encoding(&signature, flags)?;
@@ -1029,6 +1273,23 @@ pub fn eval_script(
 					_ => {}
 				}
 			}
+			Opcode::OP_CHECKSIGADD => {
+				// OP_CHECKSIGADD is only available in tapscript
+				if !is_tapscript {
+					return Err(Error::BadOpcode);
+				}
+
+				// (sig num pubkey -- num)
+				stack.require(3)?;
+				let pubkey = stack.pop()?;
+				let n = Num::from_slice(&stack.pop()?, flags.verify_minimaldata, 4)?;
+				let signature = stack.pop()?;
+				let success = eval_checksig_tapscript(&signature, &pubkey, flags, checker, execdata)?;
+				stack.push((n + Num::from(success)).to_bytes());
+			}
+			Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY if is_tapscript => {
+				return Err(Error::TapscriptCheckMultiSig);
+			}
 			Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
 				let keys_count = Num::from_slice(&stack.pop()?, flags.verify_minimaldata, 4)?;
 				if keys_count < 0.into() || keys_count > script::MAX_PUBKEYS_PER_MULTISIG.into() {
@@ -1048,13 +1309,10 @@ pub fn eval_script(
 
 				let mut subscript = script.subscript(begincode);
 
-				for signature in &sigs {
-					match version {
-						SignatureVersion::WitnessV0 => (),
-						SignatureVersion::Base => {
-							let signature_script = Builder::default().push_data(&*signature).into_script();
-							subscript = subscript.find_and_delete(&*signature_script);
-						}
+				if version == SignatureVersion::Base {
+					for signature in &sigs {
+						let signature_script = Builder::default().push_data(&*signature).into_script();
+						subscript = subscript.find_and_delete(&*signature_script);
 					}
 				}
 
@@ -1105,7 +1363,7 @@ pub fn eval_script(
 			}
 		}
 
-		if stack.len() + altstack.len() > 1000 {
+		if stack.len() + altstack.len() > MAX_STACK_SIZE {
 			return Err(Error::StackSize);
 		}
 	}
@@ -1124,15 +1382,18 @@ pub fn eval_script(
 
 #[cfg(test)]
 mod tests {
-	use super::{eval_script, is_public_key, verify_script};
+	use super::{compute_tapleaf_hash, eval_script, is_public_key, verify_script, verify_taproot_commitment};
 	use crate::bytes::Bytes;
 	use crate::script::MAX_SCRIPT_ELEMENT_SIZE;
-	use crate::sign::SignatureVersion;
+	use crate::sign::{ScriptExecutionData, SignatureVersion};
 	use crate::{
 		Builder, Error, NoopSignatureChecker, Num, Opcode, Script, ScriptWitness, Stack, TransactionInputSigner,
 		TransactionSignatureChecker, VerificationFlags,
 	};
-	use chain::Transaction;
+	use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+	use crypto::{tagged_hash, SHA256};
+	use keys::{Private, XOnlyPublic};
+	use std::{env, fs, path};
 
 	#[test]
 	fn tests_is_public_key() {
@@ -4045,4 +4306,515 @@ mod tests {
 			Ok(())
 		);
 	}
+
+	fn run_taproot_commitment_test(script_pubkey: &'static str, script: &'static str, control: &'static str) {
+		let script_pubkey: Bytes = script_pubkey.into();
+		let script: Bytes = script.into();
+		let control: Bytes = control.into();
+		let tapleaf_hash = compute_tapleaf_hash(control[0] & 0xfe, &script);
+		assert!(verify_taproot_commitment(&control, &script_pubkey[2..], &tapleaf_hash));
+
+		let mut control = control.to_vec();
+		control[1] ^= 1;
+		assert!(!verify_taproot_commitment(&control, &script_pubkey[2..], &tapleaf_hash));
+	}
+
+	// test vectors printed by feature_taproot.py of Bitcoin Core
+	#[test]
+	fn test_taproot_commitment() {
+		run_taproot_commitment_test("51205dc8e62b15e0ebdf44751676be35ba32eed2e84608b290d4061bbff136cd7ba9", "6a", "c1a9d6f66cd4b25004f526bfa873e56942f98e8e492bd79ed6532b966104817c2bda584e7d32612381cf88edc1c02e28a296e807c16ad22f591ee113946e48a71e0641e660d1e5392fb79d64838c2b84faf04b7f5f283c9d8bf83e39e177b64372a0cd22eeab7e093873e851e247714eff762d8a30be699ba4456cfe6491b282e193a071350ae099005a5950d74f73ba13077a57bc478007fb0e4d1099ce9cf3d4");
+		run_taproot_commitment_test("5120e208c869c40d8827101c5ad3238018de0f3f5183d77a0c53d18ac28ddcbcd8ad", "f4", "c0a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f40090ab1f4890d51115998242ebce636efb9ede1b516d9eb8952dc1068e0335306199aaf103cceb41d9bc37ec231aca89b984b5fd3c65977ce764d51033ac65adb4da14e029b1e154a85bfd9139e7aa2720b6070a4ceba8264ca61d5d3ac27aceb9ef4b54cd43c2d1fd5e11b5c2e93cf29b91ea3dc5b832201f02f7473a28c63246");
+		run_taproot_commitment_test(
+			"5120567666e7df90e0450bb608e17c01ed3fbcfa5355a5f8273e34e583bfaa70ce09",
+			"203455139bf238a3067bd72ed77e0ab8db590330f55ed58dba7366b53bf4734279ac",
+			"c1a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f400",
+		);
+		run_taproot_commitment_test("5120580a19e47269414a55eb86d5d0c6c9b371455d9fd2154412a57dec840df99fe1", "6a", "bca0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f40042ba1bd1c63c03ccff60d4c4d53a653f87909eb3358e7fa45c9d805231fb08c933e1f4e0f9d17f591df1419df7d5b7eb5f744f404c5ef9ecdb1b89b18cafa3a816d8b5dba3205f9a9c05f866d91f40d2793a7586d502cb42f46c7a11f66ad4aa");
+		run_taproot_commitment_test("5120228b94a4806254a38d6efa8a134c28ebc89546209559dfe40b2b0493bafacc5b", "6a50", "c0a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f4009c9aed3dfd11ab0e78bf87ef3bf296269dc4b0f7712140386d6980992bab4b45");
+		run_taproot_commitment_test(
+			"5120b0a79103c31fe51eea61d2873bad8a25a310da319d7e7a85f825fa7a00ea3f85",
+			"203455139bf238a3067bd72ed77e0ab8db590330f55ed58dba7366b53bf4734279ad51",
+			"c1a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f400",
+		);
+		run_taproot_commitment_test("5120a32b0b8cfafe0f0f8d5870030ba4d19a8725ad345cb3c8420f86ac4e0dff6207", "4c", "e8a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f400615da7ac8d078e5fc7f4690fc2127ba40f0f97cc070ade5b3a7919783d91ef3f13734aab908ae998e57848a01268fe8217d70bc3ee8ea8ceae158ae964a4b5f3af20b50d7019bf47fde210eee5c52f1cfe71cfca78f2d3e7c1fd828c80351525");
+		run_taproot_commitment_test("5120017316303aed02bcdec424c851c9eacbe192b013139bd9634c4e19b3475b06e1", "61", "02a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f40050462265ca552b23cbb4fe021b474313c8cb87d4a18b3f7bdbeb2b418279ba31fc6509d829cd42336f563363cb3538d78758e0876c71e13012eb2b656eb0edb051a2420a840d5c8c6c762abc7410af2c311f606b20ca2ace56a8139f84b1379a");
+		run_taproot_commitment_test("512093c7378d96518a75448821c4f7c8f4bae7ce60f804d03d1f0628dd5dd0f5de51", "04ffffffff203455139bf238a3067bd72ed77e0ab8db590330f55ed58dba7366b53bf4734279ba04feffffff87ab", "c1a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f400c9a5cd1f6c8a81f5648e39f9810591df1c9a8f1fe97c92e03ecd7c0c016c951983e05473c6e8238cb4c780ea2ce62552b2a3eee068ceffc00517cd7b97e10dad");
+		run_taproot_commitment_test("5120ee9aecb28f5f35ce1f8b5ec80275ac0f81bca4a21b29b4632fb4bcbef8823e6a", "2021a5981b13be29c9d4ea179ea44a8b773ea8c02d68f6f6eefd98de20d4bd055fac", "c13359c284c196b6e80f0cf1d93b6a397cf7ee722f0427b705bd954b88ada8838bd2622fd0e104fc50aa763b43c6a792d7d117029983abd687223b4344a9402c618bba7f5fc3fa8a57491f6842acde88c1e675ca35caea3b1a69ee2c2d9b10f615");
+	}
+
+	fn taproot_flags() -> VerificationFlags {
+		VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+	}
+
+	/// Returns output script committing to the internal key and optional script tree, and parity of the output key
+	fn taproot_output(internal: &XOnlyPublic, merkle_root: Option<&SHA256>) -> (Script, bool) {
+		let mut data = internal.to_vec();
+		if let Some(merkle_root) = merkle_root {
+			data.extend_from_slice(&merkle_root[..]);
+		}
+		let (output, parity) = internal.tweak_add(&tagged_hash(b"TapTweak", &data)).unwrap();
+		let script = Builder::default().push_opcode(Opcode::OP_1).push_data(&output).into_script();
+		(script, parity)
+	}
+
+	fn taproot_checker(script_pubkey: &Script) -> TransactionSignatureChecker {
+		let tx = Transaction {
+			version: 2,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint {
+					hash: Default::default(),
+					index: 0,
+				},
+				script_sig: Bytes::new(),
+				sequence: 0xffff_ffff,
+				script_witness: vec![],
+			}],
+			outputs: vec![TransactionOutput {
+				value: 90_000,
+				script_pubkey: Builder::default().push_opcode(Opcode::OP_1).into_bytes(),
+			}],
+			lock_time: 0,
+		};
+		let mut signer: TransactionInputSigner = tx.into();
+		signer.spent_outputs = vec![TransactionOutput {
+			value: 100_000,
+			script_pubkey: script_pubkey.to_bytes(),
+		}];
+		TransactionSignatureChecker {
+			signer,
+			input_index: 0,
+			input_amount: 100_000,
+		}
+	}
+
+	fn sign_taproot(checker: &TransactionSignatureChecker, private: &Private, sighashtype: u32, execdata: &ScriptExecutionData) -> Bytes {
+		let version = match execdata.tapleaf_hash {
+			Some(_) => SignatureVersion::Tapscript,
+			None => SignatureVersion::Taproot,
+		};
+		let hash = checker.signer.signature_hash_schnorr(0, sighashtype, version, execdata).unwrap();
+		let mut signature: Vec<u8> = private.sign_schnorr(&hash).unwrap().into();
+		if sighashtype != 0 {
+			signature.push(sighashtype as u8);
+		}
+		signature.into()
+	}
+
+	fn run_tapscript_test(script: &Script, leaf_version: u8, stack: Vec<Bytes>, flags: &VerificationFlags, expected: Result<(), Error>) {
+		let private: Private = "KxDQjJwvLdNNGhsipGgmceWaPjRndZuaQB9B2tgdHsw5sQ8Rtqje".into();
+		let internal = private.xonly_public().unwrap();
+		let tapleaf_hash = compute_tapleaf_hash(leaf_version, script);
+		let (script_pubkey, parity) = taproot_output(&internal, Some(&tapleaf_hash));
+		let checker = taproot_checker(&script_pubkey);
+
+		let mut control = vec![leaf_version | parity as u8];
+		control.extend_from_slice(&internal);
+		let mut witness = stack;
+		witness.push(script.to_bytes());
+		witness.push(control.into());
+
+		let result = verify_script(
+			&Script::new(Bytes::new()),
+			&script_pubkey,
+			&witness,
+			flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, expected);
+	}
+
+	#[test]
+	fn test_taproot_key_path() {
+		let private: Private = "KxDQjJwvLdNNGhsipGgmceWaPjRndZuaQB9B2tgdHsw5sQ8Rtqje".into();
+		let internal = private.xonly_public().unwrap();
+		let (script_pubkey, _) = taproot_output(&internal, None);
+		let tweaked = private.tweak_xonly(&tagged_hash(b"TapTweak", &internal)).unwrap();
+		let checker = taproot_checker(&script_pubkey);
+		let flags = taproot_flags();
+		let execdata = ScriptExecutionData::default();
+		let verify = |witness: Vec<Bytes>, flags: &VerificationFlags| {
+			verify_script(
+				&Script::new(Bytes::new()),
+				&script_pubkey,
+				&witness,
+				flags,
+				&checker,
+				SignatureVersion::Base,
+			)
+		};
+
+		for sighashtype in &[0x00, 0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+			let signature = sign_taproot(&checker, &tweaked, *sighashtype, &execdata);
+			assert_eq!(verify(vec![signature], &flags), Ok(()));
+		}
+
+		// signature of internal key
+		let signature = sign_taproot(&checker, &private, 0x00, &execdata);
+		assert_eq!(verify(vec![signature.clone()], &flags), Err(Error::SchnorrSig));
+		// before taproot activation witness v1 programs are anyone-can-spend
+		assert_eq!(
+			verify(
+				vec![signature],
+				&VerificationFlags::default().verify_p2sh(true).verify_witness(true)
+			),
+			Ok(())
+		);
+
+		let signature = sign_taproot(&checker, &tweaked, 0x00, &execdata);
+		// SIGHASH_DEFAULT must not be explicit
+		let mut explicit_default = signature.to_vec();
+		explicit_default.push(0x00);
+		assert_eq!(verify(vec![explicit_default.into()], &flags), Err(Error::SchnorrSigHashtype));
+		let mut invalid_hashtype = signature.to_vec();
+		invalid_hashtype.push(0x04);
+		assert_eq!(verify(vec![invalid_hashtype.into()], &flags), Err(Error::SchnorrSigHashtype));
+		assert_eq!(verify(vec![signature[..63].to_vec().into()], &flags), Err(Error::SchnorrSigSize));
+		assert_eq!(verify(vec![], &flags), Err(Error::WitnessProgramWitnessEmpty));
+
+		// annex is committed to by the signature
+		let annex: Bytes = "50010203".into();
+		assert_eq!(verify(vec![signature.clone(), annex.clone()], &flags), Err(Error::SchnorrSig));
+		let execdata = ScriptExecutionData {
+			annex_hash: Some(crypto::sha256(&ser::serialize(&annex))),
+			..Default::default()
+		};
+		let signature = sign_taproot(&checker, &tweaked, 0x00, &execdata);
+		assert_eq!(verify(vec![signature, annex], &flags), Ok(()));
+	}
+
+	#[test]
+	fn test_taproot_script_path_checksig() {
+		let private: Private = "KxDQjJwvLdNNGhsipGgmceWaPjRndZuaQB9B2tgdHsw5sQ8Rtqje".into();
+		let internal = private.xonly_public().unwrap();
+		let script = Builder::default()
+			.push_data(&internal)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		let tapleaf_hash = compute_tapleaf_hash(0xc0, &script);
+		let (script_pubkey, parity) = taproot_output(&internal, Some(&tapleaf_hash));
+		let checker = taproot_checker(&script_pubkey);
+		let flags = taproot_flags();
+		let mut control = vec![0xc0 | parity as u8];
+		control.extend_from_slice(&internal);
+		let execdata = ScriptExecutionData {
+			tapleaf_hash: Some(tapleaf_hash),
+			codeseparator_pos: 0xffff_ffff,
+			..Default::default()
+		};
+		let verify = |witness: Vec<Bytes>| {
+			verify_script(
+				&Script::new(Bytes::new()),
+				&script_pubkey,
+				&witness,
+				&flags,
+				&checker,
+				SignatureVersion::Base,
+			)
+		};
+
+		let signature = sign_taproot(&checker, &private, 0x01, &execdata);
+		assert_eq!(verify(vec![signature.clone(), script.to_bytes(), control.clone().into()]), Ok(()));
+
+		// key path signature of the same key commits to different data
+		let key_path_signature = sign_taproot(&checker, &private, 0x01, &ScriptExecutionData::default());
+		assert_eq!(
+			verify(vec![key_path_signature, script.to_bytes(), control.clone().into()]),
+			Err(Error::SchnorrSig)
+		);
+
+		// empty signature is a valid failure
+		assert_eq!(
+			verify(vec![Bytes::new(), script.to_bytes(), control.clone().into()]),
+			Err(Error::EvalFalse)
+		);
+
+		// merkle proof does not match
+		let mut wrong_parity = control.clone();
+		wrong_parity[0] ^= 1;
+		assert_eq!(
+			verify(vec![signature.clone(), script.to_bytes(), wrong_parity.into()]),
+			Err(Error::WitnessProgramMismatch)
+		);
+
+		let mut wrong_size = control.clone();
+		wrong_size.push(0);
+		assert_eq!(
+			verify(vec![signature.clone(), script.to_bytes(), wrong_size.into()]),
+			Err(Error::TaprootWrongControlSize)
+		);
+		assert_eq!(
+			verify(vec![signature, script.to_bytes(), control[..32].to_vec().into()]),
+			Err(Error::TaprootWrongControlSize)
+		);
+	}
+
+	#[test]
+	fn test_tapscript_checksigadd() {
+		let private1: Private = "KxDQjJwvLdNNGhsipGgmceWaPjRndZuaQB9B2tgdHsw5sQ8Rtqje".into();
+		let private2: Private = "5HusYj2b2x4nroApgfvaSfKYZhRbKFH41bVyPooymbC6KfgSXdD".into();
+		let public1 = private1.xonly_public().unwrap();
+		let public2 = private2.xonly_public().unwrap();
+		let script = Builder::default()
+			.push_data(&public1)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.push_data(&public2)
+			.push_opcode(Opcode::OP_CHECKSIGADD)
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_NUMEQUAL)
+			.into_script();
+		let tapleaf_hash = compute_tapleaf_hash(0xc0, &script);
+		let (script_pubkey, parity) = taproot_output(&public1, Some(&tapleaf_hash));
+		let checker = taproot_checker(&script_pubkey);
+		let flags = taproot_flags();
+		let mut control = vec![0xc0 | parity as u8];
+		control.extend_from_slice(&public1);
+		let execdata = ScriptExecutionData {
+			tapleaf_hash: Some(tapleaf_hash),
+			codeseparator_pos: 0xffff_ffff,
+			..Default::default()
+		};
+		let signature1 = sign_taproot(&checker, &private1, 0x00, &execdata);
+		let signature2 = sign_taproot(&checker, &private2, 0x00, &execdata);
+		let verify = |witness: Vec<Bytes>| {
+			verify_script(
+				&Script::new(Bytes::new()),
+				&script_pubkey,
+				&witness,
+				&flags,
+				&checker,
+				SignatureVersion::Base,
+			)
+		};
+
+		assert_eq!(
+			verify(vec![
+				signature2.clone(),
+				signature1.clone(),
+				script.to_bytes(),
+				control.clone().into()
+			]),
+			Ok(())
+		);
+		assert_eq!(
+			verify(vec![Bytes::new(), signature1.clone(), script.to_bytes(), control.clone().into()]),
+			Err(Error::EvalFalse)
+		);
+		assert_eq!(
+			verify(vec![signature1, signature2, script.to_bytes(), control.into()]),
+			Err(Error::SchnorrSig)
+		);
+
+		// OP_CHECKSIGADD is not available outside of tapscript
+		let script = Builder::default().push_opcode(Opcode::OP_CHECKSIGADD).into_script();
+		let stack = vec![Bytes::new(), Bytes::new(), Bytes::new()].into();
+		basic_test_with_flags(&script, &VerificationFlags::default(), Err(Error::BadOpcode), stack);
+	}
+
+	#[test]
+	fn test_tapscript_rules() {
+		let flags = taproot_flags();
+		let discourage_flags = taproot_flags()
+			.verify_discourage_op_success(true)
+			.verify_discourage_upgradable_taproot_version(true)
+			.verify_discourage_upgradable_pubkeytype(true);
+
+		// OP_SUCCESSx makes the script succeed, even if it is not executed
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_RETURN)
+			.push_opcode(Opcode::OP_CAT)
+			.into_script();
+		run_tapscript_test(&script, 0xc0, vec![], &flags, Ok(()));
+		run_tapscript_test(&script, 0xc0, vec![], &discourage_flags, Err(Error::DiscourageOpSuccess));
+		let script: Script = "bb".into();
+		run_tapscript_test(&script, 0xc0, vec![], &flags, Ok(()));
+
+		// unknown leaf versions are not executed
+		let script = Builder::default().push_opcode(Opcode::OP_RETURN).into_script();
+		run_tapscript_test(&script, 0xc2, vec![], &flags, Ok(()));
+		run_tapscript_test(
+			&script,
+			0xc2,
+			vec![],
+			&discourage_flags,
+			Err(Error::DiscourageUpgradableTaprootVersion),
+		);
+
+		// OP_CHECKMULTISIG is disabled
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script();
+		run_tapscript_test(&script, 0xc0, vec![], &flags, Err(Error::TapscriptCheckMultiSig));
+
+		// OP_IF argument must be minimal
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_IF)
+			.push_opcode(Opcode::OP_1)
+			.push_opcode(Opcode::OP_ENDIF)
+			.into_script();
+		run_tapscript_test(&script, 0xc0, vec![vec![1].into()], &flags, Ok(()));
+		run_tapscript_test(&script, 0xc0, vec![vec![2].into()], &flags, Err(Error::TapscriptMinimalIf));
+
+		// unknown public key types succeed, empty ones fail
+		let script = Builder::default()
+			.push_data(&[1; 33])
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		run_tapscript_test(&script, 0xc0, vec![vec![1].into()], &flags, Ok(()));
+		run_tapscript_test(
+			&script,
+			0xc0,
+			vec![vec![1].into()],
+			&discourage_flags,
+			Err(Error::DiscourageUpgradablePubkeyType),
+		);
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		run_tapscript_test(&script, 0xc0, vec![Bytes::new()], &flags, Err(Error::TapscriptEmptyPubkey));
+
+		// every non-empty signature consumes validation weight
+		let script = (0..10)
+			.fold(Builder::default(), |builder, _| {
+				builder.push_data(&[1; 33]).push_opcode(Opcode::OP_CHECKSIGVERIFY)
+			})
+			.push_opcode(Opcode::OP_1)
+			.into_script();
+		run_tapscript_test(&script, 0xc0, vec![vec![1].into(); 5], &flags, Err(Error::InvalidStackOperation));
+		run_tapscript_test(
+			&script,
+			0xc0,
+			vec![vec![1].into(); 10],
+			&flags,
+			Err(Error::TapscriptValidationWeight),
+		);
+
+		// no opcode count and script size limits
+		let mut script = vec![Opcode::OP_NOP as u8; 10_001];
+		script.push(Opcode::OP_1 as u8);
+		let script: Script = Bytes::from(script).into();
+		run_tapscript_test(&script, 0xc0, vec![], &flags, Ok(()));
+	}
+
+	/// Consensus flags in the order of bits used by `script_flags`.
+	const CONSENSUS_FLAGS: [&str; 7] = [
+		"P2SH",
+		"DERSIG",
+		"NULLDUMMY",
+		"CHECKLOCKTIMEVERIFY",
+		"CHECKSEQUENCEVERIFY",
+		"WITNESS",
+		"TAPROOT",
+	];
+
+	fn script_flags(flags: &str) -> u32 {
+		flags.split(',').filter(|flag| !flag.is_empty()).fold(0, |mask, flag| {
+			let bit = CONSENSUS_FLAGS.iter().position(|name| *name == flag).expect("unknown script flag");
+			mask | 1 << bit
+		})
+	}
+
+	fn verification_flags(mask: u32) -> VerificationFlags {
+		VerificationFlags::default()
+			.verify_p2sh(mask & 1 != 0)
+			.verify_dersig(mask & 1 << 1 != 0)
+			.verify_nulldummy(mask & 1 << 2 != 0)
+			.verify_locktime(mask & 1 << 3 != 0)
+			.verify_checksequence(mask & 1 << 4 != 0)
+			.verify_witness(mask & 1 << 5 != 0)
+			.verify_taproot(mask & 1 << 6 != 0)
+	}
+
+	/// All combinations of consensus flags, in which witness requires p2sh and taproot requires witness.
+	fn all_consensus_flags() -> Vec<u32> {
+		let (p2sh, witness, taproot) = (script_flags("P2SH"), script_flags("WITNESS"), script_flags("TAPROOT"));
+		(0..1u32 << CONSENSUS_FLAGS.len())
+			.filter(|flags| flags & witness == 0 || flags & p2sh != 0)
+			.filter(|flags| flags & taproot == 0 || flags & witness != 0)
+			.collect()
+	}
+
+	fn hex_value(value: &serde_json::Value) -> Bytes {
+		value.as_str().expect("hex string").parse().expect("valid hex")
+	}
+
+	/// Runs single test of script_assets_test.json from Bitcoin Core. Success case must be valid with every subset
+	/// of the test flags (or with all flags, if the test is final). Failure case must be invalid with every superset.
+	fn run_script_assets_test(test: &serde_json::Value) {
+		let tx: Transaction = ser::deserialize(&hex_value(&test["tx"])[..]).unwrap();
+		let prevouts: Vec<TransactionOutput> = test["prevouts"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|prevout| ser::deserialize(&hex_value(prevout)[..]).unwrap())
+			.collect();
+		let index = test["index"].as_u64().unwrap() as usize;
+		let test_flags = script_flags(test["flags"].as_str().unwrap());
+		let is_final = test["final"].as_bool().unwrap_or(false);
+		let comment = test["comment"].as_str().unwrap_or_default();
+
+		let verify = |case: &serde_json::Value, flags: u32| {
+			let script_sig: Script = hex_value(&case["scriptSig"]).into();
+			let witness: Vec<Bytes> = case["witness"].as_array().unwrap().iter().map(hex_value).collect();
+			let mut tx = tx.clone();
+			tx.inputs[index].script_sig = script_sig.to_bytes();
+			tx.inputs[index].script_witness = witness.clone();
+			let mut signer: TransactionInputSigner = tx.into();
+			signer.spent_outputs = prevouts.clone();
+			let checker = TransactionSignatureChecker {
+				signer,
+				input_index: index,
+				input_amount: prevouts[index].value,
+			};
+			verify_script(
+				&script_sig,
+				&prevouts[index].script_pubkey.clone().into(),
+				&witness,
+				&verification_flags(flags),
+				&checker,
+				SignatureVersion::Base,
+			)
+		};
+
+		for flags in all_consensus_flags() {
+			if test.get("success").is_some() && (is_final || flags & test_flags == flags) {
+				assert_eq!(
+					verify(&test["success"], flags),
+					Ok(()),
+					"{} should succeed with flags {:b}",
+					comment,
+					flags
+				);
+			}
+			if test.get("failure").is_some() && flags & test_flags == test_flags {
+				assert!(
+					verify(&test["failure"], flags).is_err(),
+					"{} should fail with flags {:b}",
+					comment,
+					flags
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_script_assets() {
+		// stored vectors are in the same format and cover signed key path, script path and segwit v0 spends
+		let tests: serde_json::Value = serde_json::from_str(include_str!("../test_data/script_assets_test.json")).unwrap();
+		tests.as_array().unwrap().iter().for_each(run_script_assets_test);
+
+		// full set of vectors generated by feature_taproot.py --dumptests is too big to be stored in the repository
+		if let Some(dir) = env::var_os("DIR_UNIT_TEST_DATA") {
+			let file = path::Path::new(&dir).join("script_assets_test.json");
+			let tests: serde_json::Value = serde_json::from_slice(&fs::read(file).unwrap()).unwrap();
+			tests.as_array().unwrap().iter().for_each(run_script_assets_test);
+		}
+	}
 }
@@ -3,6 +3,8 @@ extern crate chain;
 extern crate keys;
 extern crate log;
 extern crate primitives;
+#[cfg(test)]
+extern crate serde_json;
 extern crate serialization as ser;
 
 mod builder;
@@ -25,6 +27,6 @@ pub use self::interpreter::{eval_script, verify_script};
 pub use self::num::Num;
 pub use self::opcode::Opcode;
 pub use self::script::{is_witness_commitment_script, Script, ScriptAddress, ScriptType, ScriptWitness};
-pub use self::sign::{SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+pub use self::sign::{ScriptExecutionData, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
 pub use self::stack::Stack;
 pub use self::verify::{NoopSignatureChecker, SignatureChecker, TransactionSignatureChecker};
@@ -213,6 +213,9 @@ pub enum Opcode {
 	OP_NOP8 = 0xb7,
 	OP_NOP9 = 0xb8,
 	OP_NOP10 = 0xb9,
+
+	// tapscript
+	OP_CHECKSIGADD = 0xba,
 }
 
 impl fmt::Display for Opcode {
@@ -431,6 +434,9 @@ impl Opcode {
 			0xb8 => Some(OP_NOP9),
 			0xb9 => Some(OP_NOP10),
 
+			// tapscript
+			0xba => Some(OP_CHECKSIGADD),
+
 			_ => None,
 		}
 	}
@@ -452,6 +458,11 @@ impl Opcode {
 		}
 	}
 
+	/// Returns true if byte is one of the OP_SUCCESSx opcodes of tapscript (BIP342)
+	pub fn is_success(opcode: u8) -> bool {
+		matches!(opcode, 80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254)
+	}
+
 	/// Returns true if opcode is countable
 	pub fn is_countable(&self) -> bool {
 		*self > Opcode::OP_16
@@ -481,6 +492,19 @@ impl Opcode {
 mod tests {
 	use super::Opcode;
 
+	#[test]
+	fn test_is_success() {
+		assert!(Opcode::is_success(Opcode::OP_RESERVED as u8));
+		assert!(Opcode::is_success(Opcode::OP_CAT as u8));
+		assert!(Opcode::is_success(Opcode::OP_MUL as u8));
+		assert!(Opcode::is_success(0xbb));
+		assert!(Opcode::is_success(0xfe));
+		assert!(!Opcode::is_success(Opcode::OP_CHECKSIGADD as u8));
+		assert!(!Opcode::is_success(Opcode::OP_VERIF as u8));
+		assert!(!Opcode::is_success(Opcode::OP_CHECKSIG as u8));
+		assert!(!Opcode::is_success(0xff));
+	}
+
 	#[test]
 	fn test_to_from_opcode() {
 		// push value
@@ -702,5 +726,8 @@ mod tests {
 		assert_eq!(Opcode::OP_NOP8, Opcode::from_u8(Opcode::OP_NOP8 as u8).unwrap());
 		assert_eq!(Opcode::OP_NOP9, Opcode::from_u8(Opcode::OP_NOP9 as u8).unwrap());
 		assert_eq!(Opcode::OP_NOP10, Opcode::from_u8(Opcode::OP_NOP10 as u8).unwrap());
+
+		// tapscript
+		assert_eq!(Opcode::OP_CHECKSIGADD, Opcode::from_u8(Opcode::OP_CHECKSIGADD as u8).unwrap());
 	}
 }
@@ -3,7 +3,8 @@
 use crate::bytes::Bytes;
 use crate::{Error, Opcode};
 use crypto::Hash;
-use keys::{self, AddressHash, Public};
+use crypto::SHA256;
+use keys::{self, AddressHash, AddressPayload, Public};
 use std::{fmt, ops};
 
 /// Maximum number of bytes pushable to the stack
@@ -18,6 +19,36 @@ pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
 /// Maximum script length in bytes
 pub const MAX_SCRIPT_SIZE: usize = 10000;
 
+/// Maximum number of elements on the stack and altstack combined
+pub const MAX_STACK_SIZE: usize = 1000;
+
+/// First byte of the last witness element that marks it as annex (BIP341)
+pub const ANNEX_TAG: u8 = 0x50;
+
+/// Mask of the leaf version bits in the first byte of control block
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+
+/// Leaf version of tapscript (BIP342)
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+
+/// Size of control block without merkle path
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+
+/// Size of single merkle path node in control block
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+
+/// Maximum number of merkle path nodes in control block
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// Maximum size of control block
+pub const TAPROOT_CONTROL_MAX_SIZE: usize = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+
+/// Validation weight consumed by every executed signature check in tapscript
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+
+/// Validation weight budget added to the witness size in tapscript
+pub const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
 /// Classified script type
 #[derive(PartialEq, Debug)]
 pub enum ScriptType {
@@ -29,6 +60,7 @@ pub enum ScriptType {
 	NullData,
 	WitnessScript,
 	WitnessKey,
+	WitnessTaproot,
 }
 
 /// Address from Script
@@ -36,8 +68,8 @@ pub enum ScriptType {
 pub struct ScriptAddress {
 	/// The type of the address.
 	pub kind: keys::Type,
-	/// Public key hash.
-	pub hash: AddressHash,
+	/// Public key hash, script hash or witness program.
+	pub hash: AddressPayload,
 }
 
 impl ScriptAddress {
@@ -45,7 +77,7 @@ impl ScriptAddress {
 	pub fn new_p2pkh(hash: AddressHash) -> Self {
 		ScriptAddress {
 			kind: keys::Type::P2PKH,
-			hash,
+			hash: hash.into(),
 		}
 	}
 
@@ -53,7 +85,31 @@ impl ScriptAddress {
 	pub fn new_p2sh(hash: AddressHash) -> Self {
 		ScriptAddress {
 			kind: keys::Type::P2SH,
-			hash,
+			hash: hash.into(),
+		}
+	}
+
+	/// Creates P2WPKH-type ScriptAddress
+	pub fn new_p2wpkh(hash: AddressHash) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2WPKH,
+			hash: hash.into(),
+		}
+	}
+
+	/// Creates P2WSH-type ScriptAddress
+	pub fn new_p2wsh(hash: SHA256) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2WSH,
+			hash: hash.into(),
+		}
+	}
+
+	/// Creates P2TR-type ScriptAddress
+	pub fn new_p2tr(output_key: SHA256) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2TR,
+			hash: output_key.into(),
 		}
 	}
 }
@@ -160,6 +216,11 @@ impl Script {
 		self.data.len() == 34 && self.data[0] == Opcode::OP_0 as u8 && self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
 	}
 
+	/// Extra-fast test for pay-to-taproot scripts.
+	pub fn is_pay_to_taproot(&self) -> bool {
+		self.data.len() == 34 && self.data[0] == Opcode::OP_1 as u8 && self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
+	}
+
 	/// Extra-fast test for multisig scripts.
 	pub fn is_multisig_script(&self) -> bool {
 		if self.data.len() < 3 {
@@ -344,6 +405,8 @@ impl Script {
 			ScriptType::WitnessKey
 		} else if self.is_pay_to_witness_script_hash() {
 			ScriptType::WitnessScript
+		} else if self.is_pay_to_taproot() {
+			ScriptType::WitnessTaproot
 		} else {
 			ScriptType::NonStandard
 		}
@@ -429,12 +492,15 @@ impl Script {
 				Ok(addresses)
 			}
 			ScriptType::NullData => Ok(vec![]),
-			ScriptType::WitnessScript => {
-				Ok(vec![]) // TODO
-			}
-			ScriptType::WitnessKey => {
-				Ok(vec![]) // TODO
-			}
+			ScriptType::WitnessScript => Ok(vec![ScriptAddress::new_p2wsh(
+				SHA256::from_slice(&self.data[2..34]).map_err(|_| keys::Error::InvalidAddress)?,
+			)]),
+			ScriptType::WitnessKey => Ok(vec![ScriptAddress::new_p2wpkh(
+				AddressHash::from_slice(&self.data[2..22]).map_err(|_| keys::Error::InvalidAddress)?,
+			)]),
+			ScriptType::WitnessTaproot => Ok(vec![ScriptAddress::new_p2tr(
+				SHA256::from_slice(&self.data[2..34]).map_err(|_| keys::Error::InvalidAddress)?,
+			)]),
 		}
 	}
 
@@ -576,7 +642,8 @@ pub fn is_witness_commitment_script(script: &[u8]) -> bool {
 mod tests {
 	use super::{Script, ScriptAddress, ScriptType, MAX_SCRIPT_ELEMENT_SIZE};
 	use crate::{Builder, Opcode};
-	use keys::{Address, Public};
+	use crypto::{Hash, SHA256};
+	use keys::{Address, AddressHash, Public, XOnlyPublic};
 
 	#[test]
 	fn test_is_pay_to_script_hash() {
@@ -738,7 +805,7 @@ OP_ADD
 
 	#[test]
 	fn test_extract_destinations_pub_key_hash() {
-		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash;
+		let address = AddressHash::from_slice(&Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash).unwrap();
 		let script = Builder::build_p2pkh(&address);
 		assert_eq!(script.script_type(), ScriptType::PubKeyHash);
 		assert_eq!(script.extract_destinations(), Ok(vec![ScriptAddress::new_p2pkh(address),]));
@@ -746,12 +813,46 @@ OP_ADD
 
 	#[test]
 	fn test_extract_destinations_script_hash() {
-		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash;
+		let address = AddressHash::from_slice(&Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash).unwrap();
 		let script = Builder::build_p2sh(&address);
 		assert_eq!(script.script_type(), ScriptType::ScriptHash);
 		assert_eq!(script.extract_destinations(), Ok(vec![ScriptAddress::new_p2sh(address),]));
 	}
 
+	#[test]
+	fn test_extract_destinations_witness_key_hash() {
+		let address = AddressHash::from_slice(&Address::from("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").hash).unwrap();
+		let script = Builder::build_p2wpkh(&address);
+		assert_eq!(script.script_type(), ScriptType::WitnessKey);
+		assert_eq!(script.extract_destinations(), Ok(vec![ScriptAddress::new_p2wpkh(address),]));
+	}
+
+	#[test]
+	fn test_extract_destinations_witness_script_hash() {
+		let address = Address::from("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3");
+		let hash = SHA256::from_slice(&address.hash).unwrap();
+		let script = Builder::build_p2wsh(&hash);
+		assert_eq!(script, Builder::build_address(&address));
+		assert_eq!(script.script_type(), ScriptType::WitnessScript);
+		assert_eq!(script.extract_destinations(), Ok(vec![ScriptAddress::new_p2wsh(hash),]));
+	}
+
+	#[test]
+	fn test_extract_destinations_taproot() {
+		let address = Address::from("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0");
+		let script = Builder::build_p2tr(&XOnlyPublic::from_slice(&address.hash).unwrap());
+		assert_eq!(script, Builder::build_address(&address));
+		assert_eq!(
+			script,
+			"512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into()
+		);
+		assert_eq!(script.script_type(), ScriptType::WitnessTaproot);
+		assert_eq!(
+			script.extract_destinations(),
+			Ok(vec![ScriptAddress::new_p2tr(SHA256::from_slice(&address.hash).unwrap()),])
+		);
+	}
+
 	#[test]
 	fn test_extract_destinations_multisig() {
 		let pubkey1_bytes = [0; 33];
@@ -4,8 +4,8 @@ use crate::bytes::Bytes;
 use crate::chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
 use crate::keys::KeyPair;
 use crate::{Builder, Script};
-use bitcrypto::{dhash256, Hash, SHA256D};
-use ser::Stream;
+use bitcrypto::{dhash256, sha256, tagged_hash, Hash, SHA256, SHA256D};
+use ser::{serialize, Stream};
 
 const ONE: [u8; 32] = [
 	1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -15,6 +15,10 @@ const ONE: [u8; 32] = [
 pub enum SignatureVersion {
 	Base,
 	WitnessV0,
+	/// Taproot key path spend (BIP341)
+	Taproot,
+	/// Taproot script path spend (BIP342)
+	Tapscript,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -80,6 +84,19 @@ impl Sighash {
 	}
 }
 
+/// Data committed to by taproot signatures in addition to the transaction itself
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScriptExecutionData {
+	/// Hash of the tapleaf being executed, script path spends only
+	pub tapleaf_hash: Option<SHA256>,
+	/// Opcode position of the last executed OP_CODESEPARATOR, 0xffffffff if none
+	pub codeseparator_pos: u32,
+	/// Hash of the compact size prefixed annex, if present
+	pub annex_hash: Option<SHA256>,
+	/// Remaining signature validation budget of tapscript
+	pub validation_weight_left: i64,
+}
+
 #[derive(Debug)]
 pub struct UnsignedTransactionInput {
 	pub previous_output: OutPoint,
@@ -102,6 +119,8 @@ pub struct TransactionInputSigner {
 	pub inputs: Vec<UnsignedTransactionInput>,
 	pub outputs: Vec<TransactionOutput>,
 	pub lock_time: u32,
+	/// Outputs spent by the inputs, in the same order. Required only by taproot signature hash.
+	pub spent_outputs: Vec<TransactionOutput>,
 }
 
 /// Used for resigning and loading test transactions
@@ -112,6 +131,7 @@ impl From<Transaction> for TransactionInputSigner {
 			inputs: t.inputs.into_iter().map(Into::into).collect(),
 			outputs: t.outputs,
 			lock_time: t.lock_time,
+			spent_outputs: Vec::new(),
 		}
 	}
 }
@@ -129,7 +149,104 @@ impl TransactionInputSigner {
 		match sigversion {
 			SignatureVersion::Base => self.signature_hash_original(input_index, script_pubkey, sighashtype, sighash),
 			SignatureVersion::WitnessV0 => self.signature_hash_witness0(input_index, input_amount, script_pubkey, sighashtype, sighash),
+			// key path spend without annex, script path spends must use `signature_hash_schnorr`
+			SignatureVersion::Taproot | SignatureVersion::Tapscript => self
+				.signature_hash_schnorr(input_index, sighashtype, SignatureVersion::Taproot, &ScriptExecutionData::default())
+				.map(|hash| SHA256D::from_inner(hash.into_inner()))
+				.unwrap_or_else(|| SHA256D::from_inner(ONE)),
+		}
+	}
+
+	/// Computes BIP341 signature hash. Returns None if sighashtype is invalid, SIGHASH_SINGLE
+	/// is used without matching output or spent outputs are not known.
+	pub fn signature_hash_schnorr(
+		&self,
+		input_index: usize,
+		sighashtype: u32,
+		sigversion: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Option<SHA256> {
+		let ext_flag = match sigversion {
+			SignatureVersion::Taproot => 0u8,
+			SignatureVersion::Tapscript => 1u8,
+			SignatureVersion::Base | SignatureVersion::WitnessV0 => return None,
+		};
+
+		// SIGHASH_DEFAULT (0) commits to the same data as SIGHASH_ALL
+		match sighashtype {
+			0x00..=0x03 | 0x81..=0x83 => (),
+			_ => return None,
+		}
+
+		if input_index >= self.inputs.len() || self.spent_outputs.len() != self.inputs.len() {
+			return None;
+		}
+
+		let sighash = Sighash::from_u32(sighashtype);
+		let mut stream = Stream::default();
+		// epoch
+		stream.append(&0u8);
+		stream.append(&(sighashtype as u8));
+		stream.append(&self.version);
+		stream.append(&self.lock_time);
+
+		if !sighash.anyone_can_pay {
+			let mut prevouts = Stream::default();
+			let mut amounts = Stream::default();
+			let mut script_pubkeys = Stream::default();
+			let mut sequences = Stream::default();
+			for (input, spent_output) in self.inputs.iter().zip(self.spent_outputs.iter()) {
+				prevouts.append(&input.previous_output);
+				amounts.append(&spent_output.value);
+				script_pubkeys.append(&spent_output.script_pubkey);
+				sequences.append(&input.sequence);
+			}
+			stream.append_slice(&sha256(&prevouts.out()));
+			stream.append_slice(&sha256(&amounts.out()));
+			stream.append_slice(&sha256(&script_pubkeys.out()));
+			stream.append_slice(&sha256(&sequences.out()));
+		}
+
+		if sighash.base == SighashBase::All {
+			let mut outputs = Stream::default();
+			for output in &self.outputs {
+				outputs.append(output);
+			}
+			stream.append_slice(&sha256(&outputs.out()));
+		}
+
+		let spend_type = ext_flag * 2 + execdata.annex_hash.is_some() as u8;
+		stream.append(&spend_type);
+
+		if sighash.anyone_can_pay {
+			let input = &self.inputs[input_index];
+			let spent_output = &self.spent_outputs[input_index];
+			stream.append(&input.previous_output);
+			stream.append(&spent_output.value);
+			stream.append(&spent_output.script_pubkey);
+			stream.append(&input.sequence);
+		} else {
+			stream.append(&(input_index as u32));
 		}
+
+		if let Some(ref annex_hash) = execdata.annex_hash {
+			stream.append_slice(annex_hash);
+		}
+
+		if sighash.base == SighashBase::Single {
+			let output = self.outputs.get(input_index)?;
+			stream.append_slice(&sha256(&serialize(output)));
+		}
+
+		if ext_flag == 1 {
+			let tapleaf_hash = execdata.tapleaf_hash.as_ref()?;
+			stream.append_slice(tapleaf_hash);
+			// key version
+			stream.append(&0u8);
+			stream.append(&execdata.codeseparator_pos);
+		}
+
+		Some(tagged_hash(b"TapSighash", &stream.out()))
 	}
 
 	/// input_index - index of input to sign
@@ -306,12 +423,13 @@ fn compute_hash_outputs(sighash: Sighash, input_index: usize, outputs: &[Transac
 
 #[cfg(test)]
 mod tests {
-	use super::{Sighash, SighashBase, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+	use super::{ScriptExecutionData, Sighash, SighashBase, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
 	use crate::bytes::Bytes;
 	use crate::script::Script;
-	use bitcrypto::{FromHex, FromInnerHex, SHA256D};
+	use bitcrypto::{sha256, tagged_hash, FromHex, FromInnerHex, Hash, SHA256, SHA256D};
 	use chain::{OutPoint, Transaction, TransactionOutput};
 	use keys::{Address, KeyPair, Private};
+	use ser::{serialize, Reader, Stream};
 
 	// http://www.righto.com/2014/02/bitcoins-hard-way-using-raw-bitcoin.html
 	// https://blockchain.info/rawtx/81b4c832d70cb56ff957589752eb4125a4cab78a25a8fc52d6a09e5bd4404d48
@@ -351,6 +469,7 @@ mod tests {
 			lock_time: 0,
 			inputs: vec![unsigned_input],
 			outputs: vec![output],
+			spent_outputs: vec![],
 		};
 
 		let hash = input_signer.signature_hash(0, 0, &previous_output, SignatureVersion::Base, SighashBase::All.into());
@@ -875,6 +994,89 @@ mod tests {
 		run_test_sighash("fea256ce01272d125e577c0a09570a71366898280dda279b021000db1325f27edda41a53460100000002ab53c752c21c013c2b3a01000000000000000000", "65", 0, 1145543262, "076b9f844f6ae429de228a2c337c704df1652c292b6c6494882190638dad9efd");
 	}
 
+	fn run_test_sighash_schnorr(
+		tx: &'static str,
+		spent_outputs: &'static str,
+		input_index: usize,
+		hash_type: u32,
+		annex: Option<&'static str>,
+		tapleaf_hash: Option<SHA256>,
+		result: &'static str,
+	) {
+		let tx: Transaction = tx.into();
+		let mut signer: TransactionInputSigner = tx.into();
+		let spent_outputs: Bytes = spent_outputs.into();
+		signer.spent_outputs = Reader::new(&spent_outputs).read_list().unwrap();
+
+		let annex_hash = annex.map(|annex| sha256(&serialize(&Bytes::from(annex))));
+		let sigversion = match tapleaf_hash {
+			Some(_) => SignatureVersion::Tapscript,
+			None => SignatureVersion::Taproot,
+		};
+		let execdata = ScriptExecutionData {
+			tapleaf_hash,
+			codeseparator_pos: 0xffff_ffff,
+			annex_hash,
+			validation_weight_left: 0,
+		};
+
+		let hash = signer
+			.signature_hash_schnorr(input_index, hash_type, sigversion, &execdata)
+			.unwrap();
+		assert_eq!(hash, SHA256::from_slice(&Vec::<u8>::from_hex(result).unwrap()).unwrap());
+	}
+
+	fn tapleaf_hash(script: &'static str) -> SHA256 {
+		let script: Bytes = script.into();
+		let mut stream = Stream::default();
+		stream.append(&0xc0u8);
+		stream.append(&script);
+		tagged_hash(b"TapLeaf", &stream.out())
+	}
+
+	// test vectors generated by Bitcoin Core test framework
+	#[test]
+	fn test_signature_hash_schnorr_key_path() {
+		run_test_sighash_schnorr("020000000164eb050a5e3da0c2a65e4786f26d753b7bc69691fabccafb11f7acef36641f1846010000003101b2b404392a22000000000017a9147f2bde86fe78bf68a0544a4f290e12f0b7e0a08c87580200000000000017a91425d11723074ecfb96a0a83c3956bfaf362ae0c908758020000000000001600147e20f938993641de67bb0cdd71682aa34c4d29ad5802000000000000160014c64984dc8761acfa99418bd6bedc79b9287d652d72000000", "01365724000000000023542156b39dab4f8f3508e0432cfb41fab110170acaa2d4c42539cb90a4dc7c093bc500", 0, 0x00, None, None, "33ca0ebfb4a945eeee9569fc0f5040221275f88690b7f8592ada88ce3bdf6703");
+		run_test_sighash_schnorr("0200000002fff49be59befe7566050737910f6ccdc5e749c7f8860ddc140386463d88c5ad0f3000000002cf68eb4a3d67f9d4c079249f7e4f27b8854815cb1ed13842d4fbf395f9e217fd605ee24090100000065235d9203f458520000000000160014b6d48333bb13b4c644e57c43a9a26df3a44b785e58020000000000001976a914eea9461a9e1e3f765d3af3e726162e0229fe3eb688ac58020000000000001976a9143a8869c9f2b5ea1d4ff3aeeb6a8fb2fffb1ad5fe88ac0ad7125c", "02591f220000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece48fb310000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece", 1, 0x01, None, None, "626ab955d58c9a8a600a0c580549d06dc7da4e802eb2a531f62a588e430967a8");
+		run_test_sighash_schnorr("0200000001350005f65aa830ced2079df348e2d8c2bdb4f10e2dde6a161d8a07b40d1ad87dae000000001611d0d603d9dc0e000000000017a914459b6d7d6bbb4d8837b4bf7e9a4556f952da2f5c8758020000000000001976a9141dd70e1299ffc2d5b51f6f87de9dfe9398c33cbb88ac58020000000000001976a9141dd70e1299ffc2d5b51f6f87de9dfe9398c33cbb88aca71c1f4f", "01c4811000000000002251201bf9297d0a2968ae6693aadd0fa514717afefd218087a239afb7418e2d22e65c", 0, 0x81, None, None, "dfa9437f9c9a1d1f9af271f79f2f5482f287cdb0d2e03fa92c8a9b216cc6061c");
+		run_test_sighash_schnorr("020000000185bed1a6da2bffbd60ec681a1bfb71c5111d6395b99b3f8b2bf90167111bcb18f5010000007c83ace802ded24a00000000001600142c4698f9f7a773866879755aa78c516fb332af8e5802000000000000160014d38639dfbac4259323b98a472405db0c461b31fa61073747", "0144c84d0000000000225120e3f2107989c88e67296ab2faca930efa2e3a5bd3ff0904835a11c9e807458621", 0, 0x02, None, None, "3129de36a5d05fff97ffca31eb75fcccbbbc27b3147a7a36a9e4b45d8b625067");
+		run_test_sighash_schnorr("eb93dbb901028c8515589dac980b6e7f8e4088b77ed866ca0d6d210a7218b6fd0f6b22dd6d7300000000eb4740a9047efc0e0000000000160014913da2128d8fcf292b3691db0e187414aa1783825802000000000000160014913da2128d8fcf292b3691db0e187414aa178382580200000000000017a9143dd27f01c6f7ef9bb9159937b17f17065ed01a0c875802000000000000160014d7630e19df70ada9905ede1722b800c0005f246641000000", "013fed110000000000225120eb536ae8c33580290630fc495046e998086a64f8f33b93b07967d9029b265c55", 0, 0x82, None, None, "2441e8b0e063a2083ee790f14f2045022f07258ddde5ee01de543c9e789d80ae");
+		run_test_sighash_schnorr("02000000017836b409a5fed32211407e44b971591f2032053f14701fb5b3a30c0ff382f2cc9c0100000061ac55f60288fb5600000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ac58020000000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ace4000000", "01efa558000000000022512007071ea3dc7e331b0687d0193d1e6d6ed10e645ef36f10ef8831d5e522ac9e80", 0, 0x03, None, None, "30239345177cadd0e3ea413d49803580abb6cb27971b481b7788a78d35117a88");
+		run_test_sighash_schnorr("0100000001aa6deae89d5e0aaca58714fc76ef6f3c8284224888089232d4e663843ed3ab3eae010000008b6657a60450cb4c0000000000160014a3d42b5413ef0c0701c4702f3cd7d4df222c147058020000000000001976a91430b4ed8723a4ee8992aa2c8814cfe5c3ad0ab9d988ac5802000000000000160014365b1166a6ed0a5e8e9dff17a6d00bbb43454bc758020000000000001976a914bc98c51a84fe7fad5dc380eb8b39586eff47241688ac4f313247", "0107af4e00000000002251202c36d243dfc06cb56a248e62df27ecba7417307511a81ae61aa41c597a929c69", 0, 0x83, None, None, "bf9c83f26c6dd16449e4921f813f551c4218e86f2ec906ca8611175b41b566df");
+	}
+
+	#[test]
+	fn test_signature_hash_schnorr_annex_and_script_path() {
+		run_test_sighash_schnorr("0200000001df8123752e8f37d132c4e9f1ff7e4f9b986ade9211267e9ebd5fd22a5e718dec6d01000000ce4023b903cb7b23000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787580200000000000017a914afd0d512a2c5c2b40e25669e9cc460303c325b8b87580200000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787f6020000", "01ea49260000000000225120ab5e9800806bf18cb246edcf5fe63441208fe955a4b5a35bbff65f5db622a010", 0, 0x83, Some("507b979802e62d397acb29f56743a791894b99372872fc5af06a4f6e8d242d0615cda53062bb20e6ec79756fe39183f0c128adfe85559a8fa042b042c018aa8010143799e44f0893c40e1e"), None, "3b003000add359a364a156e73e02846782a59d0d95ca8c4638aaad99f2ef915c");
+		run_test_sighash_schnorr("020000000189fc651483f9296b906455dd939813bf086b1bbe7c77635e157c8e14ae29062195010000004445b5c7044561320000000000160014331414dbdada7fb578f700f38fb69995fc9b5ab958020000000000001976a914268db0a8104cc6d8afd91233cc8b3d1ace8ac3ef88ac580200000000000017a914ec00dcb368d6a693e11986d265f659d2f59e8be2875802000000000000160014c715799a49a0bae3956df9c17cb4440a673ac0df6f010000", "011bec34000000000022512028055142ea437db73382e991861446040b61dd2185c4891d7daf6893d79f7182", 0, 0x01, None, Some(tapleaf_hash("20cc4e1107aea1d170c5ff5b6817e1303010049724fb3caa7941792ea9d29b3e2bacab")), "d66de5274a60400c7b08c86ba6b7f198f40660079edf53aca89d2a9501317f2e");
+		run_test_sighash_schnorr("020000000132fb72cb8fba496755f027a9743e2d698c831fdb8304e4d1a346ac92cbf51acba50100000026bdc7df044aad34000000000017a9144fa2554ed6174586854fa3bc01de58dcf33567d0875802000000000000160014950367e1e62cdf240b35b883fc2f5e39f0eb9ab95802000000000000160014950367e1e62cdf240b35b883fc2f5e39f0eb9ab958020000000000001600141b31217d48ccc8760dcc0710fade5866d628e733a02d5122", "011458360000000000225120a7baec3fb9f84614e3899fcc010c638f80f13539344120e1f4d8b68a9a011a13", 0, 0x01, Some("50a6272b470e1460e3332ade7bb14b81671c564fb6245761bd5bd531394b28860e0b3808ab229fb51791fb6ae6fa82d915b2efb8f6df83ae1f5ab3db13e30928875e2a22b749d89358de481f19286cd4caa792ce27f9559082d227a731c5486882cc707f83da361c51b7aadd9a0cf68fe7480c410fa137b454482d9a1ebf0f96d760b4d61426fc109c6e8e99a508372c45caa7b000a41f8251305da3f206c1849985ba03f3d9592832b4053afbd23ab25d0465df0bc25a36c223aacf8e04ec736a418c72dc319e4da3e972e349713ca600965e7c665f2090d5a70e241ac164115a1f5639f28b1773327715ca307ace64a2de7f0e3df70a2ffee3857689f909c0dad46d8a20fa373a4cc6eed6d4c9806bf146f0d76baae1"), Some(tapleaf_hash("7520ab9160dd8299dc1367659be3e8f66781fe440d52940c7f8d314a89b9f2698d406ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6eadac")), "a0042aa434f9a75904b64043f2a283f8b4c143c7f4f7f49a6cbe5b9f745f4c15");
+	}
+
+	#[test]
+	fn test_signature_hash_schnorr_invalid() {
+		let tx: Transaction = "02000000017836b409a5fed32211407e44b971591f2032053f14701fb5b3a30c0ff382f2cc9c0100000061ac55f60288fb5600000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ac58020000000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ace4000000".into();
+		let mut signer: TransactionInputSigner = tx.into();
+		let execdata = ScriptExecutionData::default();
+		// spent outputs are unknown
+		assert_eq!(signer.signature_hash_schnorr(0, 0x01, SignatureVersion::Taproot, &execdata), None);
+
+		let spent_outputs: Bytes = "01efa558000000000022512007071ea3dc7e331b0687d0193d1e6d6ed10e645ef36f10ef8831d5e522ac9e80".into();
+		signer.spent_outputs = Reader::new(&spent_outputs).read_list().unwrap();
+		assert!(signer
+			.signature_hash_schnorr(0, 0x01, SignatureVersion::Taproot, &execdata)
+			.is_some());
+		assert_eq!(signer.signature_hash_schnorr(0, 0x04, SignatureVersion::Taproot, &execdata), None);
+		assert_eq!(signer.signature_hash_schnorr(0, 0x80, SignatureVersion::Taproot, &execdata), None);
+		assert_eq!(signer.signature_hash_schnorr(0, 0x01, SignatureVersion::WitnessV0, &execdata), None);
+		// tapscript requires tapleaf hash
+		assert_eq!(signer.signature_hash_schnorr(0, 0x01, SignatureVersion::Tapscript, &execdata), None);
+
+		// SIGHASH_SINGLE without matching output
+		signer.outputs.truncate(0);
+		assert_eq!(signer.signature_hash_schnorr(0, 0x03, SignatureVersion::Taproot, &execdata), None);
+	}
+
 	#[test]
 	fn test_sighash_from_u32() {
 		assert!(!Sighash::is_defined(0xFFFFFF82));
@@ -1,9 +1,9 @@
-use crate::sign::SignatureVersion;
-use crate::{Num, Script, TransactionInputSigner};
+use crate::sign::{ScriptExecutionData, SignatureVersion};
+use crate::{Error, Num, Script, TransactionInputSigner};
 use chain::constants::{
 	LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
 };
-use keys::{Message, Public, Signature};
+use keys::{Message, Public, Signature, XOnlyPublic};
 
 /// Checks transaction signature
 pub trait SignatureChecker {
@@ -18,6 +18,17 @@ pub trait SignatureChecker {
 		version: SignatureVersion,
 	) -> bool;
 
+	/// Checks BIP340 signature of taproot or tapscript spend. Signature is expected to be 64 bytes long,
+	/// with hashtype already stripped from it.
+	fn check_schnorr_signature(
+		&self,
+		signature: &[u8],
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Result<(), Error>;
+
 	fn check_lock_time(&self, lock_time: Num) -> bool;
 
 	fn check_sequence(&self, sequence: Num) -> bool;
@@ -34,6 +45,17 @@ impl SignatureChecker for NoopSignatureChecker {
 		false
 	}
 
+	fn check_schnorr_signature(
+		&self,
+		_: &[u8],
+		_: &XOnlyPublic,
+		_: u32,
+		_: SignatureVersion,
+		_: &ScriptExecutionData,
+	) -> Result<(), Error> {
+		Err(Error::SchnorrSig)
+	}
+
 	fn check_lock_time(&self, _: Num) -> bool {
 		false
 	}
@@ -69,6 +91,24 @@ impl SignatureChecker for TransactionSignatureChecker {
 		self.verify_signature(signature, public, &hash)
 	}
 
+	fn check_schnorr_signature(
+		&self,
+		signature: &[u8],
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Result<(), Error> {
+		let hash = self
+			.signer
+			.signature_hash_schnorr(self.input_index, sighashtype, version, execdata)
+			.ok_or(Error::SchnorrSigHashtype)?;
+		match public.verify_schnorr(&hash, signature) {
+			Ok(true) => Ok(()),
+			_ => Err(Error::SchnorrSig),
+		}
+	}
+
 	fn check_lock_time(&self, lock_time: Num) -> bool {
 		// There are two kinds of nLockTime: lock-by-blockheight
 		// and lock-by-blocktime, distinguished by whether
@@ -1,5 +1,5 @@
 use crate::bytes::Bytes;
-use crate::chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
+use crate::chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction};
 use crate::{BlockMeta, BlockRef};
 use bitcrypto::SHA256D;
 
@@ -35,3 +35,11 @@ pub trait BlockProvider: BlockHeaderProvider {
 	/// returns all transactions in the block by block reference (number/hash)
 	fn block_transactions(&self, block_ref: BlockRef) -> Vec<IndexedTransaction>;
 }
+
+pub trait BlockFilterProvider {
+	/// resolves basic compact filter by block reference (number/hash)
+	fn block_filter(&self, block_ref: BlockRef) -> Option<BlockFilter>;
+
+	/// resolves basic compact filter header by block reference (number/hash)
+	fn block_filter_header(&self, block_ref: BlockRef) -> Option<SHA256D>;
+}
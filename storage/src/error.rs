@@ -8,6 +8,7 @@ pub enum Error {
 	UnknownParent,
 	AncientFork,
 	InconsistentData,
+	SnapshotError(String),
 }
 
 impl fmt::Display for Error {
@@ -19,6 +20,7 @@ impl fmt::Display for Error {
 			Error::UnknownParent => write!(f, "Block parent is unknown"),
 			Error::AncientFork => write!(f, "Fork is too long to proceed"),
 			Error::InconsistentData => write!(f, "Database inconsistency detected"),
+			Error::SnapshotError(s) => write!(f, "UTXO snapshot error: {}", s),
 		}
 	}
 }
@@ -21,9 +21,12 @@ mod block_provider;
 mod block_ref;
 mod duplex_store;
 mod error;
+mod prune;
+mod script_index;
 mod store;
 mod transaction_meta;
 mod transaction_provider;
+mod utxo_snapshot;
 
 pub use primitives::{bytes, hash};
 
@@ -33,10 +36,16 @@ pub use block_height::BlockHeight;
 pub use block_iterator::BlockIterator;
 pub use block_meta::BlockMeta;
 pub use block_origin::{BlockOrigin, SideChainOrigin};
-pub use block_provider::{BlockHeaderProvider, BlockProvider};
+pub use block_provider::{BlockFilterProvider, BlockHeaderProvider, BlockProvider};
 pub use block_ref::BlockRef;
 pub use duplex_store::{DuplexTransactionOutputProvider, NoopStore};
 pub use error::Error;
+pub use prune::{PruneProvider, MIN_BLOCKS_TO_KEEP};
+pub use script_index::{ScriptIndexProvider, ScriptOutput, ScriptOutputSpend};
 pub use store::{AsSubstore, CanonStore, SharedStore, Store};
 pub use transaction_meta::TransactionMeta;
 pub use transaction_provider::{CachedTransactionOutputProvider, TransactionMetaProvider, TransactionOutputProvider, TransactionProvider};
+pub use utxo_snapshot::{
+	utxo_set_hash, verify_utxo_snapshot, write_utxo_snapshot, SnapshotBase, SnapshotHasher, SnapshotInfo, SnapshotMetadata, SnapshotOutput,
+	SnapshotReader, SnapshotTransaction, UtxoSnapshotProvider,
+};
@@ -0,0 +1,18 @@
+/// Minimal number of the most recent blocks, which are never pruned.
+/// Allows to serve recent blocks to NODE_NETWORK_LIMITED peers and to handle reorganizations.
+pub const MIN_BLOCKS_TO_KEEP: u32 = 288;
+
+/// Information about pruned part of the chain
+pub trait PruneProvider {
+	/// returns true if storage is running in pruning mode
+	fn is_pruning_enabled(&self) -> bool;
+
+	/// returns height of the last block, which transactions have been pruned
+	/// or None if nothing has been pruned yet
+	fn pruned_height(&self) -> Option<u32>;
+
+	/// returns true if transactions of the canon block at given height have been pruned
+	fn is_pruned(&self, number: u32) -> bool {
+		self.pruned_height().map_or(false, |pruned_height| number <= pruned_height)
+	}
+}
@@ -0,0 +1,34 @@
+use crate::Error;
+use bitcrypto::{SHA256, SHA256D};
+use chain::OutPoint;
+
+/// Transaction output, indexed by the hash of its script pubkey
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScriptOutput {
+	/// Output reference
+	pub outpoint: OutPoint,
+	/// Height of the block, containing output
+	pub height: u32,
+	/// Output value in satoshis
+	pub value: u64,
+	/// Canon chain input, spending this output
+	pub spent: Option<ScriptOutputSpend>,
+}
+
+/// Reference to the input, spending indexed output
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScriptOutputSpend {
+	/// Hash of the spending transaction
+	pub tx_hash: SHA256D,
+	/// Height of the block, containing spending transaction
+	pub height: u32,
+}
+
+pub trait ScriptIndexProvider {
+	/// returns true if script index is maintained by the store
+	fn is_script_index_enabled(&self) -> bool;
+
+	/// resolves canon chain outputs, paying to script with given sha256 hash, ordered by their position in chain.
+	/// returns None if script index is not maintained
+	fn script_outputs(&self, script_hash: &SHA256) -> Result<Option<Vec<ScriptOutput>>, Error>;
+}
@@ -1,6 +1,7 @@
 use crate::BlockHeight;
 use crate::{
-	BlockChain, BlockHeaderProvider, BlockProvider, Forkable, TransactionMetaProvider, TransactionOutputProvider, TransactionProvider,
+	BlockChain, BlockFilterProvider, BlockHeaderProvider, BlockProvider, Forkable, PruneProvider, ScriptIndexProvider,
+	TransactionMetaProvider, TransactionOutputProvider, TransactionProvider, UtxoSnapshotProvider,
 };
 use chain::IndexedBlockHeader;
 use std::sync::Arc;
@@ -25,21 +26,47 @@ pub trait Store: AsSubstore {
 }
 
 /// Allows casting Arc<Store> to reference to any substore type
-pub trait AsSubstore: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider {
+pub trait AsSubstore:
+	BlockChain
+	+ BlockProvider
+	+ BlockFilterProvider
+	+ PruneProvider
+	+ ScriptIndexProvider
+	+ TransactionProvider
+	+ TransactionMetaProvider
+	+ TransactionOutputProvider
+	+ UtxoSnapshotProvider
+{
 	fn as_block_provider(&self) -> &dyn BlockProvider;
 
 	fn as_block_header_provider(&self) -> &dyn BlockHeaderProvider;
 
+	fn as_block_filter_provider(&self) -> &dyn BlockFilterProvider;
+
+	fn as_prune_provider(&self) -> &dyn PruneProvider;
+
+	fn as_script_index_provider(&self) -> &dyn ScriptIndexProvider;
+
 	fn as_transaction_provider(&self) -> &dyn TransactionProvider;
 
 	fn as_transaction_output_provider(&self) -> &dyn TransactionOutputProvider;
 
 	fn as_transaction_meta_provider(&self) -> &dyn TransactionMetaProvider;
+
+	fn as_utxo_snapshot_provider(&self) -> &dyn UtxoSnapshotProvider;
 }
 
 impl<T> AsSubstore for T
 where
-	T: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider,
+	T: BlockChain
+		+ BlockProvider
+		+ BlockFilterProvider
+		+ PruneProvider
+		+ ScriptIndexProvider
+		+ TransactionProvider
+		+ TransactionMetaProvider
+		+ TransactionOutputProvider
+		+ UtxoSnapshotProvider,
 {
 	fn as_block_provider(&self) -> &dyn BlockProvider {
 		&*self
@@ -49,6 +76,18 @@ where
 		&*self
 	}
 
+	fn as_block_filter_provider(&self) -> &dyn BlockFilterProvider {
+		&*self
+	}
+
+	fn as_prune_provider(&self) -> &dyn PruneProvider {
+		&*self
+	}
+
+	fn as_script_index_provider(&self) -> &dyn ScriptIndexProvider {
+		&*self
+	}
+
 	fn as_transaction_provider(&self) -> &dyn TransactionProvider {
 		&*self
 	}
@@ -60,6 +99,10 @@ where
 	fn as_transaction_meta_provider(&self) -> &dyn TransactionMetaProvider {
 		&*self
 	}
+
+	fn as_utxo_snapshot_provider(&self) -> &dyn UtxoSnapshotProvider {
+		&*self
+	}
 }
 
 pub type SharedStore = Arc<dyn CanonStore + Send + Sync>;
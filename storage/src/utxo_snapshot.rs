@@ -0,0 +1,527 @@
+//! UTXO set snapshots (assumeutxo)
+//!
+//! Snapshot consists of the metadata, headers of the canon chain blocks (except genesis) and canon chain
+//! transactions with unspent outputs, ordered by their position in the chain.
+//! Hash of the serialized transactions must be known to the network (see `network::AssumeUtxo`) before
+//! the snapshot could be loaded into the database.
+
+use crate::bytes::Bytes;
+use crate::{BlockHeight, BlockRef, Error, Store, TransactionMeta};
+use bitcrypto::{Hash, HashEngine, SHA256D};
+use chain::{IndexedBlockHeader, IndexedTransaction, Transaction, TransactionInput, TransactionOutput};
+use network::{AssumeUtxo, ConsensusParams, Magic};
+use ser::{serialize, Deserializable, Error as ReaderError, Reader, Serializable, Stream};
+use std::io;
+
+/// First bytes of every snapshot file
+const SNAPSHOT_MAGIC: [u8; 5] = *b"utxo\xff";
+/// Version of the snapshot format
+const SNAPSHOT_VERSION: u16 = 1;
+/// Maximal number of unspent outputs of the single snapshot transaction
+const MAX_SNAPSHOT_TRANSACTION_OUTPUTS: usize = 1_000_000;
+
+/// Snapshot metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotMetadata {
+	/// Magic of the network, the snapshot has been made for
+	pub network_magic: Magic,
+	/// Hash of the snapshot base block
+	pub base_hash: SHA256D,
+	/// Height of the snapshot base block
+	pub base_height: u32,
+	/// Number of transactions in the snapshot
+	pub transactions: u64,
+}
+
+impl Serializable for SnapshotMetadata {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append_slice(&SNAPSHOT_MAGIC)
+			.append(&SNAPSHOT_VERSION)
+			.append(&self.network_magic)
+			.append(&self.base_hash)
+			.append(&self.base_height)
+			.append(&self.transactions);
+	}
+}
+
+impl Deserializable for SnapshotMetadata {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError>
+	where
+		T: io::Read,
+	{
+		let mut magic = [0u8; 5];
+		reader.read_slice(&mut magic)?;
+		let version: u16 = reader.read()?;
+		if magic != SNAPSHOT_MAGIC || version != SNAPSHOT_VERSION {
+			return Err(ReaderError::MalformedData);
+		}
+
+		Ok(SnapshotMetadata {
+			network_magic: reader.read()?,
+			base_hash: reader.read()?,
+			base_height: reader.read()?,
+			transactions: reader.read()?,
+		})
+	}
+}
+
+/// Canon chain transaction with unspent outputs
+#[derive(Debug, Clone, PartialEq, Serializable)]
+pub struct SnapshotTransaction {
+	/// Transaction hash
+	pub hash: SHA256D,
+	/// Height of the block, containing transaction
+	pub height: u32,
+	/// Is this a coinbase transaction?
+	pub is_coinbase: bool,
+	/// Unspent outputs, ordered by their index
+	pub outputs: Vec<SnapshotOutput>,
+}
+
+/// Unspent transaction output
+#[derive(Debug, Clone, PartialEq, Serializable, Deserializable)]
+pub struct SnapshotOutput {
+	/// Index of the output in the transaction
+	pub index: u32,
+	/// The output itself
+	pub output: TransactionOutput,
+}
+
+impl Deserializable for SnapshotTransaction {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError>
+	where
+		T: io::Read,
+	{
+		Ok(SnapshotTransaction {
+			hash: reader.read()?,
+			height: reader.read()?,
+			is_coinbase: reader.read()?,
+			outputs: reader.read_list_max(MAX_SNAPSHOT_TRANSACTION_OUTPUTS)?,
+		})
+	}
+}
+
+impl SnapshotTransaction {
+	/// Builds snapshot transaction from the stored transaction. Returns None if all outputs are spent.
+	pub fn from_stored(tx: &IndexedTransaction, meta: &TransactionMeta) -> Option<Self> {
+		let outputs = tx
+			.raw
+			.outputs
+			.iter()
+			.enumerate()
+			.filter(|(index, _)| meta.is_spent(*index) == Some(false))
+			.map(|(index, output)| SnapshotOutput {
+				index: index as u32,
+				output: output.clone(),
+			})
+			.collect::<Vec<_>>();
+		if outputs.is_empty() {
+			return None;
+		}
+
+		Some(SnapshotTransaction {
+			hash: tx.hash,
+			height: meta.height(),
+			is_coinbase: meta.is_coinbase(),
+			outputs,
+		})
+	}
+
+	/// Builds transaction and its meta to store in the database. Stored transaction has single empty input
+	/// (transaction without inputs can't be deserialized) and spent outputs are replaced with empty outputs,
+	/// so it must never be served as a real transaction.
+	pub fn to_stored(&self) -> (IndexedTransaction, TransactionMeta) {
+		let outputs_len = self.outputs.last().map(|output| output.index as usize + 1).unwrap_or(0);
+		let spent_output = TransactionOutput {
+			value: 0,
+			script_pubkey: Bytes::new(),
+		};
+		let mut transaction = Transaction {
+			inputs: vec![TransactionInput::default()],
+			outputs: vec![spent_output; outputs_len],
+			..Transaction::default()
+		};
+
+		let mut meta = TransactionMeta::new(self.height, outputs_len);
+		if self.is_coinbase {
+			meta.set_coinbase();
+		}
+		for index in 0..outputs_len {
+			meta.denote_used(index);
+		}
+		for output in &self.outputs {
+			transaction.outputs[output.index as usize] = output.output.clone();
+			meta.denote_unused(output.index as usize);
+		}
+
+		(IndexedTransaction::new(self.hash, transaction), meta)
+	}
+}
+
+/// Base block of the UTXO snapshot, the chain state has been loaded from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotBase {
+	/// Snapshot base block
+	pub block: BlockHeight,
+	/// Number of transactions, loaded from the snapshot
+	pub transactions: u64,
+	/// Is the snapshot validated by full validation of the blocks up to the base block?
+	pub validated: bool,
+}
+
+pub trait UtxoSnapshotProvider {
+	/// returns base of the UTXO snapshot, if chain state has been loaded from the snapshot
+	fn snapshot_base(&self) -> Option<SnapshotBase>;
+
+	/// resolves hash of the snapshot transaction by its index in the snapshot
+	fn snapshot_transaction_hash(&self, index: u64) -> Option<SHA256D>;
+
+	/// resolves transaction, loaded from the snapshot. Returned transaction only has unspent outputs
+	/// (see `SnapshotTransaction::to_stored`), so it must never be served as a real transaction
+	fn snapshot_transaction(&self, hash: &SHA256D) -> Option<IndexedTransaction>;
+}
+
+/// Written snapshot summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotInfo {
+	/// Snapshot metadata
+	pub metadata: SnapshotMetadata,
+	/// Number of unspent outputs in the snapshot
+	pub outputs: u64,
+	/// Hash of the snapshot transactions
+	pub hash: SHA256D,
+}
+
+/// Computes hash of the snapshot transactions
+#[derive(Default)]
+pub struct SnapshotHasher {
+	engine: <SHA256D as Hash>::Engine,
+}
+
+impl SnapshotHasher {
+	pub fn input(&mut self, transaction: &SnapshotTransaction) {
+		self.engine.input(&serialize(transaction));
+	}
+
+	pub fn finish(self) -> SHA256D {
+		SHA256D::from_engine(self.engine)
+	}
+}
+
+/// Reads snapshot from the stream: metadata, then all headers, then all transactions.
+pub struct SnapshotReader<R> {
+	reader: Reader<R>,
+	metadata: SnapshotMetadata,
+	headers_read: u32,
+	transactions_read: u64,
+}
+
+impl<R> SnapshotReader<R>
+where
+	R: io::Read,
+{
+	pub fn new(read: R) -> Result<Self, Error> {
+		let mut reader = Reader::from_read(read);
+		let metadata = reader.read().map_err(snapshot_read_error)?;
+		Ok(SnapshotReader {
+			reader,
+			metadata,
+			headers_read: 0,
+			transactions_read: 0,
+		})
+	}
+
+	pub fn metadata(&self) -> &SnapshotMetadata {
+		&self.metadata
+	}
+
+	/// Reads next header. Returns None after header of the base block has been read.
+	pub fn read_header(&mut self) -> Result<Option<IndexedBlockHeader>, Error> {
+		if self.headers_read == self.metadata.base_height {
+			return Ok(None);
+		}
+
+		let header = self.reader.read().map_err(snapshot_read_error)?;
+		self.headers_read += 1;
+		Ok(Some(header))
+	}
+
+	/// Reads next transaction. Returns None after the last transaction has been read.
+	pub fn read_transaction(&mut self) -> Result<Option<SnapshotTransaction>, Error> {
+		if self.headers_read != self.metadata.base_height {
+			return Err(Error::SnapshotError("headers must be read before transactions".into()));
+		}
+		if self.transactions_read == self.metadata.transactions {
+			if !self.reader.is_finished() {
+				return Err(Error::SnapshotError("unexpected data after the last transaction".into()));
+			}
+			return Ok(None);
+		}
+
+		let transaction = self.reader.read().map_err(snapshot_read_error)?;
+		self.transactions_read += 1;
+		Ok(Some(transaction))
+	}
+}
+
+/// Writes snapshot of the canon chain UTXO set, using seek to update metadata once all transactions are written.
+pub fn write_utxo_snapshot<W>(store: &dyn Store, network_magic: Magic, writer: &mut W) -> Result<SnapshotInfo, Error>
+where
+	W: io::Write + io::Seek,
+{
+	let best_block = store.best_block();
+	let mut metadata = SnapshotMetadata {
+		network_magic,
+		base_hash: best_block.hash,
+		base_height: best_block.number,
+		transactions: 0,
+	};
+
+	let start = writer.stream_position().map_err(snapshot_io_error)?;
+	write(writer, &metadata)?;
+	for number in 1..=best_block.number {
+		let header = store.block_header(BlockRef::Number(number)).ok_or(Error::InconsistentData)?;
+		write(writer, &header.raw)?;
+	}
+
+	let mut outputs = 0;
+	let mut hasher = SnapshotHasher::default();
+	for_each_utxo_transaction(store, best_block.number, |snapshot_tx| {
+		write(writer, &snapshot_tx)?;
+		hasher.input(&snapshot_tx);
+		metadata.transactions += 1;
+		outputs += snapshot_tx.outputs.len() as u64;
+		Ok(())
+	})?;
+
+	if store.best_block() != best_block {
+		return Err(Error::SnapshotError(
+			"best block has changed while snapshot was being written".into(),
+		));
+	}
+
+	writer.seek(io::SeekFrom::Start(start)).map_err(snapshot_io_error)?;
+	write(writer, &metadata)?;
+	writer.seek(io::SeekFrom::End(0)).map_err(snapshot_io_error)?;
+	writer.flush().map_err(snapshot_io_error)?;
+
+	Ok(SnapshotInfo {
+		metadata,
+		outputs,
+		hash: hasher.finish(),
+	})
+}
+
+/// Computes hash of the canon chain UTXO set, which is equal to the hash of the snapshot, written at the same block.
+pub fn utxo_set_hash(store: &dyn Store) -> Result<SHA256D, Error> {
+	let best_block = store.best_block();
+	let mut hasher = SnapshotHasher::default();
+	for_each_utxo_transaction(store, best_block.number, |snapshot_tx| {
+		hasher.input(&snapshot_tx);
+		Ok(())
+	})?;
+
+	if store.best_block() != best_block {
+		return Err(Error::SnapshotError(
+			"best block has changed while UTXO set was being hashed".into(),
+		));
+	}
+
+	Ok(hasher.finish())
+}
+
+/// Calls `f` for every canon chain transaction with unspent outputs, in the snapshot order.
+fn for_each_utxo_transaction<F>(store: &dyn Store, best_block: u32, mut f: F) -> Result<(), Error>
+where
+	F: FnMut(SnapshotTransaction) -> Result<(), Error>,
+{
+	let mut visit_transaction = |hash: &SHA256D, height: Option<u32>| -> Result<(), Error> {
+		let meta = match store.transaction_meta(hash) {
+			Some(meta) => meta,
+			// fully spent transactions of pruned blocks are deleted
+			None if height.map_or(false, |height| store.is_pruned(height)) => return Ok(()),
+			None => return Err(Error::InconsistentData),
+		};
+		// transaction hash might be duplicated in the chain (BIP30) => only the latest copy is stored
+		if height.map(|height| height != meta.height()).unwrap_or(false) {
+			return Ok(());
+		}
+
+		let tx = match height {
+			Some(_) => store.transaction(hash),
+			None => store.snapshot_transaction(hash),
+		};
+		let tx = tx.ok_or(Error::InconsistentData)?;
+		match SnapshotTransaction::from_stored(&tx, &meta) {
+			Some(snapshot_tx) => f(snapshot_tx),
+			None => Ok(()),
+		}
+	};
+
+	// genesis coinbase could never be spent => it is never included in the snapshot
+	let mut first_block = 1;
+	if let Some(snapshot_base) = store.snapshot_base() {
+		for index in 0..snapshot_base.transactions {
+			let hash = store.snapshot_transaction_hash(index).ok_or(Error::InconsistentData)?;
+			visit_transaction(&hash, None)?;
+		}
+		first_block = snapshot_base.block.number + 1;
+	}
+	for number in first_block..=best_block {
+		for hash in store.block_transaction_hashes(BlockRef::Number(number)) {
+			visit_transaction(&hash, Some(number))?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Reads the whole snapshot and checks that it is one of the snapshots, known to the network.
+/// Returns matching assumeutxo data.
+pub fn verify_utxo_snapshot<R>(read: R, consensus: &ConsensusParams) -> Result<AssumeUtxo, Error>
+where
+	R: io::Read,
+{
+	let mut reader = SnapshotReader::new(read)?;
+	let metadata = reader.metadata().clone();
+	if metadata.network_magic != consensus.magic() {
+		return Err(Error::SnapshotError("snapshot has been made for another network".into()));
+	}
+
+	let assumeutxo = consensus
+		.assumeutxo_for(&metadata.base_hash)
+		.ok_or_else(|| Error::SnapshotError(format!("snapshot base block {} is not known to the network", metadata.base_hash)))?;
+	if assumeutxo.height != metadata.base_height || assumeutxo.transactions != metadata.transactions {
+		return Err(Error::SnapshotError("snapshot metadata does not match the known snapshot".into()));
+	}
+
+	let mut previous_hash = *consensus.network.genesis_block().hash();
+	while let Some(header) = reader.read_header()? {
+		if header.raw.previous_header_hash != previous_hash {
+			return Err(Error::SnapshotError(format!(
+				"header {} is not connected to the previous header",
+				header.hash
+			)));
+		}
+		previous_hash = header.hash;
+	}
+	if previous_hash != metadata.base_hash {
+		return Err(Error::SnapshotError("headers chain does not end with snapshot base block".into()));
+	}
+
+	let mut hasher = SnapshotHasher::default();
+	while let Some(transaction) = reader.read_transaction()? {
+		hasher.input(&transaction);
+	}
+	let hash = hasher.finish();
+	if hash != assumeutxo.txoutset_hash {
+		return Err(Error::SnapshotError(format!(
+			"snapshot hash {} does not match the expected hash {}",
+			hash, assumeutxo.txoutset_hash
+		)));
+	}
+
+	Ok(assumeutxo.clone())
+}
+
+fn write<W, T>(writer: &mut W, value: &T) -> Result<(), Error>
+where
+	W: io::Write,
+	T: Serializable,
+{
+	writer.write_all(&serialize(value)).map_err(snapshot_io_error)
+}
+
+fn snapshot_io_error(err: io::Error) -> Error {
+	Error::SnapshotError(err.to_string())
+}
+
+fn snapshot_read_error(err: ReaderError) -> Error {
+	Error::SnapshotError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{SnapshotMetadata, SnapshotOutput, SnapshotTransaction};
+	use crate::TransactionMeta;
+	use bitcrypto::SHA256D;
+	use chain::{IndexedTransaction, Transaction, TransactionOutput};
+	use ser::{deserialize, serialize, Error as ReaderError};
+
+	fn output(value: u64) -> TransactionOutput {
+		TransactionOutput {
+			value,
+			script_pubkey: "51".into(),
+		}
+	}
+
+	#[test]
+	fn test_snapshot_metadata_serialization() {
+		let metadata = SnapshotMetadata {
+			network_magic: 0xd9b4bef9,
+			base_hash: SHA256D::default(),
+			base_height: 10,
+			transactions: 20,
+		};
+		let serialized = serialize(&metadata);
+		assert_eq!(&serialized[..7], b"utxo\xff\x01\x00");
+		assert_eq!(deserialize::<_, SnapshotMetadata>(&serialized[..]).unwrap(), metadata);
+
+		let mut wrong_version = serialized.take();
+		wrong_version[5] = 2;
+		assert_eq!(
+			deserialize::<_, SnapshotMetadata>(&wrong_version[..]).unwrap_err(),
+			ReaderError::MalformedData
+		);
+	}
+
+	#[test]
+	fn test_snapshot_transaction_stored_roundtrip() {
+		let mut raw = Transaction::default();
+		raw.outputs = vec![output(1), output(2), output(3)];
+		let tx = IndexedTransaction::from_raw(raw);
+
+		let mut meta = TransactionMeta::new_coinbase(5, 3);
+		meta.denote_used(0);
+		meta.denote_used(2);
+		let snapshot_tx = SnapshotTransaction::from_stored(&tx, &meta).unwrap();
+		assert_eq!(
+			snapshot_tx,
+			SnapshotTransaction {
+				hash: tx.hash,
+				height: 5,
+				is_coinbase: true,
+				outputs: vec![SnapshotOutput {
+					index: 1,
+					output: output(2)
+				}],
+			}
+		);
+		assert_eq!(
+			deserialize::<_, SnapshotTransaction>(&serialize(&snapshot_tx)[..]).unwrap(),
+			snapshot_tx
+		);
+
+		let (stored_tx, stored_meta) = snapshot_tx.to_stored();
+		assert_eq!(stored_tx.hash, tx.hash);
+		assert_eq!(
+			stored_tx.raw.outputs,
+			vec![
+				TransactionOutput {
+					value: 0,
+					script_pubkey: "".into()
+				},
+				output(2)
+			]
+		);
+		assert_eq!(stored_meta.height(), 5);
+		assert!(stored_meta.is_coinbase());
+		assert_eq!(stored_meta.is_spent(0), Some(true));
+		assert_eq!(stored_meta.is_spent(1), Some(false));
+		assert_eq!(stored_meta.is_spent(2), None);
+
+		meta.denote_used(1);
+		assert_eq!(SnapshotTransaction::from_stored(&tx, &meta), None);
+	}
+}
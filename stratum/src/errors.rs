@@ -0,0 +1,57 @@
+//! Stratum error codes and error objects
+
+mod codes {
+	pub const OTHER: i64 = 20;
+	pub const JOB_NOT_FOUND: i64 = 21;
+	pub const DUPLICATE_SHARE: i64 = 22;
+	pub const LOW_DIFFICULTY_SHARE: i64 = 23;
+	pub const UNAUTHORIZED_WORKER: i64 = 24;
+	pub const NOT_SUBSCRIBED: i64 = 25;
+}
+
+use serde_json::Value;
+
+/// Stratum error, reported to the miner as `[code, message, traceback]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+	/// Any error, which has no dedicated code
+	Other(String),
+	/// Job is unknown or stale
+	JobNotFound,
+	/// Share has already been submitted
+	DuplicateShare,
+	/// Share hash doesn't meet the session difficulty
+	LowDifficultyShare,
+	/// Worker hasn't been authorized
+	UnauthorizedWorker,
+	/// Session hasn't been subscribed to jobs
+	NotSubscribed,
+}
+
+impl Error {
+	pub fn code(&self) -> i64 {
+		match *self {
+			Error::Other(_) => codes::OTHER,
+			Error::JobNotFound => codes::JOB_NOT_FOUND,
+			Error::DuplicateShare => codes::DUPLICATE_SHARE,
+			Error::LowDifficultyShare => codes::LOW_DIFFICULTY_SHARE,
+			Error::UnauthorizedWorker => codes::UNAUTHORIZED_WORKER,
+			Error::NotSubscribed => codes::NOT_SUBSCRIBED,
+		}
+	}
+
+	pub fn message(&self) -> &str {
+		match *self {
+			Error::Other(ref message) => message,
+			Error::JobNotFound => "Job not found",
+			Error::DuplicateShare => "Duplicate share",
+			Error::LowDifficultyShare => "Low difficulty share",
+			Error::UnauthorizedWorker => "Unauthorized worker",
+			Error::NotSubscribed => "Not subscribed",
+		}
+	}
+
+	pub fn to_value(&self) -> Value {
+		Value::Array(vec![self.code().into(), self.message().into(), Value::Null])
+	}
+}
@@ -0,0 +1,357 @@
+//! Mining jobs, built from the block templates
+
+use bitcrypto::{ToHex, SHA256D};
+use chain::{merkle_node_hash, BlockHeader, IndexedBlock, Transaction};
+use keys::Address;
+use miner::{BlockTemplate, CoinbaseTransactionBuilder, SimpleCoinbaseTransactionBuilder};
+use parking_lot::Mutex;
+use primitives::bytes::Bytes;
+use primitives::U256;
+use script::{Builder, Opcode};
+use ser::{serialize, CompactInteger, Serializable};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use verification::is_valid_proof_of_work_hash;
+
+/// Size of the extranonce, assigned by the server to every session
+pub const EXTRANONCE1_SIZE: usize = 4;
+/// Size of the extranonce, rolled by the miner
+pub const EXTRANONCE2_SIZE: usize = 4;
+/// Maximal number of seconds, block time could be ahead of the current time
+const MAX_FUTURE_BLOCK_TIME: u32 = 2 * 60 * 60;
+/// Precision of the share difficulty, used when converting it to the target
+const DIFFICULTY_PRECISION: u64 = 1_000_000;
+
+/// Single mining job: block template with the coinbase transaction, split around the extranonce
+pub struct Job {
+	/// Job id, reported to the miner
+	pub id: String,
+	template: BlockTemplate,
+	payout_address: Address,
+	/// Part of the coinbase script, preceding the extranonce (BIP34 height and extranonce push)
+	script_sig_prefix: Bytes,
+	/// Serialized coinbase transaction up to the extranonce
+	coinbase1: Bytes,
+	/// Serialized coinbase transaction after the extranonce
+	coinbase2: Bytes,
+	/// Merkle branch of the coinbase transaction
+	merkle_branch: Vec<SHA256D>,
+}
+
+impl Job {
+	pub fn new(id: String, template: BlockTemplate, payout_address: Address) -> Self {
+		let script_sig_prefix = Builder::default()
+			.push_num(template.height.into())
+			.push_opcode(Opcode::OP_PUSHBYTES_8)
+			.into_bytes();
+		let mut job = Job {
+			id,
+			template,
+			payout_address,
+			script_sig_prefix,
+			coinbase1: Bytes::default(),
+			coinbase2: Bytes::default(),
+			merkle_branch: Vec::new(),
+		};
+
+		// coinbase transaction (without witness) is: version, inputs count, previous output, script length, script, ...
+		let placeholder = [0u8; EXTRANONCE1_SIZE + EXTRANONCE2_SIZE];
+		let coinbase = serialize(&job.coinbase(&placeholder[..EXTRANONCE1_SIZE], &placeholder[EXTRANONCE1_SIZE..]));
+		let script_sig_len = job.script_sig_prefix.len() + placeholder.len();
+		let extranonce_offset = 4 + 1 + 36 + CompactInteger::from(script_sig_len).serialized_size() + job.script_sig_prefix.len();
+		job.coinbase1 = coinbase[..extranonce_offset].to_vec().into();
+		job.coinbase2 = coinbase[extranonce_offset + placeholder.len()..].to_vec().into();
+		job.merkle_branch = merkle_branch(&job.template.transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>());
+		job
+	}
+
+	/// Hash of the block, this job is built on top of
+	pub fn previous_header_hash(&self) -> &SHA256D {
+		&self.template.previous_header_hash
+	}
+
+	/// Parameters of `mining.notify`
+	pub fn notify_params(&self, clean_jobs: bool) -> Value {
+		Value::Array(vec![
+			self.id.clone().into(),
+			stratum_previous_hash(&self.template.previous_header_hash).into(),
+			self.coinbase1.to_hex().into(),
+			self.coinbase2.to_hex().into(),
+			Value::Array(self.merkle_branch.iter().map(|hash| (&**hash).to_hex().into()).collect()),
+			format!("{:08x}", self.template.version).into(),
+			format!("{:08x}", u32::from(self.template.bits)).into(),
+			format!("{:08x}", self.template.time).into(),
+			clean_jobs.into(),
+		])
+	}
+
+	/// Returns true if block time of the share is acceptable
+	pub fn is_valid_time(&self, time: u32, current_time: u32) -> bool {
+		time >= self.template.time && time <= current_time.saturating_add(MAX_FUTURE_BLOCK_TIME)
+	}
+
+	/// Builds coinbase transaction with given extranonces
+	pub fn coinbase(&self, extranonce1: &[u8], extranonce2: &[u8]) -> Transaction {
+		let mut builder = SimpleCoinbaseTransactionBuilder::with_address(&self.payout_address, self.template.coinbase_value);
+		if let Some(ref commitment) = self.template.default_witness_commitment {
+			builder = builder.with_witness_commitment(commitment.clone());
+		}
+
+		let mut script_sig = self.script_sig_prefix.to_vec();
+		script_sig.extend_from_slice(extranonce1);
+		script_sig.extend_from_slice(extranonce2);
+		builder.set_extranonce(&script_sig);
+		builder.finish()
+	}
+
+	/// Builds block header with given coinbase transaction
+	pub fn header(&self, coinbase: &Transaction, time: u32, nonce: u32) -> BlockHeader {
+		let merkle_root_hash = self
+			.merkle_branch
+			.iter()
+			.fold(coinbase.hash(), |hash, branch_hash| merkle_node_hash(&hash, branch_hash));
+		BlockHeader {
+			version: self.template.version,
+			previous_header_hash: self.template.previous_header_hash,
+			merkle_root_hash,
+			time,
+			bits: self.template.bits,
+			nonce,
+		}
+	}
+
+	/// Returns true if header hash meets the network target
+	pub fn is_block_solution(&self, hash: &SHA256D) -> bool {
+		is_valid_proof_of_work_hash(self.template.bits, hash)
+	}
+
+	/// Builds solved block
+	pub fn block(&self, header: BlockHeader, coinbase: Transaction) -> IndexedBlock {
+		let mut transactions = Vec::with_capacity(self.template.transactions.len() + 1);
+		transactions.push(coinbase.into());
+		transactions.extend(self.template.transactions.iter().cloned());
+		IndexedBlock::new(header.into(), transactions)
+	}
+}
+
+/// Jobs, issued to the miners
+pub struct JobManager {
+	payout_address: Address,
+	state: Mutex<JobManagerState>,
+}
+
+#[derive(Default)]
+struct JobManagerState {
+	next_id: u64,
+	current: Option<Arc<Job>>,
+	jobs: HashMap<String, Arc<Job>>,
+}
+
+impl JobManager {
+	pub fn new(payout_address: Address) -> Self {
+		JobManager {
+			payout_address,
+			state: Mutex::default(),
+		}
+	}
+
+	/// Creates new job from the template. Returns the job and true if all previous jobs are now stale
+	pub fn update(&self, template: BlockTemplate) -> (Arc<Job>, bool) {
+		let mut state = self.state.lock();
+		let clean_jobs = state
+			.current
+			.as_ref()
+			.map_or(true, |current| current.previous_header_hash() != &template.previous_header_hash);
+		if clean_jobs {
+			state.jobs.clear();
+		}
+
+		state.next_id += 1;
+		let job = Arc::new(Job::new(format!("{:x}", state.next_id), template, self.payout_address.clone()));
+		state.jobs.insert(job.id.clone(), job.clone());
+		state.current = Some(job.clone());
+		(job, clean_jobs)
+	}
+
+	/// Returns the most recent job
+	pub fn current(&self) -> Option<Arc<Job>> {
+		self.state.lock().current.clone()
+	}
+
+	/// Returns job with given id, if it is not stale
+	pub fn get(&self, id: &str) -> Option<Arc<Job>> {
+		self.state.lock().jobs.get(id).cloned()
+	}
+}
+
+/// Returns hashes, which are combined with the coinbase transaction hash to compute the merkle root
+fn merkle_branch(transactions: &[SHA256D]) -> Vec<SHA256D> {
+	let mut branch = Vec::new();
+	// every level, except for the coinbase path node
+	let mut level = transactions.to_vec();
+	while !level.is_empty() {
+		branch.push(level[0]);
+		level = level[1..]
+			.chunks(2)
+			.map(|pair| merkle_node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+			.collect();
+	}
+	branch
+}
+
+/// Previous block hash in the Stratum format: internal byte order with swapped bytes of every 4-byte word
+fn stratum_previous_hash(hash: &SHA256D) -> String {
+	let mut bytes = hash.to_vec();
+	for word in bytes.chunks_mut(4) {
+		word.reverse();
+	}
+	bytes.to_hex()
+}
+
+/// Converts share difficulty to the target. Difficulty 1 target is 0xffff * 2^208
+pub fn difficulty_target(difficulty: f64) -> U256 {
+	let scaled_difficulty = ((difficulty * DIFFICULTY_PRECISION as f64) as u64).max(1);
+	let difficulty_1_target = U256::from(0xffffu64) << 208;
+	difficulty_1_target * U256::from(DIFFICULTY_PRECISION) / U256::from(scaled_difficulty)
+}
+
+/// Returns true if hash is lower or equal than target
+pub fn meets_target(hash: &SHA256D, target: &U256) -> bool {
+	U256::from_little_endian(&**hash) <= *target
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::{difficulty_target, meets_target, merkle_branch, stratum_previous_hash, Job, JobManager};
+	use bitcrypto::{dhash256, Hash, SHA256D};
+	use chain::{merkle_root, IndexedTransaction, Transaction};
+	use keys::{Address, AddressHash, Network, Type};
+	use miner::BlockTemplate;
+	use primitives::bytes::Bytes;
+	use primitives::U256;
+	use ser::serialize;
+
+	pub fn payout_address() -> Address {
+		Address {
+			kind: Type::P2PKH,
+			network: Network::Regtest,
+			hash: AddressHash::from_inner([1u8; 20]).into(),
+		}
+	}
+
+	pub fn template(previous_header_hash: SHA256D, bits: u32, transactions: Vec<IndexedTransaction>) -> BlockTemplate {
+		BlockTemplate {
+			version: 0x2000_0000,
+			previous_header_hash,
+			time: 1_600_000_000,
+			bits: bits.into(),
+			height: 500,
+			transactions,
+			coinbase_value: 5_000_000_000,
+			size_limit: 4_000_000,
+			sigop_limit: 80_000,
+			weight_limit: 4_000_000,
+			default_witness_commitment: Some(vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed].into()),
+		}
+	}
+
+	fn transactions(count: u32) -> Vec<IndexedTransaction> {
+		(0..count)
+			.map(|lock_time| {
+				Transaction {
+					version: 1,
+					lock_time,
+					..Default::default()
+				}
+				.into()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn job_coinbase_is_split_around_extranonce() {
+		let job = Job::new(
+			"1".into(),
+			template(SHA256D::default(), 0x207fffff, transactions(3)),
+			payout_address(),
+		);
+		let coinbase = job.coinbase(&[1, 2, 3, 4], &[5, 6, 7, 8]);
+		let mut joined = job.coinbase1.to_vec();
+		joined.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+		joined.extend_from_slice(&job.coinbase2);
+		assert_eq!(Bytes::from(joined), serialize(&coinbase));
+		assert_eq!(dhash256(&serialize(&coinbase)), coinbase.hash());
+		// BIP34 height
+		assert_eq!(&coinbase.inputs[0].script_sig[..3], &[0x02, 0xf4, 0x01]);
+		assert_eq!(coinbase.outputs.len(), 2);
+	}
+
+	#[test]
+	fn job_merkle_branch() {
+		for count in 0..8 {
+			let transactions = transactions(count);
+			let coinbase_hash = dhash256(&[count as u8]);
+			let mut hashes = vec![coinbase_hash];
+			hashes.extend(transactions.iter().map(|tx| tx.hash));
+
+			let branch = merkle_branch(&hashes[1..]);
+			let root = branch
+				.iter()
+				.fold(coinbase_hash, |hash, branch_hash| chain::merkle_node_hash(&hash, branch_hash));
+			assert_eq!(root, merkle_root(&hashes));
+		}
+	}
+
+	#[test]
+	fn job_notify_params() {
+		let mut previous_hash = [0u8; 32];
+		for (index, byte) in previous_hash.iter_mut().enumerate() {
+			*byte = index as u8;
+		}
+		let previous_hash = SHA256D::from_inner(previous_hash);
+		assert_eq!(
+			stratum_previous_hash(&previous_hash),
+			"03020100070605040b0a09080f0e0d0c13121110171615141b1a19181f1e1d1c"
+		);
+
+		let job = Job::new("1f".into(), template(previous_hash, 0x1d00ffff, Vec::new()), payout_address());
+		let params = job.notify_params(true);
+		assert_eq!(params[0], "1f");
+		assert_eq!(params[4], serde_json::json!([]));
+		assert_eq!(params[5], "20000000");
+		assert_eq!(params[6], "1d00ffff");
+		assert_eq!(params[7], "5f5e1000");
+		assert_eq!(params[8], true);
+	}
+
+	#[test]
+	fn job_manager_cleans_stale_jobs() {
+		let manager = JobManager::new(payout_address());
+		let (first, clean) = manager.update(template(SHA256D::default(), 0x207fffff, Vec::new()));
+		assert!(clean);
+		let (second, clean) = manager.update(template(SHA256D::default(), 0x207fffff, transactions(1)));
+		assert!(!clean);
+		assert!(manager.get(&first.id).is_some());
+
+		let (third, clean) = manager.update(template(dhash256(&[1]), 0x207fffff, Vec::new()));
+		assert!(clean);
+		assert!(manager.get(&first.id).is_none());
+		assert!(manager.get(&second.id).is_none());
+		assert_eq!(manager.current().unwrap().id, third.id);
+	}
+
+	#[test]
+	fn share_difficulty_target() {
+		let difficulty_1_target = U256::from(0xffffu64) << 208;
+		assert_eq!(difficulty_target(1.0), difficulty_1_target);
+		assert_eq!(difficulty_target(2.0), difficulty_1_target / U256::from(2u64));
+		assert_eq!(difficulty_target(0.5), difficulty_1_target * U256::from(2u64));
+
+		let mut hash = [0u8; 32];
+		hash[27] = 0xff;
+		assert!(meets_target(&SHA256D::from_inner(hash), &difficulty_target(1.0)));
+		hash[28] = 0x01;
+		assert!(!meets_target(&SHA256D::from_inner(hash), &difficulty_target(1.0)));
+	}
+}
@@ -0,0 +1,30 @@
+//! Stratum v1 mining server
+//!
+//! https://en.bitcoin.it/wiki/Stratum_mining_protocol
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+extern crate bitcrypto;
+extern crate chain;
+extern crate keys;
+extern crate miner;
+extern crate parking_lot;
+extern crate primitives;
+extern crate script;
+extern crate serde;
+extern crate serde_json;
+extern crate serialization as ser;
+extern crate sync;
+extern crate verification;
+
+mod errors;
+mod job;
+mod notifier;
+mod server;
+mod session;
+mod types;
+mod vardiff;
+
+pub use crate::server::{new_tcp, Dependencies, Server, TcpConfiguration};
@@ -0,0 +1,33 @@
+use bitcrypto::SHA256D;
+use std::sync::atomic::{AtomicBool, Ordering};
+use sync::SyncListener;
+use tokio::sync::broadcast;
+
+/// Forwards new best blocks to the Stratum jobs updater
+pub struct Notifier {
+	sender: broadcast::Sender<SHA256D>,
+	is_synchronizing: AtomicBool,
+}
+
+impl Notifier {
+	pub fn new(sender: broadcast::Sender<SHA256D>) -> Self {
+		Notifier {
+			sender,
+			is_synchronizing: AtomicBool::default(),
+		}
+	}
+}
+
+impl SyncListener for Notifier {
+	fn synchronization_state_switched(&self, is_synchronizing: bool) {
+		self.is_synchronizing.store(is_synchronizing, Ordering::SeqCst);
+	}
+
+	fn best_storage_block_inserted(&self, block_hash: &SHA256D) {
+		// there's no point in mining on top of the stale tip while node is synchronizing
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			// sending only fails when jobs updater is not running
+			let _ = self.sender.send(*block_hash);
+		}
+	}
+}
@@ -0,0 +1,245 @@
+use crate::job::{Job, JobManager};
+use crate::notifier::Notifier;
+use crate::session::Session;
+use bitcrypto::SHA256D;
+use keys::Address;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task;
+
+/// Number of best block and job notifications, buffered for every receiver
+const NOTIFICATIONS_BUFFER_SIZE: usize = 16;
+/// Interval between job updates, made to include new memory pool transactions
+const JOB_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// Maximal length of the single request line. Miner is disconnected if it sends longer line
+const MAX_REQUEST_LENGTH: usize = 16 * 1024;
+
+pub struct Dependencies {
+	pub local_sync_node: sync::LocalNodeRef,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct TcpConfiguration {
+	pub enabled: bool,
+	pub interface: String,
+	pub port: u16,
+	/// Address, receiving coinbase outputs of the found blocks
+	pub payout_address: Option<Address>,
+	/// Initial (and minimal) share difficulty
+	pub difficulty: f64,
+}
+
+impl TcpConfiguration {
+	pub fn with_port(port: u16) -> Self {
+		TcpConfiguration {
+			enabled: false,
+			interface: "127.0.0.1".into(),
+			port,
+			payout_address: None,
+			difficulty: 1.0,
+		}
+	}
+}
+
+/// Stratum server, accepting miner connections
+pub struct Server {
+	listener: TcpListener,
+	local_sync_node: sync::LocalNodeRef,
+	jobs: Arc<JobManager>,
+	min_difficulty: f64,
+	new_blocks: broadcast::Sender<SHA256D>,
+	new_jobs: broadcast::Sender<(Arc<Job>, bool)>,
+	next_extranonce1: AtomicU32,
+}
+
+/// Binds Stratum server to the configured address. Must be called from within the Tokio runtime.
+pub async fn new_tcp(conf: TcpConfiguration, deps: Dependencies) -> Result<Option<Server>, String> {
+	if !conf.enabled {
+		return Ok(None);
+	}
+
+	let payout_address = conf
+		.payout_address
+		.ok_or_else(|| "Stratum server requires payout address, use the --stratum-address option".to_owned())?;
+	let url = format!("{}:{}", conf.interface, conf.port);
+	let addr: SocketAddr = url
+		.parse()
+		.map_err(|_| format!("Invalid Stratum listen host/port given: {}", url))?;
+	let listener = match TcpListener::bind(addr).await {
+		Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
+			return Err(format!("Stratum address {} is already in use, make sure that another instance of a Stratum server is not running or change the address using the --stratum-port and --stratum-interface options.", url));
+		}
+		Err(err) => return Err(format!("Stratum error: {:?}", err)),
+		Ok(listener) => listener,
+	};
+
+	let (new_blocks, _) = broadcast::channel(NOTIFICATIONS_BUFFER_SIZE);
+	deps.local_sync_node
+		.install_sync_listener(Box::new(Notifier::new(new_blocks.clone())));
+
+	let (new_jobs, _) = broadcast::channel(NOTIFICATIONS_BUFFER_SIZE);
+	Ok(Some(Server {
+		listener,
+		local_sync_node: deps.local_sync_node,
+		jobs: Arc::new(JobManager::new(payout_address)),
+		min_difficulty: conf.difficulty,
+		new_blocks,
+		new_jobs,
+		next_extranonce1: AtomicU32::default(),
+	}))
+}
+
+impl Server {
+	/// Accepts miner connections until the runtime is shut down
+	pub async fn run(self) {
+		info!(target: "stratum", "Stratum server is listening on {:?}", self.listener.local_addr());
+		tokio::spawn(update_jobs(
+			self.local_sync_node.clone(),
+			self.jobs.clone(),
+			self.new_blocks.subscribe(),
+			self.new_jobs.clone(),
+		));
+
+		loop {
+			match self.listener.accept().await {
+				Ok((stream, address)) => {
+					trace!(target: "stratum", "Accepted Stratum connection from {}", address);
+					let extranonce1 = self.next_extranonce1.fetch_add(1, Ordering::SeqCst).to_be_bytes();
+					let session = Arc::new(Session::new(
+						self.jobs.clone(),
+						Arc::new(self.local_sync_node.clone()),
+						extranonce1.to_vec().into(),
+						self.min_difficulty,
+					));
+					tokio::spawn(serve_connection(stream, session, self.new_jobs.subscribe()));
+				}
+				Err(err) => warn!(target: "stratum", "Failed to accept Stratum connection: {}", err),
+			}
+		}
+	}
+}
+
+/// Issues new job on every new best block and periodically refreshes the current job.
+/// The first job is issued immediately
+async fn update_jobs(
+	local_sync_node: sync::LocalNodeRef,
+	jobs: Arc<JobManager>,
+	mut new_blocks: broadcast::Receiver<SHA256D>,
+	new_jobs: broadcast::Sender<(Arc<Job>, bool)>,
+) {
+	let mut refresh = tokio::time::interval(JOB_REFRESH_INTERVAL);
+	loop {
+		tokio::select! {
+			block = new_blocks.recv() => match block {
+				Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => (),
+				Err(broadcast::error::RecvError::Closed) => break,
+			},
+			_ = refresh.tick() => (),
+		}
+
+		update_job(&local_sync_node, &jobs, &new_jobs).await;
+	}
+}
+
+async fn update_job(local_sync_node: &sync::LocalNodeRef, jobs: &Arc<JobManager>, new_jobs: &broadcast::Sender<(Arc<Job>, bool)>) {
+	// block assembler reads storage and memory pool synchronously => do not block the runtime threads
+	let local_sync_node = local_sync_node.clone();
+	let jobs = jobs.clone();
+	match task::spawn_blocking(move || jobs.update(local_sync_node.get_block_template(true))).await {
+		Ok(job) => {
+			trace!(target: "stratum", "Issued Stratum job {} on top of {}", job.0.id, job.0.previous_header_hash());
+			// sending only fails when there are no connected miners
+			let _ = new_jobs.send(job);
+		}
+		Err(err) => warn!(target: "stratum", "Stratum job creation has failed: {}", err),
+	}
+}
+
+/// Answers line-delimited requests of the single miner and sends new jobs
+async fn serve_connection(stream: TcpStream, session: Arc<Session>, mut new_jobs: broadcast::Receiver<(Arc<Job>, bool)>) {
+	let (reader, mut writer) = stream.into_split();
+	let mut reader = BufReader::new(reader);
+	let mut line = Vec::new();
+	loop {
+		// found blocks are verified synchronously => do not block the runtime threads
+		let messages = tokio::select! {
+			request = read_line(&mut reader, &mut line, MAX_REQUEST_LENGTH) => match request {
+				Ok(Some(request)) => {
+					let session = session.clone();
+					task::spawn_blocking(move || session.handle_request(&request)).await
+				}
+				Ok(None) => break,
+				Err(err) => {
+					trace!(target: "stratum", "Stratum connection error: {}", err);
+					break;
+				}
+			},
+			job = new_jobs.recv() => match job {
+				Ok((job, clean_jobs)) => Ok(session.job_notifications(&job, clean_jobs)),
+				// some of missed jobs could have invalidated previous ones => restart with the current job
+				Err(broadcast::error::RecvError::Lagged(_)) => Ok(session.current_job_notifications()),
+				Err(broadcast::error::RecvError::Closed) => break,
+			},
+		};
+
+		let messages = match messages {
+			Ok(messages) => messages,
+			Err(err) => {
+				warn!(target: "stratum", "Stratum request processing has failed: {}", err);
+				break;
+			}
+		};
+
+		for message in messages {
+			if writer.write_all(message.as_bytes()).await.is_err() || writer.write_all(b"\n").await.is_err() {
+				return;
+			}
+		}
+	}
+}
+
+/// Reads the next line of at most `max_length` bytes. Partially read line is kept in the `buffer`, so reading
+/// could be safely cancelled and resumed. Returns `None` at the end of stream and error if line is too long.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R, buffer: &mut Vec<u8>, max_length: usize) -> io::Result<Option<String>> {
+	// one more byte to detect too long lines
+	let limit = (max_length + 1).saturating_sub(buffer.len()) as u64;
+	reader.take(limit).read_until(b'\n', buffer).await?;
+
+	if buffer.last() == Some(&b'\n') {
+		buffer.pop();
+		if buffer.last() == Some(&b'\r') {
+			buffer.pop();
+		}
+	} else if buffer.len() > max_length {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "line is too long"));
+	} else if buffer.is_empty() {
+		return Ok(None);
+	}
+
+	let line = std::mem::take(buffer);
+	String::from_utf8(line)
+		.map(Some)
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::read_line;
+
+	#[tokio::test]
+	async fn read_line_is_bounded() {
+		let mut reader: &[u8] = b"{\"id\":1}\n0123456789a\n";
+		let mut buffer = Vec::new();
+		assert_eq!(
+			read_line(&mut reader, &mut buffer, 10).await.unwrap(),
+			Some("{\"id\":1}".to_owned())
+		);
+		assert!(read_line(&mut reader, &mut buffer, 10).await.is_err());
+	}
+}
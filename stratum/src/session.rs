@@ -0,0 +1,369 @@
+use crate::errors::Error;
+use crate::job::{difficulty_target, meets_target, Job, JobManager, EXTRANONCE2_SIZE};
+use crate::types::{Notification, Request, Response};
+use crate::vardiff::Vardiff;
+use bitcrypto::{FromHex, ToHex};
+use chain::IndexedBlock;
+use parking_lot::Mutex;
+use primitives::bytes::Bytes;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Accepts blocks, found by the miners
+pub trait BlockSink: Send + Sync {
+	/// Verifies block and schedules it for inserting into the chain. Returns reject reason if block is invalid
+	fn submit_block(&self, block: IndexedBlock) -> Result<(), String>;
+}
+
+impl BlockSink for sync::LocalNodeRef {
+	fn submit_block(&self, block: IndexedBlock) -> Result<(), String> {
+		(**self).submit_block(block)
+	}
+}
+
+/// State of the single miner connection
+struct SessionState {
+	/// Miner is subscribed to jobs
+	subscribed: bool,
+	/// Names of authorized workers
+	workers: HashSet<String>,
+	/// Share difficulty
+	vardiff: Vardiff,
+	/// Difficulty, which has been sent to the miner
+	sent_difficulty: Option<f64>,
+	/// Shares, submitted since the last clean job: job id, extranonce2, time and nonce
+	shares: HashSet<(String, Bytes, u32, u32)>,
+}
+
+/// Stratum client session
+pub struct Session {
+	jobs: Arc<JobManager>,
+	block_sink: Arc<dyn BlockSink>,
+	/// Extranonce, assigned to this session
+	extranonce1: Bytes,
+	state: Mutex<SessionState>,
+}
+
+impl Session {
+	pub fn new(jobs: Arc<JobManager>, block_sink: Arc<dyn BlockSink>, extranonce1: Bytes, min_difficulty: f64) -> Self {
+		Session {
+			jobs,
+			block_sink,
+			extranonce1,
+			state: Mutex::new(SessionState {
+				subscribed: false,
+				workers: HashSet::new(),
+				vardiff: Vardiff::new(min_difficulty, current_time()),
+				sent_difficulty: None,
+				shares: HashSet::new(),
+			}),
+		}
+	}
+
+	/// Handles single request line. Returns response, followed by notifications
+	pub fn handle_request(&self, line: &str) -> Vec<String> {
+		let request: Request = match serde_json::from_str(line) {
+			Ok(request) => request,
+			Err(err) => {
+				trace!(target: "stratum", "Invalid Stratum request: {}", err);
+				return vec![response(Value::Null, Err(Error::Other("Invalid request".into())))];
+			}
+		};
+
+		let mut notifications = Vec::new();
+		let result = match request.method.as_str() {
+			"mining.subscribe" => self.subscribe(),
+			"mining.authorize" => self.authorize(&request.params, &mut notifications),
+			"mining.extranonce.subscribe" => Ok(true.into()),
+			"mining.submit" => self.submit(&request.params, &mut notifications),
+			method => Err(Error::Other(format!("Method not found: {}", method))),
+		};
+
+		let mut messages = vec![response(request.id, result)];
+		messages.extend(notifications);
+		messages
+	}
+
+	/// Returns notifications about new job
+	pub fn job_notifications(&self, job: &Job, clean_jobs: bool) -> Vec<String> {
+		let mut state = self.state.lock();
+		if !state.subscribed || state.workers.is_empty() {
+			return Vec::new();
+		}
+
+		if clean_jobs {
+			state.shares.clear();
+		}
+		let mut notifications = Vec::new();
+		Self::push_job(&mut state, job, clean_jobs, &mut notifications);
+		notifications
+	}
+
+	/// Returns notifications about the current job, which invalidates all previous jobs
+	pub fn current_job_notifications(&self) -> Vec<String> {
+		match self.jobs.current() {
+			Some(job) => self.job_notifications(&job, true),
+			None => Vec::new(),
+		}
+	}
+
+	fn subscribe(&self) -> Result<Value, Error> {
+		self.state.lock().subscribed = true;
+		let subscription_id = self.extranonce1.to_hex();
+		Ok(serde_json::json!([
+			[["mining.set_difficulty", subscription_id], ["mining.notify", subscription_id]],
+			self.extranonce1.to_hex(),
+			EXTRANONCE2_SIZE,
+		]))
+	}
+
+	fn authorize(&self, params: &[Value], notifications: &mut Vec<String>) -> Result<Value, Error> {
+		let worker = string_param(params, 0)?;
+		let mut state = self.state.lock();
+		let is_first_worker = state.workers.is_empty();
+		state.workers.insert(worker.to_owned());
+
+		// start sending jobs once the first worker is authorized
+		if state.subscribed && is_first_worker {
+			if let Some(job) = self.jobs.current() {
+				Self::push_job(&mut state, &job, true, notifications);
+			}
+		}
+		Ok(true.into())
+	}
+
+	fn submit(&self, params: &[Value], notifications: &mut Vec<String>) -> Result<Value, Error> {
+		let worker = string_param(params, 0)?;
+		let job_id = string_param(params, 1)?;
+		let extranonce2: Bytes = string_param(params, 2)?
+			.parse()
+			.map_err(|_| Error::Other("Invalid extranonce2".into()))?;
+		let time = hex_u32_param(params, 3)?;
+		let nonce = hex_u32_param(params, 4)?;
+
+		{
+			let state = self.state.lock();
+			if !state.subscribed {
+				return Err(Error::NotSubscribed);
+			}
+			if !state.workers.contains(worker) {
+				return Err(Error::UnauthorizedWorker);
+			}
+		}
+		if extranonce2.len() != EXTRANONCE2_SIZE {
+			return Err(Error::Other("Invalid extranonce2 size".into()));
+		}
+		let job = self.jobs.get(job_id).ok_or(Error::JobNotFound)?;
+		let now = current_time();
+		if !job.is_valid_time(time, now) {
+			return Err(Error::Other("ntime out of range".into()));
+		}
+
+		let coinbase = job.coinbase(&self.extranonce1, &extranonce2);
+		let header = job.header(&coinbase, time, nonce);
+		let hash = header.hash();
+
+		let share_target = {
+			let mut state = self.state.lock();
+			if !state.shares.insert((job.id.clone(), extranonce2.clone(), time, nonce)) {
+				return Err(Error::DuplicateShare);
+			}
+			difficulty_target(state.vardiff.accepted_difficulty())
+		};
+
+		if job.is_block_solution(&hash) {
+			info!(target: "stratum", "Worker {} has found block {}", worker, hash);
+			self.block_sink
+				.submit_block(job.block(header, coinbase))
+				.map_err(|reason| Error::Other(format!("Block rejected: {}", reason)))?;
+		} else if !meets_target(&hash, &share_target) {
+			return Err(Error::LowDifficultyShare);
+		}
+
+		trace!(target: "stratum", "Worker {} has submitted share {}", worker, hash);
+		let mut state = self.state.lock();
+		if state.vardiff.share_accepted(now) {
+			// new difficulty only applies to the new jobs
+			if let Some(job) = self.jobs.current() {
+				Self::push_job(&mut state, &job, false, notifications);
+			}
+		}
+		Ok(true.into())
+	}
+
+	/// Pushes job notification, preceded by difficulty notification if difficulty has changed
+	fn push_job(state: &mut SessionState, job: &Job, clean_jobs: bool, notifications: &mut Vec<String>) {
+		let difficulty = state.vardiff.difficulty();
+		if state.sent_difficulty != Some(difficulty) {
+			state.sent_difficulty = Some(difficulty);
+			notifications.push(notification("mining.set_difficulty", serde_json::json!([difficulty])));
+		}
+		state.vardiff.job_sent();
+		notifications.push(notification("mining.notify", job.notify_params(clean_jobs)));
+	}
+}
+
+fn string_param(params: &[Value], index: usize) -> Result<&str, Error> {
+	params
+		.get(index)
+		.and_then(Value::as_str)
+		.ok_or_else(|| Error::Other("Invalid params".into()))
+}
+
+/// Time and nonce are sent as big-endian hex strings
+fn hex_u32_param(params: &[Value], index: usize) -> Result<u32, Error> {
+	let param = string_param(params, index)?;
+	let bytes: Vec<u8> = FromHex::from_hex(param).map_err(|_| Error::Other("Invalid params".into()))?;
+	if bytes.len() != 4 {
+		return Err(Error::Other("Invalid params".into()));
+	}
+	Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn response(id: Value, result: Result<Value, Error>) -> String {
+	let response = match result {
+		Ok(result) => Response {
+			id,
+			result,
+			error: Value::Null,
+		},
+		Err(error) => Response {
+			id,
+			result: Value::Null,
+			error: error.to_value(),
+		},
+	};
+	serde_json::to_string(&response).expect("response is always serializable")
+}
+
+fn notification(method: &'static str, params: Value) -> String {
+	serde_json::to_string(&Notification::new(method, params)).expect("notification is always serializable")
+}
+
+fn current_time() -> u32 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as u32
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BlockSink, Session};
+	use crate::job::tests::{payout_address, template};
+	use crate::job::JobManager;
+	use bitcrypto::SHA256D;
+	use chain::IndexedBlock;
+	use parking_lot::Mutex;
+	use std::sync::Arc;
+
+	#[derive(Default)]
+	struct BlockSinkMock {
+		blocks: Mutex<Vec<IndexedBlock>>,
+	}
+
+	impl BlockSink for BlockSinkMock {
+		fn submit_block(&self, block: IndexedBlock) -> Result<(), String> {
+			self.blocks.lock().push(block);
+			Ok(())
+		}
+	}
+
+	fn session(bits: u32, min_difficulty: f64) -> (Session, Arc<BlockSinkMock>) {
+		let jobs = Arc::new(JobManager::new(payout_address()));
+		jobs.update(template(SHA256D::default(), bits, Vec::new()));
+		let block_sink = Arc::new(BlockSinkMock::default());
+		let session = Session::new(jobs, block_sink.clone(), vec![0, 0, 0, 1].into(), min_difficulty);
+		(session, block_sink)
+	}
+
+	fn submit(session: &Session, extranonce2: &str, nonce: u32) -> String {
+		session
+			.handle_request(&format!(
+				r#"{{"id":4,"method":"mining.submit","params":["worker","1","{}","5f5e1000","{:08x}"]}}"#,
+				extranonce2, nonce
+			))
+			.remove(0)
+	}
+
+	#[test]
+	fn session_subscribe_and_authorize() {
+		let (session, _) = session(0x207fffff, 1.0);
+		assert_eq!(
+			session.handle_request(r#"{"id":1,"method":"mining.subscribe","params":["cgminer/4.10.0"]}"#),
+			vec![r#"{"id":1,"result":[[["mining.set_difficulty","00000001"],["mining.notify","00000001"]],"00000001",4],"error":null}"#]
+		);
+
+		let messages = session.handle_request(r#"{"id":2,"method":"mining.authorize","params":["worker","x"]}"#);
+		assert_eq!(messages.len(), 3);
+		assert_eq!(messages[0], r#"{"id":2,"result":true,"error":null}"#);
+		assert_eq!(messages[1], r#"{"id":null,"method":"mining.set_difficulty","params":[1.0]}"#);
+		assert!(messages[2].starts_with(r#"{"id":null,"method":"mining.notify","params":["1","#));
+		assert!(messages[2].ends_with(r#""20000000","207fffff","5f5e1000",true]}"#));
+
+		assert_eq!(
+			session.handle_request(r#"{"id":3,"method":"mining.unknown","params":[]}"#),
+			vec![r#"{"id":3,"result":null,"error":[20,"Method not found: mining.unknown",null]}"#]
+		);
+	}
+
+	#[test]
+	fn session_submit_errors() {
+		let (session, _) = session(0x1d00ffff, 1_000_000.0);
+		assert_eq!(
+			submit(&session, "00000000", 0),
+			r#"{"id":4,"result":null,"error":[25,"Not subscribed",null]}"#
+		);
+		session.handle_request(r#"{"id":1,"method":"mining.subscribe","params":[]}"#);
+		assert_eq!(
+			submit(&session, "00000000", 0),
+			r#"{"id":4,"result":null,"error":[24,"Unauthorized worker",null]}"#
+		);
+		session.handle_request(r#"{"id":2,"method":"mining.authorize","params":["worker","x"]}"#);
+		assert_eq!(
+			submit(&session, "0000", 0),
+			r#"{"id":4,"result":null,"error":[20,"Invalid extranonce2 size",null]}"#
+		);
+		assert_eq!(
+			submit(&session, "00000000", 0),
+			r#"{"id":4,"result":null,"error":[23,"Low difficulty share",null]}"#
+		);
+		assert_eq!(
+			submit(&session, "00000000", 0),
+			r#"{"id":4,"result":null,"error":[22,"Duplicate share",null]}"#
+		);
+		assert_eq!(
+			session
+				.handle_request(r#"{"id":5,"method":"mining.submit","params":["worker","2","00000000","5f5e1000","00000000"]}"#)
+				.remove(0),
+			r#"{"id":5,"result":null,"error":[21,"Job not found",null]}"#
+		);
+	}
+
+	#[test]
+	fn session_submits_found_block() {
+		let (session, block_sink) = session(0x207fffff, 1_000_000.0);
+		session.handle_request(r#"{"id":1,"method":"mining.subscribe","params":[]}"#);
+		session.handle_request(r#"{"id":2,"method":"mining.authorize","params":["worker","x"]}"#);
+
+		// every second hash meets the regtest target
+		let mut nonce = 0;
+		loop {
+			let response = submit(&session, "01020304", nonce);
+			if response == r#"{"id":4,"result":true,"error":null}"# {
+				break;
+			}
+			assert_eq!(response, r#"{"id":4,"result":null,"error":[23,"Low difficulty share",null]}"#);
+			nonce += 1;
+		}
+
+		let blocks = block_sink.blocks.lock();
+		assert_eq!(blocks.len(), 1);
+		assert_eq!(blocks[0].header.raw.nonce, nonce);
+		assert_eq!(blocks[0].header.raw.time, 1_600_000_000);
+		assert_eq!(blocks[0].header.raw.merkle_root_hash, blocks[0].merkle_root());
+		assert_eq!(
+			&blocks[0].transactions[0].raw.inputs[0].script_sig[3..],
+			&[0x08, 0, 0, 0, 1, 1, 2, 3, 4]
+		);
+	}
+}
@@ -0,0 +1,37 @@
+use serde_json::Value;
+
+/// Request of the miner
+#[derive(Debug, Deserialize)]
+pub struct Request {
+	#[serde(default)]
+	pub id: Value,
+	pub method: String,
+	#[serde(default)]
+	pub params: Vec<Value>,
+}
+
+/// Response to the miner request. Exactly one of `result` and `error` is not null
+#[derive(Debug, Serialize)]
+pub struct Response {
+	pub id: Value,
+	pub result: Value,
+	pub error: Value,
+}
+
+/// Server-initiated message. Stratum notifications always have null id
+#[derive(Debug, Serialize)]
+pub struct Notification {
+	pub id: Value,
+	pub method: &'static str,
+	pub params: Value,
+}
+
+impl Notification {
+	pub fn new(method: &'static str, params: Value) -> Self {
+		Notification {
+			id: Value::Null,
+			method,
+			params,
+		}
+	}
+}
@@ -0,0 +1,132 @@
+//! Variable share difficulty, adjusted to the hash rate of the miner
+
+/// Desired number of seconds between shares of the single session
+const TARGET_SHARE_TIME: f64 = 15.0;
+/// Minimal number of seconds between difficulty adjustments
+const RETARGET_INTERVAL: u32 = 90;
+/// Difficulty isn't adjusted while average share time is within this fraction of the target time
+const ALLOWED_VARIANCE: f64 = 0.3;
+/// Maximal factor of the single difficulty adjustment
+const MAX_ADJUSTMENT_FACTOR: f64 = 4.0;
+
+/// Share difficulty of the single session
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vardiff {
+	/// Current share difficulty
+	difficulty: f64,
+	/// Difficulty, which is still accepted until the miner receives new job
+	previous_difficulty: f64,
+	/// Minimal share difficulty
+	min_difficulty: f64,
+	/// Number of shares since the last adjustment
+	shares: u32,
+	/// Time of the last adjustment
+	retarget_time: u32,
+}
+
+impl Vardiff {
+	pub fn new(min_difficulty: f64, current_time: u32) -> Self {
+		Vardiff {
+			difficulty: min_difficulty,
+			previous_difficulty: min_difficulty,
+			min_difficulty,
+			shares: 0,
+			retarget_time: current_time,
+		}
+	}
+
+	/// Current share difficulty
+	pub fn difficulty(&self) -> f64 {
+		self.difficulty
+	}
+
+	/// Minimal difficulty of shares, which are accepted now.
+	/// Miner keeps working with previous difficulty until it receives new job
+	pub fn accepted_difficulty(&self) -> f64 {
+		self.difficulty.min(self.previous_difficulty)
+	}
+
+	/// Called when new job is sent to the miner
+	pub fn job_sent(&mut self) {
+		self.previous_difficulty = self.difficulty;
+	}
+
+	/// Called when share is accepted. Returns true if difficulty has been adjusted
+	pub fn share_accepted(&mut self, current_time: u32) -> bool {
+		self.shares += 1;
+		let elapsed = current_time.saturating_sub(self.retarget_time);
+		if elapsed < RETARGET_INTERVAL {
+			return false;
+		}
+
+		let average_share_time = f64::from(elapsed) / f64::from(self.shares);
+		self.shares = 0;
+		self.retarget_time = current_time;
+		if (average_share_time - TARGET_SHARE_TIME).abs() <= TARGET_SHARE_TIME * ALLOWED_VARIANCE {
+			return false;
+		}
+
+		let factor = (TARGET_SHARE_TIME / average_share_time)
+			.max(1.0 / MAX_ADJUSTMENT_FACTOR)
+			.min(MAX_ADJUSTMENT_FACTOR);
+		let difficulty = (self.difficulty * factor).max(self.min_difficulty);
+		if (difficulty - self.difficulty).abs() < f64::EPSILON {
+			return false;
+		}
+
+		self.previous_difficulty = self.difficulty;
+		self.difficulty = difficulty;
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Vardiff;
+
+	#[test]
+	fn vardiff_increases_difficulty_of_fast_miner() {
+		let mut vardiff = Vardiff::new(1.0, 0);
+		// share every second
+		for time in 1..90 {
+			assert!(!vardiff.share_accepted(time));
+		}
+		assert!(vardiff.share_accepted(90));
+		assert_eq!(vardiff.difficulty(), 4.0);
+		assert_eq!(vardiff.accepted_difficulty(), 1.0);
+		vardiff.job_sent();
+		assert_eq!(vardiff.accepted_difficulty(), 4.0);
+	}
+
+	#[test]
+	fn vardiff_decreases_difficulty_of_slow_miner() {
+		let mut vardiff = Vardiff::new(1.0, 0);
+		for time in 1..60 {
+			vardiff.share_accepted(time);
+		}
+		assert!(vardiff.share_accepted(120));
+		assert_eq!(vardiff.difficulty(), 4.0);
+		vardiff.job_sent();
+
+		// share every 30 seconds
+		assert!(!vardiff.share_accepted(150));
+		assert!(!vardiff.share_accepted(180));
+		assert!(vardiff.share_accepted(210));
+		assert_eq!(vardiff.difficulty(), 2.0);
+		assert_eq!(vardiff.accepted_difficulty(), 2.0);
+
+		// never goes below minimal difficulty
+		assert!(vardiff.share_accepted(1_000));
+		assert_eq!(vardiff.difficulty(), 1.0);
+		assert!(!vardiff.share_accepted(10_000));
+	}
+
+	#[test]
+	fn vardiff_keeps_difficulty_within_variance() {
+		let mut vardiff = Vardiff::new(1.0, 0);
+		for time in 1..7 {
+			assert!(!vardiff.share_accepted(time * 16));
+		}
+		assert_eq!(vardiff.difficulty(), 1.0);
+	}
+}
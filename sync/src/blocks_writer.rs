@@ -214,6 +214,18 @@ mod tests {
 		assert_eq!(db.best_block().number, 1);
 	}
 
+	#[test]
+	fn blocks_writer_verifies_regtest_assumeutxo_chain() {
+		let genesis = Network::Regtest.genesis_block();
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone()]));
+		let consensus = ConsensusParams::new(Network::Regtest);
+		let mut blocks_target = BlocksWriter::new(db.clone(), consensus.clone(), default_verification_params());
+		for block in test_data::build_n_regtest_blocks_from(110, 0, &genesis.header.raw) {
+			assert_eq!(blocks_target.append_block(block.into()), Ok(()));
+		}
+		assert_eq!(db.best_block().hash, consensus.assumeutxo[0].block_hash);
+	}
+
 	#[test]
 	fn blocks_write_able_to_reorganize() {
 		// (1) b0 ---> (2) b1
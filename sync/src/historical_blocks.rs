@@ -0,0 +1,170 @@
+use crate::types::{PeerIndex, PeersRef};
+use bitcrypto::SHA256D;
+use chain::IndexedBlock;
+use message::common::{InventoryType, InventoryVector};
+use message::types;
+use parking_lot::{Condvar, Mutex};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Downloads blocks, which are not stored by the node, because chain state has been loaded from the UTXO snapshot.
+/// Blocks are requested from full nodes only and are never passed to the synchronization client.
+pub struct HistoricalBlocks {
+	/// Synchronization peers
+	peers: PeersRef,
+	/// Requested and received blocks
+	data: Mutex<HistoricalBlocksData>,
+	/// Notified when requested block is received
+	received: Condvar,
+}
+
+#[derive(Default)]
+struct HistoricalBlocksData {
+	/// Requested blocks, with peers they have been requested from
+	requested: HashMap<SHA256D, PeerIndex>,
+	/// Received blocks, which are not yet taken
+	received: HashMap<SHA256D, IndexedBlock>,
+	/// Last peer blocks have been requested from
+	last_peer: Option<PeerIndex>,
+}
+
+impl HistoricalBlocks {
+	pub fn new(peers: PeersRef) -> Self {
+		HistoricalBlocks {
+			peers,
+			data: Mutex::default(),
+			received: Condvar::new(),
+		}
+	}
+
+	/// Requests blocks from the next connected full node. Returns peer, blocks have been requested from,
+	/// or None if there are no such peers.
+	pub fn request(&self, hashes: &[SHA256D]) -> Option<PeerIndex> {
+		let mut data = self.data.lock();
+		let mut full_nodes = self
+			.peers
+			.enumerate()
+			.into_iter()
+			.filter(|peer_index| self.peers.is_full_node(*peer_index))
+			.collect::<Vec<_>>();
+		full_nodes.sort_unstable();
+		// blocks are requested from different peers in turn, so that the next request is sent to another peer
+		// if the previous one has failed
+		let peer_index = full_nodes
+			.iter()
+			.find(|peer_index| data.last_peer.map_or(true, |last_peer| **peer_index > last_peer))
+			.or_else(|| full_nodes.first())
+			.cloned()?;
+		let connection = self.peers.connection(peer_index)?;
+
+		trace!(target: "sync", "Requesting {} historical blocks from peer#{}", hashes.len(), peer_index);
+		data.last_peer = Some(peer_index);
+		for hash in hashes {
+			data.received.remove(hash);
+			data.requested.insert(*hash, peer_index);
+		}
+		connection.send_getdata(types::GetData {
+			inventory: hashes
+				.iter()
+				.map(|hash| InventoryVector {
+					inv_type: InventoryType::MessageWitnessBlock,
+					hash: *hash,
+				})
+				.collect(),
+		});
+		Some(peer_index)
+	}
+
+	/// Waits until requested block is received. Returns None if block isn't received in time.
+	pub fn wait(&self, hash: &SHA256D, timeout: Duration) -> Option<IndexedBlock> {
+		let mut data = self.data.lock();
+		if !data.received.contains_key(hash) {
+			self.received.wait_for(&mut data, timeout);
+		}
+		data.received.remove(hash)
+	}
+
+	/// Forgets about all requested blocks
+	pub fn cancel(&self) {
+		let mut data = self.data.lock();
+		data.requested.clear();
+		data.received.clear();
+	}
+
+	/// Accepts block if it has been requested from this peer. Otherwise returns it back.
+	pub fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock) -> Option<IndexedBlock> {
+		let mut data = self.data.lock();
+		if data.requested.get(block.hash()) != Some(&peer_index) {
+			return Some(block);
+		}
+
+		trace!(target: "sync", "Received historical block {} from peer#{}", block.hash(), peer_index);
+		data.requested.remove(block.hash());
+		data.received.insert(*block.hash(), block);
+		self.received.notify_all();
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::HistoricalBlocks;
+	use crate::inbound_connection::tests::DummyOutboundSyncConnection;
+	use crate::synchronization_peers::{PeersContainer, PeersImpl};
+	use message::Services;
+	use std::sync::Arc;
+	use std::time::Duration;
+
+	#[test]
+	fn historical_blocks_are_requested_from_full_nodes_in_turn() {
+		let peers = Arc::new(PeersImpl::default());
+		let full_node1 = DummyOutboundSyncConnection::new();
+		let full_node2 = DummyOutboundSyncConnection::new();
+		peers.insert(
+			0,
+			Services::default().with_network_limited(true),
+			DummyOutboundSyncConnection::new(),
+		);
+		peers.insert(1, Services::default().with_network(true), full_node1.clone());
+		peers.insert(2, Services::default().with_network(true), full_node2.clone());
+		let historical_blocks = HistoricalBlocks::new(peers.clone());
+
+		let hash = test_data::block_h1().hash();
+		assert_eq!(historical_blocks.request(&[hash]), Some(1));
+		assert_eq!(historical_blocks.request(&[hash]), Some(2));
+		assert_eq!(historical_blocks.request(&[hash]), Some(1));
+		assert_eq!(full_node1.messages.lock()["getdata"], 2);
+		assert_eq!(full_node2.messages.lock()["getdata"], 1);
+
+		peers.remove(1);
+		peers.remove(2);
+		assert_eq!(historical_blocks.request(&[hash]), None);
+	}
+
+	#[test]
+	fn historical_blocks_accepts_requested_blocks_only() {
+		let peers = Arc::new(PeersImpl::default());
+		peers.insert(0, Services::default().with_network(true), DummyOutboundSyncConnection::new());
+		peers.insert(1, Services::default().with_network(true), DummyOutboundSyncConnection::new());
+		let historical_blocks = HistoricalBlocks::new(peers);
+
+		let b1 = test_data::block_h1();
+		let b2 = test_data::block_h2();
+		assert_eq!(historical_blocks.request(&[b1.hash()]), Some(0));
+		// block has been requested from another peer
+		assert!(historical_blocks.on_block(1, b1.clone().into()).is_some());
+		// block has not been requested
+		assert!(historical_blocks.on_block(0, b2.into()).is_some());
+		assert!(historical_blocks.wait(&b1.hash(), Duration::from_millis(1)).is_none());
+
+		assert!(historical_blocks.on_block(0, b1.clone().into()).is_none());
+		assert_eq!(
+			historical_blocks.wait(&b1.hash(), Duration::from_millis(1)).unwrap().hash(),
+			&b1.hash()
+		);
+		// block is only accepted once
+		assert!(historical_blocks.on_block(0, b1.into()).is_some());
+	}
+}
@@ -186,6 +186,18 @@ impl InboundSyncConnection for InboundConnection {
 	fn on_notfound(&self, message: types::NotFound) {
 		self.node.on_notfound(self.peer_index, message);
 	}
+
+	fn on_get_cfilters(&self, message: types::GetCFilters) {
+		self.node.on_get_cfilters(self.peer_index, message);
+	}
+
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders) {
+		self.node.on_get_cfheaders(self.peer_index, message);
+	}
+
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt) {
+		self.node.on_get_cfcheckpt(self.peer_index, message);
+	}
 }
 
 #[cfg(test)]
@@ -276,7 +288,17 @@ pub mod tests {
 		fn send_notfound(&self, _message: types::NotFound) {
 			*self.messages.lock().entry("notfound".to_owned()).or_insert(0) += 1;
 		}
+		fn send_cfilter(&self, _message: types::CFilter) {
+			*self.messages.lock().entry("cfilter".to_owned()).or_insert(0) += 1;
+		}
+		fn send_cfheaders(&self, _message: types::CFHeaders) {
+			*self.messages.lock().entry("cfheaders".to_owned()).or_insert(0) += 1;
+		}
+		fn send_cfcheckpt(&self, _message: types::CFCheckpt) {
+			*self.messages.lock().entry("cfcheckpt".to_owned()).or_insert(0) += 1;
+		}
 		fn ignored(&self, _id: RequestId) {}
+		fn misbehaving(&self, _score: u32) {}
 		fn close(&self) {}
 	}
 }
@@ -20,6 +20,7 @@ extern crate serialization as ser;
 extern crate verification;
 
 mod blocks_writer;
+mod historical_blocks;
 mod inbound_connection;
 mod inbound_connection_factory;
 mod local_node;
@@ -35,7 +36,11 @@ mod synchronization_verifier;
 mod types;
 mod utils;
 
+pub use local_node::MiningInformation;
+pub use miner::MemoryPoolLimits;
+pub use types::HistoricalBlocksRef;
 pub use types::LocalNodeRef;
+pub use types::MemoryPoolRef;
 pub use types::PeersRef;
 
 use bitcrypto::SHA256D;
@@ -70,12 +75,23 @@ pub struct VerificationParameters {
 	pub verification_edge: SHA256D,
 }
 
+#[derive(Debug, Clone, Default)]
+/// Memory pool parameters.
+pub struct MemoryPoolParameters {
+	/// Replace memory pool transactions, even if they do not signal replaceability (full-RBF).
+	pub full_rbf: bool,
+	/// Memory pool size, expiry and packages limits.
+	pub limits: MemoryPoolLimits,
+}
+
 /// Synchronization events listener
 pub trait SyncListener: Send + 'static {
 	/// Called when node switches to synchronization state
 	fn synchronization_state_switched(&self, is_synchronizing: bool);
 	/// Called when new best storage block is inserted
 	fn best_storage_block_inserted(&self, block_hash: &SHA256D);
+	/// Called when transactions are inserted to or evicted from the memory pool
+	fn memory_pool_changed(&self) {}
 }
 
 /// Create blocks writer.
@@ -100,7 +116,9 @@ pub fn create_local_sync_node(
 	db: storage::SharedStore,
 	peers: PeersRef,
 	verification_params: VerificationParameters,
+	memory_pool_params: MemoryPoolParameters,
 ) -> LocalNodeRef {
+	use historical_blocks::HistoricalBlocks;
 	use local_node::LocalNode as SyncNode;
 	use miner::MemoryPool;
 	use synchronization_chain::Chain as SyncChain;
@@ -122,6 +140,10 @@ pub fn create_local_sync_node(
 		// during regtests, peer is providing us with zero fee transactions => we shouldn't ignore these
 		memory_pool.accept_zero_fee_transactions();
 	}
+	if memory_pool_params.full_rbf {
+		memory_pool.enable_full_rbf();
+	}
+	memory_pool.set_limits(memory_pool_params.limits);
 
 	let block_speed_meter = Arc::new(AverageSpeedMeter::with_inspect_items(BLOCKS_SPEED_BLOCKS_TO_INSPECT));
 	let memory_pool = Arc::new(RwLock::new(memory_pool));
@@ -145,6 +167,7 @@ pub fn create_local_sync_node(
 		chain_verifier.clone(),
 		block_speed_meter,
 	);
+	let historical_blocks = Arc::new(HistoricalBlocks::new(peers.clone()));
 	let verifier_sink = Arc::new(CoreVerificationSink::new(sync_client_core.clone()));
 	let verifier = AsyncVerifier::new(chain_verifier, db.clone(), memory_pool.clone(), verifier_sink, verification_params);
 	let sync_client = SynchronizationClient::new(sync_state.clone(), sync_client_core, verifier);
@@ -156,6 +179,7 @@ pub fn create_local_sync_node(
 		sync_state,
 		sync_client,
 		sync_server,
+		historical_blocks,
 	))
 }
 
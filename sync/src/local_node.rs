@@ -4,19 +4,35 @@ use crate::synchronization_peers::{BlockAnnouncementType, TransactionAnnouncemen
 use crate::synchronization_server::{Server, ServerTask};
 use crate::synchronization_verifier::TransactionVerificationSink;
 use crate::types::{
-	ClientRef, MemoryPoolRef, PeerIndex, PeersRef, RequestId, ServerRef, StorageRef, SyncListenerRef, SynchronizationStateRef,
+	ClientRef, HistoricalBlocksRef, MemoryPoolRef, PeerIndex, PeersRef, RequestId, ServerRef, StorageRef, SyncListenerRef,
+	SynchronizationStateRef,
 };
 use bitcrypto::SHA256D;
 use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
 use futures::future::lazy;
-use keys::AddressHash;
+use keys::Address;
 use message::types;
 use miner::block_template::BlockTemplate;
-use miner::{mine_block, BlockAssembler};
-use network::ConsensusParams;
+use miner::{BlockAssembler, CpuMiner, FeeEstimator, MemoryPoolDump};
+use network::{ConsensusParams, Network};
 use parking_lot::{Condvar, Mutex};
 use primitives::time::{RealTime, Time};
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use storage::BlockRef;
+use verification::{BackwardsCompatibleChainVerifier, Deployments, VerificationLevel, Verify};
+
+/// Number of the most recent blocks, used to estimate network hash rate
+const NETWORK_HASH_RATE_BLOCKS: u32 = 120;
+/// Expected number of hashes, required to find block with difficulty 1
+const DIFFICULTY_1_HASHES: f64 = 4_294_967_296.0;
+/// Maximal time to wait until generated block is inserted into the storage
+const GENERATED_BLOCK_INSERT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Interval between checks of the generated block insertion
+const GENERATED_BLOCK_INSERT_CHECK_INTERVAL: Duration = Duration::from_millis(10);
 
 /// Local synchronization node
 pub struct LocalNode<U: Server, V: Client> {
@@ -34,6 +50,31 @@ pub struct LocalNode<U: Server, V: Client> {
 	client: ClientRef<V>,
 	/// Synchronization server
 	server: ServerRef<U>,
+	/// Historical blocks downloader
+	historical_blocks: HistoricalBlocksRef,
+	/// Cpu miner, used to generate blocks
+	cpu_miner: CpuMiner,
+	/// Deployments cache, used to create block templates
+	deployments: Deployments,
+}
+
+/// Mining-related information
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningInformation {
+	/// Network we are working on
+	pub network: Network,
+	/// Number of the best block
+	pub blocks: u32,
+	/// Difficulty of the best block
+	pub difficulty: f64,
+	/// Estimated network hash rate (hashes per second)
+	pub network_hash_rate: f64,
+	/// Number of memory pool transactions
+	pub pooled_transactions: usize,
+	/// Hash rate of the last local cpu mining run (hashes per second)
+	pub hashes_per_second: f64,
+	/// Number of cpu mining threads
+	pub mining_threads: usize,
 }
 
 /// Transaction accept verification sink
@@ -62,6 +103,7 @@ where
 		state: SynchronizationStateRef,
 		client: ClientRef<V>,
 		server: ServerRef<U>,
+		historical_blocks: HistoricalBlocksRef,
 	) -> Self {
 		LocalNode {
 			consensus,
@@ -71,6 +113,9 @@ where
 			state,
 			client,
 			server,
+			historical_blocks,
+			cpu_miner: CpuMiner::default(),
+			deployments: Deployments::new(),
 		}
 	}
 
@@ -87,6 +132,11 @@ where
 		self.client.information()
 	}
 
+	/// Return shared reference to historical blocks downloader.
+	pub fn historical_blocks(&self) -> HistoricalBlocksRef {
+		self.historical_blocks.clone()
+	}
+
 	/// When new peer connects to the node
 	pub fn on_connect(&self, peer_index: PeerIndex, peer_name: String, version: types::Version) {
 		trace!(target: "sync", "Starting new sync session with peer#{}: {}", peer_index, peer_name);
@@ -131,7 +181,9 @@ where
 	/// When block is received
 	pub fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
 		trace!(target: "sync", "Got `block` message from peer#{}. Block hash: {}", peer_index, block.header.hash);
-		self.client.on_block(peer_index, block);
+		if let Some(block) = self.historical_blocks.on_block(peer_index, block) {
+			self.client.on_block(peer_index, block);
+		}
 	}
 
 	/// When notfound is received
@@ -185,6 +237,24 @@ where
 		self.server.execute(ServerTask::GetBlockTxn(peer_index, message));
 	}
 
+	/// When peer asks us for compact filters of the range of blocks
+	pub fn on_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		trace!(target: "sync", "Got `getcfilters` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFilters(peer_index, message));
+	}
+
+	/// When peer asks us for compact filter headers of the range of blocks
+	pub fn on_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		trace!(target: "sync", "Got `getcfheaders` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFHeaders(peer_index, message));
+	}
+
+	/// When peer asks us for compact filter headers checkpoints
+	pub fn on_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		trace!(target: "sync", "Got `getcfcheckpt` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFCheckpt(peer_index, message));
+	}
+
 	/// When peer sets bloom filter for connection
 	pub fn on_filterload(&self, peer_index: PeerIndex, message: types::FilterLoad) {
 		trace!(target: "sync", "Got `filterload` message from peer#{}", peer_index);
@@ -265,8 +335,50 @@ where
 		self.client.accept_block(block);
 	}
 
-	/// Verify and then schedule new transaction
+	/// Verify block and schedule it for inserting into the chain.
+	/// Returns reject reason (BIP22) if block is invalid
+	pub fn submit_block(&self, block: IndexedBlock) -> Result<(), String> {
+		if self.storage.contains_block(BlockRef::Hash(block.header.hash)) {
+			return Err("duplicate".into());
+		}
+
+		BackwardsCompatibleChainVerifier::new(self.storage.clone(), self.consensus.clone())
+			.verify(VerificationLevel::Full, &block)
+			.map_err(|err| err.reject_reason().to_owned())?;
+		self.accept_block(block);
+		Ok(())
+	}
+
+	/// Verify block proposal (BIP23) without inserting it into the chain.
+	/// Returns reject reason if proposal is invalid
+	pub fn verify_block_proposal(&self, block: &IndexedBlock) -> Result<(), String> {
+		if self.storage.contains_block(BlockRef::Hash(block.header.hash)) {
+			return Err("duplicate".into());
+		}
+		if block.header.raw.previous_header_hash != self.storage.best_block().hash {
+			return Err("inconclusive-not-best-prevblk".into());
+		}
+
+		BackwardsCompatibleChainVerifier::new(self.storage.clone(), self.consensus.clone())
+			.verify_block_proposal(block)
+			.map_err(|err| err.reject_reason().to_owned())
+	}
+
+	/// Verify block header and schedule requesting its block, if it extends the best headers chain
+	pub fn submit_header(&self, header: IndexedBlockHeader) -> Result<(), String> {
+		trace!(target: "sync", "Received new header. Block hash: {}", header.hash);
+		self.client.accept_header(header)
+	}
+
+	/// Verify and then schedule new own transaction. Transaction is announced to peers until some peer requests it
 	pub fn accept_transaction(&self, transaction: IndexedTransaction) -> Result<SHA256D, String> {
+		let hash = self.verify_transaction(transaction)?;
+		self.memory_pool.write().add_unbroadcast(&hash);
+		Ok(hash)
+	}
+
+	/// Verify and then schedule new transaction
+	fn verify_transaction(&self, transaction: IndexedTransaction) -> Result<SHA256D, String> {
 		let sink_data = Arc::new(TransactionAcceptSinkData::default());
 		let sink = TransactionAcceptSink::new(sink_data.clone()).boxed();
 		{
@@ -277,28 +389,182 @@ where
 		sink_data.wait()
 	}
 
-	/// Get block template for mining
-	pub fn get_block_template(&self) -> BlockTemplate {
+	/// Get block template for mining. Transactions with witness are only included if `segwit` is true
+	pub fn get_block_template(&self, segwit: bool) -> BlockTemplate {
 		let block_assembler = BlockAssembler {
-			max_block_size: self.consensus.max_block_size as u32,
-			max_block_sigops: self.consensus.max_block_sigops as u32,
+			max_block_weight: self.consensus.max_block_weight as u32,
+			max_block_sigops_cost: self.consensus.max_block_sigops_cost as u32,
+			segwit,
 		};
 		let memory_pool = &*self.memory_pool.read();
-		block_assembler.create_new_block(&self.storage, memory_pool, RealTime.now().as_secs() as u32, &self.consensus)
-	}
-
-	pub fn generate_block(&self, to_address: Option<AddressHash>, max_tries: usize) -> Option<SHA256D> {
-		let block_template = self.get_block_template();
-		if let Some(block) = mine_block(block_template, to_address, max_tries) {
-			let block_hash = block.hash().clone();
-			info!("Generated block: {}", block_hash);
-			self.accept_block(block);
-			Some(block_hash)
-		} else {
-			None
+		block_assembler.create_new_block(
+			&self.storage,
+			memory_pool,
+			RealTime.now().as_secs() as u32,
+			&self.consensus,
+			&self.deployments,
+		)
+	}
+
+	/// Mines block on top of the best block and waits until it is inserted into the storage.
+	/// If best block changes while mining, starts mining on top of the new best block
+	pub fn generate_block(&self, to_address: Option<Address>, max_tries: usize) -> Option<SHA256D> {
+		loop {
+			let block_template = self.get_block_template(true);
+			let previous_header_hash = block_template.previous_header_hash;
+			let is_stale = || self.storage.best_block().hash != previous_header_hash;
+			match self.cpu_miner.mine_block(block_template, to_address.clone(), max_tries, is_stale) {
+				Some(block) => {
+					let block_hash = *block.hash();
+					info!("Generated block: {}", block_hash);
+					self.accept_block(block);
+					self.wait_for_best_block(&block_hash);
+					return Some(block_hash);
+				}
+				None if is_stale() => continue,
+				None => return None,
+			}
+		}
+	}
+
+	/// Get mining-related information
+	pub fn mining_information(&self) -> MiningInformation {
+		let best_block = self.storage.best_block();
+		MiningInformation {
+			network: self.consensus.network,
+			blocks: best_block.number,
+			difficulty: self.storage.difficulty(),
+			network_hash_rate: self.network_hash_rate(best_block.number),
+			pooled_transactions: self.memory_pool.read().information().transactions_count,
+			hashes_per_second: self.cpu_miner.hashes_per_second(),
+			mining_threads: self.cpu_miner.threads(),
+		}
+	}
+
+	/// Estimates network hash rate from the work and time span of the most recent blocks
+	fn network_hash_rate(&self, best_block_number: u32) -> f64 {
+		let first_block_number = best_block_number.saturating_sub(NETWORK_HASH_RATE_BLOCKS);
+		let mut work = 0f64;
+		let mut min_time = u32::max_value();
+		let mut max_time = 0;
+		for number in first_block_number..=best_block_number {
+			let header = match self.storage.block_header(BlockRef::Number(number)) {
+				Some(header) => header,
+				None => return 0.0,
+			};
+			// work of the first block is not included into the time span
+			if number != first_block_number {
+				work += header.raw.bits.to_f64() * DIFFICULTY_1_HASHES;
+			}
+			min_time = min_time.min(header.raw.time);
+			max_time = max_time.max(header.raw.time);
+		}
+
+		if max_time <= min_time {
+			return 0.0;
+		}
+		work / f64::from(max_time - min_time)
+	}
+
+	/// Waits until given block becomes the best block in the storage (or until timeout is reached)
+	fn wait_for_best_block(&self, block_hash: &SHA256D) {
+		let started = Instant::now();
+		while self.storage.best_block().hash != *block_hash && started.elapsed() < GENERATED_BLOCK_INSERT_TIMEOUT {
+			thread::sleep(GENERATED_BLOCK_INSERT_CHECK_INTERVAL);
 		}
 	}
 
+	/// Get reference to the memory pool
+	pub fn memory_pool(&self) -> MemoryPoolRef {
+		self.memory_pool.clone()
+	}
+
+	/// Writes memory pool dump to the file, returning number of dumped transactions
+	pub fn save_memory_pool(&self, path: &Path) -> Result<usize, String> {
+		let dump = MemoryPoolDump::new(&*self.memory_pool.read());
+		// write to the temporary file first, so that the previous dump is kept if writing fails
+		let new_path = path.with_extension("new");
+		fs::write(&new_path, dump.serialize())
+			.and_then(|_| fs::rename(&new_path, path))
+			.map_err(|err| format!("Cannot write memory pool dump to {}: {}", path.display(), err))?;
+		Ok(dump.entries.len())
+	}
+
+	/// Reads memory pool dump from the file and restores transactions,
+	/// returning number of transactions, which have been accepted to the memory pool
+	pub fn load_memory_pool(&self, path: &Path) -> Result<usize, String> {
+		let data = fs::read(path).map_err(|err| format!("Cannot read memory pool dump from {}: {}", path.display(), err))?;
+		let dump =
+			MemoryPoolDump::deserialize(&data).map_err(|err| format!("Cannot read memory pool dump from {}: {}", path.display(), err))?;
+		Ok(self.restore_memory_pool(dump))
+	}
+
+	/// Writes fee estimator statistics to the file
+	pub fn save_fee_estimates(&self, path: &Path) -> Result<(), String> {
+		let data = self.memory_pool.read().fee_estimator().serialize();
+		let new_path = path.with_extension("new");
+		fs::write(&new_path, data)
+			.and_then(|_| fs::rename(&new_path, path))
+			.map_err(|err| format!("Cannot write fee estimates to {}: {}", path.display(), err))
+	}
+
+	/// Reads fee estimator statistics from the file
+	pub fn load_fee_estimates(&self, path: &Path) -> Result<(), String> {
+		let data = fs::read(path).map_err(|err| format!("Cannot read fee estimates from {}: {}", path.display(), err))?;
+		let fee_estimator = FeeEstimator::deserialize(&data, self.storage.best_block().number)
+			.map_err(|err| format!("Cannot read fee estimates from {}: {}", path.display(), err))?;
+		self.memory_pool.write().set_fee_estimator(fee_estimator);
+		Ok(())
+	}
+
+	/// Verifies transactions of the memory pool dump and inserts accepted transactions to the memory pool,
+	/// returning number of accepted transactions
+	pub fn restore_memory_pool(&self, dump: MemoryPoolDump) -> usize {
+		// expired transactions would be evicted from the memory pool anyway
+		let expiry_time = self.memory_pool.read().limits().expiry_time;
+		let min_time = RealTime.now().as_secs() as i64 - expiry_time as i64;
+		let (mut accepted, mut failed, mut expired) = (0, 0, 0);
+		for entry in dump.entries {
+			if entry.time <= min_time {
+				expired += 1;
+				continue;
+			}
+
+			match self.verify_transaction(entry.transaction) {
+				Ok(hash) => {
+					let mut memory_pool = self.memory_pool.write();
+					memory_pool.set_entry_time(&hash, entry.time as u64);
+					if entry.fee_delta != 0 {
+						memory_pool.set_virtual_fee(&hash, entry.fee_delta);
+					}
+					accepted += 1;
+				}
+				Err(err) => {
+					trace!(target: "sync", "Memory pool dump transaction is rejected: {}", err);
+					failed += 1;
+				}
+			}
+		}
+
+		// fee deltas of transactions, which are not in the memory pool, are applied when transactions are accepted
+		let mut memory_pool = self.memory_pool.write();
+		for (hash, fee_delta) in dump.fee_deltas {
+			memory_pool.set_virtual_fee(&hash, fee_delta);
+		}
+		for hash in dump.unbroadcast {
+			memory_pool.add_unbroadcast(&hash);
+		}
+
+		info!(
+			target: "sync",
+			"Restored {} memory pool transactions ({} rejected, {} expired)",
+			accepted,
+			failed,
+			expired
+		);
+		accepted
+	}
+
 	/// Install synchronization events listener
 	pub fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.client.install_sync_listener(listener);
@@ -345,6 +611,8 @@ pub mod tests {
 	extern crate test_data;
 
 	use super::LocalNode;
+	use crate::historical_blocks::HistoricalBlocks;
+	use crate::inbound_connection::tests::DummyOutboundSyncConnection;
 	use crate::synchronization_chain::Chain;
 	use crate::synchronization_client::SynchronizationClient;
 	use crate::synchronization_client_core::{Config, CoreVerificationSink, SynchronizationClientCore};
@@ -360,11 +628,12 @@ pub mod tests {
 	use chain::Transaction;
 	use db::BlockChainDatabase;
 	use message::common::{InventoryType, InventoryVector};
-	use message::types;
-	use miner::MemoryPool;
+	use message::{types, Services};
+	use miner::{MemoryPool, MemoryPoolDump, MemoryPoolDumpEntry};
 	use network::{ConsensusParams, Network};
 	use parking_lot::RwLock;
 	use primitives::bytes::Bytes;
+	use primitives::time::{RealTime, Time};
 	use std::iter::repeat;
 	use std::sync::Arc;
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
@@ -416,6 +685,7 @@ pub mod tests {
 		};
 		verifier.set_sink(Arc::new(CoreVerificationSink::new(client_core.clone())));
 		let client = SynchronizationClient::new(sync_state.clone(), client_core, verifier);
+		let historical_blocks = Arc::new(HistoricalBlocks::new(sync_peers.clone()));
 		let local_node = LocalNode::new(
 			ConsensusParams::new(Network::Mainnet),
 			storage,
@@ -424,6 +694,7 @@ pub mod tests {
 			sync_state,
 			client,
 			server.clone(),
+			historical_blocks,
 		);
 		(executor, server, local_node)
 	}
@@ -453,6 +724,20 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn local_node_mining_information() {
+		let (_, _, local_node) = create_local_node(None);
+		let information = local_node.mining_information();
+		assert_eq!(information.network, Network::Mainnet);
+		assert_eq!(information.blocks, 0);
+		assert_eq!(information.difficulty, 1.0);
+		// there's single block => no time span
+		assert_eq!(information.network_hash_rate, 0.0);
+		assert_eq!(information.pooled_transactions, 0);
+		assert_eq!(information.hashes_per_second, 0.0);
+		assert!(information.mining_threads >= 1);
+	}
+
 	#[test]
 	fn local_node_accepts_local_transaction() {
 		let (executor, _, local_node) = create_local_node(None);
@@ -471,7 +756,21 @@ pub mod tests {
 		let result = local_node.accept_transaction(transaction.clone().into());
 		assert_eq!(result, Ok(transaction_hash.clone()));
 
-		assert_eq!(executor.take_tasks(), vec![Task::RelayNewTransaction(transaction.into(), 83333333)]);
+		assert_eq!(
+			executor.take_tasks(),
+			vec![Task::RelayNewTransaction(transaction.clone().into(), 83333333)]
+		);
+
+		// unbroadcast transaction is announced to newly connected peers
+		let peer_index2 = 1;
+		local_node
+			.peers
+			.insert(peer_index2, Services::default(), DummyOutboundSyncConnection::new());
+		local_node.on_connect(peer_index2, "test".into(), types::Version::default());
+		assert!(executor.take_tasks().contains(&Task::Inventory(
+			peer_index2,
+			types::Inv::with_inventory(vec![InventoryVector::tx(transaction_hash)])
+		)));
 	}
 
 	#[test]
@@ -497,4 +796,147 @@ pub mod tests {
 
 		assert_eq!(executor.take_tasks(), vec![]);
 	}
+
+	#[test]
+	fn local_node_restores_memory_pool() {
+		let genesis = test_data::genesis();
+		let accepted: Transaction = test_data::TransactionBuilder::with_output(1)
+			.add_input(&genesis.transactions[0], 0)
+			.into();
+		let expired: Transaction = test_data::TransactionBuilder::with_output(2)
+			.add_input(&genesis.transactions[0], 0)
+			.into();
+		let rejected: Transaction = test_data::TransactionBuilder::with_output(3)
+			.add_input(&genesis.transactions[0], 0)
+			.into();
+
+		let mut verifier = DummyVerifier::default();
+		verifier.error_when_verifying(rejected.hash(), "simulated");
+		let (_, _, local_node) = create_local_node(Some(verifier));
+
+		let now = RealTime.now().as_secs() as i64;
+		let dump = MemoryPoolDump {
+			entries: vec![
+				MemoryPoolDumpEntry {
+					transaction: accepted.clone().into(),
+					time: now - 100,
+					fee_delta: 1000,
+				},
+				MemoryPoolDumpEntry {
+					transaction: expired.clone().into(),
+					time: now - 14 * 24 * 60 * 60,
+					fee_delta: 0,
+				},
+				MemoryPoolDumpEntry {
+					transaction: rejected.clone().into(),
+					time: now,
+					fee_delta: 0,
+				},
+			],
+			fee_deltas: vec![(expired.hash(), 500)],
+			unbroadcast: vec![accepted.hash(), rejected.hash()],
+		};
+		assert_eq!(local_node.restore_memory_pool(dump), 1);
+
+		let memory_pool = local_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		let entry = memory_pool.get_entry(&accepted.hash()).unwrap();
+		assert_eq!(entry.time, (now - 100) as u64);
+		assert_eq!(entry.miner_virtual_fee, 1000);
+		assert!(!memory_pool.contains(&expired.hash()));
+		assert!(!memory_pool.contains(&rejected.hash()));
+		// virtual fee of the expired transaction is kept until transaction is received again
+		assert_eq!(memory_pool.pending_fee_deltas(), vec![(expired.hash(), 500)]);
+		assert_eq!(memory_pool.unbroadcast(), vec![accepted.hash()]);
+	}
+
+	#[test]
+	fn local_node_saves_and_loads_fee_estimates() {
+		let path = std::env::temp_dir().join(format!("fee_estimates-{}.dat", std::process::id()));
+
+		let (_, _, local_node) = create_local_node(None);
+		assert_eq!(local_node.save_fee_estimates(&path), Ok(()));
+		assert_eq!(local_node.load_fee_estimates(&path), Ok(()));
+
+		std::fs::write(&path, &[1, 2, 3]).unwrap();
+		assert!(local_node.load_fee_estimates(&path).is_err());
+		std::fs::remove_file(&path).unwrap();
+		assert!(local_node.load_fee_estimates(&path).is_err());
+	}
+
+	#[test]
+	fn local_node_saves_and_loads_memory_pool() {
+		let genesis = test_data::genesis();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(1)
+			.add_input(&genesis.transactions[0], 0)
+			.into();
+		let path = std::env::temp_dir().join(format!("mempool-{}.dat", std::process::id()));
+
+		let (_, _, local_node) = create_local_node(None);
+		local_node.accept_transaction(transaction.clone().into()).unwrap();
+		assert_eq!(local_node.save_memory_pool(&path), Ok(1));
+
+		let (_, _, local_node) = create_local_node(None);
+		assert_eq!(local_node.load_memory_pool(&path), Ok(1));
+		assert!(local_node.memory_pool().read().contains(&transaction.hash()));
+		// own transaction is still announced to peers
+		assert_eq!(local_node.memory_pool().read().unbroadcast(), vec![transaction.hash()]);
+
+		std::fs::remove_file(&path).unwrap();
+		assert!(local_node.load_memory_pool(&path).is_err());
+	}
+
+	#[test]
+	fn local_node_submits_block() {
+		let (_, _, local_node) = create_local_node(None);
+
+		// parent of the block is unknown
+		assert_eq!(
+			local_node.submit_block(test_data::block_h2().into()),
+			Err("prev-blk-not-found".into())
+		);
+		// valid block is accepted
+		assert_eq!(local_node.submit_block(test_data::block_h1().into()), Ok(()));
+		assert_eq!(local_node.information().chain.stored, 2);
+		// and it is reported as duplicate on the next submission
+		assert_eq!(local_node.submit_block(test_data::block_h1().into()), Err("duplicate".into()));
+	}
+
+	#[test]
+	fn local_node_verifies_block_proposal() {
+		let (_, _, local_node) = create_local_node(None);
+
+		// proof-of-work isn't checked for proposals
+		let mut proposal: chain::IndexedBlock = test_data::block_h1().into();
+		proposal.header.raw.nonce += 1;
+		proposal.header.hash = proposal.header.raw.hash();
+		assert_eq!(local_node.verify_block_proposal(&proposal), Ok(()));
+		assert_eq!(local_node.information().chain.stored, 1);
+
+		// but invalid proposals are rejected
+		let mut proposal: chain::IndexedBlock = test_data::block_h1().into();
+		proposal.header.raw.merkle_root_hash = Default::default();
+		proposal.header.hash = proposal.header.raw.hash();
+		assert_eq!(local_node.verify_block_proposal(&proposal), Err("bad-txnmrklroot".into()));
+
+		// proposal must extend the best block
+		assert_eq!(
+			local_node.verify_block_proposal(&test_data::block_h2().into()),
+			Err("inconclusive-not-best-prevblk".into())
+		);
+	}
+
+	#[test]
+	fn local_node_submits_header() {
+		let (_, _, local_node) = create_local_node(None);
+
+		let header2 = test_data::block_h2().block_header;
+		assert_eq!(
+			local_node.submit_header(header2.clone().into()),
+			Err(format!("Must submit previous header ({}) first", header2.previous_header_hash))
+		);
+		assert_eq!(local_node.submit_header(test_data::block_h1().block_header.into()), Ok(()));
+		assert_eq!(local_node.submit_header(header2.into()), Ok(()));
+		assert_eq!(local_node.information().chain.headers.best, 2);
+	}
 }
@@ -365,6 +365,8 @@ impl Chain {
 				// => delete accepted transactions from verification queue and from the memory pool
 				// + also remove transactions which spent outputs which have been spent by transactions from the block
 				let mut memory_pool = self.memory_pool.write();
+				let transactions_hashes = block.transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+				memory_pool.process_best_block(self.best_storage_block.number, &transactions_hashes);
 				for tx in &block.transactions {
 					memory_pool.remove_by_hash(&tx.hash);
 					self.verifying_transactions.remove(&tx.hash);
@@ -405,11 +407,13 @@ impl Chain {
 					.flat_map(|block_hash| self.storage.block_transaction_hashes(block_hash.into()))
 					.collect::<Vec<_>>();
 
-				let mut memory_pool = self.memory_pool.write();
-				for transaction_accepted in this_block_transactions_hashes
+				let transactions_accepted = this_block_transactions_hashes
 					.into_iter()
 					.chain(new_main_blocks_transactions_hashes.into_iter())
-				{
+					.collect::<Vec<_>>();
+				let mut memory_pool = self.memory_pool.write();
+				memory_pool.process_best_block(self.best_storage_block.number, &transactions_accepted);
+				for transaction_accepted in transactions_accepted {
 					memory_pool.remove_by_hash(&transaction_accepted);
 					self.verifying_transactions.remove(&transaction_accepted);
 				}
@@ -621,6 +625,11 @@ impl Chain {
 		}
 		// now insert transaction itself
 		memory_pool.insert_verified(transaction, &FeeCalculator(self.storage.as_transaction_output_provider()));
+		// and evict transactions if memory pool has exceeded its limits
+		let evicted = memory_pool.limit_size();
+		if !evicted.is_empty() {
+			trace!(target: "sync", "Evicted {} transactions from the memory pool", evicted.len());
+		}
 	}
 
 	/// Calculate block locator hashes for hash queue
@@ -1030,11 +1039,15 @@ mod tests {
 
 		// only one transaction is in the memory pool
 		assert_eq!(chain.information().transactions.transactions_count, 1);
+		assert_eq!(chain.memory_pool().read().fee_estimator().tracked_transactions_count(), 1);
 
 		// when block is inserted to the database => all accepted transactions are removed from mempool && verifying queue
 		chain.insert_best_block(b1.into()).expect("block accepted");
 
 		assert_eq!(chain.information().transactions.transactions_count, 0);
+		// confirmed transaction is accounted by the fee estimator
+		assert_eq!(chain.memory_pool().read().fee_estimator().best_height(), 1);
+		assert_eq!(chain.memory_pool().read().fee_estimator().tracked_transactions_count(), 0);
 		assert!(!chain.forget_verifying_transaction(&tx1_hash));
 		assert!(!chain.forget_verifying_transaction(&tx2_hash));
 	}
@@ -1240,7 +1253,7 @@ mod tests {
 				.input().hash(tx0.hash()).index(0).build()
 				.build()
 			.build(); // genesis -> b0[tx1]
-		  // tx from b0 && tx2 are spending same output
+			 // tx from b0 && tx2 are spending same output
 		let tx2: Transaction = test_data::TransactionBuilder::with_output(20).add_input(&tx0, 0).into();
 
 		// insert tx2 to memory pool
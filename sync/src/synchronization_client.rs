@@ -131,6 +131,7 @@ pub trait Client: Send + Sync + 'static {
 	fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound);
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: UnitFuture);
 	fn accept_block(&self, block: IndexedBlock);
+	fn accept_header(&self, header: IndexedBlockHeader) -> Result<(), String>;
 	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String>;
 	fn install_sync_listener(&self, listener: SyncListenerRef);
 	fn shutdown(&self);
@@ -217,6 +218,10 @@ where
 		}
 	}
 
+	fn accept_header(&self, header: IndexedBlockHeader) -> Result<(), String> {
+		self.core.lock().accept_header(header)
+	}
+
 	fn on_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction) {
 		// block can became:
 		// ignored, orphaned => no verification should occur
@@ -2,6 +2,7 @@ use crate::synchronization_chain::Information as ChainInformation;
 use crate::synchronization_chain::{BlockInsertionResult, BlockState, Chain, TransactionState};
 use crate::synchronization_executor::{Task, TaskExecutor};
 use crate::synchronization_manager::ManagementWorker;
+use crate::synchronization_peers::TransactionAnnouncementType;
 use crate::synchronization_peers_tasks::Information as PeersTasksInformation;
 use crate::synchronization_peers_tasks::PeersTasks;
 use crate::synchronization_verifier::{BlockVerificationSink, TransactionVerificationSink, VerificationSink, VerificationTask};
@@ -71,6 +72,7 @@ pub trait ClientCore {
 	fn on_notfound(&mut self, peer_index: PeerIndex, message: types::NotFound);
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: UnitFuture);
 	fn accept_block(&mut self, block: IndexedBlock) -> Option<VecDeque<IndexedBlock>>;
+	fn accept_header(&mut self, header: IndexedBlockHeader) -> Result<(), String>;
 	fn accept_transaction(
 		&mut self,
 		transaction: IndexedTransaction,
@@ -126,10 +128,12 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
 	sync_speed_meter: AverageSpeedMeter,
 	/// Configuration
 	config: Config,
-	/// Synchronization events listener
-	listener: Option<SyncListenerRef>,
+	/// Synchronization events listeners
+	listeners: Vec<SyncListenerRef>,
 	/// Time of last duplicated blocks request.
 	last_dup_time: Option<Instant>,
+	/// Minimal fee rate of the memory pool, announced to peers in `feefilter` messages.
+	announced_min_fee_rate: u64,
 }
 
 /// Verification sink for synchronization client core
@@ -216,6 +220,34 @@ where
 		// not useful until peer responds with headers message
 		self.peers_tasks.unuseful_peer(peer_index);
 		self.peers_tasks.on_headers_requested(peer_index);
+		// do not let peer send us transactions, which won't be accepted to the memory pool
+		if self.announced_min_fee_rate != 0 {
+			self.executor.execute(Task::FeeFilter(
+				peer_index,
+				types::FeeFilter::with_fee_rate(self.announced_min_fee_rate),
+			));
+		}
+		// announce own transactions, which have not been requested by other peers yet
+		let unbroadcast: Vec<_> = {
+			let memory_pool = self.chain.memory_pool();
+			let memory_pool = memory_pool.read();
+			memory_pool
+				.unbroadcast()
+				.into_iter()
+				.filter_map(|hash| memory_pool.read_by_hash(&hash).map(|tx| IndexedTransaction::new(hash, tx.clone())))
+				.filter(|tx| {
+					matches!(
+						self.peers.filter_transaction(peer_index, tx, None),
+						TransactionAnnouncementType::SendInventory
+					)
+				})
+				.map(|tx| InventoryVector::tx(tx.hash))
+				.collect()
+		};
+		if !unbroadcast.is_empty() {
+			self.executor
+				.execute(Task::Inventory(peer_index, types::Inv::with_inventory(unbroadcast)));
+		}
 	}
 
 	fn on_disconnect(&mut self, peer_index: PeerIndex) {
@@ -560,6 +592,48 @@ where
 		result
 	}
 
+	fn accept_header(&mut self, header: IndexedBlockHeader) -> Result<(), String> {
+		match self.chain.block_state(&header.hash) {
+			BlockState::Unknown => (),
+			BlockState::DeadEnd => return Err("duplicate-invalid".into()),
+			// header is already known
+			_ => return Ok(()),
+		}
+
+		let parent_hash = header.raw.previous_header_hash;
+		match self.chain.block_state(&parent_hash) {
+			BlockState::Unknown => return Err(format!("Must submit previous header ({}) first", parent_hash)),
+			BlockState::DeadEnd => return Err("bad-prevblk".into()),
+			_ => (),
+		}
+
+		let headers_provider = MessageBlockHeadersProvider::new(&self.chain, self.chain.best_block_header().number);
+		if let Err(error) = self
+			.chain_verifier
+			.verify_block_header(&headers_provider, &header.hash, &header.raw)
+		{
+			self.chain.mark_dead_end_block(&header.hash);
+			return Err(error.reject_reason().into());
+		}
+
+		// we only track the best headers chain => headers of side chains are verified, but not scheduled
+		if self.chain.best_block_header().hash != parent_hash {
+			return Ok(());
+		}
+
+		trace!(target: "sync", "Scheduling submitted header {}", header.hash);
+		self.chain.schedule_blocks_headers(vec![header]);
+		if !self.state.is_synchronizing() {
+			if self.chain.length_of_blocks_state(BlockState::Scheduled) + self.chain.length_of_blocks_state(BlockState::Requested) == 1 {
+				self.switch_to_nearly_saturated_state();
+			} else {
+				self.switch_to_synchronization_state();
+			}
+		}
+		self.execute_synchronization_tasks(None, None);
+		Ok(())
+	}
+
 	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>> {
 		// check if this transaction is already known
 		if self.orphaned_transactions_pool.contains(&transaction.hash)
@@ -643,9 +717,7 @@ where
 	}
 
 	fn install_sync_listener(&mut self, listener: SyncListenerRef) {
-		// currently single, single-setup listener is supported
-		assert!(self.listener.is_none());
-		self.listener = Some(listener);
+		self.listeners.push(listener);
 	}
 
 	/// Schedule new synchronization tasks, if any.
@@ -907,8 +979,9 @@ where
 			block_speed_meter,
 			sync_speed_meter: AverageSpeedMeter::with_inspect_items(SYNC_SPEED_BLOCKS_TO_INSPECT),
 			config,
-			listener: None,
+			listeners: Vec::new(),
 			last_dup_time: None,
+			announced_min_fee_rate: 0,
 		}));
 
 		{
@@ -991,6 +1064,38 @@ where
 		}
 	}
 
+	/// Evict transactions from the memory pool, if it has exceeded its limits, and announce
+	/// significant changes of the memory pool minimal fee rate to peers
+	pub fn manage_memory_pool(&mut self) {
+		let (min_fee_rate, evicted) = {
+			let memory_pool = self.chain.memory_pool();
+			let mut memory_pool = memory_pool.write();
+			let evicted = memory_pool.limit_size();
+			if !evicted.is_empty() {
+				trace!(target: "sync", "Evicted {} transactions from the memory pool", evicted.len());
+			}
+			(memory_pool.min_fee_rate(), evicted)
+		};
+
+		if !evicted.is_empty() {
+			for listener in &self.listeners {
+				listener.memory_pool_changed();
+			}
+		}
+
+		// ignore insignificant changes
+		let announced_min_fee_rate = self.announced_min_fee_rate;
+		if min_fee_rate * 4 >= announced_min_fee_rate * 3 && min_fee_rate * 3 <= announced_min_fee_rate * 4 {
+			return;
+		}
+
+		self.announced_min_fee_rate = min_fee_rate;
+		for peer_index in self.peers.enumerate() {
+			self.executor
+				.execute(Task::FeeFilter(peer_index, types::FeeFilter::with_fee_rate(min_fee_rate)));
+		}
+	}
+
 	/// Forget blocks, which have been requested several times, but no one has responded
 	pub fn forget_failed_blocks(&mut self, blocks_to_forget: &[SHA256D]) {
 		if blocks_to_forget.is_empty() {
@@ -1180,7 +1285,7 @@ where
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(true);
 		}
 
@@ -1194,7 +1299,7 @@ where
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1208,7 +1313,7 @@ where
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1270,9 +1375,9 @@ where
 				self.shared_state
 					.update_best_storage_block_height(self.chain.best_storage_block().number);
 
-				// notify listener
+				// notify listeners
 				if let Some(best_block_hash) = insert_result.canonized_blocks_hashes.last() {
-					if let Some(ref listener) = self.listener {
+					for listener in &self.listeners {
 						listener.best_storage_block_inserted(best_block_hash);
 					}
 				}
@@ -1355,6 +1460,9 @@ where
 
 		// transaction was in verification queue => insert to memory pool
 		self.chain.insert_verified_transaction(transaction.clone());
+		for listener in &self.listeners {
+			listener.memory_pool_changed();
+		}
 
 		// calculate transaction fee rate
 		let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
@@ -1448,9 +1556,10 @@ pub mod tests {
 	use db::BlockChainDatabase;
 	use message::common::InventoryVector;
 	use message::{types, Services};
-	use miner::MemoryPool;
+	use miner::{MemoryPool, MemoryPoolLimits, NonZeroFeeCalculator};
 	use network::{ConsensusParams, Network};
 	use parking_lot::{Mutex, RwLock};
+	use ser::Serializable;
 	use std::sync::Arc;
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 
@@ -2789,4 +2898,48 @@ pub mod tests {
 		assert_eq!(data.lock().is_synchronizing, false);
 		assert_eq!(data.lock().best_blocks.len(), 3);
 	}
+
+	#[test]
+	fn min_fee_rate_is_announced_in_feefilter() {
+		let (executor, core, sync) = create_sync(None, None);
+		core.lock().peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+
+		// nothing is announced while memory pool isn't full
+		core.lock().manage_memory_pool();
+		assert_eq!(executor.take_tasks(), vec![]);
+
+		// evict transaction from the full memory pool
+		let tx0: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(20).into();
+		let min_fee_rate = {
+			let memory_pool = core.lock().chain().memory_pool();
+			let mut memory_pool = memory_pool.write();
+			memory_pool.insert_verified(tx0.clone().into(), &NonZeroFeeCalculator);
+			memory_pool.insert_verified(tx1.into(), &NonZeroFeeCalculator);
+			memory_pool.set_limits(MemoryPoolLimits {
+				max_size_in_bytes: tx0.serialized_size(),
+				..Default::default()
+			});
+			assert_eq!(memory_pool.limit_size().len(), 1);
+			memory_pool.min_fee_rate()
+		};
+		assert!(min_fee_rate > 0);
+
+		// minimal fee rate is announced to connected peers
+		core.lock().manage_memory_pool();
+		assert_eq!(
+			executor.take_tasks(),
+			vec![Task::FeeFilter(0, types::FeeFilter::with_fee_rate(min_fee_rate))]
+		);
+
+		// insignificant change isn't announced
+		core.lock().manage_memory_pool();
+		assert_eq!(executor.take_tasks(), vec![]);
+
+		// and to newly connected peers
+		sync.on_connect(1);
+		assert!(executor
+			.take_tasks()
+			.contains(&Task::FeeFilter(1, types::FeeFilter::with_fee_rate(min_fee_rate))));
+	}
 }
@@ -39,6 +39,12 @@ pub enum Task {
 	BlockTxn(PeerIndex, types::BlockTxn),
 	/// Send notfound
 	NotFound(PeerIndex, types::NotFound),
+	/// Send compact block filter
+	CFilter(PeerIndex, types::CFilter),
+	/// Send compact block filter headers
+	CFHeaders(PeerIndex, types::CFHeaders),
+	/// Send compact block filter headers checkpoints
+	CFCheckpt(PeerIndex, types::CFCheckpt),
 	/// Send inventory
 	Inventory(PeerIndex, types::Inv),
 	/// Send headers
@@ -47,6 +53,8 @@ pub enum Task {
 	RelayNewBlock(IndexedBlock),
 	/// Relay new transaction to peers
 	RelayNewTransaction(IndexedTransaction, u64),
+	/// Send feefilter
+	FeeFilter(PeerIndex, types::FeeFilter),
 }
 
 /// Synchronization tasks executor
@@ -165,6 +173,27 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_cfilter(&self, peer_index: PeerIndex, cfilter: types::CFilter) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfilter for block {} to peer#{}", cfilter.block_hash, peer_index);
+			connection.send_cfilter(cfilter);
+		}
+	}
+
+	fn execute_cfheaders(&self, peer_index: PeerIndex, cfheaders: types::CFHeaders) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfheaders to peer#{} with {} items", peer_index, cfheaders.filter_hashes.len());
+			connection.send_cfheaders(cfheaders);
+		}
+	}
+
+	fn execute_cfcheckpt(&self, peer_index: PeerIndex, cfcheckpt: types::CFCheckpt) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfcheckpt to peer#{} with {} items", peer_index, cfcheckpt.filter_headers.len());
+			connection.send_cfcheckpt(cfcheckpt);
+		}
+	}
+
 	fn execute_inventory(&self, peer_index: PeerIndex, inventory: types::Inv) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending inventory to peer#{} with {} items", peer_index, inventory.inventory.len());
@@ -182,6 +211,13 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_feefilter(&self, peer_index: PeerIndex, feefilter: types::FeeFilter) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending feefilter {} to peer#{}", feefilter.fee_rate, peer_index);
+			connection.send_feefilter(feefilter);
+		}
+	}
+
 	fn execute_relay_block(&self, block: IndexedBlock) {
 		for peer_index in self.peers.enumerate() {
 			match self.peers.filter_block(peer_index, &block) {
@@ -229,10 +265,14 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::WitnessTransaction(peer_index, transaction) => self.execute_witness_transaction(peer_index, transaction),
 			Task::BlockTxn(peer_index, blocktxn) => self.execute_block_txn(peer_index, blocktxn),
 			Task::NotFound(peer_index, notfound) => self.execute_notfound(peer_index, notfound),
+			Task::CFilter(peer_index, cfilter) => self.execute_cfilter(peer_index, cfilter),
+			Task::CFHeaders(peer_index, cfheaders) => self.execute_cfheaders(peer_index, cfheaders),
+			Task::CFCheckpt(peer_index, cfcheckpt) => self.execute_cfcheckpt(peer_index, cfcheckpt),
 			Task::Inventory(peer_index, inventory) => self.execute_inventory(peer_index, inventory),
 			Task::Headers(peer_index, headers, request_id) => self.execute_headers(peer_index, headers, request_id),
 			Task::RelayNewBlock(block) => self.execute_relay_block(block),
 			Task::RelayNewTransaction(transaction, fee_rate) => self.execute_relay_transaction(transaction, fee_rate),
+			Task::FeeFilter(peer_index, feefilter) => self.execute_feefilter(peer_index, feefilter),
 		}
 	}
 }
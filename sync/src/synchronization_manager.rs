@@ -89,6 +89,8 @@ impl ManagementWorker {
 			let mut core = core.lock();
 			// trace synchronization state
 			core.print_synchronization_information();
+			// evict expired transactions && announce memory pool fee rate
+			core.manage_memory_pool();
 			// execute management tasks if not saturated
 			if core.state().is_synchronizing() || core.state().is_nearly_saturated() {
 				let (blocks_to_request, blocks_to_forget) =
@@ -3,10 +3,13 @@ use crate::utils::{ConnectionFilter, KnownHashType};
 use bitcrypto::SHA256D;
 use chain::{IndexedBlock, IndexedTransaction};
 use message::{types, Services};
-use p2p::OutboundSyncConnectionRef;
+use p2p::{OutboundSyncConnectionRef, BAN_SCORE};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 
+/// Misbehavior score of the misbehaving peer. Peer is banned after repeating misbehavior five times.
+const MISBEHAVING_SCORE: u32 = BAN_SCORE / 5;
+
 /// Block announcement type
 #[derive(Debug, Clone, Copy)]
 pub enum BlockAnnouncementType {
@@ -54,9 +57,9 @@ pub trait PeersContainer {
 	fn insert(&self, peer_index: PeerIndex, services: Services, connection: OutboundSyncConnectionRef);
 	/// Remove peer connection
 	fn remove(&self, peer_index: PeerIndex);
-	/// Close and remove peer connection due to misbehaving
+	/// Close and remove peer connection due to misbehaving, peer is banned after repeated misbehavior
 	fn misbehaving(&self, peer_index: PeerIndex, reason: &str);
-	/// Close and remove peer connection due to detected DOS attempt
+	/// Close and remove peer connection due to detected DOS attempt and ban the peer
 	fn dos(&self, peer_index: PeerIndex, reason: &str);
 }
 
@@ -93,6 +96,8 @@ pub trait PeersFilters {
 pub trait PeersOptions {
 	/// Is node supporting SegWit?
 	fn is_segwit_enabled(&self, peer_index: PeerIndex) -> bool;
+	/// Is node serving all blocks (NODE_NETWORK)?
+	fn is_full_node(&self, peer_index: PeerIndex) -> bool;
 	/// Set up new block announcement type for the connection
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType);
 	/// Set up new transaction announcement type for the connection
@@ -176,6 +181,7 @@ impl PeersContainer for PeersImpl {
 	fn misbehaving(&self, peer_index: PeerIndex, reason: &str) {
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
 			warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior: {}", peer_index, reason);
+			peer.connection.misbehaving(MISBEHAVING_SCORE);
 			peer.connection.close();
 		}
 	}
@@ -183,6 +189,7 @@ impl PeersContainer for PeersImpl {
 	fn dos(&self, peer_index: PeerIndex, reason: &str) {
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
 			warn!(target: "sync", "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
+			peer.connection.misbehaving(BAN_SCORE);
 			peer.connection.close();
 		}
 	}
@@ -276,6 +283,14 @@ impl PeersOptions for PeersImpl {
 			.unwrap_or_default()
 	}
 
+	fn is_full_node(&self, peer_index: PeerIndex) -> bool {
+		self.peers
+			.read()
+			.get(&peer_index)
+			.map(|peer| peer.services.network())
+			.unwrap_or_default()
+	}
+
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType) {
 		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
 			peer.block_announcement_type = announcement_type;
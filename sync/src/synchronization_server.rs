@@ -2,7 +2,7 @@ use crate::synchronization_executor::{Task, TaskExecutor};
 use crate::types::{BlockHeight, ExecutorRef, MemoryPoolRef, PeerIndex, PeersRef, RequestId, StorageRef};
 use crate::utils::KnownHashType;
 use bitcrypto::SHA256D;
-use chain::IndexedTransaction;
+use chain::{IndexedTransaction, BASIC_FILTER_TYPE};
 use message::{common, types};
 use parking_lot::{Condvar, Mutex};
 use std::collections::hash_map::Entry;
@@ -26,6 +26,12 @@ pub enum ServerTask {
 	Mempool(PeerIndex),
 	/// Serve 'getblocktxn' request
 	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Serve 'getcfilters' request
+	GetCFilters(PeerIndex, types::GetCFilters),
+	/// Serve 'getcfheaders' request
+	GetCFHeaders(PeerIndex, types::GetCFHeaders),
+	/// Serve 'getcfcheckpt' request
+	GetCFCheckpt(PeerIndex, types::GetCFCheckpt),
 }
 
 /// Synchronization server
@@ -84,7 +90,10 @@ impl ServerTask {
 			| ServerTask::GetBlocks(peer_index, _)
 			| ServerTask::GetHeaders(peer_index, _, _)
 			| ServerTask::Mempool(peer_index)
-			| ServerTask::GetBlockTxn(peer_index, _) => peer_index,
+			| ServerTask::GetBlockTxn(peer_index, _)
+			| ServerTask::GetCFilters(peer_index, _)
+			| ServerTask::GetCFHeaders(peer_index, _)
+			| ServerTask::GetCFCheckpt(peer_index, _) => peer_index,
 		}
 	}
 }
@@ -247,6 +256,9 @@ where
 			ServerTask::GetHeaders(peer_index, message, request_id) => self.serve_get_headers(peer_index, message, request_id),
 			ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index),
 			ServerTask::GetBlockTxn(peer_index, message) => self.serve_get_block_txn(peer_index, message),
+			ServerTask::GetCFilters(peer_index, message) => self.serve_get_cfilters(peer_index, message),
+			ServerTask::GetCFHeaders(peer_index, message) => self.serve_get_cfheaders(peer_index, message),
+			ServerTask::GetCFCheckpt(peer_index, message) => self.serve_get_cfcheckpt(peer_index, message),
 		}
 
 		None
@@ -282,9 +294,12 @@ where
 		match next_item.inv_type {
 			common::InventoryType::MessageTx => {
 				// only transaction from memory pool can be requested
-				if let Some(transaction) = self.memory_pool.read().read_by_hash(&next_item.hash) {
+				let mut memory_pool = self.memory_pool.write();
+				if let Some(transaction) = memory_pool.read_by_hash(&next_item.hash).cloned() {
 					trace!(target: "sync", "'getblocks' response to peer#{} is ready with tx {}", peer_index, next_item.hash);
-					let transaction = IndexedTransaction::new(next_item.hash, transaction.clone());
+					// transaction has been successfully broadcasted
+					memory_pool.remove_unbroadcast(&next_item.hash);
+					let transaction = IndexedTransaction::new(next_item.hash, transaction);
 					self.executor.execute(Task::Transaction(peer_index, transaction));
 				} else {
 					notfound.inventory.push(next_item);
@@ -292,9 +307,12 @@ where
 			}
 			common::InventoryType::MessageWitnessTx => {
 				// only transaction from memory pool can be requested
-				if let Some(transaction) = self.memory_pool.read().read_by_hash(&next_item.hash) {
+				let mut memory_pool = self.memory_pool.write();
+				if let Some(transaction) = memory_pool.read_by_hash(&next_item.hash).cloned() {
 					trace!(target: "sync", "'getblocks' response to peer#{} is ready with witness-tx {}", peer_index, next_item.hash);
-					let transaction = IndexedTransaction::new(next_item.hash, transaction.clone());
+					// transaction has been successfully broadcasted
+					memory_pool.remove_unbroadcast(&next_item.hash);
+					let transaction = IndexedTransaction::new(next_item.hash, transaction);
 					self.executor.execute(Task::WitnessTransaction(peer_index, transaction));
 				} else {
 					notfound.inventory.push(next_item);
@@ -357,6 +375,8 @@ where
 	fn serve_get_blocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
 		if let Some(block_height) = self.locate_best_common_block(&message.hash_stop, &message.block_locator_hashes) {
 			let inventory: Vec<_> = (block_height + 1..block_height + 1 + (types::GETBLOCKS_MAX_RESPONSE_HASHES as BlockHeight))
+				// pruned blocks could not be served => they are never announced
+				.take_while(|block_height| !self.storage.is_pruned(*block_height))
 				.map(|block_height| self.storage.block_hash(block_height))
 				.take_while(Option::is_some)
 				.map(Option::unwrap)
@@ -497,6 +517,157 @@ where
 		));
 	}
 
+	fn serve_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		let stop_height = match self.locate_filters_range(
+			peer_index,
+			"getcfilters",
+			message.filter_type,
+			message.start_height,
+			&message.stop_hash,
+			types::GETCFILTERS_MAX_RESPONSE_FILTERS,
+		) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let mut cfilters = Vec::with_capacity((stop_height - message.start_height + 1) as usize);
+		for block_height in message.start_height..stop_height + 1 {
+			match self
+				.storage
+				.block_hash(block_height)
+				.and_then(|block_hash| self.storage.block_filter(block_hash.into()).map(|filter| (block_hash, filter)))
+			{
+				Some((block_hash, filter)) => cfilters.push(types::CFilter {
+					filter_type: message.filter_type,
+					block_hash,
+					filter: filter.content,
+				}),
+				None => {
+					warn!(target: "sync", "'getcfilters' request from peer#{} is ignored as filter of block#{} is not indexed", peer_index, block_height);
+					return;
+				}
+			}
+		}
+
+		trace!(target: "sync", "'getcfilters' response to peer#{} is ready with {} filters", peer_index, cfilters.len());
+		for cfilter in cfilters {
+			self.executor.execute(Task::CFilter(peer_index, cfilter));
+		}
+	}
+
+	fn serve_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		let stop_height = match self.locate_filters_range(
+			peer_index,
+			"getcfheaders",
+			message.filter_type,
+			message.start_height,
+			&message.stop_hash,
+			types::GETCFHEADERS_MAX_RESPONSE_HEADERS,
+		) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let previous_filter_header = match message.start_height {
+			0 => Some(SHA256D::default()),
+			start_height => self.storage.block_filter_header((start_height - 1).into()),
+		};
+		let filter_hashes = (message.start_height..stop_height + 1)
+			.map(|block_height| self.storage.block_filter(block_height.into()).map(|filter| filter.hash()))
+			.collect::<Option<Vec<_>>>();
+		let (previous_filter_header, filter_hashes) = match (previous_filter_header, filter_hashes) {
+			(Some(previous_filter_header), Some(filter_hashes)) => (previous_filter_header, filter_hashes),
+			_ => {
+				warn!(target: "sync", "'getcfheaders' request from peer#{} is ignored as some filters are not indexed", peer_index);
+				return;
+			}
+		};
+
+		trace!(target: "sync", "'getcfheaders' response to peer#{} is ready with {} hashes", peer_index, filter_hashes.len());
+		self.executor.execute(Task::CFHeaders(
+			peer_index,
+			types::CFHeaders {
+				filter_type: message.filter_type,
+				stop_hash: message.stop_hash,
+				previous_filter_header,
+				filter_hashes,
+			},
+		));
+	}
+
+	fn serve_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		let stop_height = match self.locate_filters_range(peer_index, "getcfcheckpt", message.filter_type, 0, &message.stop_hash, u32::MAX)
+		{
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let filter_headers = (1..stop_height / types::CFCHECKPT_INTERVAL + 1)
+			.map(|index| self.storage.block_filter_header((index * types::CFCHECKPT_INTERVAL).into()))
+			.collect::<Option<Vec<_>>>();
+		let filter_headers = match filter_headers {
+			Some(filter_headers) => filter_headers,
+			None => {
+				warn!(target: "sync", "'getcfcheckpt' request from peer#{} is ignored as some filters are not indexed", peer_index);
+				return;
+			}
+		};
+
+		trace!(target: "sync", "'getcfcheckpt' response to peer#{} is ready with {} headers", peer_index, filter_headers.len());
+		self.executor.execute(Task::CFCheckpt(
+			peer_index,
+			types::CFCheckpt {
+				filter_type: message.filter_type,
+				stop_hash: message.stop_hash,
+				filter_headers,
+			},
+		));
+	}
+
+	/// Checks compact filters request and returns height of the stop block
+	fn locate_filters_range(
+		&self,
+		peer_index: PeerIndex,
+		command: &str,
+		filter_type: u8,
+		start_height: BlockHeight,
+		stop_hash: &SHA256D,
+		max_len: u32,
+	) -> Option<BlockHeight> {
+		if filter_type != BASIC_FILTER_TYPE {
+			self.peers.misbehaving(
+				peer_index,
+				&format!("Got '{}' message with unsupported filter type {}", command, filter_type),
+			);
+			return None;
+		}
+
+		// only canon chain filters are served
+		let stop_height = match self.storage.block_number(stop_hash) {
+			Some(stop_height) if self.storage.block_hash(stop_height).as_ref() == Some(stop_hash) => stop_height,
+			_ => {
+				self.peers.misbehaving(
+					peer_index,
+					&format!("Got '{}' message with unknown stop block {}", command, stop_hash),
+				);
+				return None;
+			}
+		};
+
+		if start_height > stop_height || stop_height - start_height >= max_len {
+			self.peers.misbehaving(
+				peer_index,
+				&format!(
+					"Got '{}' message with invalid range of blocks: {}..{}",
+					command, start_height, stop_height
+				),
+			);
+			return None;
+		}
+
+		Some(stop_height)
+	}
+
 	fn locate_best_common_block(&self, hash_stop: &SHA256D, locator: &[SHA256D]) -> Option<BlockHeight> {
 		for block_hash in locator.iter().chain(&[hash_stop.clone()]) {
 			if let Some(block_number) = self.storage.block_number(block_hash) {
@@ -778,6 +949,138 @@ pub mod tests {
 		assert!(!peers.enumerate().contains(&0));
 	}
 
+	#[test]
+	fn server_getcfilters_responds_filters_when_good_request() {
+		let (storage, _, executor, _, server) = create_synchronization_server();
+		storage.insert(test_data::block_h1().into()).expect("Db write error");
+		storage.canonize(&test_data::block_h1().hash()).unwrap();
+
+		// when asking for filters of all blocks
+		server.execute(ServerTask::GetCFilters(
+			0,
+			types::GetCFilters {
+				filter_type: 0,
+				start_height: 0,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+
+		// => respond with filter of every block
+		let expected_cfilter = |block_hash: SHA256D| types::CFilter {
+			filter_type: 0,
+			block_hash,
+			filter: storage.block_filter(block_hash.into()).unwrap().content,
+		};
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(
+			tasks,
+			vec![
+				Task::CFilter(0, expected_cfilter(test_data::genesis().hash())),
+				Task::CFilter(0, expected_cfilter(test_data::block_h1().hash())),
+			]
+		);
+	}
+
+	#[test]
+	fn server_getcfheaders_responds_filter_hashes_when_good_request() {
+		let (storage, _, executor, _, server) = create_synchronization_server();
+		storage.insert(test_data::block_h1().into()).expect("Db write error");
+		storage.canonize(&test_data::block_h1().hash()).unwrap();
+
+		// when asking for filter headers, starting from the block#1
+		server.execute(ServerTask::GetCFHeaders(
+			0,
+			types::GetCFHeaders {
+				filter_type: 0,
+				start_height: 1,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+
+		// => respond with previous filter header + filter hashes
+		let filter = storage.block_filter(1.into()).unwrap();
+		let previous_filter_header = storage.block_filter_header(0.into()).unwrap();
+		assert_eq!(
+			filter.header(&previous_filter_header),
+			storage.block_filter_header(1.into()).unwrap()
+		);
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(
+			tasks,
+			vec![Task::CFHeaders(
+				0,
+				types::CFHeaders {
+					filter_type: 0,
+					stop_hash: test_data::block_h1().hash(),
+					previous_filter_header,
+					filter_hashes: vec![filter.hash()],
+				}
+			)]
+		);
+	}
+
+	#[test]
+	fn server_getcfcheckpt_responds_empty_checkpoints_for_short_chain() {
+		let (_, _, executor, _, server) = create_synchronization_server();
+
+		// when asking for checkpoints of the chain, which is shorter than checkpoint interval
+		server.execute(ServerTask::GetCFCheckpt(
+			0,
+			types::GetCFCheckpt {
+				filter_type: 0,
+				stop_hash: test_data::genesis().hash(),
+			},
+		));
+
+		// => respond with empty checkpoints
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(
+			tasks,
+			vec![Task::CFCheckpt(
+				0,
+				types::CFCheckpt {
+					filter_type: 0,
+					stop_hash: test_data::genesis().hash(),
+					filter_headers: vec![],
+				}
+			)]
+		);
+	}
+
+	#[test]
+	fn server_getcfilters_do_not_responds_when_bad_request() {
+		let (_, _, _, peers, server) = create_synchronization_server();
+
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+
+		// when asking for unsupported filter type
+		server.execute(ServerTask::GetCFilters(
+			0,
+			types::GetCFilters {
+				filter_type: 1,
+				start_height: 0,
+				stop_hash: test_data::genesis().hash(),
+			},
+		));
+		// when asking for filters of unknown block
+		server.execute(ServerTask::GetCFHeaders(
+			1,
+			types::GetCFHeaders {
+				filter_type: 0,
+				start_height: 0,
+				stop_hash: SHA256D::default(),
+			},
+		));
+
+		// server closes connections
+		use std::thread;
+		use std::time::Duration;
+		thread::park_timeout(Duration::from_millis(100)); // TODO: get rid of timeout
+		assert!(!peers.enumerate().contains(&0));
+		assert!(!peers.enumerate().contains(&1));
+	}
+
 	#[test]
 	fn server_getdata_responds_notfound_when_transaction_is_inaccessible() {
 		let (_, _, executor, _, server) = create_synchronization_server();
@@ -805,9 +1108,9 @@ pub mod tests {
 		let tx_verified_hash = tx_verified.hash();
 		// given in-memory transaction
 		{
-			memory_pool
-				.write()
-				.insert_verified(tx_verified.clone().into(), &NonZeroFeeCalculator);
+			let mut memory_pool = memory_pool.write();
+			memory_pool.insert_verified(tx_verified.clone().into(), &NonZeroFeeCalculator);
+			memory_pool.add_unbroadcast(&tx_verified_hash);
 		}
 		// when asking for known in-memory transaction
 		let inventory = vec![InventoryVector {
@@ -822,6 +1125,8 @@ pub mod tests {
 			tasks.extend(DummyTaskExecutor::wait_tasks_for(executor, 100));
 		}
 		assert_eq!(tasks, vec![Task::Transaction(0, tx_verified.into()),]);
+		// => transaction is not unbroadcast anymore
+		assert_eq!(memory_pool.read().unbroadcast(), vec![]);
 	}
 
 	#[test]
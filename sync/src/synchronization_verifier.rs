@@ -189,13 +189,18 @@ impl AsyncVerifier {
 						}
 						Ok(tx_output_provider) => {
 							let time: u32 = RealTime.now().as_secs() as u32;
-							match verifier.verifier.verify_mempool_transaction(
-								storage.as_block_header_provider(),
-								&tx_output_provider,
-								height,
-								time,
-								&transaction,
-							) {
+							match verifier
+								.verifier
+								.verify_mempool_transaction(
+									storage.as_block_header_provider(),
+									&tx_output_provider,
+									height,
+									time,
+									&transaction,
+								)
+								.and_then(|_| tx_output_provider.check_replacement(memory_pool, &transaction.raw))
+								.and_then(|_| tx_output_provider.check_limits(memory_pool, &transaction.raw))
+							{
 								Ok(_) => sink.on_transaction_verification_success(transaction.into()),
 								Err(e) => sink.on_transaction_verification_error(&format!("{:?}", e), &transaction.hash),
 							}
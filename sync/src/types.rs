@@ -1,4 +1,5 @@
 use super::SyncListener;
+use crate::historical_blocks::HistoricalBlocks;
 use crate::local_node::LocalNode;
 use crate::synchronization_client::SynchronizationClient;
 use crate::synchronization_executor::LocalSynchronizationTaskExecutor;
@@ -51,6 +52,9 @@ pub type ServerRef<T> = Arc<T>;
 /// Reference to local node
 pub type LocalNodeRef = Arc<LocalNode<ServerImpl, SynchronizationClient<LocalSynchronizationTaskExecutor, AsyncVerifier>>>;
 
+/// Reference to historical blocks downloader
+pub type HistoricalBlocksRef = Arc<HistoricalBlocks>;
+
 /// Synchronization events listener reference
 pub type SyncListenerRef = Box<dyn SyncListener>;
 
@@ -1,12 +1,12 @@
 use super::super::types::{MemoryPoolRef, StorageRef};
 use chain::{OutPoint, Transaction, TransactionOutput};
-use miner::{DoubleSpendCheckResult, HashedOutPoint, NonFinalDoubleSpendSet};
+use miner::{transaction_fee, DoubleSpendCheckResult, HashedOutPoint, NonFinalDoubleSpendSet};
 use std::collections::HashMap;
 use storage::TransactionOutputProvider;
 use verification::TransactionError;
 
 /// Transaction output observer, which looks into both storage && into memory pool.
-/// It also allows to replace non-final transactions in the memory pool.
+/// It also allows to replace replaceable (BIP125) transactions in the memory pool.
 pub struct MemoryPoolTransactionOutputProvider {
 	/// Storage provider
 	storage_provider: StorageRef,
@@ -58,6 +58,25 @@ impl MemoryPoolTransactionOutputProvider {
 			}),
 		}
 	}
+
+	/// Checks that verified transaction pays enough to replace conflicting memory pool transactions (BIP125)
+	pub fn check_replacement(&self, memory_pool: &MemoryPoolRef, transaction: &Transaction) -> Result<(), TransactionError> {
+		match self.nonfinal_spends {
+			Some(ref nonfinal_spends) => memory_pool
+				.read()
+				.check_replacement(transaction, transaction_fee(self, transaction), nonfinal_spends)
+				.map_err(TransactionError::Replacement),
+			None => Ok(()),
+		}
+	}
+
+	/// Checks that verified transaction could be accepted to the memory pool without exceeding its limits
+	pub fn check_limits(&self, memory_pool: &MemoryPoolRef, transaction: &Transaction) -> Result<(), TransactionError> {
+		memory_pool
+			.read()
+			.check_limits(transaction, transaction_fee(self, transaction))
+			.map_err(TransactionError::MemoryPoolLimit)
+	}
 }
 
 impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
@@ -104,12 +123,13 @@ mod tests {
 	extern crate test_data;
 
 	use super::MemoryPoolTransactionOutputProvider;
-	use chain::OutPoint;
+	use chain::{OutPoint, Transaction};
 	use db::BlockChainDatabase;
-	use miner::{MemoryPool, NonZeroFeeCalculator};
+	use miner::{FeeCalculator, MemoryPool, NonZeroFeeCalculator};
 	use parking_lot::RwLock;
 	use std::sync::Arc;
-	use storage::TransactionOutputProvider;
+	use storage::{AsSubstore, TransactionOutputProvider};
+	use verification::{ReplacementError, TransactionError};
 
 	#[test]
 	fn when_transaction_depends_on_removed_nonfinal_transaction() {
@@ -201,4 +221,36 @@ mod tests {
 			None
 		);
 	}
+
+	#[test]
+	fn when_transaction_replaces_bip125_transaction() {
+		let genesis = test_data::genesis();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]));
+		let original: Transaction = test_data::TransactionBuilder::with_input(&genesis.transactions[0], 0)
+			.add_output(4_999_000_000)
+			.lock()
+			.into();
+		let underpaying: Transaction = test_data::TransactionBuilder::with_input(&genesis.transactions[0], 0)
+			.add_output(4_999_000_001)
+			.into();
+		let replacement: Transaction = test_data::TransactionBuilder::with_input(&genesis.transactions[0], 0)
+			.add_output(4_998_000_000)
+			.into();
+
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		memory_pool
+			.write()
+			.insert_verified(original.clone().into(), &FeeCalculator(storage.as_transaction_output_provider()));
+
+		let provider = MemoryPoolTransactionOutputProvider::for_transaction(storage.clone(), &memory_pool, &underpaying).unwrap();
+		assert_eq!(
+			provider.check_replacement(&memory_pool, &underpaying),
+			Err(TransactionError::Replacement(ReplacementError::InsufficientFeeRate(
+				original.hash()
+			)))
+		);
+
+		let provider = MemoryPoolTransactionOutputProvider::for_transaction(storage, &memory_pool, &replacement).unwrap();
+		assert_eq!(provider.check_replacement(&memory_pool, &replacement), Ok(()));
+	}
 }
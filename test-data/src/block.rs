@@ -2,10 +2,11 @@
 
 use super::genesis;
 use crate::invoke::{Identity, Invoke};
-use bitcrypto::SHA256D;
+use bitcrypto::{Hash, SHA256D};
 use chain;
 use primitives::bytes::Bytes;
 use primitives::compact::Compact;
+use primitives::U256;
 use script::{Builder as ScriptBuilder, Opcode};
 use ser::{serialized_list_size, Serializable};
 use std::cell::Cell;
@@ -564,6 +565,47 @@ pub fn build_n_empty_blocks(n: u32, start_nonce: u32) -> Vec<chain::Block> {
 	result
 }
 
+/// Builds chain of `n` blocks on top of the regtest block at `previous_height`. Every block has valid regtest
+/// proof of work and the single coinbase, paying block reward to OP_TRUE. The chain only depends on its parent,
+/// so the same chain (and the same UTXO snapshot) is built every time.
+pub fn build_n_regtest_blocks_from(n: u32, previous_height: u32, previous: &chain::BlockHeader) -> Vec<chain::Block> {
+	let mut result = Vec::new();
+	let mut previous = previous.clone();
+	for height in previous_height + 1..=previous_height + n {
+		let coinbase = chain::Transaction {
+			version: 1,
+			inputs: vec![chain::TransactionInput::coinbase(
+				ScriptBuilder::default().push_data(&height.to_le_bytes()).into_bytes(),
+			)],
+			outputs: vec![chain::TransactionOutput {
+				value: 5_000_000_000,
+				script_pubkey: ScriptBuilder::default().push_opcode(Opcode::OP_1).into_bytes(),
+			}],
+			lock_time: 0,
+		};
+		let mut header = chain::BlockHeader {
+			version: 4,
+			previous_header_hash: previous.hash(),
+			merkle_root_hash: coinbase.hash(),
+			time: previous.time + 10 * 60,
+			bits: previous.bits,
+			nonce: 0,
+		};
+		let target = header.bits.to_u256().expect("regtest bits are valid; qed");
+		while {
+			let mut hash = header.hash().into_inner();
+			hash.reverse();
+			U256::from(&hash[..]) > target
+		} {
+			header.nonce += 1;
+		}
+
+		previous = header.clone();
+		result.push(chain::Block::new(header, vec![coinbase]));
+	}
+	result
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::{block_builder, block_hash_builder};
@@ -12,7 +12,10 @@ pub mod chain_builder;
 pub mod invoke;
 pub mod time;
 
-pub use block::{block_builder, block_hash_builder, build_n_empty_blocks, build_n_empty_blocks_from, build_n_empty_blocks_from_genesis};
+pub use block::{
+	block_builder, block_hash_builder, build_n_empty_blocks, build_n_empty_blocks_from, build_n_empty_blocks_from_genesis,
+	build_n_regtest_blocks_from,
+};
 pub use chain_builder::{ChainBuilder, TransactionBuilder};
 
 pub fn block1() -> Block {
@@ -305,6 +305,7 @@ pub struct TransactionEval<'a> {
 	verify_checksequence: bool,
 	verify_dersig: bool,
 	verify_witness: bool,
+	verify_taproot: bool,
 	verify_nulldummy: bool,
 	verify_monolith_opcodes: bool,
 	verify_sigpushonly: bool,
@@ -332,6 +333,7 @@ impl<'a> TransactionEval<'a> {
 		let verify_checksequence = deployments.csv();
 		let verify_witness = deployments.segwit();
 		let verify_nulldummy = verify_witness;
+		let verify_taproot = deployments.taproot();
 		let verify_sigpushonly = false;
 		let verify_cleanstack = false;
 
@@ -345,6 +347,7 @@ impl<'a> TransactionEval<'a> {
 			verify_checksequence,
 			verify_dersig,
 			verify_witness,
+			verify_taproot,
 			verify_nulldummy,
 			verify_monolith_opcodes,
 			verify_sigpushonly,
@@ -362,7 +365,21 @@ impl<'a> TransactionEval<'a> {
 			return Ok(());
 		}
 
-		let signer: TransactionInputSigner = self.transaction.raw.clone().into();
+		// taproot signature hashes commit to all outputs spent by the transaction
+		let spent_outputs = self
+			.transaction
+			.raw
+			.inputs
+			.iter()
+			.map(|input| {
+				self.store
+					.transaction_output(&input.previous_output, usize::MAX)
+					.ok_or(TransactionError::UnknownReference(input.previous_output.hash))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let mut signer: TransactionInputSigner = self.transaction.raw.clone().into();
+		signer.spent_outputs = spent_outputs.clone();
 
 		let mut checker = TransactionSignatureChecker {
 			signer,
@@ -370,12 +387,7 @@ impl<'a> TransactionEval<'a> {
 			input_amount: 0,
 		};
 
-		for (index, input) in self.transaction.raw.inputs.iter().enumerate() {
-			let output = self
-				.store
-				.transaction_output(&input.previous_output, usize::max_value())
-				.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone()))?;
-
+		for (index, (input, output)) in self.transaction.raw.inputs.iter().zip(spent_outputs).enumerate() {
 			checker.input_index = index;
 			checker.input_amount = output.value;
 
@@ -391,6 +403,7 @@ impl<'a> TransactionEval<'a> {
 				.verify_dersig(self.verify_dersig)
 				.verify_nulldummy(self.verify_nulldummy)
 				.verify_witness(self.verify_witness)
+				.verify_taproot(self.verify_taproot)
 				.verify_concat(self.verify_monolith_opcodes)
 				.verify_split(self.verify_monolith_opcodes)
 				.verify_and(self.verify_monolith_opcodes)
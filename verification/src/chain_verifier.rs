@@ -34,7 +34,12 @@ impl BackwardsCompatibleChainVerifier {
 		}
 	}
 
-	fn verify_block(&self, verification_level: VerificationLevel, block: &IndexedBlock) -> Result<(), Error> {
+	/// Verifies block proposal (BIP23). Proposal is verified as a regular block, except for the proof-of-work
+	pub fn verify_block_proposal(&self, block: &IndexedBlock) -> Result<(), Error> {
+		self.verify_block(VerificationLevel::Full, block, false)
+	}
+
+	fn verify_block(&self, verification_level: VerificationLevel, block: &IndexedBlock, check_pow: bool) -> Result<(), Error> {
 		if verification_level == VerificationLevel::NoVerification {
 			return Ok(());
 		}
@@ -42,7 +47,11 @@ impl BackwardsCompatibleChainVerifier {
 		let current_time = RealTime.now().as_secs() as u32;
 		// first run pre-verification
 		let chain_verifier = ChainVerifier::new(block, self.consensus.network, current_time);
-		chain_verifier.check()?;
+		if check_pow {
+			chain_verifier.check()?;
+		} else {
+			chain_verifier.check_without_pow()?;
+		}
 
 		assert_eq!(
 			Some(self.store.best_block().hash),
@@ -176,7 +185,7 @@ impl BackwardsCompatibleChainVerifier {
 
 impl Verify for BackwardsCompatibleChainVerifier {
 	fn verify(&self, level: VerificationLevel, block: &IndexedBlock) -> Result<(), Error> {
-		let result = self.verify_block(level, block);
+		let result = self.verify_block(level, block, true);
 		trace!(
 			target: "verification", "Block {} (transactions: {}) verification finished. Result {:?}",
 			block.hash(),
@@ -1,13 +1,12 @@
 use crate::timestamp::median_timestamp;
 use bitcrypto::SHA256D;
-use network::{ConsensusParams, Deployment};
+use network::{ConsensusParams, Deployment, DEPLOYMENT_CSV, DEPLOYMENT_SEGWIT, DEPLOYMENT_TAPROOT};
 use parking_lot::Mutex;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use storage::{BlockAncestors, BlockHeaderProvider, BlockIterator, BlockRef};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum ThresholdState {
+pub enum ThresholdState {
 	Defined,
 	Started,
 	LockedIn,
@@ -29,11 +28,8 @@ impl ThresholdState {
 		}
 	}
 
-	fn is_active(&self) -> bool {
-		match *self {
-			ThresholdState::Active => true,
-			_ => false,
-		}
+	pub fn is_active(&self) -> bool {
+		matches!(*self, ThresholdState::Active)
 	}
 }
 
@@ -46,11 +42,41 @@ struct DeploymentState {
 	block_hash: SHA256D,
 	/// Threshold state for given block
 	state: ThresholdState,
+	/// Number of the first block, since which `state` applies
+	since: u32,
 }
 
 /// Last known deployment states
 type DeploymentStateCache = HashMap<&'static str, DeploymentState>;
 
+/// Deployment state, as seen by given block
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeploymentInfo {
+	/// Threshold state for the block
+	pub state: ThresholdState,
+	/// Number of the first block, since which `state` applies
+	pub since: u32,
+	/// Threshold state for the next block
+	pub next_state: ThresholdState,
+	/// Signalling statistics of the period, the block belongs to. Only known for started and locked in deployments.
+	pub statistics: Option<DeploymentStatistics>,
+}
+
+/// Version bits signalling statistics of single period
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeploymentStatistics {
+	/// Length of the period
+	pub period: u32,
+	/// Number of signalling blocks, required to lock in the deployment
+	pub threshold: u32,
+	/// Number of blocks, elapsed since the beginning of the period
+	pub elapsed: u32,
+	/// Number of signalling blocks in the elapsed part of the period
+	pub count: u32,
+	/// Whether threshold still can be reached in this period
+	pub possible: bool,
+}
+
 #[derive(Default, Debug)]
 pub struct Deployments {
 	cache: Mutex<DeploymentStateCache>,
@@ -68,42 +94,85 @@ impl Deployments {
 		Deployments::default()
 	}
 
-	/// Returns true if csv deployment is active
-	pub fn csv(&self, number: u32, headers: &dyn BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
-		match consensus.csv_deployment {
-			Some(csv) => {
-				let mut cache = self.cache.lock();
-				threshold_state(
-					&mut cache,
-					csv,
-					number,
-					headers,
-					consensus.miner_confirmation_window,
-					consensus.rule_change_activation_threshold,
-				)
-				.is_active()
-			}
+	/// Returns true if deployment with given name is known and active
+	pub fn is_active(&self, name: &str, number: u32, headers: &dyn BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		match consensus.deployment(name) {
+			Some(deployment) => self.state(deployment, number, headers, consensus).is_active(),
 			None => false,
 		}
 	}
 
+	/// Returns threshold state of given deployment for block with given number
+	pub fn state(
+		&self,
+		deployment: &Deployment,
+		number: u32,
+		headers: &dyn BlockHeaderProvider,
+		consensus: &ConsensusParams,
+	) -> ThresholdState {
+		self.deployment_state(deployment, number, headers, consensus).state
+	}
+
+	/// Returns state of given deployment for block with given number, along with signalling statistics
+	pub fn info(
+		&self,
+		deployment: &Deployment,
+		number: u32,
+		headers: &dyn BlockHeaderProvider,
+		consensus: &ConsensusParams,
+	) -> DeploymentInfo {
+		let current = self.deployment_state(deployment, number, headers, consensus);
+		let next_state = self.state(deployment, number + 1, headers, consensus);
+		let statistics = match current.state {
+			ThresholdState::Started | ThresholdState::LockedIn => Some(deployment_statistics(
+				deployment,
+				number,
+				headers,
+				consensus.miner_confirmation_window,
+				consensus.rule_change_activation_threshold,
+			)),
+			ThresholdState::Defined | ThresholdState::Active | ThresholdState::Failed => None,
+		};
+
+		DeploymentInfo {
+			state: current.state,
+			since: current.since,
+			next_state,
+			statistics,
+		}
+	}
+
+	/// Returns true if csv deployment is active
+	pub fn csv(&self, number: u32, headers: &dyn BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		self.is_active(DEPLOYMENT_CSV, number, headers, consensus)
+	}
+
 	/// Returns true if SegWit deployment is active
 	pub fn segwit(&self, number: u32, headers: &dyn BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
-		match consensus.segwit_deployment {
-			Some(segwit) => {
-				let mut cache = self.cache.lock();
-				threshold_state(
-					&mut cache,
-					segwit,
-					number,
-					headers,
-					consensus.miner_confirmation_window,
-					consensus.rule_change_activation_threshold,
-				)
-				.is_active()
-			}
-			None => false,
-		}
+		self.is_active(DEPLOYMENT_SEGWIT, number, headers, consensus)
+	}
+
+	/// Returns true if Taproot deployment is active
+	pub fn taproot(&self, number: u32, headers: &dyn BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		self.is_active(DEPLOYMENT_TAPROOT, number, headers, consensus)
+	}
+
+	fn deployment_state(
+		&self,
+		deployment: &Deployment,
+		number: u32,
+		headers: &dyn BlockHeaderProvider,
+		consensus: &ConsensusParams,
+	) -> DeploymentState {
+		let mut cache = self.cache.lock();
+		deployment_state(
+			&mut cache,
+			*deployment,
+			number,
+			headers,
+			consensus.miner_confirmation_window,
+			consensus.rule_change_activation_threshold,
+		)
 	}
 }
 
@@ -117,12 +186,20 @@ impl<'a> BlockDeployments<'a> {
 		}
 	}
 
+	pub fn is_active(&self, name: &str) -> bool {
+		self.deployments.is_active(name, self.number, self.headers, self.consensus)
+	}
+
 	pub fn csv(&self) -> bool {
-		self.deployments.csv(self.number, self.headers, self.consensus)
+		self.is_active(DEPLOYMENT_CSV)
 	}
 
 	pub fn segwit(&self) -> bool {
-		self.deployments.segwit(self.number, self.headers, self.consensus)
+		self.is_active(DEPLOYMENT_SEGWIT)
+	}
+
+	pub fn taproot(&self) -> bool {
+		self.is_active(DEPLOYMENT_TAPROOT)
 	}
 }
 
@@ -139,6 +216,7 @@ impl<'a> AsRef<Deployments> for BlockDeployments<'a> {
 }
 
 /// Calculates threshold state of given deployment
+#[cfg(test)]
 fn threshold_state(
 	cache: &mut DeploymentStateCache,
 	deployment: Deployment,
@@ -147,13 +225,39 @@ fn threshold_state(
 	miner_confirmation_window: u32,
 	rule_change_activation_threshold: u32,
 ) -> ThresholdState {
+	deployment_state(
+		cache,
+		deployment,
+		number,
+		headers,
+		miner_confirmation_window,
+		rule_change_activation_threshold,
+	)
+	.state
+}
+
+/// Calculates state of given deployment
+fn deployment_state(
+	cache: &mut DeploymentStateCache,
+	deployment: Deployment,
+	number: u32,
+	headers: &dyn BlockHeaderProvider,
+	miner_confirmation_window: u32,
+	rule_change_activation_threshold: u32,
+) -> DeploymentState {
 	// deployments are checked using previous block index
 	if let Some(activation) = deployment.activation {
-		if activation <= number {
-			return ThresholdState::Active;
+		let (state, since) = if activation <= number {
+			(ThresholdState::Active, activation)
 		} else {
-			return ThresholdState::Defined;
-		}
+			(ThresholdState::Defined, 0)
+		};
+		return DeploymentState {
+			block_number: number,
+			block_hash: SHA256D::default(),
+			state,
+			since,
+		};
 	}
 
 	// number is number of block which is currently validating
@@ -166,50 +270,72 @@ fn threshold_state(
 
 	let hash = match headers.block_header(BlockRef::Number(number)) {
 		Some(header) => header.hash,
-		None => return ThresholdState::Defined,
+		None => return DeploymentState::default(),
 	};
 
-	match cache.entry(deployment.name) {
+	let (to_check, initial_state) = match cache.get(deployment.name) {
 		// by checking hash, we make sure we are on the same branch
-		Entry::Occupied(ref entry) if entry.get().block_number == number && entry.get().block_hash == hash => entry.get().state,
-		// otherwise we need to recalculate threshold state
-		Entry::Occupied(mut entry) => {
-			let deployment_state = entry.get().clone();
-			if deployment_state.state.is_final() {
-				return deployment_state.state;
+		Some(cached) if cached.block_number == number && cached.block_hash == hash => return cached.clone(),
+		// state of one of the previous periods of the same branch is known => resume from it
+		Some(cached) if cached.block_number < number && is_on_chain(cached, headers) => {
+			if cached.state.is_final() {
+				return cached.clone();
 			}
-			let threshold_state = deployment_state.state;
-			let deployment_iter = ThresholdIterator::new(
-				deployment,
-				headers,
-				number,
-				miner_confirmation_window,
-				rule_change_activation_threshold,
-				threshold_state,
-			);
-			let state = deployment_iter.last().expect("iter must have at least one item");
-			let result = state.state;
-			entry.insert(state);
-			result
-		}
-		Entry::Vacant(entry) => {
-			let deployment_iter = ThresholdIterator::new(
-				deployment,
-				headers,
-				miner_confirmation_window - 1,
-				miner_confirmation_window,
-				rule_change_activation_threshold,
-				ThresholdState::Defined,
-			);
-			let state = deployment_iter.last().unwrap_or_else(|| DeploymentState {
-				block_number: number,
-				block_hash: hash,
-				state: ThresholdState::Defined,
-			});
-			let result = state.state;
-			entry.insert(state);
-			result
+
+			let to_check = if cached.block_number < miner_confirmation_window - 1 {
+				miner_confirmation_window - 1
+			} else {
+				cached.block_number + miner_confirmation_window
+			};
+			(to_check, cached.clone())
 		}
+		// otherwise we need to recalculate threshold state from the very beginning
+		_ => (miner_confirmation_window - 1, DeploymentState::default()),
+	};
+
+	let deployment_iter = ThresholdIterator::new(
+		deployment,
+		headers,
+		to_check,
+		miner_confirmation_window,
+		rule_change_activation_threshold,
+		initial_state.state,
+		initial_state.since,
+	);
+	let state = deployment_iter
+		.take_while(|state| state.block_number <= number)
+		.last()
+		.unwrap_or(DeploymentState {
+			block_number: number,
+			block_hash: hash,
+			..initial_state
+		});
+	cache.insert(deployment.name, state.clone());
+	state
+}
+
+fn is_on_chain(state: &DeploymentState, headers: &dyn BlockHeaderProvider) -> bool {
+	headers
+		.block_header(BlockRef::Number(state.block_number))
+		.is_some_and(|header| header.hash == state.block_hash)
+}
+
+/// Calculates signalling statistics of the period, block with given number belongs to
+fn deployment_statistics(
+	deployment: &Deployment,
+	number: u32,
+	headers: &dyn BlockHeaderProvider,
+	miner_confirmation_window: u32,
+	rule_change_activation_threshold: u32,
+) -> DeploymentStatistics {
+	let elapsed = number % miner_confirmation_window + 1;
+	let count = count_deployment_matches(number, headers, *deployment, elapsed) as u32;
+	DeploymentStatistics {
+		period: miner_confirmation_window,
+		threshold: rule_change_activation_threshold,
+		elapsed,
+		count,
+		possible: miner_confirmation_window - rule_change_activation_threshold >= elapsed - count,
 	}
 }
 
@@ -235,6 +361,7 @@ struct ThresholdIterator<'a> {
 	miner_confirmation_window: u32,
 	rule_change_activation_threshold: u32,
 	last_state: ThresholdState,
+	since: u32,
 }
 
 impl<'a> ThresholdIterator<'a> {
@@ -245,6 +372,7 @@ impl<'a> ThresholdIterator<'a> {
 		miner_confirmation_window: u32,
 		rule_change_activation_threshold: u32,
 		state: ThresholdState,
+		since: u32,
 	) -> Self {
 		ThresholdIterator {
 			deployment,
@@ -253,6 +381,7 @@ impl<'a> ThresholdIterator<'a> {
 			miner_confirmation_window,
 			rule_change_activation_threshold,
 			last_state: state,
+			since,
 		}
 	}
 }
@@ -261,41 +390,54 @@ impl<'a> Iterator for ThresholdIterator<'a> {
 	type Item = DeploymentState;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let (block_number, header) = match self.block_iterator.next() {
-			Some(header) => header,
-			None => return None,
-		};
+		if self.last_state.is_final() {
+			return None;
+		}
+
+		let (block_number, header) = self.block_iterator.next()?;
 
 		let median = median_timestamp(&header.raw, self.headers);
+		let previous_state = self.last_state;
 
 		match self.last_state {
 			ThresholdState::Defined => {
-				if median >= self.deployment.timeout {
+				if median >= self.deployment.timeout && !self.deployment.lock_in_on_timeout {
 					self.last_state = ThresholdState::Failed;
 				} else if median >= self.deployment.start_time {
 					self.last_state = ThresholdState::Started;
 				}
 			}
 			ThresholdState::Started => {
-				if median >= self.deployment.timeout {
-					self.last_state = ThresholdState::Failed;
-				} else {
-					let count = count_deployment_matches(block_number, self.headers, self.deployment, self.miner_confirmation_window);
-					if count >= self.rule_change_activation_threshold as usize {
-						self.last_state = ThresholdState::LockedIn;
-					}
+				// threshold reached in the last period locks deployment in, even if timeout is also reached
+				let count = count_deployment_matches(block_number, self.headers, self.deployment, self.miner_confirmation_window);
+				if count >= self.rule_change_activation_threshold as usize {
+					self.last_state = ThresholdState::LockedIn;
+				} else if median >= self.deployment.timeout {
+					self.last_state = if self.deployment.lock_in_on_timeout {
+						ThresholdState::LockedIn
+					} else {
+						ThresholdState::Failed
+					};
 				}
 			}
 			ThresholdState::LockedIn => {
-				self.last_state = ThresholdState::Active;
+				// state is computed for the blocks of the next period
+				if block_number + 1 >= self.deployment.min_activation_height {
+					self.last_state = ThresholdState::Active;
+				}
 			}
-			ThresholdState::Failed | ThresholdState::Active => return None,
+			ThresholdState::Failed | ThresholdState::Active => unreachable!("final states are checked above; qed"),
+		}
+
+		if previous_state != self.last_state {
+			self.since = block_number + 1;
 		}
 
 		let result = DeploymentState {
 			block_number,
 			block_hash: header.hash,
 			state: self.last_state,
+			since: self.since,
 		};
 
 		Some(result)
@@ -304,10 +446,12 @@ impl<'a> Iterator for ThresholdIterator<'a> {
 
 #[cfg(test)]
 mod tests {
-	use super::{first_of_the_period, threshold_state, DeploymentStateCache, ThresholdState};
+	use super::{
+		first_of_the_period, threshold_state, DeploymentInfo, DeploymentStateCache, DeploymentStatistics, Deployments, ThresholdState,
+	};
 	use bitcrypto::SHA256D;
 	use chain::{BlockHeader, IndexedBlockHeader};
-	use network::Deployment;
+	use network::{ConsensusParams, Deployment, Network};
 	use primitives::bytes::Bytes;
 	use std::collections::HashMap;
 	use std::sync::atomic::{AtomicUsize, Ordering};
@@ -325,11 +469,7 @@ mod tests {
 
 	impl DeploymentHeaderProvider {
 		pub fn mine(&mut self, height: u32, time: u32, version: u32) {
-			let mut previous_header_hash = self
-				.by_height
-				.last()
-				.map(|h| h.previous_header_hash.clone())
-				.unwrap_or(SHA256D::default());
+			let mut previous_header_hash = self.by_height.last().map(|h| h.hash()).unwrap_or(SHA256D::default());
 			while self.by_height.len() < height as usize {
 				let header = BlockHeader {
 					version,
@@ -370,13 +510,7 @@ mod tests {
 		let (mut cache, headers, deployment) = (
 			DeploymentStateCache::default(),
 			DeploymentHeaderProvider::default(),
-			Deployment {
-				name: "test",
-				bit: 0,
-				start_time: make_test_time(10000),
-				timeout: make_test_time(20000),
-				activation: None,
-			},
+			Deployment::bip9("test", 0, make_test_time(10000), make_test_time(20000)),
 		);
 
 		assert_eq!(
@@ -506,19 +640,20 @@ mod tests {
 	}
 
 	// https://github.com/bitcoin/bitcoin/blob/a90e6d2bffc422ddcdb771c53aac0bceb970a2c4/src/test/versionbits_tests.cpp#L172
+	// since bitcoin/bitcoin#21377 threshold is checked before timeout, so the deployment is locked in
 	#[test]
-	fn test_threshold_state_defined_to_started_to_failed_when_threshold_reached() {
+	fn test_threshold_state_defined_to_started_to_lockedin_when_threshold_reached_at_timeout() {
 		let (mut cache, mut headers, deployment) = prepare_deployments();
 		let test_cases = vec![
 			(1, make_test_time(1), 0x20000000, ThresholdState::Defined),
 			(1000, make_test_time(10000) - 1, 0x20000001, ThresholdState::Defined),
 			(2000, make_test_time(10000), 0x20000001, ThresholdState::Started),
 			(2999, make_test_time(30000), 0x20000001, ThresholdState::Started),
-			(3000, make_test_time(30000), 0x20000001, ThresholdState::Failed),
-			(3999, make_test_time(30001), 0x20000000, ThresholdState::Failed),
-			(4000, make_test_time(30002), 0x20000000, ThresholdState::Failed),
-			(14333, make_test_time(30003), 0x20000000, ThresholdState::Failed),
-			(20000, make_test_time(40000), 0x20000000, ThresholdState::Failed),
+			(3000, make_test_time(30000), 0x20000001, ThresholdState::LockedIn),
+			(3999, make_test_time(30001), 0x20000000, ThresholdState::LockedIn),
+			(4000, make_test_time(30002), 0x20000000, ThresholdState::Active),
+			(14333, make_test_time(30003), 0x20000000, ThresholdState::Active),
+			(20000, make_test_time(40000), 0x20000000, ThresholdState::Active),
 		];
 
 		for (height, time, version, state) in test_cases {
@@ -647,4 +782,142 @@ mod tests {
 			);
 		}
 	}
+
+	fn check_threshold_states(deployment: Deployment, test_cases: Vec<(u32, u32, u32, ThresholdState)>) {
+		let (mut cache, mut headers, _) = prepare_deployments();
+		for (height, time, version, state) in test_cases {
+			headers.mine(height, time, version);
+
+			assert_eq!(
+				threshold_state(
+					&mut cache,
+					deployment,
+					height,
+					&headers,
+					MINER_CONFIRMATION_WINDOW,
+					RULE_CHANGE_ACTIVATION_THRESHOLD
+				),
+				state
+			);
+			assert_eq!(
+				threshold_state(
+					&mut DeploymentStateCache::default(),
+					deployment,
+					height,
+					&headers,
+					MINER_CONFIRMATION_WINDOW,
+					RULE_CHANGE_ACTIVATION_THRESHOLD
+				),
+				state
+			);
+		}
+	}
+
+	#[test]
+	fn test_threshold_state_lockedin_waits_for_min_activation_height() {
+		let (_, _, deployment) = prepare_deployments();
+		let deployment = deployment.with_min_activation_height(6500);
+		check_threshold_states(
+			deployment,
+			vec![
+				(1, make_test_time(1), 0x20000000, ThresholdState::Defined),
+				(1000, make_test_time(10000) - 1, 0x20000000, ThresholdState::Defined),
+				(2000, make_test_time(10000), 0x20000001, ThresholdState::Started),
+				(3000, make_test_time(10010), 0x20000001, ThresholdState::LockedIn),
+				(4000, make_test_time(10020), 0x20000000, ThresholdState::LockedIn),
+				(6500, make_test_time(10030), 0x20000000, ThresholdState::LockedIn),
+				(6999, make_test_time(10040), 0x20000000, ThresholdState::LockedIn),
+				(7000, make_test_time(10050), 0x20000000, ThresholdState::Active),
+				(8000, make_test_time(10060), 0x20000000, ThresholdState::Active),
+			],
+		);
+	}
+
+	#[test]
+	fn test_threshold_state_lock_in_on_timeout() {
+		let (_, _, deployment) = prepare_deployments();
+		let deployment = deployment.with_lock_in_on_timeout();
+		check_threshold_states(
+			deployment,
+			vec![
+				(1, make_test_time(1), 0x20000000, ThresholdState::Defined),
+				(1000, make_test_time(10000) - 1, 0x20000000, ThresholdState::Defined),
+				(2000, make_test_time(10000), 0x20000000, ThresholdState::Started),
+				(3000, make_test_time(20000), 0x20000000, ThresholdState::LockedIn),
+				(4000, make_test_time(20010), 0x20000000, ThresholdState::Active),
+				(5000, make_test_time(20020), 0x20000000, ThresholdState::Active),
+			],
+		);
+	}
+
+	#[test]
+	fn test_threshold_state_of_previous_block() {
+		let (mut cache, mut headers, deployment) = prepare_deployments();
+		headers.mine(1000, make_test_time(10000) - 1, 0x20000000);
+		headers.mine(2000, make_test_time(10000), 0x20000001);
+		headers.mine(3000, make_test_time(10010), 0x20000001);
+		headers.mine(4000, make_test_time(10020), 0x20000000);
+
+		let states = vec![
+			(4000, ThresholdState::Active),
+			(3000, ThresholdState::LockedIn),
+			(2000, ThresholdState::Started),
+			(1000, ThresholdState::Defined),
+		];
+		for (height, state) in states {
+			assert_eq!(
+				threshold_state(
+					&mut cache,
+					deployment,
+					height,
+					&headers,
+					MINER_CONFIRMATION_WINDOW,
+					RULE_CHANGE_ACTIVATION_THRESHOLD
+				),
+				state
+			);
+		}
+	}
+
+	#[test]
+	fn test_deployment_info() {
+		let (_, mut headers, deployment) = prepare_deployments();
+		let consensus = ConsensusParams {
+			miner_confirmation_window: MINER_CONFIRMATION_WINDOW,
+			rule_change_activation_threshold: RULE_CHANGE_ACTIVATION_THRESHOLD,
+			..ConsensusParams::new(Network::Unitest)
+		};
+		headers.mine(1000, make_test_time(10000) - 1, 0x20000000);
+		headers.mine(2000, make_test_time(10000), 0x20000000);
+		headers.mine(2300, make_test_time(10010), 0x20000001);
+		headers.mine(2500, make_test_time(10020), 0x20000000);
+
+		let deployments = Deployments::new();
+		assert_eq!(
+			deployments.info(&deployment, 2499, &headers, &consensus),
+			DeploymentInfo {
+				state: ThresholdState::Started,
+				since: 2000,
+				next_state: ThresholdState::Started,
+				statistics: Some(DeploymentStatistics {
+					period: 1000,
+					threshold: 900,
+					elapsed: 500,
+					count: 300,
+					possible: false,
+				}),
+			}
+		);
+
+		let buried = Deployment::bip9("buried", 1, 0, 0).buried(100);
+		assert_eq!(
+			deployments.info(&buried, 2499, &headers, &consensus),
+			DeploymentInfo {
+				state: ThresholdState::Active,
+				since: 100,
+				next_state: ThresholdState::Active,
+				statistics: None,
+			}
+		);
+	}
 }
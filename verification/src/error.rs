@@ -64,6 +64,40 @@ pub enum Error {
 	Database(DBError),
 }
 
+impl Error {
+	/// Returns reason of the block rejection, as reported by `submitblock` RPC (BIP22)
+	pub fn reject_reason(&self) -> &'static str {
+		match *self {
+			Error::Duplicate => "duplicate",
+			Error::DuplicatedTransactions => "bad-txns-duplicate",
+			Error::Empty | Error::Size(_) => "bad-blk-length",
+			Error::Pow => "high-hash",
+			Error::FuturisticTimestamp => "time-too-new",
+			Error::Timestamp => "time-too-old",
+			Error::Coinbase => "bad-cb-missing",
+			Error::Transaction(_, ref err) => err.reject_reason(),
+			Error::Difficulty { .. } => "bad-diffbits",
+			Error::MerkleRoot => "bad-txnmrklroot",
+			Error::CoinbaseOverspend { .. } => "bad-cb-amount",
+			Error::CoinbaseScript => "bad-cb-height",
+			Error::MaximumSigops | Error::MaximumSigopsCost => "bad-blk-sigops",
+			Error::CoinbaseSignatureLength(_) => "bad-cb-length",
+			Error::Weight => "bad-blk-weight",
+			Error::NonFinalBlock => "bad-txns-nonfinal",
+			Error::OldVersionBlock => "bad-version",
+			Error::TransactionFeeAndRewardOverflow | Error::TransactionFeesOverflow | Error::ReferencedInputsSumOverflow => {
+				"bad-txns-accumulated-fee-outofrange"
+			}
+			Error::WitnessInvalidNonceSize => "bad-witness-nonce-size",
+			Error::WitnessMerkleCommitmentMismatch => "bad-witness-merkle-match",
+			Error::UnexpectedWitness => "unexpected-witness",
+			Error::NonCanonicalTransactionOrdering => "bad-txns-ordering",
+			Error::Database(DBError::UnknownParent) => "prev-blk-not-found",
+			Error::Database(_) => "inconclusive",
+		}
+	}
+}
+
 impl Display for Error {
 	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
 		write!(f, "{:?}", self)
@@ -119,4 +153,62 @@ pub enum TransactionError {
 	ReturnReplayProtection,
 	/// Transaction with witness is received before SegWit is activated.
 	PrematureWitness,
+	/// Transaction conflicts with memory pool transactions and can't replace them (BIP125).
+	Replacement(ReplacementError),
+	/// Transaction can't be accepted to the memory pool, because it would exceed memory pool limits.
+	MemoryPoolLimit(MemoryPoolLimitError),
+}
+
+impl TransactionError {
+	/// Returns reason of the block rejection, caused by this transaction error (BIP22)
+	pub fn reject_reason(&self) -> &'static str {
+		match *self {
+			TransactionError::Empty => "bad-txns-empty",
+			TransactionError::NullNonCoinbase => "bad-txns-prevout-null",
+			TransactionError::CoinbaseSignatureLength(_) => "bad-cb-length",
+			TransactionError::MaxSize | TransactionError::MinSize => "bad-txns-size",
+			TransactionError::MaxSigops | TransactionError::Sigops(_) | TransactionError::SigopsP2SH(_) => "bad-blk-sigops",
+			TransactionError::Maturity => "bad-txns-premature-spend-of-coinbase",
+			TransactionError::Signature(_, _) | TransactionError::SignatureMallformed(_) => "mandatory-script-verify-flag-failed",
+			TransactionError::Input(_) | TransactionError::UnknownReference(_) | TransactionError::UsingSpentOutput(_, _) => {
+				"bad-txns-inputs-missingorspent"
+			}
+			TransactionError::Overspend => "bad-txns-in-belowout",
+			TransactionError::MisplacedCoinbase => "bad-cb-multiple",
+			TransactionError::UnspentTransactionWithTheSameHash => "bad-txns-BIP30",
+			TransactionError::PrematureWitness => "unexpected-witness",
+			TransactionError::MemoryPoolCoinbase
+			| TransactionError::ReturnReplayProtection
+			| TransactionError::Replacement(_)
+			| TransactionError::MemoryPoolLimit(_) => "rejected",
+		}
+	}
+}
+
+#[derive(Debug, PartialEq)]
+/// Reasons why transaction can't replace conflicting memory pool transactions (BIP125)
+pub enum ReplacementError {
+	/// Replacement would evict too many memory pool transactions
+	TooManyReplacements(usize),
+	/// Replacement spends output of unconfirmed transaction, which isn't spent by replaced transactions
+	NewUnconfirmedInput(SHA256D, u32),
+	/// Replacement pays less fee than required to replace conflicting transactions (paid fee, required fee)
+	InsufficientFee(u64, u64),
+	/// Replacement fee rate isn't higher than fee rate of the conflicting transaction
+	InsufficientFeeRate(SHA256D),
+}
+
+#[derive(Debug, PartialEq)]
+/// Memory pool limits, which transaction violates
+pub enum MemoryPoolLimitError {
+	/// Transaction pays less than current minimal fee of the memory pool (paid fee, required fee)
+	InsufficientFee(u64, u64),
+	/// Transaction has too many in-pool ancestors (number of ancestors, including transaction itself)
+	TooManyAncestors(usize),
+	/// Transaction and its in-pool ancestors are too large (total size)
+	TooLargeAncestors(usize),
+	/// In-pool ancestor of transaction would have too many in-pool descendants
+	TooManyDescendants(SHA256D),
+	/// In-pool ancestor of transaction would have too large in-pool descendants
+	TooLargeDescendants(SHA256D),
 }
@@ -104,9 +104,9 @@ pub use verify_header::HeaderVerifier;
 pub use verify_transaction::{MemoryPoolTransactionVerifier, TransactionVerifier};
 
 pub use chain_verifier::BackwardsCompatibleChainVerifier;
-pub use deployments::Deployments;
-pub use error::{Error, TransactionError};
-pub use sigops::transaction_sigops;
+pub use deployments::{DeploymentInfo, DeploymentStatistics, Deployments, ThresholdState};
+pub use error::{Error, MemoryPoolLimitError, ReplacementError, TransactionError};
+pub use sigops::{transaction_sigops, transaction_sigops_cost};
 pub use timestamp::{median_timestamp, median_timestamp_inclusive};
 pub use verification_level::VerificationLevel;
 pub use work::{block_reward_satoshi, is_valid_proof_of_work, is_valid_proof_of_work_hash, work_required};
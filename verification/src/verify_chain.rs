@@ -29,6 +29,14 @@ impl<'a> ChainVerifier<'a> {
 		Ok(())
 	}
 
+	/// Same as `check`, but doesn't check proof-of-work of the block header
+	pub fn check_without_pow(&self) -> Result<(), Error> {
+		self.block.check()?;
+		self.header.check_without_pow()?;
+		self.check_transactions()?;
+		Ok(())
+	}
+
 	fn check_transactions(&self) -> Result<(), Error> {
 		self.transactions
 			.par_iter()
@@ -23,6 +23,11 @@ impl<'a> HeaderVerifier<'a> {
 		self.timestamp.check()?;
 		Ok(())
 	}
+
+	/// Same as `check`, but doesn't check proof-of-work of the header
+	pub fn check_without_pow(&self) -> Result<(), Error> {
+		self.timestamp.check()
+	}
 }
 
 pub struct HeaderProofOfWork<'a> {
@@ -0,0 +1,163 @@
+//! Largest-first coin selection
+
+use crate::error::Error;
+
+/// Outputs below this value are not created
+pub const DUST_THRESHOLD: u64 = 546;
+
+/// Spendable output of the wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+	pub value: u64,
+	/// Weight of the input, spending this output
+	pub input_weight: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+	/// Indices of the selected candidates
+	pub inputs: Vec<usize>,
+	/// Amount, paid to the recipient
+	pub amount: u64,
+	/// Change amount, or None if change is too small to be created
+	pub change: Option<u64>,
+	pub fee: u64,
+}
+
+/// Returns fee (in satoshis) of the transaction with given weight
+pub fn fee_for_weight(weight: usize, fee_rate: u64) -> u64 {
+	let vsize = (weight as u64 + 3) / 4;
+	(vsize * fee_rate + 999) / 1000
+}
+
+/// Selects candidates, paying `amount` to the recipient.
+///
+/// `fixed_weight` is the weight of the transaction without inputs and change output, `change_weight` is the
+/// weight of the change output and `fee_rate` is in satoshis per 1000 virtual bytes. If `subtract_fee` is true,
+/// fee is paid by the recipient.
+pub fn select_coins(
+	candidates: &[Candidate],
+	amount: u64,
+	fixed_weight: usize,
+	change_weight: usize,
+	fee_rate: u64,
+	subtract_fee: bool,
+) -> Result<Selection, Error> {
+	if amount < DUST_THRESHOLD {
+		return Err(Error::AmountTooSmall);
+	}
+
+	let mut order: Vec<usize> = (0..candidates.len()).collect();
+	order.sort_by(|a, b| candidates[*b].value.cmp(&candidates[*a].value));
+
+	let mut inputs = Vec::new();
+	let mut total = 0u64;
+	let mut weight = fixed_weight;
+	for index in order {
+		inputs.push(index);
+		total += candidates[index].value;
+		weight += candidates[index].input_weight;
+
+		let fee = fee_for_weight(weight, fee_rate);
+		let required = if subtract_fee { amount } else { amount + fee };
+		if total < required {
+			continue;
+		}
+
+		let fee_with_change = fee_for_weight(weight + change_weight, fee_rate);
+		let (amount, change, fee) = match subtract_fee {
+			true => match total - amount {
+				change if change >= DUST_THRESHOLD => (amount.saturating_sub(fee_with_change), Some(change), fee_with_change),
+				excess => (amount.saturating_sub(fee.saturating_sub(excess)), None, fee.max(excess)),
+			},
+			false => match (total - amount).saturating_sub(fee_with_change) {
+				change if change >= DUST_THRESHOLD => (amount, Some(change), fee_with_change),
+				_ => (amount, None, total - amount),
+			},
+		};
+		if amount < DUST_THRESHOLD {
+			return Err(Error::AmountTooSmall);
+		}
+
+		return Ok(Selection {
+			inputs,
+			amount,
+			change,
+			fee,
+		});
+	}
+
+	Err(Error::InsufficientFunds)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{fee_for_weight, select_coins, Candidate, Selection};
+	use crate::error::Error;
+
+	fn candidates(values: &[u64]) -> Vec<Candidate> {
+		values
+			.iter()
+			.map(|value| Candidate {
+				value: *value,
+				input_weight: 272,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn coin_selection_prefers_largest_outputs() {
+		assert_eq!(fee_for_weight(400, 1000), 100);
+		assert_eq!(fee_for_weight(401, 1000), 101);
+
+		let candidates = candidates(&[10_000, 50_000, 20_000]);
+		// 400 + 2 * 272 = 944 WU => 236 vB, change adds 31 vB
+		assert_eq!(
+			select_coins(&candidates, 60_000, 400, 124, 1000, false),
+			Ok(Selection {
+				inputs: vec![1, 2],
+				amount: 60_000,
+				change: Some(9_733),
+				fee: 267,
+			})
+		);
+		// change is dust => added to fee
+		assert_eq!(
+			select_coins(&candidates, 69_500, 400, 124, 1000, false),
+			Ok(Selection {
+				inputs: vec![1, 2],
+				amount: 69_500,
+				change: None,
+				fee: 500,
+			})
+		);
+		assert_eq!(
+			select_coins(&candidates, 79_900, 400, 124, 1000, false),
+			Err(Error::InsufficientFunds)
+		);
+		assert_eq!(select_coins(&candidates, 500, 400, 124, 1000, false), Err(Error::AmountTooSmall));
+	}
+
+	#[test]
+	fn coin_selection_subtracts_fee_from_amount() {
+		let candidates = candidates(&[10_000, 50_000, 20_000]);
+		assert_eq!(
+			select_coins(&candidates, 80_000, 400, 124, 1000, true),
+			Ok(Selection {
+				inputs: vec![1, 2, 0],
+				amount: 79_696,
+				change: None,
+				fee: 304,
+			})
+		);
+		assert_eq!(
+			select_coins(&candidates, 60_000, 400, 124, 1000, true),
+			Ok(Selection {
+				inputs: vec![1, 2],
+				amount: 59_733,
+				change: Some(10_000),
+				fee: 267,
+			})
+		);
+	}
+}
@@ -0,0 +1,460 @@
+//! Output script descriptors (BIP380)
+//!
+//! Only descriptors of single (non-ranged) keys are supported: `pkh(KEY)`, `wpkh(KEY)`, `sh(wpkh(KEY))`,
+//! `wsh(multi(k,KEY,...))` and key path only `tr(KEY)`. KEY is either hex encoded public key or WIF encoded
+//! private key.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki
+
+use crate::error::Error;
+use bitcrypto::{dhash160, sha256, tagged_hash, FromHex, Hash, ToHex, SHA256};
+use keys::{Address, AddressPayload, KeyPair, Network, Private, Type, XOnlyPublic};
+use primitives::bytes::Bytes;
+use script::{Builder, Opcode, Script};
+use ser::{CompactInteger, Serializable};
+use std::fmt;
+use std::str::FromStr;
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_LENGTH: usize = 8;
+/// Maximal number of keys in `multi()` expression
+const MAX_MULTISIG_KEYS: usize = 16;
+/// Maximal size of DER encoded signature with sighash type
+const MAX_SIGNATURE_SIZE: usize = 73;
+/// Size of BIP340 signature with default sighash type
+const SCHNORR_SIGNATURE_SIZE: usize = 64;
+/// Weight of outpoint, sequence and empty script length of the transaction input
+const BASE_INPUT_WEIGHT: usize = (32 + 4 + 4 + 1) * 4;
+
+/// Public key of the descriptor: compressed, uncompressed or x-only
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DescriptorKey(Bytes);
+
+impl DescriptorKey {
+	/// Returns public key of the private key. Taproot descriptors are using x-only keys
+	pub fn from_private(private: &Private, xonly: bool) -> Result<Self, Error> {
+		if xonly {
+			return Ok(DescriptorKey(private.xonly_public()?.to_vec().into()));
+		}
+
+		let keypair = KeyPair::from_private(Private {
+			network: private.network,
+			secret: private.secret,
+			compressed: private.compressed,
+		})?;
+		Ok(DescriptorKey(keypair.public().to_vec().into()))
+	}
+
+	fn from_hex(hex: &str) -> Result<Self, Error> {
+		let bytes: Vec<u8> = FromHex::from_hex(hex).map_err(|_| Error::InvalidDescriptor(format!("invalid key '{}'", hex)))?;
+		match (bytes.len(), bytes.first()) {
+			(32, _) | (33, Some(2)) | (33, Some(3)) | (65, Some(4)) => Ok(DescriptorKey(bytes.into())),
+			_ => Err(Error::InvalidDescriptor(format!("invalid key '{}'", hex))),
+		}
+	}
+
+	/// Returns serialized public key
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	pub fn is_compressed(&self) -> bool {
+		self.0.len() == 33
+	}
+
+	pub fn is_xonly(&self) -> bool {
+		self.0.len() == 32
+	}
+
+	/// Returns x-only public key, dropping parity of the compressed key
+	pub fn xonly(&self) -> Result<XOnlyPublic, Error> {
+		match self.0.len() {
+			32 => Ok(XOnlyPublic::from_slice(&self.0)?),
+			33 => Ok(XOnlyPublic::from_slice(&self.0[1..])?),
+			_ => Err(Error::Key(keys::Error::InvalidPublic)),
+		}
+	}
+}
+
+impl fmt::Display for DescriptorKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.to_hex().fmt(f)
+	}
+}
+
+/// Output script descriptor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Descriptor {
+	/// `pkh(KEY)`: P2PKH output
+	Pkh(DescriptorKey),
+	/// `wpkh(KEY)`: P2WPKH output
+	Wpkh(DescriptorKey),
+	/// `sh(wpkh(KEY))`: P2WPKH output, nested into P2SH
+	ShWpkh(DescriptorKey),
+	/// `wsh(multi(k,KEY,...))`: k-of-n multisig P2WSH output
+	WshMulti(usize, Vec<DescriptorKey>),
+	/// `tr(KEY)`: P2TR output without script tree
+	Tr(DescriptorKey),
+}
+
+impl Descriptor {
+	/// Parses descriptor, returning also private keys of the descriptor
+	pub fn parse(s: &str) -> Result<(Descriptor, Vec<(DescriptorKey, Private)>), Error> {
+		let s = match s.find('#') {
+			Some(position) => {
+				let (descriptor, checksum) = (&s[..position], &s[position + 1..]);
+				if descriptor_checksum(descriptor).as_deref() != Some(checksum) {
+					return Err(Error::InvalidChecksum);
+				}
+				descriptor
+			}
+			None => s,
+		};
+
+		let mut secrets = Vec::new();
+		let descriptor = if let Some(inner) = function_arguments(s, "pkh") {
+			Descriptor::Pkh(parse_key(inner, false, &mut secrets)?)
+		} else if let Some(inner) = function_arguments(s, "wpkh") {
+			Descriptor::Wpkh(parse_segwit_key(inner, &mut secrets)?)
+		} else if let Some(inner) = function_arguments(s, "sh").and_then(|inner| function_arguments(inner, "wpkh")) {
+			Descriptor::ShWpkh(parse_segwit_key(inner, &mut secrets)?)
+		} else if let Some(inner) = function_arguments(s, "wsh").and_then(|inner| function_arguments(inner, "multi")) {
+			let mut arguments = inner.split(',');
+			let threshold: usize = arguments
+				.next()
+				.and_then(|threshold| threshold.parse().ok())
+				.ok_or_else(|| Error::InvalidDescriptor("invalid multisig threshold".into()))?;
+			let keys = arguments
+				.map(|key| parse_segwit_key(key, &mut secrets))
+				.collect::<Result<Vec<_>, _>>()?;
+			if threshold == 0 || threshold > keys.len() || keys.len() > MAX_MULTISIG_KEYS {
+				return Err(Error::InvalidDescriptor(format!(
+					"multisig threshold {} of {} keys is not supported",
+					threshold,
+					keys.len()
+				)));
+			}
+			Descriptor::WshMulti(threshold, keys)
+		} else if let Some(inner) = function_arguments(s, "tr") {
+			if inner.contains(',') {
+				return Err(Error::InvalidDescriptor("taproot script trees are not supported".into()));
+			}
+			let key = parse_key(inner, true, &mut secrets)?;
+			key.xonly()?;
+			Descriptor::Tr(key)
+		} else {
+			return Err(Error::InvalidDescriptor(format!("unsupported descriptor '{}'", s)));
+		};
+
+		Ok((descriptor, secrets))
+	}
+
+	/// Returns all keys of the descriptor
+	pub fn keys(&self) -> Vec<&DescriptorKey> {
+		match *self {
+			Descriptor::Pkh(ref key) | Descriptor::Wpkh(ref key) | Descriptor::ShWpkh(ref key) | Descriptor::Tr(ref key) => vec![key],
+			Descriptor::WshMulti(_, ref keys) => keys.iter().collect(),
+		}
+	}
+
+	/// Returns address of the descriptor output
+	pub fn address(&self, network: Network) -> Address {
+		let (kind, hash) = match *self {
+			Descriptor::Pkh(ref key) => (Type::P2PKH, dhash160(key.as_bytes()).into()),
+			Descriptor::Wpkh(ref key) => (Type::P2WPKH, dhash160(key.as_bytes()).into()),
+			Descriptor::ShWpkh(_) => (
+				Type::P2SH,
+				dhash160(&self.redeem_script().expect("sh descriptor has redeem script; qed")).into(),
+			),
+			Descriptor::WshMulti(..) => (
+				Type::P2WSH,
+				sha256(&self.witness_script().expect("wsh descriptor has witness script; qed")).into(),
+			),
+			Descriptor::Tr(ref key) => {
+				let output_key = taproot_output_key(key).expect("taproot keys are validated when descriptor is parsed; qed");
+				(
+					Type::P2TR,
+					AddressPayload::Program(SHA256::from_slice(&output_key).expect("x-only key is 32 bytes long; qed")),
+				)
+			}
+		};
+
+		Address { kind, network, hash }
+	}
+
+	/// Returns script of the descriptor output
+	pub fn script_pubkey(&self) -> Script {
+		// network doesn't affect the output script
+		Builder::build_address(&self.address(Network::Mainnet))
+	}
+
+	/// Returns P2SH redeem script of `sh()` descriptor
+	pub fn redeem_script(&self) -> Option<Script> {
+		match *self {
+			Descriptor::ShWpkh(ref key) => Some(Builder::build_p2wpkh(&dhash160(key.as_bytes()))),
+			_ => None,
+		}
+	}
+
+	/// Returns P2WSH witness script of `wsh()` descriptor
+	pub fn witness_script(&self) -> Option<Script> {
+		match *self {
+			Descriptor::WshMulti(threshold, ref keys) => {
+				let builder = keys
+					.iter()
+					.fold(Builder::default().push_opcode(small_number(threshold)), |builder, key| {
+						builder.push_data(key.as_bytes())
+					});
+				Some(
+					builder
+						.push_opcode(small_number(keys.len()))
+						.push_opcode(Opcode::OP_CHECKMULTISIG)
+						.into_script(),
+				)
+			}
+			_ => None,
+		}
+	}
+
+	/// Returns maximal weight of the transaction input, spending the descriptor output
+	pub fn max_input_weight(&self) -> usize {
+		match *self {
+			Descriptor::Pkh(ref key) => {
+				let script_sig_size = 1 + MAX_SIGNATURE_SIZE + 1 + key.as_bytes().len();
+				BASE_INPUT_WEIGHT + (script_sig_size + compact_size(script_sig_size) - 1) * 4
+			}
+			Descriptor::Wpkh(_) => BASE_INPUT_WEIGHT + witness_weight(&[MAX_SIGNATURE_SIZE, 33]),
+			Descriptor::ShWpkh(_) => BASE_INPUT_WEIGHT + 23 * 4 + witness_weight(&[MAX_SIGNATURE_SIZE, 33]),
+			Descriptor::WshMulti(threshold, ref keys) => {
+				let witness_script_size = 1 + keys.len() * 34 + 1 + 1;
+				let mut items = vec![0];
+				items.extend(std::iter::repeat(MAX_SIGNATURE_SIZE).take(threshold));
+				items.push(witness_script_size);
+				BASE_INPUT_WEIGHT + witness_weight(&items)
+			}
+			Descriptor::Tr(_) => BASE_INPUT_WEIGHT + witness_weight(&[SCHNORR_SIGNATURE_SIZE]),
+		}
+	}
+
+	/// Returns true if the descriptor output is spent with witness
+	pub fn is_segwit(&self) -> bool {
+		match *self {
+			Descriptor::Pkh(_) => false,
+			_ => true,
+		}
+	}
+
+	fn to_string_without_checksum(&self) -> String {
+		match *self {
+			Descriptor::Pkh(ref key) => format!("pkh({})", key),
+			Descriptor::Wpkh(ref key) => format!("wpkh({})", key),
+			Descriptor::ShWpkh(ref key) => format!("sh(wpkh({}))", key),
+			Descriptor::WshMulti(threshold, ref keys) => format!(
+				"wsh(multi({},{}))",
+				threshold,
+				keys.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+			),
+			Descriptor::Tr(ref key) => format!("tr({})", key),
+		}
+	}
+}
+
+impl fmt::Display for Descriptor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let descriptor = self.to_string_without_checksum();
+		let checksum = descriptor_checksum(&descriptor).expect("descriptor only consists of checksum input characters; qed");
+		write!(f, "{}#{}", descriptor, checksum)
+	}
+}
+
+impl FromStr for Descriptor {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Descriptor::parse(s).map(|(descriptor, _)| descriptor)
+	}
+}
+
+/// Returns output key of the taproot descriptor without script tree (BIP86)
+pub fn taproot_output_key(internal_key: &DescriptorKey) -> Result<XOnlyPublic, Error> {
+	let internal_key = internal_key.xonly()?;
+	let (output_key, _) = internal_key.tweak_add(&taproot_tweak(&internal_key))?;
+	Ok(output_key)
+}
+
+/// Returns tweak of the internal key without script tree
+pub fn taproot_tweak(internal_key: &XOnlyPublic) -> SHA256 {
+	tagged_hash(b"TapTweak", &**internal_key)
+}
+
+/// Computes descriptor checksum. Returns None if descriptor has unsupported characters
+pub fn descriptor_checksum(descriptor: &str) -> Option<String> {
+	let mut checksum = 1u64;
+	let mut class = 0u64;
+	let mut class_count = 0;
+	for c in descriptor.chars() {
+		let position = INPUT_CHARSET.find(c)? as u64;
+		checksum = polymod(checksum, position & 31);
+		class = class * 3 + (position >> 5);
+		class_count += 1;
+		if class_count == 3 {
+			checksum = polymod(checksum, class);
+			class = 0;
+			class_count = 0;
+		}
+	}
+	if class_count > 0 {
+		checksum = polymod(checksum, class);
+	}
+	for _ in 0..CHECKSUM_LENGTH {
+		checksum = polymod(checksum, 0);
+	}
+	checksum ^= 1;
+
+	Some(
+		(0..CHECKSUM_LENGTH)
+			.map(|i| CHECKSUM_CHARSET[((checksum >> (5 * (CHECKSUM_LENGTH - 1 - i))) & 31) as usize] as char)
+			.collect(),
+	)
+}
+
+fn polymod(checksum: u64, value: u64) -> u64 {
+	const GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+
+	let top = checksum >> 35;
+	let mut checksum = ((checksum & 0x7ffffffff) << 5) ^ value;
+	for (i, generator) in GENERATOR.iter().enumerate() {
+		if (top >> i) & 1 == 1 {
+			checksum ^= generator;
+		}
+	}
+	checksum
+}
+
+/// Returns OP_1..OP_16 opcode of the number
+fn small_number(n: usize) -> Opcode {
+	Opcode::from_u8(Opcode::OP_1 as u8 + n as u8 - 1).expect("multisig numbers are within [1; 16] interval; qed")
+}
+
+/// Returns arguments of `name(arguments)` expression
+fn function_arguments<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+	if s.len() > name.len() + 1 && s.starts_with(name) && s[name.len()..].starts_with('(') && s.ends_with(')') {
+		Some(&s[name.len() + 1..s.len() - 1])
+	} else {
+		None
+	}
+}
+
+fn parse_key(s: &str, xonly: bool, secrets: &mut Vec<(DescriptorKey, Private)>) -> Result<DescriptorKey, Error> {
+	if s.len() == 64 || s.len() == 66 || s.len() == 130 {
+		let key = DescriptorKey::from_hex(s)?;
+		if key.is_xonly() != xonly && !(xonly && key.is_compressed()) {
+			return Err(Error::InvalidDescriptor(format!("key '{}' is not allowed in this context", s)));
+		}
+		return Ok(key);
+	}
+
+	let private: Private = s.parse().map_err(|_| Error::InvalidDescriptor(format!("invalid key '{}'", s)))?;
+	let key = DescriptorKey::from_private(&private, xonly)?;
+	secrets.push((key.clone(), private));
+	Ok(key)
+}
+
+fn parse_segwit_key(s: &str, secrets: &mut Vec<(DescriptorKey, Private)>) -> Result<DescriptorKey, Error> {
+	let key = parse_key(s, false, secrets)?;
+	if !key.is_compressed() {
+		return Err(Error::InvalidDescriptor(format!(
+			"segwit descriptors only allow compressed keys, got '{}'",
+			s
+		)));
+	}
+	Ok(key)
+}
+
+fn compact_size(value: usize) -> usize {
+	CompactInteger::from(value).serialized_size()
+}
+
+/// Returns weight of the witness, containing items of given sizes
+fn witness_weight(items: &[usize]) -> usize {
+	compact_size(items.len()) + items.iter().map(|size| compact_size(*size) + size).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{descriptor_checksum, Descriptor, DescriptorKey};
+	use crate::error::Error;
+	use bitcrypto::ToHex;
+	use keys::{Network, Private};
+
+	#[test]
+	fn descriptor_checksum_matches_bip380() {
+		assert_eq!(descriptor_checksum("raw(deadbeef)"), Some("89f8spxm".into()));
+		assert_eq!(descriptor_checksum("raw(deadbeef)\u{1}"), None);
+	}
+
+	#[test]
+	fn descriptor_parse_and_display() {
+		let descriptor: Descriptor = "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)#8fhd9pwu"
+			.parse()
+			.unwrap();
+		assert_eq!(
+			descriptor.to_string(),
+			"pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)#8fhd9pwu"
+		);
+		assert_eq!(
+			"pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)#8fhd9pwx".parse::<Descriptor>(),
+			Err(Error::InvalidChecksum)
+		);
+		assert!("wpkh(04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235)"
+			.parse::<Descriptor>()
+			.is_err());
+		assert!("wsh(multi(3,03a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7,03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb))"
+			.parse::<Descriptor>()
+			.is_err());
+		assert!("sh(multi(1,03a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7))"
+			.parse::<Descriptor>()
+			.is_err());
+	}
+
+	#[test]
+	fn descriptor_script_pubkey() {
+		let script_pubkey = |descriptor: &str| descriptor.parse::<Descriptor>().unwrap().script_pubkey().to_bytes().to_hex();
+		assert_eq!(
+			script_pubkey("pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)"),
+			"76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac"
+		);
+		assert_eq!(
+			script_pubkey("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"),
+			"00147dd65592d0ab2fe0d0257d571abf032cd9db93dc"
+		);
+		assert_eq!(
+			script_pubkey("sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))"),
+			"a914cc6ffbc0bf31af759451068f90ba7a0272b6b33287"
+		);
+		assert_eq!(
+			script_pubkey("wsh(multi(1,03a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7,03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb))"),
+			"0020e558b18e77e6ad826c69f259a55d560c4470e23533327e151ceb1396ba790d40"
+		);
+		assert_eq!(
+			script_pubkey("tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)"),
+			"512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11"
+		);
+	}
+
+	#[test]
+	fn descriptor_with_private_keys() {
+		let private = Private {
+			network: Network::Testnet,
+			secret: [1u8; 32],
+			compressed: true,
+		};
+		let (descriptor, secrets) = Descriptor::parse(&format!("tr({})", private)).unwrap();
+		let key = DescriptorKey::from_private(&private, true).unwrap();
+		assert_eq!(descriptor, Descriptor::Tr(key.clone()));
+		assert_eq!(secrets, vec![(key, private)]);
+		// private keys are never displayed
+		assert!(descriptor.to_string().starts_with("tr(") && descriptor.to_string().len() == 3 + 64 + 1 + 9);
+	}
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// Descriptor can't be parsed
+	InvalidDescriptor(String),
+	/// Descriptor checksum doesn't match the descriptor
+	InvalidChecksum,
+	/// Address type is unknown
+	InvalidAddressType(String),
+	/// Address belongs to another network
+	InvalidNetwork,
+	/// Key error
+	Key(keys::Error),
+	/// Wallet file can't be read or written
+	Io(String),
+	/// Wallet file is corrupted
+	Corrupted(String),
+	/// Wallet is encrypted and needs to be unlocked with the passphrase
+	Locked,
+	/// Wallet is already encrypted
+	AlreadyEncrypted,
+	/// Wallet is not encrypted
+	NotEncrypted,
+	/// Passphrase doesn't match the wallet passphrase
+	IncorrectPassphrase,
+	/// Private key of the spent output is unknown
+	MissingKey,
+	/// Amount is below the dust threshold
+	AmountTooSmall,
+	/// Wallet has not enough spendable funds
+	InsufficientFunds,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::InvalidDescriptor(ref details) => write!(f, "Invalid descriptor: {}", details),
+			Error::InvalidChecksum => "Invalid descriptor checksum".fmt(f),
+			Error::InvalidAddressType(ref address_type) => write!(f, "Unknown address type '{}'", address_type),
+			Error::InvalidNetwork => "Address belongs to another network".fmt(f),
+			Error::Key(ref err) => err.fmt(f),
+			Error::Io(ref details) => write!(f, "Wallet file error: {}", details),
+			Error::Corrupted(ref details) => write!(f, "Wallet file is corrupted: {}", details),
+			Error::Locked => "Please enter the wallet passphrase with walletpassphrase first".fmt(f),
+			Error::AlreadyEncrypted => "Wallet is already encrypted".fmt(f),
+			Error::NotEncrypted => "Wallet is not encrypted".fmt(f),
+			Error::IncorrectPassphrase => "The wallet passphrase entered was incorrect".fmt(f),
+			Error::MissingKey => "Private key of the spent output is unknown".fmt(f),
+			Error::AmountTooSmall => "Transaction amount too small".fmt(f),
+			Error::InsufficientFunds => "Insufficient funds".fmt(f),
+		}
+	}
+}
+
+impl From<keys::Error> for Error {
+	fn from(err: keys::Error) -> Self {
+		Error::Key(err)
+	}
+}
@@ -0,0 +1,301 @@
+//! Private keys of the wallet, optionally encrypted with the passphrase.
+//!
+//! Encryption key is derived from the passphrase with PBKDF2-HMAC-SHA512. Every secret is encrypted with
+//! ChaCha20-Poly1305 under its own random nonce, with the public key as associated data. Secrets that fail
+//! authentication are rejected, and decrypted secrets are verified against their public keys.
+
+use crate::descriptor::DescriptorKey;
+use crate::error::Error;
+use bitcrypto::{pbkdf2_hmac_sha512, sha256, SHA256};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use keys::{Network, Private, Secret};
+use primitives::bytes::Bytes;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Number of PBKDF2 iterations, deriving the encryption key from the passphrase
+pub const KEY_DERIVATION_ITERATIONS: u32 = 25_000;
+/// Size of the random key derivation salt
+const SALT_SIZE: usize = 16;
+/// Size of the plain secret
+const SECRET_SIZE: usize = 32;
+/// Size of the random nonce, prepended to every encrypted secret
+const NONCE_SIZE: usize = 12;
+/// Size of the encrypted secret: nonce, encrypted secret and authentication tag
+const ENCRYPTED_SECRET_SIZE: usize = NONCE_SIZE + SECRET_SIZE + 16;
+
+type EncryptionKey = [u8; 32];
+
+/// Key derivation parameters of the encrypted wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encryption {
+	pub salt: Bytes,
+	pub iterations: u32,
+	/// Hash of the encryption key, used to verify the passphrase
+	pub check: SHA256,
+}
+
+impl Encryption {
+	fn new(passphrase: &str) -> (Self, EncryptionKey) {
+		let salt: [u8; SALT_SIZE] = rand::random();
+		let key = derive_key(passphrase, &salt, KEY_DERIVATION_ITERATIONS);
+		let encryption = Encryption {
+			salt: salt.to_vec().into(),
+			iterations: KEY_DERIVATION_ITERATIONS,
+			check: sha256(&key),
+		};
+		(encryption, key)
+	}
+
+	fn key(&self, passphrase: &str) -> Result<EncryptionKey, Error> {
+		let key = derive_key(passphrase, &self.salt, self.iterations);
+		if sha256(&key) != self.check {
+			return Err(Error::IncorrectPassphrase);
+		}
+		Ok(key)
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct KeyStore {
+	/// Secrets by their public keys. Encrypted if encryption is set
+	secrets: HashMap<DescriptorKey, Bytes>,
+	encryption: Option<Encryption>,
+	/// Encryption key of the unlocked wallet and time when the wallet is locked again
+	unlocked: Option<(EncryptionKey, Instant)>,
+}
+
+impl KeyStore {
+	/// Creates key store with secrets, read from the wallet file
+	pub fn new(secrets: HashMap<DescriptorKey, Bytes>, encryption: Option<Encryption>) -> Result<Self, Error> {
+		let secret_size = match encryption {
+			Some(_) => ENCRYPTED_SECRET_SIZE,
+			None => SECRET_SIZE,
+		};
+		if let Some(key) = secrets.keys().find(|key| secrets[*key].len() != secret_size) {
+			return Err(Error::Corrupted(format!("invalid secret of the key {}", key)));
+		}
+
+		Ok(KeyStore {
+			secrets,
+			encryption,
+			unlocked: None,
+		})
+	}
+
+	/// Returns stored (possibly encrypted) secrets
+	pub fn secrets(&self) -> &HashMap<DescriptorKey, Bytes> {
+		&self.secrets
+	}
+
+	pub fn encryption(&self) -> Option<&Encryption> {
+		self.encryption.as_ref()
+	}
+
+	pub fn is_encrypted(&self) -> bool {
+		self.encryption.is_some()
+	}
+
+	/// Returns true if the wallet is encrypted and private keys can't be decrypted now
+	pub fn is_locked(&self) -> bool {
+		self.is_encrypted() && self.unlocked_key().is_none()
+	}
+
+	pub fn contains(&self, key: &DescriptorKey) -> bool {
+		self.secrets.contains_key(key)
+	}
+
+	/// Stores private key of the public key. Fails if the wallet is locked
+	pub fn insert(&mut self, key: DescriptorKey, private: &Private) -> Result<(), Error> {
+		let secret = match self.encryption {
+			Some(_) => encrypt(self.unlocked_key().ok_or(Error::Locked)?, &key, &private.secret),
+			None => private.secret.to_vec().into(),
+		};
+		self.secrets.insert(key, secret);
+		Ok(())
+	}
+
+	/// Returns private key of the public key, or None if the key is unknown. Fails if the wallet is locked
+	pub fn private(&self, key: &DescriptorKey, network: Network) -> Result<Option<Private>, Error> {
+		let secret = match self.secrets.get(key) {
+			Some(secret) => secret,
+			None => return Ok(None),
+		};
+		let secret = match self.encryption {
+			Some(_) => decrypt(self.unlocked_key().ok_or(Error::Locked)?, key, secret)?,
+			None => {
+				let mut plain = Secret::default();
+				plain.copy_from_slice(secret);
+				plain
+			}
+		};
+		let private = Private {
+			network,
+			secret,
+			compressed: key.as_bytes().len() != 65,
+		};
+		if DescriptorKey::from_private(&private, key.is_xonly())? != *key {
+			return Err(Error::Corrupted(format!("secret of the key {} doesn't match the key", key)));
+		}
+		Ok(Some(private))
+	}
+
+	/// Encrypts all secrets with the passphrase. Wallet remains locked after encryption
+	pub fn encrypt(&mut self, passphrase: &str) -> Result<(), Error> {
+		if self.is_encrypted() {
+			return Err(Error::AlreadyEncrypted);
+		}
+
+		let (encryption, encryption_key) = Encryption::new(passphrase);
+		for (key, secret) in self.secrets.iter_mut() {
+			*secret = encrypt(&encryption_key, key, secret);
+		}
+		self.encryption = Some(encryption);
+		self.unlocked = None;
+		Ok(())
+	}
+
+	/// Unlocks the encrypted wallet for the given time
+	pub fn unlock(&mut self, passphrase: &str, timeout: Duration) -> Result<(), Error> {
+		let encryption = self.encryption.as_ref().ok_or(Error::NotEncrypted)?;
+		let encryption_key = encryption.key(passphrase)?;
+		self.unlocked = Some((encryption_key, Instant::now() + timeout));
+		Ok(())
+	}
+
+	/// Locks the encrypted wallet
+	pub fn lock(&mut self) -> Result<(), Error> {
+		if !self.is_encrypted() {
+			return Err(Error::NotEncrypted);
+		}
+		self.unlocked = None;
+		Ok(())
+	}
+
+	fn unlocked_key(&self) -> Option<&EncryptionKey> {
+		match self.unlocked {
+			Some((ref encryption_key, until)) if Instant::now() < until => Some(encryption_key),
+			_ => None,
+		}
+	}
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> EncryptionKey {
+	let mut key = EncryptionKey::default();
+	pbkdf2_hmac_sha512(passphrase.as_bytes(), salt, iterations, &mut key);
+	key
+}
+
+/// Encrypts the secret of the key under the random nonce
+fn encrypt(encryption_key: &EncryptionKey, key: &DescriptorKey, secret: &[u8]) -> Bytes {
+	let nonce: [u8; NONCE_SIZE] = rand::random();
+	let payload = Payload {
+		msg: secret,
+		aad: key.as_bytes(),
+	};
+	let encrypted = ChaCha20Poly1305::new(encryption_key.into())
+		.encrypt(&nonce.into(), payload)
+		.expect("secret is shorter than the ChaCha20 limit; qed");
+
+	let mut result = nonce.to_vec();
+	result.extend_from_slice(&encrypted);
+	result.into()
+}
+
+/// Decrypts the secret of the key. Fails if the secret has been modified or belongs to other key
+fn decrypt(encryption_key: &EncryptionKey, key: &DescriptorKey, secret: &[u8]) -> Result<Secret, Error> {
+	let (nonce, encrypted) = secret.split_at(NONCE_SIZE);
+	let payload = Payload {
+		msg: encrypted,
+		aad: key.as_bytes(),
+	};
+	let decrypted = ChaCha20Poly1305::new(encryption_key.into())
+		.decrypt(nonce.into(), payload)
+		.map_err(|_| Error::Corrupted(format!("secret of the key {} can't be decrypted", key)))?;
+
+	let mut result = Secret::default();
+	result.copy_from_slice(&decrypted);
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{KeyStore, NONCE_SIZE, SECRET_SIZE};
+	use crate::descriptor::DescriptorKey;
+	use crate::error::Error;
+	use keys::{Network, Private};
+	use std::time::Duration;
+
+	fn private(secret: u8) -> Private {
+		Private {
+			network: Network::Regtest,
+			secret: [secret; 32],
+			compressed: true,
+		}
+	}
+
+	#[test]
+	fn keystore_encrypts_and_unlocks_keys() {
+		let mut keystore = KeyStore::default();
+		let key = DescriptorKey::from_private(&private(1), false).unwrap();
+		keystore.insert(key.clone(), &private(1)).unwrap();
+		assert_eq!(keystore.lock(), Err(Error::NotEncrypted));
+
+		keystore.encrypt("passphrase").unwrap();
+		assert_eq!(keystore.encrypt("passphrase"), Err(Error::AlreadyEncrypted));
+		assert_ne!(
+			keystore.secrets()[&key][NONCE_SIZE..NONCE_SIZE + SECRET_SIZE],
+			private(1).secret[..]
+		);
+		assert_eq!(keystore.private(&key, Network::Regtest), Err(Error::Locked));
+
+		assert_eq!(keystore.unlock("wrong", Duration::from_secs(60)), Err(Error::IncorrectPassphrase));
+		keystore.unlock("passphrase", Duration::from_secs(60)).unwrap();
+		assert_eq!(keystore.private(&key, Network::Regtest), Ok(Some(private(1))));
+
+		// new keys are encrypted too
+		let xonly_key = DescriptorKey::from_private(&private(2), true).unwrap();
+		keystore.insert(xonly_key.clone(), &private(2)).unwrap();
+		assert_eq!(keystore.private(&xonly_key, Network::Regtest), Ok(Some(private(2))));
+
+		keystore.lock().unwrap();
+		assert_eq!(keystore.private(&xonly_key, Network::Regtest), Err(Error::Locked));
+		assert_eq!(keystore.insert(key, &private(1)), Err(Error::Locked));
+
+		// unlock expires
+		keystore.unlock("passphrase", Duration::from_secs(0)).unwrap();
+		assert_eq!(keystore.private(&xonly_key, Network::Regtest), Err(Error::Locked));
+	}
+
+	#[test]
+	fn keystore_rejects_modified_secrets() {
+		let mut keystore = KeyStore::default();
+		let key = DescriptorKey::from_private(&private(1), false).unwrap();
+		let other_key = DescriptorKey::from_private(&private(2), false).unwrap();
+		keystore.insert(key.clone(), &private(1)).unwrap();
+		keystore.insert(other_key.clone(), &private(2)).unwrap();
+		keystore.encrypt("passphrase").unwrap();
+		keystore.unlock("passphrase", Duration::from_secs(60)).unwrap();
+
+		// same secret is encrypted under different nonces
+		keystore.insert(key.clone(), &private(1)).unwrap();
+		let encrypted = keystore.secrets()[&key].clone();
+		keystore.insert(key.clone(), &private(1)).unwrap();
+		assert_ne!(keystore.secrets()[&key], encrypted);
+
+		let mut modified = encrypted.to_vec();
+		modified[20] ^= 1;
+		keystore.secrets.insert(key.clone(), modified.into());
+		assert!(matches!(keystore.private(&key, Network::Regtest), Err(Error::Corrupted(_))));
+
+		// secret is bound to its key
+		let other_secret = keystore.secrets()[&other_key].clone();
+		keystore.secrets.insert(key.clone(), other_secret);
+		assert!(matches!(keystore.private(&key, Network::Regtest), Err(Error::Corrupted(_))));
+
+		// secrets of the wrong size are rejected on load
+		let secrets = vec![(key, vec![0u8; 32].into())].into_iter().collect();
+		assert!(KeyStore::new(secrets, keystore.encryption().cloned()).is_err());
+	}
+}
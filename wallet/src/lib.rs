@@ -0,0 +1,38 @@
+//! Descriptor wallet
+//!
+//! Wallet stores output descriptors and their private keys in the JSON file in the data directory. Outputs of
+//! the descriptors are tracked in the best chain and in the memory pool.
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+extern crate bitcrypto;
+extern crate chacha20poly1305;
+extern crate chain;
+extern crate keys;
+extern crate miner;
+extern crate parking_lot;
+extern crate primitives;
+extern crate rand;
+extern crate script;
+extern crate serde;
+extern crate serde_json;
+extern crate serialization as ser;
+extern crate storage;
+extern crate sync;
+
+mod coin_selection;
+mod descriptor;
+mod error;
+mod keystore;
+mod listener;
+mod signer;
+mod wallet;
+mod wallet_file;
+
+pub use crate::coin_selection::DUST_THRESHOLD;
+pub use crate::descriptor::{Descriptor, DescriptorKey};
+pub use crate::error::Error;
+pub use crate::listener::WalletListener;
+pub use crate::wallet::{AddressType, BlockPosition, Category, TransactionEntry, Unspent, Wallet};
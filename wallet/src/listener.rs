@@ -0,0 +1,27 @@
+use crate::wallet::Wallet;
+use bitcrypto::SHA256D;
+use std::sync::Arc;
+use storage::SharedStore;
+use sync::SyncListener;
+
+/// Processes new best blocks by the wallet
+pub struct WalletListener {
+	wallet: Arc<Wallet>,
+	storage: SharedStore,
+}
+
+impl WalletListener {
+	pub fn new(wallet: Arc<Wallet>, storage: SharedStore) -> Self {
+		WalletListener { wallet, storage }
+	}
+}
+
+impl SyncListener for WalletListener {
+	fn synchronization_state_switched(&self, _is_synchronizing: bool) {}
+
+	fn best_storage_block_inserted(&self, _block_hash: &SHA256D) {
+		if let Err(err) = self.wallet.sync_chain(self.storage.as_store()) {
+			error!(target: "wallet", "Failed to process new best block: {}", err);
+		}
+	}
+}
@@ -0,0 +1,172 @@
+//! Signs inputs, spending outputs of the wallet descriptors
+
+use crate::descriptor::{taproot_tweak, Descriptor, DescriptorKey};
+use crate::error::Error;
+use bitcrypto::dhash160;
+use keys::Private;
+use primitives::bytes::Bytes;
+use script::{Builder, ScriptExecutionData, ScriptWitness, SignatureVersion, TransactionInputSigner};
+
+/// SIGHASH_ALL
+const SIGHASH_ALL: u32 = 1;
+/// SIGHASH_DEFAULT of taproot signatures, committing to the same data as SIGHASH_ALL
+const SIGHASH_DEFAULT: u32 = 0;
+
+/// Returns script_sig and witness of the input, spending output of the descriptor.
+/// `spent_outputs` of the signer must be filled if the input spends taproot output
+pub fn sign_input<F>(
+	signer: &TransactionInputSigner,
+	input_index: usize,
+	input_amount: u64,
+	descriptor: &Descriptor,
+	mut private_key: F,
+) -> Result<(Bytes, ScriptWitness), Error>
+where
+	F: FnMut(&DescriptorKey) -> Result<Option<Private>, Error>,
+{
+	let mut private = |key: &DescriptorKey| private_key(key).and_then(|private| private.ok_or(Error::MissingKey));
+	let ecdsa_signature = |private: &Private, script_code, sigversion| -> Result<Bytes, Error> {
+		let hash = signer.signature_hash(input_index, input_amount, script_code, sigversion, SIGHASH_ALL);
+		let mut signature: Vec<u8> = private.sign(&hash)?.into();
+		signature.push(SIGHASH_ALL as u8);
+		Ok(signature.into())
+	};
+
+	match *descriptor {
+		Descriptor::Pkh(ref key) => {
+			let signature = ecdsa_signature(&private(key)?, &descriptor.script_pubkey(), SignatureVersion::Base)?;
+			let script_sig = Builder::default().push_data(&signature).push_data(key.as_bytes()).into_bytes();
+			Ok((script_sig, Vec::new()))
+		}
+		Descriptor::Wpkh(ref key) | Descriptor::ShWpkh(ref key) => {
+			let script_code = Builder::build_p2pkh(&dhash160(key.as_bytes()));
+			let signature = ecdsa_signature(&private(key)?, &script_code, SignatureVersion::WitnessV0)?;
+			let script_sig = match descriptor.redeem_script() {
+				Some(redeem_script) => Builder::default().push_data(&redeem_script).into_bytes(),
+				None => Bytes::default(),
+			};
+			Ok((script_sig, vec![signature, key.as_bytes().to_vec().into()]))
+		}
+		Descriptor::WshMulti(threshold, ref keys) => {
+			let witness_script = descriptor.witness_script().expect("wsh descriptor has witness script; qed");
+			// empty item is consumed by the CHECKMULTISIG bug
+			let mut witness = vec![Bytes::default()];
+			for key in keys {
+				if witness.len() > threshold {
+					break;
+				}
+				match private(key) {
+					Ok(private) => witness.push(ecdsa_signature(&private, &witness_script, SignatureVersion::WitnessV0)?),
+					Err(Error::MissingKey) => continue,
+					Err(err) => return Err(err),
+				}
+			}
+			if witness.len() <= threshold {
+				return Err(Error::MissingKey);
+			}
+			witness.push(witness_script.to_bytes());
+			Ok((Bytes::default(), witness))
+		}
+		Descriptor::Tr(ref key) => {
+			let private = private(key)?.tweak_xonly(&taproot_tweak(&key.xonly()?))?;
+			let hash = signer
+				.signature_hash_schnorr(
+					input_index,
+					SIGHASH_DEFAULT,
+					SignatureVersion::Taproot,
+					&ScriptExecutionData::default(),
+				)
+				.ok_or(Error::MissingKey)?;
+			let signature: Vec<u8> = private.sign_schnorr(&hash)?.into();
+			Ok((Bytes::default(), vec![signature.into()]))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::sign_input;
+	use crate::descriptor::{Descriptor, DescriptorKey};
+	use crate::error::Error;
+	use chain::{OutPoint, TransactionOutput};
+	use keys::{Network, Private};
+	use script::{
+		verify_script, Script, SignatureVersion, TransactionInputSigner, TransactionSignatureChecker, UnsignedTransactionInput,
+		VerificationFlags,
+	};
+
+	fn private(secret: u8) -> Private {
+		Private {
+			network: Network::Regtest,
+			secret: [secret; 32],
+			compressed: true,
+		}
+	}
+
+	fn verify_signed_input(descriptor: &str) -> Result<(), Error> {
+		let (descriptor, secrets) = Descriptor::parse(descriptor)?;
+		let spent_output = TransactionOutput {
+			value: 100_000,
+			script_pubkey: descriptor.script_pubkey().to_bytes(),
+		};
+		let signer = TransactionInputSigner {
+			version: 2,
+			inputs: vec![UnsignedTransactionInput {
+				previous_output: OutPoint::default(),
+				sequence: 0xffff_fffd,
+			}],
+			outputs: vec![TransactionOutput {
+				value: 90_000,
+				script_pubkey: Default::default(),
+			}],
+			lock_time: 0,
+			spent_outputs: vec![spent_output.clone()],
+		};
+		let (script_sig, witness) = sign_input(&signer, 0, spent_output.value, &descriptor, |key| {
+			Ok(secrets
+				.iter()
+				.find(|(secret_key, _)| secret_key == key)
+				.map(|(_, private)| Private { ..*private }))
+		})?;
+
+		let checker = TransactionSignatureChecker {
+			signer,
+			input_index: 0,
+			input_amount: spent_output.value,
+		};
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+			.verify_nulldummy(true)
+			.verify_cleanstack(true);
+		verify_script(
+			&script_sig.into(),
+			&Script::from(spent_output.script_pubkey),
+			&witness,
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		)
+		.expect("signed input is valid");
+		Ok(())
+	}
+
+	#[test]
+	fn sign_input_of_every_descriptor() {
+		let key = |secret| DescriptorKey::from_private(&private(secret), false).unwrap();
+		assert_eq!(verify_signed_input(&format!("pkh({})", private(1))), Ok(()));
+		assert_eq!(verify_signed_input(&format!("wpkh({})", private(2))), Ok(()));
+		assert_eq!(verify_signed_input(&format!("sh(wpkh({}))", private(3))), Ok(()));
+		assert_eq!(verify_signed_input(&format!("tr({})", private(4))), Ok(()));
+		assert_eq!(
+			verify_signed_input(&format!("wsh(multi(2,{},{},{}))", private(5), key(6), private(7))),
+			Ok(())
+		);
+		assert_eq!(
+			verify_signed_input(&format!("wsh(multi(2,{},{},{}))", private(5), key(6), key(7))),
+			Err(Error::MissingKey)
+		);
+		assert_eq!(verify_signed_input(&format!("wpkh({})", key(2))), Err(Error::MissingKey));
+	}
+}
@@ -0,0 +1,1113 @@
+//! Descriptor wallet, tracking outputs of its descriptors in the best chain and in the memory pool
+
+use crate::coin_selection::{select_coins, Candidate};
+use crate::descriptor::{Descriptor, DescriptorKey};
+use crate::error::Error;
+use crate::keystore::{Encryption, KeyStore};
+use crate::signer::sign_input;
+use crate::wallet_file::{BlockRecord, DescriptorRecord, EncryptionRecord, KeyRecord, TransactionRecord, WalletFile, WALLET_FILE_VERSION};
+use bitcrypto::{FromHex, FromStr, ToHex, SHA256D};
+use chain::{IndexedBlock, IndexedTransaction, OutPoint, Transaction, TransactionInput, TransactionOutput};
+use keys::generator::{Generator, Random};
+use keys::{Address, Network, Private};
+use miner::MemoryPool;
+use parking_lot::Mutex;
+use primitives::bytes::Bytes;
+use script::{Builder, TransactionInputSigner, UnsignedTransactionInput};
+use ser::{deserialize, serialize_with_flags, Serializable, SERIALIZE_TRANSACTION_WITNESS};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::time::Duration;
+use storage::{BlockHeight, BlockProvider, BlockRef, Store};
+
+/// Number of confirmations, after which coinbase outputs become spendable by the wallet
+const COINBASE_MATURITY: u32 = 100;
+/// Wallet file is written after this number of processed blocks, even if none of them has wallet transactions
+const BLOCKS_BETWEEN_WRITES: u32 = 100;
+/// Version of the wallet transactions
+const TRANSACTION_VERSION: i32 = 2;
+/// Sequence of inputs, signaling BIP125 replaceability
+const SEQUENCE_REPLACEABLE: u32 = 0xffff_fffd;
+/// Sequence of inputs of non-replaceable transactions. Lock time is still enforced
+const SEQUENCE_NON_REPLACEABLE: u32 = 0xffff_fffe;
+/// Maximal difference between block time and the rescan timestamp
+const RESCAN_TIMESTAMP_WINDOW: u32 = 2 * 60 * 60;
+
+/// Type of the new address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressType {
+	/// P2PKH address: `pkh()` descriptor
+	Legacy,
+	/// P2SH-P2WPKH address: `sh(wpkh())` descriptor
+	P2shSegwit,
+	/// P2WPKH address: `wpkh()` descriptor
+	Bech32,
+	/// P2TR address: `tr()` descriptor
+	Bech32m,
+}
+
+impl str::FromStr for AddressType {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"legacy" => Ok(AddressType::Legacy),
+			"p2sh-segwit" => Ok(AddressType::P2shSegwit),
+			"bech32" => Ok(AddressType::Bech32),
+			"bech32m" => Ok(AddressType::Bech32m),
+			_ => Err(Error::InvalidAddressType(s.into())),
+		}
+	}
+}
+
+/// Block of the best chain, containing wallet transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockPosition {
+	pub height: u32,
+	pub hash: SHA256D,
+	pub time: u32,
+}
+
+/// Unspent output of the wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unspent {
+	pub outpoint: OutPoint,
+	pub output: TransactionOutput,
+	pub address: Address,
+	pub label: String,
+	pub descriptor: Descriptor,
+	/// Number of confirmations, zero for memory pool outputs
+	pub confirmations: u32,
+	/// True if wallet knows enough private keys to spend the output
+	pub spendable: bool,
+	/// True if output is either confirmed or created by the wallet itself
+	pub safe: bool,
+}
+
+/// Category of the wallet transaction entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+	Send,
+	Receive,
+	/// Mature coinbase output
+	Generate,
+	/// Coinbase output, which is not spendable yet
+	Immature,
+}
+
+impl Category {
+	pub fn as_str(&self) -> &'static str {
+		match *self {
+			Category::Send => "send",
+			Category::Receive => "receive",
+			Category::Generate => "generate",
+			Category::Immature => "immature",
+		}
+	}
+}
+
+/// Single payment of the wallet transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionEntry {
+	pub txid: SHA256D,
+	pub category: Category,
+	pub address: Option<Address>,
+	pub label: Option<String>,
+	/// Amount in satoshis, negative for sent payments
+	pub amount: i64,
+	pub vout: u32,
+	/// Fee in satoshis (negative), sent payments only
+	pub fee: Option<i64>,
+	pub confirmations: u32,
+	pub block: Option<BlockPosition>,
+	/// Time of the block or time, when transaction has entered the memory pool
+	pub time: u64,
+}
+
+struct DescriptorEntry {
+	descriptor: Descriptor,
+	internal: bool,
+	label: String,
+}
+
+/// Confirmed wallet transaction
+struct WalletTransaction {
+	transaction: IndexedTransaction,
+	block: BlockPosition,
+}
+
+/// Confirmed or memory pool transaction of the wallet
+struct ViewTransaction<'a> {
+	hash: SHA256D,
+	transaction: &'a Transaction,
+	block: Option<BlockPosition>,
+	time: u64,
+}
+
+struct WalletState {
+	descriptors: Vec<DescriptorEntry>,
+	/// Indices of descriptors by their output scripts
+	scripts: HashMap<Bytes, usize>,
+	keys: KeyStore,
+	transactions: HashMap<SHA256D, WalletTransaction>,
+	/// Last block, processed by the wallet. None for new wallets
+	best_block: Option<BlockHeight>,
+	/// Number of processed blocks since the wallet file has been written
+	blocks_since_write: u32,
+}
+
+/// Descriptor wallet
+pub struct Wallet {
+	path: PathBuf,
+	network: Network,
+	state: Mutex<WalletState>,
+}
+
+impl Wallet {
+	/// Opens the wallet file, creating new empty wallet if the file doesn't exist
+	pub fn open(path: &Path, network: Network) -> Result<Self, Error> {
+		let mut state = WalletState {
+			descriptors: Vec::new(),
+			scripts: HashMap::new(),
+			keys: KeyStore::default(),
+			transactions: HashMap::new(),
+			best_block: None,
+			blocks_since_write: 0,
+		};
+		if path.exists() {
+			state.read(WalletFile::read(path)?)?;
+		}
+
+		Ok(Wallet {
+			path: path.to_owned(),
+			network,
+			state: Mutex::new(state),
+		})
+	}
+
+	pub fn network(&self) -> Network {
+		self.network
+	}
+
+	/// Writes the wallet file
+	pub fn flush(&self) -> Result<(), Error> {
+		self.write(&mut self.state.lock())
+	}
+
+	/// Processes blocks of the best chain, which haven't been processed yet, and rolls back
+	/// transactions of the blocks, which are no longer in the best chain
+	pub fn sync_chain(&self, store: &dyn Store) -> Result<(), Error> {
+		let mut state = self.state.lock();
+		let best_block = store.best_block();
+		let blocks = store.as_block_provider();
+		let mut tip = match state.best_block {
+			Some(ref tip) => tip.clone(),
+			// new wallet has no transactions in the existing blocks
+			None => {
+				state.best_block = Some(best_block);
+				return self.write(&mut state);
+			}
+		};
+
+		let mut changed = false;
+		if blocks.block_hash(tip.number) != Some(tip.hash) {
+			tip = find_fork(blocks, tip);
+			debug!(target: "wallet", "Rolling back wallet transactions above the block {}", tip.number);
+			let fork_height = tip.number;
+			state.transactions.retain(|_, transaction| transaction.block.height <= fork_height);
+			changed = true;
+		}
+
+		let mut outpoints = state.outpoints();
+		for height in tip.number + 1..=best_block.number {
+			match blocks.block(BlockRef::Number(height)) {
+				Some(block) => changed |= state.process_block(height, &block, &mut outpoints),
+				None => warn!(target: "wallet", "Block {} is not available, its wallet transactions are ignored", height),
+			}
+			state.blocks_since_write += 1;
+		}
+
+		state.best_block = Some(best_block);
+		if changed || state.blocks_since_write >= BLOCKS_BETWEEN_WRITES {
+			self.write(&mut state)?;
+		}
+		Ok(())
+	}
+
+	/// Processes blocks of the best chain again, starting from the first block, which could contain
+	/// transactions, made after `timestamp`
+	pub fn rescan(&self, store: &dyn Store, timestamp: u32) -> Result<(), Error> {
+		let mut state = self.state.lock();
+		let blocks = store.as_block_provider();
+		let best_height = match state.best_block {
+			Some(ref tip) => tip.number,
+			None => return Ok(()),
+		};
+
+		let block_time = |height| {
+			blocks
+				.block_header(BlockRef::Number(height))
+				.map_or(u32::max_value(), |header| header.raw.time)
+		};
+		// block times are only roughly increasing => leave some room for blocks with earlier times
+		let timestamp = timestamp.saturating_sub(RESCAN_TIMESTAMP_WINDOW);
+		let (mut low, mut high) = (0, best_height + 1);
+		while low < high {
+			let middle = low + (high - low) / 2;
+			if block_time(middle) < timestamp {
+				low = middle + 1;
+			} else {
+				high = middle;
+			}
+		}
+
+		debug!(target: "wallet", "Rescanning blocks {}..{}", low, best_height);
+		let mut outpoints = state.outpoints();
+		for height in low..=best_height {
+			if let Some(block) = blocks.block(BlockRef::Number(height)) {
+				state.process_block(height, &block, &mut outpoints);
+			}
+		}
+		self.write(&mut state)
+	}
+
+	/// Creates new key and returns address of its descriptor
+	pub fn new_address(&self, label: String, address_type: AddressType) -> Result<Address, Error> {
+		let mut state = self.state.lock();
+		let descriptor = state.new_descriptor(self.network, address_type, label, false)?;
+		self.write(&mut state)?;
+		Ok(descriptor.address(self.network))
+	}
+
+	/// Adds descriptor and its private keys to the wallet. Use `rescan` to find
+	/// transactions of the descriptor in the existing blocks
+	pub fn import_descriptor(&self, descriptor: &str, label: String, internal: bool) -> Result<Descriptor, Error> {
+		let (descriptor, secrets) = Descriptor::parse(descriptor)?;
+		let mut state = self.state.lock();
+		for (key, private) in secrets {
+			state.keys.insert(key, &private)?;
+		}
+		let script_pubkey = descriptor.script_pubkey().to_bytes();
+		if !state.scripts.contains_key(&script_pubkey) {
+			state.add_descriptor(DescriptorEntry {
+				descriptor: descriptor.clone(),
+				internal,
+				label,
+			});
+		}
+		self.write(&mut state)?;
+		Ok(descriptor)
+	}
+
+	/// Returns spendable balance of outputs with at least `min_confirmations` confirmations
+	pub fn balance(&self, memory_pool: &MemoryPool, min_confirmations: u32) -> u64 {
+		self.unspent(memory_pool, min_confirmations, u32::max_value())
+			.into_iter()
+			.filter(|unspent| unspent.spendable && unspent.safe)
+			.map(|unspent| unspent.output.value)
+			.sum()
+	}
+
+	/// Returns mature unspent outputs of the wallet
+	pub fn unspent(&self, memory_pool: &MemoryPool, min_confirmations: u32, max_confirmations: u32) -> Vec<Unspent> {
+		let state = self.state.lock();
+		let view = state.view(memory_pool);
+		state
+			.unspent(&view, self.network)
+			.into_iter()
+			.filter(|unspent| unspent.confirmations >= min_confirmations && unspent.confirmations <= max_confirmations)
+			.collect()
+	}
+
+	/// Returns most recent `count` payments of the wallet, skipping `skip` most recent ones. If `label` is given,
+	/// only payments to the descriptors with this label are returned
+	pub fn transactions(&self, memory_pool: &MemoryPool, label: Option<&str>, count: usize, skip: usize) -> Vec<TransactionEntry> {
+		let state = self.state.lock();
+		let view = state.view(memory_pool);
+		let values: HashMap<OutPoint, u64> = view
+			.iter()
+			.flat_map(|transaction| state.wallet_outputs(transaction))
+			.map(|(outpoint, output, _)| (outpoint, output.value))
+			.collect();
+
+		let mut entries = Vec::new();
+		for transaction in &view {
+			let confirmations = state.confirmations(transaction.block.as_ref());
+			let debit: u64 = transaction
+				.transaction
+				.inputs
+				.iter()
+				.filter_map(|input| values.get(&input.previous_output))
+				.sum();
+			let all_inputs_spent_by_wallet = transaction
+				.transaction
+				.inputs
+				.iter()
+				.all(|input| values.contains_key(&input.previous_output));
+			let fee = match debit != 0 && all_inputs_spent_by_wallet {
+				true => Some(-(debit as i64 - transaction.transaction.total_spends() as i64)),
+				false => None,
+			};
+			let entry = |category, address, label, amount, vout, fee| TransactionEntry {
+				txid: transaction.hash,
+				category,
+				address,
+				label,
+				amount,
+				vout,
+				fee,
+				confirmations,
+				block: transaction.block,
+				time: transaction.time,
+			};
+
+			for (vout, output) in transaction.transaction.outputs.iter().enumerate() {
+				let descriptor = state.scripts.get(&output.script_pubkey).map(|index| &state.descriptors[*index]);
+				let address = || {
+					descriptor.map(|entry| entry.descriptor.address(self.network)).or_else(|| {
+						script::Script::from(output.script_pubkey.clone())
+							.extract_destinations()
+							.ok()
+							.and_then(|destinations| destinations.into_iter().next())
+							.map(|destination| Address {
+								kind: destination.kind,
+								network: self.network,
+								hash: destination.hash,
+							})
+					})
+				};
+
+				if debit != 0 {
+					// change outputs are not displayed
+					if descriptor.map_or(false, |entry| entry.internal) {
+						continue;
+					}
+					entries.push(entry(Category::Send, address(), None, -(output.value as i64), vout as u32, fee));
+				}
+
+				if let Some(entry_descriptor) = descriptor.filter(|entry| !entry.internal || debit == 0) {
+					let category = match transaction.transaction.is_coinbase() {
+						true if confirmations > COINBASE_MATURITY => Category::Generate,
+						true => Category::Immature,
+						false => Category::Receive,
+					};
+					entries.push(entry(
+						category,
+						address(),
+						Some(entry_descriptor.label.clone()),
+						output.value as i64,
+						vout as u32,
+						None,
+					));
+				}
+			}
+		}
+
+		if let Some(label) = label {
+			entries.retain(|entry| entry.label.as_deref() == Some(label));
+		}
+		let end = entries.len().saturating_sub(skip);
+		let start = end.saturating_sub(count);
+		entries.drain(start..end).collect()
+	}
+
+	/// Creates and signs transaction, paying `amount` to the address. Change is paid to the new key of the wallet.
+	/// `fee_rate` is in satoshis per 1000 virtual bytes
+	pub fn create_transaction(
+		&self,
+		memory_pool: &MemoryPool,
+		address: &Address,
+		amount: u64,
+		fee_rate: u64,
+		subtract_fee: bool,
+		replaceable: bool,
+	) -> Result<IndexedTransaction, Error> {
+		if (address.network == Network::Mainnet) != (self.network == Network::Mainnet) {
+			return Err(Error::InvalidNetwork);
+		}
+
+		let mut state = self.state.lock();
+		if state.keys.is_locked() {
+			return Err(Error::Locked);
+		}
+
+		let unspent: Vec<Unspent> = {
+			let view = state.view(memory_pool);
+			state
+				.unspent(&view, self.network)
+				.into_iter()
+				.filter(|unspent| unspent.spendable && unspent.safe)
+				.collect()
+		};
+		let candidates: Vec<Candidate> = unspent
+			.iter()
+			.map(|unspent| Candidate {
+				value: unspent.output.value,
+				input_weight: unspent.descriptor.max_input_weight(),
+			})
+			.collect();
+
+		let recipient_script = Builder::build_address(address).to_bytes();
+		let recipient_output_weight = output_weight(&recipient_script);
+		// version, lock time, inputs and outputs count, segwit marker and flag
+		let fixed_weight = (4 + 4 + 1 + 1) * 4 + 2 + recipient_output_weight;
+		let change_weight = output_weight(&Builder::build_p2wpkh(&Default::default()).to_bytes());
+		let selection = select_coins(&candidates, amount, fixed_weight, change_weight, fee_rate, subtract_fee)?;
+
+		let mut outputs = vec![TransactionOutput {
+			value: selection.amount,
+			script_pubkey: recipient_script,
+		}];
+		if let Some(change) = selection.change {
+			let descriptor = state.new_descriptor(self.network, AddressType::Bech32, String::new(), true)?;
+			let change = TransactionOutput {
+				value: change,
+				script_pubkey: descriptor.script_pubkey().to_bytes(),
+			};
+			// change position doesn't reveal which output is the change
+			outputs.insert(rand::random::<bool>() as usize, change);
+		}
+
+		let spent: Vec<&Unspent> = selection.inputs.iter().map(|index| &unspent[*index]).collect();
+		let sequence = if replaceable {
+			SEQUENCE_REPLACEABLE
+		} else {
+			SEQUENCE_NON_REPLACEABLE
+		};
+		let signer = TransactionInputSigner {
+			version: TRANSACTION_VERSION,
+			inputs: spent
+				.iter()
+				.map(|unspent| UnsignedTransactionInput {
+					previous_output: unspent.outpoint.clone(),
+					sequence,
+				})
+				.collect(),
+			outputs,
+			// discourages fee sniping
+			lock_time: state.best_block.as_ref().map_or(0, |tip| tip.number),
+			spent_outputs: spent.iter().map(|unspent| unspent.output.clone()).collect(),
+		};
+
+		let mut inputs = Vec::with_capacity(spent.len());
+		for (index, unspent) in spent.iter().enumerate() {
+			let keys = &state.keys;
+			let (script_sig, script_witness) = sign_input(&signer, index, unspent.output.value, &unspent.descriptor, |key| {
+				keys.private(key, self.network)
+			})?;
+			inputs.push(TransactionInput {
+				previous_output: unspent.outpoint.clone(),
+				script_sig,
+				sequence,
+				script_witness,
+			});
+		}
+
+		// change key must be stored before the transaction is broadcasted
+		if selection.change.is_some() {
+			self.write(&mut state)?;
+		}
+
+		Ok(IndexedTransaction::from_raw(Transaction {
+			version: signer.version,
+			inputs,
+			outputs: signer.outputs,
+			lock_time: signer.lock_time,
+		}))
+	}
+
+	/// Encrypts private keys of the wallet with the passphrase
+	pub fn encrypt(&self, passphrase: &str) -> Result<(), Error> {
+		let mut state = self.state.lock();
+		state.keys.encrypt(passphrase)?;
+		self.write(&mut state)
+	}
+
+	/// Stores decryption key of the encrypted wallet for the given time
+	pub fn unlock(&self, passphrase: &str, timeout: Duration) -> Result<(), Error> {
+		self.state.lock().keys.unlock(passphrase, timeout)
+	}
+
+	/// Forgets decryption key of the encrypted wallet
+	pub fn lock(&self) -> Result<(), Error> {
+		self.state.lock().keys.lock()
+	}
+
+	fn write(&self, state: &mut WalletState) -> Result<(), Error> {
+		state.to_file().write(&self.path)?;
+		state.blocks_since_write = 0;
+		Ok(())
+	}
+}
+
+impl WalletState {
+	fn add_descriptor(&mut self, entry: DescriptorEntry) {
+		self.scripts
+			.insert(entry.descriptor.script_pubkey().to_bytes(), self.descriptors.len());
+		self.descriptors.push(entry);
+	}
+
+	fn new_descriptor(&mut self, network: Network, address_type: AddressType, label: String, internal: bool) -> Result<Descriptor, Error> {
+		let keypair = Random::new(network).generate()?;
+		let private = Private { ..*keypair.private() };
+		let key = DescriptorKey::from_private(&private, address_type == AddressType::Bech32m)?;
+		let descriptor = match address_type {
+			AddressType::Legacy => Descriptor::Pkh(key.clone()),
+			AddressType::P2shSegwit => Descriptor::ShWpkh(key.clone()),
+			AddressType::Bech32 => Descriptor::Wpkh(key.clone()),
+			AddressType::Bech32m => Descriptor::Tr(key.clone()),
+		};
+		self.keys.insert(key, &private)?;
+		self.add_descriptor(DescriptorEntry {
+			descriptor: descriptor.clone(),
+			internal,
+			label,
+		});
+		Ok(descriptor)
+	}
+
+	fn confirmations(&self, block: Option<&BlockPosition>) -> u32 {
+		match (block, self.best_block.as_ref()) {
+			(Some(block), Some(tip)) => tip.number.saturating_sub(block.height) + 1,
+			_ => 0,
+		}
+	}
+
+	/// Returns outputs of the confirmed wallet transactions, paying to the wallet descriptors
+	fn outpoints(&self) -> HashSet<OutPoint> {
+		self.transactions
+			.values()
+			.flat_map(|transaction| {
+				transaction
+					.transaction
+					.raw
+					.outputs
+					.iter()
+					.enumerate()
+					.filter(move |(_, output)| self.scripts.contains_key(&output.script_pubkey))
+					.map(move |(index, _)| OutPoint {
+						hash: transaction.transaction.hash,
+						index: index as u32,
+					})
+			})
+			.collect()
+	}
+
+	/// Returns true if transaction pays to or spends from the wallet. Wallet outputs of the transaction
+	/// are added to the `outpoints`
+	fn track_transaction(&self, hash: &SHA256D, transaction: &Transaction, outpoints: &mut HashSet<OutPoint>) -> bool {
+		let mut is_relevant = transaction.inputs.iter().any(|input| outpoints.contains(&input.previous_output));
+		for (index, output) in transaction.outputs.iter().enumerate() {
+			if self.scripts.contains_key(&output.script_pubkey) {
+				outpoints.insert(OutPoint {
+					hash: *hash,
+					index: index as u32,
+				});
+				is_relevant = true;
+			}
+		}
+		is_relevant
+	}
+
+	/// Stores wallet transactions of the block. Returns true if block has wallet transactions
+	fn process_block(&mut self, height: u32, block: &IndexedBlock, outpoints: &mut HashSet<OutPoint>) -> bool {
+		let position = BlockPosition {
+			height,
+			hash: *block.hash(),
+			time: block.header.raw.time,
+		};
+		let mut has_transactions = false;
+		for transaction in &block.transactions {
+			if self.track_transaction(&transaction.hash, &transaction.raw, outpoints) {
+				trace!(target: "wallet", "Found wallet transaction {} in the block {}", transaction.hash, height);
+				self.transactions.insert(
+					transaction.hash,
+					WalletTransaction {
+						transaction: transaction.clone(),
+						block: position,
+					},
+				);
+				has_transactions = true;
+			}
+		}
+		has_transactions
+	}
+
+	/// Returns confirmed transactions of the wallet and memory pool transactions, paying to or spending from the wallet
+	fn view<'a>(&'a self, memory_pool: &'a MemoryPool) -> Vec<ViewTransaction<'a>> {
+		let mut view: Vec<ViewTransaction> = self
+			.transactions
+			.values()
+			.map(|transaction| ViewTransaction {
+				hash: transaction.transaction.hash,
+				transaction: &transaction.transaction.raw,
+				block: Some(transaction.block),
+				time: transaction.block.time.into(),
+			})
+			.collect();
+		view.sort_by_key(|transaction| (transaction.block.map(|block| block.height), transaction.time));
+
+		// memory pool transactions could spend outputs of other memory pool transactions => repeat until nothing is found
+		let mut outpoints = self.outpoints();
+		let mut candidates: Vec<_> = memory_pool
+			.get_transactions_ids()
+			.iter()
+			.filter_map(|hash| memory_pool.get_entry(hash))
+			.collect();
+		candidates.sort_by_key(|entry| entry.time);
+		loop {
+			let view_size = view.len();
+			candidates.retain(|entry| {
+				if !self.track_transaction(&entry.hash, &entry.transaction, &mut outpoints) {
+					return true;
+				}
+				view.push(ViewTransaction {
+					hash: entry.hash,
+					transaction: &entry.transaction,
+					block: None,
+					time: entry.time,
+				});
+				false
+			});
+			if view.len() == view_size {
+				break;
+			}
+		}
+		view
+	}
+
+	/// Returns outputs of the transaction, paying to the wallet, with indices of their descriptors
+	fn wallet_outputs<'a>(
+		&'a self,
+		transaction: &'a ViewTransaction,
+	) -> impl Iterator<Item = (OutPoint, &'a TransactionOutput, usize)> + 'a {
+		transaction
+			.transaction
+			.outputs
+			.iter()
+			.enumerate()
+			.filter_map(move |(index, output)| {
+				self.scripts.get(&output.script_pubkey).map(|descriptor| {
+					let outpoint = OutPoint {
+						hash: transaction.hash,
+						index: index as u32,
+					};
+					(outpoint, output, *descriptor)
+				})
+			})
+	}
+
+	fn unspent(&self, view: &[ViewTransaction], network: Network) -> Vec<Unspent> {
+		let spent: HashSet<&OutPoint> = view
+			.iter()
+			.flat_map(|transaction| transaction.transaction.inputs.iter().map(|input| &input.previous_output))
+			.collect();
+		let wallet_outpoints: HashSet<OutPoint> = view
+			.iter()
+			.flat_map(|transaction| self.wallet_outputs(transaction))
+			.map(|(outpoint, _, _)| outpoint)
+			.collect();
+
+		let mut unspent = Vec::new();
+		for transaction in view {
+			let confirmations = self.confirmations(transaction.block.as_ref());
+			if transaction.transaction.is_coinbase() && confirmations <= COINBASE_MATURITY {
+				continue;
+			}
+			// unconfirmed outputs are only trusted if transaction is created by the wallet
+			let safe = confirmations != 0
+				|| transaction
+					.transaction
+					.inputs
+					.iter()
+					.all(|input| wallet_outpoints.contains(&input.previous_output));
+
+			for (outpoint, output, descriptor_index) in self.wallet_outputs(transaction) {
+				if spent.contains(&outpoint) {
+					continue;
+				}
+				let entry = &self.descriptors[descriptor_index];
+				unspent.push(Unspent {
+					outpoint,
+					output: output.clone(),
+					address: entry.descriptor.address(network),
+					label: entry.label.clone(),
+					descriptor: entry.descriptor.clone(),
+					confirmations,
+					spendable: self.is_spendable(&entry.descriptor),
+					safe,
+				});
+			}
+		}
+		unspent
+	}
+
+	fn is_spendable(&self, descriptor: &Descriptor) -> bool {
+		let known_keys = descriptor.keys().into_iter().filter(|key| self.keys.contains(key)).count();
+		match *descriptor {
+			Descriptor::WshMulti(threshold, _) => known_keys >= threshold,
+			_ => known_keys != 0,
+		}
+	}
+
+	fn read(&mut self, file: WalletFile) -> Result<(), Error> {
+		let corrupted = |what: &str| Error::Corrupted(format!("invalid {}", what));
+		for record in file.descriptors {
+			self.add_descriptor(DescriptorEntry {
+				descriptor: record.descriptor.parse()?,
+				internal: record.internal,
+				label: record.label,
+			});
+		}
+
+		let mut secrets = HashMap::new();
+		for record in file.keys {
+			let (descriptor, _) = Descriptor::parse(&format!("tr({})", record.public))
+				.or_else(|_| Descriptor::parse(&format!("pkh({})", record.public)))
+				.map_err(|_| corrupted("key"))?;
+			let key = descriptor.keys()[0].clone();
+			let secret: Vec<u8> = FromHex::from_hex(&record.secret).map_err(|_| corrupted("secret"))?;
+			secrets.insert(key, secret.into());
+		}
+		let encryption = match file.encryption {
+			Some(record) => Some(Encryption {
+				salt: record.salt.parse().map_err(|_| corrupted("encryption salt"))?,
+				iterations: record.iterations,
+				check: bitcrypto::SHA256::from_str(&record.check).map_err(|_| corrupted("encryption check"))?,
+			}),
+			None => None,
+		};
+		self.keys = KeyStore::new(secrets, encryption)?;
+
+		self.best_block = match file.best_block {
+			Some(record) => Some(BlockHeight {
+				number: record.height,
+				hash: SHA256D::from_str(&record.hash).map_err(|_| corrupted("best block"))?,
+			}),
+			None => None,
+		};
+
+		for record in file.transactions {
+			let data: Vec<u8> = FromHex::from_hex(&record.hex).map_err(|_| corrupted("transaction"))?;
+			let transaction: Transaction = deserialize(data.as_slice()).map_err(|_| corrupted("transaction"))?;
+			let transaction = IndexedTransaction::from_raw(transaction);
+			let block = BlockPosition {
+				height: record.block.height,
+				hash: SHA256D::from_str(&record.block.hash).map_err(|_| corrupted("transaction block"))?,
+				time: record.time,
+			};
+			self.transactions.insert(transaction.hash, WalletTransaction { transaction, block });
+		}
+		Ok(())
+	}
+
+	fn to_file(&self) -> WalletFile {
+		let mut transactions: Vec<&WalletTransaction> = self.transactions.values().collect();
+		transactions.sort_by_key(|transaction| transaction.block.height);
+		let mut keys: Vec<KeyRecord> = self
+			.keys
+			.secrets()
+			.iter()
+			.map(|(key, secret)| KeyRecord {
+				public: key.to_string(),
+				secret: secret.to_hex(),
+			})
+			.collect();
+		keys.sort_by(|a, b| a.public.cmp(&b.public));
+
+		WalletFile {
+			version: WALLET_FILE_VERSION,
+			descriptors: self
+				.descriptors
+				.iter()
+				.map(|entry| DescriptorRecord {
+					descriptor: entry.descriptor.to_string(),
+					internal: entry.internal,
+					label: entry.label.clone(),
+				})
+				.collect(),
+			keys,
+			encryption: self.keys.encryption().map(|encryption| EncryptionRecord {
+				salt: encryption.salt.to_hex(),
+				iterations: encryption.iterations,
+				check: encryption.check.to_string(),
+			}),
+			best_block: self.best_block.as_ref().map(|tip| BlockRecord {
+				height: tip.number,
+				hash: tip.hash.to_string(),
+			}),
+			transactions: transactions
+				.into_iter()
+				.map(|transaction| TransactionRecord {
+					hex: serialize_with_flags(&transaction.transaction.raw, SERIALIZE_TRANSACTION_WITNESS).to_hex(),
+					block: BlockRecord {
+						height: transaction.block.height,
+						hash: transaction.block.hash.to_string(),
+					},
+					time: transaction.block.time,
+				})
+				.collect(),
+		}
+	}
+}
+
+/// Returns the last block of the best chain, which is an ancestor of the given block
+fn find_fork(blocks: &dyn BlockProvider, mut block: BlockHeight) -> BlockHeight {
+	// decanonized blocks are still stored => compare with the best chain block at the same height
+	while block.number != 0 && blocks.block_hash(block.number) != Some(block.hash) {
+		match blocks.block_header(BlockRef::Hash(block.hash)) {
+			Some(header) => {
+				block.hash = header.raw.previous_header_hash;
+				block.number -= 1;
+			}
+			None => break,
+		}
+	}
+	match blocks.block_hash(block.number) == Some(block.hash) {
+		true => block,
+		false => BlockHeight {
+			number: 0,
+			hash: blocks.block_hash(0).unwrap_or_default(),
+		},
+	}
+}
+
+/// Returns weight of the transaction output with given script
+fn output_weight(script_pubkey: &Bytes) -> usize {
+	(8 + script_pubkey.serialized_size()) * 4
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate db;
+	extern crate test_data;
+
+	use self::db::BlockChainDatabase;
+	use super::{AddressType, Category, Wallet, SEQUENCE_REPLACEABLE};
+	use crate::descriptor::{Descriptor, DescriptorKey};
+	use crate::error::Error;
+	use bitcrypto::SHA256D;
+	use chain::{IndexedBlock, Transaction};
+	use keys::{Address, Network, Private};
+	use miner::{FeeCalculator, MemoryPool};
+	use script::Builder;
+	use std::path::PathBuf;
+	use std::time::Duration;
+	use storage::AsSubstore;
+
+	fn wallet_path(name: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("wallet-{}-{}.json", name, std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		path
+	}
+
+	fn recipient() -> Address {
+		let private = Private {
+			network: Network::Regtest,
+			secret: [1; 32],
+			compressed: true,
+		};
+		let (descriptor, _) = Descriptor::parse(&format!("wpkh({})", private)).unwrap();
+		descriptor.address(Network::Regtest)
+	}
+
+	/// Creates block with coinbase, paying to the recipient, and transaction, spending the coinbase and paying `value` to the address
+	fn block(parent: SHA256D, nonce: u32, address: Option<&Address>, value: u64) -> IndexedBlock {
+		let mut builder = test_data::block_builder()
+			.header()
+			.parent(parent)
+			.nonce(nonce)
+			.build()
+			.transaction()
+			.coinbase()
+			.output()
+			.value(50)
+			.script_pubkey_bytes(Builder::build_address(&recipient()).to_bytes())
+			.build()
+			.build();
+		if let Some(address) = address {
+			builder = builder
+				.derived_transaction(0, 0)
+				.output()
+				.value(value)
+				.script_pubkey_bytes(Builder::build_address(address).to_bytes())
+				.build()
+				.build();
+		}
+		builder.build().into()
+	}
+
+	#[test]
+	fn wallet_tracks_outputs_and_sends_transactions() {
+		let genesis: IndexedBlock = test_data::genesis().into();
+		let storage = BlockChainDatabase::transient(&genesis).unwrap();
+		let path = wallet_path("send");
+		let wallet = Wallet::open(&path, Network::Regtest).unwrap();
+		wallet.sync_chain(&storage).unwrap();
+		let address = wallet.new_address("savings".into(), AddressType::Bech32).unwrap();
+
+		let b1 = block(*genesis.hash(), 1, Some(&address), 100_000);
+		storage.insert(b1.clone()).unwrap();
+		storage.canonize(b1.hash()).unwrap();
+		wallet.sync_chain(&storage).unwrap();
+
+		let mut memory_pool = MemoryPool::new();
+		assert_eq!(wallet.balance(&memory_pool, 1), 100_000);
+		let unspent = wallet.unspent(&memory_pool, 1, 9_999_999);
+		assert_eq!(unspent.len(), 1);
+		assert_eq!(unspent[0].address, address);
+		assert_eq!(unspent[0].label, "savings");
+		assert_eq!(unspent[0].confirmations, 1);
+		assert!(unspent[0].spendable && unspent[0].safe);
+
+		assert_eq!(
+			wallet.create_transaction(&memory_pool, &recipient(), 100_000, 1000, false, true),
+			Err(Error::InsufficientFunds)
+		);
+		let transaction = wallet
+			.create_transaction(&memory_pool, &recipient(), 40_000, 1000, false, true)
+			.unwrap();
+		assert_eq!(transaction.raw.inputs.len(), 1);
+		assert_eq!(transaction.raw.inputs[0].sequence, SEQUENCE_REPLACEABLE);
+		assert_eq!(transaction.raw.outputs.len(), 2);
+		let fee = 100_000 - transaction.raw.total_spends();
+		assert!(fee > 0 && fee < 1000);
+
+		memory_pool.insert_verified(transaction.clone(), &FeeCalculator(storage.as_transaction_output_provider()));
+		// change is unconfirmed, but safe
+		assert_eq!(wallet.balance(&memory_pool, 1), 0);
+		assert_eq!(wallet.balance(&memory_pool, 0), 60_000 - fee);
+
+		let entries = wallet.transactions(&memory_pool, None, 10, 0);
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].category, Category::Receive);
+		assert_eq!(entries[0].amount, 100_000);
+		assert_eq!(entries[0].label, Some("savings".into()));
+		assert_eq!(entries[0].confirmations, 1);
+		assert_eq!(entries[1].category, Category::Send);
+		assert_eq!(entries[1].txid, transaction.hash);
+		assert_eq!(entries[1].amount, -40_000);
+		assert_eq!(entries[1].address, Some(recipient()));
+		assert_eq!(entries[1].fee, Some(-(fee as i64)));
+		assert_eq!(entries[1].confirmations, 0);
+		assert_eq!(wallet.transactions(&memory_pool, None, 1, 1), entries[..1].to_vec());
+		assert_eq!(wallet.transactions(&memory_pool, Some("savings"), 10, 0), entries[..1].to_vec());
+
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn wallet_rolls_back_reorganized_blocks() {
+		let genesis: IndexedBlock = test_data::genesis().into();
+		let storage = BlockChainDatabase::transient(&genesis).unwrap();
+		let path = wallet_path("reorg");
+		let wallet = Wallet::open(&path, Network::Regtest).unwrap();
+		wallet.sync_chain(&storage).unwrap();
+		let address = wallet.new_address(String::new(), AddressType::Legacy).unwrap();
+		let memory_pool = MemoryPool::new();
+
+		let b1 = block(*genesis.hash(), 1, Some(&address), 100_000);
+		storage.insert(b1.clone()).unwrap();
+		storage.canonize(b1.hash()).unwrap();
+		wallet.sync_chain(&storage).unwrap();
+		assert_eq!(wallet.balance(&memory_pool, 1), 100_000);
+
+		storage.decanonize().unwrap();
+		let b1_fork = block(*genesis.hash(), 2, None, 0);
+		let b2_fork = block(*b1_fork.hash(), 3, Some(&address), 30_000);
+		for block in vec![b1_fork, b2_fork] {
+			storage.insert(block.clone()).unwrap();
+			storage.canonize(block.hash()).unwrap();
+		}
+		wallet.sync_chain(&storage).unwrap();
+		let unspent = wallet.unspent(&memory_pool, 0, 9_999_999);
+		assert_eq!(unspent.len(), 1);
+		assert_eq!(unspent[0].output.value, 30_000);
+		assert_eq!(unspent[0].confirmations, 1);
+
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn wallet_imports_descriptors() {
+		let genesis: IndexedBlock = test_data::genesis().into();
+		let storage = BlockChainDatabase::transient(&genesis).unwrap();
+		let private = |secret| Private {
+			network: Network::Regtest,
+			secret: [secret; 32],
+			compressed: true,
+		};
+		let public_key = |secret| DescriptorKey::from_private(&private(secret), false).unwrap();
+		let multisig = format!("wsh(multi(1,{},{}))", public_key(2), private(3));
+		let (descriptor, _) = Descriptor::parse(&multisig).unwrap();
+		let b1 = block(*genesis.hash(), 1, Some(&descriptor.address(Network::Regtest)), 100_000);
+		storage.insert(b1.clone()).unwrap();
+		storage.canonize(b1.hash()).unwrap();
+
+		let path = wallet_path("import");
+		let wallet = Wallet::open(&path, Network::Regtest).unwrap();
+		wallet.sync_chain(&storage).unwrap();
+		assert_eq!(wallet.import_descriptor(&multisig, "multisig".into(), false), Ok(descriptor));
+		assert!(wallet.import_descriptor("wsh(multi(1,invalid))", String::new(), false).is_err());
+
+		// outputs of the existing blocks are only found by rescan
+		let memory_pool = MemoryPool::new();
+		assert_eq!(wallet.unspent(&memory_pool, 0, 9_999_999), vec![]);
+		wallet.rescan(&storage, 0).unwrap();
+		let unspent = wallet.unspent(&memory_pool, 0, 9_999_999);
+		assert_eq!(unspent.len(), 1);
+		assert_eq!(unspent[0].label, "multisig");
+		assert!(unspent[0].spendable);
+		assert!(wallet
+			.create_transaction(&memory_pool, &recipient(), 40_000, 1000, false, true)
+			.is_ok());
+
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn wallet_is_persisted_and_encrypted() {
+		let genesis: IndexedBlock = test_data::genesis().into();
+		let storage = BlockChainDatabase::transient(&genesis).unwrap();
+		let path = wallet_path("persist");
+		let memory_pool = MemoryPool::new();
+		let taproot_address = {
+			let wallet = Wallet::open(&path, Network::Regtest).unwrap();
+			wallet.sync_chain(&storage).unwrap();
+			let address = wallet.new_address("taproot".into(), AddressType::Bech32m).unwrap();
+			let b1 = block(*genesis.hash(), 1, Some(&address), 100_000);
+			storage.insert(b1.clone()).unwrap();
+			storage.canonize(b1.hash()).unwrap();
+			wallet.sync_chain(&storage).unwrap();
+			wallet.encrypt("passphrase").unwrap();
+			address
+		};
+
+		let wallet = Wallet::open(&path, Network::Regtest).unwrap();
+		wallet.sync_chain(&storage).unwrap();
+		let unspent = wallet.unspent(&memory_pool, 1, 9_999_999);
+		assert_eq!(unspent.len(), 1);
+		assert_eq!(unspent[0].address, taproot_address);
+		assert_eq!(unspent[0].label, "taproot");
+
+		assert_eq!(
+			wallet.create_transaction(&memory_pool, &recipient(), 40_000, 1000, false, false),
+			Err(Error::Locked)
+		);
+		assert_eq!(wallet.unlock("wrong", Duration::from_secs(60)), Err(Error::IncorrectPassphrase));
+		wallet.unlock("passphrase", Duration::from_secs(60)).unwrap();
+		let transaction = wallet
+			.create_transaction(&memory_pool, &recipient(), 40_000, 1000, true, false)
+			.unwrap();
+		let spent: Transaction = transaction.raw;
+		assert_eq!(spent.inputs[0].script_witness.len(), 1);
+		wallet.lock().unwrap();
+		assert_eq!(
+			wallet.create_transaction(&memory_pool, &recipient(), 40_000, 1000, false, false),
+			Err(Error::Locked)
+		);
+
+		let _ = std::fs::remove_file(&path);
+	}
+}
@@ -0,0 +1,76 @@
+//! JSON file, storing descriptors, keys and confirmed transactions of the wallet
+
+use crate::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Version of the wallet file format
+pub const WALLET_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct WalletFile {
+	pub version: u32,
+	pub descriptors: Vec<DescriptorRecord>,
+	/// Hex encoded (possibly encrypted) secrets by hex encoded public keys
+	pub keys: Vec<KeyRecord>,
+	pub encryption: Option<EncryptionRecord>,
+	/// Last block, processed by the wallet
+	pub best_block: Option<BlockRecord>,
+	pub transactions: Vec<TransactionRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DescriptorRecord {
+	/// Public descriptor with checksum
+	pub descriptor: String,
+	/// True if descriptor is used for change outputs
+	pub internal: bool,
+	pub label: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct KeyRecord {
+	pub public: String,
+	pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EncryptionRecord {
+	pub salt: String,
+	pub iterations: u32,
+	pub check: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockRecord {
+	pub height: u32,
+	pub hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransactionRecord {
+	/// Hex encoded transaction
+	pub hex: String,
+	pub block: BlockRecord,
+	/// Time of the block
+	pub time: u32,
+}
+
+impl WalletFile {
+	pub fn read(path: &Path) -> Result<Self, Error> {
+		let data = fs::read(path).map_err(|err| Error::Io(format!("cannot read {}: {}", path.display(), err)))?;
+		let file: WalletFile = serde_json::from_slice(&data).map_err(|err| Error::Corrupted(err.to_string()))?;
+		if file.version != WALLET_FILE_VERSION {
+			return Err(Error::Corrupted(format!("unsupported wallet file version {}", file.version)));
+		}
+		Ok(file)
+	}
+
+	/// Writes the file to the temporary file first, so that the wallet file is never partially written
+	pub fn write(&self, path: &Path) -> Result<(), Error> {
+		let data = serde_json::to_vec_pretty(self).map_err(|err| Error::Io(err.to_string()))?;
+		let temp_path = path.with_extension("tmp");
+		fs::write(&temp_path, &data).map_err(|err| Error::Io(format!("cannot write {}: {}", temp_path.display(), err)))?;
+		fs::rename(&temp_path, path).map_err(|err| Error::Io(format!("cannot write {}: {}", path.display(), err)))
+	}
+}